@@ -6,7 +6,9 @@
 //! - 备份和恢复
 //! - 性能测试
 
+use simple_db::columnar::TableLayout;
 use simple_db::engine::DatabaseEngine;
+use simple_db::vectorized::Aggregate;
 use simple_db::query::{QueryBuilder, ComparisonOperator};
 use simple_db::types::{Value, DataType, Schema, ColumnDefinition};
 
@@ -106,7 +108,7 @@ async fn create_complex_tables(engine: &mut DatabaseEngine) -> Result<(), Box<dy
 
 /// 插入大量数据
 async fn insert_large_dataset(engine: &mut DatabaseEngine) -> Result<(), Box<dyn std::error::Error>> {
-    let departments = vec![
+    let departments = [
         ("研发部", "北京", 1000000.0),
         ("市场部", "上海", 500000.0),
         ("销售部", "广州", 800000.0),
@@ -141,12 +143,12 @@ async fn insert_large_dataset(engine: &mut DatabaseEngine) -> Result<(), Box<dyn
     let mut employee_data = Vec::new();
     for (id, name, department, salary, hire_date, is_manager) in &employees {
         let mut data = std::collections::HashMap::new();
-        data.insert("id".to_string(), Value::Integer(id));
+        data.insert("id".to_string(), Value::Integer(*id));
         data.insert("name".to_string(), Value::Text(name.to_string()));
         data.insert("department".to_string(), Value::Text(department.to_string()));
-        data.insert("salary".to_string(), Value::Float(salary));
+        data.insert("salary".to_string(), Value::Float(*salary));
         data.insert("hire_date".to_string(), Value::Text(hire_date.to_string()));
-        data.insert("is_manager".to_string(), Value::Boolean(is_manager));
+        data.insert("is_manager".to_string(), Value::Boolean(*is_manager));
         employee_data.push(data);
     }
 
@@ -163,11 +165,11 @@ async fn insert_large_dataset(engine: &mut DatabaseEngine) -> Result<(), Box<dyn
     let mut project_data = Vec::new();
     for (id, name, start_date, end_date, budget, status) in &projects {
         let mut data = std::collections::HashMap::new();
-        data.insert("id".to_string(), Value::Integer(id));
+        data.insert("id".to_string(), Value::Integer(*id));
         data.insert("name".to_string(), Value::Text(name.to_string()));
         data.insert("start_date".to_string(), Value::Text(start_date.to_string()));
         data.insert("end_date".to_string(), Value::Text(end_date.to_string()));
-        data.insert("budget".to_string(), Value::Float(budget));
+        data.insert("budget".to_string(), Value::Float(*budget));
         data.insert("status".to_string(), Value::Text(status.to_string()));
         project_data.push(data);
     }
@@ -187,10 +189,10 @@ async fn insert_large_dataset(engine: &mut DatabaseEngine) -> Result<(), Box<dyn
     let mut assignment_data = Vec::new();
     for (emp_id, proj_id, role, hours) in &assignments {
         let mut data = std::collections::HashMap::new();
-        data.insert("employee_id".to_string(), Value::Integer(emp_id));
-        data.insert("project_id".to_string(), Value::Integer(proj_id));
+        data.insert("employee_id".to_string(), Value::Integer(*emp_id));
+        data.insert("project_id".to_string(), Value::Integer(*proj_id));
         data.insert("role".to_string(), Value::Text(role.to_string()));
-        data.insert("hours_per_week".to_string(), Value::Integer(hours));
+        data.insert("hours_per_week".to_string(), Value::Integer(*hours));
         assignment_data.push(data);
     }
 
@@ -373,7 +375,7 @@ async fn performance_tests(engine: &mut DatabaseEngine) -> Result<(), Box<dyn st
         ColumnDefinition::new("category", DataType::Text, false),
         ColumnDefinition::new("timestamp", DataType::DateTime, false),
     ]);
-    engine.create_table("perf_test", schema).await?;
+    engine.create_table("perf_test", schema.clone()).await?;
 
     // 批量插入性能测试
     println!("\n6.2 批量插入性能测试...");
@@ -392,7 +394,7 @@ async fn performance_tests(engine: &mut DatabaseEngine) -> Result<(), Box<dyn st
         batch_data.push(data);
     }
 
-    let ids = engine.batch_insert("perf_test", batch_data).await?;
+    let ids = engine.batch_insert("perf_test", batch_data.clone()).await?;
     let insert_duration = start.elapsed();
     println!("✓ 插入 {} 条数据，耗时: {:?}", ids.len(), insert_duration);
     println!("  平均每条: {:.2}ms", insert_duration.as_millis() as f64 / ids.len() as f64);
@@ -437,8 +439,26 @@ async fn performance_tests(engine: &mut DatabaseEngine) -> Result<(), Box<dyn st
         println!("✓ 统计 {} 条数据，耗时: {:?}", count, count_duration);
     }
 
+    // 列式布局的同一份数据按 1024 个值一批求值条件与聚合
+    println!("\n6.6 行式与列式聚合对比...");
+    engine.create_table_with_layout("perf_test_columnar", schema, TableLayout::Columnar).await?;
+    engine.batch_insert("perf_test_columnar", batch_data).await?;
+    for table in ["perf_test", "perf_test_columnar"] {
+        let query = QueryBuilder::select(table)
+            .where_condition("category", ComparisonOperator::Equal, Value::Text("A".to_string()))
+            .where_condition("value", ComparisonOperator::GreaterThan, Value::Float(100.0))
+            .build();
+        let start = std::time::Instant::now();
+        let mut sum = Value::Null;
+        for _ in 0..100 {
+            sum = engine.aggregate(query.clone(), Aggregate::Sum, "value").await?;
+        }
+        println!("✓ {}: SUM(value) = {}，100 次耗时: {:?}", table, sum, start.elapsed());
+    }
+
     // 清理测试表
     engine.drop_table("perf_test").await?;
+    engine.drop_table("perf_test_columnar").await?;
     println!("\n✓ 清理了性能测试表");
 
     Ok(())
@@ -539,7 +559,7 @@ fn print_query_result(result: &simple_db::query::QueryResult) {
         print!("  | ");
         for col in ["id", "name", "department", "salary", "status"] {
             if let Some(value) = row.get(col) {
-                print!("{}: {} | ", col, value.to_string());
+                print!("{}: {} | ", col, value);
             }
         }
         println!();
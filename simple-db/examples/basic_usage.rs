@@ -100,13 +100,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     updates.insert("active".to_string(), Value::Boolean(false));
 
     let affected = engine.update("users", conditions, updates).await?;
-    println!("✓ 更新了 {} 行", affected);
+    println!("✓ 更新了 {} 行", affected.len());
 
     // 8. 删除数据
     println!("\n8. 删除用户数据...");
     let conditions = vec![("id".to_string(), ComparisonOperator::Equal, Value::Integer(3))];
     let affected = engine.delete("users", conditions).await?;
-    println!("✓ 删除了 {} 行", affected);
+    println!("✓ 删除了 {} 行", affected.len());
 
     // 9. 查询更新后的数据
     println!("\n9. 更新后的用户数据:");
@@ -134,7 +134,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         let unique = if column.unique { "YES" } else { "NO" };
         let primary = if column.primary_key { "YES" } else { "NO" };
         println!("  {}: {} NULL={}, UNIQUE={}, PK={}",
-            column.name, column.data_type.to_string(), nullable, unique, primary);
+            column.name, column.data_type, nullable, unique, primary);
     }
 
     // 12. 数据库统计
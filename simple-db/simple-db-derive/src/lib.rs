@@ -0,0 +1,123 @@
+//! simple-db 的派生宏：`#[derive(FromRow, ToRow)]` 在结构体与数据行之间自动转换
+//!
+//! 字段默认映射到同名列，可用字段属性调整：
+//! - `#[row(rename = "col")]` 映射到名为 `col` 的列
+//! - `#[row(skip)]` 不参与映射，读取时使用 `Default::default()`
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, LitStr};
+
+/// 为结构体实现 `simple_db::FromRow`
+#[proc_macro_derive(FromRow, attributes(row))]
+pub fn derive_from_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_from_row(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// 为结构体实现 `simple_db::ToRow`
+#[proc_macro_derive(ToRow, attributes(row))]
+pub fn derive_to_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_to_row(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// 一个具名字段及其映射设置
+struct FieldMapping<'a> {
+    ident: &'a syn::Ident,
+    ty: &'a syn::Type,
+    column: String,
+    skip: bool,
+}
+
+fn named_fields(input: &DeriveInput) -> syn::Result<Vec<FieldMapping<'_>>> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new_spanned(&input.ident, "只支持具名字段的结构体")),
+        },
+        _ => return Err(syn::Error::new_spanned(&input.ident, "只支持结构体")),
+    };
+
+    fields.iter().map(|field| {
+        let ident = field.ident.as_ref().expect("具名字段");
+        let mut mapping = FieldMapping {
+            ident,
+            ty: &field.ty,
+            column: ident.to_string().trim_start_matches("r#").to_string(),
+            skip: false,
+        };
+
+        for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("row")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    mapping.column = meta.value()?.parse::<LitStr>()?.value();
+                    Ok(())
+                } else if meta.path.is_ident("skip") {
+                    mapping.skip = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("未知的 row 属性，可用: rename, skip"))
+                }
+            })?;
+        }
+        Ok(mapping)
+    }).collect()
+}
+
+fn expand_from_row(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = named_fields(input)?.into_iter().map(|field| {
+        let ident = field.ident;
+        let ty = field.ty;
+        let column = &field.column;
+        if field.skip {
+            quote! { #ident: ::std::default::Default::default() }
+        } else {
+            quote! { #ident: ::simple_db::mapping::column::<#ty>(row, #column)? }
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::simple_db::mapping::FromRow for #name #ty_generics #where_clause {
+            fn from_row(row: &::simple_db::types::Row) -> ::simple_db::error::Result<Self> {
+                ::std::result::Result::Ok(Self {
+                    #(#fields,)*
+                })
+            }
+        }
+    })
+}
+
+fn expand_to_row(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let inserts = named_fields(input)?.into_iter().filter(|field| !field.skip).map(|field| {
+        let ident = field.ident;
+        let column = &field.column;
+        quote! {
+            data.insert(
+                ::std::string::String::from(#column),
+                ::simple_db::types::Value::from(::std::clone::Clone::clone(&self.#ident)),
+            );
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::simple_db::mapping::ToRow for #name #ty_generics #where_clause {
+            fn to_row(&self) -> ::std::collections::HashMap<::std::string::String, ::simple_db::types::Value> {
+                let mut data = ::std::collections::HashMap::new();
+                #(#inserts)*
+                data
+            }
+        }
+    })
+}
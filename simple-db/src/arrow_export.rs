@@ -0,0 +1,186 @@
+use std::sync::Arc;
+use arrow::array::{
+    Array, ArrayRef, BinaryArray, BooleanArray, Date32Array, Float64Array, Int64Array, StringArray,
+    Time64MicrosecondArray, TimestampMicrosecondArray,
+};
+use arrow::datatypes::{DataType as ArrowType, Field, Schema as ArrowSchema, TimeUnit};
+use arrow::record_batch::{RecordBatch, RecordBatchOptions};
+use chrono::{NaiveDate, Timelike};
+
+use crate::error::{DatabaseError, Result};
+use crate::query::QueryResult;
+use crate::types::{DataType, Row, Value};
+
+impl QueryResult {
+    /// 转换为 Arrow RecordBatch，可直接交给 DataFusion、Polars 或 Arrow Flight
+    ///
+    /// 列顺序与类型取自结果携带的表结构；表结构之外的列按名称排在后面，
+    /// 类型取自结果的列信息，没有时由第一个非空值推断。JSON 列输出为字符串。
+    pub fn to_arrow(&self) -> Result<RecordBatch> {
+        let mut fields = Vec::new();
+        let mut arrays = Vec::new();
+        for (name, data_type, nullable) in self.arrow_columns() {
+            let array = build_array(&self.rows, &name, &data_type)?;
+            let nullable = nullable || array.null_count() > 0;
+            fields.push(Field::new(name, arrow_type(&data_type), nullable));
+            arrays.push(array);
+        }
+
+        let options = RecordBatchOptions::new().with_row_count(Some(self.rows.len()));
+        RecordBatch::try_new_with_options(Arc::new(ArrowSchema::new(fields)), arrays, &options)
+            .map_err(|e| DatabaseError::other(format!("构建 RecordBatch 失败: {}", e)))
+    }
+
+    /// 输出列：(列名, 类型, 是否可空)
+    fn arrow_columns(&self) -> Vec<(String, DataType, bool)> {
+        let mut columns: Vec<_> = self.schema.iter()
+            .flat_map(|schema| &schema.columns)
+            .map(|column| (column.name.clone(), column.data_type.clone(), column.nullable && !column.primary_key))
+            .collect();
+
+        let mut extra: Vec<&String> = self.rows.iter()
+            .flat_map(|row| row.data.keys())
+            .filter(|name| !columns.iter().any(|(column, _, _)| column == *name))
+            .collect();
+        extra.sort();
+        extra.dedup();
+        for name in extra {
+            let known = self.columns.iter().find(|(column, _)| column == name).map(|(_, data_type)| data_type.clone());
+            let data_type = known.unwrap_or_else(|| self.rows.iter()
+                .filter_map(|row| row.get(name))
+                .find(|value| !value.is_null())
+                .map_or(DataType::Text, Value::get_type));
+            columns.push((name.clone(), data_type, true));
+        }
+        columns
+    }
+}
+
+fn arrow_type(data_type: &DataType) -> ArrowType {
+    match data_type {
+        DataType::Integer => ArrowType::Int64,
+        DataType::Float => ArrowType::Float64,
+        DataType::Boolean => ArrowType::Boolean,
+        // 数组与坐标以 JSON 文本导出，UUID 以连字符格式的文本导出，时间间隔以 `1 day 02:00:00` 形式的文本导出
+        DataType::Text | DataType::Json | DataType::Array(_) | DataType::Point | DataType::Uuid | DataType::Interval => ArrowType::Utf8,
+        DataType::Date => ArrowType::Date32,
+        DataType::Time => ArrowType::Time64(TimeUnit::Microsecond),
+        DataType::DateTime => ArrowType::Timestamp(TimeUnit::Microsecond, None),
+        DataType::Binary => ArrowType::Binary,
+    }
+}
+
+/// 取出某列的值并转换为列类型，NULL 与缺失值为 None
+fn column_values<T>(rows: &[Row], column: &str, data_type: &DataType, extract: impl Fn(Value) -> Option<T>) -> Result<Vec<Option<T>>> {
+    rows.iter()
+        .map(|row| match row.get(column) {
+            None | Some(Value::Null) => Ok(None),
+            Some(value) => value.coerce_to(data_type)
+                .and_then(&extract)
+                .map(Some)
+                .ok_or_else(|| DatabaseError::type_mismatch(
+                    format!("列 '{}' 的值 '{}' 无法转换为 {}", column, value, data_type)
+                )),
+        })
+        .collect()
+}
+
+fn build_array(rows: &[Row], column: &str, data_type: &DataType) -> Result<ArrayRef> {
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
+    let array: ArrayRef = match data_type {
+        DataType::Integer => Arc::new(Int64Array::from(column_values(rows, column, data_type, |value| match value {
+            Value::Integer(i) => Some(i),
+            _ => None,
+        })?)),
+        DataType::Float => Arc::new(Float64Array::from(column_values(rows, column, data_type, |value| match value {
+            Value::Float(f) => Some(f),
+            Value::Integer(i) => Some(i as f64),
+            _ => None,
+        })?)),
+        DataType::Boolean => Arc::new(BooleanArray::from(column_values(rows, column, data_type, |value| match value {
+            Value::Boolean(b) => Some(b),
+            _ => None,
+        })?)),
+        DataType::Text => Arc::new(StringArray::from(column_values(rows, column, data_type, |value| match value {
+            Value::Text(s) => Some(s),
+            _ => None,
+        })?)),
+        DataType::Json => Arc::new(StringArray::from(column_values(rows, column, data_type, |value| match value {
+            Value::Json(json) => Some(json.to_string()),
+            _ => None,
+        })?)),
+        DataType::Array(_) => Arc::new(StringArray::from(column_values(rows, column, data_type, |value| match value {
+            Value::Array(_) => Some(value.to_json().to_string()),
+            _ => None,
+        })?)),
+        DataType::Point => Arc::new(StringArray::from(column_values(rows, column, data_type, |value| match value {
+            Value::Point { .. } => Some(value.to_json().to_string()),
+            _ => None,
+        })?)),
+        DataType::Uuid => Arc::new(StringArray::from(column_values(rows, column, data_type, |value| match value {
+            Value::Uuid(id) => Some(id.to_string()),
+            _ => None,
+        })?)),
+        DataType::Interval => Arc::new(StringArray::from(column_values(rows, column, data_type, |value| match value {
+            Value::Interval(interval) => Some(interval.to_string()),
+            _ => None,
+        })?)),
+        DataType::Date => Arc::new(Date32Array::from(column_values(rows, column, data_type, |value| match value {
+            Value::Date(date) => Some((date - epoch).num_days() as i32),
+            _ => None,
+        })?)),
+        DataType::Time => Arc::new(Time64MicrosecondArray::from(column_values(rows, column, data_type, |value| match value {
+            Value::Time(time) => Some(time.num_seconds_from_midnight() as i64 * 1_000_000 + (time.nanosecond() / 1_000) as i64),
+            _ => None,
+        })?)),
+        DataType::DateTime => Arc::new(TimestampMicrosecondArray::from(column_values(rows, column, data_type, |value| match value {
+            Value::DateTime(datetime) => Some(datetime.and_utc().timestamp_micros()),
+            _ => None,
+        })?)),
+        DataType::Binary => Arc::new(column_values(rows, column, data_type, |value| match value {
+            Value::Binary(bytes) => Some(bytes),
+            _ => None,
+        })?.into_iter().collect::<BinaryArray>()),
+    };
+    Ok(array)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::QueryType;
+    use crate::types::{ColumnDefinition, Schema};
+
+    #[test]
+    fn test_query_result_to_arrow() {
+        let schema = Schema::new(vec![
+            ColumnDefinition::new("id", DataType::Integer, true),
+            ColumnDefinition::new("day", DataType::Date, false),
+        ]);
+        let rows: Vec<Row> = (0..3).map(|i| {
+            let mut row = Row::new();
+            row.set("id", Value::Integer(i));
+            if i > 0 {
+                row.set("day", Value::Date(NaiveDate::from_ymd_opt(1970, 1, 1 + i as u32).unwrap()));
+            }
+            row.set("note", Value::Text(format!("n{}", i)));
+            row
+        }).collect();
+        let result = QueryResult::new(QueryType::Select, "t".to_string(), 0)
+            .with_rows(rows)
+            .with_schema(schema);
+
+        let batch = result.to_arrow().unwrap();
+        assert_eq!(batch.num_rows(), 3);
+        let names: Vec<_> = batch.schema().fields().iter().map(|f| f.name().clone()).collect();
+        assert_eq!(names, vec!["id", "day", "note"]);
+        assert_eq!(batch.schema().field(0).data_type(), &ArrowType::Int64);
+        assert!(!batch.schema().field(0).is_nullable());
+
+        let days = batch.column(1).as_any().downcast_ref::<Date32Array>().unwrap();
+        assert!(days.is_null(0));
+        assert_eq!(days.value(2), 2);
+        let notes = batch.column(2).as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(notes.value(1), "n1");
+    }
+}
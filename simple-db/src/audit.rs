@@ -0,0 +1,151 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::types::{ColumnDefinition, DataType, Row, Schema, Value};
+
+/// 审计记录所在的系统表，开启审计时自动创建，可以像普通表一样查询
+pub const AUDIT_TABLE: &str = "_audit";
+
+/// 被审计的修改类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    Insert,
+    Update,
+    Delete,
+}
+
+impl fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AuditAction::Insert => "INSERT",
+            AuditAction::Update => "UPDATE",
+            AuditAction::Delete => "DELETE",
+        })
+    }
+}
+
+/// 审计记录的保留策略，由 `DatabaseEngine::apply_audit_retention` 执行
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AuditRetention {
+    /// 早于这个时长的记录被删除
+    pub max_age: Option<chrono::Duration>,
+    /// 最多保留的记录数，超出时删除最旧的记录
+    pub max_rows: Option<usize>,
+}
+
+impl AuditRetention {
+    pub fn max_age(mut self, max_age: chrono::Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn max_rows(mut self, max_rows: usize) -> Self {
+        self.max_rows = Some(max_rows);
+        self
+    }
+}
+
+/// 审计配置
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditConfig {
+    /// 记录为操作者的名称
+    pub actor: String,
+    pub retention: AuditRetention,
+}
+
+impl AuditConfig {
+    pub fn new<S: Into<String>>(actor: S) -> Self {
+        Self {
+            actor: actor.into(),
+            retention: AuditRetention::default(),
+        }
+    }
+
+    pub fn retention(mut self, retention: AuditRetention) -> Self {
+        self.retention = retention;
+        self
+    }
+}
+
+/// 一行的修改，插入时没有修改前的值，删除时没有修改后的值
+#[derive(Debug, Clone)]
+pub(crate) struct AuditChange {
+    pub table: String,
+    pub action: AuditAction,
+    pub row_id: Uuid,
+    pub before: Option<Row>,
+    pub after: Option<Row>,
+}
+
+impl AuditChange {
+    pub fn insert(table: &str, row: Row) -> Self {
+        Self { table: table.to_string(), action: AuditAction::Insert, row_id: row.id, before: None, after: Some(row) }
+    }
+
+    pub fn update(table: &str, before: Row, after: Row) -> Self {
+        Self { table: table.to_string(), action: AuditAction::Update, row_id: before.id, before: Some(before), after: Some(after) }
+    }
+
+    pub fn delete(table: &str, row: Row) -> Self {
+        Self { table: table.to_string(), action: AuditAction::Delete, row_id: row.id, before: Some(row), after: None }
+    }
+
+    /// 转换为审计表中的一行
+    pub fn into_row(self, actor: &str, at: DateTime<Utc>) -> Row {
+        let mut row = Row::new();
+        row.set("at", Value::DateTime(at.naive_utc()));
+        row.set("actor", Value::Text(actor.to_string()));
+        row.set("table_name", Value::Text(self.table));
+        row.set("operation", Value::Text(self.action.to_string()));
+        row.set("row_id", Value::Uuid(self.row_id));
+        row.set("before", self.before.map_or(Value::Null, |before| row_json(&before)));
+        row.set("after", self.after.map_or(Value::Null, |after| row_json(&after)));
+        row
+    }
+}
+
+/// 审计表的结构
+pub fn audit_schema() -> Schema {
+    Schema::new(vec![
+        ColumnDefinition::new("at", DataType::DateTime, false).nullable(false),
+        ColumnDefinition::new("actor", DataType::Text, false),
+        ColumnDefinition::new("table_name", DataType::Text, false).nullable(false),
+        ColumnDefinition::new("operation", DataType::Text, false).nullable(false),
+        ColumnDefinition::new("row_id", DataType::Uuid, false).nullable(false),
+        ColumnDefinition::new("before", DataType::Json, false),
+        ColumnDefinition::new("after", DataType::Json, false),
+    ])
+}
+
+/// 行的列值组成的 JSON 对象
+fn row_json(row: &Row) -> Value {
+    let object = row.data.iter()
+        .map(|(column, value)| (column.clone(), value.to_json()))
+        .collect();
+    Value::Json(serde_json::Value::Object(object))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_change_into_audit_row() {
+        let mut before = Row::new();
+        before.set("name", Value::Text("a".to_string()));
+        let mut after = before.clone();
+        after.set("name", Value::Text("b".to_string()));
+
+        let row = AuditChange::update("users", before.clone(), after).into_row("alice", Utc::now());
+        assert!(audit_schema().validate_row(&row).is_ok());
+        assert_eq!(row.get_text("operation"), Some("UPDATE"));
+        assert_eq!(row.get("row_id"), Some(&Value::Uuid(before.id)));
+        assert_eq!(row.get("before"), Some(&Value::Json(serde_json::json!({"name": "a"}))));
+        assert_eq!(row.get("after"), Some(&Value::Json(serde_json::json!({"name": "b"}))));
+
+        let row = AuditChange::delete("users", before).into_row("alice", Utc::now());
+        assert_eq!(row.get("after"), Some(&Value::Null));
+    }
+}
@@ -0,0 +1,92 @@
+use std::sync::{Arc, Mutex};
+
+use crate::error::Result;
+use crate::storage::{LogEntry, Snapshot};
+
+/// 持久化后端：保存快照与快照之后的日志，取代数据目录中的快照文件与日志段
+///
+/// 通过 `DatabaseEngine::with_backend` 使用。方法在阻塞线程池中调用（wasm32 上直接在当前任务中调用），调用期间持有存储引擎的锁，
+/// 同一个后端不会被并发调用。快照中的表数据直接内联在 `Snapshot::tables` 中。
+pub trait StorageBackend: Send + Sync {
+    /// 追加一条日志，ID 严格递增
+    fn append(&self, entry: &LogEntry) -> Result<()>;
+
+    /// 按 ID 顺序返回 `from_id` 之后的日志
+    fn read_log(&self, from_id: u64) -> Result<Vec<LogEntry>>;
+
+    /// 保存快照，替换之前的快照；快照覆盖的日志（ID 不超过 `last_log_id`）之后不再需要，可以丢弃
+    fn save_snapshot(&self, snapshot: &Snapshot) -> Result<()>;
+
+    /// 最近一次保存的快照
+    fn load_snapshot(&self) -> Result<Option<Snapshot>>;
+}
+
+/// 数据保存在进程内存中的后端，克隆共享同一份数据
+///
+/// 引擎释放后数据仍然保留，可以用同一个后端重新打开引擎，适合测试恢复流程。
+#[derive(Clone, Default)]
+pub struct MemoryBackend {
+    state: Arc<Mutex<MemoryBackendState>>,
+}
+
+#[derive(Default)]
+struct MemoryBackendState {
+    /// 快照序列化后的 JSON，与持久化到外部存储时经历同样的编码过程
+    snapshot: Option<String>,
+    log: Vec<LogEntry>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 尚未被快照覆盖的日志条目数
+    pub fn log_len(&self) -> usize {
+        self.state.lock().unwrap().log.len()
+    }
+}
+
+impl StorageBackend for MemoryBackend {
+    fn append(&self, entry: &LogEntry) -> Result<()> {
+        self.state.lock().unwrap().log.push(entry.clone());
+        Ok(())
+    }
+
+    fn read_log(&self, from_id: u64) -> Result<Vec<LogEntry>> {
+        let state = self.state.lock().unwrap();
+        Ok(state.log.iter().filter(|entry| entry.id > from_id).cloned().collect())
+    }
+
+    fn save_snapshot(&self, snapshot: &Snapshot) -> Result<()> {
+        let json = serde_json::to_string(snapshot)?;
+        let mut state = self.state.lock().unwrap();
+        state.snapshot = Some(json);
+        state.log.retain(|entry| entry.id > snapshot.last_log_id);
+        Ok(())
+    }
+
+    fn load_snapshot(&self) -> Result<Option<Snapshot>> {
+        let state = self.state.lock().unwrap();
+        state.snapshot.as_deref().map(serde_json::from_str).transpose().map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::StorageOperation;
+
+    #[test]
+    fn test_memory_backend_drops_covered_log() {
+        let backend = MemoryBackend::new();
+        for id in 1..=3 {
+            backend.append(&LogEntry::new(id, StorageOperation::Drop { table: format!("t{}", id) })).unwrap();
+        }
+        assert_eq!(backend.read_log(1).unwrap().iter().map(|entry| entry.id).collect::<Vec<_>>(), vec![2, 3]);
+
+        backend.save_snapshot(&Snapshot::new(Vec::new(), 2)).unwrap();
+        assert_eq!(backend.log_len(), 1);
+        assert_eq!(backend.load_snapshot().unwrap().map(|snapshot| snapshot.last_log_id), Some(2));
+    }
+}
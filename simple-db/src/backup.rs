@@ -0,0 +1,141 @@
+use std::future::Future;
+#[cfg(feature = "native")]
+use std::path::PathBuf;
+use std::pin::Pin;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::error::{DatabaseError, Result};
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// 备份目标：逐个接收备份中的文件
+///
+/// 实现该 trait 即可把备份写到对象存储等位置，每个文件对应一个对象。
+pub trait BackupSink: Send {
+    /// 写入名为 `name` 的备份文件
+    fn write_file<'a>(&'a mut self, name: &'a str, data: &'a [u8]) -> BoxFuture<'a, Result<()>>;
+
+    /// 所有文件写入完成后调用
+    fn finish(&mut self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// 把备份文件写入本地目录
+#[cfg(feature = "native")]
+#[derive(Debug, Clone)]
+pub struct DirectorySink {
+    dir: PathBuf,
+}
+
+#[cfg(feature = "native")]
+impl DirectorySink {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+#[cfg(feature = "native")]
+impl BackupSink for DirectorySink {
+    fn write_file<'a>(&'a mut self, name: &'a str, data: &'a [u8]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            // 名称可以带子目录，例如 `tables/` 下的表文件
+            let path = self.dir.join(name);
+            tokio::fs::create_dir_all(path.parent().unwrap_or(&self.dir)).await?;
+            tokio::fs::write(path, data).await?;
+            Ok(())
+        })
+    }
+}
+
+/// 把备份打包为 tar 流写入任意 `AsyncWrite`，例如文件、标准输出或网络连接
+pub struct TarSink<W> {
+    writer: W,
+}
+
+impl<W: AsyncWrite + Unpin + Send> TarSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: AsyncWrite + Unpin + Send> BackupSink for TarSink<W> {
+    fn write_file<'a>(&'a mut self, name: &'a str, data: &'a [u8]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let header = tar_header(name, data.len() as u64)?;
+            self.writer.write_all(&header).await?;
+            self.writer.write_all(data).await?;
+            let padding = (TAR_BLOCK - data.len() % TAR_BLOCK) % TAR_BLOCK;
+            self.writer.write_all(&[0; TAR_BLOCK][..padding]).await?;
+            Ok(())
+        })
+    }
+
+    fn finish(&mut self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            // tar 以两个全零块结尾
+            self.writer.write_all(&[0; 2 * TAR_BLOCK]).await?;
+            self.writer.flush().await?;
+            Ok(())
+        })
+    }
+}
+
+const TAR_BLOCK: usize = 512;
+
+/// 生成 ustar 格式的文件头
+fn tar_header(name: &str, size: u64) -> Result<[u8; TAR_BLOCK]> {
+    if name.len() > 100 {
+        return Err(DatabaseError::other(format!("备份文件名过长: {}", name)));
+    }
+
+    let mut header = [0u8; TAR_BLOCK];
+    let mtime = chrono::Utc::now().timestamp().max(0) as u64;
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    header[100..108].copy_from_slice(b"0000644\0");
+    header[108..116].copy_from_slice(b"0000000\0");
+    header[116..124].copy_from_slice(b"0000000\0");
+    header[124..136].copy_from_slice(format!("{:011o}\0", size).as_bytes());
+    header[136..148].copy_from_slice(format!("{:011o}\0", mtime).as_bytes());
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+
+    // 校验和按校验和字段为空格时计算
+    header[148..156].copy_from_slice(b"        ");
+    let checksum: u32 = header.iter().map(|byte| *byte as u32).sum();
+    header[148..156].copy_from_slice(format!("{:06o}\0 ", checksum).as_bytes());
+    Ok(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_tar_sink_layout() {
+        let mut sink = TarSink::new(Vec::new());
+        sink.write_file("snapshot.json", b"{}").await.unwrap();
+        sink.write_file("transaction.log", &[b'x'; 600]).await.unwrap();
+        sink.finish().await.unwrap();
+
+        let archive = sink.into_inner();
+        // 头 + 1 块数据，头 + 2 块数据，结尾 2 块
+        assert_eq!(archive.len(), 7 * TAR_BLOCK);
+        assert_eq!(&archive[..13], b"snapshot.json");
+        assert_eq!(&archive[TAR_BLOCK..TAR_BLOCK + 2], b"{}");
+        assert_eq!(&archive[2 * TAR_BLOCK..2 * TAR_BLOCK + 15], b"transaction.log");
+        assert_eq!(&archive[2 * TAR_BLOCK + 124..2 * TAR_BLOCK + 135], b"00000001130");
+        assert!(archive[5 * TAR_BLOCK..].iter().all(|byte| *byte == 0));
+
+        let checksum: u32 = archive[..TAR_BLOCK].iter().enumerate()
+            .map(|(i, byte)| if (148..156).contains(&i) { b' ' as u32 } else { *byte as u32 })
+            .sum();
+        let stored = std::str::from_utf8(&archive[148..154]).unwrap();
+        assert_eq!(u32::from_str_radix(stored, 8).unwrap(), checksum);
+    }
+}
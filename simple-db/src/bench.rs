@@ -0,0 +1,316 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::columnar::TableLayout;
+use crate::engine::DatabaseEngine;
+use crate::error::{DatabaseError, Result};
+use crate::query::{ComparisonOperator, QueryBuilder};
+use crate::types::{ColumnDefinition, DataType, Schema, Value};
+use crate::vectorized::Aggregate;
+
+/// 基准测试使用的表名
+pub const BENCH_TABLE: &str = "bench";
+
+/// 预先装载数据时每批插入的行数
+const LOAD_BATCH_SIZE: usize = 1000;
+
+/// 基准测试的负载类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Workload {
+    /// 逐行插入
+    Insert,
+    /// 带过滤条件的全表扫描
+    Scan,
+    /// 按行 ID 读取单行
+    PointRead,
+    /// 带过滤条件对数值列求和
+    Aggregate,
+    /// 70% 点读、20% 插入、10% 按主键更新
+    Mixed,
+}
+
+impl Workload {
+    /// 未指定操作次数时的默认值；扫描每次读取全表，次数较少
+    pub fn default_operations(&self, rows: usize) -> usize {
+        match self {
+            Workload::Insert => rows,
+            Workload::Scan | Workload::Aggregate => 10,
+            Workload::PointRead | Workload::Mixed => 1000,
+        }
+    }
+}
+
+impl FromStr for Workload {
+    type Err = DatabaseError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "insert" => Ok(Workload::Insert),
+            "scan" => Ok(Workload::Scan),
+            "point-read" => Ok(Workload::PointRead),
+            "aggregate" => Ok(Workload::Aggregate),
+            "mixed" => Ok(Workload::Mixed),
+            _ => Err(DatabaseError::parse_error(format!("未知的负载类型: {}（可选 insert、scan、point-read、aggregate、mixed）", s))),
+        }
+    }
+}
+
+impl fmt::Display for Workload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Workload::Insert => "insert",
+            Workload::Scan => "scan",
+            Workload::PointRead => "point-read",
+            Workload::Aggregate => "aggregate",
+            Workload::Mixed => "mixed",
+        })
+    }
+}
+
+/// 基准测试配置
+#[derive(Debug, Clone)]
+pub struct BenchConfig {
+    pub workload: Workload,
+    /// 表中的行数；insert 负载从空表开始，其余负载先装载这么多行
+    pub rows: usize,
+    pub operations: Option<usize>,
+    /// 随机数种子，相同的种子产生相同的操作序列
+    pub seed: u64,
+    /// `bench` 表的存储布局
+    pub layout: TableLayout,
+}
+
+impl BenchConfig {
+    pub fn new(workload: Workload, rows: usize) -> Self {
+        Self {
+            workload,
+            rows,
+            operations: None,
+            seed: 42,
+            layout: TableLayout::Row,
+        }
+    }
+
+    pub fn operations(mut self, operations: usize) -> Self {
+        self.operations = Some(operations);
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn layout(mut self, layout: TableLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+}
+
+/// 单次操作延迟的分布
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencySummary {
+    pub min: Duration,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl LatencySummary {
+    /// 按最近秩法计算百分位数，没有样本时全部为零
+    pub fn from_samples(mut samples: Vec<Duration>) -> Self {
+        if samples.is_empty() {
+            return Self {
+                min: Duration::ZERO,
+                mean: Duration::ZERO,
+                p50: Duration::ZERO,
+                p95: Duration::ZERO,
+                p99: Duration::ZERO,
+                max: Duration::ZERO,
+            };
+        }
+        samples.sort_unstable();
+        let percentile = |p: usize| samples[(samples.len() * p).div_ceil(100).max(1) - 1];
+        Self {
+            min: samples[0],
+            mean: samples.iter().sum::<Duration>() / samples.len() as u32,
+            p50: percentile(50),
+            p95: percentile(95),
+            p99: percentile(99),
+            max: samples[samples.len() - 1],
+        }
+    }
+}
+
+/// 基准测试结果
+#[derive(Debug, Clone)]
+pub struct BenchReport {
+    pub workload: Workload,
+    /// 测试结束时表中的行数
+    pub rows: usize,
+    pub operations: usize,
+    /// 执行操作的总耗时，不含装载数据
+    pub elapsed: Duration,
+    pub load_time: Duration,
+    pub latency: LatencySummary,
+}
+
+impl BenchReport {
+    /// 每秒完成的操作数
+    pub fn throughput(&self) -> f64 {
+        if self.elapsed.is_zero() {
+            0.0
+        } else {
+            self.operations as f64 / self.elapsed.as_secs_f64()
+        }
+    }
+}
+
+/// 生成合成数据并运行负载；会重建 `bench` 表，应当在独立的内存数据库上运行
+pub async fn run(engine: &DatabaseEngine, config: &BenchConfig) -> Result<BenchReport> {
+    if engine.get_table_info(BENCH_TABLE).await.is_ok() {
+        engine.drop_table(BENCH_TABLE).await?;
+    }
+    engine.create_table_with_layout(BENCH_TABLE, Schema::new(vec![
+        ColumnDefinition::new("id", DataType::Integer, true),
+        ColumnDefinition::new("name", DataType::Text, false),
+        ColumnDefinition::new("value", DataType::Float, false),
+        ColumnDefinition::new("category", DataType::Text, false),
+    ]), config.layout).await?;
+
+    let load_start = Instant::now();
+    let mut ids = Vec::new();
+    let preload = if config.workload == Workload::Insert { 0 } else { config.rows };
+    for start in (0..preload).step_by(LOAD_BATCH_SIZE) {
+        let end = (start + LOAD_BATCH_SIZE).min(preload);
+        ids.extend(engine.batch_insert(BENCH_TABLE, (start..end).map(bench_row).collect()).await?);
+    }
+    let load_time = load_start.elapsed();
+
+    let operations = config.operations.unwrap_or_else(|| config.workload.default_operations(config.rows));
+    let mut rng = XorShift(config.seed.max(1));
+    let mut next_key = preload;
+    let mut samples = Vec::with_capacity(operations);
+    let start = Instant::now();
+    for _ in 0..operations {
+        let op_start = Instant::now();
+        let op = match config.workload {
+            Workload::Insert => Op::Insert,
+            Workload::Scan => Op::Scan,
+            Workload::PointRead => Op::Read,
+            Workload::Aggregate => Op::Aggregate,
+            Workload::Mixed => match rng.next() % 100 {
+                0..=69 => Op::Read,
+                70..=89 => Op::Insert,
+                _ => Op::Update,
+            },
+        };
+        match op {
+            Op::Insert => {
+                ids.push(engine.insert(BENCH_TABLE, bench_row(next_key)).await?);
+                next_key += 1;
+            }
+            Op::Scan => {
+                let query = QueryBuilder::select(BENCH_TABLE)
+                    .where_condition("category", ComparisonOperator::Equal, Value::Text("c3".to_string()))
+                    .build();
+                engine.query(query).await?;
+            }
+            Op::Aggregate => {
+                let query = QueryBuilder::select(BENCH_TABLE)
+                    .where_condition("category", ComparisonOperator::Equal, Value::Text("c3".to_string()))
+                    .where_condition("value", ComparisonOperator::GreaterThanOrEqual, Value::Float(next_key as f64 * 0.75))
+                    .build();
+                engine.aggregate(query, Aggregate::Sum, "value").await?;
+            }
+            // 空表上没有可读的行，只计入耗时
+            Op::Read if ids.is_empty() => {}
+            Op::Read => {
+                engine.get_row(BENCH_TABLE, ids[rng.next() as usize % ids.len()]).await?;
+            }
+            Op::Update if next_key == 0 => {}
+            Op::Update => {
+                let key = rng.next() as usize % next_key;
+                let updates = HashMap::from([("value".to_string(), Value::Float(rng.next() as f64))]);
+                engine.update(BENCH_TABLE, vec![("id".to_string(), ComparisonOperator::Equal, Value::Integer(key as i64))], updates).await?;
+            }
+        }
+        samples.push(op_start.elapsed());
+    }
+    let elapsed = start.elapsed();
+
+    Ok(BenchReport {
+        workload: config.workload,
+        rows: engine.get_table_info(BENCH_TABLE).await?.row_count,
+        operations,
+        elapsed,
+        load_time,
+        latency: LatencySummary::from_samples(samples),
+    })
+}
+
+/// 单次操作
+enum Op {
+    Insert,
+    Scan,
+    Aggregate,
+    Read,
+    Update,
+}
+
+fn bench_row(key: usize) -> HashMap<String, Value> {
+    HashMap::from([
+        ("id".to_string(), Value::Integer(key as i64)),
+        ("name".to_string(), Value::Text(format!("item-{}", key))),
+        ("value".to_string(), Value::Float(key as f64 * 1.5)),
+        ("category".to_string(), Value::Text(format!("c{}", key % 10))),
+    ])
+}
+
+/// 可复现的伪随机数，基准测试不需要密码学强度
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_workloads_and_percentiles() {
+        let samples: Vec<Duration> = (1..=100).rev().map(Duration::from_millis).collect();
+        let summary = LatencySummary::from_samples(samples);
+        assert_eq!(
+            (summary.min, summary.p50, summary.p95, summary.p99, summary.max),
+            (Duration::from_millis(1), Duration::from_millis(50), Duration::from_millis(95), Duration::from_millis(99), Duration::from_millis(100))
+        );
+
+        let engine = DatabaseEngine::in_memory();
+        let report = run(&engine, &BenchConfig::new(Workload::Insert, 50)).await.unwrap();
+        assert_eq!((report.operations, report.rows), (50, 50));
+
+        for workload in [Workload::Scan, Workload::PointRead, Workload::Aggregate] {
+            for layout in [TableLayout::Row, TableLayout::Columnar] {
+                let report = run(&engine, &BenchConfig::new(workload, 30).operations(5).layout(layout)).await.unwrap();
+                assert_eq!((report.operations, report.rows), (5, 30));
+                assert!(report.latency.p50 <= report.latency.p99);
+            }
+        }
+
+        let report = run(&engine, &BenchConfig::new(Workload::Mixed, 30).operations(200)).await.unwrap();
+        assert!(report.rows > 30 && report.rows < 30 + 200);
+        assert_eq!("point-read".parse::<Workload>().unwrap(), Workload::PointRead);
+        assert!("random".parse::<Workload>().is_err());
+    }
+}
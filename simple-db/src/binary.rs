@@ -0,0 +1,145 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::{DatabaseError, Result};
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// 小写十六进制
+pub fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub fn decode_hex(hex: &str) -> Result<Vec<u8>> {
+    if !hex.bytes().all(|c| c.is_ascii_hexdigit()) || !hex.len().is_multiple_of(2) {
+        return Err(DatabaseError::parse_error(format!("无效的十六进制字面量: {}", hex)));
+    }
+    Ok((0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap_or_default())
+        .collect())
+}
+
+/// 标准字母表、带 `=` 填充的 base64
+pub fn encode_base64(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| group | (*byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// 解码标准字母表的 base64，末尾的 `=` 填充可以省略
+pub fn decode_base64(text: &str) -> Result<Vec<u8>> {
+    let invalid = || DatabaseError::parse_error(format!("无效的 base64 字面量: {}", text));
+    let digits = text.trim_end_matches('=');
+    if text.len() - digits.len() > 2 || digits.len() % 4 == 1 {
+        return Err(invalid());
+    }
+    let mut bytes = Vec::with_capacity(digits.len() * 3 / 4);
+    for chunk in digits.as_bytes().chunks(4) {
+        let mut group = 0u32;
+        for (i, c) in chunk.iter().enumerate() {
+            let digit = BASE64_ALPHABET.iter().position(|a| a == c).ok_or_else(invalid)?;
+            group |= (digit as u32) << (18 - 6 * i);
+        }
+        bytes.extend(group.to_be_bytes()[1..chunk.len()].iter());
+    }
+    Ok(bytes)
+}
+
+/// 解析二进制值的文本形式，用于 CSV 导入与字符串字面量：`\xDEADBEEF`、`0xDEADBEEF` 为十六进制，
+/// `base64:3q2+7w==` 为 base64；其他文本返回 None，不会被误当作二进制
+pub fn parse_text(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim();
+    if let Some(hex) = text.strip_prefix("\\x").or_else(|| text.strip_prefix("0x")) {
+        return decode_hex(hex).ok();
+    }
+    let (prefix, rest) = text.split_at_checked(7)?;
+    if prefix.eq_ignore_ascii_case("base64:") {
+        return decode_base64(rest).ok();
+    }
+    None
+}
+
+/// 结果中二进制值的显示方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BinaryFormat {
+    /// 只显示长度，例如 `BINARY(4 bytes)`
+    #[default]
+    Size,
+    /// `\x` 加十六进制，可以原样导入
+    Hex,
+    /// `base64:` 加 base64，可以原样导入
+    Base64,
+}
+
+impl BinaryFormat {
+    pub fn render(&self, bytes: &[u8]) -> String {
+        match self {
+            BinaryFormat::Size => format!("BINARY({} bytes)", bytes.len()),
+            BinaryFormat::Hex => format!("\\x{}", encode_hex(bytes)),
+            BinaryFormat::Base64 => format!("base64:{}", encode_base64(bytes)),
+        }
+    }
+}
+
+impl FromStr for BinaryFormat {
+    type Err = DatabaseError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "size" => Ok(BinaryFormat::Size),
+            "hex" => Ok(BinaryFormat::Hex),
+            "base64" => Ok(BinaryFormat::Base64),
+            _ => Err(DatabaseError::parse_error(format!("未知的二进制显示方式: {}（可选 size、hex、base64）", s))),
+        }
+    }
+}
+
+impl fmt::Display for BinaryFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BinaryFormat::Size => "size",
+            BinaryFormat::Hex => "hex",
+            BinaryFormat::Base64 => "base64",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_and_base64_round_trip() {
+        let bytes = [0xde, 0xad, 0xbe, 0xef];
+        assert_eq!(encode_hex(&bytes), "deadbeef");
+        assert_eq!(decode_hex("DEADbeef").unwrap(), bytes);
+        assert!(decode_hex("abc").is_err());
+        assert!(decode_hex("+f").is_err());
+
+        for (plain, encoded) in [("", ""), ("f", "Zg=="), ("fo", "Zm8="), ("foo", "Zm9v"), ("foob", "Zm9vYg==")] {
+            assert_eq!(encode_base64(plain.as_bytes()), encoded);
+            assert_eq!(decode_base64(encoded).unwrap(), plain.as_bytes());
+            assert_eq!(decode_base64(encoded.trim_end_matches('=')).unwrap(), plain.as_bytes());
+        }
+        assert!(decode_base64("Zm9vY").is_err());
+        assert!(decode_base64("Zm9v!").is_err());
+
+        assert_eq!(parse_text("\\xDEADBEEF"), Some(bytes.to_vec()));
+        assert_eq!(parse_text(" 0xdeadbeef "), Some(bytes.to_vec()));
+        assert_eq!(parse_text("base64:3q2+7w=="), Some(bytes.to_vec()));
+        assert_eq!(parse_text("deadbeef"), None);
+        assert_eq!(BinaryFormat::Hex.render(&bytes), "\\xdeadbeef");
+        assert_eq!(BinaryFormat::Base64.render(&bytes), "base64:3q2+7w==");
+        assert_eq!(BinaryFormat::default().render(&bytes), "BINARY(4 bytes)");
+    }
+}
@@ -0,0 +1,133 @@
+use crate::columnar::TableLayout;
+use crate::storage::MemoryStorage;
+use crate::types::{ColumnDefinition, DataType, Row, Schema, Table, Value};
+
+/// 系统目录表所在的模式名
+pub const INFORMATION_SCHEMA: &str = "information_schema";
+/// 每张用户表一行
+pub const TABLES: &str = "information_schema.tables";
+/// 每个列一行
+pub const COLUMNS: &str = "information_schema.columns";
+
+/// 是否为系统目录表名
+pub fn is_system_table(name: &str) -> bool {
+    name == TABLES || name == COLUMNS
+}
+
+/// 根据当前表目录构造系统目录表，名称不是系统目录表时返回 None
+///
+/// 目录表是只读的快照，每次查询时重新生成，不会写入日志或快照。
+pub fn system_table(storage: &MemoryStorage, name: &str) -> Option<Table> {
+    let (schema, rows) = match name {
+        TABLES => (tables_schema(), tables_rows(storage)),
+        COLUMNS => (columns_schema(), columns_rows(storage)),
+        _ => return None,
+    };
+
+    let mut table = Table::new(name.to_string(), schema);
+    table.insert_batch(rows).ok()?;
+    Some(table)
+}
+
+fn tables_schema() -> Schema {
+    Schema::new(vec![
+        ColumnDefinition::new("table_name", DataType::Text, true),
+        ColumnDefinition::new("table_type", DataType::Text, false),
+        ColumnDefinition::new("layout", DataType::Text, false),
+        ColumnDefinition::new("partition_key", DataType::Text, false),
+        ColumnDefinition::new("row_count", DataType::Integer, false),
+        ColumnDefinition::new("created_at", DataType::DateTime, false),
+    ])
+}
+
+fn columns_schema() -> Schema {
+    Schema::new(vec![
+        ColumnDefinition::new("table_name", DataType::Text, false),
+        ColumnDefinition::new("column_name", DataType::Text, false),
+        ColumnDefinition::new("ordinal_position", DataType::Integer, false),
+        ColumnDefinition::new("data_type", DataType::Text, false),
+        ColumnDefinition::new("is_nullable", DataType::Boolean, false),
+        ColumnDefinition::new("is_unique", DataType::Boolean, false),
+        ColumnDefinition::new("is_primary_key", DataType::Boolean, false),
+        ColumnDefinition::new("column_default", DataType::Text, false),
+        ColumnDefinition::new("collation", DataType::Text, false),
+    ])
+}
+
+fn sorted_tables(storage: &MemoryStorage) -> Vec<String> {
+    let mut names = storage.list_tables();
+    names.sort();
+    names
+}
+
+fn tables_rows(storage: &MemoryStorage) -> Vec<Row> {
+    sorted_tables(storage).into_iter()
+        .filter_map(|name| {
+            let table = storage.get_table(&name).ok().flatten()?;
+            let layout = match table.layout {
+                TableLayout::Row => "ROW",
+                TableLayout::Columnar => "COLUMNAR",
+            };
+            let mut row = Row::new();
+            row.set("table_name", Value::Text(name.clone()));
+            row.set("table_type", Value::Text("BASE TABLE".to_string()));
+            row.set("layout", Value::Text(layout.to_string()));
+            row.set("partition_key", table.partition_spec.as_ref()
+                .map_or(Value::Null, |spec| Value::Text(spec.column().to_string())));
+            row.set("row_count", Value::Integer(table.row_count() as i64));
+            row.set("created_at", Value::DateTime(table.created_at.naive_utc()));
+            Some(row)
+        })
+        .collect()
+}
+
+fn columns_rows(storage: &MemoryStorage) -> Vec<Row> {
+    let mut rows = Vec::new();
+    for name in sorted_tables(storage) {
+        let Ok(Some(table)) = storage.get_table(&name) else { continue };
+        for (position, column) in table.schema().columns.iter().enumerate() {
+            let mut row = Row::new();
+            row.set("table_name", Value::Text(name.clone()));
+            row.set("column_name", Value::Text(column.name.clone()));
+            row.set("ordinal_position", Value::Integer(position as i64 + 1));
+            row.set("data_type", Value::Text(column.data_type.to_string()));
+            row.set("is_nullable", Value::Boolean(column.nullable));
+            row.set("is_unique", Value::Boolean(column.unique));
+            row.set("is_primary_key", Value::Boolean(column.primary_key));
+            row.set("column_default", column.default_text().map_or(Value::Null, Value::Text));
+            row.set("collation", Value::Text(column.collation.to_string()));
+            rows.push(row);
+        }
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_system_tables_describe_storage() {
+        let mut storage = MemoryStorage::new();
+        storage.create_table("users", Schema::new(vec![
+            ColumnDefinition::new("id", DataType::Integer, true),
+            ColumnDefinition::new("name", DataType::Text, false).default_value(Value::Text("anon".to_string())),
+        ])).unwrap();
+
+        let tables = system_table(&storage, TABLES).unwrap();
+        let rows = tables.all_rows().unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].get_text("table_name"), Some("users"));
+        assert_eq!(rows[0].get("partition_key"), Some(&Value::Null));
+
+        let columns = system_table(&storage, COLUMNS).unwrap();
+        let rows = columns.all_rows().unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].get_text("column_name"), Some("name"));
+        assert_eq!(rows[1].get_integer("ordinal_position"), Some(2));
+        assert_eq!(rows[1].get_text("column_default"), Some("anon"));
+        assert_eq!(rows[0].get_boolean("is_primary_key"), Some(true));
+
+        assert!(system_table(&storage, "users").is_none());
+    }
+}
@@ -0,0 +1,119 @@
+//! 引擎读取的当前时间
+//!
+//! 行的创建与修改时间、日志条目的时间戳、`NOW()`、TTL 与审计都通过 [`now`] 取得当前时间。
+//! 默认读取系统时间；在 [`with_clock`] 的作用域内读取给定的时钟，模拟测试借此得到可重复的时间。
+//! 时钟随任务传递，交给阻塞线程池的磁盘写入经 [`spawn_blocking`] 继续使用同一个时钟。
+
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Duration, Utc};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::error::DatabaseError;
+use crate::error::Result;
+
+/// 测量耗时用的单调时钟；wasm32-unknown-unknown 上标准库没有时钟，改用浏览器的 `performance.now()`
+#[cfg(not(target_arch = "wasm32"))]
+pub use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+pub use web_time::Instant;
+
+/// 时间来源
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// 系统时钟
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// 只在调用 `advance` 或 `set` 时才前进的虚拟时钟，克隆共享同一个时间
+#[derive(Debug, Clone)]
+pub struct VirtualClock {
+    now: Arc<Mutex<DateTime<Utc>>>,
+}
+
+impl VirtualClock {
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Self { now: Arc::new(Mutex::new(start)) }
+    }
+
+    /// 时间前进 `duration`
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.lock().unwrap() = now;
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap()
+    }
+}
+
+tokio::task_local! {
+    static CLOCK: Arc<dyn Clock>;
+}
+
+/// 当前时间：在 `with_clock` 的作用域内读取给定的时钟，否则读取系统时间
+pub fn now() -> DateTime<Utc> {
+    CLOCK.try_with(|clock| clock.now()).unwrap_or_else(|_| Utc::now())
+}
+
+/// 在 `future` 执行期间使用 `clock` 作为当前时间
+pub async fn with_clock<F: Future>(clock: Arc<dyn Clock>, future: F) -> F::Output {
+    CLOCK.scope(clock, future).await
+}
+
+/// 在阻塞线程池中执行 `f`，沿用调用者所在作用域的时钟
+///
+/// wasm32 上没有线程，`f` 直接在当前任务中执行。
+pub async fn spawn_blocking<F, T>(f: F) -> Result<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let clock = CLOCK.try_with(Arc::clone).ok();
+        tokio::task::spawn_blocking(move || match clock {
+            Some(clock) => CLOCK.sync_scope(clock, f),
+            None => f(),
+        })
+        .await
+        .map_err(|e| DatabaseError::other(format!("磁盘 IO 任务失败: {}", e)))
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        Ok(f())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_virtual_clock_scope() {
+        let start = "2100-01-01T00:00:00Z".parse().unwrap();
+        let clock = VirtualClock::new(start);
+        let (inside, blocking) = with_clock(Arc::new(clock.clone()), async {
+            clock.advance(Duration::seconds(90));
+            (now(), spawn_blocking(now).await.unwrap())
+        }).await;
+        assert_eq!(inside, start + Duration::seconds(90));
+        assert_eq!(blocking, inside);
+        // 作用域之外读取系统时间
+        assert!(now() < start);
+    }
+}
@@ -0,0 +1,93 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{DatabaseError, Result};
+use crate::types::Value;
+
+/// 文本列的排序规则，影响相等比较、唯一约束、ORDER BY 与 LIKE
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Collation {
+    /// 按字节比较
+    #[default]
+    Binary,
+    /// 忽略大小写：`'Alice' = 'alice'`，唯一约束同样忽略大小写
+    CaseInsensitive,
+    /// 字典序：先忽略大小写比较，相同时小写在前；相等比较仍区分大小写
+    ///
+    /// 这是不依赖 ICU 的近似实现，不处理重音等区域规则。
+    Locale,
+}
+
+impl Collation {
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        match self {
+            Collation::Binary => a.cmp(b),
+            Collation::CaseInsensitive => fold(a).cmp(fold(b)),
+            // 大小写不同的字符串按二进制逆序比较，使小写排在大写之前
+            Collation::Locale => fold(a).cmp(fold(b)).then_with(|| b.cmp(a)),
+        }
+    }
+
+    /// 唯一性检查使用的值键，按此规则相等的值键相同（Value 含浮点数，无法直接哈希）
+    pub fn key(&self, value: &Value) -> String {
+        match (self, value) {
+            (Collation::CaseInsensitive, Value::Text(text)) => format!("{:?}", Value::Text(fold(text).collect())),
+            _ => format!("{:?}", value),
+        }
+    }
+
+    /// LIKE 匹配是否忽略大小写
+    pub fn like_ignores_case(&self) -> bool {
+        matches!(self, Collation::CaseInsensitive)
+    }
+}
+
+fn fold(text: &str) -> impl Iterator<Item = char> + '_ {
+    text.chars().flat_map(char::to_lowercase)
+}
+
+impl fmt::Display for Collation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Collation::Binary => "BINARY",
+            Collation::CaseInsensitive => "NOCASE",
+            Collation::Locale => "LOCALE",
+        })
+    }
+}
+
+impl FromStr for Collation {
+    type Err = DatabaseError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_uppercase().as_str() {
+            "BINARY" => Ok(Collation::Binary),
+            "NOCASE" | "CASE_INSENSITIVE" | "CI" => Ok(Collation::CaseInsensitive),
+            "LOCALE" | "UNICODE" => Ok(Collation::Locale),
+            _ => Err(DatabaseError::parse_error(format!("未知的排序规则: {}", s))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collation_ordering_and_keys() {
+        assert_eq!(Collation::Binary.compare("b", "A"), Ordering::Greater);
+        assert_eq!(Collation::CaseInsensitive.compare("Alice", "aLICE"), Ordering::Equal);
+        assert_eq!(Collation::CaseInsensitive.compare("b", "A"), Ordering::Greater);
+
+        let mut words = vec!["b", "B", "a", "A"];
+        words.sort_by(|a, b| Collation::Locale.compare(a, b));
+        assert_eq!(words, vec!["a", "A", "b", "B"]);
+
+        let text = |s: &str| Value::Text(s.to_string());
+        assert_eq!(Collation::CaseInsensitive.key(&text("Bob")), Collation::CaseInsensitive.key(&text("BOB")));
+        assert_ne!(Collation::Locale.key(&text("Bob")), Collation::Locale.key(&text("BOB")));
+        assert_eq!("nocase".parse::<Collation>().unwrap(), Collation::CaseInsensitive);
+    }
+}
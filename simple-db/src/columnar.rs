@@ -0,0 +1,310 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::types::{DataType, Row, Schema, Value, CREATED_AT_COLUMN, ID_COLUMN, UPDATED_AT_COLUMN, VERSION_COLUMN};
+
+/// 表的存储布局
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TableLayout {
+    /// 按行存储，每行一个 `Row`
+    #[default]
+    Row,
+    /// 按列存储，每列一个类型化的 Vec，适合聚合扫描
+    Columnar,
+}
+
+/// 单列数据，`None` 表示该行没有值（NULL）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ColumnData {
+    Integer(Vec<Option<i64>>),
+    Float(Vec<Option<f64>>),
+    Boolean(Vec<Option<bool>>),
+    Text(Vec<Option<String>>),
+    /// 其他类型，或写入了与声明类型不符的值（宽松模式）
+    Mixed(Vec<Option<Value>>),
+}
+
+impl ColumnData {
+    fn for_type(data_type: &DataType) -> Self {
+        match data_type {
+            DataType::Integer => ColumnData::Integer(Vec::new()),
+            DataType::Float => ColumnData::Float(Vec::new()),
+            DataType::Boolean => ColumnData::Boolean(Vec::new()),
+            DataType::Text => ColumnData::Text(Vec::new()),
+            _ => ColumnData::Mixed(Vec::new()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            ColumnData::Integer(values) => values.len(),
+            ColumnData::Float(values) => values.len(),
+            ColumnData::Boolean(values) => values.len(),
+            ColumnData::Text(values) => values.len(),
+            ColumnData::Mixed(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 每行在该列中占用的固定字节数
+    pub fn slot_size(&self) -> usize {
+        match self {
+            ColumnData::Integer(_) => std::mem::size_of::<Option<i64>>(),
+            ColumnData::Float(_) => std::mem::size_of::<Option<f64>>(),
+            ColumnData::Boolean(_) => std::mem::size_of::<Option<bool>>(),
+            ColumnData::Text(_) => std::mem::size_of::<Option<String>>(),
+            ColumnData::Mixed(_) => std::mem::size_of::<Option<Value>>(),
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<Value> {
+        match self {
+            ColumnData::Integer(values) => values[index].map(Value::Integer),
+            ColumnData::Float(values) => values[index].map(Value::Float),
+            ColumnData::Boolean(values) => values[index].map(Value::Boolean),
+            ColumnData::Text(values) => values[index].clone().map(Value::Text),
+            ColumnData::Mixed(values) => values[index].clone(),
+        }
+    }
+
+    fn push(&mut self, value: Option<Value>) {
+        let index = self.len();
+        self.push_none();
+        self.set(index, value);
+    }
+
+    fn push_none(&mut self) {
+        match self {
+            ColumnData::Integer(values) => values.push(None),
+            ColumnData::Float(values) => values.push(None),
+            ColumnData::Boolean(values) => values.push(None),
+            ColumnData::Text(values) => values.push(None),
+            ColumnData::Mixed(values) => values.push(None),
+        }
+    }
+
+    fn set(&mut self, index: usize, value: Option<Value>) {
+        match (&mut *self, value) {
+            (_, None) | (_, Some(Value::Null)) => self.clear(index),
+            (ColumnData::Integer(values), Some(Value::Integer(v))) => values[index] = Some(v),
+            (ColumnData::Float(values), Some(Value::Float(v))) => values[index] = Some(v),
+            (ColumnData::Float(values), Some(Value::Integer(v))) => values[index] = Some(v as f64),
+            (ColumnData::Boolean(values), Some(Value::Boolean(v))) => values[index] = Some(v),
+            (ColumnData::Text(values), Some(Value::Text(v))) => values[index] = Some(v),
+            (ColumnData::Mixed(values), Some(v)) => values[index] = Some(v),
+            (_, Some(v)) => {
+                // 类型不符时退化为 Mixed 列，保证不丢失数据
+                self.make_mixed();
+                self.set(index, Some(v));
+            }
+        }
+    }
+
+    fn clear(&mut self, index: usize) {
+        match self {
+            ColumnData::Integer(values) => values[index] = None,
+            ColumnData::Float(values) => values[index] = None,
+            ColumnData::Boolean(values) => values[index] = None,
+            ColumnData::Text(values) => values[index] = None,
+            ColumnData::Mixed(values) => values[index] = None,
+        }
+    }
+
+    fn make_mixed(&mut self) {
+        if !matches!(self, ColumnData::Mixed(_)) {
+            let values = (0..self.len()).map(|i| self.get(i)).collect();
+            *self = ColumnData::Mixed(values);
+        }
+    }
+
+    fn retain_mask(&mut self, keep: &[bool]) {
+        match self {
+            ColumnData::Integer(values) => retain_by_mask(values, keep),
+            ColumnData::Float(values) => retain_by_mask(values, keep),
+            ColumnData::Boolean(values) => retain_by_mask(values, keep),
+            ColumnData::Text(values) => retain_by_mask(values, keep),
+            ColumnData::Mixed(values) => retain_by_mask(values, keep),
+        }
+    }
+}
+
+/// 保留 `keep` 中对应位置为 true 的元素
+fn retain_by_mask<T>(values: &mut Vec<T>, keep: &[bool]) {
+    let mut flags = keep.iter();
+    values.retain(|_| *flags.next().unwrap_or(&true));
+}
+
+/// 列式表数据：行元数据与每列数据按下标对齐
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ColumnStore {
+    ids: Vec<Uuid>,
+    created_at: Vec<chrono::DateTime<chrono::Utc>>,
+    updated_at: Vec<chrono::DateTime<chrono::Utc>>,
+    versions: Vec<u64>,
+    columns: HashMap<String, ColumnData>,
+}
+
+impl ColumnStore {
+    pub fn new(schema: &Schema) -> Self {
+        let columns = schema.columns.iter()
+            .map(|column| (column.name.clone(), ColumnData::for_type(&column.data_type)))
+            .collect();
+        Self {
+            columns,
+            ..Self::default()
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    pub fn column(&self, name: &str) -> Option<&ColumnData> {
+        self.columns.get(name)
+    }
+
+    /// 每行的固定开销：行元数据加上各列的槽位
+    pub fn row_slot_size(&self) -> usize {
+        std::mem::size_of::<Uuid>()
+            + 2 * std::mem::size_of::<chrono::DateTime<chrono::Utc>>()
+            + std::mem::size_of::<u64>()
+            + self.columns.values().map(ColumnData::slot_size).sum::<usize>()
+    }
+
+    pub fn position(&self, id: Uuid) -> Option<usize> {
+        self.ids.iter().position(|row_id| *row_id == id)
+    }
+
+    /// 读取第 `index` 行某列的值，支持伪列
+    pub fn value(&self, index: usize, column: &str) -> Option<Value> {
+        if let Some(data) = self.columns.get(column) {
+            return data.get(index);
+        }
+
+        match column {
+            ID_COLUMN => Some(Value::Uuid(self.ids[index])),
+            CREATED_AT_COLUMN => Some(Value::DateTime(self.created_at[index].naive_utc())),
+            UPDATED_AT_COLUMN => Some(Value::DateTime(self.updated_at[index].naive_utc())),
+            VERSION_COLUMN => Some(Value::Integer(self.versions[index] as i64)),
+            _ => None,
+        }
+    }
+
+    pub fn push(&mut self, mut row: Row) {
+        let len = self.len();
+        for (name, data) in self.columns.iter_mut() {
+            data.push(row.data.remove(name));
+        }
+        for (name, value) in row.data {
+            self.column_mut(&name, len).push(Some(value));
+        }
+
+        self.ids.push(row.id);
+        self.created_at.push(row.created_at);
+        self.updated_at.push(row.updated_at);
+        self.versions.push(row.version);
+    }
+
+    /// 更新第 `index` 行，版本号加一
+    pub fn update(&mut self, index: usize, updates: HashMap<String, Value>, updated_at: chrono::DateTime<chrono::Utc>) {
+        for (name, value) in updates {
+            self.column_mut(&name, self.len()).set(index, Some(value));
+        }
+        self.updated_at[index] = updated_at;
+        self.versions[index] += 1;
+    }
+
+    /// 删除 `keep` 为 false 的行，`keep` 与行一一对应
+    pub fn retain_mask(&mut self, keep: &[bool]) {
+        for data in self.columns.values_mut() {
+            data.retain_mask(keep);
+        }
+        retain_by_mask(&mut self.ids, keep);
+        retain_by_mask(&mut self.created_at, keep);
+        retain_by_mask(&mut self.updated_at, keep);
+        retain_by_mask(&mut self.versions, keep);
+    }
+
+    /// 物化第 `index` 行
+    pub fn row(&self, index: usize) -> Row {
+        let data = self.columns.iter()
+            .filter_map(|(name, column)| column.get(index).map(|value| (name.clone(), value)))
+            .collect();
+        Row {
+            id: self.ids[index],
+            data,
+            created_at: self.created_at[index],
+            updated_at: self.updated_at[index],
+            version: self.versions[index],
+        }
+    }
+
+    pub fn rows(&self) -> Vec<Row> {
+        (0..self.len()).map(|index| self.row(index)).collect()
+    }
+
+    /// 获取列，不存在时（宽松模式下的未知列）创建长度为 `len` 的空列
+    fn column_mut(&mut self, name: &str, len: usize) -> &mut ColumnData {
+        self.columns.entry(name.to_string()).or_insert_with(|| {
+            let mut data = ColumnData::Mixed(Vec::with_capacity(len));
+            for _ in 0..len {
+                data.push_none();
+            }
+            data
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ColumnDefinition;
+
+    #[test]
+    fn test_column_store_round_trip() {
+        let schema = Schema::new(vec![
+            ColumnDefinition::new("id", DataType::Integer, true),
+            ColumnDefinition::new("price", DataType::Float, false),
+        ]);
+        let mut store = ColumnStore::new(&schema);
+
+        let mut row = Row::new();
+        row.set("id", Value::Integer(1));
+        row.set("price", Value::Integer(3));
+        row.set("note", Value::Text("extra".to_string()));
+        let id = row.id;
+        store.push(row);
+
+        let mut row = Row::new();
+        row.set("id", Value::Integer(2));
+        row.set("price", Value::Text("n/a".to_string()));
+        store.push(row);
+
+        assert!(matches!(store.column("id"), Some(ColumnData::Integer(_))));
+        // 类型不符的值使列退化为 Mixed
+        assert!(matches!(store.column("price"), Some(ColumnData::Mixed(_))));
+        assert_eq!(store.value(0, "price"), Some(Value::Float(3.0)));
+        assert_eq!(store.value(1, "note"), None);
+
+        let mut updates = HashMap::new();
+        updates.insert("note".to_string(), Value::Text("changed".to_string()));
+        store.update(0, updates, chrono::Utc::now());
+
+        let row = store.row(0);
+        assert_eq!(row.id, id);
+        assert_eq!(row.version, 2);
+        assert_eq!(row.get("note"), Some(&Value::Text("changed".to_string())));
+
+        store.retain_mask(&[false, true]);
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.value(0, "id"), Some(Value::Integer(2)));
+    }
+}
@@ -0,0 +1,105 @@
+use std::collections::BTreeMap;
+
+use crate::sql::KEYWORDS;
+
+/// 后面应当跟表名的关键字与 shell 命令
+const TABLE_CONTEXT: &[&str] = &["FROM", "INTO", "UPDATE", "TABLE", "ANALYZE", "DESCRIBE", "DESC"];
+
+/// 交互式 shell 的补全候选：SQL 关键字、shell 命令、表名与列名
+#[derive(Debug, Clone, Default)]
+pub struct Completions {
+    commands: Vec<String>,
+    /// 表名到列名的映射
+    tables: BTreeMap<String, Vec<String>>,
+}
+
+impl Completions {
+    /// `commands` 为只在行首补全的 shell 命令
+    pub fn new<I, S>(commands: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            commands: commands.into_iter().map(Into::into).collect(),
+            tables: BTreeMap::new(),
+        }
+    }
+
+    /// 替换已知的表与列，表结构变化后调用
+    pub fn set_tables<I: IntoIterator<Item = (String, Vec<String>)>>(&mut self, tables: I) {
+        self.tables = tables.into_iter().collect();
+    }
+
+    /// 补全光标前的单词，返回单词的起始字节位置与排好序的候选
+    ///
+    /// 关键字按输入的大小写给出；行中提到了已知的表时只补全这些表的列。
+    pub fn complete(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        let before = &line[..pos];
+        let start = before.char_indices()
+            .rev()
+            .find(|(_, c)| !is_word_char(*c))
+            .map_or(0, |(i, c)| i + c.len_utf8());
+        let word = &before[start..];
+        let previous = before[..start].split(|c: char| !is_word_char(c)).rfind(|w| !w.is_empty());
+
+        let mut candidates: Vec<String> = Vec::new();
+        if previous.is_none() {
+            candidates.extend(self.commands.iter().cloned());
+            candidates.extend(KEYWORDS.iter().map(|keyword| keyword_case(keyword, word)));
+        } else if previous.is_some_and(|w| TABLE_CONTEXT.contains(&w.to_uppercase().as_str())) {
+            candidates.extend(self.tables.keys().cloned());
+        } else {
+            candidates.extend(KEYWORDS.iter().map(|keyword| keyword_case(keyword, word)));
+            candidates.extend(self.tables.keys().cloned());
+            let mentioned: Vec<&Vec<String>> = line.split(|c: char| !is_word_char(c))
+                .filter_map(|w| self.tables.get(w))
+                .collect();
+            let columns: Vec<&Vec<String>> = if mentioned.is_empty() { self.tables.values().collect() } else { mentioned };
+            candidates.extend(columns.into_iter().flatten().cloned());
+        }
+
+        let lower = word.to_lowercase();
+        candidates.retain(|candidate| candidate.to_lowercase().starts_with(&lower));
+        candidates.sort();
+        candidates.dedup();
+        (start, candidates)
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '.' || c == '\\'
+}
+
+/// 输入全为小写时给出小写关键字，否则为大写
+fn keyword_case(keyword: &str, typed: &str) -> String {
+    if !typed.is_empty() && typed.chars().all(|c| !c.is_uppercase()) {
+        keyword.to_lowercase()
+    } else {
+        keyword.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_complete_keywords_tables_and_columns() {
+        let mut completions = Completions::new(["tables", "\\format"]);
+        completions.set_tables([
+            ("users".to_string(), vec!["id".to_string(), "name".to_string()]),
+            ("orders".to_string(), vec!["id".to_string(), "user_id".to_string()]),
+        ]);
+        let complete = |line: &str| completions.complete(line, line.len());
+
+        assert_eq!(complete("SEL"), (0, vec!["SELECT".to_string()]));
+        assert_eq!(complete("ta"), (0, vec!["table".to_string(), "tables".to_string()]));
+        assert_eq!(complete("\\fo"), (0, vec!["\\format".to_string()]));
+        assert_eq!(complete("SELECT * FROM u"), (14, vec!["users".to_string()]));
+        assert_eq!(complete("SELECT * FROM users WHERE na"), (26, vec!["name".to_string()]));
+        // 只补全行中提到的表的列
+        assert_eq!(complete("SELECT * FROM orders WHERE u").1, vec!["unique", "update", "user_id", "users"]);
+        assert_eq!(complete("DESCRIBE "), (9, vec!["orders".to_string(), "users".to_string()]));
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+use crate::collation::Collation;
+use crate::types::{Row, Value};
+
+/// 数组列的元素索引（倒排表）：元素键 → 包含该元素的行在 `Table::indexed_rows` 中的位置
+///
+/// 索引在首次使用数组运算符查询时按需构建并缓存在表上，表数据变化后失效重建。
+#[derive(Debug, Clone, Default)]
+pub struct ElementIndex {
+    postings: HashMap<String, Vec<usize>>,
+    collation: Collation,
+}
+
+impl ElementIndex {
+    pub fn build(rows: &[Row], column: &str, collation: Collation) -> Self {
+        let mut postings: HashMap<String, Vec<usize>> = HashMap::new();
+        for (position, row) in rows.iter().enumerate() {
+            let Some(Value::Array(items)) = row.get(column) else { continue };
+            for item in items {
+                let entry = postings.entry(collation.key(item)).or_default();
+                // 同一行中重复的元素只记录一次
+                if entry.last() != Some(&position) {
+                    entry.push(position);
+                }
+            }
+        }
+        Self { postings, collation }
+    }
+
+    /// 构建索引时使用的排序规则，与条件的规则不同时索引不可用
+    pub fn collation(&self) -> Collation {
+        self.collation
+    }
+
+    /// 包含全部元素的行位置，升序
+    pub fn rows_containing_all(&self, keys: &[String]) -> Vec<usize> {
+        let mut lists: Vec<&[usize]> = Vec::with_capacity(keys.len());
+        for key in keys {
+            match self.postings.get(key) {
+                Some(positions) => lists.push(positions),
+                None => return Vec::new(),
+            }
+        }
+        lists.sort_by_key(|positions| positions.len());
+        let Some((shortest, rest)) = lists.split_first() else {
+            return Vec::new();
+        };
+        shortest.iter()
+            .copied()
+            .filter(|position| rest.iter().all(|positions| positions.binary_search(position).is_ok()))
+            .collect()
+    }
+
+    /// 至少包含一个元素的行位置，升序
+    pub fn rows_containing_any(&self, keys: &[String]) -> Vec<usize> {
+        let mut positions: Vec<usize> = keys.iter()
+            .filter_map(|key| self.postings.get(key))
+            .flatten()
+            .copied()
+            .collect();
+        positions.sort_unstable();
+        positions.dedup();
+        positions
+    }
+
+    /// 索引占用的内存估算（字节）
+    pub fn heap_size(&self) -> usize {
+        self.postings.iter()
+            .map(|(key, positions)| key.capacity() + positions.capacity() * std::mem::size_of::<usize>())
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_postings_intersection_and_union() {
+        let tag_row = |tags: &[&str]| {
+            let mut row = Row::new();
+            row.set("tags", Value::Array(tags.iter().map(|tag| Value::from(*tag)).collect()));
+            row
+        };
+        let rows = vec![tag_row(&["rust", "db"]), tag_row(&["go"]), tag_row(&["rust", "rust", "web"]), Row::new()];
+        let index = ElementIndex::build(&rows, "tags", Collation::Binary);
+        let key = |tag: &str| Collation::Binary.key(&Value::from(tag));
+
+        assert_eq!(index.rows_containing_all(&[key("rust")]), vec![0, 2]);
+        assert_eq!(index.rows_containing_all(&[key("rust"), key("db")]), vec![0]);
+        assert!(index.rows_containing_all(&[key("rust"), key("java")]).is_empty());
+        assert_eq!(index.rows_containing_any(&[key("go"), key("web")]), vec![1, 2]);
+    }
+}
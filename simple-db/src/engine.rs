@@ -1,572 +1,5493 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fs::TryLockError;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
-use tokio::sync::RwLock;
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex as AsyncMutex, OwnedMutexGuard, RwLock};
 
+use crate::audit::{audit_schema, AuditChange, AuditConfig, AUDIT_TABLE};
+use crate::backend::StorageBackend;
+use crate::clock::{self, Instant};
+use crate::backup::BackupSink;
+#[cfg(feature = "native")]
+use crate::backup::DirectorySink;
+use crate::catalog;
+use crate::collation::Collation;
 use crate::error::{DatabaseError, Result};
-use crate::types::{Row, Schema, Value};
-use crate::query::{Query, QueryResult, QueryEngine, QueryBuilder, ComparisonOperator};
-use crate::storage::{StorageEngine, MemoryStorage, StorageOperation};
+use crate::integrity::IntegrityReport;
+use crate::import::{self, CopyOptions, ImportOptions, ImportReport, SourceRow};
+use crate::lock::{LockInfo, LockManager, LockMode, LockTarget};
+use crate::columnar::TableLayout;
+use crate::expression::Expr;
+use crate::vectorized::Aggregate;
+use crate::spill::SpillPolicy;
+use crate::mapping::{FromRow, ToRow};
+use crate::migrations::{Migrator, MIGRATIONS_TABLE};
+use crate::types::{ColumnDefinition, DataType, Row, Schema, Table, TableMemoryUsage, TableOptions, Value, ValidationMode, ID_COLUMN, UPDATED_AT_COLUMN};
+use crate::stats::TableStats;
+use crate::slow_log::{SlowQuery, SlowQueryLog};
+use crate::procedure::{compare_affected_rows, Procedure, ProcedureStep};
+use crate::sequence::Sequence;
+use crate::query::{self, Query, QueryResult, QueryEngine, QueryBuilder, QueryType, ComparisonOperator, Condition, OrderBy};
+use crate::partition::PartitionSpec;
+use crate::replication::LogStream;
+use crate::session::Session;
+use crate::sql::{self, Statement};
+use crate::storage::{StorageEngine, MemoryStorage, StorageOperation, RetentionPolicy, ArchiveStatus, LogEntry, Snapshot, matches_all, track_unlogged};
+
+tokio::task_local! {
+    /// 正在执行语句的会话用户，开启审计时取代配置中的操作者
+    static SESSION_USER: String;
+}
+
+/// 默认数据库的名称，数据保存在根数据目录中
+pub const DEFAULT_DATABASE: &str = "main";
+
+/// 数据目录中的锁文件，`open` 期间持有排他锁；内容非空表示上次打开后没有正常关闭
+const LOCK_FILE: &str = "LOCK";
+
+/// 根数据目录中标记只读从库的文件，重新打开后继续拒绝写入，直到 `promote`
+const READ_ONLY_FILE: &str = "READ_ONLY";
+
+/// 保留的校验警告条数，更早的警告被丢弃
+const MAX_VALIDATION_WARNINGS: usize = 1000;
+
+/// 重放日志时在读取线程与应用数据的任务之间缓冲的条目数
+#[cfg(not(target_arch = "wasm32"))]
+const LOG_STREAM_BUFFER: usize = 1024;
+
+/// 一个命名数据库的内存数据与磁盘存储
+#[derive(Clone)]
+struct DatabaseHandle {
+    storage: Arc<RwLock<MemoryStorage>>,
+    disk_storage: Arc<AsyncMutex<StorageEngine>>,
+}
+
+/// 已准备的两阶段提交事务持有的磁盘存储锁
+struct PreparedLock {
+    disk_storage: OwnedMutexGuard<StorageEngine>,
+    /// 准备的时刻；wasm32 上 tokio 没有可用的单调时钟，不记录，事务不会超时
+    prepared_at: Option<tokio::time::Instant>,
+}
 
 /// 数据库引擎 - 提供高级数据库操作接口
 pub struct DatabaseEngine {
+    /// 当前数据库的数据，`use_database` 切换数据库时替换
     storage: Arc<RwLock<MemoryStorage>>,
-    disk_storage: Arc<Mutex<StorageEngine>>,
+    disk_storage: Arc<AsyncMutex<StorageEngine>>,
+    /// 已打开的数据库（包括当前数据库）
+    databases: Arc<Mutex<HashMap<String, DatabaseHandle>>>,
+    current_database: String,
+    /// 根数据目录，其他数据库保存在其下的 `databases/<name>` 中；使用持久化后端时为 None
+    root_dir: Option<String>,
     auto_save: bool,
+    /// 宽松模式下最近的校验警告，最多保留 `MAX_VALIDATION_WARNINGS` 条
+    warnings: Arc<Mutex<VecDeque<String>>>,
+    memory_limit: Option<usize>,
+    table_memory_limits: HashMap<String, usize>,
+    /// 只读副本只接受通过复制应用的日志
+    read_only: AtomicBool,
+    slow_log: Arc<Mutex<SlowQueryLog>>,
+    /// 开启审计时的配置，见 `enable_audit`
+    audit: Arc<Mutex<Option<AuditConfig>>>,
+    /// `open` 持有的锁文件，引擎释放时随之解锁
+    dir_lock: Option<std::fs::File>,
+    /// 纯内存模式，见 `in_memory`
+    in_memory: bool,
+    /// 数据保存在持久化后端中，见 `with_backend`
+    backed: bool,
+    /// 已准备的两阶段提交事务持有的磁盘存储锁，提交或中止时释放
+    prepared_locks: Mutex<HashMap<uuid::Uuid, PreparedLock>>,
+    /// 已准备的事务最多持有磁盘存储锁的时间，见 `set_prepared_timeout`
+    prepared_timeout: Option<Duration>,
+    /// 事务通过 `Transaction::lock_table`、`Transaction::lock_row` 显式加的锁
+    locks: LockManager,
+    next_transaction_id: AtomicU64,
 }
 
 impl DatabaseEngine {
     /// 创建新的数据库引擎
     pub fn new() -> Self {
-        Self {
+        Self::with_disk_storage(StorageEngine::new())
+    }
+
+    /// 创建纯内存的数据库引擎，从不读写磁盘：不写日志、不保存快照，也不会创建 `data/` 目录
+    ///
+    /// 适合单元测试与嵌入式使用；保存快照为空操作，恢复、日志保留与溢出等需要数据目录的操作返回错误。
+    pub fn in_memory() -> Self {
+        let mut engine = Self::new();
+        engine.auto_save = false;
+        engine.in_memory = true;
+        engine
+    }
+
+    /// 是否为纯内存模式
+    pub fn is_in_memory(&self) -> bool {
+        self.in_memory
+    }
+
+    fn check_disk(&self) -> Result<()> {
+        if self.in_memory {
+            Err(DatabaseError::other("纯内存模式的数据库没有数据目录"))
+        } else if self.backed {
+            Err(DatabaseError::other("使用持久化后端的数据库没有数据目录"))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn with_disk_storage(disk_storage: StorageEngine) -> Self {
+        let root_dir = disk_storage.data_dir().map(str::to_string);
+        let main = DatabaseHandle {
             storage: Arc::new(RwLock::new(MemoryStorage::new())),
-            disk_storage: Arc::new(Mutex::new(StorageEngine::new())),
+            disk_storage: Arc::new(AsyncMutex::new(disk_storage)),
+        };
+        Self {
+            storage: Arc::clone(&main.storage),
+            disk_storage: Arc::clone(&main.disk_storage),
+            databases: Arc::new(Mutex::new(HashMap::from([(DEFAULT_DATABASE.to_string(), main)]))),
+            current_database: DEFAULT_DATABASE.to_string(),
+            root_dir,
             auto_save: true,
+            warnings: Arc::new(Mutex::new(VecDeque::new())),
+            memory_limit: None,
+            table_memory_limits: HashMap::new(),
+            read_only: AtomicBool::new(false),
+            dir_lock: None,
+            in_memory: false,
+            backed: false,
+            prepared_locks: Mutex::new(HashMap::new()),
+            prepared_timeout: None,
+            locks: LockManager::new(),
+            next_transaction_id: AtomicU64::new(1),
+            slow_log: Arc::new(Mutex::new(SlowQueryLog::new())),
+            audit: Arc::new(Mutex::new(None)),
         }
     }
 
+    /// 使用持久化后端打开数据库：加载后端中的快照并重放之后的日志，此后的日志与快照都写入后端
+    ///
+    /// 不使用数据目录：命名数据库、恢复、日志保留与溢出等需要数据目录的操作返回错误。
+    pub async fn with_backend(backend: Arc<dyn StorageBackend>) -> Result<Self> {
+        let mut engine = Self::with_disk_storage(StorageEngine::with_backend(backend));
+        engine.backed = true;
+        engine.load_current(false).await?;
+        Ok(engine)
+    }
+
     /// 从磁盘加载数据库
     pub async fn load_from_disk() -> Result<Self> {
+        Self::load(false).await
+    }
+
+    /// 从磁盘加载数据库，先截断日志中损坏的尾部
+    pub async fn load_from_disk_with_repair() -> Result<Self> {
+        Self::load(true).await
+    }
+
+    async fn load(repair: bool) -> Result<Self> {
         let engine = Self::new();
-        engine.disk_storage.lock().unwrap().initialize()?;
+        engine.load_current(repair).await?;
+        Ok(engine)
+    }
 
-        // 加载快照
-        let snapshot = engine.disk_storage.lock().unwrap().load_snapshot()?;
-        if let Some(ref snapshot_data) = snapshot {
-            let mut storage = engine.storage.write().await;
-            for table in &snapshot_data.tables {
-                storage.create_table(&table.name, table.schema.clone())?;
+    /// 打开 `path` 下的数据库，目录不存在时创建
+    ///
+    /// 先在数据目录的锁文件上获取排他锁，另一个进程（或同一进程中另一个引擎）已经打开该目录时返回错误；
+    /// 然后加载快照并重放日志，上次打开后没有调用 `close` 正常关闭时先截断日志中损坏的尾部。
+    pub async fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let dir = path.as_ref().to_string_lossy().into_owned();
+        std::fs::create_dir_all(&dir)?;
+        let mut lock = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path.as_ref().join(LOCK_FILE))?;
+        match lock.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                return Err(DatabaseError::other(format!("数据目录 '{}' 已被其他进程打开", dir)));
             }
+            Err(TryLockError::Error(e)) => return Err(e.into()),
         }
 
-        // 重放日志
-        let last_log_id = snapshot.as_ref().map(|s| s.last_log_id).unwrap_or(0);
-        let logs = engine.disk_storage.lock().unwrap().replay_logs(last_log_id)?;
-        {
-            let mut storage = engine.storage.write().await;
-            for log in logs {
-                engine.apply_log_operation(&mut storage, log.operation)?;
-            }
-        } // storage borrow ends here
+        let mut state = String::new();
+        lock.read_to_string(&mut state)?;
+        let unclean = !state.trim().is_empty();
+        // 记录打开的进程，`close` 时清空
+        lock.set_len(0)?;
+        lock.seek(SeekFrom::Start(0))?;
+        writeln!(lock, "{}", std::process::id())?;
+        lock.sync_all()?;
 
+        let mut engine = Self::with_disk_storage(StorageEngine::with_data_dir(dir));
+        engine.load_current(unclean).await?;
+        engine.dir_lock = Some(lock);
         Ok(engine)
     }
 
-    /// 保存到磁盘
-    pub async fn save_to_disk(&self) -> Result<()> {
-        let storage = self.storage.read().await;
-        let tables = storage.get_all_data();
-        self.disk_storage.lock().unwrap().create_snapshot(tables)?;
+    /// 正常关闭：保存快照，清除锁文件中的打开标记并释放锁
+    pub async fn close(mut self) -> Result<()> {
+        self.save_to_disk().await?;
+        if let Some(lock) = self.dir_lock.take() {
+            lock.set_len(0)?;
+            lock.sync_all()?;
+        }
         Ok(())
     }
 
-    /// 设置自动保存
-    pub fn set_auto_save(&mut self, auto_save: bool) {
-        self.auto_save = auto_save;
+    /// 从当前数据库的数据目录加载快照并重放日志
+    async fn load_current(&self, repair: bool) -> Result<()> {
+        let marker = self.root_dir.as_ref().map(|dir| Path::new(dir).join(READ_ONLY_FILE));
+        let (snapshot, read_only) = self.with_disk(move |disk_storage| {
+            disk_storage.initialize()?;
+            if repair {
+                disk_storage.repair_log()?;
+            }
+            disk_storage.upgrade_log()?;
+            disk_storage.recover_log_id()?;
+            let read_only = marker.is_some_and(|marker| marker.exists());
+            Ok(((disk_storage.load_snapshot()?, disk_storage.tables_dir()), read_only))
+        }).await?;
+        if read_only {
+            self.read_only.store(true, Ordering::SeqCst);
+        }
+        self.reload(snapshot).await
     }
 
-    /// 创建表
-    pub async fn create_table(&self, name: &str, schema: Schema) -> Result<()> {
+    /// 用快照中的表与存储过程替换内存中的数据，再重放快照之后的日志
+    async fn reload(&self, (snapshot, tables_dir): (Option<Snapshot>, Option<PathBuf>)) -> Result<()> {
+        let last_log_id = snapshot.as_ref().map_or(0, |snapshot| snapshot.last_log_id);
+        let mut logs = self.stream_log(last_log_id).await;
         let mut storage = self.storage.write().await;
-        storage.create_table(name, schema.clone())?;
+        *storage = MemoryStorage::new();
+        let mut unlogged = HashSet::new();
 
-        // 记录操作日志
-        if self.auto_save {
-            self.disk_storage.lock().unwrap().write_log(StorageOperation::Create {
-                table: name.to_string(),
-                schema,
-            })?;
+        if let Some(snapshot) = snapshot {
+            unlogged.extend(snapshot.unlogged_tables);
+            // 旧版快照的表直接写在快照中；新快照只登记表文件，表在首次访问时才读入
+            for table in snapshot.tables {
+                storage.add_table(table)?;
+            }
+            for table_file in &snapshot.table_files {
+                let tables_dir = tables_dir.as_ref()
+                    .ok_or_else(|| DatabaseError::other("使用持久化后端的快照不应引用表文件"))?;
+                storage.add_table_file(&table_file.name, tables_dir.join(&table_file.file))?;
+            }
+            for procedure in snapshot.procedures {
+                storage.create_procedure(procedure)?;
+            }
+            storage.set_validation_mode(snapshot.validation_mode);
+            for sequence in snapshot.sequences {
+                storage.create_sequence(sequence)?;
+            }
+            for prepared in snapshot.prepared {
+                storage.add_prepared(prepared.txid, prepared.operations)?;
+            }
+            for txid in snapshot.committed_transactions {
+                storage.mark_committed(txid);
+            }
+        }
+
+        // 逐条读取并重放快照之后的日志
+        while let Some(log) = logs.recv().await {
+            let log = log?;
+            track_unlogged(&mut unlogged, &log.operation);
+            self.apply_log_operation(&mut storage, log.operation, log.timestamp)?;
         }
 
+        self.lock_disk().await.set_unlogged_tables(unlogged);
         Ok(())
     }
 
-    /// 删除表
-    pub async fn drop_table(&self, name: &str) -> Result<()> {
-        let mut storage = self.storage.write().await;
-        storage.drop_table(name)?;
+    /// 当前数据库的名称
+    pub fn current_database(&self) -> &str {
+        &self.current_database
+    }
 
-        // 记录操作日志
-        if self.auto_save {
-            self.disk_storage.lock().unwrap().write_log(StorageOperation::Drop {
-                table: name.to_string(),
-            })?;
-        }
+    /// 命名数据库的目录，使用持久化后端时为 None
+    fn database_dir(&self, name: &str) -> Option<String> {
+        self.root_dir.as_ref().map(|root_dir| format!("{}/databases/{}", root_dir, name))
+    }
 
-        Ok(())
+    /// 命名数据库是否保存在数据目录中；纯内存与使用持久化后端的引擎没有数据目录
+    fn has_data_dir(&self) -> bool {
+        !self.in_memory && !self.backed
     }
 
-    /// 插入数据
-    pub async fn insert(&self, table_name: &str, data: HashMap<String, Value>) -> Result<uuid::Uuid> {
-        let mut row = Row::new();
-        for (column, value) in data {
-            row.set(column, value);
+    fn check_database_name(name: &str) -> Result<()> {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(DatabaseError::other(format!("无效的数据库名: '{}'，只能包含字母、数字和下划线", name)));
         }
+        Ok(())
+    }
 
-        let row_id = row.id;
-        let mut storage = self.storage.write().await;
-        storage.insert_row(table_name, row.clone())?;
+    /// 创建命名数据库，表与数据保存在独立的目录中；使用 `use_database` 切换
+    pub fn create_database(&self, name: &str) -> Result<()> {
+        self.check_writable()?;
+        let Some(dir) = self.database_dir(name) else {
+            return Err(DatabaseError::other("使用持久化后端的数据库不支持命名数据库"));
+        };
+        Self::check_database_name(name)?;
+        let mut databases = self.databases.lock().unwrap();
+        if databases.contains_key(name) || (!self.in_memory && std::path::Path::new(&dir).exists()) {
+            return Err(DatabaseError::DatabaseExists(name.to_string()));
+        }
 
-        // 记录操作日志
+        let disk_storage = StorageEngine::with_data_dir(dir);
         if self.auto_save {
-            let _operation_data: std::collections::HashMap<String, Value> = row.data.iter()
-                .map(|(k, v)| (k.clone(), v.clone()))
-                .collect();
-            self.disk_storage.lock().unwrap().write_log(StorageOperation::Insert {
-                table: table_name.to_string(),
-                row,
-            })?;
+            disk_storage.initialize()?;
         }
+        databases.insert(name.to_string(), DatabaseHandle {
+            storage: Arc::new(RwLock::new(MemoryStorage::new())),
+            disk_storage: Arc::new(AsyncMutex::new(disk_storage)),
+        });
+        Ok(())
+    }
 
-        Ok(row_id)
+    /// 删除命名数据库及其数据目录；不能删除默认数据库或当前数据库
+    pub fn drop_database(&self, name: &str) -> Result<()> {
+        self.check_writable()?;
+        if name == DEFAULT_DATABASE || name == self.current_database {
+            return Err(DatabaseError::other(format!("不能删除数据库 '{}'：它是默认数据库或当前数据库", name)));
+        }
+        Self::check_database_name(name)?;
+        let on_disk = self.database_dir(name).filter(|dir| self.has_data_dir() && std::path::Path::new(dir).exists());
+        let opened = self.databases.lock().unwrap().remove(name).is_some();
+        if !opened && on_disk.is_none() {
+            return Err(DatabaseError::DatabaseNotFound(name.to_string()));
+        }
+        if let Some(dir) = on_disk {
+            std::fs::remove_dir_all(&dir)?;
+        }
+        Ok(())
     }
 
-    /// 查询数据
-    pub async fn query(&self, query: Query) -> Result<QueryResult> {
-        let storage = self.storage.read().await;
-        let table = storage.get_table(&query.table_name)
-            .ok_or_else(|| DatabaseError::TableNotFound(query.table_name.clone()))?;
+    /// 列出所有数据库（已打开的与磁盘上的），按名称排序
+    pub fn list_databases(&self) -> Vec<String> {
+        let mut names: HashSet<String> = self.databases.lock().unwrap().keys().cloned().collect();
+        let entries = self.root_dir.as_ref()
+            .filter(|_| self.has_data_dir())
+            .and_then(|root_dir| std::fs::read_dir(format!("{}/databases", root_dir)).ok());
+        if let Some(entries) = entries {
+            names.extend(entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().is_dir())
+                .filter_map(|entry| entry.file_name().into_string().ok()));
+        }
+        let mut names: Vec<String> = names.into_iter().collect();
+        names.sort();
+        names
+    }
 
-        let engine = QueryEngine::new();
-        engine.execute(table.clone(), query).await
+    /// 切换当前数据库，之后的表操作都作用于该数据库；磁盘上尚未打开的数据库在此时加载
+    pub async fn use_database(&mut self, name: &str) -> Result<()> {
+        if name == self.current_database {
+            return Ok(());
+        }
+        Self::check_database_name(name)?;
+        let opened = self.databases.lock().unwrap().get(name).cloned();
+        let loaded = opened.is_some();
+        let handle = match opened {
+            Some(handle) => handle,
+            None => {
+                let dir = self.database_dir(name).filter(|dir| self.has_data_dir() && std::path::Path::new(dir).exists());
+                let Some(dir) = dir else {
+                    return Err(DatabaseError::DatabaseNotFound(name.to_string()));
+                };
+                DatabaseHandle {
+                    storage: Arc::new(RwLock::new(MemoryStorage::new())),
+                    disk_storage: Arc::new(AsyncMutex::new(StorageEngine::with_data_dir(dir))),
+                }
+            }
+        };
+
+        let previous = (
+            std::mem::replace(&mut self.storage, Arc::clone(&handle.storage)),
+            std::mem::replace(&mut self.disk_storage, Arc::clone(&handle.disk_storage)),
+        );
+        if !loaded {
+            if let Err(e) = self.load_current(false).await {
+                (self.storage, self.disk_storage) = previous;
+                return Err(e);
+            }
+            self.databases.lock().unwrap().insert(name.to_string(), handle);
+        }
+        self.current_database = name.to_string();
+        Ok(())
     }
 
-    /// 更新数据
-    pub async fn update(&self, table_name: &str, conditions: Vec<(String, ComparisonOperator, Value)>, updates: HashMap<String, Value>) -> Result<usize> {
-        let _query = QueryBuilder::update(table_name, updates.clone()).build();
+    /// 为当前数据库创建会话
+    pub fn session<S: Into<String>>(&self, user: S) -> Session {
+        Session::new(user, self.current_database())
+    }
 
-        let mut storage = self.storage.write().await;
-        let table = storage.get_table_mut(table_name)
-            .ok_or_else(|| DatabaseError::TableNotFound(table_name.to_string()))?;
+    /// 切换当前数据库，并把会话的当前数据库一同切换
+    pub async fn use_database_in(&mut self, session: &mut Session, name: &str) -> Result<()> {
+        self.use_database(name).await?;
+        session.set_database(name);
+        Ok(())
+    }
 
-        let mut affected_count = 0;
+    /// 保存到磁盘，纯内存模式下不做任何事
+    pub async fn save_to_disk(&self) -> Result<()> {
+        if self.in_memory {
+            return Ok(());
+        }
+        // 先获取磁盘存储的锁再读取数据，与写操作的加锁顺序一致；写快照期间持有读锁，逐表写入表文件
+        let disk_storage = self.lock_disk().await;
+        let storage = Arc::clone(&self.storage).read_owned().await;
 
-        for row in &mut table.rows {
-            let matches = conditions.iter().all(|(column, operator, value)| {
-                let condition = crate::query::Condition::new(column, operator.clone(), value.clone());
-                condition.evaluate(row).unwrap_or(false)
-            });
+        run_blocking(disk_storage, move |disk_storage| {
+            disk_storage.save_storage(&storage, disk_storage.current_log_id())?;
 
-            if matches {
-                let row_updates: std::collections::HashMap<String, Value> = updates.iter()
-                    .map(|(k, v)| (k.clone(), v.clone()))
-                    .collect();
-                // Update the row directly since we have mutable access
-                for (key, value) in row_updates {
-                    row.set(&key, value);
-                }
-                row.updated_at = chrono::Utc::now();
-                affected_count += 1;
-            }
+            // 快照覆盖了新的日志，按保留策略处理旧日志
+            disk_storage.apply_retention()?;
+            Ok(())
+        }).await
+    }
+
+    /// 把已写入的日志 fsync 到磁盘，纯内存模式下不做任何事
+    pub async fn flush_log(&self) -> Result<()> {
+        if self.in_memory {
+            return Ok(());
         }
+        self.with_disk(|disk_storage| disk_storage.sync_log()).await
+    }
 
-        // 记录操作日志
-        if self.auto_save && affected_count > 0 {
-            for row in &table.rows {
-                let matches = conditions.iter().all(|(column, operator, value)| {
-                    let condition = crate::query::Condition::new(column, operator.clone(), value.clone());
-                    condition.evaluate(row).unwrap_or(false)
-                });
+    /// 在阻塞线程池中访问磁盘存储，文件 IO 不会阻塞异步运行时
+    async fn with_disk<F, T>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut StorageEngine) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        run_blocking(self.lock_disk().await, f).await
+    }
 
-                if matches {
-                    let operation_data = updates.iter()
-                        .map(|(k, v)| (k.clone(), v.clone()))
-                        .collect();
-                    self.disk_storage.lock().unwrap().write_log(StorageOperation::Update {
-                        table: table_name.to_string(),
-                        id: row.id.to_string(),
-                        data: operation_data,
-                    })?;
+    /// 在阻塞线程池中逐行读取 `from_id` 之后的日志，条目经有界通道交给调用方
+    ///
+    /// 读取期间一直持有磁盘存储的锁；调用方丢弃接收端后读取随之停止。
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn stream_log(&self, from_id: u64) -> mpsc::Receiver<Result<LogEntry>> {
+        let disk_storage = self.lock_disk().await;
+        let (sender, receiver) = mpsc::channel(LOG_STREAM_BUFFER);
+        tokio::task::spawn_blocking(move || {
+            let entries = match disk_storage.read_log(from_id) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    let _ = sender.blocking_send(Err(e));
+                    return;
+                }
+            };
+            for entry in entries {
+                if sender.blocking_send(entry).is_err() {
+                    break;
                 }
             }
-        }
+        });
+        receiver
+    }
 
-        Ok(affected_count)
+    /// wasm32 上没有阻塞线程池：一次读出 `from_id` 之后的日志，通道容量足以放下全部条目
+    #[cfg(target_arch = "wasm32")]
+    async fn stream_log(&self, from_id: u64) -> mpsc::Receiver<Result<LogEntry>> {
+        let disk_storage = self.lock_disk().await;
+        let entries: Vec<Result<LogEntry>> = match disk_storage.read_log(from_id) {
+            Ok(entries) => entries.collect(),
+            Err(e) => vec![Err(e)],
+        };
+        let (sender, receiver) = mpsc::channel(entries.len().max(1));
+        for entry in entries {
+            let _ = sender.try_send(entry);
+        }
+        receiver
     }
 
-    /// 删除数据
-    pub async fn delete(&self, table_name: &str, conditions: Vec<(String, ComparisonOperator, Value)>) -> Result<usize> {
-        let mut storage = self.storage.write().await;
-        let table = storage.get_table_mut(table_name)
-            .ok_or_else(|| DatabaseError::TableNotFound(table_name.to_string()))?;
+    /// 设置日志保留策略，快照保存后自动归档或删除已被覆盖的日志
+    pub async fn set_retention_policy(&self, policy: Option<RetentionPolicy>) {
+        self.lock_disk().await.set_retention_policy(policy);
+    }
 
-        let mut affected_count = 0;
-        let mut rows_to_delete = Vec::new();
+    /// 设置当前数据库单个日志段的大小上限，超出后自动切换到新的日志段
+    pub async fn set_log_segment_size(&self, bytes: u64) {
+        self.lock_disk().await.set_max_segment_size(bytes);
+    }
 
-        for row in &table.rows {
-            let matches = conditions.iter().all(|(column, operator, value)| {
-                let condition = crate::query::Condition::new(column, operator.clone(), value.clone());
-                condition.evaluate(row).unwrap_or(false)
-            });
+    /// 设置当前数据库写入日志时是否用 LZ4 压缩较长的条目（例如含大段文本、JSON 或二进制的插入）
+    pub async fn set_log_compression(&self, enabled: bool) {
+        self.lock_disk().await.set_log_compression(enabled);
+    }
 
-            if matches {
-                rows_to_delete.push(row.id);
-                affected_count += 1;
-            }
-        }
+    /// 立即按保留策略处理已被覆盖的日志，返回处理的条目数
+    pub async fn apply_retention(&self) -> Result<usize> {
+        self.check_disk()?;
+        self.with_disk(|disk_storage| disk_storage.apply_retention()).await
+    }
 
-        for row_id in rows_to_delete {
-            storage.delete_row(table_name, row_id)?;
+    /// 日志归档状态报告
+    pub async fn archive_status(&self) -> Result<ArchiveStatus> {
+        self.check_disk()?;
+        self.with_disk(|disk_storage| disk_storage.archive_status()).await
+    }
 
-            // 记录操作日志
-            if self.auto_save {
-                self.disk_storage.lock().unwrap().write_log(StorageOperation::Delete {
-                    table: table_name.to_string(),
-                    id: row_id.to_string(),
-                })?;
-            }
-        }
+    /// 设置自动保存，纯内存模式下始终关闭
+    pub fn set_auto_save(&mut self, auto_save: bool) {
+        self.auto_save = auto_save && !self.in_memory;
+    }
 
-        Ok(affected_count)
+    /// 开始一次写操作的日志记录
+    ///
+    /// 在修改内存数据之前获取磁盘存储的锁，一直持有到 `LogBatch::commit` 写完日志，
+    /// 保证日志顺序与修改顺序一致。加锁顺序总是先磁盘存储、后内存数据。
+    async fn begin_log(&self) -> LogBatch {
+        let disk_storage = self.lock_disk().await;
+        self.log_batch(disk_storage)
     }
 
-    /// 获取表信息
-    pub async fn get_table_info(&self, table_name: &str) -> Result<TableInfo> {
-        let storage = self.storage.read().await;
-        let table = storage.get_table(table_name)
-            .ok_or_else(|| DatabaseError::TableNotFound(table_name.to_string()))?;
+    /// 继续使用事务准备时持有的磁盘存储锁，没有时（如重启后恢复的事务）重新获取
+    async fn prepared_log(&self, txid: uuid::Uuid) -> LogBatch {
+        let held = self.prepared_locks.lock().unwrap().remove(&txid);
+        match held {
+            Some(prepared) => self.log_batch(prepared.disk_storage),
+            None => self.begin_log().await,
+        }
+    }
 
-        Ok(TableInfo {
-            name: table.name.clone(),
-            row_count: table.row_count(),
-            created_at: table.created_at,
-            schema: table.schema().clone(),
-        })
+    /// 获取磁盘存储的锁
+    ///
+    /// 设置了 `set_prepared_timeout` 时最多等到最早准备的事务超时，中止超时的事务、释放它持有的锁后继续等待。
+    async fn lock_disk(&self) -> OwnedMutexGuard<StorageEngine> {
+        loop {
+            let lock = Arc::clone(&self.disk_storage).lock_owned();
+            let Some(deadline) = self.prepared_deadline() else {
+                return lock.await;
+            };
+            match tokio::time::timeout_at(deadline, lock).await {
+                Ok(disk_storage) => return disk_storage,
+                Err(_) => self.expire_prepared().await,
+            }
+        }
     }
 
-    /// 列出所有表
-    pub async fn list_tables(&self) -> Vec<TableInfo> {
-        let storage = self.storage.read().await;
-        let mut tables = Vec::new();
+    fn prepared_deadline(&self) -> Option<tokio::time::Instant> {
+        let timeout = self.prepared_timeout?;
+        self.prepared_locks.lock().unwrap().values()
+            .filter_map(|prepared| prepared.prepared_at)
+            .map(|prepared_at| prepared_at + timeout)
+            .min()
+    }
 
-        for table_name in storage.list_tables() {
-            if let Some(table) = storage.get_table(&table_name) {
-                tables.push(TableInfo {
-                    name: table.name.clone(),
-                    row_count: table.row_count(),
-                    created_at: table.created_at,
-                    schema: table.schema().clone(),
-                });
+    /// 中止准备后超过 `prepared_timeout` 仍未提交或中止的事务
+    ///
+    /// 中止记录写入日志失败时，重新打开后事务仍是未决事务，由协调者的 `recover` 处理。
+    async fn expire_prepared(&self) {
+        let Some(timeout) = self.prepared_timeout else {
+            return;
+        };
+        let expired: Vec<(uuid::Uuid, PreparedLock)> = {
+            let mut held = self.prepared_locks.lock().unwrap();
+            let txids: Vec<uuid::Uuid> = held.iter()
+                .filter(|(_, prepared)| prepared.prepared_at.is_some_and(|prepared_at| prepared_at.elapsed() >= timeout))
+                .map(|(txid, _)| *txid)
+                .collect();
+            txids.into_iter().filter_map(|txid| held.remove(&txid).map(|prepared| (txid, prepared))).collect()
+        };
+        for (txid, prepared) in expired {
+            let mut log = self.log_batch(prepared.disk_storage);
+            self.storage.write().await.take_prepared(txid);
+            if log.enabled() {
+                log.push(StorageOperation::AbortPrepared { txid });
             }
+            let _ = log.commit().await;
         }
-
-        tables
     }
 
-    /// 获取数据库统计信息
-    pub async fn get_stats(&self) -> Result<DatabaseStats> {
-        let storage = self.storage.read().await;
-        let storage_stats = self.disk_storage.lock().unwrap().get_stats()?;
+    fn log_batch(&self, disk_storage: OwnedMutexGuard<StorageEngine>) -> LogBatch {
+        LogBatch {
+            enabled: self.auto_save || disk_storage.has_subscribers(),
+            persist: self.auto_save,
+            disk_storage,
+            operations: Vec::new(),
+        }
+    }
 
-        Ok(DatabaseStats {
-            total_tables: storage.list_tables().len(),
-            total_rows: storage.list_tables().iter()
-                .filter_map(|name| storage.get_table(name))
-                .map(|table| table.row_count())
-                .sum(),
-            storage_stats,
+    /// 设置是否为只读副本
+    ///
+    /// 开启自动保存时在根数据目录中记下只读状态，重新打开后保持；纯内存与使用持久化后端的引擎只在本次运行中有效。
+    pub async fn set_read_only(&self, read_only: bool) -> Result<()> {
+        self.read_only.store(read_only, Ordering::SeqCst);
+        let Some(root_dir) = self.root_dir.as_ref().filter(|_| !self.in_memory && self.auto_save).map(PathBuf::from) else {
+            return Ok(());
+        };
+        clock::spawn_blocking(move || -> Result<()> {
+            let marker = root_dir.join(READ_ONLY_FILE);
+            if read_only {
+                std::fs::create_dir_all(&root_dir)?;
+                std::fs::File::create(&marker)?.sync_all()?;
+            } else if marker.exists() {
+                std::fs::remove_file(&marker)?;
+            }
+            Ok(())
         })
+        .await?
     }
 
-    /// 备份数据库
-    pub async fn backup(&self, backup_path: &str) -> Result<()> {
-        // 先保存当前状态
-        self.save_to_disk().await?;
-        self.disk_storage.lock().unwrap().backup(backup_path)?;
-        Ok(())
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.load(Ordering::SeqCst)
     }
 
-    /// 恢复数据库
-    pub async fn restore(&self, backup_path: &str) -> Result<()> {
-        self.disk_storage.lock().unwrap().restore(backup_path)?;
-
-        // 重新加载数据
-        let logs = self.disk_storage.lock().unwrap().replay_logs(0)?;
-        let mut storage = self.storage.write().await;
-
-        // 清空当前数据
-        let table_names: Vec<String> = storage.list_tables();
-        for table_name in table_names {
-            storage.drop_table(&table_name)?;
-        }
+    /// 把从库提升为主库，之后的写入从最后应用的日志ID继续编号
+    pub async fn promote(&self) -> Result<()> {
+        self.set_read_only(false).await
+    }
 
-        // 重放日志
-        for log in logs {
-            self.apply_log_operation(&mut storage, log.operation)?;
+    fn check_writable(&self) -> Result<()> {
+        if self.is_read_only() {
+            Err(DatabaseError::ReadOnly)
+        } else {
+            Ok(())
         }
+    }
 
-        Ok(())
+    /// 最近写入或应用的日志ID
+    pub async fn last_log_id(&self) -> u64 {
+        self.lock_disk().await.current_log_id()
     }
 
-    /// 应用日志操作
-    fn apply_log_operation(&self, storage: &mut MemoryStorage, operation: StorageOperation) -> Result<()> {
-        match operation {
-            StorageOperation::Create { table, schema } => {
-                storage.create_table(&table, schema)?;
-            }
-            StorageOperation::Insert { table, row } => {
-                storage.insert_row(&table, row)?;
-            }
-            StorageOperation::Update { table, id, data } => {
-                if let Ok(uuid) = uuid::Uuid::parse_str(&id) {
-                    let updates = data.into_iter().collect();
-                    storage.update_row(&table, uuid, updates)?;
-                }
-            }
-            StorageOperation::Delete { table, id } => {
-                if let Ok(uuid) = uuid::Uuid::parse_str(&id) {
-                    storage.delete_row(&table, uuid)?;
-                }
-            }
-            StorageOperation::Drop { table } => {
-                storage.drop_table(&table)?;
+    /// 订阅日志用于传送给从库
+    ///
+    /// 先返回日志文件中 `after` 之后的条目，再持续返回新写入的条目。
+    /// 所需的条目已被快照截断时返回错误，从库需要从备份重新初始化。
+    pub async fn subscribe_log(&self, after: u64) -> Result<LogStream> {
+        let auto_save = self.auto_save;
+        self.with_disk(move |disk_storage| {
+            let receiver = disk_storage.subscribe();
+            let backlog = if auto_save {
+                disk_storage.replay_logs(after)?
+            } else {
+                Vec::new()
+            };
+
+            let first = backlog.first().map(|entry| entry.id).unwrap_or(disk_storage.current_log_id() + 1);
+            if first > after + 1 {
+                return Err(DatabaseError::other(format!(
+                    "日志 {}..{} 已不可用，从库需要从备份重新初始化", after + 1, first - 1
+                )));
             }
+            Ok(LogStream::new(backlog, receiver))
+        }).await
+    }
+
+    /// 在从库上应用主库传来的日志条目，已应用过的条目会被忽略
+    pub async fn apply_replicated(&self, entry: LogEntry) -> Result<()> {
+        let disk_storage = self.lock_disk().await;
+        if entry.id <= disk_storage.current_log_id() {
+            return Ok(());
         }
-        Ok(())
+
+        self.apply_log_operation(&mut *self.storage.write().await, entry.operation.clone(), entry.timestamp)?;
+        let persist = self.auto_save;
+        run_blocking(disk_storage, move |disk_storage| disk_storage.append_entry(&entry, persist)).await
     }
 
-    /// 执行事务
-    pub async fn transaction<F, T>(&self, operations: F) -> Result<T>
-    where
-        F: FnOnce(&mut Transaction) -> Result<T>,
-    {
-        let mut transaction = Transaction::new(self);
-        let result = operations(&mut transaction)?;
+    /// 设置当前数据库的数据校验模式，模式记入日志与快照，重新打开后保持
+    pub async fn set_validation_mode(&self, mode: ValidationMode) -> Result<()> {
+        self.check_writable()?;
+        let mut log = self.begin_log().await;
+        self.storage.write().await.set_validation_mode(mode);
+        if log.enabled() {
+            log.push(StorageOperation::SetValidationMode { mode });
+        }
+        log.commit().await
+    }
 
-        // 提交事务
-        transaction.commit().await?;
+    /// 当前数据库的数据校验模式
+    pub async fn validation_mode(&self) -> ValidationMode {
+        self.storage.read().await.validation_mode()
+    }
 
-        Ok(result)
+    /// 宽松模式下最近的校验警告，最多保留 `MAX_VALIDATION_WARNINGS` 条
+    pub fn validation_warnings(&self) -> Vec<String> {
+        self.warnings.lock().unwrap().iter().cloned().collect()
     }
 
-    /// 批量插入
-    pub async fn batch_insert(&self, table_name: &str, rows: Vec<HashMap<String, Value>>) -> Result<Vec<uuid::Uuid>> {
-        let mut ids = Vec::new();
+    /// 取出并清空已记录的校验警告
+    pub fn take_validation_warnings(&self) -> Vec<String> {
+        self.warnings.lock().unwrap().drain(..).collect()
+    }
 
-        for row_data in rows {
-            let id = self.insert(table_name, row_data).await?;
-            ids.push(id);
+    /// 按校验模式检查数据：严格模式返回错误，宽松模式记录警告
+    fn check_strict(&self, storage: &MemoryStorage, table_name: &str, schema: &Schema, data: &HashMap<String, Value>) -> Result<()> {
+        let mut violations = schema.strict_violations(data);
+        if violations.is_empty() {
+            return Ok(());
         }
 
-        Ok(ids)
+        match storage.validation_mode() {
+            ValidationMode::Strict => Err(violations.remove(0)),
+            ValidationMode::Lenient => {
+                let mut warnings = self.warnings.lock().unwrap();
+                for violation in violations {
+                    if warnings.len() == MAX_VALIDATION_WARNINGS {
+                        warnings.pop_front();
+                    }
+                    warnings.push_back(format!("表 '{}': {}", table_name, violation));
+                }
+                Ok(())
+            }
+        }
     }
 
-    /// 清空表
-    pub async fn truncate_table(&self, table_name: &str) -> Result<usize> {
+    /// 设置表的溢出阈值（字节），表在内存中的占用超过阈值后把最旧的行写入
+    /// 数据目录下 `spill/` 中的段文件，扫描时按需读回。None 表示关闭溢出。
+    pub async fn set_spill_threshold(&self, table_name: &str, threshold: Option<usize>) -> Result<()> {
+        if threshold.is_some() {
+            self.check_disk()?;
+        }
+        let spill_dir = self.lock_disk().await.data_dir().map(|dir| format!("{}/spill", dir));
         let storage = self.storage.read().await;
-        let table = storage.get_table(table_name)
+        let mut table = storage.get_table_mut(table_name)?
             .ok_or_else(|| DatabaseError::TableNotFound(table_name.to_string()))?;
+        table.set_spill_policy(threshold.zip(spill_dir).map(|(bytes, dir)| SpillPolicy::new(bytes, dir)))
+    }
 
-        let _count = table.row_count();
+    /// 设置慢查询阈值，耗时达到阈值的查询记入慢查询日志；None 表示关闭
+    pub fn set_slow_query_threshold(&self, threshold: Option<Duration>) {
+        self.slow_log.lock().unwrap().set_threshold(threshold);
+    }
 
-        // 删除所有行
-        let query = QueryBuilder::delete(table_name).build();
-        let result = self.query(query).await?;
+    pub fn slow_query_threshold(&self) -> Option<Duration> {
+        self.slow_log.lock().unwrap().threshold()
+    }
 
-        Ok(result.affected_rows)
+    /// 设置慢查询日志保留的条目数，超出时丢弃最旧的记录
+    pub fn set_slow_query_log_capacity(&self, capacity: usize) {
+        self.slow_log.lock().unwrap().set_capacity(capacity);
     }
-}
 
-impl Default for DatabaseEngine {
-    fn default() -> Self {
-        Self::new()
+    /// 慢查询日志中保留的记录，按发生顺序排列
+    pub fn slow_queries(&self) -> Vec<SlowQuery> {
+        self.slow_log.lock().unwrap().entries()
     }
-}
 
-/// 表信息
-#[derive(Debug, Clone)]
-pub struct TableInfo {
-    pub name: String,
-    pub row_count: usize,
-    pub created_at: chrono::DateTime<chrono::Utc>,
-    pub schema: Schema,
-}
+    pub fn clear_slow_queries(&self) {
+        self.slow_log.lock().unwrap().clear();
+    }
 
-/// 数据库统计信息
-#[derive(Debug)]
-pub struct DatabaseStats {
+    /// 查询耗时达到阈值时写入慢查询日志
+    fn record_slow_query(&self, query: &Query, duration: Duration, result: &QueryResult) {
+        let mut slow_log = self.slow_log.lock().unwrap();
+        if slow_log.is_slow(duration) {
+            slow_log.record(SlowQuery {
+                shape: query.shape(),
+                query_type: query.query_type.clone(),
+                table_name: query.table_name.clone(),
+                duration,
+                rows_scanned: result.rows_scanned,
+                rows_returned: result.affected_rows,
+                recorded_at: chrono::Utc::now(),
+            });
+        }
+    }
+
+    /// 设置所有表的内存上限（字节），None 表示不限制
+    pub fn set_memory_limit(&mut self, limit: Option<usize>) {
+        self.memory_limit = limit;
+    }
+
+    /// 设置单表的内存上限（字节），None 表示不限制
+    pub fn set_table_memory_limit(&mut self, table_name: &str, limit: Option<usize>) {
+        match limit {
+            Some(limit) => self.table_memory_limits.insert(table_name.to_string(), limit),
+            None => self.table_memory_limits.remove(table_name),
+        };
+    }
+
+    /// 是否设置了作用于该表的内存上限
+    fn has_memory_limit(&self, table_name: &str) -> bool {
+        self.memory_limit.is_some() || self.table_memory_limits.contains_key(table_name)
+    }
+
+    /// 检查写入 `additional` 字节后是否超出单表或全局内存上限
+    fn check_memory_budget(&self, storage: &MemoryStorage, table: &Table, additional: usize) -> Result<()> {
+        let table_bytes = table.estimated_bytes() + additional;
+        if let Some(&limit) = self.table_memory_limits.get(&table.name) {
+            if table_bytes > limit {
+                return Err(DatabaseError::out_of_memory_budget(format!("表 '{}'", table.name), table_bytes, limit));
+            }
+        }
+
+        if let Some(limit) = self.memory_limit {
+            // 当前表持有写锁，其他表使用写锁释放时记录的估算值
+            let total = storage.memory_bytes()
+                .saturating_sub(storage.table_memory_bytes(&table.name))
+                + table_bytes;
+            if total > limit {
+                return Err(DatabaseError::out_of_memory_budget("数据库", total, limit));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 内存占用报告
+    pub async fn memory_usage(&self) -> MemoryUsage {
+        let storage = self.storage.read().await;
+        // 尚未从表文件加载的表不占内存，不计入
+        let mut tables: Vec<TableMemoryUsage> = storage.list_tables().iter()
+            .filter(|name| storage.is_table_loaded(name))
+            .filter_map(|name| storage.get_table(name).ok().flatten().map(|table| table.memory_usage()))
+            .collect();
+        tables.sort_by(|a, b| a.table.cmp(&b.table));
+
+        MemoryUsage {
+            tables,
+            limit: self.memory_limit,
+        }
+    }
+
+    /// 将现有数据转换为符合严格模式的形式，并切换到严格模式
+    ///
+    /// 类型不匹配的值会尝试转换（如文本 "2020-01-15" 转为 DATE），
+    /// 转换结果写入日志。存在未知列或无法转换的值时不修改任何数据并返回错误。
+    pub async fn make_strict(&self) -> Result<usize> {
+        self.check_writable()?;
+        let mut log = self.begin_log().await;
+        let mut storage = self.storage.write().await;
+
+        let mut conversions = Vec::new();
+        for table_name in storage.list_tables() {
+            let table = storage.get_table(&table_name)?
+                .ok_or_else(|| DatabaseError::TableNotFound(table_name.clone()))?;
+
+            for row in table.all_rows()?.iter() {
+                let mut converted = HashMap::new();
+                for (column, value) in &row.data {
+                    let column_def = table.schema.get_column(column)
+                        .ok_or_else(|| DatabaseError::column_not_found(format!("{}.{}", table_name, column)))?;
+                    if !value.is_compatible_with(&column_def.data_type) {
+                        let new_value = value.coerce_to(&column_def.data_type).ok_or_else(|| {
+                            DatabaseError::type_mismatch(format!(
+                                "表 '{}' 列 '{}' 的值 '{}' 无法转换为 {}",
+                                table_name, column, value, column_def.data_type
+                            ))
+                        })?;
+                        converted.insert(column.clone(), new_value);
+                    }
+                }
+                if !converted.is_empty() {
+                    conversions.push((table_name.clone(), row.id, converted));
+                }
+            }
+        }
+
+        let converted_rows = conversions.len();
+        for (table_name, id, updates) in conversions {
+            let now = clock::now();
+            storage.update_row_at(&table_name, id, updates.clone(), now)?;
+
+            if log.enabled() {
+                log.push(StorageOperation::Update {
+                    table: table_name,
+                    id,
+                    data: updates,
+                    updated_at: Some(now),
+                });
+            }
+        }
+        storage.set_validation_mode(ValidationMode::Strict);
+        if log.enabled() {
+            log.push(StorageOperation::SetValidationMode { mode: ValidationMode::Strict });
+        }
+        drop(storage);
+        log.commit().await?;
+        self.warnings.lock().unwrap().clear();
+
+        Ok(converted_rows)
+    }
+
+    /// 创建表
+    pub async fn create_table(&self, name: &str, schema: Schema) -> Result<()> {
+        self.create_table_with_layout(name, schema, TableLayout::Row).await
+    }
+
+    /// 使用指定存储布局创建表，分析型表可选择 `TableLayout::Columnar`
+    pub async fn create_table_with_layout(&self, name: &str, schema: Schema, layout: TableLayout) -> Result<()> {
+        self.create_table_with_options(name, schema, TableOptions::new().layout(layout)).await
+    }
+
+    /// 按建表选项创建表，可指定存储布局、分区方式与表级设置
+    pub async fn create_table_with_options(&self, name: &str, mut schema: Schema, options: TableOptions) -> Result<()> {
+        self.check_writable()?;
+        sql::validate_table(name, &schema)?;
+        options.apply_settings(&mut schema);
+        let mut log = self.begin_log().await;
+        self.storage.write().await.create_table_with_options(name, schema.clone(), options.clone())?;
+
+        // 记录操作日志
+        let operation = StorageOperation::Create {
+            table: name.to_string(),
+            schema,
+            layout: options.layout,
+            partition: options.partition,
+        };
+        log.disk_storage.track_unlogged(&operation);
+        if log.enabled() {
+            log.push(operation);
+        }
+
+        log.commit().await
+    }
+
+    /// 创建临时表
+    ///
+    /// 临时表只存在于当前引擎的内存中：上面的操作不写日志、不推送给从库，表也不进入快照与备份，
+    /// 引擎关闭或重启后随之消失。
+    pub async fn create_temp_table(&self, name: &str, schema: Schema) -> Result<()> {
+        self.check_writable()?;
+        sql::validate_table(name, &schema)?;
+        let mut log = self.begin_log().await;
+        self.storage.write().await.create_table(name, schema)?;
+        log.disk_storage.add_temp_table(name);
+        Ok(())
+    }
+
+    /// 是否为临时表
+    pub async fn is_temp_table(&self, name: &str) -> bool {
+        self.lock_disk().await.is_temp_table(name)
+    }
+
+    /// 删除表
+    pub async fn drop_table(&self, name: &str) -> Result<()> {
+        self.check_writable()?;
+        let mut log = self.begin_log().await;
+        self.storage.write().await.drop_table(name)?;
+        let temporary = log.disk_storage.remove_temp_table(name);
+
+        // 记录操作日志
+        let operation = StorageOperation::Drop {
+            table: name.to_string(),
+        };
+        log.disk_storage.track_unlogged(&operation);
+        if log.enabled() && !temporary {
+            log.push(operation);
+        }
+
+        log.commit().await
+    }
+
+    /// 重命名表
+    ///
+    /// 临时表改名后仍是临时表。
+    pub async fn rename_table(&self, name: &str, new_name: &str) -> Result<()> {
+        self.check_writable()?;
+        sql::validate_table_name(new_name)?;
+        let mut log = self.begin_log().await;
+        self.storage.write().await.rename_table(name, new_name)?;
+
+        if log.disk_storage.remove_temp_table(name) {
+            log.disk_storage.add_temp_table(new_name);
+        } else {
+            let operation = StorageOperation::Rename {
+                table: name.to_string(),
+                new_name: new_name.to_string(),
+            };
+            log.disk_storage.track_unlogged(&operation);
+            if log.enabled() {
+                log.push(operation);
+            }
+        }
+
+        log.commit().await
+    }
+
+    /// 复制表：按源表的表结构与建表选项创建 `target`，`with_data` 时同时复制所有行
+    ///
+    /// 日志只记录复制操作本身，重放时从当时的源表复制；临时表的副本也是临时表。
+    pub async fn copy_table(&self, source: &str, target: &str, with_data: bool) -> Result<()> {
+        self.check_writable()?;
+        sql::validate_table_name(target)?;
+        let mut log = self.begin_log().await;
+        {
+            let mut storage = self.storage.write().await;
+            if let (true, Some(limit)) = (with_data, self.memory_limit) {
+                let total = storage.memory_bytes() + storage.table_memory_bytes(source);
+                if total > limit {
+                    return Err(DatabaseError::out_of_memory_budget("数据库", total, limit));
+                }
+            }
+            storage.copy_table(source, target, with_data)?;
+        }
+
+        if log.disk_storage.is_temp_table(source) {
+            log.disk_storage.add_temp_table(target);
+        } else {
+            let operation = StorageOperation::Copy {
+                table: target.to_string(),
+                source: source.to_string(),
+                with_data,
+            };
+            log.disk_storage.track_unlogged(&operation);
+            if log.enabled() {
+                log.push(operation);
+            }
+        }
+
+        log.commit().await
+    }
+
+    /// 按查询结果创建新表，返回写入的行数
+    ///
+    /// 表结构取自结果列的名称与类型，列都允许为 NULL、不带约束；计算列的类型按第一个非 NULL 值推断。
+    /// 建表与写入在一次写锁内完成，只记录一条 `CreateAs` 日志，重放时不再执行查询。
+    pub async fn create_table_as(&self, name: &str, query: Query) -> Result<usize> {
+        self.check_writable()?;
+        if !matches!(query.query_type, QueryType::Select) {
+            return Err(DatabaseError::other("CREATE TABLE AS 只能使用 SELECT 查询"));
+        }
+        let result = self.query(query).await?;
+        let schema = Schema::new(result.columns.iter()
+            .map(|(column, data_type)| ColumnDefinition::new(column.clone(), data_type.clone(), false))
+            .collect());
+        sql::validate_table(name, &schema)?;
+        let rows: Vec<Row> = result.rows.into_iter()
+            .map(|source| {
+                let mut row = Row::new();
+                row.data = source.data;
+                row
+            })
+            .collect();
+        let inserted = rows.len();
+
+        let mut log = self.begin_log().await;
+        {
+            let mut storage = self.storage.write().await;
+            storage.create_table_as(name, schema.clone(), rows.clone())?;
+            if let Some(limit) = self.memory_limit {
+                let total = storage.memory_bytes();
+                if total > limit {
+                    storage.drop_table(name)?;
+                    return Err(DatabaseError::out_of_memory_budget("数据库", total, limit));
+                }
+            }
+        }
+
+        if log.enabled() {
+            log.push(StorageOperation::CreateAs { table: name.to_string(), schema, rows });
+        }
+        log.commit().await?;
+        Ok(inserted)
+    }
+
+    /// 把查询结果插入另一张表，返回新行的 ID
+    ///
+    /// `mapping` 为（目标列, 结果列）对，为空时按同名对应，只取目标表中存在的列；
+    /// 计算列可以在插入前转换数据。值按目标列的类型转换后整体作为一次批量插入校验与写入，
+    /// 任意一行违反目标表的约束时都不会写入。
+    pub async fn insert_from(&self, target: &str, query: Query, mapping: &[(&str, &str)]) -> Result<Vec<uuid::Uuid>> {
+        let schema = self.table_schema(target).await?;
+        let result = self.select_for_insert(query).await?;
+        let mapping: Vec<(String, String)> = if mapping.is_empty() {
+            result.columns.iter()
+                .filter(|(column, _)| schema.get_column(column).is_some())
+                .map(|(column, _)| (column.clone(), column.clone()))
+                .collect()
+        } else {
+            mapping.iter().map(|(column, source)| (column.to_string(), source.to_string())).collect()
+        };
+        self.insert_mapped(target, &schema, result, &mapping).await
+    }
+
+    /// 执行 INSERT ... SELECT 的查询部分
+    async fn select_for_insert(&self, query: Query) -> Result<QueryResult> {
+        if !matches!(query.query_type, QueryType::Select) {
+            return Err(DatabaseError::other("INSERT ... SELECT 只能使用 SELECT 查询"));
+        }
+        self.query(query).await
+    }
+
+    /// 按列对应关系把结果行转换为目标表的行并批量插入
+    async fn insert_mapped(&self, target: &str, schema: &Schema, result: QueryResult, mapping: &[(String, String)]) -> Result<Vec<uuid::Uuid>> {
+        for (column, source) in mapping {
+            if schema.get_column(column).is_none() {
+                return Err(DatabaseError::column_not_found(column.clone()));
+            }
+            if !result.columns.iter().any(|(name, _)| name == source) {
+                return Err(DatabaseError::column_not_found(source.clone()));
+            }
+        }
+
+        let mut rows = Vec::with_capacity(result.rows.len());
+        for source in result.rows {
+            let mut data = HashMap::new();
+            for (column, source_column) in mapping {
+                let value = source.get(source_column).cloned().unwrap_or(Value::Null);
+                let data_type = schema.get_column(column).map(|c| &c.data_type);
+                data.insert(column.clone(), sql::coerce_literal(value, data_type)?);
+            }
+            rows.push(data);
+        }
+        self.batch_insert(target, rows).await
+    }
+
+    /// 插入数据
+    pub async fn insert(&self, table_name: &str, data: HashMap<String, Value>) -> Result<uuid::Uuid> {
+        self.check_writable()?;
+        let mut row = Row::new();
+        for (column, value) in data {
+            row.set(column, value);
+        }
+
+        let row_id = row.id;
+        let mut log = self.begin_log().await;
+        let audit = self.audit_for(&log, table_name);
+        let mut advanced = BTreeMap::new();
+        {
+            let storage = self.storage.read().await;
+            let mut table = storage.get_table_mut(table_name)?
+                .ok_or_else(|| DatabaseError::TableNotFound(table_name.to_string()))?;
+            self.check_strict(&storage, table_name, &table.schema, &row.data)?;
+            table.apply_defaults_with(&mut row, &mut sequence_resolver(&storage, &mut advanced))?;
+            self.check_memory_budget(&storage, &table, table.estimate_insert(std::slice::from_ref(&row)))?;
+            table.insert(row.clone())?;
+        }
+        let changes = match audit {
+            Some(_) => vec![AuditChange::insert(table_name, row.clone())],
+            None => Vec::new(),
+        };
+
+        // 记录操作日志
+        if log.enabled() {
+            log.push_sequences(advanced);
+            log.push(StorageOperation::Insert {
+                table: table_name.to_string(),
+                row,
+            });
+        }
+        self.record_audit(&mut log, audit, changes).await?;
+        log.commit().await?;
+
+        Ok(row_id)
+    }
+
+    /// 查询数据
+    pub async fn query(&self, query: Query) -> Result<QueryResult> {
+        let start_time = Instant::now();
+        // 只有开启慢查询日志时才需要保留查询用于记录
+        let tracked = self.slow_query_threshold().map(|_| query.clone());
+        let table = {
+            let storage = self.storage.read().await;
+            let table = match storage.get_table(&query.table_name)? {
+                Some(table) => table.clone(),
+                None => catalog::system_table(&storage, &query.table_name)
+                    .ok_or_else(|| DatabaseError::TableNotFound(query.table_name.clone()))?,
+            };
+            table
+        };
+
+        let engine = QueryEngine::new();
+        let result = engine.execute(table, query).await?;
+        if let Some(query) = tracked {
+            self.record_slow_query(&query, start_time.elapsed(), &result);
+        }
+        Ok(result)
+    }
+
+    /// 对满足查询条件的行计算数值列的 SUM、AVG、MIN 或 MAX，忽略 NULL，没有值时为 NULL
+    ///
+    /// 列式表按 1024 个值一批求值条件与聚合，不物化行。
+    pub async fn aggregate(&self, query: Query, aggregate: Aggregate, column: &str) -> Result<Value> {
+        let table = self.storage.read().await.get_table(&query.table_name)?
+            .ok_or_else(|| DatabaseError::TableNotFound(query.table_name.clone()))?
+            .clone();
+        QueryEngine::new().aggregate(table, query, aggregate, column).await
+    }
+
+    /// 查询并把结果行映射为 `T`
+    pub async fn query_as<T: FromRow>(&self, query: Query) -> Result<Vec<T>> {
+        self.query(query).await?.rows.iter().map(T::from_row).collect()
+    }
+
+    /// 插入结构体，列值由 `ToRow` 生成
+    pub async fn insert_struct<T: ToRow + ?Sized>(&self, table_name: &str, value: &T) -> Result<uuid::Uuid> {
+        self.insert(table_name, value.to_row()).await
+    }
+
+    /// 插入任意可序列化的值，值必须序列化为 JSON 对象，字段按表结构转换为列类型
+    ///
+    /// 表结构中没有的字段按 JSON 类型推断，`None` 字段写入 NULL。
+    pub async fn insert_serde<T: serde::Serialize + ?Sized>(&self, table_name: &str, value: &T) -> Result<uuid::Uuid> {
+        let schema = self.get_table_info(table_name).await?.schema;
+        let object = match serde_json::to_value(value)? {
+            serde_json::Value::Object(object) => object,
+            other => return Err(DatabaseError::type_mismatch(format!("只能插入序列化为对象的值，实际为 {}", other))),
+        };
+
+        let mut data = HashMap::new();
+        for (column, json) in object {
+            let data_type = schema.get_column(&column).map(|c| &c.data_type);
+            let value = Value::from_json(&json, data_type)
+                .map_err(|e| DatabaseError::type_mismatch(format!("字段 '{}': {}", column, e)))?;
+            data.insert(column, value);
+        }
+        self.insert(table_name, data).await
+    }
+
+    /// 执行查询，INSERT/UPDATE/DELETE 会真正修改数据并记录日志
+    pub async fn execute(&self, query: Query) -> Result<QueryResult> {
+        if let Some(placeholder) = query.unbound_param() {
+            return Err(DatabaseError::parse_error(format!("参数 {} 未绑定", placeholder)));
+        }
+
+        let start_time = Instant::now();
+        // 写操作逐行检查条件，读取的行数即表的行数
+        let rows_scanned = match query.query_type {
+            QueryType::Update | QueryType::Delete => self.storage.read().await
+                .get_table(&query.table_name)?.map_or(0, |table| table.row_count()),
+            _ => 0,
+        };
+
+        let mut result = match query.query_type {
+            QueryType::Select | QueryType::Count => return self.query(query).await,
+            QueryType::Update | QueryType::Delete if query.dry_run => self.dry_run(&query).await?,
+            QueryType::Insert if !query.rows.is_empty() => {
+                let ids = self.batch_insert(&query.table_name, query.rows.clone()).await?;
+                QueryResult::new(QueryType::Insert, query.table_name.clone(), 0).with_affected_ids(ids)
+            }
+            QueryType::Insert => {
+                let data = query.data.clone()
+                    .ok_or_else(|| DatabaseError::other("INSERT 查询缺少数据"))?;
+                let id = self.insert(&query.table_name, data).await?;
+                QueryResult::new(QueryType::Insert, query.table_name.clone(), 0).with_affected_ids(vec![id])
+            }
+            QueryType::Update => {
+                let data = query.data.clone()
+                    .ok_or_else(|| DatabaseError::other("UPDATE 查询缺少数据"))?;
+                let affected = self.update_bounded(
+                    &query.table_name, query.conditions.clone(), query.order_by.clone(), query.limit, data,
+                ).await?;
+                QueryResult::new(QueryType::Update, query.table_name.clone(), 0).with_affected_ids(affected)
+            }
+            QueryType::Delete => {
+                let affected = self.delete_bounded(
+                    &query.table_name, query.conditions.clone(), query.order_by.clone(), query.limit,
+                ).await?;
+                QueryResult::new(QueryType::Delete, query.table_name.clone(), 0).with_affected_ids(affected)
+            }
+        };
+
+        let elapsed = start_time.elapsed();
+        result.execution_time_ms = elapsed.as_millis() as u64;
+        result.rows_scanned = rows_scanned;
+        self.record_slow_query(&query, elapsed, &result);
+        Ok(result)
+    }
+
+    /// 开启审计：之后每次插入、更新与删除都在 `_audit` 表中记录操作者、时间与修改前后的值
+    ///
+    /// 审计表不存在时先创建。审计记录与被审计的修改写在同一批日志中，随快照保存、随日志复制；
+    /// 对临时表与审计表本身的修改不记录。
+    pub async fn enable_audit(&self, config: AuditConfig) -> Result<()> {
+        if self.get_table_info(AUDIT_TABLE).await.is_err() {
+            self.create_table(AUDIT_TABLE, audit_schema()).await?;
+        }
+        *self.audit.lock().unwrap() = Some(config);
+        Ok(())
+    }
+
+    /// 关闭审计，已有的审计记录保留
+    pub fn disable_audit(&self) {
+        *self.audit.lock().unwrap() = None;
+    }
+
+    /// 当前的审计配置，未开启时为 None
+    pub fn audit_config(&self) -> Option<AuditConfig> {
+        self.audit.lock().unwrap().clone()
+    }
+
+    /// 按保留策略删除过期与超出数量的审计记录，返回删除的条数；未开启审计时不做任何事
+    pub async fn apply_audit_retention(&self) -> Result<usize> {
+        let Some(config) = self.audit_config() else { return Ok(0) };
+        let mut deleted = 0;
+        if let Some(max_age) = config.retention.max_age {
+            let cutoff = (clock::now() - max_age).naive_utc();
+            deleted += self.delete(AUDIT_TABLE, vec![
+                ("at".to_string(), ComparisonOperator::LessThan, Value::DateTime(cutoff)),
+            ]).await?.len();
+        }
+        if let Some(max_rows) = config.retention.max_rows {
+            let excess = self.get_table_info(AUDIT_TABLE).await?.row_count.saturating_sub(max_rows);
+            if excess > 0 {
+                deleted += self.delete_bounded(AUDIT_TABLE, Vec::new(), vec![OrderBy::new("at", true)], Some(excess)).await?.len();
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// 需要审计对 `table_name` 的修改时返回审计配置
+    fn audit_for(&self, log: &LogBatch, table_name: &str) -> Option<AuditConfig> {
+        if table_name == AUDIT_TABLE || log.disk_storage.is_temp_table(table_name) {
+            return None;
+        }
+        self.audit.lock().unwrap().clone()
+    }
+
+    /// 把修改写入审计表，与被审计的修改记在同一批日志中
+    async fn record_audit(&self, log: &mut LogBatch, audit: Option<AuditConfig>, changes: Vec<AuditChange>) -> Result<()> {
+        let Some(config) = audit.filter(|_| !changes.is_empty()) else { return Ok(()) };
+        let now = clock::now();
+        let actor = SESSION_USER.try_with(Clone::clone).unwrap_or(config.actor);
+        let rows: Vec<Row> = changes.into_iter().map(|change| change.into_row(&actor, now)).collect();
+        {
+            let storage = self.storage.read().await;
+            let mut table = storage.get_table_mut(AUDIT_TABLE)?
+                .ok_or_else(|| DatabaseError::TableNotFound(AUDIT_TABLE.to_string()))?;
+            table.insert_batch(rows.clone())?;
+        }
+        if log.enabled() {
+            log.push(StorageOperation::BatchInsert {
+                table: AUDIT_TABLE.to_string(),
+                rows,
+            });
+        }
+        Ok(())
+    }
+
+    /// 求出 UPDATE/DELETE 会影响的行，按与实际执行相同的条件、排序与 LIMIT 选择，不修改数据也不写日志
+    async fn dry_run(&self, query: &Query) -> Result<QueryResult> {
+        let storage = self.storage.read().await;
+        let table = storage.get_table(&query.table_name)?
+            .ok_or_else(|| DatabaseError::TableNotFound(query.table_name.clone()))?;
+        if let Some(data) = &query.data {
+            self.check_strict(&storage, &query.table_name, &table.schema, data)?;
+        }
+
+        let (conditions, order_by) = resolve_filter(query.conditions.clone(), query.order_by.clone(), &table.schema)?;
+        let targets = limited_targets(&table, &conditions, &order_by, query.limit)?;
+        let rows: Vec<Row> = table.all_rows()?.iter()
+            .filter(|row| match &targets {
+                Some(ids) => ids.contains(&row.id),
+                None => matches_all(&conditions, row),
+            })
+            .cloned()
+            .collect();
+        let ids = rows.iter().map(|row| row.id).collect();
+
+        let mut result = QueryResult::new(query.query_type.clone(), query.table_name.clone(), 0)
+            .with_rows(rows)
+            .with_affected_ids(ids)
+            .with_schema(table.schema.clone());
+        result.dry_run = true;
+        Ok(result)
+    }
+
+    /// 创建存储过程
+    pub async fn create_procedure(&self, procedure: Procedure) -> Result<()> {
+        self.check_writable()?;
+        let mut log = self.begin_log().await;
+        self.storage.write().await.create_procedure(procedure.clone())?;
+
+        if log.enabled() {
+            log.push(StorageOperation::CreateProcedure { procedure });
+        }
+
+        log.commit().await
+    }
+
+    /// 删除存储过程
+    pub async fn drop_procedure(&self, name: &str) -> Result<()> {
+        self.check_writable()?;
+        let mut log = self.begin_log().await;
+        self.storage.write().await.drop_procedure(name)?;
+
+        if log.enabled() {
+            log.push(StorageOperation::DropProcedure {
+                name: name.to_string(),
+            });
+        }
+
+        log.commit().await
+    }
+
+    /// 创建序列，第一次 `nextval` 返回 `start`，之后每次加 `step`（可以为负）
+    ///
+    /// 序列可以在 SQL 中以 `NEXTVAL('name')`、`CURRVAL('name')` 用作列默认值或 INSERT 的值。
+    pub async fn create_sequence(&self, name: &str, start: i64, step: i64) -> Result<()> {
+        self.check_writable()?;
+        sql::validate_identifier(name)?;
+        let sequence = Sequence::new(name, start, step)?;
+        let mut log = self.begin_log().await;
+        self.storage.write().await.create_sequence(sequence.clone())?;
+
+        if log.enabled() {
+            log.push(StorageOperation::CreateSequence { sequence });
+        }
+
+        log.commit().await
+    }
+
+    /// 删除序列
+    pub async fn drop_sequence(&self, name: &str) -> Result<()> {
+        self.check_writable()?;
+        let mut log = self.begin_log().await;
+        self.storage.write().await.drop_sequence(name)?;
+
+        if log.enabled() {
+            log.push(StorageOperation::DropSequence {
+                name: name.to_string(),
+            });
+        }
+
+        log.commit().await
+    }
+
+    /// 取序列的下一个值
+    pub async fn nextval(&self, name: &str) -> Result<i64> {
+        self.check_writable()?;
+        let mut log = self.begin_log().await;
+        let value = self.storage.read().await.next_sequence_value(name)?;
+
+        if log.enabled() {
+            log.push(StorageOperation::SetSequence { name: name.to_string(), value });
+        }
+
+        log.commit().await?;
+        Ok(value)
+    }
+
+    /// 序列最近一次取得的值，从未取值时返回错误
+    pub async fn currval(&self, name: &str) -> Result<i64> {
+        self.storage.read().await.current_sequence_value(name)
+    }
+
+    /// 列出所有序列，按名称排序
+    pub async fn list_sequences(&self) -> Vec<Sequence> {
+        self.storage.read().await.get_sequences()
+    }
+
+    /// 列出所有存储过程
+    pub async fn list_procedures(&self) -> Vec<Procedure> {
+        let storage = self.storage.read().await;
+        let mut procedures = storage.get_procedures();
+        procedures.sort_by(|a, b| a.name.cmp(&b.name));
+        procedures
+    }
+
+    /// 调用存储过程，返回最后一条执行语句的结果
+    pub async fn call(&self, name: &str, args: Vec<Value>) -> Result<QueryResult> {
+        let procedure = {
+            let storage = self.storage.read().await;
+            storage.get_procedure(name)
+                .cloned()
+                .ok_or_else(|| DatabaseError::other(format!("存储过程 '{}' 不存在", name)))?
+        };
+        let params = procedure.bind_args(args)?;
+
+        let mut last_result = QueryResult::new(QueryType::Select, String::new(), 0);
+        let mut stack = vec![procedure.body.iter()];
+
+        while let Some(steps) = stack.last_mut() {
+            match steps.next() {
+                None => {
+                    stack.pop();
+                }
+                Some(ProcedureStep::Execute(query)) => {
+                    last_result = self.execute(query.clone().bind(&params)?).await?;
+                }
+                Some(ProcedureStep::IfAffectedRows { operator, value, then_steps, else_steps }) => {
+                    let branch = if compare_affected_rows(operator, last_result.affected_rows, *value)? {
+                        then_steps
+                    } else {
+                        else_steps
+                    };
+                    stack.push(branch.iter());
+                }
+            }
+        }
+
+        Ok(last_result)
+    }
+
+    /// 更新数据，返回被更新行的 ID
+    pub async fn update(&self, table_name: &str, conditions: Vec<(String, ComparisonOperator, Value)>, updates: HashMap<String, Value>) -> Result<Vec<uuid::Uuid>> {
+        self.update_matching(table_name, to_conditions(conditions), updates).await
+    }
+
+    /// 按完整的查询条件更新数据，条件可以包含 JSON 路径与排序规则
+    pub async fn update_matching(&self, table_name: &str, conditions: Vec<Condition>, updates: HashMap<String, Value>) -> Result<Vec<uuid::Uuid>> {
+        self.update_bounded(table_name, conditions, Vec::new(), None, updates).await
+    }
+
+    /// 只更新满足条件的行中按 `order_by` 排序后的前 `limit` 行，`limit` 为 None 时更新全部
+    pub async fn update_bounded(
+        &self,
+        table_name: &str,
+        conditions: Vec<Condition>,
+        order_by: Vec<OrderBy>,
+        limit: Option<usize>,
+        updates: HashMap<String, Value>,
+    ) -> Result<Vec<uuid::Uuid>> {
+        self.check_writable()?;
+        let mut log = self.begin_log().await;
+        let audit = self.audit_for(&log, table_name);
+        let now = clock::now();
+        let (conditions, targets, updated_ids, changes) = {
+            let storage = self.storage.read().await;
+            let mut table = storage.get_table_mut(table_name)?
+                .ok_or_else(|| DatabaseError::TableNotFound(table_name.to_string()))?;
+            self.check_strict(&storage, table_name, &table.schema, &updates)?;
+
+            let (conditions, order_by) = resolve_filter(conditions, order_by, &table.schema)?;
+            let targets = limited_targets(&table, &conditions, &order_by, limit)?;
+            let selected = |row: &Row| match &targets {
+                Some(ids) => ids.contains(&row.id),
+                None => matches_all(&conditions, row),
+            };
+            // 更新可能使行变大（例如写入长文本或 JSON），与插入一样先检查内存上限
+            if self.has_memory_limit(table_name) {
+                let growth = table.estimate_update(table.all_rows()?.iter().filter(|row| selected(row)), &updates);
+                self.check_memory_budget(&storage, &table, growth)?;
+            }
+            let before = match audit {
+                Some(_) => table.all_rows()?.iter().filter(|row| selected(row)).cloned().collect(),
+                None => Vec::new(),
+            };
+            let updated_ids = table.update_where(selected, &updates, now)?;
+            let changes = before.into_iter()
+                .map(|before| {
+                    let after = table.find_by_id(before.id)?.map(std::borrow::Cow::into_owned).unwrap_or_default();
+                    Ok(AuditChange::update(table_name, before, after))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            (conditions, targets, updated_ids, changes)
+        };
+        // 记录操作日志：条件可以在重放时重现结果时只写一条 UpdateWhere，否则逐行记录
+        if log.enabled() && !updated_ids.is_empty() {
+            if targets.is_none() && conditions.iter().all(Condition::is_deterministic) {
+                log.push(StorageOperation::UpdateWhere {
+                    table: table_name.to_string(),
+                    conditions,
+                    data: updates,
+                    updated_at: now,
+                });
+            } else {
+                for id in &updated_ids {
+                    log.push(StorageOperation::Update {
+                        table: table_name.to_string(),
+                        id: *id,
+                        data: updates.clone(),
+                        updated_at: Some(now),
+                    });
+                }
+            }
+        }
+        self.record_audit(&mut log, audit, changes).await?;
+        log.commit().await?;
+
+        Ok(updated_ids)
+    }
+
+    /// 乐观并发更新：仅当行的当前版本等于 `expected_version` 时才更新
+    ///
+    /// 行已被其他写入修改时返回 `DatabaseError::Conflict`，成功时返回新的版本号。
+    pub async fn update_if_version(&self, table_name: &str, id: uuid::Uuid, expected_version: u64, updates: HashMap<String, Value>) -> Result<u64> {
+        self.check_writable()?;
+        let mut log = self.begin_log().await;
+        let audit = self.audit_for(&log, table_name);
+        let now = clock::now();
+        let operation_data = updates.clone();
+        let changes = {
+            let storage = self.storage.read().await;
+            let mut table = storage.get_table_mut(table_name)?
+                .ok_or_else(|| DatabaseError::TableNotFound(table_name.to_string()))?;
+            self.check_strict(&storage, table_name, &table.schema, &updates)?;
+
+            let before = table.find_by_id(id)?
+                .ok_or_else(|| DatabaseError::other(format!("未找到ID为 {} 的行", id)))?
+                .into_owned();
+            if before.version != expected_version {
+                return Err(DatabaseError::conflict(id.to_string(), expected_version, before.version));
+            }
+            if self.has_memory_limit(table_name) {
+                self.check_memory_budget(&storage, &table, table.estimate_update([&before], &updates))?;
+            }
+            table.update_at(id, updates, now)?;
+            match audit {
+                Some(_) => {
+                    let after = table.find_by_id(id)?.map(std::borrow::Cow::into_owned).unwrap_or_default();
+                    vec![AuditChange::update(table_name, before, after)]
+                }
+                None => Vec::new(),
+            }
+        };
+
+        if log.enabled() {
+            log.push(StorageOperation::Update {
+                table: table_name.to_string(),
+                id,
+                data: operation_data,
+                updated_at: Some(now),
+            });
+        }
+        self.record_audit(&mut log, audit, changes).await?;
+        log.commit().await?;
+
+        Ok(expected_version + 1)
+    }
+
+    /// 删除数据，返回被删除行的 ID
+    pub async fn delete(&self, table_name: &str, conditions: Vec<(String, ComparisonOperator, Value)>) -> Result<Vec<uuid::Uuid>> {
+        self.delete_matching(table_name, to_conditions(conditions)).await
+    }
+
+    /// 按完整的查询条件删除数据，条件可以包含 JSON 路径与排序规则
+    pub async fn delete_matching(&self, table_name: &str, conditions: Vec<Condition>) -> Result<Vec<uuid::Uuid>> {
+        self.delete_bounded(table_name, conditions, Vec::new(), None).await
+    }
+
+    /// 删除表中已过期的行，即最后修改早于表设置的存活时间的行，返回被删除的行数
+    ///
+    /// 过期的行对查询已不可见，这里把它们真正删除并照常写日志；表没有设置存活时间时什么都不做。
+    pub async fn purge_expired(&self, table_name: &str) -> Result<usize> {
+        let ttl = {
+            let storage = self.storage.read().await;
+            let table = storage.get_table(table_name)?
+                .ok_or_else(|| DatabaseError::TableNotFound(table_name.to_string()))?;
+            table.schema.settings.ttl
+        };
+        let Some(ttl) = ttl else { return Ok(0) };
+        let condition = Condition::new(UPDATED_AT_COLUMN, ComparisonOperator::LessThan, query::expiry_cutoff(ttl));
+        Ok(self.delete_matching(table_name, vec![condition]).await?.len())
+    }
+
+    /// 只删除满足条件的行中按 `order_by` 排序后的前 `limit` 行，例如删除最旧的 100 行
+    pub async fn delete_bounded(
+        &self,
+        table_name: &str,
+        conditions: Vec<Condition>,
+        order_by: Vec<OrderBy>,
+        limit: Option<usize>,
+    ) -> Result<Vec<uuid::Uuid>> {
+        self.check_writable()?;
+        let mut log = self.begin_log().await;
+        let audit = self.audit_for(&log, table_name);
+        let (conditions, targets, deleted_ids, changes) = {
+            let storage = self.storage.read().await;
+            let mut table = storage.get_table_mut(table_name)?
+                .ok_or_else(|| DatabaseError::TableNotFound(table_name.to_string()))?;
+
+            let (conditions, order_by) = resolve_filter(conditions, order_by, &table.schema)?;
+            let targets = limited_targets(&table, &conditions, &order_by, limit)?;
+            let selected = |row: &Row| match &targets {
+                Some(ids) => ids.contains(&row.id),
+                None => matches_all(&conditions, row),
+            };
+            let changes = match audit {
+                Some(_) => table.all_rows()?.iter()
+                    .filter(|row| selected(row))
+                    .map(|row| AuditChange::delete(table_name, row.clone()))
+                    .collect(),
+                None => Vec::new(),
+            };
+            let deleted_ids = table.delete_where(selected)?;
+            (conditions, targets, deleted_ids, changes)
+        };
+        // 记录操作日志
+        if log.enabled() && !deleted_ids.is_empty() {
+            if targets.is_none() && conditions.iter().all(Condition::is_deterministic) {
+                log.push(StorageOperation::DeleteWhere {
+                    table: table_name.to_string(),
+                    conditions,
+                });
+            } else {
+                for row_id in &deleted_ids {
+                    log.push(StorageOperation::Delete {
+                        table: table_name.to_string(),
+                        id: *row_id,
+                    });
+                }
+            }
+        }
+        self.record_audit(&mut log, audit, changes).await?;
+        log.commit().await?;
+
+        Ok(deleted_ids)
+    }
+
+    /// 收集表的统计信息（行数、不同值个数、最小/最大值、NULL 比例），随快照保存
+    pub async fn analyze(&self, table_name: &str) -> Result<TableStats> {
+        let storage = self.storage.read().await;
+        let mut table = storage.get_table_mut(table_name)?
+            .ok_or_else(|| DatabaseError::TableNotFound(table_name.to_string()))?;
+        table.analyze().cloned()
+    }
+
+    /// 收集所有表的统计信息
+    pub async fn analyze_all(&self) -> Result<()> {
+        let names = self.storage.read().await.list_tables();
+        for name in names {
+            self.analyze(&name).await?;
+        }
+        Ok(())
+    }
+
+    /// 表最近一次 ANALYZE 的统计信息，从未分析过时返回 None
+    pub async fn table_stats(&self, table_name: &str) -> Result<Option<TableStats>> {
+        let storage = self.storage.read().await;
+        let table = storage.get_table(table_name)?
+            .ok_or_else(|| DatabaseError::TableNotFound(table_name.to_string()))?;
+        Ok(table.stats.clone())
+    }
+
+    /// 按行 ID 读取一行，ID 即 `insert` 的返回值
+    pub async fn get_row(&self, table_name: &str, id: uuid::Uuid) -> Result<Option<Row>> {
+        let storage = self.storage.read().await;
+        let table = storage.get_table(table_name)?
+            .ok_or_else(|| DatabaseError::TableNotFound(table_name.to_string()))?;
+        Ok(table.find_by_id(id)?.map(std::borrow::Cow::into_owned))
+    }
+
+    /// 获取表信息
+    pub async fn get_table_info(&self, table_name: &str) -> Result<TableInfo> {
+        let storage = self.storage.read().await;
+        let table = storage.get_table(table_name)?
+            .ok_or_else(|| DatabaseError::TableNotFound(table_name.to_string()))?;
+
+        Ok(TableInfo {
+            name: table.name.clone(),
+            row_count: table.row_count(),
+            created_at: table.created_at,
+            schema: table.schema().clone(),
+        })
+    }
+
+    /// 表结构，包括 `information_schema` 下的系统目录表
+    async fn table_schema(&self, table_name: &str) -> Result<Schema> {
+        let storage = self.storage.read().await;
+        if let Some(table) = storage.get_table(table_name)? {
+            return Ok(table.schema().clone());
+        }
+        catalog::system_table(&storage, table_name)
+            .map(|table| table.schema().clone())
+            .ok_or_else(|| DatabaseError::TableNotFound(table_name.to_string()))
+    }
+
+    /// 列出所有表
+    pub async fn list_tables(&self) -> Vec<TableInfo> {
+        let storage = self.storage.read().await;
+        let mut tables = Vec::new();
+
+        for table_name in storage.list_tables() {
+            if let Ok(Some(table)) = storage.get_table(&table_name) {
+                tables.push(TableInfo {
+                    name: table.name.clone(),
+                    row_count: table.row_count(),
+                    created_at: table.created_at,
+                    schema: table.schema().clone(),
+                });
+            }
+        }
+
+        tables
+    }
+
+    /// 建表语句，`table_name` 为 None 时按表名顺序输出所有表
+    pub async fn schema_sql(&self, table_name: Option<&str>) -> Result<String> {
+        let storage = self.storage.read().await;
+        let names = match table_name {
+            Some(name) => vec![name.to_string()],
+            None => {
+                let mut names = storage.list_tables();
+                names.sort();
+                names
+            }
+        };
+
+        let mut out = String::new();
+        for name in names {
+            let table = storage.get_table(&name)?
+                .ok_or_else(|| DatabaseError::TableNotFound(name.clone()))?;
+            out.push_str(&create_table_sql(&table));
+        }
+        Ok(out)
+    }
+
+    /// 列出索引：主键与唯一约束列，以及数组列上按需构建的元素索引
+    pub async fn list_indexes(&self, table_name: Option<&str>) -> Result<Vec<IndexInfo>> {
+        let storage = self.storage.read().await;
+        let names = match table_name {
+            Some(name) => vec![name.to_string()],
+            None => {
+                let mut names = storage.list_tables();
+                names.sort();
+                names
+            }
+        };
+
+        let mut indexes = Vec::new();
+        for name in names {
+            let table = storage.get_table(&name)?
+                .ok_or_else(|| DatabaseError::TableNotFound(name.clone()))?;
+            for column in &table.schema.columns {
+                let kind = if column.primary_key {
+                    IndexKind::PrimaryKey
+                } else if column.unique {
+                    IndexKind::Unique
+                } else if column.data_type.element_type().is_some() && !table.is_partitioned() {
+                    IndexKind::Element
+                } else {
+                    continue;
+                };
+                indexes.push(IndexInfo { table_name: name.clone(), column: column.name.clone(), kind });
+            }
+        }
+        Ok(indexes)
+    }
+
+    /// 检查数据完整性，返回发现的问题，不修改任何数据
+    ///
+    /// 逐表检查行与位置索引、分区、溢出键以及约束是否一致，再校验数据目录中快照、表文件与日志段的校验和；
+    /// 纯内存模式的数据库只检查表。
+    pub async fn check_integrity(&self) -> Result<IntegrityReport> {
+        let mut report = IntegrityReport::default();
+        {
+            let storage = self.storage.read().await;
+            let mut names = storage.list_tables();
+            names.sort();
+            for name in names {
+                let Some(table) = storage.get_table(&name)? else { continue };
+                report.tables_checked += 1;
+                report.rows_checked += table.row_count();
+                report.issues.extend(table.integrity_issues()?);
+            }
+        }
+        if !self.in_memory {
+            self.lock_disk().await.verify_files(&mut report)?;
+        }
+        Ok(report)
+    }
+
+    /// 获取数据库统计信息
+    pub async fn get_stats(&self) -> Result<DatabaseStats> {
+        let storage_stats = if self.in_memory {
+            crate::storage::StorageStats { current_log_id: self.last_log_id().await, ..Default::default() }
+        } else {
+            self.with_disk(|disk_storage| disk_storage.get_stats()).await?
+        };
+        let storage = self.storage.read().await;
+
+        let total_rows = storage.list_tables().iter()
+            .filter_map(|name| storage.get_table(name).transpose())
+            .map(|table| table.map(|table| table.row_count()))
+            .sum::<Result<usize>>()?;
+        let total_tables = storage.list_tables().len();
+        drop(storage);
+
+        Ok(DatabaseStats {
+            total_tables,
+            total_rows,
+            storage_stats,
+            memory: self.memory_usage().await,
+        })
+    }
+
+    /// 备份数据库
+    #[cfg(feature = "native")]
+    pub async fn backup(&self, backup_path: &str) -> Result<()> {
+        self.backup_to(&mut DirectorySink::new(backup_path)).await
+    }
+
+    /// 把备份写入任意备份目标，例如 tar 流、标准输出或对象存储
+    ///
+    /// 热备份：只在建立检查点时短暂持有锁，之后的序列化、读取日志与写入备份目标期间写操作照常进行。
+    /// 备份的内容是检查点时刻的一致状态，日志只包含不晚于检查点的条目。
+    pub async fn backup_to<S: BackupSink + ?Sized>(&self, sink: &mut S) -> Result<()> {
+        let checkpoint = {
+            let disk_storage = self.lock_disk().await;
+            let storage = self.storage.read().await;
+            disk_storage.checkpoint(&storage)?
+        };
+        let log_id = checkpoint.log_id();
+        let files = clock::spawn_blocking(move || checkpoint.files()).await??;
+
+        for (name, data) in &files {
+            sink.write_file(name, data).await?;
+        }
+        sink.finish().await?;
+
+        self.lock_disk().await.mark_backup(log_id);
+        Ok(())
+    }
+
+    /// 恢复数据库
+    pub async fn restore(&self, backup_path: &str) -> Result<()> {
+        self.check_disk()?;
+        let backup_path = backup_path.to_string();
+        let snapshot = self.with_disk(move |disk_storage| {
+            disk_storage.restore(&backup_path)?;
+            disk_storage.recover_log_id()?;
+            Ok((disk_storage.load_snapshot()?, disk_storage.tables_dir()))
+        }).await?;
+
+        // 丢弃当前数据，按恢复后的快照与日志重新加载
+        self.reload(snapshot).await
+    }
+
+    /// 应用日志操作
+    ///
+    /// `timestamp` 为日志条目时间，用于旧日志中缺少更新时间的 Update 操作。
+    fn apply_log_operation(&self, storage: &mut MemoryStorage, operation: StorageOperation, timestamp: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        match operation {
+            StorageOperation::Create { table, schema, layout, partition } => {
+                storage.create_table_with_options(&table, schema, TableOptions { layout, partition, ..TableOptions::default() })?;
+            }
+            StorageOperation::Insert { table, row } => {
+                if let Some(existing) = storage.get_table(&table)? {
+                    self.check_strict(storage, &table, &existing.schema, &row.data)?;
+                }
+                storage.insert_row(&table, row)?;
+            }
+            StorageOperation::BatchInsert { table, rows } => {
+                if let Some(existing) = storage.get_table(&table)? {
+                    for row in &rows {
+                        self.check_strict(storage, &table, &existing.schema, &row.data)?;
+                    }
+                }
+                storage.insert_rows(&table, rows)?;
+            }
+            StorageOperation::Update { table, id, data, updated_at } => {
+                if let Some(existing) = storage.get_table(&table)? {
+                    self.check_strict(storage, &table, &existing.schema, &data)?;
+                }
+                storage.update_row_at(&table, id, data, updated_at.unwrap_or(timestamp))?;
+            }
+            StorageOperation::Delete { table, id } => {
+                storage.delete_row(&table, id)?;
+            }
+            StorageOperation::UpdateWhere { table, conditions, data, updated_at } => {
+                if let Some(existing) = storage.get_table(&table)? {
+                    self.check_strict(storage, &table, &existing.schema, &data)?;
+                }
+                storage.update_rows_where(&table, &conditions, &data, updated_at)?;
+            }
+            StorageOperation::DeleteWhere { table, conditions } => {
+                storage.delete_rows_where(&table, &conditions)?;
+            }
+            StorageOperation::Truncate { table } => {
+                storage.truncate_table(&table)?;
+            }
+            StorageOperation::Drop { table } => {
+                storage.drop_table(&table)?;
+            }
+            StorageOperation::Rename { table, new_name } => {
+                storage.rename_table(&table, &new_name)?;
+            }
+            StorageOperation::Copy { table, source, with_data } => {
+                storage.copy_table(&source, &table, with_data)?;
+            }
+            StorageOperation::CreateAs { table, schema, rows } => {
+                storage.create_table_as(&table, schema, rows)?;
+            }
+            StorageOperation::CreateProcedure { procedure } => {
+                storage.create_procedure(procedure)?;
+            }
+            StorageOperation::DropProcedure { name } => {
+                storage.drop_procedure(&name)?;
+            }
+            StorageOperation::CreateSequence { sequence } => {
+                storage.create_sequence(sequence)?;
+            }
+            StorageOperation::DropSequence { name } => {
+                storage.drop_sequence(&name)?;
+            }
+            StorageOperation::SetSequence { name, value } => {
+                storage.set_sequence_value(&name, value)?;
+            }
+            StorageOperation::SetValidationMode { mode } => {
+                storage.set_validation_mode(mode);
+            }
+            StorageOperation::Prepare { txid, operations } => {
+                storage.add_prepared(txid, operations)?;
+            }
+            StorageOperation::CommitPrepared { txid } => {
+                let operations = storage.take_prepared(txid)
+                    .ok_or_else(|| DatabaseError::other(format!("事务 '{}' 未准备", txid)))?;
+                for operation in operations {
+                    self.apply_log_operation(storage, operation, timestamp)?;
+                }
+                storage.mark_committed(txid);
+            }
+            StorageOperation::AbortPrepared { txid } => {
+                storage.take_prepared(txid);
+            }
+        }
+        Ok(())
+    }
+
+    /// 执行事务
+    pub async fn transaction<F, T>(&self, operations: F) -> Result<T>
+    where
+        F: FnOnce(&mut Transaction) -> Result<T>,
+    {
+        let mut transaction = Transaction::new(self);
+        let result = operations(&mut transaction)?;
+
+        // 提交事务
+        transaction.commit().await?;
+
+        Ok(result)
+    }
+
+    /// 按选项中的隔离级别执行异步事务，各级别的行为见 `IsolationLevel`
+    pub async fn transaction_with<F, T>(&self, options: TransactionOptions, operations: F) -> Result<T>
+    where
+        F: AsyncFnOnce(&mut Transaction<'_>) -> Result<T>,
+    {
+        let mut transaction = Transaction::with_options(self, options).await?;
+        let result = operations(&mut transaction).await?;
+
+        transaction.commit().await?;
+
+        Ok(result)
+    }
+
+    /// 执行异步事务
+    ///
+    /// 闭包中可以 `await` 查询，通过 `tx.query` 读取本事务尚未提交的写入；
+    /// 闭包返回错误时事务中的操作全部丢弃。
+    pub async fn transaction_async<F, T>(&self, operations: F) -> Result<T>
+    where
+        F: AsyncFnOnce(&mut Transaction<'_>) -> Result<T>,
+    {
+        let mut transaction = Transaction::new(self);
+        let result = operations(&mut transaction).await?;
+
+        transaction.commit().await?;
+
+        Ok(result)
+    }
+
+    /// 设置已准备的事务最多持有磁盘存储锁的时间，None（默认）表示一直等待协调者的决定
+    ///
+    /// 超时后的事务在其他操作等待磁盘存储锁时由本库中止并释放锁，协调者之后的 `commit_prepared` 返回错误；
+    /// 超时应大于协调者从准备到提交的最长间隔。wasm32 上不记录准备时刻，设置超时没有效果。
+    pub fn set_prepared_timeout(&mut self, timeout: Option<Duration>) {
+        self.prepared_timeout = timeout;
+    }
+
+    /// 两阶段提交的准备阶段：确认 `operations` 能在当前数据上全部执行，写入准备记录后等待协调者的决定
+    ///
+    /// 准备成功后一直持有磁盘存储的锁，其他写入等到 `commit_prepared` 或 `abort_prepared` 之后才能进行，
+    /// 因此提交时操作一定能执行；协调者失联时可由管理员调用 `abort_prepared`，或用 `set_prepared_timeout` 设置超时。
+    /// 操作只能是建表、删表与行的增删改。
+    pub async fn prepare_transaction(&self, txid: uuid::Uuid, operations: Vec<StorageOperation>) -> Result<()> {
+        self.check_writable()?;
+        let mut log = self.begin_log().await;
+        {
+            let mut storage = self.storage.write().await;
+            validate_prepared(&storage, &operations)?;
+            storage.add_prepared(txid, operations.clone())?;
+        }
+        if log.enabled() {
+            log.push(StorageOperation::Prepare { txid, operations });
+        }
+        match log.commit_holding().await {
+            Ok(disk_storage) => {
+                let prepared_at = (!cfg!(target_arch = "wasm32")).then(tokio::time::Instant::now);
+                self.prepared_locks.lock().unwrap().insert(txid, PreparedLock { disk_storage, prepared_at });
+                Ok(())
+            }
+            Err(e) => {
+                self.storage.write().await.take_prepared(txid);
+                Err(e)
+            }
+        }
+    }
+
+    /// 提交已准备的事务，执行其中的操作
+    pub async fn commit_prepared(&self, txid: uuid::Uuid) -> Result<()> {
+        self.check_writable()?;
+        let mut log = self.prepared_log(txid).await;
+        self.apply_log_operation(&mut *self.storage.write().await, StorageOperation::CommitPrepared { txid }, clock::now())?;
+        if log.enabled() {
+            log.push(StorageOperation::CommitPrepared { txid });
+        }
+        log.commit().await?;
+
+        if self.auto_save {
+            self.save_to_disk().await?;
+        }
+        Ok(())
+    }
+
+    /// 中止已准备的事务，丢弃其中的操作
+    pub async fn abort_prepared(&self, txid: uuid::Uuid) -> Result<()> {
+        self.check_writable()?;
+        let mut log = self.prepared_log(txid).await;
+        if self.storage.write().await.take_prepared(txid).is_none() {
+            return Err(DatabaseError::other(format!("事务 '{}' 未准备", txid)));
+        }
+        if log.enabled() {
+            log.push(StorageOperation::AbortPrepared { txid });
+        }
+        log.commit().await
+    }
+
+    /// 事务锁的持有者与等待者
+    pub fn lock_info(&self) -> Vec<LockInfo> {
+        self.locks.info()
+    }
+
+    /// `SHOW LOCKS` 的结果，每把锁的持有者或等待者一行
+    fn locks_result(&self) -> QueryResult {
+        let rows = self.lock_info().into_iter()
+            .map(|info| {
+                let mut row = Row::new();
+                row.set("transaction".to_string(), Value::Integer(info.transaction as i64));
+                row.set("table".to_string(), Value::Text(info.target.table().to_string()));
+                row.set("row_id".to_string(), match &info.target {
+                    LockTarget::Row { id, .. } => Value::Text(id.to_string()),
+                    LockTarget::Table(_) => Value::Null,
+                });
+                row.set("mode".to_string(), Value::Text(info.mode.to_string()));
+                row.set("granted".to_string(), Value::Boolean(info.granted));
+                row
+            })
+            .collect();
+        let mut result = QueryResult::new(QueryType::Select, "locks".to_string(), 0).with_rows(rows);
+        result.columns = vec![
+            ("transaction".to_string(), DataType::Integer),
+            ("table".to_string(), DataType::Text),
+            ("row_id".to_string(), DataType::Text),
+            ("mode".to_string(), DataType::Text),
+            ("granted".to_string(), DataType::Boolean),
+        ];
+        result
+    }
+
+    /// 已准备、尚未提交或中止的事务；重启后由协调者根据其他参与者上的结果决定
+    pub async fn in_doubt_transactions(&self) -> Vec<uuid::Uuid> {
+        let mut txids: Vec<_> = self.storage.read().await.prepared_transactions().into_iter()
+            .map(|prepared| prepared.txid)
+            .collect();
+        txids.sort_unstable();
+        txids
+    }
+
+    /// 已在本库提交、尚未被遗忘的两阶段提交事务
+    pub async fn committed_transactions(&self) -> Vec<uuid::Uuid> {
+        self.storage.read().await.committed_transactions()
+    }
+
+    pub async fn is_committed(&self, txid: uuid::Uuid) -> bool {
+        self.storage.read().await.is_committed(txid)
+    }
+
+    /// 协调者确认所有参与者都已提交后丢弃事务的提交记录；不写日志，重放日志时记录会重新出现
+    pub async fn forget_transaction(&self, txid: uuid::Uuid) -> bool {
+        self.storage.write().await.forget_committed(txid)
+    }
+
+    /// 批量插入
+    ///
+    /// 所有行先整体校验，再在一次写锁内写入，并只记录一条 `BatchInsert` 日志。
+    /// 任意一行校验失败时整批都不会写入。
+    pub async fn batch_insert(&self, table_name: &str, rows: Vec<HashMap<String, Value>>) -> Result<Vec<uuid::Uuid>> {
+        self.check_writable()?;
+        let mut rows: Vec<Row> = rows.into_iter()
+            .map(|data| {
+                let mut row = Row::new();
+                row.data = data;
+                row
+            })
+            .collect();
+        let ids = rows.iter().map(|row| row.id).collect();
+
+        let mut log = self.begin_log().await;
+        let audit = self.audit_for(&log, table_name);
+        let mut advanced = BTreeMap::new();
+        let changes = {
+            let storage = self.storage.read().await;
+            let mut table = storage.get_table_mut(table_name)?
+                .ok_or_else(|| DatabaseError::TableNotFound(table_name.to_string()))?;
+            let mut resolve = sequence_resolver(&storage, &mut advanced);
+            for row in &mut rows {
+                self.check_strict(&storage, table_name, &table.schema, &row.data)?;
+                table.apply_defaults_with(row, &mut resolve)?;
+            }
+            self.check_memory_budget(&storage, &table, table.estimate_insert(&rows))?;
+            let changes: Vec<AuditChange> = match audit {
+                Some(_) => rows.iter().map(|row| AuditChange::insert(table_name, row.clone())).collect(),
+                None => Vec::new(),
+            };
+
+            if log.enabled() {
+                table.insert_batch(rows.clone())?;
+            } else {
+                table.insert_batch(std::mem::take(&mut rows))?;
+            }
+            changes
+        };
+
+        if log.enabled() {
+            log.push_sequences(advanced);
+            log.push(StorageOperation::BatchInsert {
+                table: table_name.to_string(),
+                rows,
+            });
+        }
+        self.record_audit(&mut log, audit, changes).await?;
+        log.commit().await?;
+
+        Ok(ids)
+    }
+
+    /// 批量装载行，保留行自带的 ID，返回装载的行数
+    ///
+    /// 用于恢复导出的数据或导入大量行：行按迭代器给出的数量预先分配，所有行在一次加锁内校验，
+    /// 唯一列与行 ID 的重复只按一次建立的键集合检查；日志在全部行写入内存后作为一条记录提交。
+    /// 任意一行校验失败时整批不生效。
+    pub async fn load_rows<I: IntoIterator<Item = Row>>(&self, table_name: &str, rows: I) -> Result<usize> {
+        // 在等待锁之前取完迭代器，迭代器不必是 Send
+        let rows: Vec<Row> = rows.into_iter().collect();
+        self.bulk_load(table_name, rows, |_, e| Err(e)).await
+    }
+
+    /// 批量装载的公共部分；第 `i` 行校验失败时调用 `reject(i, 错误)`，返回错误则整批不生效，否则跳过该行
+    async fn bulk_load<F>(&self, table_name: &str, rows: Vec<Row>, mut reject: F) -> Result<usize>
+    where
+        F: FnMut(usize, DatabaseError) -> Result<()> + Send,
+    {
+        self.check_writable()?;
+        let mut log = self.begin_log().await;
+        let audit = self.audit_for(&log, table_name);
+        let mut accepted = Vec::with_capacity(rows.len());
+        let mut advanced = BTreeMap::new();
+        let (count, changes) = {
+            let storage = self.storage.read().await;
+            let mut resolve = sequence_resolver(&storage, &mut advanced);
+            let mut table = storage.get_table_mut(table_name)?
+                .ok_or_else(|| DatabaseError::TableNotFound(table_name.to_string()))?;
+            let mut ids: HashSet<uuid::Uuid> = HashSet::with_capacity(table.row_count() + rows.len());
+            if table.row_count() > 0 {
+                ids.extend(table.all_rows()?.iter().map(|row| row.id));
+            }
+            let unique: Vec<(String, Collation)> = table.schema.columns.iter()
+                .filter(|column| column.unique)
+                .map(|column| (column.name.clone(), column.collation))
+                .collect();
+            let mut keys: Vec<HashSet<String>> = unique.iter().map(|(column, _)| table.unique_keys(column)).collect();
+
+            for (index, mut row) in rows.into_iter().enumerate() {
+                let checked = self.check_strict(&storage, table_name, &table.schema, &row.data)
+                    .and_then(|_| table.apply_defaults_with(&mut row, &mut resolve))
+                    .and_then(|_| table.schema.validate_row(&row).map_err(|e| e.in_table(table_name)))
+                    .and_then(|_| check_bulk_keys(table_name, &row, &ids, &unique, &keys));
+                match checked {
+                    Ok(()) => {
+                        ids.insert(row.id);
+                        for ((column, collation), seen) in unique.iter().zip(&mut keys) {
+                            if let Some(value) = row.get(column).filter(|value| !value.is_null()) {
+                                seen.insert(collation.key(value));
+                            }
+                        }
+                        accepted.push(row);
+                    }
+                    Err(e) => reject(index, e)?,
+                }
+            }
+            self.check_memory_budget(&storage, &table, table.estimate_insert(&accepted))?;
+            let changes: Vec<AuditChange> = match audit {
+                Some(_) => accepted.iter().map(|row| AuditChange::insert(table_name, row.clone())).collect(),
+                None => Vec::new(),
+            };
+
+            let count = accepted.len();
+            if log.enabled() {
+                table.insert_batch(accepted.clone())?;
+            } else {
+                table.insert_batch(std::mem::take(&mut accepted))?;
+            }
+            (count, changes)
+        };
+
+        if log.enabled() {
+            log.push_sequences(advanced);
+            log.push(StorageOperation::BatchInsert {
+                table: table_name.to_string(),
+                rows: accepted,
+            });
+        }
+        self.record_audit(&mut log, audit, changes).await?;
+        log.commit().await?;
+
+        Ok(count)
+    }
+
+    /// 把表导出为 NDJSON 文件，每行一个 JSON 对象，返回导出的行数
+    pub async fn export_json(&self, table_name: &str, path: &str) -> Result<usize> {
+        use std::io::Write;
+
+        let storage = self.storage.read().await;
+        let table = storage.get_table(table_name)?
+            .ok_or_else(|| DatabaseError::TableNotFound(table_name.to_string()))?;
+        let rows = table.all_rows()?;
+
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        for row in rows.iter() {
+            let object: serde_json::Map<String, serde_json::Value> = row.data.iter()
+                .map(|(column, value)| (column.clone(), value.to_json()))
+                .collect();
+            serde_json::to_writer(&mut writer, &object)?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+
+        Ok(rows.len())
+    }
+
+    /// 从 NDJSON 文件导入数据，返回导入的行数
+    ///
+    /// 每行按表结构转换类型并校验，任意一行出错时整个文件都不会写入。
+    pub async fn import_json(&self, table_name: &str, path: &str) -> Result<usize> {
+        self.import_json_with(table_name, path, &ImportOptions::new()).await.map(|report| report.loaded)
+    }
+
+    /// 按导入选项从 NDJSON 文件导入数据；开启 `skip_invalid` 时跳过不合格的行而不是整体失败
+    pub async fn import_json_with(&self, table_name: &str, path: &str, options: &ImportOptions) -> Result<ImportReport> {
+        let schema = self.table_schema(table_name).await?;
+        let (rows, report) = import::read_json_rows(std::io::BufReader::new(std::fs::File::open(path)?), &schema)?;
+        self.import_source_rows(table_name, rows, report, options).await
+    }
+
+    /// 按 COPY 选项从 CSV/TSV 文件导入数据
+    pub async fn import_csv(&self, table_name: &str, path: &str, copy: &CopyOptions, options: &ImportOptions) -> Result<ImportReport> {
+        let schema = self.table_schema(table_name).await?;
+        let (rows, report) = import::read_rows(std::io::BufReader::new(std::fs::File::open(path)?), &schema, copy)?;
+        self.import_source_rows(table_name, rows, report, options).await
+    }
+
+    /// 推断表结构并导入带表头的 CSV 文件（扩展名为 `.tsv` 时按 TSV 读取），返回新表名与导入结果
+    ///
+    /// 表名取文件名去掉扩展名，表已存在时返回错误。列类型按前 `INFER_SAMPLE_ROWS` 行推断，
+    /// 之后的行无法转换为推断的类型时被跳过并记入报告。
+    pub async fn import_csv_infer(&self, path: &str) -> Result<(String, ImportReport)> {
+        let file = Path::new(path);
+        let table_name = file.file_stem()
+            .and_then(|stem| stem.to_str())
+            .ok_or_else(|| DatabaseError::other(format!("无法从路径 '{}' 得到表名", path)))?
+            .to_string();
+        let format = match file.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("tsv") => import::CopyFormat::Tsv,
+            _ => import::CopyFormat::Csv,
+        };
+        let copy = CopyOptions::new(format).header(true);
+
+        let reader = std::io::BufReader::new(std::fs::File::open(path)?);
+        let schema = import::infer_schema(reader, &copy, import::INFER_SAMPLE_ROWS)?;
+        self.create_table(&table_name, schema).await?;
+        let report = self.import_csv(&table_name, path, &copy, &ImportOptions::new().skip_invalid(true)).await?;
+        Ok((table_name, report))
+    }
+
+    /// 装载读出的行，读取阶段与装载阶段被拒绝的行合并到报告中，按行号排列
+    ///
+    /// 未开启 `skip_invalid` 时遇到第一个不合格的行即返回带行号的错误，不写入任何行。
+    async fn import_source_rows(&self, table_name: &str, rows: Vec<SourceRow>, mut report: ImportReport, options: &ImportOptions) -> Result<ImportReport> {
+        if !options.skip_invalid {
+            if let Some(error) = report.first_error() {
+                return Err(error);
+            }
+        }
+
+        let (mut sources, rows): (Vec<(usize, String)>, Vec<Row>) = rows.into_iter()
+            .map(|source| ((source.line, source.record), source.row))
+            .unzip();
+        let mut rejected = Vec::new();
+        report.loaded = self.bulk_load(table_name, rows, |index, e| {
+            if options.skip_invalid {
+                rejected.push((index, e));
+                Ok(())
+            } else {
+                Err(DatabaseError::parse_error(format!("第 {} 行: {}", sources[index].0, e)))
+            }
+        }).await?;
+
+        for (index, e) in rejected {
+            let (line, record) = &mut sources[index];
+            report.reject(*line, std::mem::take(record), &e);
+        }
+        report.errors.sort_by_key(|rejected| rejected.line);
+        if let Some(path) = &options.reject_file {
+            report.write_rejects(path)?;
+        }
+        Ok(report)
+    }
+
+    /// 执行 SQL 脚本，返回 INSERT/SELECT/UPDATE/DELETE 语句的结果，DDL 语句不产生结果
+    ///
+    /// 语句依次执行，遇到错误时停止，之前的语句已经生效。
+    pub async fn execute_sql(&self, sql: &str) -> Result<Vec<QueryResult>> {
+        let mut results = Vec::new();
+        for statement in sql::parse_script(sql)? {
+            if let Some(result) = self.execute_statement(statement).await? {
+                results.push(result);
+            }
+        }
+        Ok(results)
+    }
+
+    /// 在会话中执行 SQL 脚本，语句依次通过 `execute_statement_in` 执行
+    pub async fn execute_sql_in(&self, session: &Session, sql: &str) -> Result<Vec<QueryResult>> {
+        let mut results = Vec::new();
+        for statement in sql::parse_script(sql)? {
+            if let Some(result) = self.execute_statement_in(session, statement).await? {
+                results.push(result);
+            }
+        }
+        Ok(results)
+    }
+
+    /// 在会话中执行一条语句
+    ///
+    /// 会话的当前数据库必须是引擎正在使用的数据库，审计记录以会话用户为操作者。
+    /// 超时只作用于只读语句：写语句一旦开始就执行完成，不会只修改了内存却没有写入日志。
+    pub async fn execute_statement_in(&self, session: &Session, statement: Statement) -> Result<Option<QueryResult>> {
+        if session.database() != self.current_database {
+            return Err(DatabaseError::other(format!(
+                "会话的当前数据库 '{}' 不是引擎正在使用的数据库 '{}'", session.database(), self.current_database
+            )));
+        }
+        let read_only = matches!(&statement, Statement::Query(query) if matches!(query.query_type, QueryType::Select) || query.dry_run);
+        let execution = SESSION_USER.scope(session.user.clone(), self.execute_statement(statement));
+        match session.timeout.filter(|_| read_only) {
+            Some(timeout) => tokio::time::timeout(timeout, execution).await
+                .map_err(|_| DatabaseError::other(format!("语句执行超过 {} ms，已取消", timeout.as_millis())))?,
+            None => execution.await,
+        }
+    }
+
+    /// 执行一条已解析的 SQL 语句
+    pub async fn execute_statement(&self, statement: Statement) -> Result<Option<QueryResult>> {
+        match statement {
+            Statement::CreateTable { name, schema, options } => {
+                self.create_table_with_options(&name, schema, options).await?;
+                Ok(None)
+            }
+            Statement::Truncate { name } => {
+                let count = self.truncate_table(&name).await?;
+                Ok(Some(QueryResult::new(QueryType::Delete, name, 0).with_affected_rows(count)))
+            }
+            Statement::DropTable { name } => {
+                self.drop_table(&name).await?;
+                Ok(None)
+            }
+            Statement::CreateDatabase { name } => {
+                self.create_database(&name)?;
+                Ok(None)
+            }
+            Statement::DropDatabase { name } => {
+                self.drop_database(&name)?;
+                Ok(None)
+            }
+            Statement::CreateSequence { name, start, step } => {
+                self.create_sequence(&name, start, step).await?;
+                Ok(None)
+            }
+            Statement::DropSequence { name } => {
+                self.drop_sequence(&name).await?;
+                Ok(None)
+            }
+            Statement::ShowLocks => Ok(Some(self.locks_result())),
+            Statement::Analyze { table } => {
+                match table {
+                    Some(table) => self.analyze(&table).await.map(|_| ())?,
+                    None => self.analyze_all().await?,
+                }
+                Ok(None)
+            }
+            Statement::Insert { table, columns, rows } => {
+                let schema = self.get_table_info(&table).await?.schema;
+                let columns = if columns.is_empty() {
+                    schema.columns.iter().map(|column| column.name.clone()).collect()
+                } else {
+                    columns
+                };
+
+                let rows = self.bind_insert_sequences(rows).await?;
+                let mut data_rows = Vec::with_capacity(rows.len());
+                for values in rows {
+                    if values.len() != columns.len() {
+                        return Err(DatabaseError::parse_error(format!(
+                            "INSERT 有 {} 列，但提供了 {} 个值", columns.len(), values.len()
+                        )));
+                    }
+                    let mut data = HashMap::new();
+                    for (column, value) in columns.iter().zip(values) {
+                        let data_type = schema.get_column(column).map(|c| &c.data_type);
+                        let value = value.evaluate(&Row::new())?;
+                        data.insert(column.clone(), sql::coerce_literal(value, data_type)?);
+                    }
+                    data_rows.push(data);
+                }
+
+                self.execute(Query::insert_many(table, data_rows)).await.map(Some)
+            }
+            Statement::InsertSelect { table, columns, mut query } => {
+                self.prepare_statement_query(&mut query).await?;
+                let schema = self.table_schema(&table).await?;
+                let columns = if columns.is_empty() {
+                    schema.columns.iter().map(|column| column.name.clone()).collect()
+                } else {
+                    columns
+                };
+                let result = self.select_for_insert(query).await?;
+                if result.columns.len() != columns.len() {
+                    return Err(DatabaseError::parse_error(format!(
+                        "INSERT 有 {} 列，但查询结果有 {} 列", columns.len(), result.columns.len()
+                    )));
+                }
+                let mapping: Vec<(String, String)> = columns.into_iter()
+                    .zip(result.columns.iter().map(|(column, _)| column.clone()))
+                    .collect();
+                let ids = self.insert_mapped(&table, &schema, result, &mapping).await?;
+                Ok(Some(QueryResult::new(QueryType::Insert, table, 0).with_affected_ids(ids)))
+            }
+            Statement::CreateTableAs { name, mut query } => {
+                self.prepare_statement_query(&mut query).await?;
+                let inserted = self.create_table_as(&name, query).await?;
+                Ok(Some(QueryResult::new(QueryType::Insert, name, 0).with_affected_rows(inserted)))
+            }
+            Statement::Query(mut query) => {
+                self.prepare_statement_query(&mut query).await?;
+                self.execute(query).await.map(Some)
+            }
+            Statement::Copy { .. } => Err(DatabaseError::other("COPY FROM STDIN 的数据需要由客户端提供，请在 shell 中执行或调用 copy_from")),
+        }
+    }
+
+    /// 代入 SQL 查询中的序列值，并把条件与写入的字面量按列类型转换
+    async fn prepare_statement_query(&self, query: &mut Query) -> Result<()> {
+        if query.uses_sequences() {
+            let storage = self.storage.read().await;
+            query.bind_sequences(&mut |function, name| {
+                if function.eq_ignore_ascii_case("CURRVAL") {
+                    storage.current_sequence_value(name)
+                } else {
+                    Err(DatabaseError::other(format!("查询中不能调用 {}，序列只在 INSERT 中前进", function)))
+                }
+            })?;
+        }
+        // 字面量按列类型转换，例如字符串转日期
+        let schema = self.table_schema(&query.table_name).await?;
+        for condition in &mut query.conditions {
+            if let Some(column) = schema.get_column(&condition.column) {
+                let compares_column = condition.path.is_none() && condition.is_single_column()
+                    && !matches!(condition.operator, ComparisonOperator::In | ComparisonOperator::Like | ComparisonOperator::WithinRadius);
+                if compares_column {
+                    // 数组运算符的单个值按元素类型转换
+                    let data_type = match column.data_type.element_type() {
+                        Some(element) if condition.operator.is_array_operator()
+                            && !matches!(condition.value, Value::Array(_)) => element,
+                        _ => &column.data_type,
+                    };
+                    let value = std::mem::replace(&mut condition.value, Value::Null);
+                    condition.value = sql::coerce_literal(value, Some(data_type))?;
+                }
+            }
+        }
+        if let Some(data) = &mut query.data {
+            for (column, value) in data.iter_mut() {
+                let data_type = schema.get_column(column).map(|c| &c.data_type);
+                *value = sql::coerce_literal(std::mem::replace(value, Value::Null), data_type)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 代入 INSERT 各行中的 `NEXTVAL`、`CURRVAL`，按行的顺序取值，前进过的序列单独记录一条日志
+    async fn bind_insert_sequences(&self, rows: Vec<Vec<Expr>>) -> Result<Vec<Vec<Expr>>> {
+        if !rows.iter().flatten().any(Expr::uses_sequences) {
+            return Ok(rows);
+        }
+        self.check_writable()?;
+        let mut log = self.begin_log().await;
+        let mut advanced = BTreeMap::new();
+        let rows = {
+            let storage = self.storage.read().await;
+            let mut resolve = sequence_resolver(&storage, &mut advanced);
+            rows.iter()
+                .map(|values| values.iter().map(|value| value.bind_sequences(&mut resolve)).collect())
+                .collect::<Result<Vec<Vec<Expr>>>>()?
+        };
+        if log.enabled() {
+            log.push_sequences(advanced);
+        }
+        log.commit().await?;
+        Ok(rows)
+    }
+
+    /// 按 COPY 选项从 `reader` 读取 CSV/TSV 数据，经批量装载写入表
+    ///
+    /// 无法解析或违反约束的行被跳过并记入报告（行号从数据第一行算起），其余行作为一批装载。
+    pub async fn copy_from<R: std::io::BufRead>(&self, table_name: &str, reader: R, options: &CopyOptions) -> Result<ImportReport> {
+        let schema = self.table_schema(table_name).await?;
+        let (rows, report) = import::read_rows(reader, &schema, options)?;
+        self.import_source_rows(table_name, rows, report, &ImportOptions::new().skip_invalid(true)).await
+    }
+
+    /// 把整个数据库导出为 SQL 文本（CREATE TABLE 与 INSERT 语句），返回导出的行数
+    ///
+    /// 输出可以用 `execute_sql` 或 `simple-db execute` 恢复；存储过程不包含在内，
+    /// 行ID与时间戳会在恢复时重新生成，序列从下一个值开始重新创建。
+    pub async fn dump(&self, path: &str) -> Result<usize> {
+        let storage = self.storage.read().await;
+        let mut names = storage.list_tables();
+        names.sort();
+
+        let mut out = String::new();
+        let sequences = storage.get_sequences();
+        for sequence in &sequences {
+            let start = sequence.current.map_or(sequence.start, |current| current.saturating_add(sequence.step));
+            out.push_str(&format!(
+                "CREATE SEQUENCE {} START {} INCREMENT {};\n", sql::quote_identifier(&sequence.name), start, sequence.step
+            ));
+        }
+        if !sequences.is_empty() {
+            out.push('\n');
+        }
+        let mut total = 0;
+        for name in names {
+            let Some(table) = storage.get_table(&name)? else {
+                continue;
+            };
+            out.push_str(&create_table_sql(&table));
+
+            let rows = table.all_rows()?;
+            let mut columns: Vec<String> = table.schema.columns.iter().map(|c| c.name.clone()).collect();
+            let mut extra: Vec<&String> = rows.iter()
+                .flat_map(|row| row.data.keys())
+                .filter(|key| table.schema.get_column(key).is_none())
+                .collect();
+            extra.sort();
+            extra.dedup();
+            columns.extend(extra.into_iter().cloned());
+
+            let column_list = columns.iter().map(|c| sql::quote_identifier(c)).collect::<Vec<_>>().join(", ");
+            for chunk in rows.chunks(DUMP_ROWS_PER_INSERT) {
+                let values: Vec<String> = chunk.iter()
+                    .map(|row| {
+                        let literals: Vec<String> = columns.iter()
+                            .map(|column| row.get(column).map(sql::literal).unwrap_or_else(|| "NULL".to_string()))
+                            .collect();
+                        format!("({})", literals.join(", "))
+                    })
+                    .collect();
+                out.push_str(&format!(
+                    "INSERT INTO {} ({}) VALUES\n  {};\n",
+                    sql::quote_identifier(&name), column_list, values.join(",\n  ")
+                ));
+            }
+            out.push('\n');
+            total += rows.len();
+        }
+
+        std::fs::write(path, out)?;
+        Ok(total)
+    }
+
+    /// 已应用的迁移版本（升序），尚未执行过迁移时为空
+    pub async fn applied_migrations(&self) -> Result<Vec<i64>> {
+        if self.get_table_info(MIGRATIONS_TABLE).await.is_err() {
+            return Ok(Vec::new());
+        }
+        let query = QueryBuilder::select(MIGRATIONS_TABLE).build();
+        let mut versions: Vec<i64> = self.query(query).await?.rows.iter()
+            .filter_map(|row| row.get_integer("version"))
+            .collect();
+        versions.sort_unstable();
+        Ok(versions)
+    }
+
+    /// 按版本号依次执行尚未应用的迁移，直到 `target`（为 None 时执行全部），返回本次应用的版本
+    ///
+    /// 每个迁移成功后立即记入 `schema_migrations`；迁移失败时停止，之前的迁移保持已应用。
+    pub async fn migrate_up(&self, migrator: &Migrator, target: Option<i64>) -> Result<Vec<i64>> {
+        self.check_writable()?;
+        if self.get_table_info(MIGRATIONS_TABLE).await.is_err() {
+            self.create_table(MIGRATIONS_TABLE, Schema::new(vec![
+                ColumnDefinition::new("version", DataType::Integer, true),
+                ColumnDefinition::new("name", DataType::Text, false),
+                ColumnDefinition::new("applied_at", DataType::DateTime, false),
+            ])).await?;
+        }
+
+        let applied = self.applied_migrations().await?;
+        let mut done = Vec::new();
+        for migration in migrator.migrations() {
+            if target.is_some_and(|target| migration.version > target) {
+                break;
+            }
+            if applied.contains(&migration.version) {
+                continue;
+            }
+
+            migration.up.run(self).await.map_err(|e| {
+                DatabaseError::other(format!("迁移 {} ({}) 失败: {}", migration.version, migration.name, e))
+            })?;
+
+            let mut data = HashMap::new();
+            data.insert("version".to_string(), Value::Integer(migration.version));
+            data.insert("name".to_string(), Value::Text(migration.name.clone()));
+            data.insert("applied_at".to_string(), Value::DateTime(clock::now().naive_utc()));
+            self.insert(MIGRATIONS_TABLE, data).await?;
+            done.push(migration.version);
+        }
+        Ok(done)
+    }
+
+    /// 从最新版本开始逐个回滚已应用的迁移，直到只剩版本不大于 `target` 的迁移，返回本次回滚的版本
+    pub async fn migrate_down(&self, migrator: &Migrator, target: i64) -> Result<Vec<i64>> {
+        self.check_writable()?;
+        let mut done = Vec::new();
+        for version in self.applied_migrations().await?.into_iter().rev() {
+            if version <= target {
+                break;
+            }
+
+            let migration = migrator.get(version)
+                .ok_or_else(|| DatabaseError::other(format!("找不到已应用的迁移 {}", version)))?;
+            let down = migration.down.as_ref()
+                .ok_or_else(|| DatabaseError::other(format!("迁移 {} ({}) 不能回滚", version, migration.name)))?;
+            down.run(self).await.map_err(|e| {
+                DatabaseError::other(format!("回滚迁移 {} ({}) 失败: {}", version, migration.name, e))
+            })?;
+
+            self.delete(MIGRATIONS_TABLE, vec![
+                ("version".to_string(), ComparisonOperator::Equal, Value::Integer(version)),
+            ]).await?;
+            done.push(version);
+        }
+        Ok(done)
+    }
+
+    /// 清空表，返回删除的行数
+    ///
+    /// 不逐行删除：直接换成空的行存储并丢弃索引，耗时与行数无关，只记录一条 `Truncate` 日志；
+    /// 表结构与建表选项保留。
+    pub async fn truncate_table(&self, table_name: &str) -> Result<usize> {
+        self.check_writable()?;
+        let mut log = self.begin_log().await;
+        let count = self.storage.read().await.truncate_table(table_name)?;
+
+        if log.enabled() {
+            log.push(StorageOperation::Truncate { table: table_name.to_string() });
+        }
+        log.commit().await?;
+        Ok(count)
+    }
+}
+
+impl Default for DatabaseEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 表信息
+#[derive(Debug, Clone)]
+pub struct TableInfo {
+    pub name: String,
+    pub row_count: usize,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub schema: Schema,
+}
+
+/// 索引类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexKind {
+    PrimaryKey,
+    Unique,
+    /// 数组列的元素索引，供 `CONTAINS` 等条件使用
+    Element,
+}
+
+impl std::fmt::Display for IndexKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            IndexKind::PrimaryKey => "PRIMARY KEY",
+            IndexKind::Unique => "UNIQUE",
+            IndexKind::Element => "ELEMENT",
+        })
+    }
+}
+
+/// 索引信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexInfo {
+    pub table_name: String,
+    pub column: String,
+    pub kind: IndexKind,
+}
+
+/// 数据库统计信息
+#[derive(Debug)]
+pub struct DatabaseStats {
     pub total_tables: usize,
     pub total_rows: usize,
     pub storage_stats: crate::storage::StorageStats,
+    pub memory: MemoryUsage,
+}
+
+/// 内存占用报告
+#[derive(Debug, Clone)]
+pub struct MemoryUsage {
+    pub tables: Vec<TableMemoryUsage>,
+    pub limit: Option<usize>,
+}
+
+impl MemoryUsage {
+    pub fn total_bytes(&self) -> usize {
+        self.tables.iter().map(TableMemoryUsage::total_bytes).sum()
+    }
+}
+
+/// 事务隔离级别
+///
+/// 事务的写操作都缓冲到提交时才执行，各级别的区别在于事务中的查询读到什么，以及提交时做哪些检查：
+///
+/// - `ReadCommitted`：查询读取最新提交的数据，同一事务中两次查询可能读到不同的结果（不可重复读）；
+///   提交时不做检查，与其他事务并发修改同一行时后提交者覆盖先提交者（丢失更新）
+/// - `SnapshotIsolation`：查询读取事务开始时的快照，可重复读；提交时若本事务更新或删除的行已被其他事务修改，
+///   返回 `DatabaseError::Conflict`（先提交者胜）。读取不同的行、各自修改不重叠的行仍可能违反跨行约束（写偏斜）
+/// - `Serializable`：在快照隔离的基础上，提交时若本事务查询过的表已被其他事务修改，返回
+///   `DatabaseError::SerializationFailure`，从而排除写偏斜。检查以表为粒度，可能误报，调用方应重试
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IsolationLevel {
+    #[default]
+    ReadCommitted,
+    SnapshotIsolation,
+    Serializable,
+}
+
+/// 事务选项，见 `DatabaseEngine::transaction_with`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TransactionOptions {
+    pub isolation: IsolationLevel,
+}
+
+impl TransactionOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn isolation(mut self, isolation: IsolationLevel) -> Self {
+        self.isolation = isolation;
+        self
+    }
+}
+
+/// 事务对象
+pub struct Transaction<'a> {
+    engine: &'a DatabaseEngine,
+    operations: Vec<StorageOperation>,
+    savepoints: Vec<(String, usize)>,
+    isolation: IsolationLevel,
+    /// 快照隔离与可串行化级别下事务开始时各表的克隆及版本
+    snapshot: Option<HashMap<String, (Table, u64)>>,
+    /// 查询过的表，可串行化级别在提交时检查
+    read_tables: Mutex<HashSet<String>>,
+    /// 锁管理器中的事务编号
+    id: u64,
+}
+
+impl<'a> Transaction<'a> {
+    fn new(engine: &'a DatabaseEngine) -> Self {
+        Self {
+            engine,
+            operations: Vec::new(),
+            savepoints: Vec::new(),
+            isolation: IsolationLevel::ReadCommitted,
+            snapshot: None,
+            read_tables: Mutex::new(HashSet::new()),
+            id: engine.next_transaction_id.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    /// 事务编号，`lock_info` 与 `SHOW LOCKS` 中以它标识事务
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// 对表加锁，持有到事务提交或放弃；与其他事务的等待形成环时返回 `DatabaseError::Deadlock`，
+    /// 此时应放弃事务以释放已持有的锁
+    pub async fn lock_table(&self, table: &str, mode: LockMode) -> Result<()> {
+        self.engine.locks.acquire(self.id, LockTarget::Table(table.to_string()), mode).await
+    }
+
+    /// 对行加锁，规则同 `lock_table`
+    pub async fn lock_row(&self, table: &str, id: uuid::Uuid, mode: LockMode) -> Result<()> {
+        self.engine.locks.acquire(self.id, LockTarget::Row { table: table.to_string(), id }, mode).await
+    }
+
+    async fn with_options(engine: &'a DatabaseEngine, options: TransactionOptions) -> Result<Self> {
+        let mut transaction = Self::new(engine);
+        transaction.isolation = options.isolation;
+        if options.isolation != IsolationLevel::ReadCommitted {
+            transaction.snapshot = Some(engine.storage.read().await.snapshot_tables()?);
+        }
+        Ok(transaction)
+    }
+
+    pub fn isolation(&self) -> IsolationLevel {
+        self.isolation
+    }
+
+    /// 创建保存点，同名保存点会遮蔽之前的保存点
+    pub fn savepoint(&mut self, name: &str) -> Result<()> {
+        self.savepoints.push((name.to_string(), self.operations.len()));
+        Ok(())
+    }
+
+    /// 回滚到保存点：撤销其后的所有操作，保存点本身保留，之后创建的保存点被移除
+    pub fn rollback_to(&mut self, name: &str) -> Result<()> {
+        let index = self.find_savepoint(name)?;
+        let (_, position) = self.savepoints[index];
+        self.operations.truncate(position);
+        self.savepoints.truncate(index + 1);
+        Ok(())
+    }
+
+    /// 释放保存点（及其后创建的保存点），已执行的操作保留
+    pub fn release_savepoint(&mut self, name: &str) -> Result<()> {
+        let index = self.find_savepoint(name)?;
+        self.savepoints.truncate(index);
+        Ok(())
+    }
+
+    fn find_savepoint(&self, name: &str) -> Result<usize> {
+        self.savepoints.iter()
+            .rposition(|(sp_name, _)| sp_name == name)
+            .ok_or_else(|| DatabaseError::other(format!("保存点 '{}' 不存在", name)))
+    }
+
+    /// 在事务中查询，结果包含本事务尚未提交的写入
+    pub async fn query(&self, query: Query) -> Result<QueryResult> {
+        let mut table = match &self.snapshot {
+            Some(snapshot) => snapshot.get(&query.table_name).map(|(table, _)| table.clone()),
+            None => {
+                let storage = self.engine.storage.read().await;
+                let table = storage.get_table(&query.table_name)?.map(|table| table.clone());
+                table
+            }
+        };
+        if self.isolation == IsolationLevel::Serializable {
+            self.read_tables.lock().unwrap().insert(query.table_name.clone());
+        }
+
+        for operation in &self.operations {
+            apply_pending_operation(&mut table, &query.table_name, operation)?;
+        }
+
+        let table = table.ok_or_else(|| DatabaseError::TableNotFound(query.table_name.clone()))?;
+        QueryEngine::new().execute(table, query).await
+    }
+
+    /// 在事务中创建表
+    pub fn create_table(&mut self, name: &str, schema: Schema) -> Result<()> {
+        self.operations.push(StorageOperation::Create {
+            table: name.to_string(),
+            schema,
+            layout: TableLayout::Row,
+            partition: None,
+        });
+        Ok(())
+    }
+
+    /// 在事务中插入数据
+    pub fn insert(&mut self, table_name: &str, data: HashMap<String, Value>) -> Result<uuid::Uuid> {
+        let mut row = Row::new();
+        for (column, value) in data {
+            row.set(column, value);
+        }
+
+        self.operations.push(StorageOperation::Insert {
+            table: table_name.to_string(),
+            row: row.clone(),
+        });
+
+        Ok(row.id)
+    }
+
+    /// 在事务中更新数据
+    pub fn update(&mut self, table_name: &str, id: uuid::Uuid, updates: HashMap<String, Value>) -> Result<()> {
+        self.operations.push(StorageOperation::Update {
+            table: table_name.to_string(),
+            id,
+            data: updates,
+            updated_at: Some(clock::now()),
+        });
+        Ok(())
+    }
+
+    /// 在事务中删除数据
+    pub fn delete(&mut self, table_name: &str, id: uuid::Uuid) -> Result<()> {
+        self.operations.push(StorageOperation::Delete {
+            table: table_name.to_string(),
+            id,
+        });
+        Ok(())
+    }
+
+    /// 按隔离级别检查事务开始后其他事务的写入，在提交持有写锁时调用
+    fn validate(&self, storage: &MemoryStorage) -> Result<()> {
+        let Some(snapshot) = &self.snapshot else { return Ok(()) };
+        for operation in &self.operations {
+            let (table, id) = match operation {
+                StorageOperation::Update { table, id, .. } | StorageOperation::Delete { table, id } => (table, *id),
+                _ => continue,
+            };
+            let Some(expected) = snapshot.get(table)
+                .map(|(table, _)| table.find_by_id(id))
+                .transpose()?
+                .flatten()
+                .map(|row| row.version) else { continue };
+            let actual = match storage.get_table(table)? {
+                Some(current) => current.find_by_id(id)?.map(|row| row.version),
+                None => None,
+            };
+            if actual != Some(expected) {
+                return Err(DatabaseError::conflict(id.to_string(), expected, actual.unwrap_or(0)));
+            }
+        }
+
+        if self.isolation == IsolationLevel::Serializable {
+            for table in self.read_tables.lock().unwrap().iter() {
+                if storage.table_version(table) != snapshot.get(table).map(|(_, version)| *version) {
+                    return Err(DatabaseError::serialization_failure(format!("事务读取的表 '{}' 已被其他事务修改", table)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 提交事务
+    pub async fn commit(mut self) -> Result<()> {
+        self.engine.check_writable()?;
+        let mut log = self.engine.begin_log().await;
+        let audit = self.engine.audit.lock().unwrap().clone();
+        let mut storage = self.engine.storage.write().await;
+        self.validate(&storage)?;
+
+        // 执行所有操作
+        let mut changes = Vec::new();
+        let mut advanced = BTreeMap::new();
+        for mut operation in std::mem::take(&mut self.operations) {
+            // 默认值在提交时求出并记入日志，调用序列的默认值在此取值
+            if let StorageOperation::Insert { table, row } = &mut operation {
+                if let Some(table) = storage.get_table(table)? {
+                    table.apply_defaults_with(row, &mut sequence_resolver(&storage, &mut advanced))?;
+                }
+            }
+            let audited = operation.table_name()
+                .filter(|_| audit.is_some())
+                .filter(|table| *table != AUDIT_TABLE && !log.disk_storage.is_temp_table(table))
+                .map(str::to_string);
+            let find = |storage: &MemoryStorage, table: &str, id: uuid::Uuid| -> Result<Option<Row>> {
+                Ok(match storage.get_table(table)? {
+                    Some(table) => table.find_by_id(id)?.map(std::borrow::Cow::into_owned),
+                    None => None,
+                })
+            };
+            let before = match (&audited, &operation) {
+                (Some(table), StorageOperation::Update { id, .. } | StorageOperation::Delete { id, .. }) => find(&storage, table, *id)?,
+                _ => None,
+            };
+            let after_id = match &operation {
+                StorageOperation::Insert { row, .. } => Some(row.id),
+                StorageOperation::Update { id, .. } => Some(*id),
+                _ => None,
+            };
+
+            self.engine.apply_log_operation(&mut storage, operation.clone(), clock::now())?;
+
+            if let Some(table) = &audited {
+                let after = match after_id {
+                    Some(id) => find(&storage, table, id)?,
+                    None => None,
+                };
+                match (before, after) {
+                    (None, Some(after)) => changes.push(AuditChange::insert(table, after)),
+                    (Some(before), Some(after)) => changes.push(AuditChange::update(table, before, after)),
+                    (Some(before), None) => changes.push(AuditChange::delete(table, before)),
+                    (None, None) => {}
+                }
+            }
+
+            // 记录到磁盘
+            if log.enabled() {
+                log.push(operation);
+            }
+        }
+
+        if log.enabled() {
+            log.push_sequences(advanced);
+        }
+
+        // 释放写锁并写完日志后再创建快照，save_to_disk 需要获取两把锁
+        drop(storage);
+        self.engine.record_audit(&mut log, audit, changes).await?;
+        log.commit().await?;
+
+        // 如果启用了自动保存，创建快照
+        if self.engine.auto_save {
+            self.engine.save_to_disk().await?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for Transaction<'_> {
+    /// 提交完成或放弃事务时释放它持有的锁
+    fn drop(&mut self) {
+        self.engine.locks.release_all(self.id);
+    }
+}
+
+/// 一次写操作产生的日志，见 `DatabaseEngine::begin_log`
+struct LogBatch {
+    disk_storage: OwnedMutexGuard<StorageEngine>,
+    /// 开启自动保存或有从库订阅时才需要生成日志
+    enabled: bool,
+    /// 是否写入日志文件
+    persist: bool,
+    operations: Vec<StorageOperation>,
+}
+
+impl LogBatch {
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn push(&mut self, operation: StorageOperation) {
+        self.operations.push(operation);
+    }
+
+    /// 记录前进过的序列的当前值
+    fn push_sequences(&mut self, advanced: BTreeMap<String, i64>) {
+        self.operations.extend(advanced.into_iter().map(|(name, value)| StorageOperation::SetSequence { name, value }));
+    }
+
+    /// 写入日志并推送给订阅的从库；需要写文件时在阻塞线程池中进行，之后释放磁盘存储的锁
+    async fn commit(self) -> Result<()> {
+        self.commit_holding().await.map(drop)
+    }
+
+    /// 与 `commit` 相同，但继续持有磁盘存储的锁
+    async fn commit_holding(self) -> Result<OwnedMutexGuard<StorageEngine>> {
+        let LogBatch { mut disk_storage, persist, operations, .. } = self;
+        let record = move |disk_storage: &mut StorageEngine| {
+            operations.into_iter().try_for_each(|operation| disk_storage.record(operation, persist))
+        };
+        if persist {
+            clock::spawn_blocking(move || record(&mut disk_storage).map(|_| disk_storage)).await?
+        } else {
+            record(&mut disk_storage)?;
+            Ok(disk_storage)
+        }
+    }
+}
+
+/// 插入时代入序列值：`NEXTVAL` 前进序列并把新值记入 `advanced`，`CURRVAL` 取当前值
+fn sequence_resolver<'a>(storage: &'a MemoryStorage, advanced: &'a mut BTreeMap<String, i64>) -> impl FnMut(&str, &str) -> Result<i64> + 'a {
+    move |function, name| {
+        if function.eq_ignore_ascii_case("NEXTVAL") {
+            let value = storage.next_sequence_value(name)?;
+            advanced.insert(name.to_string(), value);
+            Ok(value)
+        } else {
+            storage.current_sequence_value(name)
+        }
+    }
+}
+
+/// 持有磁盘存储的锁在阻塞线程池中执行 `f`
+async fn run_blocking<F, T>(mut disk_storage: OwnedMutexGuard<StorageEngine>, f: F) -> Result<T>
+where
+    F: FnOnce(&mut StorageEngine) -> Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    clock::spawn_blocking(move || f(&mut disk_storage)).await?
+}
+
+/// 批量装载时检查行 ID 与唯一列是否与已有的行或同批中先前的行重复
+fn check_bulk_keys(table: &str, row: &Row, ids: &HashSet<uuid::Uuid>, unique: &[(String, Collation)], keys: &[HashSet<String>]) -> Result<()> {
+    if ids.contains(&row.id) {
+        return Err(DatabaseError::unique_violation(table, ID_COLUMN, row.id));
+    }
+    for ((column, collation), seen) in unique.iter().zip(keys) {
+        if let Some(value) = row.get(column).filter(|value| !value.is_null()) {
+            if seen.contains(&collation.key(value)) {
+                return Err(DatabaseError::unique_violation(table, column, value));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 把 `(列, 运算符, 值)` 三元组转换为查询条件
+fn to_conditions(conditions: Vec<(String, ComparisonOperator, Value)>) -> Vec<Condition> {
+    conditions.into_iter()
+        .map(|(column, operator, value)| Condition::new(column, operator, value))
+        .collect()
+}
+
+/// 为条件填入列的排序规则并校验 JSON 路径与函数调用，与 `QueryEngine::execute` 的处理一致
+fn resolve_filter(conditions: Vec<Condition>, order_by: Vec<OrderBy>, schema: &Schema) -> Result<(Vec<Condition>, Vec<OrderBy>)> {
+    let mut query = Query::select("");
+    query.conditions = conditions;
+    query.order_by = order_by;
+    query.resolve_collations(schema);
+    query.validate_columns(schema)?;
+    query.validate_json_paths()?;
+    query.validate_expressions()?;
+    Ok((query.conditions, query.order_by))
+}
+
+/// 有 `limit` 时先选出要修改的行：满足条件的行按 `order_by` 稳定排序后取前 `limit` 行
+fn limited_targets(table: &Table, conditions: &[Condition], order_by: &[OrderBy], limit: Option<usize>) -> Result<Option<HashSet<uuid::Uuid>>> {
+    let Some(limit) = limit else { return Ok(None) };
+    let rows = table.all_rows()?;
+    let mut matching: Vec<&Row> = rows.iter().filter(|row| matches_all(conditions, row)).collect();
+    matching.sort_by(|a, b| query::compare_rows(a, b, order_by));
+    Ok(Some(matching.into_iter().take(limit).map(|row| row.id).collect()))
+}
+
+/// 在表的副本上依次执行两阶段提交事务的操作，确认它们都能成功
+fn validate_prepared(storage: &MemoryStorage, operations: &[StorageOperation]) -> Result<()> {
+    let mut tables: HashMap<String, Option<Table>> = HashMap::new();
+    for operation in operations {
+        let name = match operation {
+            StorageOperation::Rename { .. } | StorageOperation::Copy { .. } | StorageOperation::CreateAs { .. } => None,
+            _ => operation.table_name(),
+        }.ok_or_else(|| DatabaseError::other("两阶段提交的事务只能包含建表、删表与行的增删改"))?;
+        if !tables.contains_key(name) {
+            tables.insert(name.to_string(), storage.get_table(name)?.map(|table| table.clone()));
+        }
+        let table = tables.get_mut(name).unwrap();
+        match (operation, table.is_some()) {
+            (StorageOperation::Create { .. }, true) => return Err(DatabaseError::TableExists(name.to_string())),
+            (StorageOperation::Create { .. }, false) => {}
+            (_, false) => return Err(DatabaseError::TableNotFound(name.to_string())),
+            _ => {}
+        }
+        apply_pending_operation(table, name, operation)?;
+    }
+    Ok(())
+}
+
+/// 将事务中尚未提交的操作叠加到表的副本上
+fn apply_pending_operation(table: &mut Option<Table>, table_name: &str, operation: &StorageOperation) -> Result<()> {
+    match operation {
+        StorageOperation::Create { table: name, schema, layout, partition } if name == table_name => {
+            let options = TableOptions { layout: *layout, partition: partition.clone(), ..TableOptions::default() };
+            *table = Some(Table::with_options(name.clone(), schema.clone(), options)?);
+        }
+        StorageOperation::Drop { table: name } if name == table_name => {
+            *table = None;
+        }
+        StorageOperation::Insert { table: name, row } if name == table_name => {
+            if let Some(table) = table {
+                table.insert(row.clone())?;
+            }
+        }
+        StorageOperation::BatchInsert { table: name, rows } if name == table_name => {
+            if let Some(table) = table {
+                table.insert_batch(rows.clone())?;
+            }
+        }
+        StorageOperation::Update { table: name, id, data, updated_at } if name == table_name => {
+            if let Some(table) = table {
+                table.update_at(*id, data.clone(), updated_at.unwrap_or_else(chrono::Utc::now))?;
+            }
+        }
+        StorageOperation::Delete { table: name, id } if name == table_name => {
+            if let Some(table) = table {
+                table.delete(*id)?;
+            }
+        }
+        StorageOperation::UpdateWhere { table: name, conditions, data, updated_at } if name == table_name => {
+            if let Some(table) = table {
+                table.update_where(|row| matches_all(conditions, row), data, *updated_at)?;
+            }
+        }
+        StorageOperation::DeleteWhere { table: name, conditions } if name == table_name => {
+            if let Some(table) = table {
+                table.delete_where(|row| matches_all(conditions, row))?;
+            }
+        }
+        StorageOperation::Truncate { table: name } if name == table_name => {
+            if let Some(table) = table {
+                table.truncate();
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// 每条 INSERT 语句包含的行数
+const DUMP_ROWS_PER_INSERT: usize = 100;
+
+/// 生成建表语句
+fn create_table_sql(table: &Table) -> String {
+    let columns: Vec<String> = table.schema.columns.iter()
+        .map(|column| {
+            let mut definition = format!("{} {}", sql::quote_identifier(&column.name), column.data_type);
+            if column.primary_key {
+                definition.push_str(" PRIMARY KEY");
+            } else {
+                if !column.nullable {
+                    definition.push_str(" NOT NULL");
+                }
+                if column.unique {
+                    definition.push_str(" UNIQUE");
+                }
+            }
+            if let Some(expr) = &column.default_expr {
+                definition.push_str(&format!(" DEFAULT {}", expr));
+            } else if let Some(default_value) = &column.default_value {
+                definition.push_str(&format!(" DEFAULT {}", sql::literal(default_value)));
+            }
+            if column.collation != Collation::Binary {
+                definition.push_str(&format!(" COLLATE {}", column.collation));
+            }
+            definition
+        })
+        .collect();
+
+    let mut statement = format!("CREATE TABLE {} (\n  {}\n)", sql::quote_identifier(&table.name), columns.join(",\n  "));
+    if table.layout == TableLayout::Columnar {
+        statement.push_str(" LAYOUT COLUMNAR");
+    }
+    match &table.partition_spec {
+        Some(PartitionSpec::Hash { column, buckets }) => {
+            statement.push_str(&format!(" PARTITION BY HASH ({}) BUCKETS {}", sql::quote_identifier(column), buckets));
+        }
+        Some(PartitionSpec::Range { column, bounds }) => {
+            let bounds: Vec<String> = bounds.iter().map(sql::literal).collect();
+            statement.push_str(&format!(" PARTITION BY RANGE ({}) BOUNDS ({})", sql::quote_identifier(column), bounds.join(", ")));
+        }
+        None => {}
+    }
+    let settings = &table.schema.settings;
+    let mut options = Vec::new();
+    if settings.unlogged {
+        options.push("UNLOGGED".to_string());
+    }
+    if let Some(ttl) = settings.ttl {
+        options.push(format!("TTL {}", ttl.as_secs()));
+    }
+    if settings.compressed {
+        options.push("COMPRESSION LZ4".to_string());
+    }
+    if let Some(max_rows) = settings.max_rows {
+        options.push(format!("MAX_ROWS {}", max_rows));
+    }
+    if !options.is_empty() {
+        statement.push_str(&format!(" WITH ({})", options.join(", ")));
+    }
+    statement.push_str(";\n");
+    statement
 }
 
-/// 事务对象
-pub struct Transaction<'a> {
-    engine: &'a DatabaseEngine,
-    operations: Vec<StorageOperation>,
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ColumnDefinition, DataType, VERSION_COLUMN};
+    use crate::interval::Interval;
+    use crate::partition::PartitionSpec;
+    use crate::migrations::Migration;
+    use crate::expression::{BinaryOperator, Expr};
+
+    #[tokio::test]
+    async fn test_database_engine() {
+        let engine = DatabaseEngine::in_memory();
+
+        // 创建表
+        let schema = Schema::new(vec![
+            ColumnDefinition::new("id", DataType::Integer, true),
+            ColumnDefinition::new("name", DataType::Text, false),
+        ]);
+
+        engine.create_table("users", schema).await.unwrap();
+
+        // 插入数据
+        let mut data = HashMap::new();
+        data.insert("id".to_string(), Value::Integer(1));
+        data.insert("name".to_string(), Value::Text("Alice".to_string()));
+
+        let _id = engine.insert("users", data).await.unwrap();
+
+        // 查询数据
+        let query = QueryBuilder::select("users")
+            .where_condition("id", ComparisonOperator::Equal, Value::Integer(1))
+            .build();
+
+        let result = engine.query(query).await.unwrap();
+        assert_eq!(result.rows.len(), 1);
+
+        // 获取表信息
+        let table_info = engine.get_table_info("users").await.unwrap();
+        assert_eq!(table_info.row_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_transaction() {
+        let engine = DatabaseEngine::in_memory();
+
+        let schema = Schema::new(vec![
+            ColumnDefinition::new("id", DataType::Integer, true),
+            ColumnDefinition::new("value", DataType::Text, false),
+        ]);
+
+        let result = engine.transaction(|tx| {
+            tx.create_table("test_table", schema.clone())?;
+
+            let mut data1 = HashMap::new();
+            data1.insert("id".to_string(), Value::Integer(1));
+            data1.insert("value".to_string(), Value::Text("test1".to_string()));
+            tx.insert("test_table", data1)?;
+
+            let mut data2 = HashMap::new();
+            data2.insert("id".to_string(), Value::Integer(2));
+            data2.insert("value".to_string(), Value::Text("test2".to_string()));
+            tx.insert("test_table", data2)?;
+
+            Ok::<(), DatabaseError>(())
+        }).await;
+
+        assert!(result.is_ok());
+
+        // 验证数据
+        let tables = engine.list_tables().await;
+        assert!(tables.iter().any(|t| t.name == "test_table"));
+    }
+
+    #[tokio::test]
+    async fn test_batch_operations() {
+        let engine = DatabaseEngine::in_memory();
+
+        let schema = Schema::new(vec![
+            ColumnDefinition::new("id", DataType::Integer, true),
+            ColumnDefinition::new("name", DataType::Text, false),
+        ]);
+
+        engine.create_table("batch_test", schema).await.unwrap();
+
+        // 批量插入
+        let mut rows = Vec::new();
+        for i in 1..=5 {
+            let mut data = HashMap::new();
+            data.insert("id".to_string(), Value::Integer(i));
+            data.insert("name".to_string(), Value::Text(format!("User {}", i)));
+            rows.push(data);
+        }
+
+        let ids = engine.batch_insert("batch_test", rows).await.unwrap();
+        assert_eq!(ids.len(), 5);
+
+        // 多行 INSERT 作为一次批量插入执行，任意一行违反约束时整批都不写入
+        let row = |id: i64| HashMap::from([
+            ("id".to_string(), Value::Integer(id)),
+            ("name".to_string(), Value::Text(format!("User {}", id))),
+        ]);
+        let query = Query::insert_many("batch_test", vec![row(6), row(7)]);
+        assert_eq!(query.shape(), r#"INSERT INTO batch_test (id, name) VALUES (?, ?), ..."#);
+        assert_eq!(engine.execute(query).await.unwrap().affected_ids.len(), 2);
+        let error = engine.execute(Query::insert_many("batch_test", vec![row(8), row(1)])).await.unwrap_err();
+        assert!(error.is_constraint_violation());
+        let results = engine.execute_sql("INSERT INTO batch_test VALUES (8, 'a'), (9, 'b'), (10, 'c')").await.unwrap();
+        assert_eq!(results[0].affected_ids.len(), 3);
+
+        // 统计信息
+        let stats = engine.get_stats().await.unwrap();
+        assert_eq!(stats.total_rows, 10);
+    }
+
+    #[tokio::test]
+    #[allow(clippy::await_holding_lock)] // 有意在等待期间持有 lock_a 的写锁
+    async fn test_per_table_locks() {
+        let engine = DatabaseEngine::in_memory();
+
+        for name in ["lock_a", "lock_b"] {
+            let schema = Schema::new(vec![ColumnDefinition::new("id", DataType::Integer, true)]);
+            engine.create_table(name, schema).await.unwrap();
+        }
+
+        // 持有 lock_a 的写锁时，lock_b 上的写入不会被阻塞
+        let storage = engine.storage.read().await;
+        let _guard = storage.get_table_mut("lock_a").unwrap().unwrap();
+
+        let mut data = HashMap::new();
+        data.insert("id".to_string(), Value::Integer(1));
+        let insert = engine.insert("lock_b", data);
+        tokio::time::timeout(std::time::Duration::from_secs(1), insert).await
+            .expect("写入其他表不应等待 lock_a 的写锁")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_columnar_table() {
+        let engine = DatabaseEngine::in_memory();
+
+        let schema = Schema::new(vec![
+            ColumnDefinition::new("id", DataType::Integer, true),
+            ColumnDefinition::new("amount", DataType::Float, false),
+        ]);
+        engine.create_table_with_layout("sales", schema, TableLayout::Columnar).await.unwrap();
+
+        let rows = (1..=10).map(|i| {
+            let mut data = HashMap::new();
+            data.insert("id".to_string(), Value::Integer(i));
+            data.insert("amount".to_string(), Value::Float(i as f64 * 1.5));
+            data
+        }).collect();
+        engine.batch_insert("sales", rows).await.unwrap();
+
+        let mut duplicate = HashMap::new();
+        duplicate.insert("id".to_string(), Value::Integer(3));
+        assert!(engine.insert("sales", duplicate).await.is_err());
+
+        let mut updates = HashMap::new();
+        updates.insert("amount".to_string(), Value::Float(100.0));
+        let conditions = vec![("id".to_string(), ComparisonOperator::LessThanOrEqual, Value::Integer(2))];
+        assert_eq!(engine.update("sales", conditions, updates).await.unwrap().len(), 2);
+
+        let conditions = vec![("id".to_string(), ComparisonOperator::GreaterThan, Value::Integer(8))];
+        assert_eq!(engine.delete("sales", conditions).await.unwrap().len(), 2);
+
+        let count = QueryBuilder::count("sales")
+            .where_condition("amount", ComparisonOperator::GreaterThanOrEqual, Value::Float(9.0))
+            .build();
+        assert_eq!(engine.query(count).await.unwrap().count, Some(5));
+
+        let top = QueryBuilder::select("sales")
+            .order_by("amount", false)
+            .limit(1)
+            .build();
+        let result = engine.query(top).await.unwrap();
+        assert_eq!(result.rows[0].get("id"), Some(&Value::Integer(1)));
+        assert_eq!(result.rows[0].version, 2);
+        assert_eq!(engine.get_table_info("sales").await.unwrap().row_count, 8);
+    }
+
+    #[tokio::test]
+    async fn test_zone_maps_skip_chunks() {
+        let engine = DatabaseEngine::in_memory();
+        let schema = Schema::new(vec![
+            ColumnDefinition::new("id", DataType::Integer, true),
+            ColumnDefinition::new("ts", DataType::DateTime, false),
+        ]);
+        engine.create_table("events", schema).await.unwrap();
+        let start = chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        let rows = (0..5000).map(|i| HashMap::from([
+            ("id".to_string(), Value::Integer(i)),
+            ("ts".to_string(), Value::DateTime(start + chrono::Duration::minutes(i))),
+        ])).collect();
+        engine.batch_insert("events", rows).await.unwrap();
+
+        // 只有最后一块可能包含最近 100 分钟的数据
+        let recent = |count: bool| {
+            let builder = if count { QueryBuilder::count("events") } else { QueryBuilder::select("events") };
+            builder.where_condition("ts", ComparisonOperator::GreaterThanOrEqual, Value::DateTime(start + chrono::Duration::minutes(4900))).build()
+        };
+        let result = engine.query(recent(false)).await.unwrap();
+        assert_eq!((result.rows.len(), result.rows_scanned), (100, 5000 - 4096));
+        assert_eq!(engine.query(recent(true)).await.unwrap().count, Some(100));
+
+        // 写入后映射失效重建，追加的旧时间戳行仍能被找到
+        let mut late = HashMap::new();
+        late.insert("id".to_string(), Value::Integer(5000));
+        late.insert("ts".to_string(), Value::DateTime(start));
+        engine.insert("events", late).await.unwrap();
+        let query = QueryBuilder::select("events")
+            .where_condition("ts", ComparisonOperator::Equal, Value::DateTime(start))
+            .build();
+        let result = engine.query(query).await.unwrap();
+        assert_eq!((result.rows.len(), result.rows_scanned), (2, 1024 + 905));
+    }
+
+    #[tokio::test]
+    async fn test_vectorized_matches_row_layout() {
+        let engine = DatabaseEngine::in_memory();
+        for (name, layout) in [("row_t", TableLayout::Row), ("col_t", TableLayout::Columnar)] {
+            let schema = Schema::new(vec![
+                ColumnDefinition::new("id", DataType::Integer, true),
+                ColumnDefinition::new("qty", DataType::Integer, false),
+                ColumnDefinition::new("price", DataType::Float, false),
+                ColumnDefinition::new("category", DataType::Text, false),
+            ]);
+            engine.create_table_with_layout(name, schema, layout).await.unwrap();
+            let rows = (0..2500).map(|i| {
+                let mut data = HashMap::from([
+                    ("id".to_string(), Value::Integer(i)),
+                    ("category".to_string(), Value::Text(["A", "B", "C"][i as usize % 3].to_string())),
+                ]);
+                if i % 7 != 0 {
+                    data.insert("qty".to_string(), Value::Integer(i % 50));
+                    data.insert("price".to_string(), Value::Float(i as f64 * 0.5));
+                }
+                data
+            }).collect();
+            engine.batch_insert(name, rows).await.unwrap();
+        }
+
+        let queries: Vec<Vec<(&str, ComparisonOperator, Value)>> = vec![
+            vec![("category", ComparisonOperator::Equal, Value::Text("B".to_string()))],
+            vec![("qty", ComparisonOperator::GreaterThan, Value::Float(20.5)), ("price", ComparisonOperator::LessThan, Value::Integer(900))],
+            vec![("qty", ComparisonOperator::IsNull, Value::Null)],
+            // LIKE 不能按列求值，退回逐行求值
+            vec![("category", ComparisonOperator::Like, Value::Text("A%".to_string())), ("qty", ComparisonOperator::NotEqual, Value::Integer(3))],
+        ];
+        for conditions in queries {
+            let mut results = Vec::new();
+            for table in ["row_t", "col_t"] {
+                let mut select = QueryBuilder::select(table).order_by("id", true);
+                let mut count = QueryBuilder::count(table);
+                for (column, operator, value) in &conditions {
+                    select = select.where_condition(column, operator.clone(), value.clone());
+                    count = count.where_condition(column, operator.clone(), value.clone());
+                }
+                let select = select.build();
+                let ids: Vec<Option<Value>> = engine.query(select.clone()).await.unwrap().rows.iter()
+                    .map(|row| row.get("id").cloned())
+                    .collect();
+                let count = engine.query(count.build()).await.unwrap().count;
+                let mut aggregates = Vec::new();
+                for aggregate in [Aggregate::Sum, Aggregate::Avg, Aggregate::Min, Aggregate::Max] {
+                    aggregates.push(engine.aggregate(select.clone(), aggregate, "qty").await.unwrap());
+                    aggregates.push(engine.aggregate(select.clone(), aggregate, "price").await.unwrap());
+                }
+                results.push((ids, count, aggregates));
+            }
+            assert_eq!(results[0], results[1], "{:?}", conditions);
+        }
+
+        let all = QueryBuilder::select("col_t").build();
+        assert_eq!(engine.aggregate(all.clone(), Aggregate::Sum, "qty").await.unwrap(), Value::Integer((0..2500).filter(|i| i % 7 != 0).map(|i| i % 50).sum()));
+        assert!(engine.aggregate(all.clone(), Aggregate::Sum, "category").await.is_err());
+        assert!(engine.aggregate(all, Aggregate::Sum, "missing").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_memory_budget() {
+        let mut engine = DatabaseEngine::in_memory();
+
+        for name in ["mem_a", "mem_b"] {
+            let schema = Schema::new(vec![
+                ColumnDefinition::new("id", DataType::Integer, true),
+                ColumnDefinition::new("payload", DataType::Text, false),
+            ]);
+            engine.create_table(name, schema).await.unwrap();
+        }
+
+        let row = |i: i64| {
+            let mut data = HashMap::new();
+            data.insert("id".to_string(), Value::Integer(i));
+            data.insert("payload".to_string(), Value::Text("x".repeat(1000)));
+            data
+        };
+
+        engine.insert("mem_a", row(1)).await.unwrap();
+        let usage = engine.memory_usage().await;
+        let one_row = usage.total_bytes();
+        assert!(usage.tables.iter().any(|t| t.table == "mem_a" && t.value_bytes >= 1000));
+
+        // 单表上限
+        engine.set_table_memory_limit("mem_a", Some(one_row * 2 + one_row / 2));
+        engine.insert("mem_a", row(2)).await.unwrap();
+        let err = engine.insert("mem_a", row(3)).await.unwrap_err();
+        assert!(matches!(err, DatabaseError::OutOfMemoryBudget { .. }));
+        assert!(engine.batch_insert("mem_a", vec![row(4)]).await.is_err());
+
+        // 使行变大的更新同样受上限约束，不变大的更新不受影响
+        let grow = |len: usize| HashMap::from([("payload".to_string(), Value::Text("y".repeat(len)))]);
+        let conditions = || vec![("id".to_string(), ComparisonOperator::Equal, Value::Integer(1))];
+        let err = engine.update("mem_a", conditions(), grow(one_row * 2)).await.unwrap_err();
+        assert!(matches!(err, DatabaseError::OutOfMemoryBudget { .. }));
+        assert_eq!(engine.update("mem_a", conditions(), grow(1000)).await.unwrap().len(), 1);
+
+        // 全局上限计入其他表
+        engine.set_table_memory_limit("mem_a", None);
+        engine.set_memory_limit(Some(one_row * 3 + one_row / 2));
+        engine.insert("mem_b", row(1)).await.unwrap();
+        assert!(engine.insert("mem_b", row(2)).await.is_err());
+
+        let stats = engine.get_stats().await.unwrap();
+        assert_eq!(stats.total_rows, 3);
+        assert_eq!(stats.memory.total_bytes(), engine.memory_usage().await.total_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_spill_to_disk() {
+        // 溢出需要数据目录，不能使用纯内存模式
+        let dir = std::env::temp_dir().join(format!("simple-db-spill-{}", uuid::Uuid::new_v4()));
+        let mut engine = DatabaseEngine::open(&dir).await.unwrap();
+        engine.set_auto_save(false);
+
+        let schema = Schema::new(vec![
+            ColumnDefinition::new("id", DataType::Integer, true),
+            ColumnDefinition::new("payload", DataType::Text, false),
+        ]);
+        engine.create_table("spill_test", schema).await.unwrap();
+        engine.set_spill_threshold("spill_test", Some(20_000)).await.unwrap();
+
+        for i in 0..50 {
+            let mut data = HashMap::new();
+            data.insert("id".to_string(), Value::Integer(i));
+            data.insert("payload".to_string(), Value::Text("x".repeat(1000)));
+            engine.insert("spill_test", data).await.unwrap();
+        }
+
+        let usage = engine.memory_usage().await;
+        let table_usage = &usage.tables[0];
+        assert!(table_usage.spilled_rows > 0);
+        assert!(table_usage.total_bytes() <= 20_000);
+        assert_eq!(engine.get_table_info("spill_test").await.unwrap().row_count, 50);
+
+        // 扫描时按需读取已溢出的行
+        let query = QueryBuilder::select("spill_test").order_by("id", true).limit(3).build();
+        let ids: Vec<_> = engine.query(query).await.unwrap().rows.iter()
+            .map(|row| row.get("id").cloned())
+            .collect();
+        assert_eq!(ids, vec![Some(Value::Integer(0)), Some(Value::Integer(1)), Some(Value::Integer(2))]);
+
+        // 唯一约束同样覆盖已溢出的行
+        let mut duplicate = HashMap::new();
+        duplicate.insert("id".to_string(), Value::Integer(0));
+        assert!(engine.insert("spill_test", duplicate).await.is_err());
+
+        let mut updates = HashMap::new();
+        updates.insert("payload".to_string(), Value::Text("cold".to_string()));
+        let conditions = vec![("id".to_string(), ComparisonOperator::Equal, Value::Integer(0))];
+        assert_eq!(engine.update("spill_test", conditions, updates).await.unwrap().len(), 1);
+        let conditions = vec![("id".to_string(), ComparisonOperator::LessThan, Value::Integer(10))];
+        assert_eq!(engine.delete("spill_test", conditions).await.unwrap().len(), 10);
+
+        engine.set_spill_threshold("spill_test", None).await.unwrap();
+        let usage = engine.memory_usage().await;
+        assert_eq!(usage.tables[0].spilled_rows, 0);
+        assert_eq!(usage.tables[0].rows, 40);
+        engine.close().await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_range_partitioned_table() {
+        let engine = DatabaseEngine::in_memory();
+
+        let schema = Schema::new(vec![
+            ColumnDefinition::new("id", DataType::Integer, true),
+            ColumnDefinition::new("year", DataType::Integer, false),
+        ]);
+        let spec = PartitionSpec::range("year", vec![Value::Integer(2020), Value::Integer(2023)]);
+        engine.create_table_with_options("orders", schema, TableOptions::new().partition(spec)).await.unwrap();
+
+        for (id, year) in [(1, 2019), (2, 2021), (3, 2022), (4, 2024)] {
+            let mut data = HashMap::new();
+            data.insert("id".to_string(), Value::Integer(id));
+            data.insert("year".to_string(), Value::Integer(year));
+            engine.insert("orders", data).await.unwrap();
+        }
+
+        {
+            let storage = engine.storage.read().await;
+            let table = storage.get_table("orders").unwrap().unwrap();
+            let sizes: Vec<_> = table.partitions.iter().map(Table::row_count).collect();
+            assert_eq!(sizes, vec![1, 2, 1]);
+        }
+
+        let query = QueryBuilder::select("orders")
+            .where_condition("year", ComparisonOperator::GreaterThanOrEqual, Value::Integer(2021))
+            .order_by("id", true)
+            .build();
+        let ids: Vec<_> = engine.query(query).await.unwrap().rows.iter()
+            .map(|row| row.get("id").cloned())
+            .collect();
+        assert_eq!(ids, vec![Some(Value::Integer(2)), Some(Value::Integer(3)), Some(Value::Integer(4))]);
+
+        // 唯一约束跨分区检查
+        let mut duplicate = HashMap::new();
+        duplicate.insert("id".to_string(), Value::Integer(1));
+        duplicate.insert("year".to_string(), Value::Integer(2024));
+        assert!(engine.insert("orders", duplicate).await.is_err());
+
+        // 修改分区键后行移动到新分区
+        let mut updates = HashMap::new();
+        updates.insert("year".to_string(), Value::Integer(2025));
+        let conditions = vec![("id".to_string(), ComparisonOperator::Equal, Value::Integer(1))];
+        assert_eq!(engine.update("orders", conditions, updates).await.unwrap().len(), 1);
+
+        let storage = engine.storage.read().await;
+        let table = storage.get_table("orders").unwrap().unwrap();
+        let sizes: Vec<_> = table.partitions.iter().map(Table::row_count).collect();
+        assert_eq!(sizes, vec![0, 2, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_json_export_import() {
+        let engine = DatabaseEngine::in_memory();
+
+        let schema = Schema::new(vec![
+            ColumnDefinition::new("id", DataType::Integer, true),
+            ColumnDefinition::new("price", DataType::Float, false),
+            ColumnDefinition::new("day", DataType::Date, false),
+        ]);
+        engine.create_table("items", schema.clone()).await.unwrap();
+        let mut data = HashMap::new();
+        data.insert("id".to_string(), Value::Integer(1));
+        data.insert("price".to_string(), Value::Float(2.5));
+        data.insert("day".to_string(), Value::Date(chrono::NaiveDate::from_ymd_opt(2024, 3, 1).unwrap()));
+        engine.insert("items", data).await.unwrap();
+
+        let path = std::env::temp_dir().join(format!("simple-db-export-{}.ndjson", uuid::Uuid::new_v4()));
+        let path = path.to_str().unwrap();
+        assert_eq!(engine.export_json("items", path).await.unwrap(), 1);
+        let line = std::fs::read_to_string(path).unwrap();
+        assert_eq!(line.trim(), r#"{"day":"2024-03-01","id":1,"price":2.5}"#);
+
+        engine.create_table("copy", schema).await.unwrap();
+        assert_eq!(engine.import_json("copy", path).await.unwrap(), 1);
+        let rows = engine.query(QueryBuilder::select("copy").build()).await.unwrap().rows;
+        assert_eq!(rows[0].get("day"), Some(&Value::Date(chrono::NaiveDate::from_ymd_opt(2024, 3, 1).unwrap())));
+
+        // 类型错误时整个文件都不导入
+        std::fs::write(path, "{\"id\":2,\"price\":1}\n{\"id\":\"x\",\"price\":1}\n").unwrap();
+        let err = engine.import_json("copy", path).await.unwrap_err();
+        assert!(err.to_string().contains("第 2 行"));
+        assert_eq!(engine.get_table_info("copy").await.unwrap().row_count, 1);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sql_dump_round_trip() {
+        let engine = DatabaseEngine::in_memory();
+        engine.execute_sql("
+            CREATE TABLE events (id INTEGER PRIMARY KEY, at DATETIME NOT NULL, tag TEXT DEFAULT 'none', payload BINARY)
+                PARTITION BY RANGE (id) BOUNDS (10);
+            INSERT INTO events (id, at, payload) VALUES (1, '2024-05-01T08:00:00', X'00ff'), (20, '2024-05-02T09:30:00', NULL);
+            INSERT INTO events VALUES (3, '2024-05-03T10:00:00', 'it''s', NULL);
+        ").await.unwrap();
+
+        let results = engine.execute_sql("SELECT * FROM events WHERE at > '2024-05-01T12:00:00' ORDER BY id").await.unwrap();
+        let ids: Vec<_> = results[0].rows.iter().map(|row| row.get("id").cloned()).collect();
+        assert_eq!(ids, vec![Some(Value::Integer(3)), Some(Value::Integer(20))]);
+
+        let path = std::env::temp_dir().join(format!("simple-db-dump-{}.sql", uuid::Uuid::new_v4()));
+        let path = path.to_str().unwrap();
+        assert_eq!(engine.dump(path).await.unwrap(), 3);
+
+        let restored = DatabaseEngine::in_memory();
+        restored.execute_sql(&std::fs::read_to_string(path).unwrap()).await.unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let storage = restored.storage.read().await;
+        let table = storage.get_table("events").unwrap().unwrap();
+        assert!(table.is_partitioned());
+        assert_eq!(table.row_count(), 3);
+        let rows = table.all_rows().unwrap();
+        let first = rows.iter().find(|row| row.get("id") == Some(&Value::Integer(1))).unwrap();
+        assert_eq!(first.get("payload"), Some(&Value::Binary(vec![0, 255])));
+        assert_eq!(first.get("tag"), Some(&Value::Text("none".to_string())));
+        let third = rows.iter().find(|row| row.get("id") == Some(&Value::Integer(3))).unwrap();
+        assert_eq!(third.get("tag"), Some(&Value::Text("it's".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_schema_sql_and_indexes() {
+        let engine = DatabaseEngine::in_memory();
+        engine.execute_sql("
+            CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT UNIQUE, tags TEXT[]);
+            CREATE TABLE logs (id INTEGER PRIMARY KEY, message TEXT NOT NULL);
+        ").await.unwrap();
+
+        assert_eq!(
+            engine.schema_sql(Some("logs")).await.unwrap(),
+            "CREATE TABLE logs (\n  id INTEGER PRIMARY KEY,\n  message TEXT NOT NULL\n);\n"
+        );
+        let all = engine.schema_sql(None).await.unwrap();
+        assert!(all.find("CREATE TABLE logs").unwrap() < all.find("CREATE TABLE users").unwrap());
+        assert!(engine.schema_sql(Some("missing")).await.is_err());
+
+        // 含空格的表名与关键字列名输出时加引号，无效的标识符在修改前报错
+        engine.execute_sql("CREATE TABLE \"order items\" (\"order\" INTEGER);").await.unwrap();
+        assert_eq!(
+            engine.schema_sql(Some("order items")).await.unwrap(),
+            "CREATE TABLE \"order items\" (\n  \"order\" INTEGER\n);\n"
+        );
+        let invalid = |result: Result<()>| matches!(result, Err(DatabaseError::InvalidIdentifier { .. }));
+        assert!(invalid(engine.create_table("main.logs", Schema::new(Vec::new())).await));
+        assert!(invalid(engine.rename_table("logs", " logs").await));
+        assert!(invalid(engine.create_sequence("", 1, 1).await));
+        engine.drop_table("order items").await.unwrap();
+
+        let indexes: Vec<(String, IndexKind)> = engine.list_indexes(Some("users")).await.unwrap()
+            .into_iter()
+            .map(|index| (index.column, index.kind))
+            .collect();
+        assert_eq!(indexes, vec![
+            ("id".to_string(), IndexKind::PrimaryKey),
+            ("email".to_string(), IndexKind::Unique),
+            ("tags".to_string(), IndexKind::Element),
+        ]);
+        assert_eq!(engine.list_indexes(None).await.unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_named_databases() {
+        let dir = std::env::temp_dir().join(format!("simple-db-databases-{}", uuid::Uuid::new_v4()));
+        let mut engine = DatabaseEngine::open(&dir).await.unwrap();
+        engine.execute_sql("CREATE TABLE users (id INTEGER PRIMARY KEY); INSERT INTO users VALUES (1);").await.unwrap();
+
+        engine.execute_sql("CREATE DATABASE analytics;").await.unwrap();
+        assert!(matches!(engine.create_database("analytics"), Err(DatabaseError::DatabaseExists(_))));
+        assert!(engine.create_database("bad-name").is_err());
+
+        engine.use_database("analytics").await.unwrap();
+        assert_eq!(engine.current_database(), "analytics");
+        assert!(engine.list_tables().await.is_empty());
+        engine.execute_sql("CREATE TABLE users (id INTEGER PRIMARY KEY, score FLOAT);").await.unwrap();
+        assert!(engine.drop_database("analytics").is_err());
+
+        engine.use_database(DEFAULT_DATABASE).await.unwrap();
+        assert_eq!(engine.get_table_info("users").await.unwrap().row_count, 1);
+        assert_eq!(engine.get_table_info("users").await.unwrap().schema.columns.len(), 1);
+        assert!(engine.list_databases().contains(&"analytics".to_string()));
+
+        // 磁盘上的数据库在重新打开后仍然列出
+        engine.close().await.unwrap();
+        let mut engine = DatabaseEngine::open(&dir).await.unwrap();
+        assert_eq!(engine.list_databases(), vec!["analytics".to_string(), DEFAULT_DATABASE.to_string()]);
+        engine.execute_sql("DROP DATABASE analytics;").await.unwrap();
+        assert!(matches!(engine.use_database("analytics").await, Err(DatabaseError::DatabaseNotFound(_))));
+        assert!(!dir.join("databases").join("analytics").exists());
+        engine.close().await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // 使用持久化后端的引擎没有数据目录，不会读取文件系统根目录下的 databases/
+        let mut backed = DatabaseEngine::with_backend(Arc::new(crate::backend::MemoryBackend::new())).await.unwrap();
+        assert_eq!(backed.list_databases(), vec![DEFAULT_DATABASE.to_string()]);
+        assert!(matches!(backed.use_database("analytics").await, Err(DatabaseError::DatabaseNotFound(_))));
+        assert!(matches!(backed.drop_database("analytics"), Err(DatabaseError::DatabaseNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_migrate_up_and_down() {
+        let engine = DatabaseEngine::in_memory();
+        let migrator = Migrator::new()
+            .migration(Migration::sql(1, "create_users", "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT);")
+                .down_sql("DROP TABLE users;")).unwrap()
+            .migration(Migration::rust(2, "seed_users", |engine| Box::pin(async move {
+                engine.execute_sql("INSERT INTO users VALUES (1, 'alice'), (2, 'bob');").await.map(|_| ())
+            })).down_sql("DELETE FROM users;")).unwrap()
+            .migration(Migration::sql(3, "create_posts", "CREATE TABLE posts (id INTEGER PRIMARY KEY);")).unwrap();
+
+        assert_eq!(engine.migrate_up(&migrator, Some(2)).await.unwrap(), vec![1, 2]);
+        assert_eq!(engine.get_table_info("users").await.unwrap().row_count, 2);
+        assert_eq!(engine.migrate_up(&migrator, None).await.unwrap(), vec![3]);
+        assert!(engine.migrate_up(&migrator, None).await.unwrap().is_empty());
+        assert_eq!(engine.applied_migrations().await.unwrap(), vec![1, 2, 3]);
+
+        // 迁移 3 没有回滚步骤
+        assert!(engine.migrate_down(&migrator, 0).await.is_err());
+        engine.drop_table("posts").await.unwrap();
+        engine.delete(MIGRATIONS_TABLE, vec![("version".to_string(), ComparisonOperator::Equal, Value::Integer(3))]).await.unwrap();
+
+        assert_eq!(engine.migrate_down(&migrator, 1).await.unwrap(), vec![2]);
+        assert_eq!(engine.get_table_info("users").await.unwrap().row_count, 0);
+        assert_eq!(engine.migrate_down(&migrator, 0).await.unwrap(), vec![1]);
+        assert!(engine.get_table_info("users").await.is_err());
+        assert!(engine.applied_migrations().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_information_schema_queries() {
+        let engine = DatabaseEngine::in_memory();
+        engine.execute_sql("
+            CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+            CREATE TABLE events (id INTEGER PRIMARY KEY, at DATETIME) LAYOUT COLUMNAR;
+            INSERT INTO users VALUES (1, 'alice'), (2, 'bob');
+        ").await.unwrap();
+
+        let results = engine.execute_sql("
+            SELECT * FROM information_schema.tables WHERE row_count > 0;
+            SELECT * FROM information_schema.columns WHERE table_name = 'events' ORDER BY ordinal_position;
+        ").await.unwrap();
+        assert_eq!(results[0].rows.len(), 1);
+        assert_eq!(results[0].rows[0].get_text("table_name"), Some("users"));
+        let columns: Vec<_> = results[1].rows.iter().filter_map(|row| row.get_text("column_name")).collect();
+        assert_eq!(columns, vec!["id", "at"]);
+        assert_eq!(results[1].rows[1].get_text("data_type"), Some("DATETIME"));
+
+        assert!(engine.execute_sql("DELETE FROM information_schema.tables").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_insert_serde_and_deserialize_into() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Event {
+            id: i64,
+            at: chrono::NaiveDateTime,
+            tags: Vec<String>,
+            note: Option<String>,
+        }
+
+        let engine = DatabaseEngine::in_memory();
+        engine.execute_sql("CREATE TABLE events (id INTEGER PRIMARY KEY, at DATETIME, tags JSON, note TEXT)").await.unwrap();
+
+        let event = Event {
+            id: 1,
+            at: "2024-05-01T08:30:00".parse().unwrap(),
+            tags: vec!["a".to_string(), "b".to_string()],
+            note: None,
+        };
+        engine.insert_serde("events", &event).await.unwrap();
+        assert!(engine.insert_serde("events", &42).await.is_err());
+
+        let result = engine.query(QueryBuilder::select("events").build()).await.unwrap();
+        let row = &result.rows[0];
+        assert_eq!(row.get("at"), Some(&Value::DateTime(event.at)));
+        assert_eq!(row.get("tags"), Some(&Value::Json(serde_json::json!(["a", "b"]))));
+        assert_eq!(row.deserialize_into::<Event>().unwrap(), event);
+    }
+
+    #[tokio::test]
+    async fn test_case_insensitive_collation() {
+        let engine = DatabaseEngine::in_memory();
+        engine.execute_sql("
+            CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT UNIQUE COLLATE NOCASE, name TEXT COLLATE LOCALE);
+            INSERT INTO users VALUES (1, 'Alice@Example.com', 'bob'), (2, 'carol@example.com', 'Alice');
+        ").await.unwrap();
+
+        let err = engine.execute_sql("INSERT INTO users VALUES (3, 'ALICE@example.COM', 'x')").await.unwrap_err();
+        assert!(matches!(err, DatabaseError::UniqueViolation { .. }));
+
+        let results = engine.execute_sql("
+            SELECT * FROM users WHERE email = 'alice@example.com';
+            SELECT * FROM users WHERE email LIKE 'CAROL%';
+            SELECT * FROM users ORDER BY name;
+        ").await.unwrap();
+        assert_eq!(results[0].rows[0].get_integer("id"), Some(1));
+        assert_eq!(results[1].rows[0].get_integer("id"), Some(2));
+        let names: Vec<_> = results[2].rows.iter().filter_map(|row| row.get_text("name")).collect();
+        assert_eq!(names, vec!["Alice", "bob"]);
+
+        let deleted = engine.delete("users", vec![
+            ("email".to_string(), ComparisonOperator::Equal, Value::Text("CAROL@EXAMPLE.COM".to_string())),
+        ]).await.unwrap();
+        assert_eq!(deleted.len(), 1);
+
+        let storage = engine.storage.read().await;
+        let sql = create_table_sql(&storage.get_table("users").unwrap().unwrap());
+        assert!(sql.contains("COLLATE NOCASE") && sql.contains("COLLATE LOCALE"), "{}", sql);
+    }
+
+    #[tokio::test]
+    async fn test_json_path_conditions_and_extracts() {
+        let engine = DatabaseEngine::in_memory();
+        engine.execute_sql(r#"
+            CREATE TABLE people (id INTEGER PRIMARY KEY, meta JSON);
+            INSERT INTO people VALUES
+                (1, '{"address": {"city": "Paris"}, "age": 30}'),
+                (2, '{"address": {"city": "Berlin"}, "age": 41}'),
+                (3, NULL);
+        "#).await.unwrap();
+
+        let query = QueryBuilder::select("people")
+            .where_json("meta", "$.address.city", ComparisonOperator::Equal, Value::Text("Paris".to_string()))
+            .extract_json("age", "meta", "$.age")
+            .build();
+        let result = engine.query(query).await.unwrap();
+        assert_eq!(result.rows.len(), 1);
+        assert_eq!(result.rows[0].get_integer("age"), Some(30));
+
+        let results = engine.execute_sql("
+            SELECT *, json_extract(meta, '$.address.city') AS city FROM people
+                WHERE json_extract(meta, '$.age') > 35;
+            SELECT COUNT(*) FROM people WHERE json_extract(meta, '$.address.city') IS NULL;
+            UPDATE people SET meta = NULL WHERE json_extract(meta, '$.address.city') = 'Berlin';
+        ").await.unwrap();
+        assert_eq!(results[0].rows.len(), 1);
+        assert_eq!(results[0].rows[0].get_text("city"), Some("Berlin"));
+        assert_eq!(results[1].count, Some(1));
+        assert_eq!(results[2].affected_rows, 1);
+
+        let bad_path = QueryBuilder::select("people")
+            .where_json("meta", "$.tags[x]", ComparisonOperator::Equal, Value::Null)
+            .build();
+        assert!(engine.query(bad_path).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_array_containment_queries() {
+        let engine = DatabaseEngine::in_memory();
+        let results = engine.execute_sql(r#"
+            CREATE TABLE posts (id INTEGER PRIMARY KEY, tags TEXT[] COLLATE NOCASE, scores FLOAT[]);
+            INSERT INTO posts VALUES
+                (1, ARRAY['rust', 'db'], ARRAY[1, 2.5]),
+                (2, '["go", "Web"]', NULL),
+                (3, ARRAY['Rust', 'web'], ARRAY[]);
+            SELECT * FROM posts WHERE tags @> 'RUST' ORDER BY id;
+            SELECT * FROM posts WHERE tags @> ARRAY['rust', 'web'];
+            SELECT * FROM posts WHERE tags && ARRAY['go', 'db'] ORDER BY id;
+            SELECT COUNT(*) FROM posts WHERE tags <@ ARRAY['rust', 'db', 'web'];
+        "#).await.unwrap();
+        let ids = |index: usize| results[index].rows.iter().filter_map(|row| row.get_integer("id")).collect::<Vec<_>>();
+        assert_eq!(ids(1), vec![1, 3]);
+        assert_eq!(ids(2), vec![3]);
+        assert_eq!(ids(3), vec![1, 2]);
+        assert_eq!(results[4].count, Some(2));
+
+        let first = engine.query(QueryBuilder::select("posts").where_condition("id", ComparisonOperator::Equal, Value::Integer(1)).build())
+            .await.unwrap();
+        assert_eq!(first.rows[0].get("scores"), Some(&Value::Array(vec![Value::Float(1.0), Value::Float(2.5)])));
+
+        // 元素索引在写入后失效重建
+        engine.execute_sql("DELETE FROM posts WHERE id = 3; INSERT INTO posts (id, tags) VALUES (4, ARRAY['rust']);").await.unwrap();
+        let results = engine.execute_sql("SELECT * FROM posts WHERE tags @> 'rust' ORDER BY id").await.unwrap();
+        assert_eq!(results[0].rows.iter().filter_map(|row| row.get_integer("id")).collect::<Vec<_>>(), vec![1, 4]);
+        assert!(engine.memory_usage().await.tables[0].index_bytes > 0);
+
+        assert!(engine.execute_sql("SELECT * FROM posts WHERE id @> 1").await.unwrap()[0].rows.is_empty());
+        assert!(engine.execute_sql("INSERT INTO posts VALUES (5, NULL, ARRAY[1, 'x'])").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_within_radius_queries() {
+        let engine = DatabaseEngine::in_memory();
+        let results = engine.execute_sql("
+            CREATE TABLE places (id INTEGER PRIMARY KEY, name TEXT, location POINT);
+            INSERT INTO places VALUES
+                (1, 'square', POINT(31.2304, 121.4737)),
+                (2, 'bund', 'POINT(31.2400, 121.4900)'),
+                (3, 'beijing', '39.9042,116.4074'),
+                (4, 'unknown', NULL);
+            SELECT * FROM places WHERE location WITHIN 2000 OF POINT(31.2304, 121.4737) ORDER BY id;
+            SELECT * FROM places WHERE location WITHIN 500 OF POINT(31.2304, 121.4737);
+            SELECT *, ROUND(DISTANCE(location, POINT(31.2304, 121.4737))) AS meters FROM places WHERE id = 2;
+        ").await.unwrap();
+        let ids = |index: usize| results[index].rows.iter().filter_map(|row| row.get_integer("id")).collect::<Vec<_>>();
+        assert_eq!(ids(1), vec![1, 2]);
+        assert_eq!(ids(2), vec![1]);
+        let meters = results[3].rows[0].get_float("meters").unwrap();
+        assert!((1_800.0..2_000.0).contains(&meters), "{}", meters);
+        assert_eq!(results[1].rows[0].get("location").map(Value::to_string), Some("POINT(31.2304, 121.4737)".to_string()));
+        assert!(engine.memory_usage().await.tables[0].index_bytes > 0);
+
+        // 查询构建器，以及写入后网格索引失效重建
+        engine.execute_sql("INSERT INTO places VALUES (5, 'peoples park', POINT(31.2317, 121.4700))").await.unwrap();
+        let nearby = engine.query(QueryBuilder::select("places").where_within_radius("location", (31.2304, 121.4737), 1_000.0).build())
+            .await.unwrap();
+        assert_eq!(nearby.rows.len(), 2);
+
+        assert!(engine.execute_sql("INSERT INTO places VALUES (6, 'bad', POINT(91, 0))").await.is_err());
+        assert!(engine.execute_sql("SELECT * FROM places WHERE name WITHIN 10 OF POINT(0, 0)").await.unwrap()[0].rows.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_binary_literals_and_conditions() {
+        let engine = DatabaseEngine::in_memory();
+        let results = engine.execute_sql(r"
+            CREATE TABLE blobs (id INTEGER PRIMARY KEY, data BINARY);
+            INSERT INTO blobs VALUES (1, X'CAFEBABE'), (2, B64'yv4='), (3, '\xDEADBEEF'), (4, 'base64:yv7Avg==');
+            SELECT * FROM blobs WHERE data = X'CAFE' ORDER BY id;
+            SELECT * FROM blobs WHERE data STARTS WITH X'CAFE' ORDER BY id;
+            SELECT * FROM blobs WHERE data > '0xCAFF' ORDER BY id;
+            SELECT *, OCTET_LENGTH(data) AS size, TO_HEX(data) AS hex, TO_BASE64(data) AS base64 FROM blobs WHERE id = 4;
+        ").await.unwrap();
+        let ids = |index: usize| results[index].rows.iter().filter_map(|row| row.get_integer("id")).collect::<Vec<_>>();
+        assert_eq!(ids(1), vec![2]);
+        assert_eq!(ids(2), vec![1, 2, 4]);
+        assert_eq!(ids(3), vec![3]);
+        let row = &results[4].rows[0];
+        assert_eq!(row.get_integer("size"), Some(4));
+        assert_eq!(row.get_text("hex"), Some("cafec0be"));
+        assert_eq!(row.get_text("base64"), Some("yv7Avg=="));
+
+        // CSV 中的二进制字段使用与 `.binary hex` 输出相同的写法
+        let report = engine.copy_from("blobs", "5,\\x0102\n6,zz\n".as_bytes(), &CopyOptions::new(import::CopyFormat::Csv)).await.unwrap();
+        assert_eq!((report.loaded, report.rejected), (1, 1));
+        let result = engine.execute_sql("SELECT * FROM blobs WHERE id = 5").await.unwrap();
+        assert_eq!(crate::output::render_with(&result[0], crate::output::OutputFormat::Csv, crate::binary::BinaryFormat::Hex), "id,data\n5,\\x0102\n");
+
+        assert!(engine.execute_sql("SELECT * FROM blobs WHERE data = X'ABC'").await.is_err());
+        assert!(engine.execute_sql("SELECT * FROM blobs WHERE data = 'not bytes'").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_expressions_in_select_and_where() {
+        let engine = DatabaseEngine::in_memory();
+        let results = engine.execute_sql("
+            CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT, price INTEGER, qty FLOAT, budget FLOAT) LAYOUT COLUMNAR;
+            INSERT INTO items VALUES
+                (1, 'Widget', 4, 2.5, 30.0),
+                (2, 'gadget', 10, 3, 25.0),
+                (3, 'Gizmo', 7, NULL, 1.0);
+            SELECT *, price * qty AS total, LOWER(name) || '#' || id AS label FROM items
+                WHERE price * qty > budget - 15 ORDER BY id;
+            SELECT COUNT(*) FROM items WHERE LENGTH(name) = 6 AND ABS(-price) < 8;
+            SELECT * FROM items WHERE price * 2 > qty * 5 ORDER BY id;
+            DELETE FROM items WHERE UPPER(name) = 'GIZMO';
+        ").await.unwrap();
+
+        assert_eq!(results[1].rows.len(), 1);
+        let row = &results[1].rows[0];
+        assert_eq!(row.get_integer("id"), Some(2));
+        assert_eq!(row.get("total"), Some(&Value::Float(30.0)));
+        assert_eq!(row.get_text("label"), Some("gadget#2"));
+        assert_eq!(results[2].count, Some(1));
+        assert_eq!(results[3].rows.iter().filter_map(|row| row.get_integer("id")).collect::<Vec<_>>(), vec![2]);
+        assert_eq!(results[4].affected_rows, 1);
+
+        let query = QueryBuilder::select("items")
+            .where_expr(Expr::column("budget"), ComparisonOperator::GreaterThan, Expr::column("price"))
+            .unwrap()
+            .select_expr("double", Expr::binary(Expr::column("price"), BinaryOperator::Multiply, Expr::literal(2i64)))
+            .order_by("id", true)
+            .build();
+        let result = engine.query(query).await.unwrap();
+        assert_eq!(result.rows.iter().filter_map(|row| row.get_integer("double")).collect::<Vec<_>>(), vec![8, 20]);
+
+        assert!(engine.execute_sql("SELECT * FROM items WHERE NOPE(name) = 1").await.is_err());
+        assert!(engine.execute_sql("SELECT * FROM items WHERE LOWER(name, id) = 'x'").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_date_functions_and_dynamic_defaults() {
+        let engine = DatabaseEngine::in_memory();
+        let before = chrono::Utc::now().naive_utc();
+        let results = engine.execute_sql("
+            CREATE TABLE orders (id INTEGER PRIMARY KEY, placed DATE, created_at DATETIME NOT NULL DEFAULT NOW(),
+                due DATE DEFAULT placed + 30);
+            INSERT INTO orders (id, placed) VALUES (1, '2024-01-31'), (2, '2024-03-15');
+            INSERT INTO orders (id, placed, created_at) VALUES (3, '2023-12-01', '2020-01-01T00:00:00');
+            SELECT *, EXTRACT(MONTH FROM placed) AS month, DATE_TRUNC('month', placed) AS period,
+                DATE_ADD(placed, 1, 'month') AS next FROM orders WHERE EXTRACT(YEAR FROM placed) = 2024 ORDER BY id;
+            SELECT COUNT(*) FROM orders WHERE created_at > NOW() - 3600;
+            SELECT COUNT(*) FROM orders WHERE AGE(created_at) > 86400;
+        ").await.unwrap();
+
+        let date = |s: &str| Value::Date(s.parse().unwrap());
+        let rows = &results[2].rows;
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].get_integer("month"), Some(1));
+        assert_eq!(rows[1].get("period"), Some(&date("2024-03-01")));
+        assert_eq!(rows[0].get("next"), Some(&date("2024-02-29")));
+        assert_eq!(rows[0].get("due"), Some(&date("2024-03-01")));
+        match rows[0].get("created_at") {
+            Some(Value::DateTime(created)) => assert!(*created >= before),
+            other => panic!("unexpected created_at {:?}", other),
+        }
+        assert_eq!(results[3].count, Some(2));
+        assert_eq!(results[4].count, Some(1));
+
+        // 动态默认值以表达式形式随表结构导出
+        let sql = create_table_sql(&engine.storage.read().await.get_table("orders").unwrap().unwrap());
+        assert!(sql.contains("DEFAULT NOW()"), "{}", sql);
+        assert!(engine.execute_sql("CREATE TABLE bad (id INTEGER PRIMARY KEY, at DATETIME DEFAULT NOPE())").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_interval_arithmetic_and_conditions() {
+        let engine = DatabaseEngine::in_memory();
+        engine.execute_sql("
+            CREATE TABLE staff (id INTEGER PRIMARY KEY, hire_date DATE, last_login DATETIME, probation INTERVAL DEFAULT INTERVAL '90 days');
+            INSERT INTO staff (id, hire_date, last_login) VALUES (1, '2024-01-31', '2024-05-01T08:00:00');
+        ").await.unwrap();
+        let now = crate::functions::now();
+        engine.insert("staff", HashMap::from([
+            ("id".to_string(), Value::Integer(2)),
+            ("hire_date".to_string(), Value::Date(now.date())),
+            ("last_login".to_string(), Value::DateTime(now)),
+            ("probation".to_string(), Value::Interval(Interval::new(6, 0, 0))),
+        ])).await.unwrap();
+        let results = engine.execute_sql("
+            SELECT *, hire_date + probation AS confirmed, hire_date + INTERVAL '1 month' AS next,
+                last_login - INTERVAL '1 day 02:30:00' AS earlier, probation * 2 AS doubled FROM staff WHERE id = 1;
+            SELECT * FROM staff WHERE hire_date >= CURRENT_DATE() - INTERVAL '30 days';
+            SELECT * FROM staff WHERE probation > INTERVAL '2 months' AND last_login > NOW() - INTERVAL '1 hour';
+        ").await.unwrap();
+
+        let row = &results[0].rows[0];
+        assert_eq!(row.get("confirmed"), Some(&Value::Date("2024-04-30".parse().unwrap())));
+        assert_eq!(row.get("next"), Some(&Value::Date("2024-02-29".parse().unwrap())));
+        assert_eq!(row.get("earlier"), Some(&Value::DateTime("2024-04-30T05:30:00".parse().unwrap())));
+        assert_eq!(row.get("doubled").map(Value::to_string), Some("180 days".to_string()));
+        assert_eq!(results[1].rows.len(), 1);
+        assert_eq!(results[2].rows[0].get_integer("id"), Some(2));
+
+        // 时间间隔以字面量形式随表结构与数据导出
+        let sql = create_table_sql(&engine.storage.read().await.get_table("staff").unwrap().unwrap());
+        assert!(sql.contains("DEFAULT INTERVAL '90 days'"), "{}", sql);
+        assert!(engine.execute_sql("SELECT * FROM staff WHERE probation > INTERVAL '3 fortnights'").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sequences_as_defaults_and_values() {
+        use crate::backend::MemoryBackend;
+
+        let backend = MemoryBackend::new();
+        let engine = DatabaseEngine::with_backend(Arc::new(backend.clone())).await.unwrap();
+        engine.execute_sql("
+            CREATE SEQUENCE order_no START WITH 1000 INCREMENT BY 10;
+            CREATE TABLE orders (id INTEGER DEFAULT NEXTVAL('order_no'), item TEXT);
+            CREATE TABLE invoices (id INTEGER, order_id INTEGER);
+            INSERT INTO orders (item) VALUES ('a'), ('b');
+        ").await.unwrap();
+        engine.save_to_disk().await.unwrap();
+        // 同一行中先取下一个值，CURRVAL 再读到它
+        engine.execute_sql("INSERT INTO invoices VALUES (NEXTVAL('order_no') + 1, CURRVAL('order_no'))").await.unwrap();
+        engine.transaction(|tx| {
+            tx.insert("orders", HashMap::from([("item".to_string(), Value::Text("c".to_string()))]))
+        }).await.unwrap();
+        assert_eq!(engine.currval("order_no").await.unwrap(), 1030);
+        drop(engine);
+
+        // 快照之后前进的值从日志恢复
+        let engine = DatabaseEngine::with_backend(Arc::new(backend.clone())).await.unwrap();
+        assert_eq!(engine.nextval("order_no").await.unwrap(), 1040);
+        let results = engine.execute_sql("
+            SELECT * FROM orders ORDER BY id;
+            SELECT * FROM invoices WHERE order_id = CURRVAL('order_no') - 20;
+        ").await.unwrap();
+        let ids: Vec<Option<i64>> = results[0].rows.iter().map(|row| row.get_integer("id")).collect();
+        assert_eq!(ids, vec![Some(1000), Some(1010), Some(1030)]);
+        assert_eq!(results[1].rows[0].get_integer("id"), Some(1021));
+
+        assert!(engine.execute_sql("SELECT * FROM orders WHERE id = NEXTVAL('order_no')").await.is_err());
+        assert!(engine.execute_sql("INSERT INTO orders VALUES (NEXTVAL('missing'), 'x')").await.is_err());
+        assert!(engine.create_sequence("order_no", 1, 1).await.is_err());
+        assert!(engine.create_sequence("zero", 1, 0).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_row_id_lookup() {
+        let engine = DatabaseEngine::in_memory();
+        engine.execute_sql("CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT, parent UUID)").await.unwrap();
+        let first = engine.insert("notes", HashMap::from([
+            ("id".to_string(), Value::Integer(1)),
+            ("body".to_string(), Value::Text("hello".to_string())),
+        ])).await.unwrap();
+        let second = engine.insert("notes", HashMap::from([
+            ("id".to_string(), Value::Integer(2)),
+            ("parent".to_string(), Value::Uuid(first)),
+        ])).await.unwrap();
+
+        let row = engine.get_row("notes", first).await.unwrap().unwrap();
+        assert_eq!(row.get_text("body"), Some("hello"));
+        assert!(engine.get_row("notes", uuid::Uuid::new_v4()).await.unwrap().is_none());
+
+        let query = QueryBuilder::select("notes")
+            .where_condition(crate::types::ID_COLUMN, ComparisonOperator::Equal, Value::Uuid(second))
+            .with_row_id()
+            .build();
+        let rows = engine.query(query).await.unwrap().rows;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].get("_id"), Some(&Value::Uuid(second)));
+
+        // SQL 中行 ID 与 UUID 列都可以用文本字面量比较
+        let results = engine.execute_sql(&format!(
+            "SELECT *, _id AS row_id FROM notes WHERE _id = '{}'; SELECT COUNT(*) FROM notes WHERE parent = '{}'",
+            first, first
+        )).await.unwrap();
+        assert_eq!(results[0].rows[0].get("row_id"), Some(&Value::Uuid(first)));
+        assert_eq!(results[1].count, Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_analyze_collects_stats() {
+        let engine = DatabaseEngine::in_memory();
+        engine.execute_sql("CREATE TABLE cities (id INTEGER PRIMARY KEY, name TEXT COLLATE NOCASE, population INTEGER)").await.unwrap();
+        engine.execute_sql(
+            "INSERT INTO cities (id, name, population) VALUES (1, 'Paris', 2100); \
+             INSERT INTO cities (id, name, population) VALUES (2, 'PARIS', 900); \
+             INSERT INTO cities (id, name) VALUES (3, 'Oslo')"
+        ).await.unwrap();
+        assert!(engine.table_stats("cities").await.unwrap().is_none());
+
+        engine.execute_sql("ANALYZE cities").await.unwrap();
+        let stats = engine.table_stats("cities").await.unwrap().unwrap();
+        assert_eq!(stats.row_count, 3);
+        assert_eq!(stats.column("name").unwrap().distinct_count, 2);
+        let population = stats.column("population").unwrap();
+        assert_eq!((population.min.clone(), population.max.clone()), (Some(Value::Integer(900)), Some(Value::Integer(2100))));
+        assert_eq!(population.null_fraction(stats.row_count), 1.0 / 3.0);
+
+        engine.execute_sql("ANALYZE").await.unwrap();
+        assert!(engine.execute_sql("ANALYZE missing").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_slow_query_log() {
+        let engine = DatabaseEngine::in_memory();
+        engine.execute_sql("CREATE TABLE events (id INTEGER PRIMARY KEY, kind TEXT)").await.unwrap();
+        engine.execute_sql("INSERT INTO events (id, kind) VALUES (1, 'click'), (2, 'view'), (3, 'click')").await.unwrap();
+        engine.execute_sql("SELECT * FROM events").await.unwrap();
+        assert!(engine.slow_queries().is_empty());
+
+        // 阈值为零时记录所有查询
+        engine.set_slow_query_threshold(Some(Duration::ZERO));
+        engine.execute_sql("SELECT * FROM events WHERE kind = 'click' ORDER BY id DESC LIMIT 5").await.unwrap();
+        engine.execute_sql("DELETE FROM events WHERE id = 2").await.unwrap();
+        let entries = engine.slow_queries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].shape, "SELECT * FROM events WHERE kind = ? ORDER BY id DESC LIMIT ?");
+        assert_eq!((entries[0].rows_scanned, entries[0].rows_returned), (3, 2));
+        assert_eq!(entries[1].shape, "DELETE FROM events WHERE id = ?");
+        assert_eq!((entries[1].rows_scanned, entries[1].rows_returned), (3, 1));
+
+        engine.set_slow_query_threshold(Some(Duration::from_secs(60)));
+        engine.execute_sql("SELECT COUNT(*) FROM events").await.unwrap();
+        assert_eq!(engine.slow_queries().len(), 2);
+        engine.clear_slow_queries();
+        assert!(engine.slow_queries().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_bounded_update_and_delete() {
+        let engine = DatabaseEngine::in_memory();
+        let results = engine.execute_sql("
+            CREATE TABLE events (id INTEGER PRIMARY KEY, kind TEXT, seen BOOLEAN);
+            INSERT INTO events (id, kind, seen) VALUES (5, 'a', FALSE), (3, 'b', FALSE), (1, 'a', FALSE), (4, 'a', FALSE), (2, 'b', FALSE);
+            UPDATE events SET seen = TRUE WHERE kind = 'a' ORDER BY id DESC LIMIT 2;
+            DELETE FROM events ORDER BY id LIMIT 2;
+            SELECT * FROM events ORDER BY id;
+        ").await.unwrap();
+
+        assert_eq!(results[1].affected_rows, 2);
+        assert_eq!(results[2].affected_rows, 2);
+        let remaining: Vec<_> = results[3].rows.iter()
+            .map(|row| (row.get_integer("id").unwrap(), row.get_boolean("seen").unwrap()))
+            .collect();
+        assert_eq!(remaining, vec![(3, false), (4, true), (5, true)]);
+
+        // 返回被删除行的 ID，可以直接用于后续操作
+        let last = engine.query(QueryBuilder::select("events").where_condition("id", ComparisonOperator::Equal, Value::Integer(5)).build()).await.unwrap();
+        let deleted = engine.delete_bounded("events", Vec::new(), vec![OrderBy::new("id", false)], Some(1)).await.unwrap();
+        assert_eq!(deleted, vec![last.rows[0].id]);
+        assert_eq!(results[2].affected_ids.len(), 2);
+        assert!(engine.execute_sql("SELECT * FROM events WHERE id = 5").await.unwrap()[0].rows.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_does_not_modify_or_log() {
+        let engine = DatabaseEngine::in_memory();
+        engine.execute_sql("
+            CREATE TABLE events (id INTEGER PRIMARY KEY, kind TEXT);
+            INSERT INTO events VALUES (1, 'a'), (2, 'b'), (3, 'a'), (4, 'a');
+        ").await.unwrap();
+        let mut stream = engine.subscribe_log(engine.last_log_id().await).await.unwrap();
+
+        let results = engine.execute_sql("
+            DELETE FROM events WHERE kind = 'a' DRY RUN;
+            UPDATE events SET kind = 'c' WHERE kind = 'a' ORDER BY id DESC LIMIT 2 DRY RUN;
+            SELECT COUNT(*) FROM events WHERE kind = 'a';
+        ").await.unwrap();
+        assert!(results[0].dry_run);
+        assert_eq!(results[0].affected_rows, 3);
+        assert_eq!(results[0].affected_ids.len(), 3);
+        let ids: Vec<_> = results[1].rows.iter().filter_map(|row| row.get_integer("id")).collect();
+        assert_eq!(ids, vec![3, 4]);
+        assert_eq!(results[2].count, Some(3));
+
+        // 试运行没有写日志，订阅者收到的第一条是随后真正执行的删除
+        engine.execute_sql("DELETE FROM events WHERE id = 2").await.unwrap();
+        let entry = stream.recv().await.unwrap();
+        assert!(matches!(entry.operation, StorageOperation::DeleteWhere { .. }));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_writes_log_in_apply_order() {
+        let engine = DatabaseEngine::in_memory();
+        engine.execute_sql("CREATE TABLE counters (id INTEGER PRIMARY KEY, n INTEGER); INSERT INTO counters VALUES (1, 0);").await.unwrap();
+        let mut stream = engine.subscribe_log(engine.last_log_id().await).await.unwrap();
+
+        // 写操作的 future 可以交给多线程运行时并发执行
+        let engine = Arc::new(engine);
+        let tasks: Vec<_> = (0..8).map(|task| {
+            let engine = Arc::clone(&engine);
+            tokio::spawn(async move {
+                for i in 0..25 {
+                    let updates = HashMap::from([("n".to_string(), Value::Integer(task * 100 + i))]);
+                    engine.update("counters", vec![("id".to_string(), ComparisonOperator::Equal, Value::Integer(1))], updates).await.unwrap();
+                }
+            })
+        }).collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        let mut last = None;
+        for expected_id in 1..=200 {
+            let entry = stream.recv().await.unwrap();
+            assert_eq!(entry.id, expected_id);
+            if let StorageOperation::UpdateWhere { data, .. } = entry.operation {
+                last = data.get("n").cloned();
+            }
+        }
+        let row = engine.query(QueryBuilder::select("counters").build()).await.unwrap().rows.remove(0);
+        assert_eq!(row.get("n").cloned(), last);
+    }
+
+    #[tokio::test]
+    async fn test_temp_tables_skip_log_and_snapshot() {
+        let dir = std::env::temp_dir().join(format!("simple-db-temp-{}", uuid::Uuid::new_v4()));
+        let engine = DatabaseEngine::open(&dir).await.unwrap();
+        let mut stream = engine.subscribe_log(engine.last_log_id().await).await.unwrap();
+        let schema = Schema::new(vec![ColumnDefinition::new("id", DataType::Integer, true)]);
+        engine.create_table("kept", schema.clone()).await.unwrap();
+        engine.create_temp_table("scratch", schema).await.unwrap();
+        assert!(engine.is_temp_table("scratch").await && !engine.is_temp_table("kept").await);
+        engine.execute_sql("INSERT INTO scratch (id) VALUES (1), (2); INSERT INTO kept (id) VALUES (1);").await.unwrap();
+        assert_eq!(engine.get_table_info("scratch").await.unwrap().row_count, 2);
+
+        // 临时表上的操作不分配日志ID
+        assert_eq!(engine.last_log_id().await, 2);
+        for _ in 0..2 {
+            assert_eq!(stream.recv().await.unwrap().operation.table_name(), Some("kept"));
+        }
+
+        engine.save_to_disk().await.unwrap();
+        engine.close().await.unwrap();
+        let engine = DatabaseEngine::open(&dir).await.unwrap();
+        assert!(engine.get_table_info("scratch").await.is_err());
+        assert_eq!(engine.get_table_info("kept").await.unwrap().row_count, 1);
+
+        // 删除临时表也不写日志
+        engine.create_temp_table("scratch", Schema::new(Vec::new())).await.unwrap();
+        engine.drop_table("scratch").await.unwrap();
+        assert!(!engine.is_temp_table("scratch").await);
+        assert_eq!(engine.last_log_id().await, 2);
+        engine.close().await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_table_options() {
+        let dir = std::env::temp_dir().join(format!("simple-db-table-options-{}", uuid::Uuid::new_v4()));
+        let engine = DatabaseEngine::open(&dir).await.unwrap();
+        engine.execute_sql("
+            CREATE TABLE cache (id INTEGER PRIMARY KEY, value TEXT) WITH (UNLOGGED, COMPRESSION LZ4, MAX_ROWS 2);
+            CREATE TABLE sessions (id INTEGER PRIMARY KEY) WITH (TTL 60);
+            INSERT INTO cache VALUES (1, 'a'), (2, 'b');
+            INSERT INTO sessions VALUES (1), (2);
+        ").await.unwrap();
+        assert_eq!(
+            engine.schema_sql(Some("cache")).await.unwrap(),
+            "CREATE TABLE cache (\n  id INTEGER PRIMARY KEY,\n  value TEXT\n) WITH (UNLOGGED, COMPRESSION LZ4, MAX_ROWS 2);\n"
+        );
+        let err = engine.execute_sql("INSERT INTO cache VALUES (3, 'c')").await.unwrap_err();
+        assert!(matches!(err, DatabaseError::TableFull { limit: 2, .. }));
+
+        // 不写日志的表上的插入不分配日志ID
+        assert_eq!(engine.last_log_id().await, 3);
+
+        // 最后修改早于存活时间的行不可见，purge_expired 把它删除
+        {
+            let storage = engine.storage.read().await;
+            let id = storage.get_table("sessions").unwrap().unwrap().all_rows().unwrap()[0].id;
+            storage.update_row_at("sessions", id, HashMap::new(), chrono::Utc::now() - chrono::Duration::hours(1)).unwrap();
+        }
+        let count = engine.execute_sql("SELECT COUNT(*) FROM sessions").await.unwrap().remove(0).count;
+        assert_eq!(count, Some(1));
+        assert_eq!(engine.purge_expired("sessions").await.unwrap(), 1);
+        assert_eq!(engine.get_table_info("sessions").await.unwrap().row_count, 1);
+
+        // 压缩的表文件带标记；快照之后对不写日志的表的修改在崩溃后丢失
+        engine.save_to_disk().await.unwrap();
+        let tables_dir = dir.join("tables");
+        let compressed = std::fs::read_dir(&tables_dir).unwrap()
+            .filter(|entry| std::fs::read(entry.as_ref().unwrap().path()).unwrap().starts_with(b"simple-db-lz4"))
+            .count();
+        assert_eq!(compressed, 1);
+        engine.execute_sql("DELETE FROM cache WHERE id = 1; CREATE TABLE other (id INTEGER);").await.unwrap();
+        drop(engine);
 
-impl<'a> Transaction<'a> {
-    fn new(engine: &'a DatabaseEngine) -> Self {
-        Self {
-            engine,
-            operations: Vec::new(),
+        let engine = DatabaseEngine::open(&dir).await.unwrap();
+        assert_eq!(engine.get_table_info("cache").await.unwrap().row_count, 2);
+        assert!(engine.get_table_info("other").await.is_ok());
+        let before = engine.last_log_id().await;
+        engine.execute_sql("DELETE FROM cache WHERE id = 2").await.unwrap();
+        assert_eq!(engine.last_log_id().await, before);
+        engine.close().await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_create_table_as() {
+        let dir = std::env::temp_dir().join(format!("simple-db-ctas-{}", uuid::Uuid::new_v4()));
+        let engine = DatabaseEngine::open(&dir).await.unwrap();
+        engine.execute_sql("
+            CREATE TABLE orders (id INTEGER PRIMARY KEY, price FLOAT NOT NULL, qty INTEGER);
+            INSERT INTO orders VALUES (1, 2.5, 4), (2, 10.0, 1), (3, 1.0, NULL);
+        ").await.unwrap();
+        let before = engine.last_log_id().await;
+
+        // 表结构取自结果列，计算列的类型按值推断；建表与写入只记录一条日志
+        let results = engine.execute_sql("CREATE TABLE big AS SELECT *, price * qty AS total FROM orders WHERE price > 2").await.unwrap();
+        assert_eq!(results[0].affected_rows, 2);
+        assert_eq!(engine.last_log_id().await, before + 1);
+        let schema = engine.table_schema("big").await.unwrap();
+        let columns: Vec<(&str, &DataType, bool)> = schema.columns.iter()
+            .map(|column| (column.name.as_str(), &column.data_type, column.nullable))
+            .collect();
+        assert_eq!(columns, vec![
+            ("id", &DataType::Integer, true), ("price", &DataType::Float, true),
+            ("qty", &DataType::Integer, true), ("total", &DataType::Float, true),
+        ]);
+
+        let err = engine.create_table_as("big", Query::select("orders")).await.unwrap_err();
+        assert!(matches!(err, DatabaseError::TableExists(_)));
+        assert!(engine.create_table_as("counted", Query::count("orders")).await.is_err());
+        drop(engine);
+
+        // 重放日志得到相同的行，不再执行查询
+        let engine = DatabaseEngine::open(&dir).await.unwrap();
+        let rows = engine.execute_sql("SELECT * FROM big ORDER BY id").await.unwrap().remove(0).rows;
+        let totals: Vec<(i64, Value)> = rows.iter().map(|row| (row.get_integer("id").unwrap(), row.get("total").unwrap().clone())).collect();
+        assert_eq!(totals, vec![(1, Value::Float(10.0)), (2, Value::Float(10.0))]);
+        engine.close().await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_truncate_table() {
+        let dir = std::env::temp_dir().join(format!("simple-db-truncate-{}", uuid::Uuid::new_v4()));
+        let engine = DatabaseEngine::open(&dir).await.unwrap();
+        engine.execute_sql("
+            CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT) PARTITION BY HASH (id) BUCKETS 4;
+            INSERT INTO items VALUES (1, 'a'), (2, 'b'), (3, 'c');
+        ").await.unwrap();
+        let before = engine.last_log_id().await;
+
+        // 清空后唯一键不再占用，只记录一条日志
+        let results = engine.execute_sql("TRUNCATE TABLE items").await.unwrap();
+        assert_eq!(results[0].affected_rows, 3);
+        assert_eq!(engine.last_log_id().await, before + 1);
+        assert_eq!(engine.get_table_info("items").await.unwrap().row_count, 0);
+        engine.execute_sql("INSERT INTO items VALUES (1, 'again')").await.unwrap();
+        assert_eq!(engine.truncate_table("items").await.unwrap(), 1);
+        assert!(matches!(engine.truncate_table("missing").await, Err(DatabaseError::TableNotFound(_))));
+        engine.execute_sql("INSERT INTO items VALUES (2, 'kept')").await.unwrap();
+        drop(engine);
+
+        let engine = DatabaseEngine::open(&dir).await.unwrap();
+        let rows = engine.execute_sql("SELECT * FROM items").await.unwrap().remove(0).rows;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].get_text("name"), Some("kept"));
+        engine.close().await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_check_integrity() {
+        use crate::integrity::IssueKind;
+
+        let dir = std::env::temp_dir().join(format!("simple-db-integrity-{}", uuid::Uuid::new_v4()));
+        let engine = DatabaseEngine::open(&dir).await.unwrap();
+        engine.execute_sql("
+            CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT UNIQUE);
+            INSERT INTO users VALUES (1, 'a@example.com'), (2, 'b@example.com');
+        ").await.unwrap();
+        engine.save_to_disk().await.unwrap();
+        engine.execute_sql("INSERT INTO users VALUES (3, 'c@example.com')").await.unwrap();
+        let report = engine.check_integrity().await.unwrap();
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!((report.tables_checked, report.rows_checked, report.files_checked), (1, 3, 2));
+        assert!(report.log_entries_checked >= 1);
+
+        // 绕过约束写入重复的行，并在日志末尾写入损坏的条目
+        {
+            let storage = engine.storage.read().await;
+            let mut table = storage.get_table_mut("users").unwrap().unwrap();
+            let duplicate = table.rows.slots()[0].clone();
+            table.rows.push(duplicate);
         }
+        let segment = engine.disk_storage.lock().await.log_segments().unwrap().pop().unwrap();
+        let mut log = std::fs::OpenOptions::new().append(true).open(&segment).unwrap();
+        std::io::Write::write_all(&mut log, b"00000000 {\"broken\": true}\n").unwrap();
+
+        let report = engine.check_integrity().await.unwrap();
+        assert_eq!(report.issues_of(IssueKind::Index).count(), 1);
+        assert_eq!(report.issues_of(IssueKind::Constraint).count(), 2);
+        assert_eq!(report.issues_of(IssueKind::Log).count(), 1);
+        assert_eq!(report.issues_of(IssueKind::Snapshot).count(), 0);
+        drop(engine);
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 
-    /// 在事务中创建表
-    pub fn create_table(&mut self, name: &str, schema: Schema) -> Result<()> {
-        self.operations.push(StorageOperation::Create {
-            table: name.to_string(),
-            schema,
-        });
-        Ok(())
+    #[tokio::test]
+    async fn test_insert_from_query() {
+        let engine = DatabaseEngine::in_memory();
+        engine.execute_sql("
+            CREATE TABLE events (id INTEGER PRIMARY KEY, kind TEXT, day TEXT);
+            CREATE TABLE archive (event_id INTEGER UNIQUE, label TEXT NOT NULL, day DATE);
+            INSERT INTO events VALUES (1, 'click', '2024-01-01'), (2, 'view', '2024-01-02'), (3, NULL, '2024-01-03');
+        ").await.unwrap();
+
+        // 按对应关系取列，计算列转换数据，文本按目标列类型转换为日期
+        let query = QueryBuilder::select("events")
+            .where_condition("id", ComparisonOperator::LessThan, Value::Integer(3))
+            .select_expr("label", Expr::function("UPPER", vec![Expr::column("kind")]))
+            .build();
+        let ids = engine.insert_from("archive", query, &[("event_id", "id"), ("label", "label"), ("day", "day")]).await.unwrap();
+        assert_eq!(ids.len(), 2);
+        let rows = engine.execute_sql("SELECT * FROM archive ORDER BY event_id").await.unwrap().remove(0).rows;
+        assert_eq!(rows[1].get_text("label"), Some("VIEW"));
+        assert_eq!(rows[1].get("day"), Some(&Value::Date(chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap())));
+
+        // 目标表的约束整批校验：第 3 行的 label 为 NULL，整批都不写入
+        let err = engine.execute_sql("INSERT INTO archive SELECT * FROM events WHERE id > 1").await.unwrap_err();
+        assert!(err.is_constraint_violation());
+        assert_eq!(engine.get_table_info("archive").await.unwrap().row_count, 2);
+        assert!(engine.execute_sql("INSERT INTO archive (event_id) SELECT * FROM events").await.is_err());
+        let err = engine.insert_from("archive", Query::select("events"), &[("missing", "id")]).await.unwrap_err();
+        assert!(matches!(err, DatabaseError::ColumnNotFound(_)));
     }
 
-    /// 在事务中插入数据
-    pub fn insert(&mut self, table_name: &str, data: HashMap<String, Value>) -> Result<uuid::Uuid> {
-        let mut row = Row::new();
-        for (column, value) in data {
-            row.set(column, value);
+    #[tokio::test]
+    async fn test_audit_log_records_mutations() {
+        use crate::audit::AuditRetention;
+
+        let engine = DatabaseEngine::in_memory();
+        engine.execute_sql("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT);").await.unwrap();
+        engine.create_temp_table("scratch", Schema::new(vec![ColumnDefinition::new("id", DataType::Integer, true)])).await.unwrap();
+        engine.enable_audit(AuditConfig::new("alice").retention(AuditRetention::default().max_rows(3))).await.unwrap();
+
+        engine.execute_sql("
+            INSERT INTO users VALUES (1, 'a'), (2, 'b');
+            UPDATE users SET name = 'c' WHERE id = 1;
+            DELETE FROM users WHERE id = 2;
+            INSERT INTO scratch VALUES (1);
+        ").await.unwrap();
+        engine.transaction(|tx| {
+            tx.insert("users", HashMap::from([("id".to_string(), Value::Integer(3))]))?;
+            Ok::<(), DatabaseError>(())
+        }).await.unwrap();
+
+        let audit = engine.query(QueryBuilder::select(AUDIT_TABLE).build()).await.unwrap();
+        let operations: Vec<_> = audit.rows.iter().filter_map(|row| row.get_text("operation")).collect();
+        assert_eq!(operations, vec!["INSERT", "INSERT", "UPDATE", "DELETE", "INSERT"]);
+        assert!(audit.rows.iter().all(|row| row.get_text("actor") == Some("alice")));
+        let update = &audit.rows[2];
+        assert_eq!(update.get("before"), Some(&Value::Json(serde_json::json!({"id": 1, "name": "a"}))));
+        assert_eq!(update.get("after"), Some(&Value::Json(serde_json::json!({"id": 1, "name": "c"}))));
+        assert_eq!(audit.rows[3].get("after"), Some(&Value::Null));
+
+        // 保留策略删除最旧的记录，删除本身不产生审计记录
+        assert_eq!(engine.apply_audit_retention().await.unwrap(), 2);
+        assert_eq!(engine.get_table_info(AUDIT_TABLE).await.unwrap().row_count, 3);
+
+        engine.disable_audit();
+        engine.execute_sql("DELETE FROM users").await.unwrap();
+        assert_eq!(engine.get_table_info(AUDIT_TABLE).await.unwrap().row_count, 3);
+    }
+
+    #[tokio::test]
+    async fn test_session_user_and_database() {
+        let mut engine = DatabaseEngine::in_memory();
+        let mut bob = engine.session("bob").with_timeout(Duration::from_secs(5));
+        let carol = engine.session("carol");
+        engine.execute_sql_in(&bob, "CREATE TABLE users (id INTEGER PRIMARY KEY)").await.unwrap();
+        engine.enable_audit(AuditConfig::new("system")).await.unwrap();
+
+        engine.execute_sql_in(&bob, "INSERT INTO users VALUES (1)").await.unwrap();
+        engine.execute_sql("INSERT INTO users VALUES (2)").await.unwrap();
+        let results = engine.execute_sql_in(&bob, "SELECT * FROM users").await.unwrap();
+        assert_eq!(results[0].rows.len(), 2);
+        let audit = engine.query(QueryBuilder::select(AUDIT_TABLE).build()).await.unwrap();
+        let actors: Vec<_> = audit.rows.iter().filter_map(|row| row.get_text("actor")).collect();
+        assert_eq!(actors, vec!["bob", "system"]);
+
+        // 切换数据库后，仍指向原数据库的会话不能执行语句
+        engine.create_database("other").unwrap();
+        engine.use_database_in(&mut bob, "other").await.unwrap();
+        assert_eq!(bob.database(), "other");
+        assert!(engine.execute_sql_in(&carol, "SELECT * FROM users").await.is_err());
+        assert!(engine.execute_sql_in(&bob, "CREATE TABLE t (id INTEGER)").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_backend_persists_snapshot_and_log() {
+        use crate::backend::MemoryBackend;
+
+        let backend = MemoryBackend::new();
+        let engine = DatabaseEngine::with_backend(Arc::new(backend.clone())).await.unwrap();
+        engine.execute_sql("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT); INSERT INTO users VALUES (1, 'a');").await.unwrap();
+        engine.save_to_disk().await.unwrap();
+        engine.execute_sql("INSERT INTO users VALUES (2, 'b')").await.unwrap();
+        assert_eq!(backend.log_len(), 1);
+        assert!(engine.create_database("other").is_err());
+        assert!(engine.restore("backup").await.is_err());
+        drop(engine);
+
+        // 从后端恢复：快照加上快照之后的日志
+        let engine = DatabaseEngine::with_backend(Arc::new(backend.clone())).await.unwrap();
+        assert_eq!(engine.get_table_info("users").await.unwrap().row_count, 2);
+        engine.execute_sql("INSERT INTO users VALUES (3, 'c')").await.unwrap();
+        let ids: Vec<u64> = backend.read_log(0).unwrap().iter().map(|entry| entry.id).collect();
+        assert_eq!(ids.len(), 2);
+        assert!(ids[0] < ids[1]);
+    }
+
+    #[tokio::test]
+    async fn test_load_rows_in_one_log_entry() {
+        let engine = DatabaseEngine::in_memory();
+        let mut stream = engine.subscribe_log(0).await.unwrap();
+        engine.execute_sql("CREATE TABLE items (id INTEGER PRIMARY KEY, label TEXT DEFAULT 'none')").await.unwrap();
+        let row = |id: i64| {
+            let mut row = Row::new();
+            row.set("id", Value::Integer(id));
+            row
+        };
+
+        let rows: Vec<Row> = (0..5_000).map(row).collect();
+        let first_id = rows[0].id;
+        assert_eq!(engine.load_rows("items", rows).await.unwrap(), 5_000);
+        let loaded = engine.get_row("items", first_id).await.unwrap().unwrap();
+        assert_eq!(loaded.get("label"), Some(&Value::Text("none".to_string())));
+
+        // 与已有数据或批内重复时整批不生效
+        assert!(engine.load_rows("items", [row(5_000), row(0)]).await.is_err());
+        let mut duplicate = row(5_001);
+        duplicate.id = first_id;
+        assert!(engine.load_rows("items", std::iter::once(duplicate)).await.is_err());
+        assert_eq!(engine.get_table_info("items").await.unwrap().row_count, 5_000);
+
+        stream.recv().await.unwrap();
+        match stream.recv().await.unwrap().operation {
+            StorageOperation::BatchInsert { rows, .. } => assert_eq!(rows.len(), 5_000),
+            other => panic!("unexpected operation: {:?}", other),
         }
+        assert_eq!(engine.last_log_id().await, 2);
+    }
 
-        self.operations.push(StorageOperation::Insert {
-            table: table_name.to_string(),
-            row: row.clone(),
-        });
+    #[tokio::test]
+    async fn test_import_skips_invalid_rows_into_reject_file() {
+        let engine = DatabaseEngine::in_memory();
+        engine.execute_sql("CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT UNIQUE, age INTEGER)").await.unwrap();
+        engine.execute_sql("INSERT INTO users (id, email, age) VALUES (1, 'a@x', 30)").await.unwrap();
 
-        Ok(row.id)
+        let dir = std::env::temp_dir().join(format!("simple-db-import-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let csv = dir.join("users.csv");
+        std::fs::write(&csv, "id,email,age\n2,b@x,20\n3,c@x,old\n4,a@x,40\n5,e@x,50\n5,f@x,60\n").unwrap();
+        let path = csv.to_str().unwrap();
+        let copy = CopyOptions::new(import::CopyFormat::Csv).header(true);
+
+        // 默认遇到第一个不合格的行即失败
+        let error = engine.import_csv("users", path, &copy, &ImportOptions::new()).await.unwrap_err();
+        assert!(error.to_string().contains("第 3 行"));
+        assert_eq!(engine.get_table_info("users").await.unwrap().row_count, 1);
+
+        let rejects = dir.join("rejects.json");
+        let report = engine.import_csv("users", path, &copy, &ImportOptions::new().reject_file(&rejects)).await.unwrap();
+        assert_eq!((report.loaded, report.rejected), (2, 3));
+        let lines: Vec<usize> = report.errors.iter().map(|rejected| rejected.line).collect();
+        assert_eq!(lines, vec![3, 4, 6]);
+        assert_eq!(engine.get_table_info("users").await.unwrap().row_count, 3);
+
+        let written: Vec<serde_json::Value> = std::fs::read_to_string(&rejects).unwrap().lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(written.len(), 3);
+        assert_eq!((written[1]["line"].as_u64(), written[1]["record"].as_str()), (Some(4), Some("4,a@x,40")));
+        assert!(written[1]["error"].as_str().unwrap().contains("唯一"));
+
+        let json = dir.join("users.json");
+        std::fs::write(&json, "{\"id\": 7, \"email\": \"g@x\"}\nnot json\n").unwrap();
+        let report = engine.import_json_with("users", json.to_str().unwrap(), &ImportOptions::new().skip_invalid(true)).await.unwrap();
+        assert_eq!((report.loaded, report.errors[0].line), (1, 2));
+        assert!(engine.import_json("users", json.to_str().unwrap()).await.is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 
-    /// 在事务中更新数据
-    pub fn update(&mut self, table_name: &str, id: uuid::Uuid, updates: HashMap<String, Value>) -> Result<()> {
-        let operation_data = updates.iter()
-            .map(|(k, v)| (k.clone(), v.clone()))
+    #[tokio::test]
+    async fn test_import_csv_infers_schema() {
+        let engine = DatabaseEngine::in_memory();
+        let dir = std::env::temp_dir().join(format!("simple-db-infer-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("sales.csv");
+        std::fs::write(&path, "region,units,price,shipped,day\neast,3,9.5,true,2024-03-01\nwest,,12,false,2024-03-02\n").unwrap();
+
+        let (table, report) = engine.import_csv_infer(path.to_str().unwrap()).await.unwrap();
+        assert_eq!((table.as_str(), report.loaded, report.rejected), ("sales", 2, 0));
+        let types: Vec<DataType> = engine.get_table_info("sales").await.unwrap().schema.columns.into_iter()
+            .map(|column| column.data_type)
             .collect();
-        self.operations.push(StorageOperation::Update {
-            table: table_name.to_string(),
-            id: id.to_string(),
-            data: operation_data,
-        });
-        Ok(())
+        assert_eq!(types, vec![DataType::Text, DataType::Integer, DataType::Float, DataType::Boolean, DataType::Date]);
+        let result = engine.execute_sql("SELECT * FROM sales WHERE price > 10").await.unwrap();
+        assert_eq!(result[0].rows[0].get("region"), Some(&Value::Text("west".to_string())));
+
+        assert!(matches!(engine.import_csv_infer(path.to_str().unwrap()).await, Err(DatabaseError::TableExists(_))));
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 
-    /// 在事务中删除数据
-    pub fn delete(&mut self, table_name: &str, id: uuid::Uuid) -> Result<()> {
-        self.operations.push(StorageOperation::Delete {
-            table: table_name.to_string(),
-            id: id.to_string(),
-        });
-        Ok(())
+    #[tokio::test]
+    async fn test_rename_and_copy_table_replay_from_log() {
+        let dir = std::env::temp_dir().join(format!("simple-db-rename-{}", uuid::Uuid::new_v4()));
+        let engine = DatabaseEngine::open(&dir).await.unwrap();
+        engine.execute_sql("
+            CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT);
+            INSERT INTO users (id, name) VALUES (1, 'a'), (2, 'b');
+        ").await.unwrap();
+
+        // 先建好新表再替换旧表
+        engine.copy_table("users", "users_next", true).await.unwrap();
+        engine.copy_table("users", "users_empty", false).await.unwrap();
+        engine.execute_sql("INSERT INTO users_next (id, name) VALUES (3, 'c')").await.unwrap();
+        engine.rename_table("users", "users_old").await.unwrap();
+        engine.rename_table("users_next", "users").await.unwrap();
+        assert!(matches!(engine.rename_table("users", "users_old").await, Err(DatabaseError::TableExists(_))));
+        assert!(matches!(engine.copy_table("missing", "x", true).await, Err(DatabaseError::TableNotFound(_))));
+
+        engine.create_temp_table("scratch", Schema::new(Vec::new())).await.unwrap();
+        engine.copy_table("scratch", "scratch_copy", false).await.unwrap();
+        engine.rename_table("scratch", "scratch_renamed").await.unwrap();
+        assert!(engine.is_temp_table("scratch_copy").await && engine.is_temp_table("scratch_renamed").await);
+        let last_log_id = engine.last_log_id().await;
+
+        // 不经 close 释放引擎，重新打开时只能从日志重放
+        drop(engine);
+        let engine = DatabaseEngine::open(&dir).await.unwrap();
+        assert_eq!(engine.last_log_id().await, last_log_id);
+        let row_count = |table: &'static str| {
+            let engine = &engine;
+            async move { engine.get_table_info(table).await.unwrap().row_count }
+        };
+        assert_eq!((row_count("users").await, row_count("users_old").await, row_count("users_empty").await), (3, 2, 0));
+        assert!(engine.get_table_info("users_next").await.is_err());
+        assert!(engine.get_table_info("scratch_renamed").await.is_err());
+        engine.close().await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 
-    /// 提交事务
-    pub async fn commit(self) -> Result<()> {
-        let mut storage = self.engine.storage.write().await;
+    #[tokio::test]
+    async fn test_in_memory_engine_never_touches_disk() {
+        let mut engine = DatabaseEngine::in_memory();
+        engine.set_auto_save(true);
+        assert!(engine.is_in_memory());
 
-        // 执行所有操作
-        for operation in self.operations {
-            self.engine.apply_log_operation(&mut storage, operation.clone())?;
+        let name = format!("mem_{}", uuid::Uuid::new_v4().simple());
+        engine.create_database(&name).unwrap();
+        engine.use_database(&name).await.unwrap();
+        engine.execute_sql("CREATE TABLE t (id INTEGER PRIMARY KEY); INSERT INTO t (id) VALUES (1);").await.unwrap();
+        engine.save_to_disk().await.unwrap();
+        assert!(!std::path::Path::new(&engine.database_dir(&name).unwrap()).exists());
+        assert_eq!(engine.last_log_id().await, 0);
 
-            // 记录到磁盘
-            if self.engine.auto_save {
-                self.engine.disk_storage.lock().unwrap().write_log(operation)?;
+        assert!(engine.restore("backup").await.is_err());
+        assert!(engine.set_spill_threshold("t", Some(1024)).await.is_err());
+        assert_eq!(engine.get_stats().await.unwrap().total_rows, 1);
+    }
+
+    #[tokio::test]
+    async fn test_open_locks_directory_and_recovers_unclean_shutdown() {
+        let dir = std::env::temp_dir().join(format!("simple-db-open-{}", uuid::Uuid::new_v4()));
+        let engine = DatabaseEngine::open(&dir).await.unwrap();
+        engine.execute_sql("CREATE TABLE kv (k TEXT PRIMARY KEY, v INTEGER); INSERT INTO kv (k, v) VALUES ('a', 1);").await.unwrap();
+        assert!(DatabaseEngine::open(&dir).await.is_err());
+        engine.close().await.unwrap();
+        assert!(std::fs::read_to_string(dir.join(LOCK_FILE)).unwrap().is_empty());
+
+        // 没有 close 就释放：锁随之释放，下次打开时截断日志中写了一半的条目
+        let engine = DatabaseEngine::open(&dir).await.unwrap();
+        engine.execute_sql("INSERT INTO kv (k, v) VALUES ('b', 2)").await.unwrap();
+        engine.flush_log().await.unwrap();
+        let segment = engine.disk_storage.lock().await.log_segments().unwrap().pop().unwrap();
+        drop(engine);
+        std::fs::OpenOptions::new().append(true).open(&segment).unwrap().write_all(b"{\"id\":").unwrap();
+
+        let engine = DatabaseEngine::open(&dir).await.unwrap();
+        assert_eq!(engine.get_table_info("kv").await.unwrap().row_count, 2);
+        engine.close().await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_read_only_survives_restart() {
+        let dir = std::env::temp_dir().join(format!("simple-db-read-only-{}", uuid::Uuid::new_v4()));
+        let engine = DatabaseEngine::open(&dir).await.unwrap();
+        engine.execute_sql("CREATE TABLE kv (k TEXT PRIMARY KEY)").await.unwrap();
+        engine.set_read_only(true).await.unwrap();
+        engine.close().await.unwrap();
+
+        // 重新打开的从库仍然拒绝写入，提升后才恢复
+        let engine = DatabaseEngine::open(&dir).await.unwrap();
+        assert!(engine.is_read_only());
+        assert!(matches!(engine.execute_sql("INSERT INTO kv (k) VALUES ('a')").await, Err(DatabaseError::ReadOnly)));
+        engine.promote().await.unwrap();
+        engine.close().await.unwrap();
+
+        let engine = DatabaseEngine::open(&dir).await.unwrap();
+        assert!(!engine.is_read_only());
+        engine.execute_sql("INSERT INTO kv (k) VALUES ('a')").await.unwrap();
+        engine.close().await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_hot_backup_does_not_block_writers() {
+        /// 收到第一个文件时插入新行；备份期间仍持有锁的话会一直等待
+        struct WritingSink {
+            engine: Arc<DatabaseEngine>,
+            files: HashMap<String, Vec<u8>>,
+        }
+
+        impl BackupSink for WritingSink {
+            fn write_file<'a>(&'a mut self, name: &'a str, data: &'a [u8]) -> crate::backup::BoxFuture<'a, Result<()>> {
+                Box::pin(async move {
+                    if self.files.is_empty() {
+                        self.engine.execute_sql("INSERT INTO items (id) VALUES (4)").await?;
+                    }
+                    self.files.insert(name.to_string(), data.to_vec());
+                    Ok(())
+                })
             }
         }
 
-        // 如果启用了自动保存，创建快照
-        if self.engine.auto_save {
-            self.engine.save_to_disk().await?;
+        let engine = DatabaseEngine::in_memory();
+        engine.execute_sql("CREATE TABLE items (id INTEGER PRIMARY KEY); INSERT INTO items (id) VALUES (1), (2), (3);").await.unwrap();
+        engine.set_validation_mode(ValidationMode::Strict).await.unwrap();
+        let engine = Arc::new(engine);
+
+        let mut sink = WritingSink { engine: Arc::clone(&engine), files: HashMap::new() };
+        tokio::time::timeout(Duration::from_secs(5), engine.backup_to(&mut sink)).await.unwrap().unwrap();
+        assert_eq!(engine.get_table_info("items").await.unwrap().row_count, 4);
+
+        // 备份是检查点时刻的状态，不包含备份期间插入的行
+        let snapshot = crate::storage::Snapshot::decode(&sink.files["snapshot.json"], "snapshot.json").unwrap();
+        assert_eq!(snapshot.table_files.len(), 1);
+        assert_eq!(snapshot.validation_mode, ValidationMode::Strict);
+        let table: Table = serde_json::from_slice(&sink.files[&format!("tables/{}", snapshot.table_files[0].file)]).unwrap();
+        assert_eq!(table.row_count(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_bulk_mutations_log_single_entry() {
+        let primary = DatabaseEngine::in_memory();
+        let mut stream = primary.subscribe_log(primary.last_log_id().await).await.unwrap();
+        primary.execute_sql("
+            CREATE TABLE jobs (id INTEGER PRIMARY KEY, state TEXT, due DATETIME);
+            INSERT INTO jobs (id, state, due) VALUES (1, 'new', '2020-01-01T00:00:00'), (2, 'new', '2020-01-01T00:00:00'),
+                (3, 'new', '2999-01-01T00:00:00'), (4, 'new', '2999-01-01T00:00:00'), (5, 'new', '2999-01-01T00:00:00');
+            UPDATE jobs SET state = 'queued' WHERE id > 1;
+            DELETE FROM jobs WHERE id >= 4;
+            UPDATE jobs SET state = 'late' WHERE AGE(due) > 0;
+            DELETE FROM jobs ORDER BY id LIMIT 1;
+        ").await.unwrap();
+
+        let mut entries = Vec::new();
+        while entries.len() < 7 {
+            entries.push(stream.recv().await.unwrap());
         }
+        let kinds: Vec<&str> = entries.iter().map(|entry| match &entry.operation {
+            StorageOperation::Create { .. } => "create",
+            StorageOperation::BatchInsert { .. } => "batch",
+            StorageOperation::UpdateWhere { .. } => "update_where",
+            StorageOperation::DeleteWhere { .. } => "delete_where",
+            StorageOperation::Update { .. } => "update",
+            StorageOperation::Delete { .. } => "delete",
+            _ => "other",
+        }).collect();
+        // 依赖 NOW() 的条件与带 LIMIT 的修改仍逐行记录
+        assert_eq!(kinds, vec!["create", "batch", "update_where", "delete_where", "update", "update", "delete"]);
 
-        Ok(())
+        let replica = DatabaseEngine::in_memory();
+        for entry in entries {
+            replica.apply_replicated(entry).await.unwrap();
+        }
+        let states = |result: &QueryResult| -> Vec<(i64, String)> {
+            result.rows.iter().map(|row| (row.get_integer("id").unwrap(), row.get_text("state").unwrap().to_string())).collect()
+        };
+        let expected = primary.execute_sql("SELECT * FROM jobs ORDER BY id").await.unwrap();
+        let replayed = replica.execute_sql("SELECT * FROM jobs ORDER BY id").await.unwrap();
+        assert_eq!(states(&replayed[0]), states(&expected[0]));
+        assert_eq!(states(&replayed[0]), vec![(2, "late".to_string()), (3, "queued".to_string())]);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::types::{ColumnDefinition, DataType};
+    #[tokio::test]
+    async fn test_null_three_valued_logic() {
+        let engine = DatabaseEngine::in_memory();
+        let results = engine.execute_sql("
+            CREATE TABLE items (id INTEGER PRIMARY KEY, qty INTEGER, name TEXT);
+            INSERT INTO items (id, qty, name) VALUES (1, 5, 'apple'), (2, NULL, 'pear'), (3, 8, NULL);
+            SELECT COUNT(*) FROM items WHERE qty = NULL;
+            SELECT COUNT(*) FROM items WHERE qty IS NULL;
+            SELECT COUNT(*) FROM items WHERE qty != 5;
+            SELECT COUNT(*) FROM items WHERE qty < 100;
+            SELECT COUNT(*) FROM items WHERE qty IN (5, NULL);
+            SELECT COUNT(*) FROM items WHERE qty IN (8);
+            SELECT COUNT(*) FROM items WHERE name LIKE '%';
+            SELECT COUNT(*) FROM items WHERE qty + 1 > 0;
+        ").await.unwrap();
+
+        let counts: Vec<_> = results[1..].iter().map(|result| result.count).collect();
+        assert_eq!(counts, vec![Some(0), Some(1), Some(1), Some(2), Some(1), Some(1), Some(2), Some(2)]);
+
+        // UNKNOWN 的行同样不会被 UPDATE 与 DELETE 选中
+        engine.execute_sql("DELETE FROM items WHERE qty != 5").await.unwrap();
+        let remaining = engine.execute_sql("SELECT * FROM items ORDER BY id").await.unwrap();
+        let ids: Vec<_> = remaining[0].rows.iter().filter_map(|row| row.get_integer("id")).collect();
+        assert_eq!(ids, vec![1, 2]);
+    }
 
     #[tokio::test]
-    async fn test_database_engine() {
-        let engine = DatabaseEngine::new();
+    async fn test_update_if_version() {
+        let engine = DatabaseEngine::in_memory();
 
-        // 创建表
         let schema = Schema::new(vec![
             ColumnDefinition::new("id", DataType::Integer, true),
             ColumnDefinition::new("name", DataType::Text, false),
         ]);
+        engine.create_table("occ_test", schema).await.unwrap();
 
-        engine.create_table("users", schema).await.unwrap();
-
-        // 插入数据
         let mut data = HashMap::new();
         data.insert("id".to_string(), Value::Integer(1));
-        data.insert("name".to_string(), Value::Text("Alice".to_string()));
+        let id = engine.insert("occ_test", data).await.unwrap();
 
-        let _id = engine.insert("users", data).await.unwrap();
+        let result = engine.query(QueryBuilder::select("occ_test").build()).await.unwrap();
+        let version = result.rows[0].version;
+        assert_eq!(result.rows[0].resolve(VERSION_COLUMN).unwrap().into_owned(), Value::Integer(version as i64));
 
-        // 查询数据
-        let query = QueryBuilder::select("users")
-            .where_condition("id", ComparisonOperator::Equal, Value::Integer(1))
-            .build();
+        let mut updates = HashMap::new();
+        updates.insert("name".to_string(), Value::Text("first".to_string()));
+        let new_version = engine.update_if_version("occ_test", id, version, updates.clone()).await.unwrap();
+        assert_eq!(new_version, version + 1);
+
+        // 使用过期的版本号更新会冲突
+        let err = engine.update_if_version("occ_test", id, version, updates).await.unwrap_err();
+        assert!(matches!(err, DatabaseError::Conflict { expected, actual, .. } if expected == version && actual == new_version));
+
+        let result = engine.query(QueryBuilder::select("occ_test").build()).await.unwrap();
+        assert_eq!(result.rows[0].version, new_version);
+        assert_eq!(result.rows[0].get("name"), Some(&Value::Text("first".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_updated_at_pseudo_column() {
+        let engine = DatabaseEngine::in_memory();
+
+        let schema = Schema::new(vec![
+            ColumnDefinition::new("id", DataType::Integer, true),
+            ColumnDefinition::new("name", DataType::Text, false),
+        ]);
+        engine.create_table("ts_test", schema).await.unwrap();
+
+        for i in 1..=2 {
+            let mut data = HashMap::new();
+            data.insert("id".to_string(), Value::Integer(i));
+            engine.insert("ts_test", data).await.unwrap();
+        }
 
+        let mut updates = HashMap::new();
+        updates.insert("name".to_string(), Value::Text("changed".to_string()));
+        let conditions = vec![("id".to_string(), ComparisonOperator::Equal, Value::Integer(1))];
+        engine.update("ts_test", conditions, updates).await.unwrap();
+
+        let query = QueryBuilder::select("ts_test")
+            .order_by(UPDATED_AT_COLUMN, false)
+            .build();
         let result = engine.query(query).await.unwrap();
-        assert_eq!(result.rows.len(), 1);
+        assert_eq!(result.rows[0].get("id"), Some(&Value::Integer(1)));
+        assert!(result.rows[0].updated_at > result.rows[0].created_at);
 
-        // 获取表信息
-        let table_info = engine.get_table_info("users").await.unwrap();
-        assert_eq!(table_info.row_count, 1);
+        // 重放旧格式日志时使用日志条目时间
+        let id = result.rows[1].id;
+        let log_time = chrono::Utc::now() + chrono::Duration::hours(1);
+        let mut storage = engine.storage.write().await;
+        engine.apply_log_operation(&mut storage, StorageOperation::Update {
+            table: "ts_test".to_string(),
+            id,
+            data: HashMap::from([("name".to_string(), Value::Text("replayed".to_string()))]),
+            updated_at: None,
+        }, log_time).unwrap();
+        let table = storage.get_table("ts_test").unwrap().unwrap();
+        let row = table.find_by_id(id).unwrap().unwrap();
+        assert_eq!(row.updated_at, log_time);
     }
 
     #[tokio::test]
-    async fn test_transaction() {
-        let mut engine = DatabaseEngine::new();
-        engine.set_auto_save(false);
+    async fn test_transaction_savepoints() {
+        let engine = DatabaseEngine::in_memory();
 
         let schema = Schema::new(vec![
             ColumnDefinition::new("id", DataType::Integer, true),
-            ColumnDefinition::new("value", DataType::Text, false),
         ]);
+        engine.create_table("sp_test", schema).await.unwrap();
 
-        let result = engine.transaction(|tx| {
-            tx.create_table("test_table", schema.clone())?;
+        let row = |id: i64| {
+            let mut data = HashMap::new();
+            data.insert("id".to_string(), Value::Integer(id));
+            data
+        };
 
-            let mut data1 = HashMap::new();
-            data1.insert("id".to_string(), Value::Integer(1));
-            data1.insert("value".to_string(), Value::Text("test1".to_string()));
-            tx.insert("test_table", data1)?;
+        engine.transaction(|tx| {
+            tx.insert("sp_test", row(1))?;
+            tx.savepoint("sp1")?;
+            tx.insert("sp_test", row(2))?;
+            tx.savepoint("sp2")?;
+            tx.insert("sp_test", row(3))?;
 
-            let mut data2 = HashMap::new();
-            data2.insert("id".to_string(), Value::Integer(2));
-            data2.insert("value".to_string(), Value::Text("test2".to_string()));
-            tx.insert("test_table", data2)?;
+            tx.rollback_to("sp1")?;
+            // sp2 在回滚后失效，sp1 仍然可用
+            assert!(tx.rollback_to("sp2").is_err());
+            tx.insert("sp_test", row(4))?;
+            tx.rollback_to("sp1")?;
+            tx.insert("sp_test", row(5))?;
 
-            Ok::<(), DatabaseError>(())
+            tx.release_savepoint("sp1")?;
+            assert!(tx.rollback_to("sp1").is_err());
+            Ok(())
+        }).await.unwrap();
+
+        let query = QueryBuilder::select("sp_test").order_by("id", true).build();
+        let ids: Vec<_> = engine.query(query).await.unwrap().rows.iter()
+            .map(|row| row.get("id").cloned().unwrap())
+            .collect();
+        assert_eq!(ids, vec![Value::Integer(1), Value::Integer(5)]);
+    }
+
+    #[tokio::test]
+    async fn test_isolation_level_anomalies() {
+        let engine = DatabaseEngine::in_memory();
+        let reset = "UPDATE doctors SET on_call = true";
+        engine.execute_sql("CREATE TABLE doctors (id INTEGER PRIMARY KEY, on_call BOOLEAN); INSERT INTO doctors VALUES (1, true), (2, true);").await.unwrap();
+        let doctors = engine.query(QueryBuilder::select("doctors").order_by("id", true).build()).await.unwrap();
+        let (first, second) = (doctors.rows[0].id, doctors.rows[1].id);
+        let on_call = || QueryBuilder::select("doctors").where_condition("on_call", ComparisonOperator::Equal, Value::Boolean(true)).build();
+        let off = || HashMap::from([("on_call".to_string(), Value::Boolean(false))]);
+        let options = |isolation| TransactionOptions::new().isolation(isolation);
+
+        // 读已提交：不可重复读，且后提交者覆盖先提交者的修改
+        let reads = engine.transaction_with(options(IsolationLevel::ReadCommitted), async |tx| {
+            let before = tx.query(on_call()).await?.rows.len();
+            engine.execute_sql("UPDATE doctors SET on_call = false WHERE id = 2").await?;
+            let after = tx.query(on_call()).await?.rows.len();
+            tx.update("doctors", second, HashMap::from([("on_call".to_string(), Value::Boolean(true))]))?;
+            Ok((before, after))
+        }).await.unwrap();
+        assert_eq!(reads, (2, 1));
+        assert_eq!(engine.query(on_call()).await.unwrap().rows.len(), 2);
+
+        // 快照隔离：可重复读，并发修改同一行时后提交者失败
+        let result = engine.transaction_with(options(IsolationLevel::SnapshotIsolation), async |tx| {
+            let before = tx.query(on_call()).await?.rows.len();
+            engine.execute_sql("UPDATE doctors SET on_call = false WHERE id = 2").await?;
+            assert_eq!(tx.query(on_call()).await?.rows.len(), before);
+            tx.update("doctors", second, HashMap::from([("on_call".to_string(), Value::Boolean(true))]))
         }).await;
+        assert!(matches!(result, Err(DatabaseError::Conflict { .. })));
+        engine.execute_sql(reset).await.unwrap();
 
-        assert!(result.is_ok());
+        // 两名医生各自确认还有别人值班后下班：快照隔离允许写偏斜，可串行化拒绝后提交者
+        for (isolation, expected) in [(IsolationLevel::SnapshotIsolation, 0), (IsolationLevel::Serializable, 1)] {
+            let result = engine.transaction_with(options(isolation), async |tx| {
+                if tx.query(on_call()).await?.rows.len() >= 2 {
+                    engine.transaction_with(options(isolation), async |other| {
+                        if other.query(on_call()).await?.rows.len() >= 2 {
+                            other.update("doctors", second, off())?;
+                        }
+                        Ok(())
+                    }).await?;
+                    tx.update("doctors", first, off())?;
+                }
+                Ok(())
+            }).await;
+            assert_eq!(result.is_ok(), isolation == IsolationLevel::SnapshotIsolation);
+            if isolation == IsolationLevel::Serializable {
+                assert!(matches!(result, Err(DatabaseError::SerializationFailure(_))));
+            }
+            assert_eq!(engine.query(on_call()).await.unwrap().rows.len(), expected);
+            engine.execute_sql(reset).await.unwrap();
+        }
+    }
 
-        // 验证数据
-        let tables = engine.list_tables().await;
-        assert!(tables.iter().any(|t| t.name == "test_table"));
+    #[tokio::test]
+    async fn test_transaction_deadlock_and_show_locks() {
+        let engine = DatabaseEngine::in_memory();
+        engine.execute_sql("CREATE TABLE accounts (id INTEGER PRIMARY KEY, balance INTEGER); INSERT INTO accounts VALUES (1, 10), (2, 10);").await.unwrap();
+        let accounts = engine.query(QueryBuilder::select("accounts").order_by("id", true).build()).await.unwrap();
+        let (first, second) = (accounts.rows[0].id, accounts.rows[1].id);
+        let barrier = tokio::sync::Barrier::new(2);
+        let balance = |value: i64| HashMap::from([("balance".to_string(), Value::Integer(value))]);
+
+        // 两个转账事务以相反的顺序锁定两个账户
+        let transfer = engine.transaction_async(async |tx| {
+            tx.lock_row("accounts", first, LockMode::Exclusive).await?;
+            barrier.wait().await;
+            tx.lock_row("accounts", second, LockMode::Exclusive).await?;
+            tx.update("accounts", first, balance(5))?;
+            tx.update("accounts", second, balance(15))
+        });
+        let reverse = engine.transaction_async(async |tx| {
+            tx.lock_row("accounts", second, LockMode::Exclusive).await?;
+            barrier.wait().await;
+            while engine.lock_info().iter().all(|info| info.granted) {
+                tokio::task::yield_now().await;
+            }
+            let locks = engine.execute_sql("SHOW LOCKS").await?.remove(0);
+            assert_eq!(locks.rows.len(), 3);
+            assert_eq!(locks.rows.iter().filter(|row| row.get("granted") == Some(&Value::Boolean(false))).count(), 1);
+            tx.lock_row("accounts", first, LockMode::Exclusive).await?;
+            tx.update("accounts", second, balance(0))
+        });
+        let (transfer, reverse) = tokio::join!(transfer, reverse);
+        transfer.unwrap();
+        assert!(matches!(reverse, Err(DatabaseError::Deadlock(_))));
+        assert!(engine.lock_info().is_empty());
+
+        let balances = engine.query(QueryBuilder::select("accounts").order_by("id", true).build()).await.unwrap();
+        let balances: Vec<_> = balances.rows.iter().filter_map(|row| row.get("balance").cloned()).collect();
+        assert_eq!(balances, vec![Value::Integer(5), Value::Integer(15)]);
     }
 
     #[tokio::test]
-    async fn test_batch_operations() {
-        let engine = DatabaseEngine::new();
+    async fn test_async_transaction_reads_own_writes() {
+        let engine = DatabaseEngine::in_memory();
 
         let schema = Schema::new(vec![
             ColumnDefinition::new("id", DataType::Integer, true),
-            ColumnDefinition::new("name", DataType::Text, false),
         ]);
+        engine.create_table("async_tx", schema).await.unwrap();
 
-        engine.create_table("batch_test", schema).await.unwrap();
+        let count = engine.transaction_async(async |tx| {
+            for i in 1..=3 {
+                let mut data = HashMap::new();
+                data.insert("id".to_string(), Value::Integer(i));
+                tx.insert("async_tx", data)?;
+            }
 
-        // 批量插入
-        let mut rows = Vec::new();
-        for i in 1..=5 {
+            // 事务内可以读到尚未提交的写入，提交前外部不可见
+            let pending = tx.query(QueryBuilder::count("async_tx").build()).await?;
+            let committed = engine.query(QueryBuilder::count("async_tx").build()).await?;
+            assert_eq!(committed.count, Some(0));
+            Ok(pending.count.unwrap_or(0))
+        }).await.unwrap();
+        assert_eq!(count, 3);
+
+        let result = engine.query(QueryBuilder::count("async_tx").build()).await.unwrap();
+        assert_eq!(result.count, Some(3));
+
+        // 闭包返回错误时不提交
+        let failed: Result<()> = engine.transaction_async(async |tx| {
             let mut data = HashMap::new();
-            data.insert("id".to_string(), Value::Integer(i));
-            data.insert("name".to_string(), Value::Text(format!("User {}", i)));
-            rows.push(data);
-        }
+            data.insert("id".to_string(), Value::Integer(4));
+            tx.insert("async_tx", data)?;
+            Err(DatabaseError::other("abort"))
+        }).await;
+        assert!(failed.is_err());
+        let result = engine.query(QueryBuilder::count("async_tx").build()).await.unwrap();
+        assert_eq!(result.count, Some(3));
+    }
 
-        let ids = engine.batch_insert("batch_test", rows).await.unwrap();
-        assert_eq!(ids.len(), 5);
+    #[tokio::test]
+    async fn test_stored_procedure() {
+        use crate::query::Placeholder;
 
-        // 统计信息
-        let stats = engine.get_stats().await.unwrap();
-        assert_eq!(stats.total_rows, 5);
+        let engine = DatabaseEngine::in_memory();
+
+        let schema = Schema::new(vec![
+            ColumnDefinition::new("id", DataType::Integer, true),
+            ColumnDefinition::new("name", DataType::Text, false),
+        ]);
+        engine.create_table("accounts", schema).await.unwrap();
+
+        // upsert: 先尝试更新，没有命中则插入
+        let mut insert_data = HashMap::new();
+        insert_data.insert("id".to_string(), Value::Integer(1));
+        insert_data.insert("name".to_string(), Value::Text("created".to_string()));
+        let mut update_data = HashMap::new();
+        update_data.insert("name".to_string(), Value::Text("updated".to_string()));
+
+        let procedure = Procedure::new("touch_account", vec!["id".to_string()], vec![
+            ProcedureStep::execute(
+                QueryBuilder::update("accounts", update_data)
+                    .where_param("id", ComparisonOperator::Equal, Placeholder::Named("id".to_string()))
+                    .build(),
+            ),
+            ProcedureStep::if_affected_rows(ComparisonOperator::Equal, 0, vec![
+                ProcedureStep::execute(QueryBuilder::insert("accounts", insert_data).build()),
+            ], vec![]),
+        ]);
+        engine.create_procedure(procedure).await.unwrap();
+
+        let result = engine.call("touch_account", vec![Value::Integer(1)]).await.unwrap();
+        assert_eq!(result.affected_rows, 1);
+        let result = engine.call("touch_account", vec![Value::Integer(1)]).await.unwrap();
+        assert!(matches!(result.query_type, QueryType::Update));
+
+        let rows = engine.query(QueryBuilder::select("accounts").build()).await.unwrap().rows;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].get("name"), Some(&Value::Text("updated".to_string())));
+
+        assert!(engine.call("touch_account", vec![]).await.is_err());
+        assert!(engine.call("missing", vec![]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validation_modes() {
+        use crate::backend::MemoryBackend;
+
+        let backend = MemoryBackend::new();
+        let engine = DatabaseEngine::with_backend(Arc::new(backend.clone())).await.unwrap();
+
+        let schema = Schema::new(vec![
+            ColumnDefinition::new("id", DataType::Integer, true),
+            ColumnDefinition::new("score", DataType::Integer, false),
+        ]);
+        engine.create_table("legacy", schema).await.unwrap();
+
+        // 宽松模式：类型不符的旧数据可以写入，但会产生警告
+        let mut data = HashMap::new();
+        data.insert("id".to_string(), Value::Integer(1));
+        data.insert("score".to_string(), Value::Text("42".to_string()));
+        engine.insert("legacy", data.clone()).await.unwrap();
+        assert_eq!(engine.validation_warnings().len(), 1);
+
+        // 警告只保留最近的若干条
+        let rows = (2..=MAX_VALIDATION_WARNINGS as i64 + 1).map(|id| {
+            HashMap::from([("id".to_string(), Value::Integer(id)), ("score".to_string(), Value::Text("7".to_string()))])
+        });
+        engine.batch_insert("legacy", rows.collect()).await.unwrap();
+        let warnings = engine.take_validation_warnings();
+        assert_eq!(warnings.len(), MAX_VALIDATION_WARNINGS);
+        assert!(engine.validation_warnings().is_empty());
+
+        // 转换为严格模式
+        let converted = engine.make_strict().await.unwrap();
+        assert_eq!(converted, MAX_VALIDATION_WARNINGS + 1);
+        assert_eq!(engine.validation_mode().await, ValidationMode::Strict);
+
+        // 模式随日志与快照保存，重新打开后仍为严格模式
+        drop(engine);
+        let engine = DatabaseEngine::with_backend(Arc::new(backend.clone())).await.unwrap();
+        assert_eq!(engine.validation_mode().await, ValidationMode::Strict);
+        engine.save_to_disk().await.unwrap();
+        drop(engine);
+        let engine = DatabaseEngine::with_backend(Arc::new(backend)).await.unwrap();
+        assert_eq!(engine.validation_mode().await, ValidationMode::Strict);
+
+        let result = engine.query(QueryBuilder::select("legacy").build()).await.unwrap();
+        assert_eq!(result.rows[0].get("score"), Some(&Value::Integer(42)));
+
+        // 严格模式下拒绝类型不符的数据
+        data.insert("id".to_string(), Value::Integer(2));
+        assert!(matches!(engine.insert("legacy", data).await, Err(DatabaseError::TypeMismatch(_))));
     }
 }
\ No newline at end of file
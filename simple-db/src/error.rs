@@ -1,3 +1,5 @@
+use std::fmt;
+
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, DatabaseError>;
@@ -10,24 +12,67 @@ pub enum DatabaseError {
     #[error("表 '{0}' 不存在")]
     TableNotFound(String),
 
+    #[error("数据库 '{0}' 已存在")]
+    DatabaseExists(String),
+
+    #[error("数据库 '{0}' 不存在")]
+    DatabaseNotFound(String),
+
     #[error("列 '{0}' 不存在")]
     ColumnNotFound(String),
 
     #[error("数据类型不匹配: {0}")]
     TypeMismatch(String),
 
-    #[error("违反唯一约束: {0}")]
-    UniqueViolation(String),
+    /// 违反唯一约束；`column` 为 `_id` 时是行 ID 重复
+    #[error("违反唯一约束: {}列 '{column}' 的值 '{value}' 必须唯一", table_prefix(table))]
+    UniqueViolation { table: String, column: String, value: String },
 
-    #[error("违反非空约束: {0}")]
-    NotNullViolation(String),
+    /// 违反非空约束，包括主键与分片键为空
+    #[error("违反非空约束: {}列 '{column}' 不能为空", table_prefix(table))]
+    NotNullViolation { table: String, column: String },
 
     #[error("解析错误: {0}")]
     ParseError(String),
 
+    /// SQL 语法错误，`position` 为出错处在 SQL 文本中的字符偏移（从 0 开始）
+    #[error("解析错误: {message} (位置 {position})")]
+    SyntaxError { position: usize, message: String },
+
+    /// 表名、列名等标识符不合法
+    #[error("无效的标识符 '{name}': {reason}")]
+    InvalidIdentifier { name: String, reason: String },
+
     #[error("IO 错误: {0}")]
     IoError(#[from] std::io::Error),
 
+    #[error("日志损坏: 第 {line} 行, {reason}")]
+    CorruptLog { line: usize, reason: String },
+
+    /// 快照文件头校验失败、由更新版本写入或内容无法解析
+    #[error("快照不兼容: {path}: {reason}")]
+    IncompatibleSnapshot { path: String, reason: String },
+
+    #[error("版本冲突: 行 {id} 期望版本 {expected}, 实际版本 {actual}")]
+    Conflict { id: String, expected: u64, actual: u64 },
+
+    /// 可串行化事务读取的数据在提交前已被其他事务修改
+    #[error("无法串行化: {0}")]
+    SerializationFailure(String),
+
+    #[error("检测到死锁: {0}")]
+    Deadlock(String),
+
+    #[error("超出内存预算: {scope} 需要 {requested} 字节, 上限 {limit} 字节")]
+    OutOfMemoryBudget { scope: String, requested: usize, limit: usize },
+
+    /// 插入后表的行数会超过建表时设置的上限
+    #[error("表 '{table}' 已满: 行数上限 {limit}")]
+    TableFull { table: String, limit: usize },
+
+    #[error("只读副本不接受写入")]
+    ReadOnly,
+
     #[error("JSON 错误: {0}")]
     JsonError(#[from] serde_json::Error),
 
@@ -44,19 +89,143 @@ impl DatabaseError {
         Self::TypeMismatch(msg.into())
     }
 
-    pub fn unique_violation<S: Into<String>>(msg: S) -> Self {
-        Self::UniqueViolation(msg.into())
+    /// 表名未知时传入空字符串，由上层用 [`DatabaseError::in_table`] 补上
+    pub fn unique_violation<T: Into<String>, C: Into<String>>(table: T, column: C, value: impl fmt::Display) -> Self {
+        Self::UniqueViolation { table: table.into(), column: column.into(), value: value.to_string() }
     }
 
-    pub fn not_null_violation<S: Into<String>>(msg: S) -> Self {
-        Self::NotNullViolation(msg.into())
+    /// 表名未知时传入空字符串，由上层用 [`DatabaseError::in_table`] 补上
+    pub fn not_null_violation<T: Into<String>, C: Into<String>>(table: T, column: C) -> Self {
+        Self::NotNullViolation { table: table.into(), column: column.into() }
     }
 
     pub fn parse_error<S: Into<String>>(msg: S) -> Self {
         Self::ParseError(msg.into())
     }
 
+    pub fn syntax_error<S: Into<String>>(position: usize, message: S) -> Self {
+        Self::SyntaxError { position, message: message.into() }
+    }
+
+    pub fn invalid_identifier<N: Into<String>, S: Into<String>>(name: N, reason: S) -> Self {
+        Self::InvalidIdentifier { name: name.into(), reason: reason.into() }
+    }
+
+    pub fn corrupt_log<S: Into<String>>(line: usize, reason: S) -> Self {
+        Self::CorruptLog { line, reason: reason.into() }
+    }
+
+    pub fn incompatible_snapshot<P: Into<String>, S: Into<String>>(path: P, reason: S) -> Self {
+        Self::IncompatibleSnapshot { path: path.into(), reason: reason.into() }
+    }
+
+    pub fn table_full<S: Into<String>>(table: S, limit: usize) -> Self {
+        Self::TableFull { table: table.into(), limit }
+    }
+
+    pub fn conflict<S: Into<String>>(id: S, expected: u64, actual: u64) -> Self {
+        Self::Conflict { id: id.into(), expected, actual }
+    }
+
+    pub fn serialization_failure<S: Into<String>>(msg: S) -> Self {
+        Self::SerializationFailure(msg.into())
+    }
+
+    pub fn deadlock<S: Into<String>>(msg: S) -> Self {
+        Self::Deadlock(msg.into())
+    }
+
+    pub fn out_of_memory_budget<S: Into<String>>(scope: S, requested: usize, limit: usize) -> Self {
+        Self::OutOfMemoryBudget { scope: scope.into(), requested, limit }
+    }
+
     pub fn other<S: Into<String>>(msg: S) -> Self {
         Self::Other(msg.into())
     }
+
+    /// 为还没有表名的约束错误补上表名，例如表结构校验返回的错误
+    pub fn in_table(self, name: &str) -> Self {
+        match self {
+            Self::UniqueViolation { table, column, value } if table.is_empty() => {
+                Self::UniqueViolation { table: name.to_string(), column, value }
+            }
+            Self::NotNullViolation { table, column } if table.is_empty() => {
+                Self::NotNullViolation { table: name.to_string(), column }
+            }
+            other => other,
+        }
+    }
+
+    /// 稳定的错误码，供应用按错误种类分支，不必匹配错误信息
+    ///
+    /// 1xxx 为表、列等对象，2xxx 为约束与数据，3xxx 为 SQL 解析，4xxx 为并发，5xxx 为存储，6xxx 为资源与模式，9xxx 为其他。
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::TableExists(_) => "SDB-1001",
+            Self::TableNotFound(_) => "SDB-1002",
+            Self::DatabaseExists(_) => "SDB-1003",
+            Self::DatabaseNotFound(_) => "SDB-1004",
+            Self::ColumnNotFound(_) => "SDB-1005",
+            Self::InvalidIdentifier { .. } => "SDB-1006",
+            Self::UniqueViolation { .. } => "SDB-2001",
+            Self::NotNullViolation { .. } => "SDB-2002",
+            Self::TypeMismatch(_) => "SDB-2003",
+            Self::TableFull { .. } => "SDB-2004",
+            Self::ParseError(_) => "SDB-3001",
+            Self::SyntaxError { .. } => "SDB-3002",
+            Self::Conflict { .. } => "SDB-4001",
+            Self::SerializationFailure(_) => "SDB-4002",
+            Self::Deadlock(_) => "SDB-4003",
+            Self::IoError(_) => "SDB-5001",
+            Self::CorruptLog { .. } => "SDB-5002",
+            Self::IncompatibleSnapshot { .. } => "SDB-5003",
+            Self::JsonError(_) => "SDB-5004",
+            Self::OutOfMemoryBudget { .. } => "SDB-6001",
+            Self::ReadOnly => "SDB-6002",
+            Self::Other(_) => "SDB-9999",
+        }
+    }
+
+    /// 违反唯一、非空约束或超出表的行数上限
+    pub fn is_constraint_violation(&self) -> bool {
+        matches!(self, Self::UniqueViolation { .. } | Self::NotNullViolation { .. } | Self::TableFull { .. })
+    }
+
+    /// 表、数据库或列不存在
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, Self::TableNotFound(_) | Self::DatabaseNotFound(_) | Self::ColumnNotFound(_))
+    }
+
+    /// 与并发事务冲突，原样重试可能成功
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Self::Conflict { .. } | Self::SerializationFailure(_) | Self::Deadlock(_))
+    }
+
+    /// 错误涉及的表，只有约束错误等带有表名的错误返回
+    pub fn table(&self) -> Option<&str> {
+        match self {
+            Self::TableExists(table) | Self::TableNotFound(table) => Some(table),
+            Self::UniqueViolation { table, .. } | Self::NotNullViolation { table, .. } | Self::TableFull { table, .. } => {
+                Some(table.as_str()).filter(|table| !table.is_empty())
+            }
+            _ => None,
+        }
+    }
+
+    /// 错误涉及的列
+    pub fn column(&self) -> Option<&str> {
+        match self {
+            Self::ColumnNotFound(column) | Self::UniqueViolation { column, .. } | Self::NotNullViolation { column, .. } => Some(column),
+            _ => None,
+        }
+    }
+}
+
+/// 错误信息中表名的前缀，表名未知时为空
+fn table_prefix(table: &str) -> String {
+    if table.is_empty() {
+        String::new()
+    } else {
+        format!("表 '{}' ", table)
+    }
 }
\ No newline at end of file
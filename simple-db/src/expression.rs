@@ -0,0 +1,335 @@
+use std::fmt;
+use chrono::NaiveTime;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{DatabaseError, Result};
+use crate::functions;
+use crate::sql;
+use crate::types::{Row, Value};
+
+/// 二元运算符
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    /// 字符串拼接 `||`
+    Concat,
+}
+
+impl fmt::Display for BinaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Modulo => "%",
+            BinaryOperator::Concat => "||",
+        })
+    }
+}
+
+/// 表达式：列引用、字面量、算术与拼接、函数调用
+///
+/// 对单行求值；任一操作数为 NULL 时结果为 NULL，缺失的列按 NULL 处理。
+/// 日期可以加减整数天数，日期时间可以加减整数秒数；两者都可以加减 `INTERVAL`，间隔之间可以加减、与数字相乘除。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Expr {
+    Column(String),
+    Literal(Value),
+    Negate(Box<Expr>),
+    Binary {
+        left: Box<Expr>,
+        operator: BinaryOperator,
+        right: Box<Expr>,
+    },
+    Function {
+        name: String,
+        args: Vec<Expr>,
+    },
+}
+
+impl Expr {
+    pub fn column<S: Into<String>>(name: S) -> Self {
+        Expr::Column(name.into())
+    }
+
+    pub fn literal<V: Into<Value>>(value: V) -> Self {
+        Expr::Literal(value.into())
+    }
+
+    pub fn binary(left: Expr, operator: BinaryOperator, right: Expr) -> Self {
+        Expr::Binary {
+            left: Box::new(left),
+            operator,
+            right: Box::new(right),
+        }
+    }
+
+    pub fn function<S: Into<String>>(name: S, args: Vec<Expr>) -> Self {
+        Expr::Function {
+            name: name.into().to_uppercase(),
+            args,
+        }
+    }
+
+    /// 不引用任何列的表达式，可以在解析时直接求值
+    pub fn is_constant(&self) -> bool {
+        match self {
+            Expr::Column(_) => false,
+            Expr::Literal(_) => true,
+            Expr::Negate(inner) => inner.is_constant(),
+            Expr::Binary { left, right, .. } => left.is_constant() && right.is_constant(),
+            Expr::Function { name, args } => !functions::is_sequence(name) && args.iter().all(Expr::is_constant),
+        }
+    }
+
+    /// 是否调用了 `NEXTVAL` 或 `CURRVAL`
+    pub fn uses_sequences(&self) -> bool {
+        match self {
+            Expr::Column(_) | Expr::Literal(_) => false,
+            Expr::Negate(inner) => inner.uses_sequences(),
+            Expr::Binary { left, right, .. } => left.uses_sequences() || right.uses_sequences(),
+            Expr::Function { name, args } => functions::is_sequence(name) || args.iter().any(Expr::uses_sequences),
+        }
+    }
+
+    /// 把 `NEXTVAL('s')`、`CURRVAL('s')` 替换为 `resolve(函数名, 序列名)` 给出的值，序列名必须是常量
+    pub fn bind_sequences<F>(&self, resolve: &mut F) -> Result<Expr>
+    where
+        F: FnMut(&str, &str) -> Result<i64>,
+    {
+        match self {
+            Expr::Column(_) | Expr::Literal(_) => Ok(self.clone()),
+            Expr::Negate(inner) => Ok(Expr::Negate(Box::new(inner.bind_sequences(resolve)?))),
+            Expr::Binary { left, operator, right } => {
+                Ok(Expr::binary(left.bind_sequences(resolve)?, *operator, right.bind_sequences(resolve)?))
+            }
+            Expr::Function { name, args } if functions::is_sequence(name) => {
+                let sequence = match args.as_slice() {
+                    [arg] if arg.is_constant() => arg.evaluate(&Row::new())?,
+                    _ => Value::Null,
+                };
+                match sequence {
+                    Value::Text(sequence) => resolve(name, &sequence).map(Expr::literal),
+                    _ => Err(DatabaseError::type_mismatch(format!("{} 的参数必须是序列名字符串", name))),
+                }
+            }
+            Expr::Function { name, args } => Ok(Expr::Function {
+                name: name.clone(),
+                args: args.iter().map(|arg| arg.bind_sequences(resolve)).collect::<Result<_>>()?,
+            }),
+        }
+    }
+
+    /// 表达式引用的列名
+    pub fn columns(&self) -> Vec<&str> {
+        match self {
+            Expr::Column(name) => vec![name.as_str()],
+            Expr::Literal(_) => Vec::new(),
+            Expr::Negate(inner) => inner.columns(),
+            Expr::Binary { left, right, .. } => {
+                let mut columns = left.columns();
+                columns.extend(right.columns());
+                columns
+            }
+            Expr::Function { args, .. } => args.iter().flat_map(Expr::columns).collect(),
+        }
+    }
+
+    /// 不调用随时间变化的函数，对同一行求值结果总是相同
+    pub fn is_deterministic(&self) -> bool {
+        match self {
+            Expr::Column(_) | Expr::Literal(_) => true,
+            Expr::Negate(inner) => inner.is_deterministic(),
+            Expr::Binary { left, right, .. } => left.is_deterministic() && right.is_deterministic(),
+            Expr::Function { name, args } => {
+                !functions::is_volatile(name, args.len()) && args.iter().all(Expr::is_deterministic)
+            }
+        }
+    }
+
+    /// 检查表达式中的函数名与参数个数
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            Expr::Column(_) | Expr::Literal(_) => Ok(()),
+            Expr::Negate(inner) => inner.validate(),
+            Expr::Binary { left, right, .. } => {
+                left.validate()?;
+                right.validate()
+            }
+            Expr::Function { name, args } => {
+                functions::check(name, args.len())?;
+                args.iter().try_for_each(Expr::validate)
+            }
+        }
+    }
+
+    pub fn evaluate(&self, row: &Row) -> Result<Value> {
+        match self {
+            Expr::Column(name) => Ok(row.resolve(name).map_or(Value::Null, |value| value.into_owned())),
+            Expr::Literal(value) => Ok(value.clone()),
+            Expr::Negate(inner) => match inner.evaluate(row)? {
+                Value::Integer(i) => i.checked_neg().map(Value::Integer)
+                    .ok_or_else(|| DatabaseError::type_mismatch(format!("-({}) 溢出", i))),
+                Value::Float(f) => Ok(Value::Float(-f)),
+                Value::Interval(interval) => interval.checked_neg().map(Value::Interval)
+                    .ok_or_else(|| DatabaseError::type_mismatch(format!("-({}) 溢出", interval))),
+                Value::Null => Ok(Value::Null),
+                other => Err(DatabaseError::type_mismatch(format!("不能对 {} 取负", other.get_type()))),
+            },
+            Expr::Binary { left, operator, right } => {
+                apply_binary(*operator, left.evaluate(row)?, right.evaluate(row)?)
+            }
+            Expr::Function { name, args } => {
+                let args = args.iter().map(|arg| arg.evaluate(row)).collect::<Result<Vec<_>>>()?;
+                functions::call(name, args)
+            }
+        }
+    }
+}
+
+fn apply_binary(operator: BinaryOperator, left: Value, right: Value) -> Result<Value> {
+    use BinaryOperator::*;
+
+    if left.is_null() || right.is_null() {
+        return Ok(Value::Null);
+    }
+    let overflow = || DatabaseError::type_mismatch(format!("{} {} {} 溢出", left, operator, right));
+    match (operator, &left, &right) {
+        (Concat, left, right) => Ok(Value::Text(format!("{}{}", left, right))),
+        (Divide | Modulo, _, Value::Integer(0)) => Err(DatabaseError::type_mismatch("除数为零")),
+        // 加减时间间隔：日期加只含月与天的间隔仍是日期，否则得到日期时间
+        (Add | Subtract, Value::Date(_) | Value::DateTime(_), Value::Interval(interval)) => {
+            let interval = if operator == Add { *interval } else { interval.checked_neg().ok_or_else(overflow)? };
+            match left {
+                Value::Date(date) if interval.is_whole_days() => interval.add_to(date.and_time(NaiveTime::MIN))
+                    .map(|datetime| Value::Date(datetime.date())),
+                Value::Date(date) => interval.add_to(date.and_time(NaiveTime::MIN)).map(Value::DateTime),
+                Value::DateTime(datetime) => interval.add_to(datetime).map(Value::DateTime),
+                _ => None,
+            }.ok_or_else(overflow)
+        }
+        (Add, Value::Interval(_), Value::Date(_) | Value::DateTime(_)) => apply_binary(Add, right, left),
+        (Add | Subtract, Value::Interval(a), Value::Interval(b)) => {
+            let b = if operator == Add { *b } else { b.checked_neg().ok_or_else(overflow)? };
+            a.checked_add(&b).map(Value::Interval).ok_or_else(overflow)
+        }
+        (Multiply | Divide, Value::Interval(interval), Value::Integer(_) | Value::Float(_)) => {
+            let factor = if operator == Multiply { as_float(&right) } else { 1.0 / as_float(&right) };
+            interval.checked_mul(factor).map(Value::Interval).ok_or_else(overflow)
+        }
+        (Multiply, Value::Integer(_) | Value::Float(_), Value::Interval(_)) => apply_binary(Multiply, right, left),
+        // 日期加减整数天数，日期时间加减整数秒数；两者相减得到天数或秒数
+        (Add | Subtract, Value::Date(date), Value::Integer(days)) => {
+            let days = if operator == Add { *days } else { days.checked_neg().ok_or_else(overflow)? };
+            chrono::Duration::try_days(days).and_then(|days| date.checked_add_signed(days))
+                .map(Value::Date).ok_or_else(overflow)
+        }
+        (Add, Value::Integer(_), Value::Date(_) | Value::DateTime(_)) => apply_binary(Add, right, left),
+        (Add | Subtract, Value::DateTime(datetime), Value::Integer(seconds)) => {
+            let seconds = if operator == Add { *seconds } else { seconds.checked_neg().ok_or_else(overflow)? };
+            chrono::Duration::try_seconds(seconds).and_then(|seconds| datetime.checked_add_signed(seconds))
+                .map(Value::DateTime).ok_or_else(overflow)
+        }
+        (Subtract, Value::Date(a), Value::Date(b)) => Ok(Value::Integer((*a - *b).num_days())),
+        (Subtract, Value::DateTime(a), Value::DateTime(b)) => Ok(Value::Integer((*a - *b).num_seconds())),
+        (_, Value::Integer(a), Value::Integer(b)) => match operator {
+            Add => a.checked_add(*b),
+            Subtract => a.checked_sub(*b),
+            Multiply => a.checked_mul(*b),
+            Divide => a.checked_div(*b),
+            Modulo => a.checked_rem(*b),
+            Concat => unreachable!(),
+        }.map(Value::Integer).ok_or_else(overflow),
+        (_, Value::Integer(_) | Value::Float(_), Value::Integer(_) | Value::Float(_)) => {
+            let (a, b) = (as_float(&left), as_float(&right));
+            Ok(Value::Float(match operator {
+                Add => a + b,
+                Subtract => a - b,
+                Multiply => a * b,
+                Divide => a / b,
+                Modulo => a % b,
+                Concat => unreachable!(),
+            }))
+        }
+        _ => Err(DatabaseError::type_mismatch(format!(
+            "不支持 {} {} {}", left.get_type(), operator, right.get_type()
+        ))),
+    }
+}
+
+fn as_float(value: &Value) -> f64 {
+    match value {
+        Value::Integer(i) => *i as f64,
+        Value::Float(f) => *f,
+        _ => f64::NAN,
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Column(name) => f.write_str(&sql::quote_identifier(name)),
+            Expr::Literal(value) => f.write_str(&sql::literal(value)),
+            Expr::Negate(inner) => write!(f, "-{}", inner),
+            Expr::Binary { left, operator, right } => write!(f, "({} {} {})", left, operator, right),
+            Expr::Function { name, args } => {
+                let args: Vec<String> = args.iter().map(Expr::to_string).collect();
+                write!(f, "{}({})", name, args.join(", "))
+            }
+        }
+    }
+}
+
+/// 计算列：把表达式的值以 `alias` 列名附加到结果行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Projection {
+    pub alias: String,
+    pub expr: Expr,
+}
+
+impl Projection {
+    pub fn new<S: Into<String>>(alias: S, expr: Expr) -> Self {
+        Self {
+            alias: alias.into(),
+            expr,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expression_evaluation() {
+        let mut row = Row::new();
+        row.set("price", Value::Integer(7));
+        row.set("qty", Value::Float(1.5));
+        row.set("name", Value::Text("Widget".to_string()));
+
+        let total = Expr::binary(Expr::column("price"), BinaryOperator::Multiply, Expr::column("qty"));
+        assert_eq!(total.evaluate(&row).unwrap(), Value::Float(10.5));
+
+        let label = Expr::binary(
+            Expr::function("lower", vec![Expr::column("name")]),
+            BinaryOperator::Concat,
+            Expr::binary(Expr::literal("#"), BinaryOperator::Concat, Expr::column("price")),
+        );
+        assert_eq!(label.evaluate(&row).unwrap(), Value::Text("widget#7".to_string()));
+        assert_eq!(label.to_string(), "(LOWER(name) || ('#' || price))");
+
+        let missing = Expr::binary(Expr::column("missing"), BinaryOperator::Add, Expr::literal(1i64));
+        assert_eq!(missing.evaluate(&row).unwrap(), Value::Null);
+        assert!(Expr::binary(Expr::column("price"), BinaryOperator::Divide, Expr::literal(0i64)).evaluate(&row).is_err());
+        assert!(Expr::binary(Expr::column("name"), BinaryOperator::Add, Expr::literal(1i64)).evaluate(&row).is_err());
+
+        assert!(Expr::literal(1i64).is_constant() && !total.is_constant());
+        assert_eq!(label.columns(), vec!["name", "price"]);
+        assert!(Expr::function("nope", vec![]).validate().is_err());
+    }
+}
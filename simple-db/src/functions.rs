@@ -0,0 +1,305 @@
+use chrono::{Datelike, Duration, Months, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+
+use crate::binary;
+use crate::clock;
+use crate::error::{DatabaseError, Result};
+use crate::geo;
+use crate::json_path::JsonPath;
+use crate::types::Value;
+
+/// 内置函数：名称与参数个数范围
+const FUNCTIONS: &[(&str, usize, usize)] = &[
+    ("LOWER", 1, 1),
+    ("UPPER", 1, 1),
+    ("TRIM", 1, 1),
+    ("LENGTH", 1, 1),
+    ("ABS", 1, 1),
+    ("ROUND", 1, 2),
+    ("COALESCE", 1, usize::MAX),
+    ("JSON_EXTRACT", 2, 2),
+    ("NOW", 0, 0),
+    ("CURRENT_DATE", 0, 0),
+    ("DATE_TRUNC", 2, 2),
+    ("EXTRACT", 2, 2),
+    ("AGE", 1, 2),
+    ("DATE_ADD", 3, 3),
+    ("OCTET_LENGTH", 1, 1),
+    ("TO_HEX", 1, 1),
+    ("FROM_HEX", 1, 1),
+    ("TO_BASE64", 1, 1),
+    ("FROM_BASE64", 1, 1),
+    ("POINT", 2, 2),
+    ("DISTANCE", 2, 2),
+    ("NEXTVAL", 1, 1),
+    ("CURRVAL", 1, 1),
+];
+
+/// 检查函数是否存在、参数个数是否正确
+pub fn check(name: &str, arg_count: usize) -> Result<()> {
+    let upper = name.to_uppercase();
+    let (_, min, max) = FUNCTIONS.iter()
+        .find(|(function, _, _)| *function == upper)
+        .ok_or_else(|| DatabaseError::parse_error(format!("未知函数: {}", name)))?;
+    if arg_count < *min || arg_count > *max {
+        let expected = match (*min, *max) {
+            (min, max) if min == max => min.to_string(),
+            (min, usize::MAX) => format!("至少 {}", min),
+            (min, max) => format!("{} 到 {}", min, max),
+        };
+        return Err(DatabaseError::parse_error(
+            format!("函数 {} 需要 {} 个参数，实际 {} 个", upper, expected, arg_count)
+        ));
+    }
+    Ok(())
+}
+
+/// 结果取决于当前时间的函数：`NOW()`、`CURRENT_DATE()` 与单参数的 `AGE(ts)`
+pub fn is_volatile(name: &str, arg_count: usize) -> bool {
+    match name.to_uppercase().as_str() {
+        "NOW" | "CURRENT_DATE" | "NEXTVAL" | "CURRVAL" => true,
+        "AGE" => arg_count == 1,
+        _ => false,
+    }
+}
+
+/// 读写序列的函数 `NEXTVAL('name')`、`CURRVAL('name')`，由引擎在执行前代入序列值，见 `Expr::bind_sequences`
+pub fn is_sequence(name: &str) -> bool {
+    name.eq_ignore_ascii_case("NEXTVAL") || name.eq_ignore_ascii_case("CURRVAL")
+}
+
+/// 调用内置函数；除 COALESCE 外，任一参数为 NULL 时结果为 NULL
+pub fn call(name: &str, args: Vec<Value>) -> Result<Value> {
+    check(name, args.len())?;
+    let upper = name.to_uppercase();
+    match upper.as_str() {
+        "COALESCE" => return Ok(args.into_iter().find(|value| !value.is_null()).unwrap_or(Value::Null)),
+        "NOW" => return Ok(Value::DateTime(now())),
+        "CURRENT_DATE" => return Ok(Value::Date(now().date())),
+        "NEXTVAL" | "CURRVAL" => return Err(DatabaseError::other(
+            format!("{} 只能用于 INSERT 的值、列默认值与查询", upper)
+        )),
+        _ => {}
+    }
+    if args.iter().any(Value::is_null) {
+        return Ok(Value::Null);
+    }
+
+    match (upper.as_str(), args.as_slice()) {
+        ("LOWER", [Value::Text(text)]) => Ok(Value::Text(text.to_lowercase())),
+        ("UPPER", [Value::Text(text)]) => Ok(Value::Text(text.to_uppercase())),
+        ("TRIM", [Value::Text(text)]) => Ok(Value::Text(text.trim().to_string())),
+        ("LENGTH", [Value::Text(text)]) => Ok(Value::Integer(text.chars().count() as i64)),
+        ("LENGTH", [Value::Binary(bytes)]) => Ok(Value::Integer(bytes.len() as i64)),
+        ("LENGTH", [Value::Array(items)]) => Ok(Value::Integer(items.len() as i64)),
+        ("OCTET_LENGTH", [Value::Text(text)]) => Ok(Value::Integer(text.len() as i64)),
+        ("OCTET_LENGTH", [Value::Binary(bytes)]) => Ok(Value::Integer(bytes.len() as i64)),
+        ("TO_HEX", [Value::Binary(bytes)]) => Ok(Value::Text(binary::encode_hex(bytes))),
+        ("FROM_HEX", [Value::Text(text)]) => binary::decode_hex(text).map(Value::Binary),
+        ("TO_BASE64", [Value::Binary(bytes)]) => Ok(Value::Text(binary::encode_base64(bytes))),
+        ("FROM_BASE64", [Value::Text(text)]) => binary::decode_base64(text).map(Value::Binary),
+        ("ABS", [Value::Integer(i)]) => i.checked_abs().map(Value::Integer)
+            .ok_or_else(|| DatabaseError::type_mismatch(format!("ABS({}) 溢出", i))),
+        ("ABS", [Value::Float(f)]) => Ok(Value::Float(f.abs())),
+        ("ROUND", [Value::Integer(i)]) => Ok(Value::Integer(*i)),
+        ("ROUND", [Value::Float(f)]) => Ok(Value::Float(f.round())),
+        ("ROUND", [Value::Integer(i), Value::Integer(_)]) => Ok(Value::Integer(*i)),
+        ("ROUND", [Value::Float(f), Value::Integer(digits)]) => {
+            let factor = 10f64.powi((*digits).clamp(-15, 15) as i32);
+            Ok(Value::Float((f * factor).round() / factor))
+        }
+        ("JSON_EXTRACT", [Value::Json(json), Value::Text(path)]) => match JsonPath::parse(path)?.select(json)? {
+            Some(selected) => Value::from_json(selected, None),
+            None => Ok(Value::Null),
+        },
+        ("DATE_TRUNC", [Value::Text(unit), Value::DateTime(datetime)]) => Ok(Value::DateTime(truncate(*datetime, &unit.parse()?))),
+        ("DATE_TRUNC", [Value::Text(unit), Value::Date(date)]) => {
+            Ok(Value::Date(truncate(date.and_time(NaiveTime::MIN), &unit.parse()?).date()))
+        }
+        ("EXTRACT", [Value::Text(field), value]) => extract(field, value),
+        ("AGE", [value]) => age(&now(), value),
+        ("AGE", [later, earlier]) => age(&as_datetime(later)?, earlier),
+        ("DATE_ADD", [Value::DateTime(datetime), Value::Integer(amount), Value::Text(unit)]) => {
+            Ok(Value::DateTime(add(*datetime, *amount, &unit.parse()?)?))
+        }
+        ("DATE_ADD", [Value::Date(date), Value::Integer(amount), Value::Text(unit)]) => {
+            Ok(Value::Date(add(date.and_time(NaiveTime::MIN), *amount, &unit.parse()?)?.date()))
+        }
+        ("POINT", [lat, lon]) if degrees(lat).is_some() && degrees(lon).is_some() => {
+            let (lat, lon) = (degrees(lat).unwrap_or_default(), degrees(lon).unwrap_or_default());
+            if !geo::valid_point((lat, lon)) {
+                return Err(DatabaseError::type_mismatch(format!("坐标 ({}, {}) 超出范围", lat, lon)));
+            }
+            Ok(Value::Point { lat, lon })
+        }
+        ("DISTANCE", [Value::Point { lat: lat1, lon: lon1 }, Value::Point { lat: lat2, lon: lon2 }]) => {
+            Ok(Value::Float(geo::distance_meters((*lat1, *lon1), (*lat2, *lon2))))
+        }
+        (_, args) => {
+            let types: Vec<String> = args.iter().map(|value| value.get_type().to_string()).collect();
+            Err(DatabaseError::type_mismatch(format!("函数 {} 不支持参数类型 ({})", upper, types.join(", "))))
+        }
+    }
+}
+
+fn degrees(value: &Value) -> Option<f64> {
+    match value {
+        Value::Integer(i) => Some(*i as f64),
+        Value::Float(f) => Some(*f),
+        _ => None,
+    }
+}
+
+/// 当前 UTC 时间，与行的 `created_at` 一致
+pub fn now() -> NaiveDateTime {
+    clock::now().naive_utc()
+}
+
+/// 日期时间单位，用于 DATE_TRUNC 与 DATE_ADD
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeUnit {
+    Year,
+    Quarter,
+    Month,
+    Week,
+    Day,
+    Hour,
+    Minute,
+    Second,
+}
+
+impl std::str::FromStr for TimeUnit {
+    type Err = DatabaseError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().trim_end_matches('s') {
+            "year" => Ok(TimeUnit::Year),
+            "quarter" => Ok(TimeUnit::Quarter),
+            "month" => Ok(TimeUnit::Month),
+            "week" => Ok(TimeUnit::Week),
+            "day" => Ok(TimeUnit::Day),
+            "hour" => Ok(TimeUnit::Hour),
+            "minute" => Ok(TimeUnit::Minute),
+            "second" => Ok(TimeUnit::Second),
+            _ => Err(DatabaseError::parse_error(format!("未知的时间单位: {}", s))),
+        }
+    }
+}
+
+fn truncate(datetime: NaiveDateTime, unit: &TimeUnit) -> NaiveDateTime {
+    let date = datetime.date();
+    let first_of_month = |month: u32| NaiveDate::from_ymd_opt(date.year(), month, 1).expect("每月 1 日总是有效");
+    let (date, time) = match unit {
+        TimeUnit::Year => (first_of_month(1), NaiveTime::MIN),
+        TimeUnit::Quarter => (first_of_month((date.month0() / 3) * 3 + 1), NaiveTime::MIN),
+        TimeUnit::Month => (first_of_month(date.month()), NaiveTime::MIN),
+        TimeUnit::Week => (date - Duration::days(date.weekday().num_days_from_monday() as i64), NaiveTime::MIN),
+        TimeUnit::Day => (date, NaiveTime::MIN),
+        TimeUnit::Hour => (date, NaiveTime::from_hms_opt(datetime.hour(), 0, 0).expect("有效的小时")),
+        TimeUnit::Minute => (date, NaiveTime::from_hms_opt(datetime.hour(), datetime.minute(), 0).expect("有效的分钟")),
+        TimeUnit::Second => (date, NaiveTime::from_hms_opt(datetime.hour(), datetime.minute(), datetime.second()).expect("有效的秒")),
+    };
+    date.and_time(time)
+}
+
+fn add(datetime: NaiveDateTime, amount: i64, unit: &TimeUnit) -> Result<NaiveDateTime> {
+    let months = |count: i64| -> Option<NaiveDateTime> {
+        let months = Months::new(u32::try_from(count.unsigned_abs()).ok()?);
+        if count >= 0 { datetime.checked_add_months(months) } else { datetime.checked_sub_months(months) }
+    };
+    let result = match unit {
+        TimeUnit::Year => amount.checked_mul(12).and_then(months),
+        TimeUnit::Quarter => amount.checked_mul(3).and_then(months),
+        TimeUnit::Month => months(amount),
+        TimeUnit::Week => Duration::try_weeks(amount).and_then(|d| datetime.checked_add_signed(d)),
+        TimeUnit::Day => Duration::try_days(amount).and_then(|d| datetime.checked_add_signed(d)),
+        TimeUnit::Hour => Duration::try_hours(amount).and_then(|d| datetime.checked_add_signed(d)),
+        TimeUnit::Minute => Duration::try_minutes(amount).and_then(|d| datetime.checked_add_signed(d)),
+        TimeUnit::Second => Duration::try_seconds(amount).and_then(|d| datetime.checked_add_signed(d)),
+    };
+    result.ok_or_else(|| DatabaseError::type_mismatch(format!("{} 加上 {} 个 {:?} 超出范围", datetime, amount, unit)))
+}
+
+/// EXTRACT 的字段：year、quarter、month、day、hour、minute、second、dow（周日为 0）、doy、week（ISO 周）、epoch
+fn extract(field: &str, value: &Value) -> Result<Value> {
+    let field = field.trim().to_lowercase();
+    let (date, time) = match value {
+        Value::DateTime(datetime) => (Some(datetime.date()), Some(datetime.time())),
+        Value::Date(date) => (Some(*date), None),
+        Value::Time(time) => (None, Some(*time)),
+        other => return Err(DatabaseError::type_mismatch(format!("EXTRACT 不支持 {} 类型", other.get_type()))),
+    };
+
+    let from_date = |f: fn(&NaiveDate) -> i64| date.as_ref().map(f);
+    let from_time = |f: fn(&NaiveTime) -> i64| Some(time.map_or(0, |time| f(&time)));
+    let result = match field.as_str() {
+        "year" => from_date(|d| d.year() as i64),
+        "quarter" => from_date(|d| (d.month0() / 3 + 1) as i64),
+        "month" => from_date(|d| d.month() as i64),
+        "day" => from_date(|d| d.day() as i64),
+        "dow" => from_date(|d| d.weekday().num_days_from_sunday() as i64),
+        "doy" => from_date(|d| d.ordinal() as i64),
+        "week" => from_date(|d| d.iso_week().week() as i64),
+        "hour" => from_time(|t| t.hour() as i64),
+        "minute" => from_time(|t| t.minute() as i64),
+        "second" => from_time(|t| t.second() as i64),
+        "epoch" => match value {
+            Value::Time(time) => Some(time.num_seconds_from_midnight() as i64),
+            _ => Some(as_datetime(value)?.and_utc().timestamp()),
+        },
+        _ => return Err(DatabaseError::parse_error(format!("未知的 EXTRACT 字段: {}", field))),
+    };
+    result.map(Value::Integer)
+        .ok_or_else(|| DatabaseError::type_mismatch(format!("TIME 值没有字段 {}", field)))
+}
+
+/// 两个时间点相差的秒数，日期按当天零点计算
+fn age(later: &NaiveDateTime, earlier: &Value) -> Result<Value> {
+    Ok(Value::Integer((*later - as_datetime(earlier)?).num_seconds()))
+}
+
+fn as_datetime(value: &Value) -> Result<NaiveDateTime> {
+    match value {
+        Value::DateTime(datetime) => Ok(*datetime),
+        Value::Date(date) => Ok(date.and_time(NaiveTime::MIN)),
+        other => Err(DatabaseError::type_mismatch(format!("期望 DATE 或 DATETIME，实际为 {}", other.get_type()))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_functions() {
+        let text = |s: &str| Value::Text(s.to_string());
+        assert_eq!(call("lower", vec![text("AbC")]).unwrap(), text("abc"));
+        assert_eq!(call("LENGTH", vec![text("héllo")]).unwrap(), Value::Integer(5));
+        assert_eq!(call("ABS", vec![Value::Integer(-3)]).unwrap(), Value::Integer(3));
+        assert_eq!(call("ROUND", vec![Value::Float(2.346), Value::Integer(2)]).unwrap(), Value::Float(2.35));
+        assert_eq!(call("COALESCE", vec![Value::Null, Value::Integer(1)]).unwrap(), Value::Integer(1));
+        assert_eq!(call("UPPER", vec![Value::Null]).unwrap(), Value::Null);
+        assert_eq!(
+            call("JSON_EXTRACT", vec![Value::Json(serde_json::json!({"a": [1, 2]})), text("$.a[1]")]).unwrap(),
+            Value::Integer(2)
+        );
+
+        let datetime = |s: &str| Value::DateTime(s.parse().unwrap());
+        let ts = datetime("2024-05-17T13:45:30");
+        assert_eq!(call("DATE_TRUNC", vec![text("month"), ts.clone()]).unwrap(), datetime("2024-05-01T00:00:00"));
+        assert_eq!(call("DATE_TRUNC", vec![text("week"), ts.clone()]).unwrap(), datetime("2024-05-13T00:00:00"));
+        assert_eq!(call("DATE_TRUNC", vec![text("hours"), ts.clone()]).unwrap(), datetime("2024-05-17T13:00:00"));
+        assert_eq!(call("EXTRACT", vec![text("dow"), ts.clone()]).unwrap(), Value::Integer(5));
+        assert_eq!(call("EXTRACT", vec![text("minute"), ts.clone()]).unwrap(), Value::Integer(45));
+        assert_eq!(call("AGE", vec![ts.clone(), datetime("2024-05-16T13:45:00")]).unwrap(), Value::Integer(86_430));
+        assert_eq!(
+            call("DATE_ADD", vec![Value::Date("2024-01-31".parse().unwrap()), Value::Integer(1), text("month")]).unwrap(),
+            Value::Date("2024-02-29".parse().unwrap())
+        );
+        assert!(matches!(call("NOW", vec![]).unwrap(), Value::DateTime(_)));
+        assert!(call("EXTRACT", vec![text("year"), Value::Time(NaiveTime::MIN)]).is_err());
+
+        assert!(call("LOWER", vec![Value::Integer(1)]).is_err());
+        assert!(check("NOPE", 1).is_err());
+        assert!(check("ABS", 2).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+
+use crate::types::{Row, Value};
+
+/// 地球平均半径（米）
+pub const EARTH_RADIUS_METERS: f64 = 6_371_008.8;
+
+/// 网格索引每个格子的边长（度），约 1 公里
+const CELL_DEGREES: f64 = 0.01;
+
+/// 半径查询最多检查的格子数，超过时退回全表扫描
+const MAX_CELLS: i64 = 40_000;
+
+/// 两点之间的大圆距离（米），按球面的半正矢公式计算
+pub fn distance_meters(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (lat1, lat2) = (a.0.to_radians(), b.0.to_radians());
+    let d_lat = lat2 - lat1;
+    let d_lon = (b.1 - a.1).to_radians();
+    let h = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_METERS * h.sqrt().min(1.0).asin()
+}
+
+/// 解析 `POINT(lat, lon)` 或 `lat,lon` 形式的文本，坐标超出范围时返回 None
+pub fn parse_point(text: &str) -> Option<(f64, f64)> {
+    let text = text.trim();
+    let inner = match text.get(..5) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("POINT") => text[5..].trim().strip_prefix('(')?.strip_suffix(')')?,
+        _ => text,
+    };
+    let (lat, lon) = inner.split_once(',')?;
+    let point = (lat.trim().parse().ok()?, lon.trim().parse().ok()?);
+    valid_point(point).then_some(point)
+}
+
+/// 纬度在 [-90, 90]、经度在 [-180, 180] 之内
+pub fn valid_point((lat, lon): (f64, f64)) -> bool {
+    (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon)
+}
+
+/// 坐标列的网格索引：把经纬度按固定大小的格子分桶，格子 → 落在其中的行在 `Table::indexed_rows` 中的位置
+///
+/// 与元素索引一样在首次查询时按需构建并缓存在表上，表数据变化后失效重建。
+#[derive(Debug, Clone, Default)]
+pub struct GeoIndex {
+    cells: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl GeoIndex {
+    pub fn build(rows: &[Row], column: &str) -> Self {
+        let mut cells: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+        for (position, row) in rows.iter().enumerate() {
+            if let Some(Value::Point { lat, lon }) = row.get(column) {
+                cells.entry(cell(*lat, *lon)).or_default().push(position);
+            }
+        }
+        Self { cells }
+    }
+
+    /// 可能在 `center` 周围 `meters` 米之内的行位置，升序；范围跨越极点或 180 度经线、
+    /// 或者需要检查的格子过多时返回 None，由调用方扫描全表
+    pub fn candidates(&self, center: (f64, f64), meters: f64) -> Option<Vec<usize>> {
+        let lat_delta = (meters / EARTH_RADIUS_METERS).to_degrees();
+        let (min_lat, max_lat) = (center.0 - lat_delta, center.0 + lat_delta);
+        if min_lat <= -90.0 || max_lat >= 90.0 {
+            return None;
+        }
+        // 经度方向的跨度在离赤道最远的纬度上最大
+        let widest = min_lat.abs().max(max_lat.abs()).to_radians().cos();
+        let lon_delta = lat_delta / widest;
+        let (min_lon, max_lon) = (center.1 - lon_delta, center.1 + lon_delta);
+        if min_lon < -180.0 || max_lon > 180.0 {
+            return None;
+        }
+
+        let (low, high) = (cell(min_lat, min_lon), cell(max_lat, max_lon));
+        if (high.0 - low.0 + 1).saturating_mul(high.1 - low.1 + 1) > MAX_CELLS {
+            return None;
+        }
+        let mut positions = Vec::new();
+        for lat_cell in low.0..=high.0 {
+            for lon_cell in low.1..=high.1 {
+                if let Some(found) = self.cells.get(&(lat_cell, lon_cell)) {
+                    positions.extend_from_slice(found);
+                }
+            }
+        }
+        positions.sort_unstable();
+        Some(positions)
+    }
+
+    /// 索引占用的内存估算（字节）
+    pub fn heap_size(&self) -> usize {
+        self.cells.values()
+            .map(|positions| std::mem::size_of::<(i64, i64)>() + positions.capacity() * std::mem::size_of::<usize>())
+            .sum()
+    }
+}
+
+fn cell(lat: f64, lon: f64) -> (i64, i64) {
+    ((lat / CELL_DEGREES).floor() as i64, (lon / CELL_DEGREES).floor() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distance_and_grid_candidates() {
+        // 上海人民广场到外滩约 1.9 公里
+        let square = (31.2304, 121.4737);
+        let bund = (31.2400, 121.4900);
+        let distance = distance_meters(square, bund);
+        assert!((1_800.0..2_000.0).contains(&distance), "{}", distance);
+        assert_eq!(parse_point("POINT(31.24, 121.49)"), Some((31.24, 121.49)));
+        assert_eq!(parse_point("31.24,121.49"), Some((31.24, 121.49)));
+        assert_eq!(parse_point("POINT(91, 0)"), None);
+
+        let rows: Vec<Row> = [square, bund, (39.9042, 116.4074)].iter()
+            .map(|(lat, lon)| {
+                let mut row = Row::new();
+                row.set("location".to_string(), Value::Point { lat: *lat, lon: *lon });
+                row
+            })
+            .collect();
+        let index = GeoIndex::build(&rows, "location");
+        assert_eq!(index.candidates(square, 5_000.0), Some(vec![0, 1]));
+        assert_eq!(index.candidates(square, 100.0), Some(vec![0]));
+        assert_eq!(index.candidates((89.99, 0.0), 5_000.0), None);
+    }
+}
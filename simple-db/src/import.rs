@@ -0,0 +1,476 @@
+use std::fmt;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::error::{DatabaseError, Result};
+use crate::types::{ColumnDefinition, DataType, Row, Schema, Value};
+
+/// COPY 的数据格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CopyFormat {
+    /// 逗号分隔，字段可以用双引号包围，未加引号的空字段为 NULL
+    #[default]
+    Csv,
+    /// 制表符分隔，不使用引号；`\N` 为 NULL，`\t`、`\n`、`\r`、`\\` 为转义字符
+    Tsv,
+}
+
+impl FromStr for CopyFormat {
+    type Err = DatabaseError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(CopyFormat::Csv),
+            "tsv" | "text" => Ok(CopyFormat::Tsv),
+            _ => Err(DatabaseError::parse_error(format!("未知的 COPY 格式: {}（可选 csv、tsv）", s))),
+        }
+    }
+}
+
+impl fmt::Display for CopyFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CopyFormat::Csv => "csv",
+            CopyFormat::Tsv => "tsv",
+        })
+    }
+}
+
+/// COPY 选项
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CopyOptions {
+    pub format: CopyFormat,
+    /// 第一行为表头；未指定列时按表头中的列名对应
+    pub header: bool,
+    /// 字段分隔符，默认 CSV 为 `,`，TSV 为制表符
+    pub delimiter: Option<char>,
+    /// 表示 NULL 的字段内容，默认 CSV 为未加引号的空字段，TSV 为 `\N`
+    pub null: Option<String>,
+    /// 字段依次对应的列，为空时按表头或表结构的列顺序对应
+    pub columns: Vec<String>,
+}
+
+impl CopyOptions {
+    pub fn new(format: CopyFormat) -> Self {
+        Self { format, ..Self::default() }
+    }
+
+    pub fn header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+
+    pub fn delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = Some(delimiter);
+        self
+    }
+
+    pub fn null<S: Into<String>>(mut self, null: S) -> Self {
+        self.null = Some(null.into());
+        self
+    }
+
+    pub fn columns(mut self, columns: Vec<String>) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    fn field_delimiter(&self) -> char {
+        self.delimiter.unwrap_or(match self.format {
+            CopyFormat::Csv => ',',
+            CopyFormat::Tsv => '\t',
+        })
+    }
+}
+
+/// 导入时如何处理不合格的行
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportOptions {
+    /// 跳过无法解析或校验失败的行并继续导入；为 false 时遇到第一个不合格的行即失败，不导入任何行
+    pub skip_invalid: bool,
+    /// 把被跳过的行连同原因写入该文件，每行一个 JSON 对象
+    pub reject_file: Option<PathBuf>,
+}
+
+impl ImportOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn skip_invalid(mut self, skip_invalid: bool) -> Self {
+        self.skip_invalid = skip_invalid;
+        self
+    }
+
+    /// 设置拒绝文件，同时开启 `skip_invalid`
+    pub fn reject_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.reject_file = Some(path.into());
+        self.skip_invalid = true;
+        self
+    }
+}
+
+/// 从数据源读出的一行
+#[derive(Debug, Clone)]
+pub struct SourceRow {
+    /// 记录起始处的行号，从 1 开始，包括表头
+    pub line: usize,
+    /// 记录的原始文本，行被拒绝时原样写入拒绝文件
+    pub record: String,
+    pub row: Row,
+}
+
+/// 推断表结构时读取的数据行数
+pub const INFER_SAMPLE_ROWS: usize = 1000;
+
+/// 被拒绝的一行数据
+#[derive(Debug, Clone, PartialEq)]
+pub struct RejectedRow {
+    /// 记录起始处的行号，从 1 开始，包括表头
+    pub line: usize,
+    pub error: String,
+    /// 记录的原始文本
+    pub record: String,
+}
+
+/// 导入结果
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportReport {
+    pub loaded: usize,
+    pub rejected: usize,
+    /// 每个被拒绝的行及原因，按行号排列
+    pub errors: Vec<RejectedRow>,
+}
+
+impl ImportReport {
+    pub(crate) fn reject(&mut self, line: usize, record: String, error: &DatabaseError) {
+        self.rejected += 1;
+        self.errors.push(RejectedRow { line, error: error.to_string(), record });
+    }
+
+    /// 第一个被拒绝的行，转换为带行号的错误
+    pub(crate) fn first_error(&self) -> Option<DatabaseError> {
+        self.errors.first()
+            .map(|rejected| DatabaseError::parse_error(format!("第 {} 行: {}", rejected.line, rejected.error)))
+    }
+
+    /// 把被拒绝的行写入拒绝文件，每行一个 `{"line", "error", "record"}` 对象
+    pub fn write_rejects(&self, path: &Path) -> Result<()> {
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        for rejected in &self.errors {
+            let object = serde_json::json!({
+                "line": rejected.line,
+                "error": rejected.error,
+                "record": rejected.record,
+            });
+            writeln!(writer, "{}", object)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// 按 COPY 选项读取数据并转换为表中的行
+///
+/// 无法解析、字段数不符、无法转换为列类型或缺少必填列的记录计入报告并跳过，
+/// 读取失败等 IO 错误直接返回。返回的报告中 `loaded` 为 0，由调用方在写入后填写。
+pub fn read_rows<R: BufRead>(reader: R, schema: &Schema, options: &CopyOptions) -> Result<(Vec<SourceRow>, ImportReport)> {
+    let mut records = RecordReader::new(reader, options);
+    let mut columns = options.columns.clone();
+    if options.header {
+        if let Some(Record { line, fields: header, .. }) = records.next_record()? {
+            let header = header.map_err(|e| DatabaseError::parse_error(format!("第 {} 行: {}", line, e)))?;
+            if columns.is_empty() {
+                columns = header.into_iter().map(Option::unwrap_or_default).collect();
+            }
+        }
+    }
+    if columns.is_empty() {
+        columns = schema.columns.iter().map(|column| column.name.clone()).collect();
+    }
+    let types = columns.iter()
+        .map(|name| schema.get_column(name)
+            .map(|column| column.data_type.clone())
+            .ok_or_else(|| DatabaseError::ColumnNotFound(name.clone())))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut rows = Vec::new();
+    let mut report = ImportReport::default();
+    while let Some(Record { line, text, fields }) = records.next_record()? {
+        let row = fields.and_then(|fields| {
+            if fields.len() != columns.len() {
+                return Err(DatabaseError::parse_error(format!("有 {} 个字段，期望 {} 个", fields.len(), columns.len())));
+            }
+            let mut row = Row::new();
+            for ((column, data_type), field) in columns.iter().zip(&types).zip(fields) {
+                let value = match field {
+                    None => Value::Null,
+                    Some(text) => Value::Text(text.clone()).coerce_to(data_type).ok_or_else(|| {
+                        DatabaseError::type_mismatch(format!("列 '{}' 的值 '{}' 无法转换为 {}", column, text, data_type))
+                    })?,
+                };
+                row.set(column.clone(), value);
+            }
+            schema.validate_row(&row)?;
+            Ok(row)
+        });
+        match row {
+            Ok(row) => rows.push(SourceRow { line, record: text, row }),
+            Err(e) => report.reject(line, text, &e),
+        }
+    }
+    Ok((rows, report))
+}
+
+/// 读取 NDJSON 数据，每行一个 JSON 对象，值按列类型转换；未知列保留 JSON 推断的类型
+///
+/// 与 `read_rows` 一样，无法解析或转换的行计入报告并跳过。
+pub fn read_json_rows<R: BufRead>(reader: R, schema: &Schema) -> Result<(Vec<SourceRow>, ImportReport)> {
+    let mut rows = Vec::new();
+    let mut report = ImportReport::default();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let row = serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&line)
+            .map_err(DatabaseError::from)
+            .and_then(|object| {
+                let mut row = Row::new();
+                for (column, json) in object {
+                    let data_type = schema.get_column(&column).map(|c| &c.data_type);
+                    row.set(column, Value::from_json(&json, data_type)?);
+                }
+                Ok(row)
+            });
+        match row {
+            Ok(row) => rows.push(SourceRow { line: index + 1, record: line, row }),
+            Err(e) => report.reject(index + 1, line, &e),
+        }
+    }
+    Ok((rows, report))
+}
+
+/// 读取前 `sample_rows` 条记录推断表结构
+///
+/// 列名取自表头（或 `options.columns`），都没有时为 `column1`、`column2`……；
+/// 每列取样本中所有非空值都能转换的第一个类型，依次尝试 INTEGER、FLOAT、BOOLEAN、DATE，否则为 TEXT。
+/// 推断出的列都允许为空，不设主键。无法解析的记录不参与推断。
+pub fn infer_schema<R: BufRead>(reader: R, options: &CopyOptions, sample_rows: usize) -> Result<Schema> {
+    let mut records = RecordReader::new(reader, options);
+    let mut columns = options.columns.clone();
+    if options.header {
+        if let Some(Record { line, fields: header, .. }) = records.next_record()? {
+            let header = header.map_err(|e| DatabaseError::parse_error(format!("第 {} 行: {}", line, e)))?;
+            if columns.is_empty() {
+                columns = header.into_iter().map(Option::unwrap_or_default).collect();
+            }
+        }
+    }
+
+    // 每列仍然可能的类型
+    const CANDIDATES: [DataType; 4] = [DataType::Integer, DataType::Float, DataType::Boolean, DataType::Date];
+    let mut possible: Vec<Vec<DataType>> = vec![CANDIDATES.to_vec(); columns.len()];
+    let mut sampled = 0;
+    while sampled < sample_rows {
+        let Some(record) = records.next_record()? else { break };
+        let Ok(fields) = record.fields else { continue };
+        if columns.is_empty() {
+            columns = (1..=fields.len()).map(|i| format!("column{}", i)).collect();
+            possible = vec![CANDIDATES.to_vec(); columns.len()];
+        }
+        for (types, field) in possible.iter_mut().zip(&fields) {
+            if let Some(text) = field.as_deref().filter(|text| !text.trim().is_empty()) {
+                types.retain(|data_type| Value::Text(text.to_string()).coerce_to(data_type).is_some());
+            }
+        }
+        sampled += 1;
+    }
+    if columns.is_empty() {
+        return Err(DatabaseError::parse_error("没有可用于推断表结构的数据"));
+    }
+
+    Ok(Schema::new(columns.into_iter()
+        .zip(possible)
+        .map(|(name, types)| {
+            let data_type = types.into_iter().next().unwrap_or(DataType::Text);
+            ColumnDefinition::new(name, data_type, false)
+        })
+        .collect()))
+}
+
+/// 一条记录：起始行号、去掉行尾换行的原始文本与字段（None 为 NULL，无法解析时为错误）
+struct Record {
+    line: usize,
+    text: String,
+    fields: Result<Vec<Option<String>>>,
+}
+
+/// 逐条读取记录，CSV 中加引号的字段可以跨行
+struct RecordReader<'a, R> {
+    reader: R,
+    options: &'a CopyOptions,
+    delimiter: char,
+    line: usize,
+}
+
+impl<'a, R: BufRead> RecordReader<'a, R> {
+    fn new(reader: R, options: &'a CopyOptions) -> Self {
+        Self { reader, options, delimiter: options.field_delimiter(), line: 0 }
+    }
+
+    /// 读取下一条记录，数据结束时返回 None
+    fn next_record(&mut self) -> Result<Option<Record>> {
+        let mut text = String::new();
+        let mut first_line = self.line + 1;
+        loop {
+            if self.reader.read_line(&mut text)? == 0 {
+                if text.is_empty() {
+                    return Ok(None);
+                }
+                break;
+            }
+            self.line += 1;
+            // 跳过记录之间的空行
+            if text.trim_end_matches(['\n', '\r']).is_empty() {
+                text.clear();
+                first_line = self.line + 1;
+                continue;
+            }
+            if self.options.format == CopyFormat::Tsv || !in_quotes(&text) {
+                break;
+            }
+        }
+
+        if text.ends_with('\n') {
+            text.pop();
+        }
+        if text.ends_with('\r') {
+            text.pop();
+        }
+        let fields = match self.options.format {
+            CopyFormat::Csv => split_csv(&text, self.delimiter, self.options.null.as_deref()),
+            CopyFormat::Tsv => Ok(split_tsv(&text, self.delimiter, self.options.null.as_deref().unwrap_or("\\N"))),
+        };
+        Ok(Some(Record { line: first_line, text, fields }))
+    }
+}
+
+/// 文本末尾是否仍在引号内
+fn in_quotes(text: &str) -> bool {
+    text.matches('"').count() % 2 == 1
+}
+
+fn split_csv(record: &str, delimiter: char, null: Option<&str>) -> Result<Vec<Option<String>>> {
+    let mut fields = Vec::new();
+    let mut chars = record.chars().peekable();
+    loop {
+        let mut field = String::new();
+        let quoted = chars.peek() == Some(&'"');
+        if quoted {
+            chars.next();
+            loop {
+                match chars.next() {
+                    None => return Err(DatabaseError::parse_error("引号未闭合")),
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    }
+                    Some('"') => break,
+                    Some(c) => field.push(c),
+                }
+            }
+            if chars.peek().is_some_and(|&c| c != delimiter) {
+                return Err(DatabaseError::parse_error("引号后应为分隔符"));
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c == delimiter {
+                    break;
+                }
+                field.push(c);
+                chars.next();
+            }
+        }
+
+        let is_null = !quoted && null.map_or(field.is_empty(), |null| field == null);
+        fields.push(if is_null { None } else { Some(field) });
+        if chars.next().is_none() {
+            return Ok(fields);
+        }
+    }
+}
+
+fn split_tsv(record: &str, delimiter: char, null: &str) -> Vec<Option<String>> {
+    record.split(delimiter)
+        .map(|field| {
+            if field == null {
+                return None;
+            }
+            let mut text = String::with_capacity(field.len());
+            let mut chars = field.chars();
+            while let Some(c) = chars.next() {
+                if c != '\\' {
+                    text.push(c);
+                    continue;
+                }
+                match chars.next() {
+                    Some('t') => text.push('\t'),
+                    Some('n') => text.push('\n'),
+                    Some('r') => text.push('\r'),
+                    Some(other) => text.push(other),
+                    None => text.push('\\'),
+                }
+            }
+            Some(text)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_csv_and_tsv_rows() {
+        let schema = Schema::new(vec![
+            ColumnDefinition::new("id", DataType::Integer, true),
+            ColumnDefinition::new("name", DataType::Text, false),
+            ColumnDefinition::new("score", DataType::Float, false),
+        ]);
+
+        let csv = "name,id,score\n\"Smith, \"\"J\"\"\",1,2.5\nbob,x,1\n\n\"multi\nline\",3,\nbroken,4\n";
+        let (rows, report) = read_rows(csv.as_bytes(), &schema, &CopyOptions::new(CopyFormat::Csv).header(true)).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].row.get("name"), Some(&Value::Text("Smith, \"J\"".to_string())));
+        assert_eq!(rows[1].row.get("name"), Some(&Value::Text("multi\nline".to_string())));
+        assert_eq!(rows[1].row.get("score"), Some(&Value::Null));
+        let lines: Vec<usize> = report.errors.iter().map(|rejected| rejected.line).collect();
+        assert_eq!((report.rejected, lines), (2, vec![3, 7]));
+        assert!(report.errors[0].error.contains("'x'"));
+        assert_eq!((rows[1].line, report.errors[0].record.as_str()), (5, "bob,x,1"));
+
+        let tsv = "1\ta\\tb\t\\N\n\\N\tc\t1\n";
+        let (rows, report) = read_rows(tsv.as_bytes(), &schema, &CopyOptions::new(CopyFormat::Tsv)).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].row.get("name"), Some(&Value::Text("a\tb".to_string())));
+        // 主键为 NULL
+        assert_eq!(report.errors[0].line, 2);
+
+        let sample = "n,ratio,flag,day,note\n1,1,yes,2024-01-02,a\n2,2.5,no,,b\n,3,true,2024-02-03,4\n";
+        let inferred = infer_schema(sample.as_bytes(), &CopyOptions::new(CopyFormat::Csv).header(true), INFER_SAMPLE_ROWS).unwrap();
+        let types: Vec<String> = inferred.columns.iter().map(|column| column.data_type.to_string()).collect();
+        assert_eq!(types, vec!["INTEGER", "FLOAT", "BOOLEAN", "DATE", "TEXT"]);
+        assert!(inferred.columns.iter().all(|column| column.nullable && !column.primary_key));
+        // 只取样第一行数据时第二列仍是整数；没有表头时按位置命名
+        let inferred = infer_schema(sample.as_bytes(), &CopyOptions::new(CopyFormat::Csv).header(true), 1).unwrap();
+        assert_eq!(inferred.columns[1].data_type, DataType::Integer);
+        let inferred = infer_schema("1\tx\n".as_bytes(), &CopyOptions::new(CopyFormat::Tsv), 10).unwrap();
+        assert_eq!(inferred.columns[1].name, "column2");
+
+        let options = CopyOptions::new(CopyFormat::Csv).columns(vec!["missing".to_string()]);
+        assert!(read_rows("1\n".as_bytes(), &schema, &options).is_err());
+        assert_eq!("TEXT".parse::<CopyFormat>().unwrap(), CopyFormat::Tsv);
+    }
+}
@@ -0,0 +1,90 @@
+//! 完整性检查的报告
+//!
+//! `DatabaseEngine::check_integrity` 逐表检查行与位置索引、分区、溢出键以及约束是否一致，
+//! 并校验数据目录中快照、表文件与日志段的校验和，发现的问题汇总在 [`IntegrityReport`] 中，不修改任何数据。
+//! 表结构没有外键约束，因此不存在需要检查的孤立外键引用。
+
+use std::fmt;
+
+/// 问题的种类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueKind {
+    /// 位置索引、分区或溢出键与行数据不一致
+    Index,
+    /// 已保存的行违反表结构的约束：类型、非空或唯一
+    Constraint,
+    /// 快照清单或表文件无法读取、校验和不匹配
+    Snapshot,
+    /// 日志条目损坏或编号不递增
+    Log,
+}
+
+impl fmt::Display for IssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            IssueKind::Index => "索引",
+            IssueKind::Constraint => "约束",
+            IssueKind::Snapshot => "快照",
+            IssueKind::Log => "日志",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// 发现的一个问题，`location` 为表名或文件路径
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntegrityIssue {
+    pub kind: IssueKind,
+    pub location: String,
+    pub message: String,
+}
+
+impl IntegrityIssue {
+    pub fn new<L: Into<String>, M: Into<String>>(kind: IssueKind, location: L, message: M) -> Self {
+        Self { kind, location: location.into(), message: message.into() }
+    }
+}
+
+/// 完整性检查的结果
+#[derive(Debug, Clone, Default)]
+pub struct IntegrityReport {
+    pub tables_checked: usize,
+    pub rows_checked: usize,
+    /// 校验过的快照清单与表文件数
+    pub files_checked: usize,
+    pub log_entries_checked: usize,
+    pub issues: Vec<IntegrityIssue>,
+}
+
+impl IntegrityReport {
+    /// 没有发现任何问题
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    pub fn push<L: Into<String>, M: Into<String>>(&mut self, kind: IssueKind, location: L, message: M) {
+        self.issues.push(IntegrityIssue::new(kind, location, message));
+    }
+
+    /// 某一种类的问题
+    pub fn issues_of(&self, kind: IssueKind) -> impl Iterator<Item = &IntegrityIssue> {
+        self.issues.iter().filter(move |issue| issue.kind == kind)
+    }
+}
+
+impl fmt::Display for IntegrityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f, "检查了 {} 张表、{} 行、{} 个快照文件、{} 条日志",
+            self.tables_checked, self.rows_checked, self.files_checked, self.log_entries_checked
+        )?;
+        if self.is_ok() {
+            return write!(f, "未发现问题");
+        }
+        writeln!(f, "发现 {} 个问题:", self.issues.len())?;
+        for issue in &self.issues {
+            writeln!(f, "  [{}] {}: {}", issue.kind, issue.location, issue.message)?;
+        }
+        Ok(())
+    }
+}
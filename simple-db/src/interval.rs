@@ -0,0 +1,197 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{Duration, Months, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{DatabaseError, Result};
+
+const MICROS_PER_SECOND: i64 = 1_000_000;
+const MICROS_PER_DAY: i64 = 86_400 * MICROS_PER_SECOND;
+
+/// 时间间隔：月数、天数与微秒数分别保存
+///
+/// 月与天的长度不固定，加到日期上时先按日历加月，再加天数与微秒，与 PostgreSQL 的 INTERVAL 一致。
+/// 比较时按一个月 30 天、一天 24 小时换算。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Interval {
+    pub months: i32,
+    pub days: i32,
+    pub micros: i64,
+}
+
+impl Interval {
+    pub fn new(months: i32, days: i32, micros: i64) -> Self {
+        Self { months, days, micros }
+    }
+
+    pub fn days(days: i32) -> Self {
+        Self::new(0, days, 0)
+    }
+
+    /// 只有月与天，加到日期上结果仍是日期
+    pub fn is_whole_days(&self) -> bool {
+        self.micros == 0
+    }
+
+    pub fn checked_add(&self, other: &Interval) -> Option<Interval> {
+        Some(Interval {
+            months: self.months.checked_add(other.months)?,
+            days: self.days.checked_add(other.days)?,
+            micros: self.micros.checked_add(other.micros)?,
+        })
+    }
+
+    pub fn checked_neg(&self) -> Option<Interval> {
+        Some(Interval { months: self.months.checked_neg()?, days: self.days.checked_neg()?, micros: self.micros.checked_neg()? })
+    }
+
+    /// 各部分分别乘以 `factor`，小数部分向下一级换算，例如 `INTERVAL '1 month' * 1.5` 为 1 个月 15 天
+    pub fn checked_mul(&self, factor: f64) -> Option<Interval> {
+        let months = self.months as f64 * factor;
+        let days = self.days as f64 * factor + months.fract() * 30.0;
+        let micros = self.micros as f64 * factor + days.fract() * MICROS_PER_DAY as f64;
+        let fits = |value: f64, max: f64| value.is_finite() && value.abs() <= max;
+        (fits(months, i32::MAX as f64) && fits(days, i32::MAX as f64) && fits(micros, i64::MAX as f64))
+            .then(|| Interval::new(months.trunc() as i32, days.trunc() as i32, micros.round() as i64))
+    }
+
+    /// 按一个月 30 天换算的总微秒数，用于比较
+    fn total_micros(&self) -> i128 {
+        (self.months as i128 * 30 + self.days as i128) * MICROS_PER_DAY as i128 + self.micros as i128
+    }
+
+    /// 加到日期时间上，先加月再加天与微秒
+    pub fn add_to(&self, datetime: NaiveDateTime) -> Option<NaiveDateTime> {
+        let datetime = match self.months.cmp(&0) {
+            Ordering::Greater => datetime.checked_add_months(Months::new(self.months as u32))?,
+            Ordering::Less => datetime.checked_sub_months(Months::new(self.months.unsigned_abs()))?,
+            Ordering::Equal => datetime,
+        };
+        datetime.checked_add_signed(Duration::try_days(self.days as i64)?)?
+            .checked_add_signed(Duration::microseconds(self.micros))
+    }
+}
+
+impl PartialOrd for Interval {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Interval {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.total_micros().cmp(&other.total_micros())
+            .then_with(|| (self.months, self.days, self.micros).cmp(&(other.months, other.days, other.micros)))
+    }
+}
+
+/// 解析 `90 days`、`1 year 2 months`、`-3 hours 30 minutes`、`1 day 02:30:00` 这样的文本
+impl FromStr for Interval {
+    type Err = DatabaseError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || DatabaseError::parse_error(format!("无效的时间间隔: '{}'", s));
+        let mut interval = Interval::default();
+        let mut words = s.split_whitespace().peekable();
+        if words.peek().is_none() {
+            return Err(invalid());
+        }
+        while let Some(word) = words.next() {
+            if word.contains(':') {
+                let (negative, clock) = match word.strip_prefix('-') {
+                    Some(clock) => (true, clock),
+                    None => (false, word),
+                };
+                let parts: Vec<&str> = clock.split(':').collect();
+                let [hours, minutes, seconds] = match parts.as_slice() {
+                    [hours, minutes] => [*hours, *minutes, "0"],
+                    [hours, minutes, seconds] => [*hours, *minutes, *seconds],
+                    _ => return Err(invalid()),
+                };
+                let hours: i64 = hours.parse().map_err(|_| invalid())?;
+                let minutes: i64 = minutes.parse().map_err(|_| invalid())?;
+                let seconds: f64 = seconds.parse().map_err(|_| invalid())?;
+                let micros = (hours * 3600 + minutes * 60) * MICROS_PER_SECOND + (seconds * MICROS_PER_SECOND as f64).round() as i64;
+                interval.micros += if negative { -micros } else { micros };
+                continue;
+            }
+            let amount: f64 = word.parse().map_err(|_| invalid())?;
+            let unit = words.next().ok_or_else(invalid)?.to_lowercase();
+            let whole = |amount: f64| (amount.fract() == 0.0 && amount.abs() <= i32::MAX as f64).then_some(amount as i32).ok_or_else(invalid);
+            let micros = |per_unit: f64| (amount * per_unit).round() as i64;
+            match unit.trim_end_matches(',') {
+                "year" | "years" | "yr" | "yrs" => interval.months += whole(amount * 12.0)?,
+                "month" | "months" | "mon" | "mons" => interval.months += whole(amount)?,
+                "week" | "weeks" => interval.days += whole(amount * 7.0)?,
+                "day" | "days" => interval.days += whole(amount)?,
+                "hour" | "hours" | "hr" | "hrs" => interval.micros += micros(3600.0 * MICROS_PER_SECOND as f64),
+                "minute" | "minutes" | "min" | "mins" => interval.micros += micros(60.0 * MICROS_PER_SECOND as f64),
+                "second" | "seconds" | "sec" | "secs" => interval.micros += micros(MICROS_PER_SECOND as f64),
+                "millisecond" | "milliseconds" | "ms" => interval.micros += micros(1000.0),
+                "microsecond" | "microseconds" | "us" => interval.micros += micros(1.0),
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(interval)
+    }
+}
+
+/// 输出 `1 year 2 months 3 days 04:05:06` 这样的文本，可以再解析回来
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plural = |n: i32, unit: &str| format!("{} {}{}", n, unit, if n.abs() == 1 { "" } else { "s" });
+        let mut parts = Vec::new();
+        let (years, months) = (self.months / 12, self.months % 12);
+        if years != 0 {
+            parts.push(plural(years, "year"));
+        }
+        if months != 0 {
+            parts.push(plural(months, "month"));
+        }
+        if self.days != 0 {
+            parts.push(plural(self.days, "day"));
+        }
+        if self.micros != 0 || parts.is_empty() {
+            let sign = if self.micros < 0 { "-" } else { "" };
+            let micros = self.micros.unsigned_abs();
+            let (seconds, fraction) = (micros / MICROS_PER_SECOND as u64, micros % MICROS_PER_SECOND as u64);
+            let mut clock = format!("{}{:02}:{:02}:{:02}", sign, seconds / 3600, seconds / 60 % 60, seconds % 60);
+            if fraction != 0 {
+                clock.push_str(&format!(".{:06}", fraction));
+            }
+            parts.push(clock);
+        }
+        f.write_str(&parts.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_display_and_calendar_arithmetic() {
+        assert_eq!("90 days".parse::<Interval>().unwrap(), Interval::days(90));
+        assert_eq!("1 year 2 mons".parse::<Interval>().unwrap(), Interval::new(14, 0, 0));
+        assert_eq!("2 weeks -1 day".parse::<Interval>().unwrap(), Interval::days(13));
+        assert_eq!("1.5 hours".parse::<Interval>().unwrap(), Interval::new(0, 0, 5_400_000_000));
+        assert_eq!("1 day 02:30".parse::<Interval>().unwrap(), Interval::new(0, 1, 9_000_000_000));
+        assert!("90".parse::<Interval>().is_err());
+        assert!("1.5 days".parse::<Interval>().is_err());
+        assert!("3 fortnights".parse::<Interval>().is_err());
+
+        let interval = Interval::new(14, 3, 14_706_000_001);
+        assert_eq!(interval.to_string(), "1 year 2 months 3 days 04:05:06.000001");
+        assert_eq!(interval.to_string().parse::<Interval>().unwrap(), interval);
+        assert_eq!(Interval::default().to_string(), "00:00:00");
+
+        // 一个月按日历计算，月末对齐到较短月份的最后一天
+        let jan31 = chrono::NaiveDate::from_ymd_opt(2024, 1, 31).unwrap().and_hms_opt(12, 0, 0).unwrap();
+        let feb29 = chrono::NaiveDate::from_ymd_opt(2024, 2, 29).unwrap().and_hms_opt(12, 0, 0).unwrap();
+        assert_eq!(Interval::new(1, 0, 0).add_to(jan31), Some(feb29));
+        assert!(Interval::new(1, 0, 0) > Interval::days(29));
+        assert_eq!(Interval::new(1, 0, 0).checked_mul(1.5), Some(Interval::new(1, 15, 0)));
+    }
+}
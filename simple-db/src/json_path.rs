@@ -0,0 +1,122 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{DatabaseError, Result};
+
+/// JSON 路径中的一段
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// JSON 路径，例如 `$.address.city`、`$.tags[0]`、`$["first name"]`
+///
+/// 路径以原始文本保存，序列化后与写法一致；求值时再解析。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct JsonPath(String);
+
+impl JsonPath {
+    pub fn new<S: Into<String>>(path: S) -> Self {
+        Self(path.into())
+    }
+
+    /// 解析并校验路径
+    pub fn parse(path: &str) -> Result<Self> {
+        let path = Self::new(path);
+        path.segments()?;
+        Ok(path)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn segments(&self) -> Result<Vec<PathSegment>> {
+        let error = |reason: &str| DatabaseError::parse_error(format!("JSON 路径 '{}' {}", self.0, reason));
+        let chars: Vec<char> = self.0.trim().chars().collect();
+        let mut i = usize::from(chars.first() == Some(&'$'));
+        let mut segments = Vec::new();
+
+        while i < chars.len() {
+            match chars[i] {
+                '.' => {
+                    let start = i + 1;
+                    i = start;
+                    while i < chars.len() && chars[i] != '.' && chars[i] != '[' {
+                        i += 1;
+                    }
+                    if i == start {
+                        return Err(error("缺少键名"));
+                    }
+                    segments.push(PathSegment::Key(chars[start..i].iter().collect()));
+                }
+                '[' => {
+                    let end = chars[i..].iter().position(|&c| c == ']').map(|offset| i + offset)
+                        .ok_or_else(|| error("缺少 ']'"))?;
+                    let inner: String = chars[i + 1..end].iter().collect();
+                    let inner = inner.trim();
+                    let quoted = inner.len() >= 2
+                        && ((inner.starts_with('"') && inner.ends_with('"')) || (inner.starts_with('\'') && inner.ends_with('\'')));
+                    if quoted {
+                        segments.push(PathSegment::Key(inner[1..inner.len() - 1].to_string()));
+                    } else {
+                        let index = inner.parse::<usize>().map_err(|_| error("的下标必须是非负整数"))?;
+                        segments.push(PathSegment::Index(index));
+                    }
+                    i = end + 1;
+                }
+                c => return Err(error(&format!("在 '{}' 处无法解析", c))),
+            }
+        }
+        Ok(segments)
+    }
+
+    /// 取出路径指向的值，路径不存在时返回 None
+    pub fn select<'a>(&self, json: &'a serde_json::Value) -> Result<Option<&'a serde_json::Value>> {
+        let mut current = json;
+        for segment in self.segments()? {
+            let next = match segment {
+                PathSegment::Key(key) => current.get(key.as_str()),
+                PathSegment::Index(index) => current.get(index),
+            };
+            match next {
+                Some(value) => current = value,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(current))
+    }
+}
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_parsing_and_selection() {
+        let json = serde_json::json!({
+            "address": { "city": "Paris", "first line": "1 Rue" },
+            "tags": ["a", "b"],
+        });
+
+        let select = |path: &str| JsonPath::parse(path).unwrap().select(&json).unwrap().cloned();
+        assert_eq!(select("$.address.city"), Some(serde_json::json!("Paris")));
+        assert_eq!(select("$.address[\"first line\"]"), Some(serde_json::json!("1 Rue")));
+        assert_eq!(select("$.tags[1]"), Some(serde_json::json!("b")));
+        assert_eq!(select("$.tags[5]"), None);
+        assert_eq!(select("$.missing.city"), None);
+        assert_eq!(select("$"), Some(json.clone()));
+
+        assert!(JsonPath::parse("$.").is_err());
+        assert!(JsonPath::parse("$.tags[x]").is_err());
+        assert!(JsonPath::parse("address").is_err());
+    }
+}
@@ -0,0 +1,82 @@
+//! 最早版本数据目录的升级
+//!
+//! 最早的版本把日志写在不带校验和与版本字段的 `transaction.log` 中，每次启动都从 1 重新编号，
+//! 加载时静默忽略无法解析的行；快照没有文件头，`last_log_id` 是写快照的那次启动中的编号。
+//! 按当前规则读取这样的目录会报告日志损坏，或因编号重复而漏掉之后几次启动写入的条目，
+//! 因此加载时先由 `StorageEngine::upgrade_log` 用这里的转换把它整理成当前版本能读取的形式。
+
+use chrono::{DateTime, Utc};
+
+use crate::storage::LogEntry;
+
+/// 从最早版本的日志文件中读出的条目
+#[derive(Debug)]
+pub struct LegacyLog {
+    pub entries: Vec<LogEntry>,
+    /// 无法解析的行数，最早的版本加载时同样忽略这些行
+    pub skipped: usize,
+}
+
+impl LegacyLog {
+    /// 解析最早版本写入的日志
+    ///
+    /// 含有带校验和或版本字段的行时说明之后的版本已经写入过，返回 None，按当前规则读取。
+    pub fn parse(content: &str) -> Option<Self> {
+        let mut log = Self { entries: Vec::new(), skipped: 0 };
+        for line in content.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            if !line.starts_with('{') {
+                return None;
+            }
+            match LogEntry::decode(line) {
+                Ok(entry) if entry.version > 1 => return None,
+                Ok(entry) => log.entries.push(entry),
+                Err(_) => log.skipped += 1,
+            }
+        }
+        Some(log)
+    }
+
+    /// 编号是否需要重排：多次启动后编号不再递增
+    pub fn needs_renumber(&self) -> bool {
+        self.entries.windows(2).any(|pair| pair[1].id <= pair[0].id)
+    }
+
+    /// 按文件顺序从 1 重新编号
+    pub fn renumber(&mut self) {
+        for (index, entry) in self.entries.iter_mut().enumerate() {
+            entry.id = index as u64 + 1;
+        }
+    }
+
+    /// 写于 `snapshot_time` 的快照在重新编号后的日志中的位置
+    ///
+    /// 最早的版本在持有读锁时收集表再记录时间，此前写入的条目都已包含在快照中。
+    pub fn snapshot_position(&self, snapshot_time: DateTime<Utc>) -> u64 {
+        self.entries.iter().take_while(|entry| entry.timestamp <= snapshot_time).count() as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_renumber_legacy_log() {
+        let line = |id: u64, second: u32| {
+            format!(r#"{{"id":{},"timestamp":"2025-09-29T01:57:{:02}Z","operation":{{"Drop":{{"table":"t"}}}}}}"#, id, second)
+        };
+        // 第二次启动从 1 重新编号，两次启动的输出交错成一行
+        let content = [line(1, 0), line(2, 1), line(3, 2), format!("{}{}", line(1, 3), line(2, 3)), line(1, 4), line(2, 5)].join("\n");
+        let mut log = LegacyLog::parse(&content).unwrap();
+        assert_eq!(log.skipped, 1);
+        assert!(log.needs_renumber());
+        log.renumber();
+        assert_eq!(log.entries.iter().map(|entry| entry.id).collect::<Vec<_>>(), [1, 2, 3, 4, 5]);
+        assert!(!log.needs_renumber());
+        assert_eq!(log.snapshot_position("2025-09-29T01:57:02.5Z".parse().unwrap()), 3);
+
+        // 之后的版本写入的带校验和的行按当前规则读取
+        let current = LogEntry::new(4, crate::storage::StorageOperation::Drop { table: "t".to_string() }).encode().unwrap();
+        assert!(LegacyLog::parse(&format!("{}\n{}", line(1, 0), current)).is_none());
+    }
+}
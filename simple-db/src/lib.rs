@@ -1,14 +1,88 @@
+// 让派生宏生成的 `::simple_db::...` 路径在本 crate 内同样可用
+extern crate self as simple_db;
+
 pub mod error;
+pub mod binary;
 pub mod storage;
+pub mod backend;
 pub mod query;
 pub mod types;
 pub mod engine;
+pub mod procedure;
+pub mod sequence;
+pub mod columnar;
+pub mod row_store;
+pub mod vectorized;
+pub mod simd;
+pub mod spill;
+pub mod partition;
+pub mod replication;
+pub mod backup;
+pub mod sql;
+pub mod migrations;
+pub mod legacy;
+pub mod catalog;
+pub mod mapping;
+pub mod collation;
+pub mod json_path;
+pub mod expression;
+pub mod functions;
+pub mod element_index;
+pub mod geo;
+pub mod zone_map;
+pub mod interval;
+pub mod stats;
+pub mod slow_log;
+pub mod output;
+pub mod import;
+pub mod audit;
+pub mod session;
+pub mod completion;
+pub mod bench;
+pub mod shared;
+pub mod shard;
+pub mod lock;
+pub mod integrity;
+pub mod clock;
+pub mod simulation;
+#[cfg(feature = "arrow")]
+pub mod arrow_export;
+#[cfg(feature = "sled")]
+pub mod sled_backend;
+#[cfg(feature = "object-store")]
+pub mod object_store_backend;
+#[cfg(all(feature = "browser", target_arch = "wasm32"))]
+pub mod local_storage_backend;
 
 pub use error::{DatabaseError, Result};
-pub use storage::StorageEngine;
-pub use query::{Query, QueryResult, QueryEngine};
-pub use types::{Value, Row, Table, Schema, DataType};
+pub use storage::{StorageEngine, RetentionPolicy, RetentionAction, ArchiveStatus};
+pub use query::{Query, QueryResult, QueryEngine, Params, Placeholder};
+pub use types::{Value, Row, Table, TableOptions, Schema, DataType, ValidationMode};
+pub use interval::Interval;
 pub use engine::DatabaseEngine;
+pub use backend::{MemoryBackend, StorageBackend};
+pub use session::Session;
+pub use lock::{LockInfo, LockMode, LockTarget};
+pub use integrity::{IntegrityIssue, IntegrityReport, IssueKind};
+pub use clock::{Clock, SystemClock, VirtualClock};
+pub use simulation::{FaultConfig, FaultPoint, FaultyBackend, SimRng, SimulatedEngine};
+pub use shard::{ShardedEngine, ShardedTransaction};
+pub use procedure::{Procedure, ProcedureStep};
+pub use sequence::Sequence;
+pub use vectorized::Aggregate;
+pub use columnar::TableLayout;
+pub use backup::{BackupSink, TarSink};
+#[cfg(feature = "native")]
+pub use backup::DirectorySink;
+pub use partition::PartitionSpec;
+pub use collation::Collation;
+pub use json_path::JsonPath;
+pub use expression::{BinaryOperator, Expr, Projection};
+pub use stats::{ColumnStats, TableStats};
+pub use slow_log::{SlowQuery, SlowQueryLog};
+pub use migrations::{Migration, MigrationStep, Migrator};
+pub use mapping::{FromRow, FromValue, ToRow};
+pub use simple_db_derive::{FromRow, ToRow};
 
 use std::collections::HashMap;
 use std::sync::Arc;
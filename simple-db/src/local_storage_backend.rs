@@ -0,0 +1,99 @@
+use wasm_bindgen::JsValue;
+use web_sys::Storage;
+
+use crate::backend::StorageBackend;
+use crate::error::{DatabaseError, Result};
+use crate::storage::{LogEntry, Snapshot};
+
+/// 保存快照的键，位于前缀之下
+const SNAPSHOT_KEY: &str = "snapshot";
+/// 日志条目的键为该前缀加 20 位十进制的日志 ID，按键排序即按 ID 排序
+const LOG_KEY_PREFIX: &str = "log/";
+
+/// 把快照与日志保存在浏览器 localStorage 中的持久化后端，仅在 wasm32 上可用
+///
+/// 所有键都以 `<prefix>/` 开头，同一页面中不同前缀的数据库互不影响。每条日志单独保存在一个键中，
+/// 追加不必重写之前的日志；快照序列化为 JSON 保存在一个键中，保存后删除被快照覆盖的日志。
+/// localStorage 的容量通常只有几 MB，超出时写入返回错误。
+pub struct LocalStorageBackend {
+    prefix: String,
+}
+
+impl LocalStorageBackend {
+    /// 使用 `prefix` 下的键；页面不允许访问 localStorage 时返回错误
+    pub fn new<S: Into<String>>(prefix: S) -> Result<Self> {
+        local_storage()?;
+        Ok(Self { prefix: prefix.into() })
+    }
+
+    fn key(&self, name: &str) -> String {
+        format!("{}/{}", self.prefix, name)
+    }
+
+    fn log_key(&self, id: u64) -> String {
+        self.key(&format!("{}{:020}", LOG_KEY_PREFIX, id))
+    }
+
+    /// 前缀下全部日志条目的 (ID, 键)，按 ID 排序
+    fn log_keys(&self, storage: &Storage) -> Result<Vec<(u64, String)>> {
+        let log_prefix = self.key(LOG_KEY_PREFIX);
+        let mut keys = Vec::new();
+        for index in 0..storage.length().map_err(js_error)? {
+            let Some(key) = storage.key(index).map_err(js_error)? else {
+                continue;
+            };
+            if let Some(id) = key.strip_prefix(&log_prefix).and_then(|id| id.parse().ok()) {
+                keys.push((id, key));
+            }
+        }
+        keys.sort_unstable();
+        Ok(keys)
+    }
+}
+
+impl StorageBackend for LocalStorageBackend {
+    fn append(&self, entry: &LogEntry) -> Result<()> {
+        local_storage()?.set_item(&self.log_key(entry.id), &entry.encode()?).map_err(js_error)
+    }
+
+    fn read_log(&self, from_id: u64) -> Result<Vec<LogEntry>> {
+        let storage = local_storage()?;
+        let mut entries = Vec::new();
+        for (_, key) in self.log_keys(&storage)?.into_iter().filter(|(id, _)| *id > from_id) {
+            let Some(line) = storage.get_item(&key).map_err(js_error)? else {
+                continue;
+            };
+            let entry = LogEntry::decode(&line)
+                .map_err(|reason| DatabaseError::corrupt_log(entries.len() + 1, reason))?;
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
+    fn save_snapshot(&self, snapshot: &Snapshot) -> Result<()> {
+        let storage = local_storage()?;
+        storage.set_item(&self.key(SNAPSHOT_KEY), &serde_json::to_string(snapshot)?).map_err(js_error)?;
+        for (_, key) in self.log_keys(&storage)?.into_iter().filter(|(id, _)| *id <= snapshot.last_log_id) {
+            storage.remove_item(&key).map_err(js_error)?;
+        }
+        Ok(())
+    }
+
+    fn load_snapshot(&self) -> Result<Option<Snapshot>> {
+        let json = local_storage()?.get_item(&self.key(SNAPSHOT_KEY)).map_err(js_error)?;
+        json.as_deref().map(serde_json::from_str).transpose().map_err(Into::into)
+    }
+}
+
+/// 当前页面的 localStorage；`Storage` 不能跨线程传递，每次调用时重新获取
+fn local_storage() -> Result<Storage> {
+    web_sys::window()
+        .ok_or_else(|| DatabaseError::other("当前环境没有 window，无法使用 localStorage"))?
+        .local_storage()
+        .map_err(js_error)?
+        .ok_or_else(|| DatabaseError::other("当前页面不允许访问 localStorage"))
+}
+
+fn js_error(error: JsValue) -> DatabaseError {
+    DatabaseError::other(format!("localStorage 操作失败: {:?}", error))
+}
@@ -0,0 +1,221 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Mutex;
+
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+use crate::error::{DatabaseError, Result};
+
+/// 加锁的对象：整张表或表中的一行
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum LockTarget {
+    Table(String),
+    Row { table: String, id: Uuid },
+}
+
+impl LockTarget {
+    pub fn table(&self) -> &str {
+        match self {
+            LockTarget::Table(table) | LockTarget::Row { table, .. } => table,
+        }
+    }
+
+    /// 同一个对象，或者表与该表中的行
+    fn overlaps(&self, other: &LockTarget) -> bool {
+        match (self, other) {
+            (LockTarget::Row { id: a, .. }, LockTarget::Row { id: b, .. }) => a == b && self.table() == other.table(),
+            _ => self.table() == other.table(),
+        }
+    }
+}
+
+impl fmt::Display for LockTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockTarget::Table(table) => write!(f, "{}", table),
+            LockTarget::Row { table, id } => write!(f, "{}/{}", table, id),
+        }
+    }
+}
+
+/// 锁模式：共享锁之间相容，排他锁与任何锁都不相容
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LockMode {
+    Shared,
+    Exclusive,
+}
+
+impl fmt::Display for LockMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LockMode::Shared => "SHARED",
+            LockMode::Exclusive => "EXCLUSIVE",
+        })
+    }
+}
+
+/// 一把锁的持有者或等待者，见 `DatabaseEngine::lock_info`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockInfo {
+    pub target: LockTarget,
+    pub mode: LockMode,
+    /// 事务编号
+    pub transaction: u64,
+    /// 已持有为 true，仍在等待为 false
+    pub granted: bool,
+}
+
+/// 事务锁管理器
+///
+/// 表锁与该表上的行锁互相覆盖：表上的排他锁与该表任何一行的锁冲突。同一事务重复加锁不会阻塞自己，
+/// 持有共享锁时再请求排他锁即为升级。请求需要等待时先在等待图中查找环，成环则请求方返回
+/// `DatabaseError::Deadlock`，由调用方放弃事务以释放它持有的锁。
+#[derive(Default)]
+pub struct LockManager {
+    state: Mutex<LockState>,
+    /// 有锁释放时唤醒所有等待者重新检查
+    released: Notify,
+}
+
+#[derive(Default)]
+struct LockState {
+    held: Vec<(u64, LockTarget, LockMode)>,
+    waiting: HashMap<u64, (LockTarget, LockMode)>,
+}
+
+impl LockState {
+    /// 与请求冲突的其他事务
+    fn blockers(&self, transaction: u64, target: &LockTarget, mode: LockMode) -> Vec<u64> {
+        let mut blockers: Vec<u64> = self.held.iter()
+            .filter(|(holder, held, held_mode)| {
+                *holder != transaction && held.overlaps(target)
+                    && (mode == LockMode::Exclusive || *held_mode == LockMode::Exclusive)
+            })
+            .map(|(holder, _, _)| *holder)
+            .collect();
+        blockers.sort_unstable();
+        blockers.dedup();
+        blockers
+    }
+
+    fn grant(&mut self, transaction: u64, target: LockTarget, mode: LockMode) {
+        let existing = self.held.iter_mut()
+            .find(|(holder, held, _)| *holder == transaction && *held == target);
+        match existing {
+            Some((_, _, held_mode)) => *held_mode = (*held_mode).max(mode),
+            None => self.held.push((transaction, target, mode)),
+        }
+    }
+
+    /// 从 `start` 出发沿等待关系能回到 `start` 时返回环上的事务
+    fn find_cycle(&self, start: u64) -> Option<Vec<u64>> {
+        let mut path = vec![start];
+        let mut visited = HashSet::new();
+        self.search(start, start, &mut path, &mut visited).then_some(path)
+    }
+
+    fn search(&self, start: u64, current: u64, path: &mut Vec<u64>, visited: &mut HashSet<u64>) -> bool {
+        let Some((target, mode)) = self.waiting.get(&current) else { return false };
+        for next in self.blockers(current, target, *mode) {
+            if next == start {
+                return true;
+            }
+            if visited.insert(next) {
+                path.push(next);
+                if self.search(start, next, path, visited) {
+                    return true;
+                }
+                path.pop();
+            }
+        }
+        false
+    }
+}
+
+impl LockManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 为事务加锁，冲突时等待其他事务释放
+    pub async fn acquire(&self, transaction: u64, target: LockTarget, mode: LockMode) -> Result<()> {
+        loop {
+            // 先登记唤醒再检查，检查之后释放的锁不会错过
+            let released = self.released.notified();
+            {
+                let mut state = self.state.lock().unwrap();
+                if state.blockers(transaction, &target, mode).is_empty() {
+                    state.waiting.remove(&transaction);
+                    state.grant(transaction, target, mode);
+                    return Ok(());
+                }
+                state.waiting.insert(transaction, (target.clone(), mode));
+                if let Some(cycle) = state.find_cycle(transaction) {
+                    state.waiting.remove(&transaction);
+                    let cycle: Vec<String> = cycle.iter().map(u64::to_string).collect();
+                    return Err(DatabaseError::deadlock(format!(
+                        "事务 {} 等待 {} 上的{}锁时形成等待环 {} -> {}", transaction, target,
+                        if mode == LockMode::Exclusive { "排他" } else { "共享" }, cycle.join(" -> "), transaction
+                    )));
+                }
+            }
+            released.await;
+        }
+    }
+
+    /// 释放事务持有的所有锁并取消它的等待
+    pub fn release_all(&self, transaction: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.held.retain(|(holder, _, _)| *holder != transaction);
+        state.waiting.remove(&transaction);
+        drop(state);
+        self.released.notify_waiters();
+    }
+
+    /// 当前的持有者与等待者，按事务编号排序
+    pub fn info(&self) -> Vec<LockInfo> {
+        let state = self.state.lock().unwrap();
+        let mut info: Vec<LockInfo> = state.held.iter()
+            .map(|(transaction, target, mode)| LockInfo { target: target.clone(), mode: *mode, transaction: *transaction, granted: true })
+            .chain(state.waiting.iter().map(|(transaction, (target, mode))| {
+                LockInfo { target: target.clone(), mode: *mode, transaction: *transaction, granted: false }
+            }))
+            .collect();
+        info.sort_by(|a, b| (a.transaction, !a.granted, &a.target).cmp(&(b.transaction, !b.granted, &b.target)));
+        info
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_wait_and_detect_cycle() {
+        let locks = Arc::new(LockManager::new());
+        let (a, b) = (LockTarget::Table("a".to_string()), LockTarget::Row { table: "b".to_string(), id: Uuid::new_v4() });
+        locks.acquire(1, a.clone(), LockMode::Shared).await.unwrap();
+        locks.acquire(2, a.clone(), LockMode::Shared).await.unwrap();
+        locks.acquire(2, b.clone(), LockMode::Exclusive).await.unwrap();
+
+        // 事务 1 等待事务 2 持有的行锁
+        let waiter = tokio::spawn({
+            let (locks, b) = (Arc::clone(&locks), b.clone());
+            async move { locks.acquire(1, b, LockMode::Exclusive).await }
+        });
+        while !locks.info().iter().any(|info| !info.granted) {
+            tokio::task::yield_now().await;
+        }
+        // 表上的排他锁覆盖表 b 的行
+        assert_eq!(locks.state.lock().unwrap().blockers(3, &LockTarget::Table("b".to_string()), LockMode::Exclusive), vec![2]);
+
+        // 事务 2 再把表 a 的共享锁升级为排他锁会与事务 1 互相等待
+        let err = locks.acquire(2, a, LockMode::Exclusive).await.unwrap_err();
+        assert!(matches!(err, DatabaseError::Deadlock(_)));
+        locks.release_all(2);
+        waiter.await.unwrap().unwrap();
+        assert!(locks.info().iter().all(|info| info.granted && info.transaction == 1));
+    }
+}
@@ -1,11 +1,28 @@
 use clap::{Parser, Subcommand};
-use rustyline::Editor;
+use rustyline::completion::Completer;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use std::path::PathBuf;
 use std::collections::HashMap;
-use tokio;
-
-use simple_db::engine::DatabaseEngine;
-use simple_db::query::{QueryBuilder, ComparisonOperator};
+use std::sync::Arc;
+
+use simple_db::bench::{self, BenchConfig, Workload};
+use simple_db::columnar::TableLayout;
+use simple_db::completion::Completions;
+use simple_db::engine::{DatabaseEngine, IndexKind};
+use simple_db::error::DatabaseError;
+use simple_db::binary::BinaryFormat;
+use simple_db::output::{self, OutputFormat};
+use simple_db::query::{QueryBuilder, QueryResult, QueryType, ComparisonOperator, Placeholder};
+use simple_db::import::{CopyFormat, CopyOptions, ImportOptions};
+use simple_db::sql::{self, Statement};
 use simple_db::types::{Value, DataType, Schema, ColumnDefinition};
+use simple_db::migrations::Migrator;
+use simple_db::replication;
+use simple_db::session::{self, Session};
+use simple_db::expression::Expr;
 
 /// Simple DB - 一个简单的内存数据库
 #[derive(Parser, Debug)]
@@ -22,6 +39,22 @@ struct Args {
     /// 启用调试模式
     #[arg(long)]
     debug: bool,
+
+    /// 加载时截断日志中损坏的尾部
+    #[arg(long)]
+    repair: bool,
+
+    /// 查询结果的输出格式：table、json、csv、vertical
+    #[arg(long, global = true, default_value = "table")]
+    output: OutputFormat,
+
+    /// 结果中二进制值的显示方式：size、hex、base64
+    #[arg(long, global = true, default_value = "size")]
+    binary: BinaryFormat,
+
+    /// 会话用户，开启审计时记录为操作者，默认取环境变量 USER
+    #[arg(long, global = true)]
+    user: Option<String>,
 }
 
 #[derive(Subcommand, Debug)]
@@ -33,8 +66,51 @@ enum Commands {
         /// SQL文件路径
         file: String,
     },
+    /// 执行 SQL 并把结果写到标准输出，出错时以非零状态退出，便于在脚本中使用
+    Query {
+        /// 要执行的 SQL，可以包含多条以分号分隔的语句
+        #[arg(short = 'e', long = "execute")]
+        sql: String,
+    },
+    /// 在独立的内存数据库上生成合成数据，报告吞吐量与延迟百分位数
+    Bench {
+        /// 表中的行数
+        #[arg(long, default_value_t = 10_000)]
+        rows: usize,
+        /// 负载类型：insert、scan、point-read、aggregate、mixed
+        #[arg(long, default_value = "mixed")]
+        workload: Workload,
+        /// 操作次数，默认 insert 为行数，scan 与 aggregate 为 10，其余为 1000
+        #[arg(long)]
+        ops: Option<usize>,
+        /// 使用列式布局建表，扫描与聚合按批求值
+        #[arg(long)]
+        columnar: bool,
+    },
     /// 运行示例
     Example,
+    /// 作为复制主库运行，接受从库连接；收到 SIGINT 或 SIGTERM 后刷新日志、写入检查点并退出
+    Serve {
+        /// 监听地址
+        #[arg(long, default_value = "127.0.0.1:7878")]
+        listen: String,
+    },
+    /// 检查数据完整性：索引与行、约束、快照与日志的校验和，发现问题时以非零状态退出
+    Check,
+    /// 执行目录中的数据库迁移
+    Migrate {
+        /// 迁移脚本目录，文件名形如 0001_create_users.up.sql
+        dir: String,
+        /// 目标版本，默认迁移到最新版本
+        #[arg(long)]
+        to: Option<i64>,
+        /// 回滚到目标版本（默认 0，即回滚全部）
+        #[arg(long)]
+        down: bool,
+        /// 只显示迁移状态
+        #[arg(long)]
+        status: bool,
+    },
 }
 
 #[tokio::main]
@@ -44,13 +120,33 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // 初始化数据库引擎
     let mut engine = DatabaseEngine::new();
 
+    // query 的标准输出只包含结果，数据库无法加载时直接失败
+    let scripted = matches!(args.command, Some(Commands::Query { .. }));
+
+    // 加载了数据库时退出前写入最终检查点；否则只刷新日志，不用当前的内容覆盖数据目录中的快照
+    let mut checkpoint = false;
+
     // 如果指定了数据库文件，尝试加载
     if let Some(db_path) = &args.database {
-        println!("正在加载数据库: {}", db_path);
-        match DatabaseEngine::load_from_disk().await {
+        if !scripted {
+            println!("正在加载数据库: {}", db_path);
+        }
+        let loaded = if args.repair {
+            DatabaseEngine::load_from_disk_with_repair().await
+        } else {
+            DatabaseEngine::load_from_disk().await
+        };
+        match loaded {
             Ok(loaded_engine) => {
                 engine = loaded_engine;
-                println!("数据库加载成功");
+                checkpoint = true;
+                if !scripted {
+                    println!("数据库加载成功");
+                }
+            }
+            Err(e) if scripted => {
+                eprintln!("错误: 无法加载数据库: {}", e);
+                std::process::exit(1);
             }
             Err(e) => {
                 println!("警告: 无法加载数据库: {}", e);
@@ -62,13 +158,57 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // 根据命令执行不同操作
     match args.command {
         Some(Commands::Shell) => {
-            run_interactive_shell(engine).await;
+            let user = args.user.or_else(|| std::env::var("USER").ok()).unwrap_or_else(|| "simple-db".to_string());
+            let session = engine.session(user).with_output_format(args.output).with_binary_format(args.binary);
+            run_interactive_shell(&mut engine, session).await;
+            shutdown(&engine, checkpoint).await;
         }
         Some(Commands::Execute { file }) => {
-            execute_sql_file(&mut engine, &file).await?;
+            let result = execute_sql_file(&mut engine, &file, args.output, args.binary).await;
+            shutdown(&engine, checkpoint).await;
+            result?;
+        }
+        Some(Commands::Query { sql }) => {
+            let result = run_query(&engine, &sql, args.output, args.binary).await;
+            shutdown(&engine, checkpoint).await;
+            if let Err(e) = result {
+                print_sql_error(&sql, &e);
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Bench { rows, workload, ops, columnar }) => {
+            run_bench(rows, workload, ops, columnar).await?;
         }
         Some(Commands::Example) => {
             run_example(&engine).await;
+            shutdown(&engine, checkpoint).await;
+        }
+        Some(Commands::Serve { listen }) => {
+            run_server(engine, &listen, checkpoint).await?;
+        }
+        Some(Commands::Check) => {
+            if !checkpoint {
+                println!("数据库未加载，只校验数据目录中的快照与日志");
+            }
+            let report = engine.check_integrity().await;
+            shutdown(&engine, false).await;
+            match report {
+                Ok(report) => {
+                    println!("{}", report);
+                    if !report.is_ok() {
+                        std::process::exit(1);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("错误: 完整性检查失败: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(Commands::Migrate { dir, to, down, status }) => {
+            let result = run_migrations(&engine, &dir, to, down, status).await;
+            shutdown(&engine, checkpoint).await;
+            result?;
         }
         None => {
             println!("Simple DB - 简单的内存数据库");
@@ -78,42 +218,179 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             println!("  simple-db shell     # 启动交互式Shell");
             println!("  simple-db example   # 运行示例");
             println!("  simple-db execute -f file.sql  # 执行SQL文件");
+            println!("  simple-db query -e \"SELECT ...\" --output json  # 执行查询并输出结果");
+            println!("  simple-db migrate migrations/  # 执行数据库迁移");
+            println!("  simple-db --database data check  # 检查数据完整性");
+            println!("  simple-db bench --workload mixed  # 运行基准测试");
+            println!("  simple-db serve --listen 127.0.0.1:7878  # 作为复制主库运行");
+        }
+    }
+
+    Ok(())
+}
+
+/// 退出前刷新日志；`checkpoint` 时再写入最终检查点，下次加载不必重放日志
+async fn shutdown(engine: &DatabaseEngine, checkpoint: bool) {
+    let mut result = engine.flush_log().await;
+    if checkpoint && result.is_ok() {
+        result = engine.save_to_disk().await;
+    }
+    if let Err(e) = result {
+        eprintln!("错误: 关闭数据库时出错: {}", e);
+    }
+}
+
+/// 等待 SIGINT（Ctrl-C）或 SIGTERM
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+            }
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+            }
         }
     }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
 
+/// 作为复制主库运行，直到收到 SIGINT 或 SIGTERM
+async fn run_server(engine: DatabaseEngine, listen: &str, checkpoint: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let engine = Arc::new(engine);
+    let (addr, accept) = replication::serve(Arc::clone(&engine), listen).await?;
+    println!("正在 {} 上接受从库连接，按 Ctrl-C 停止", addr);
+
+    shutdown_signal().await;
+    println!("正在关闭...");
+    // 先停止接受新连接，再刷新日志并写入检查点
+    accept.abort();
+    shutdown(&engine, checkpoint).await;
+    println!("已关闭");
     Ok(())
 }
 
+/// shell 的补全助手，候选来自引擎中的表结构
+struct ShellHelper {
+    completions: Completions,
+}
+
+impl Completer for ShellHelper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(self.completions.complete(line, pos))
+    }
+}
+
+impl Hinter for ShellHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ShellHelper {}
+
+impl Validator for ShellHelper {}
+
+impl Helper for ShellHelper {}
+
+/// 历史记录文件 `~/.simpledb_history`
+fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".simpledb_history"))
+}
+
+/// 用当前的表与列更新补全候选
+async fn refresh_completions(rl: &mut Editor<ShellHelper>, engine: &DatabaseEngine) {
+    let tables = engine.list_tables().await.into_iter()
+        .map(|table| (table.name, table.schema.columns.into_iter().map(|column| column.name).collect()));
+    if let Some(helper) = rl.helper_mut() {
+        helper.completions.set_tables(tables);
+    }
+}
+
 /// 运行交互式Shell
-async fn run_interactive_shell(mut engine: DatabaseEngine) {
+async fn run_interactive_shell(engine: &mut DatabaseEngine, mut session: Session) {
     println!("Simple DB 交互式Shell");
     println!("输入 'help' 查看帮助，'exit' 退出");
     println!();
 
-    let mut rl = Editor::<()>::new().expect("Failed to create readline editor");
-    let mut current_db = None;
+    let mut rl = Editor::<ShellHelper>::new().expect("Failed to create readline editor");
+    let commands = SHELL_COMMANDS.iter().copied().chain(["\\format"]).chain(DOT_COMMANDS.iter().copied());
+    rl.set_helper(Some(ShellHelper { completions: Completions::new(commands) }));
+    let history = history_path();
+    if let Some(path) = &history {
+        // 第一次运行时文件还不存在
+        let _ = rl.load_history(path);
+    }
+    // 尚未以分号结束的 SQL 语句
+    let mut buffer = String::new();
+    // 空行上刚按过一次 Ctrl-C，再按一次退出
+    let mut interrupted = false;
 
     loop {
-        let readline = rl.readline(&format!("{}> ", current_db.as_deref().unwrap_or("nodb")));
-        match readline {
+        refresh_completions(&mut rl, engine).await;
+        let prompt = session.database().to_string();
+        let prompt = if buffer.is_empty() {
+            format!("{}> ", prompt)
+        } else {
+            format!("{:>width$}> ", "...", width = prompt.len())
+        };
+        match rl.readline(&prompt) {
             Ok(line) => {
-                let line = line.trim();
-                if line.is_empty() {
-                    continue;
+                interrupted = false;
+                if buffer.is_empty() {
+                    let command = line.trim();
+                    if command.is_empty() {
+                        continue;
+                    }
+                    // shell 命令单行输入，立即执行
+                    if is_shell_command(command) {
+                        rl.add_history_entry(command);
+                        let command = command.trim_end_matches(';').trim_end();
+                        if ["exit", "quit", ".exit", ".quit"].iter().any(|word| command.eq_ignore_ascii_case(word)) {
+                            println!("再见！");
+                            break;
+                        }
+                        if let Err(e) = handle_command(engine, command, &mut session).await {
+                            eprintln!("错误: {}", e);
+                        }
+                        continue;
+                    }
                 }
 
-                // 添加历史记录
-                rl.add_history_entry(line);
-
-                match handle_command(&mut engine, line, &mut current_db).await {
-                    Ok(()) => {}
-                    Err(e) => {
-                        eprintln!("错误: {}", e);
+                // 其余输入都是 SQL，可以跨多行，以分号结束
+                buffer.push_str(&line);
+                buffer.push('\n');
+                if sql::is_complete(&buffer) {
+                    let statement = std::mem::take(&mut buffer);
+                    rl.add_history_entry(statement.trim());
+                    // COPY 的数据紧跟在语句之后输入
+                    if let Ok(Statement::Copy { table, options }) = sql::parse_statement(&statement) {
+                        let data = read_copy_data(&mut rl);
+                        run_copy(engine, &table, &options, &data).await;
+                        continue;
                     }
+                    run_sql(engine, &statement, &session).await;
                 }
             }
             Err(rustyline::error::ReadlineError::Interrupted) => {
-                println!("使用 'exit' 命令退出");
+                if !buffer.is_empty() {
+                    buffer.clear();
+                    println!("已取消未完成的语句");
+                } else if interrupted {
+                    println!("再见！");
+                    break;
+                } else {
+                    interrupted = true;
+                    println!("再按一次 Ctrl-C 或输入 'exit' 退出");
+                }
             }
             Err(rustyline::error::ReadlineError::Eof) => {
                 println!("再见！");
@@ -125,91 +402,270 @@ async fn run_interactive_shell(mut engine: DatabaseEngine) {
             }
         }
     }
+
+    if let Some(path) = &history {
+        if let Err(e) = rl.save_history(path) {
+            eprintln!("无法保存历史记录: {}", e);
+        }
+    }
+}
+
+/// shell 命令（不经过 SQL 解析器）的第一个单词
+const SHELL_COMMANDS: &[&str] = &[
+    "help", "exit", "quit", "tables", "describe", "desc", "save", "load", "set", "unset",
+    "call", "procedures", "stats", "slow", "example", "clear", "use", "databases",
+];
+
+/// sqlite 风格的点命令
+const DOT_COMMANDS: &[&str] = &[
+    ".help", ".exit", ".quit", ".tables", ".databases", ".schema", ".indexes", ".backup", ".restore", ".import", ".export", ".timer", ".binary",
+];
+
+/// 输入是否为 shell 命令：以 `.` 或 `\` 开头，或第一个单词是 shell 命令
+fn is_shell_command(line: &str) -> bool {
+    let word = line.split_whitespace().next().unwrap_or("").trim_end_matches(';').to_lowercase();
+    word.starts_with('.') || word.starts_with('\\') || SHELL_COMMANDS.contains(&word.as_str())
+}
+
+/// 在会话中执行 SQL 文本中的语句并按会话的输出格式输出结果，遇到错误时停止；语法错误用 `^` 标出出错位置
+///
+/// 会话开启计时时在每条语句后输出耗时。
+async fn run_sql(engine: &DatabaseEngine, sql_text: &str, session: &Session) {
+    let statements = match sql::parse_script(sql_text) {
+        Ok(statements) => statements,
+        Err(e) => {
+            print_sql_error(sql_text, &e);
+            return;
+        }
+    };
+    for statement in statements {
+        let start = std::time::Instant::now();
+        match engine.execute_statement_in(session, statement).await {
+            Ok(Some(result)) => print_result(&result, session.output_format, session.binary_format),
+            Ok(None) => println!("OK"),
+            Err(e) => {
+                print_sql_error(sql_text, &e);
+                return;
+            }
+        }
+        if session.timer {
+            println!("耗时: {:.3} ms", start.elapsed().as_secs_f64() * 1000.0);
+        }
+    }
+}
+
+/// 读取 COPY 的数据行，直到单独一行的 `\.` 或输入结束
+fn read_copy_data(rl: &mut Editor<ShellHelper>) -> String {
+    println!("输入数据，以单独一行的 \\. 结束");
+    let mut data = String::new();
+    while let Ok(line) = rl.readline(">> ") {
+        if line.trim_end() == "\\." {
+            break;
+        }
+        data.push_str(&line);
+        data.push('\n');
+    }
+    data
+}
+
+/// 执行 COPY，输出导入的行数与被拒绝的行
+async fn run_copy(engine: &DatabaseEngine, table: &str, options: &CopyOptions, data: &str) {
+    match engine.copy_from(table, data.as_bytes(), options).await {
+        Ok(report) => {
+            println!("已导入 {} 行，拒绝 {} 行", report.loaded, report.rejected);
+            for rejected in &report.errors {
+                eprintln!("  第 {} 行: {}", rejected.line, rejected.error);
+            }
+        }
+        Err(e) => eprintln!("错误: {}", e),
+    }
+}
+
+/// 输出错误，语法错误时显示出错的行并在下方标出位置
+fn print_sql_error(sql_text: &str, error: &DatabaseError) {
+    eprintln!("错误 {}: {}", error.code(), error);
+    if let DatabaseError::SyntaxError { position, .. } = error {
+        eprintln!("{}", sql::caret(sql_text, *position));
+    }
 }
 
-/// 处理用户命令
+/// 输出一条语句的结果；表格以外的格式只输出数据，便于交给其他工具处理
+fn print_result(result: &QueryResult, format: OutputFormat, binary: BinaryFormat) {
+    if format != OutputFormat::Table {
+        print!("{}", output::render_with(result, format, binary));
+        return;
+    }
+    match result.query_type {
+        QueryType::Select if result.rows.is_empty() => println!("表 '{}' 没有符合条件的行", result.table_name),
+        QueryType::Select => {
+            println!("表 '{}' 查询到 {} 行:", result.table_name, result.rows.len());
+            print_rows(result, format, binary);
+        }
+        QueryType::Count => println!("表 '{}' 共有 {} 行", result.table_name, result.count.unwrap_or(0)),
+        _ if result.dry_run => {
+            println!("试运行: 表 '{}' 将影响 {} 行，数据未修改", result.table_name, result.affected_rows);
+            if !result.rows.is_empty() {
+                print_rows(result, format, binary);
+            }
+        }
+        _ => println!("表 '{}' 影响 {} 行", result.table_name, result.affected_rows),
+    }
+}
+
+/// 按输出格式打印结果行，列按表结构的顺序排列
+fn print_rows(result: &QueryResult, format: OutputFormat, binary: BinaryFormat) {
+    print!("{}", output::render_rows_with(&output::result_columns(result), &result.rows, format, binary));
+}
+
+/// 按空白拆分 shell 命令，双引号括起的部分作为一个参数，例如 `describe "user data"`；其中的 `""` 表示一个双引号
+fn split_command(command: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let (mut quoted, mut in_word) = (false, false);
+    let mut chars = command.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                word.push('"');
+            }
+            '"' => {
+                quoted = !quoted;
+                in_word = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            c => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    words
+}
+
+/// 处理 shell 命令
 async fn handle_command(
     engine: &mut DatabaseEngine,
     command: &str,
-    _current_db: &mut Option<String>,
+    session: &mut Session,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let parts: Vec<&str> = command.split_whitespace().collect();
+    let words = split_command(command);
+    let parts: Vec<&str> = words.iter().map(String::as_str).collect();
     if parts.is_empty() {
         return Ok(());
     }
 
     match parts[0].to_lowercase().as_str() {
-        "help" => {
+        "help" | ".help" => {
             print_help();
         }
-        "exit" | "quit" => {
-            std::process::exit(0);
-        }
-        "tables" => {
+        "tables" | ".tables" => {
             list_tables(engine).await;
         }
-        "create" => {
-            if parts.len() >= 3 && parts[1].to_lowercase() == "table" {
-                let table_name = parts[2];
-                create_table_interactive(engine, table_name).await?;
+        "use" => {
+            if let Some(name) = parts.get(1) {
+                engine.use_database_in(session, name).await?;
+                println!("当前数据库: {}", name);
             } else {
-                println!("用法: CREATE TABLE table_name");
+                println!("用法: USE database");
             }
         }
-        "drop" => {
-            if parts.len() >= 3 && parts[1].to_lowercase() == "table" {
-                let table_name = parts[2];
-                drop_table(engine, table_name).await?;
-            } else {
-                println!("用法: DROP TABLE table_name");
+        "databases" | ".databases" => {
+            for name in engine.list_databases() {
+                let marker = if name == session.database() { "*" } else { " " };
+                println!("{} {}", marker, name);
             }
         }
-        "insert" => {
-            if parts.len() >= 3 && parts[1].to_lowercase() == "into" {
-                let table_name = parts[2];
-                insert_interactive(engine, table_name).await?;
+        "describe" | "desc" => {
+            if parts.len() >= 2 {
+                let table_name = parts[1];
+                describe_table(engine, table_name).await?;
             } else {
-                println!("用法: INSERT INTO table_name");
+                println!("用法: DESCRIBE table_name");
             }
         }
-        "select" => {
-            if parts.len() >= 3 && parts[2].to_lowercase() == "*" && parts.len() >= 5 && parts[3].to_lowercase() == "from" {
-                let table_name = parts[4];
-                select_all(engine, table_name).await;
+        ".schema" => {
+            let schema = engine.schema_sql(parts.get(1).copied()).await?;
+            if schema.is_empty() {
+                println!("没有表");
             } else {
-                println!("用法: SELECT * FROM table_name");
+                print!("{}", schema);
             }
         }
-        "update" => {
-            if parts.len() >= 3 && parts[2].to_lowercase() == "set" {
-                let table_name = parts[1];
-                update_interactive(engine, table_name).await?;
+        ".indexes" => {
+            let indexes = engine.list_indexes(parts.get(1).copied()).await?;
+            if indexes.is_empty() {
+                println!("没有索引");
+            }
+            for index in indexes {
+                let note = if index.kind == IndexKind::Element { "（首次查询时构建）" } else { "" };
+                println!("  {}.{}  {}{}", index.table_name, index.column, index.kind, note);
+            }
+        }
+        ".backup" => {
+            if let Some(path) = parts.get(1) {
+                engine.backup(path).await?;
+                println!("已备份到 {}", path);
             } else {
-                println!("用法: UPDATE table_name SET ...");
+                println!("用法: .backup path");
             }
         }
-        "delete" => {
-            if parts.len() >= 3 && parts[1].to_lowercase() == "from" {
-                let table_name = parts[2];
-                delete_interactive(engine, table_name).await?;
+        ".restore" => {
+            if let Some(path) = parts.get(1) {
+                engine.restore(path).await?;
+                println!("已从 {} 恢复", path);
             } else {
-                println!("用法: DELETE FROM table_name");
+                println!("用法: .restore path");
             }
         }
-        "describe" | "desc" => {
-            if parts.len() >= 2 {
-                let table_name = parts[1];
-                describe_table(engine, table_name).await?;
+        ".timer" => {
+            match parts.get(1) {
+                Some(arg) => session.set(session::TIMER, Value::Text(arg.to_string()))?,
+                None => println!("用法: .timer on|off"),
+            }
+            println!("计时: {}", if session.timer { "on" } else { "off" });
+        }
+        ".binary" => {
+            match parts.get(1) {
+                Some(arg) => session.set(session::BINARY_FORMAT, Value::Text(arg.to_string()))?,
+                None => println!("用法: .binary size|hex|base64"),
+            }
+            println!("二进制显示: {}", session.binary_format);
+        }
+        ".export" => {
+            if parts.len() >= 3 {
+                let count = engine.export_json(parts[1], parts[2]).await?;
+                println!("已导出 {} 行到 {}", count, parts[2]);
             } else {
-                println!("用法: DESCRIBE table_name");
+                println!("用法: .export table_name path");
             }
         }
-        "count" => {
-            if parts.len() >= 3 && parts[1].to_lowercase() == "from" {
-                let table_name = parts[2];
-                count_table(engine, table_name).await;
+        ".import" => {
+            if parts.len() >= 3 {
+                // 指定拒绝文件时跳过不合格的行
+                let options = match parts.get(3) {
+                    Some(reject) => ImportOptions::new().reject_file(reject),
+                    None => ImportOptions::new(),
+                };
+                let report = match std::path::Path::new(parts[2]).extension().and_then(|ext| ext.to_str()) {
+                    Some("csv") => engine.import_csv(parts[1], parts[2], &CopyOptions::new(CopyFormat::Csv).header(true), &options).await?,
+                    Some("tsv") => engine.import_csv(parts[1], parts[2], &CopyOptions::new(CopyFormat::Tsv).header(true), &options).await?,
+                    _ => engine.import_json_with(parts[1], parts[2], &options).await?,
+                };
+                println!("已从 {} 导入 {} 行", parts[2], report.loaded);
+                if report.rejected > 0 {
+                    println!("跳过 {} 行，详见 {}", report.rejected, parts[3]);
+                }
             } else {
-                println!("用法: COUNT FROM table_name");
+                println!("用法: .import table_name path [reject_path]");
             }
         }
         "save" => {
@@ -220,6 +676,8 @@ async fn handle_command(
             match DatabaseEngine::load_from_disk().await {
                 Ok(loaded_engine) => {
                     *engine = loaded_engine;
+                    let name = engine.current_database().to_string();
+                    engine.use_database_in(session, &name).await?;
                     println!("数据库加载成功");
                 }
                 Err(e) => {
@@ -227,11 +685,72 @@ async fn handle_command(
                 }
             }
         }
+        "set" => {
+            if parts.len() >= 3 {
+                let raw = command.split_whitespace().skip(2).collect::<Vec<_>>().join(" ");
+                let value = infer_value(&raw);
+                session.set(parts[1], value.clone())?;
+                println!("{} = {} ({})", parts[1], value, value.get_type());
+            } else {
+                for (name, value) in session.variables() {
+                    println!("  {} = {}", name, value);
+                }
+            }
+        }
+        "unset" => {
+            if parts.len() >= 2 {
+                session.unset(parts[1]);
+            } else {
+                println!("用法: unset name");
+            }
+        }
+        "call" => {
+            call_procedure(engine, command, session).await?;
+        }
+        "procedures" => {
+            let procedures = engine.list_procedures().await;
+            if procedures.is_empty() {
+                println!("没有存储过程");
+            } else {
+                for procedure in procedures {
+                    println!("  - {}({})", procedure.name, procedure.params.join(", "));
+                }
+            }
+        }
         "stats" => {
             show_stats(engine).await;
         }
+        "\\format" => {
+            match parts.get(1) {
+                Some(name) => {
+                    session.output_format = name.parse()?;
+                    println!("输出格式: {}", session.output_format);
+                }
+                None => println!("当前输出格式: {}（可选 table、json、csv、vertical）", session.output_format),
+            }
+        }
+        "slow" => {
+            match (parts.get(1).map(|p| p.to_lowercase()), parts.get(2)) {
+                (Some(ref p), None) if p == "log" => show_slow_queries(engine),
+                (Some(ref p), Some(arg)) if p == "log" && arg.eq_ignore_ascii_case("clear") => {
+                    engine.clear_slow_queries();
+                    println!("慢查询日志已清空");
+                }
+                (Some(ref p), Some(arg)) if p == "threshold" => {
+                    if arg.eq_ignore_ascii_case("off") {
+                        engine.set_slow_query_threshold(None);
+                        println!("慢查询日志已关闭");
+                    } else {
+                        let millis: u64 = arg.parse().map_err(|_| format!("无效的毫秒数: {}", arg))?;
+                        engine.set_slow_query_threshold(Some(std::time::Duration::from_millis(millis)));
+                        println!("慢查询阈值: {} ms", millis);
+                    }
+                }
+                _ => println!("用法: SLOW LOG [CLEAR] | SLOW THRESHOLD ms|off"),
+            }
+        }
         "example" => {
-            run_example(&engine).await;
+            run_example(engine).await;
         }
         "clear" => {
             print!("{}[2J{}[H", 27 as char, 27 as char);
@@ -246,25 +765,73 @@ async fn handle_command(
 
 /// 打印帮助信息
 fn print_help() {
-    println!("可用命令:");
+    println!("SQL 语句以分号结束，可以跨多行输入，按 Ctrl-C 取消未完成的语句:");
+    println!("  CREATE TABLE name (col TYPE [PRIMARY KEY] [NOT NULL] ...) [LAYOUT COLUMNAR]");
+    println!("    [WITH (UNLOGGED, TTL seconds, COMPRESSION LZ4, MAX_ROWS n)];");
+    println!("  CREATE TABLE name AS SELECT ...;");
+    println!("  DROP TABLE name;  TRUNCATE [TABLE] name;  ANALYZE [name];  SHOW LOCKS;");
+    println!("  CREATE DATABASE name;  DROP DATABASE name;");
+    println!("  INSERT INTO name [(col, ...)] VALUES (v, ...), ... | SELECT ...;");
+    println!("  SELECT * | COUNT(*) FROM name [WHERE ...] [ORDER BY ...] [LIMIT n] [OFFSET n];");
+    println!("  UPDATE name SET col = v, ... [WHERE ...] [DRY RUN];  DELETE FROM name [WHERE ...] [DRY RUN];");
+    println!("  COPY name [(col, ...)] FROM STDIN [WITH (FORMAT csv|tsv, HEADER)];  随后输入数据，以 \\. 结束");
+    println!();
+    println!("shell 命令:");
     println!("  help                    - 显示此帮助信息");
-    println!("  exit/quit               - 退出程序");
-    println!("  tables                  - 列出所有表");
-    println!("  CREATE TABLE name       - 创建表");
-    println!("  DROP TABLE name         - 删除表");
-    println!("  INSERT INTO name        - 向表插入数据");
-    println!("  SELECT * FROM name      - 查询表中的所有数据");
-    println!("  UPDATE name SET ...     - 更新表数据");
-    println!("  DELETE FROM name        - 删除表数据");
+    println!("  exit/quit/.exit         - 退出程序");
+    println!("  tables/.tables          - 列出当前数据库的所有表");
+    println!("  USE name                - 切换当前数据库");
+    println!("  databases               - 列出所有数据库，* 标记当前数据库");
     println!("  DESCRIBE name           - 显示表结构");
-    println!("  COUNT FROM name         - 统计表的行数");
+    println!("  CALL name(args...)      - 调用存储过程，参数可使用 :name 引用变量");
+    println!("  procedures              - 列出存储过程");
+    println!("  set [name value]        - 设置/列出会话变量，在 CALL 参数中用 :name 引用");
+    println!("                            timeout ms|off、output_format、timer on|off、binary_format 为会话设置");
+    println!("  unset name              - 删除变量，会话设置恢复默认值");
+    println!("  .schema [name]          - 显示建表语句");
+    println!("  .indexes [name]         - 列出索引");
+    println!("  .backup path            - 备份数据库到目录");
+    println!("  .restore path           - 从备份目录恢复数据库");
+    println!("  .export name path       - 把表导出为 NDJSON 文件");
+    println!("  .import name path [rej] - 从 NDJSON 或带表头的 .csv/.tsv 文件导入数据，指定 rej 时跳过不合格的行并写入该文件");
+    println!("  .timer on|off           - 在每条语句后显示耗时");
+    println!("  .binary size|hex|base64 - 设置二进制值的显示方式，hex 与 base64 可以原样导入");
     println!("  save                    - 保存数据库到磁盘");
     println!("  load                    - 从磁盘加载数据库");
     println!("  stats                   - 显示数据库统计信息");
+    println!("  \\format [table|json|csv|vertical] - 设置查询结果的输出格式");
+    println!("  SLOW LOG [CLEAR]        - 显示/清空慢查询日志");
+    println!("  SLOW THRESHOLD ms|off   - 设置慢查询阈值或关闭慢查询日志");
     println!("  example                 - 运行示例");
     println!("  clear                   - 清屏");
 }
 
+/// 显示慢查询日志
+fn show_slow_queries(engine: &DatabaseEngine) {
+    let threshold = match engine.slow_query_threshold() {
+        Some(threshold) => threshold,
+        None => {
+            println!("慢查询日志未开启，使用 SLOW THRESHOLD ms 开启");
+            return;
+        }
+    };
+    let entries = engine.slow_queries();
+    if entries.is_empty() {
+        println!("没有超过 {} ms 的查询", threshold.as_millis());
+        return;
+    }
+    for entry in entries {
+        println!(
+            "  [{}] {:.3} ms  扫描 {} 行  返回 {} 行  {}",
+            entry.recorded_at.format("%Y-%m-%d %H:%M:%S"),
+            entry.duration.as_secs_f64() * 1000.0,
+            entry.rows_scanned,
+            entry.rows_returned,
+            entry.shape,
+        );
+    }
+}
+
 /// 列出所有表
 async fn list_tables(engine: &DatabaseEngine) {
     let tables = engine.list_tables().await;
@@ -273,407 +840,65 @@ async fn list_tables(engine: &DatabaseEngine) {
     } else {
         println!("表列表:");
         for table in &tables {
-            println!("  - {} ({} 行)", table.name, table.row_count);
-        }
-    }
-}
-
-/// 交互式创建表
-async fn create_table_interactive(engine: &mut DatabaseEngine, table_name: &str) -> Result<(), Box<dyn std::error::Error>> {
-    use std::io::{self, Write};
-
-    println!("创建表: {}", table_name);
-    println!("输入列定义 (格式: 名称 类型 [主键] [唯一] [非空] [默认值])");
-    println!("输入空行结束");
-
-    let mut columns = Vec::new();
-    let mut primary_key_count = 0;
-
-    loop {
-        print!("列 {}: ", columns.len() + 1);
-        io::stdout().flush()?;
-
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-        let input = input.trim();
-
-        if input.is_empty() {
-            break;
-        }
-
-        let parts: Vec<&str> = input.split_whitespace().collect();
-        if parts.len() < 2 {
-            println!("错误: 至少需要名称和类型");
-            continue;
-        }
-
-        let column_name = parts[0];
-        let data_type = DataType::from_str(parts[1])?;
-
-        let mut column_def = ColumnDefinition::new(column_name, data_type.clone(), false);
-
-        for part in &parts[2..] {
-            match part.to_lowercase().as_str() {
-                "primary" | "pk" => {
-                    column_def.primary_key = true;
-                    column_def.unique = true;
-                    column_def.nullable = false;
-                    primary_key_count += 1;
-                }
-                "unique" => {
-                    column_def.unique = true;
-                }
-                "not" | "null" => {
-                    if part.to_lowercase() == "not" && parts.get(3).map(|s| s.to_lowercase()) == Some("null".to_string()) {
-                        column_def.nullable = false;
-                    }
-                }
-                "default" => {
-                    if let Some(default_part) = parts.get(3) {
-                        let default_value = parse_default_value(default_part, &data_type)?;
-                        column_def.default_value = Some(default_value);
-                    }
-                }
-                _ => {}
-            }
-        }
-
-        columns.push(column_def);
-    }
-
-    if columns.is_empty() {
-        println!("错误: 至少需要一个列");
-        return Ok(());
-    }
-
-    if primary_key_count > 1 {
-        println!("错误: 只能有一个主键");
-        return Ok(());
-    }
-
-    let schema = Schema::new(columns);
-    engine.create_table(table_name, schema).await?;
-    println!("表 '{}' 创建成功", table_name);
-
-    Ok(())
-}
-
-/// 解析默认值
-fn parse_default_value(value: &str, data_type: &DataType) -> Result<Value, Box<dyn std::error::Error>> {
-    match data_type {
-        DataType::Integer => {
-            let int_val: i64 = value.parse()?;
-            Ok(Value::Integer(int_val))
-        }
-        DataType::Text => {
-            Ok(Value::Text(value.to_string()))
-        }
-        DataType::Boolean => {
-            let bool_val: bool = value.parse()?;
-            Ok(Value::Boolean(bool_val))
-        }
-        DataType::Float => {
-            let float_val: f64 = value.parse()?;
-            Ok(Value::Float(float_val))
-        }
-        _ => {
-            Ok(Value::Text(value.to_string()))
-        }
-    }
-}
-
-/// 交互式插入数据
-async fn insert_interactive(engine: &mut DatabaseEngine, table_name: &str) -> Result<(), Box<dyn std::error::Error>> {
-    use std::io::{self, Write};
-
-    // 获取表结构
-    let table_info = engine.get_table_info(table_name).await?;
-
-    println!("插入数据到表: {}", table_name);
-    println!("表结构:");
-    for column in &table_info.schema.columns {
-        let nullable = if column.nullable { "NULL" } else { "NOT NULL" };
-        let unique = if column.unique { " UNIQUE" } else { "" };
-        let primary = if column.primary_key { " PRIMARY KEY" } else { "" };
-        println!("  {}: {}{}{}{}", column.name, column.data_type.to_string(), nullable, unique, primary);
-    }
-
-    let mut data = HashMap::new();
-
-    for column in &table_info.schema.columns {
-        if column.primary_key {
-            // 主键自动生成
-            continue;
-        }
-
-        print!("{} ({}): ", column.name, column.data_type.to_string());
-        io::stdout().flush()?;
-
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-        let input = input.trim();
-
-        if input.is_empty() {
-            if let Some(default) = &column.default_value {
-                data.insert(column.name.clone(), default.clone());
-            } else if !column.nullable {
-                println!("错误: 列 '{}' 不能为空", column.name);
-                return Err("列不能为空".into());
-            }
-        } else {
-            let value = parse_value(input, &column.data_type)?;
-            data.insert(column.name.clone(), value);
+            println!("  - {} ({} 行)", sql::quote_identifier(&table.name), table.row_count);
         }
     }
-
-    let id = engine.insert(table_name, data).await?;
-    println!("插入成功，ID: {}", id);
-
-    Ok(())
 }
 
-/// 解析值
-fn parse_value(value: &str, data_type: &DataType) -> Result<Value, Box<dyn std::error::Error>> {
-    match data_type {
-        DataType::Integer => {
-            let int_val: i64 = value.parse()?;
-            Ok(Value::Integer(int_val))
-        }
-        DataType::Text => {
-            Ok(Value::Text(value.to_string()))
-        }
-        DataType::Boolean => {
-            let bool_val = match value.to_lowercase().as_str() {
-                "true" | "1" | "yes" | "y" => true,
-                "false" | "0" | "no" | "n" => false,
-                _ => return Err("无效的布尔值".into()),
-            };
-            Ok(Value::Boolean(bool_val))
-        }
-        DataType::Float => {
-            let float_val: f64 = value.parse()?;
-            Ok(Value::Float(float_val))
-        }
-        _ => {
-            Ok(Value::Text(value.to_string()))
-        }
+/// 推断变量值的类型：整数、浮点数、布尔值或文本（可用单引号包裹）
+fn infer_value(raw: &str) -> Value {
+    if let Some(text) = raw.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')) {
+        return Value::Text(text.to_string());
     }
-}
-
-/// 查询所有数据
-async fn select_all(engine: &DatabaseEngine, table_name: &str) {
-    let query = QueryBuilder::select(table_name).build();
-
-    match engine.query(query).await {
-        Ok(result) => {
-            if result.rows.is_empty() {
-                println!("表 '{}' 中没有数据", table_name);
-            } else {
-                println!("表 '{}' 中的数据 ({} 行):", table_name, result.rows.len());
-                print_table(&result.rows);
-            }
-        }
-        Err(e) => {
-            println!("查询失败: {}", e);
-        }
+    if let Ok(i) = raw.parse::<i64>() {
+        return Value::Integer(i);
     }
-}
-
-/// 格式化输出表格
-fn print_table(rows: &[simple_db::types::Row]) {
-    if rows.is_empty() {
-        return;
-    }
-
-    // 获取所有列名
-    let mut columns: Vec<String> = rows[0].columns().into_iter().map(|s| s.to_string()).collect();
-    columns.sort(); // 按列名排序
-
-    // 计算每列的最大宽度
-    let mut widths = HashMap::new();
-    for col in &columns {
-        let mut max_width = col.len();
-        for row in rows {
-            if let Some(value) = row.get(col) {
-                let value_str = value.to_string();
-                if value_str.len() > max_width {
-                    max_width = value_str.len();
-                }
-            }
-        }
-        widths.insert(col.clone(), max_width);
-    }
-
-    // 打印表头
-    print!("+");
-    for col in &columns {
-        print!("{:-<width$}-+", "", width = widths[col] + 2);
+    if let Ok(f) = raw.parse::<f64>() {
+        return Value::Float(f);
     }
-    println!();
-
-    print!("|");
-    for col in &columns {
-        print!(" {:<width$} |", col, width = widths[col]);
-    }
-    println!();
-
-    print!("+");
-    for col in &columns {
-        print!("{:-<width$}-+", "", width = widths[col] + 2);
-    }
-    println!();
-
-    // 打印数据行
-    for row in rows {
-        print!("|");
-        for col in &columns {
-            let value = row.get(col).map(|v| v.to_string()).unwrap_or_else(|| "NULL".to_string());
-            print!(" {:<width$} |", value, width = widths[col]);
-        }
-        println!();
-    }
-
-    // 打印表尾
-    print!("+");
-    for col in &columns {
-        print!("{:-<width$}-+", "", width = widths[col] + 2);
+    match raw.to_lowercase().as_str() {
+        "true" => Value::Boolean(true),
+        "false" => Value::Boolean(false),
+        "null" => Value::Null,
+        _ => Value::Text(raw.to_string()),
     }
-    println!();
 }
 
-/// 交互式更新数据
-async fn update_interactive(engine: &mut DatabaseEngine, table_name: &str) -> Result<(), Box<dyn std::error::Error>> {
-    use std::io::{self, Write};
-
-    println!("更新表: {}", table_name);
-    print!("输入要更新的行ID: ");
-    io::stdout().flush()?;
-
-    let mut id_input = String::new();
-    io::stdin().read_line(&mut id_input)?;
-    let id_input = id_input.trim();
-
-    let id: uuid::Uuid = id_input.parse()?;
-
-    println!("输入要更新的列和值 (格式: 列名=值)");
-    println!("输入空行结束");
-
-    let mut updates = HashMap::new();
-
-    loop {
-        print!("更新 {}: ", updates.len() + 1);
-        io::stdout().flush()?;
-
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-        let input = input.trim();
-
-        if input.is_empty() {
-            break;
-        }
-
-        if let Some((column, value_str)) = input.split_once('=') {
-            let table_info = engine.get_table_info(table_name).await?;
-            if let Some(column_def) = table_info.schema.get_column(column) {
-                let value = parse_value(value_str, &column_def.data_type)?;
-                updates.insert(column.to_string(), value);
-            } else {
-                println!("错误: 列 '{}' 不存在", column);
-            }
-        } else {
-            println!("错误: 格式应为 '列名=值'");
+/// 调用存储过程: CALL name(arg1, arg2, ...)
+async fn call_procedure(
+    engine: &DatabaseEngine,
+    command: &str,
+    session: &Session,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let rest = command.trim()[4..].trim().trim_end_matches(';');
+    let (name, args) = match rest.split_once('(') {
+        Some((name, args)) => {
+            let args = args.trim().strip_suffix(')').ok_or("缺少右括号")?;
+            (name.trim(), args)
         }
-    }
-
-    if updates.is_empty() {
-        println!("没有要更新的列");
+        None => (rest, ""),
+    };
+    if name.is_empty() {
+        println!("用法: CALL name(arg1, arg2, ...)");
         return Ok(());
     }
 
-    let conditions = vec![
-        ("id".to_string(), ComparisonOperator::Equal, Value::Text(id.to_string()))
-    ];
-
-    let affected = engine.update(table_name, conditions, updates).await?;
-    println!("更新了 {} 行", affected);
-
-    Ok(())
-}
-
-/// 交互式删除数据
-async fn delete_interactive(engine: &mut DatabaseEngine, table_name: &str) -> Result<(), Box<dyn std::error::Error>> {
-    use std::io::{self, Write};
-
-    println!("从表 {} 删除数据", table_name);
-    println!("1. 根据ID删除");
-    println!("2. 根据条件删除");
-    print!("选择删除方式: ");
-    io::stdout().flush()?;
-
-    let mut choice = String::new();
-    io::stdin().read_line(&mut choice)?;
-    let choice = choice.trim();
-
-    match choice {
-        "1" => {
-            print!("输入要删除的行ID: ");
-            io::stdout().flush()?;
-
-            let mut id_input = String::new();
-            io::stdin().read_line(&mut id_input)?;
-            let id_input = id_input.trim();
-
-            let id: uuid::Uuid = id_input.parse()?;
-
-            let conditions = vec![
-                ("id".to_string(), ComparisonOperator::Equal, Value::Text(id.to_string()))
-            ];
-
-            let affected = engine.delete(table_name, conditions).await?;
-            println!("删除了 {} 行", affected);
-        }
-        "2" => {
-            println!("输入删除条件 (格式: 列名=值)");
-            println!("输入空行结束");
-
-            let mut conditions = Vec::new();
-
-            loop {
-                print!("条件 {}: ", conditions.len() + 1);
-                io::stdout().flush()?;
-
-                let mut input = String::new();
-                io::stdin().read_line(&mut input)?;
-                let input = input.trim();
-
-                if input.is_empty() {
-                    break;
-                }
-
-                if let Some((column, value_str)) = input.split_once('=') {
-                    let table_info = engine.get_table_info(table_name).await?;
-                    if let Some(column_def) = table_info.schema.get_column(column) {
-                        let value = parse_value(value_str, &column_def.data_type)?;
-                        conditions.push((column.to_string(), ComparisonOperator::Equal, value));
-                    } else {
-                        println!("错误: 列 '{}' 不存在", column);
-                    }
-                } else {
-                    println!("错误: 格式应为 '列名=值'");
-                }
-            }
-
-            if conditions.is_empty() {
-                println!("没有指定条件");
-                return Ok(());
-            }
+    let mut values = Vec::new();
+    for arg in args.split(',').map(str::trim).filter(|a| !a.is_empty()) {
+        let value = match Placeholder::parse(arg) {
+            Some(placeholder) => session.params().get(&placeholder)
+                .cloned()
+                .ok_or_else(|| format!("变量 {} 未定义", placeholder))?,
+            None => infer_value(arg),
+        };
+        values.push(value);
+    }
 
-            let affected = engine.delete(table_name, conditions).await?;
-            println!("删除了 {} 行", affected);
-        }
-        _ => {
-            println!("无效选择");
-        }
+    let result = engine.call(name, values).await?;
+    if result.rows.is_empty() {
+        println!("存储过程 '{}' 执行完成，影响 {} 行", name, result.affected_rows);
+    } else {
+        print_rows(&result, session.output_format, session.binary_format);
     }
 
     Ok(())
@@ -713,9 +938,7 @@ async fn describe_table(engine: &DatabaseEngine, table_name: &str) -> Result<(),
         let null_str = if column.nullable { "YES" } else { "NO" };
         let unique_str = if column.unique { "YES" } else { "NO" };
         let pk_str = if column.primary_key { "YES" } else { "NO" };
-        let default_str = column.default_value.as_ref()
-            .map(|v| v.to_string())
-            .unwrap_or_else(|| "".to_string());
+        let default_str = column.default_text().unwrap_or_default();
 
         println!("{:<name_width$} | {:<type_width$} | {:<4} | {:<6} | {:<2} | {}",
                  column.name, column.data_type.to_string(), null_str, unique_str, pk_str, default_str,
@@ -725,24 +948,6 @@ async fn describe_table(engine: &DatabaseEngine, table_name: &str) -> Result<(),
     Ok(())
 }
 
-/// 统计表行数
-async fn count_table(engine: &DatabaseEngine, table_name: &str) {
-    let query = QueryBuilder::count(table_name).build();
-
-    match engine.query(query).await {
-        Ok(result) => {
-            if let Some(count) = result.count {
-                println!("表 '{}' 共有 {} 行", table_name, count);
-            } else {
-                println!("表 '{}' 中没有数据", table_name);
-            }
-        }
-        Err(e) => {
-            println!("统计失败: {}", e);
-        }
-    }
-}
-
 /// 显示数据库统计信息
 async fn show_stats(engine: &DatabaseEngine) {
     match engine.get_stats().await {
@@ -750,11 +955,22 @@ async fn show_stats(engine: &DatabaseEngine) {
             println!("数据库统计信息:");
             println!("  总表数: {}", stats.total_tables);
             println!("  总行数: {}", stats.total_rows);
-            println!("  日志文件大小: {} 字节", stats.storage_stats.log_file_size);
+            println!("  日志文件大小: {} 字节（{} 个日志段）", stats.storage_stats.log_file_size, stats.storage_stats.log_segments);
             println!("  快照文件大小: {} 字节", stats.storage_stats.snapshot_file_size);
             println!("  总存储大小: {} 字节", stats.storage_stats.total_size());
             println!("  日志条目数: {}", stats.storage_stats.total_log_entries);
             println!("  当前日志ID: {}", stats.storage_stats.current_log_id);
+            match stats.memory.limit {
+                Some(limit) => println!("  内存占用: {} / {} 字节", stats.memory.total_bytes(), limit),
+                None => println!("  内存占用: {} 字节", stats.memory.total_bytes()),
+            }
+            for table in &stats.memory.tables {
+                println!("    {}: {} 行, 行结构 {} 字节, 值 {} 字节, 索引 {} 字节",
+                    table.table, table.rows, table.row_bytes, table.value_bytes, table.index_bytes);
+                if table.spilled_rows > 0 {
+                    println!("      已溢出到磁盘: {} 行, {} 字节", table.spilled_rows, table.spilled_bytes);
+                }
+            }
         }
         Err(e) => {
             println!("获取统计信息失败: {}", e);
@@ -762,39 +978,85 @@ async fn show_stats(engine: &DatabaseEngine) {
     }
 }
 
-/// 删除表
-async fn drop_table(engine: &mut DatabaseEngine, table_name: &str) -> Result<(), Box<dyn std::error::Error>> {
-    use std::io::{self, Write};
+/// 执行SQL文件
+async fn execute_sql_file(engine: &mut DatabaseEngine, file_path: &str, format: OutputFormat, binary: BinaryFormat) -> Result<(), Box<dyn std::error::Error>> {
+    let content = std::fs::read_to_string(file_path)?;
+    println!("执行SQL文件: {}", file_path);
 
-    print!("确定要删除表 '{}' 吗? (y/N): ", table_name);
-    io::stdout().flush()?;
+    let results = engine.execute_sql(&content).await?;
+    for result in &results {
+        print_result(result, format, binary);
+    }
+    println!("执行完成，共 {} 条语句产生结果", results.len());
 
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
-    let input = input.trim().to_lowercase();
+    Ok(())
+}
 
-    if input == "y" || input == "yes" {
-        engine.drop_table(table_name).await?;
-        println!("表 '{}' 已删除", table_name);
-    } else {
-        println!("操作已取消");
+/// 执行 `query -e` 的语句，只把结果写到标准输出；遇到第一个错误时返回
+async fn run_query(engine: &DatabaseEngine, sql_text: &str, format: OutputFormat, binary: BinaryFormat) -> Result<(), DatabaseError> {
+    for statement in sql::parse_script(sql_text)? {
+        if let Some(result) = engine.execute_statement(statement).await? {
+            print!("{}", output::render_with(&result, format, binary));
+        }
+    }
+    Ok(())
+}
+
+/// 运行基准测试并输出报告
+async fn run_bench(rows: usize, workload: Workload, ops: Option<usize>, columnar: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let engine = DatabaseEngine::in_memory();
+    let layout = if columnar { TableLayout::Columnar } else { TableLayout::Row };
+    let mut config = BenchConfig::new(workload, rows).layout(layout);
+    if let Some(ops) = ops {
+        config = config.operations(ops);
     }
 
+    let report = bench::run(&engine, &config).await?;
+    let ms = |duration: std::time::Duration| duration.as_secs_f64() * 1000.0;
+    println!("负载: {}  布局: {:?}  行数: {}  操作: {}", report.workload, layout, report.rows, report.operations);
+    if !report.load_time.is_zero() {
+        println!("装载耗时: {:.3} s", report.load_time.as_secs_f64());
+    }
+    println!("耗时: {:.3} s  吞吐量: {:.1} ops/s", report.elapsed.as_secs_f64(), report.throughput());
+    let latency = &report.latency;
+    println!(
+        "延迟 (ms): min {:.3}  mean {:.3}  p50 {:.3}  p95 {:.3}  p99 {:.3}  max {:.3}",
+        ms(latency.min), ms(latency.mean), ms(latency.p50), ms(latency.p95), ms(latency.p99), ms(latency.max),
+    );
     Ok(())
 }
 
-/// 执行SQL文件
-async fn execute_sql_file(_engine: &mut DatabaseEngine, file_path: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let content = std::fs::read_to_string(file_path)?;
-    let statements: Vec<&str> = content.split(';').filter(|s| !s.trim().is_empty()).collect();
+/// 执行或回滚迁移
+async fn run_migrations(
+    engine: &DatabaseEngine,
+    dir: &str,
+    to: Option<i64>,
+    down: bool,
+    status: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let migrator = Migrator::from_dir(dir)?;
 
-    println!("执行SQL文件: {}", file_path);
-    println!("共 {} 条语句", statements.len());
+    if status {
+        let applied = engine.applied_migrations().await?;
+        for migration in migrator.migrations() {
+            let mark = if applied.contains(&migration.version) { "已应用" } else { "待执行" };
+            println!("  {:>4} {:<30} {}", migration.version, migration.name, mark);
+        }
+        return Ok(());
+    }
 
-    for (i, statement) in statements.iter().enumerate() {
-        println!("执行语句 {}: {}", i + 1, statement.trim());
-        // 这里可以扩展SQL解析和执行
-        println!("(SQL执行功能待实现)");
+    if down {
+        let reverted = engine.migrate_down(&migrator, to.unwrap_or(0)).await?;
+        for version in &reverted {
+            println!("已回滚迁移 {}", version);
+        }
+        println!("回滚完成，共 {} 个迁移", reverted.len());
+    } else {
+        let applied = engine.migrate_up(&migrator, to).await?;
+        for version in &applied {
+            println!("已应用迁移 {}", version);
+        }
+        println!("迁移完成，共 {} 个迁移", applied.len());
     }
 
     Ok(())
@@ -825,7 +1087,7 @@ async fn run_example(engine: &DatabaseEngine) {
         ColumnDefinition::new("content", DataType::Text, false),
         ColumnDefinition::new("author_id", DataType::Integer, false),
         ColumnDefinition::new("created_at", DataType::DateTime, false)
-            .default_value(Value::Text(chrono::Utc::now().to_rfc3339().to_string())),
+            .default_expr(Expr::function("NOW", Vec::new())),
     ]);
 
     match engine.create_table("posts", schema).await {
@@ -875,7 +1137,7 @@ async fn run_example(engine: &DatabaseEngine) {
     match engine.query(query).await {
         Ok(result) => {
             println!("用户表数据:");
-            print_table(&result.rows);
+            print_rows(&result, OutputFormat::Table, BinaryFormat::default());
         }
         Err(e) => println!("✗ 查询用户失败: {}", e),
     }
@@ -886,7 +1148,7 @@ async fn run_example(engine: &DatabaseEngine) {
     match engine.query(query).await {
         Ok(result) => {
             println!("文章表数据:");
-            print_table(&result.rows);
+            print_rows(&result, OutputFormat::Table, BinaryFormat::default());
         }
         Err(e) => println!("✗ 查询文章失败: {}", e),
     }
@@ -902,7 +1164,7 @@ async fn run_example(engine: &DatabaseEngine) {
     match engine.query(query).await {
         Ok(result) => {
             println!("年龄大于30的用户:");
-            print_table(&result.rows);
+            print_rows(&result, OutputFormat::Table, BinaryFormat::default());
         }
         Err(e) => println!("✗ 条件查询失败: {}", e),
     }
@@ -911,7 +1173,7 @@ async fn run_example(engine: &DatabaseEngine) {
 
     // 统计信息
     println!("5. 数据库统计信息...");
-    show_stats(&engine).await;
+    show_stats(engine).await;
 
     println!();
     println!("示例运行完成！");
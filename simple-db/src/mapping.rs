@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+
+use crate::error::{DatabaseError, Result};
+use crate::types::{Row, Value};
+
+/// 从数据行构造结构体，通常用 `#[derive(FromRow)]` 自动实现
+pub trait FromRow: Sized {
+    fn from_row(row: &Row) -> Result<Self>;
+}
+
+/// 把结构体转换为插入用的列值，通常用 `#[derive(ToRow)]` 自动实现
+pub trait ToRow {
+    fn to_row(&self) -> HashMap<String, Value>;
+}
+
+/// 从单个列值转换为 Rust 类型
+pub trait FromValue: Sized {
+    fn from_value(value: &Value) -> Result<Self>;
+}
+
+/// 读取列并转换为 `T`，缺失的列按 NULL 处理；派生宏生成的代码调用此函数
+pub fn column<T: FromValue>(row: &Row, column: &str) -> Result<T> {
+    let value = row.resolve(column);
+    T::from_value(value.as_deref().unwrap_or(&Value::Null))
+        .map_err(|e| DatabaseError::type_mismatch(format!("列 '{}': {}", column, e)))
+}
+
+fn mismatch(expected: &str, value: &Value) -> DatabaseError {
+    DatabaseError::type_mismatch(format!("期望 {}，实际为 {}", expected, value.get_type()))
+}
+
+macro_rules! impl_from_value {
+    ($ty:ty, $expected:literal, $($pattern:pat => $result:expr),+) => {
+        impl FromValue for $ty {
+            fn from_value(value: &Value) -> Result<Self> {
+                match value {
+                    $($pattern => Ok($result),)+
+                    other => Err(mismatch($expected, other)),
+                }
+            }
+        }
+    };
+}
+
+impl_from_value!(i64, "INTEGER", Value::Integer(v) => *v);
+impl_from_value!(f64, "FLOAT", Value::Float(v) => *v, Value::Integer(v) => *v as f64);
+impl_from_value!(bool, "BOOLEAN", Value::Boolean(v) => *v);
+impl_from_value!(String, "TEXT", Value::Text(v) => v.clone());
+impl_from_value!(Vec<u8>, "BINARY", Value::Binary(v) => v.clone());
+impl_from_value!(uuid::Uuid, "UUID", Value::Uuid(v) => *v);
+impl_from_value!(chrono::NaiveDate, "DATE", Value::Date(v) => *v);
+impl_from_value!(chrono::NaiveTime, "TIME", Value::Time(v) => *v);
+impl_from_value!(chrono::NaiveDateTime, "DATETIME", Value::DateTime(v) => *v);
+impl_from_value!(serde_json::Value, "JSON", Value::Json(v) => v.clone());
+impl_from_value!(Vec<Value>, "ARRAY", Value::Array(v) => v.clone());
+impl_from_value!(crate::interval::Interval, "INTERVAL", Value::Interval(v) => *v);
+
+impl FromValue for i32 {
+    fn from_value(value: &Value) -> Result<Self> {
+        let v = i64::from_value(value)?;
+        i32::try_from(v).map_err(|_| DatabaseError::type_mismatch(format!("{} 超出 i32 范围", v)))
+    }
+}
+
+impl FromValue for Value {
+    fn from_value(value: &Value) -> Result<Self> {
+        Ok(value.clone())
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: &Value) -> Result<Self> {
+        match value {
+            Value::Null => Ok(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FromRow, ToRow};
+
+    #[derive(Debug, PartialEq, FromRow, ToRow)]
+    struct User {
+        id: i64,
+        #[row(rename = "user_name")]
+        name: String,
+        score: Option<f64>,
+        #[row(skip)]
+        cached: bool,
+    }
+
+    #[test]
+    fn test_derive_round_trip() {
+        let user = User { id: 7, name: "alice".to_string(), score: None, cached: true };
+        let data = user.to_row();
+        assert_eq!(data.get("user_name"), Some(&Value::Text("alice".to_string())));
+        assert_eq!(data.get("score"), Some(&Value::Null));
+        assert!(!data.contains_key("cached"));
+
+        let mut row = Row::new();
+        row.data = data;
+        let loaded = User::from_row(&row).unwrap();
+        assert_eq!(loaded, User { cached: false, ..user });
+
+        row.set("id", Value::Text("x".to_string()));
+        let err = User::from_row(&row).unwrap_err().to_string();
+        assert!(err.contains("'id'"), "{}", err);
+    }
+}
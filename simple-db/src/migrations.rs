@@ -0,0 +1,200 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::backup::BoxFuture;
+use crate::engine::DatabaseEngine;
+use crate::error::{DatabaseError, Result};
+
+/// 记录已应用迁移版本的系统表
+pub const MIGRATIONS_TABLE: &str = "schema_migrations";
+
+/// Rust 编写的迁移步骤
+pub type MigrationFn = Arc<dyn for<'a> Fn(&'a DatabaseEngine) -> BoxFuture<'a, Result<()>> + Send + Sync>;
+
+/// 迁移的一个方向（升级或回滚）
+#[derive(Clone)]
+pub enum MigrationStep {
+    /// 依次执行的 SQL 脚本
+    Sql(String),
+    /// 任意 Rust 代码
+    Rust(MigrationFn),
+}
+
+impl MigrationStep {
+    pub(crate) async fn run(&self, engine: &DatabaseEngine) -> Result<()> {
+        match self {
+            MigrationStep::Sql(sql) => engine.execute_sql(sql).await.map(|_| ()),
+            MigrationStep::Rust(step) => step(engine).await,
+        }
+    }
+}
+
+impl fmt::Debug for MigrationStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationStep::Sql(sql) => f.debug_tuple("Sql").field(sql).finish(),
+            MigrationStep::Rust(_) => f.write_str("Rust(..)"),
+        }
+    }
+}
+
+/// 带版本号的迁移，版本号决定执行顺序
+#[derive(Debug, Clone)]
+pub struct Migration {
+    pub version: i64,
+    pub name: String,
+    pub up: MigrationStep,
+    /// 没有回滚步骤的迁移不能被 `migrate_down` 撤销
+    pub down: Option<MigrationStep>,
+}
+
+impl Migration {
+    pub fn sql<S: Into<String>>(version: i64, name: S, up: &str) -> Self {
+        Self {
+            version,
+            name: name.into(),
+            up: MigrationStep::Sql(up.to_string()),
+            down: None,
+        }
+    }
+
+    pub fn rust<S, F>(version: i64, name: S, up: F) -> Self
+    where
+        S: Into<String>,
+        F: for<'a> Fn(&'a DatabaseEngine) -> BoxFuture<'a, Result<()>> + Send + Sync + 'static,
+    {
+        Self {
+            version,
+            name: name.into(),
+            up: MigrationStep::Rust(Arc::new(up)),
+            down: None,
+        }
+    }
+
+    pub fn down_sql(mut self, down: &str) -> Self {
+        self.down = Some(MigrationStep::Sql(down.to_string()));
+        self
+    }
+
+    pub fn down_rust<F>(mut self, down: F) -> Self
+    where
+        F: for<'a> Fn(&'a DatabaseEngine) -> BoxFuture<'a, Result<()>> + Send + Sync + 'static,
+    {
+        self.down = Some(MigrationStep::Rust(Arc::new(down)));
+        self
+    }
+}
+
+/// 按版本号排序的迁移集合
+#[derive(Debug, Clone, Default)]
+pub struct Migrator {
+    migrations: Vec<Migration>,
+}
+
+impl Migrator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加迁移，版本号不能重复
+    pub fn migration(mut self, migration: Migration) -> Result<Self> {
+        if self.get(migration.version).is_some() {
+            return Err(DatabaseError::other(format!("迁移版本 {} 重复", migration.version)));
+        }
+        let position = self.migrations.partition_point(|m| m.version < migration.version);
+        self.migrations.insert(position, migration);
+        Ok(self)
+    }
+
+    /// 从目录加载 SQL 迁移
+    ///
+    /// 文件名格式为 `<版本>_<名称>.up.sql` 与可选的 `<版本>_<名称>.down.sql`，
+    /// 没有方向后缀的 `<版本>_<名称>.sql` 视为升级脚本。
+    pub fn from_dir<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let mut ups = Vec::new();
+        let mut downs = Vec::new();
+
+        for entry in fs::read_dir(dir.as_ref())? {
+            let path = entry?.path();
+            let file_name = match path.file_name().and_then(|name| name.to_str()) {
+                Some(name) if name.ends_with(".sql") => name.trim_end_matches(".sql"),
+                _ => continue,
+            };
+            let (stem, is_down) = if let Some(stem) = file_name.strip_suffix(".down") {
+                (stem, true)
+            } else {
+                (file_name.strip_suffix(".up").unwrap_or(file_name), false)
+            };
+            let (version, name) = parse_file_stem(stem)?;
+            let sql = fs::read_to_string(&path)?;
+            if is_down {
+                downs.push((version, sql));
+            } else {
+                ups.push(Migration::sql(version, name, &sql));
+            }
+        }
+
+        let mut migrator = Migrator::new();
+        for migration in ups {
+            migrator = migrator.migration(migration)?;
+        }
+        for (version, sql) in downs {
+            let migration = migrator.migrations.iter_mut()
+                .find(|m| m.version == version)
+                .ok_or_else(|| DatabaseError::other(format!("迁移 {} 只有回滚脚本", version)))?;
+            migration.down = Some(MigrationStep::Sql(sql));
+        }
+        Ok(migrator)
+    }
+
+    pub fn get(&self, version: i64) -> Option<&Migration> {
+        self.migrations.iter().find(|m| m.version == version)
+    }
+
+    pub fn migrations(&self) -> &[Migration] {
+        &self.migrations
+    }
+
+    /// 最新的迁移版本
+    pub fn latest_version(&self) -> Option<i64> {
+        self.migrations.last().map(|m| m.version)
+    }
+}
+
+/// 解析 `0001_create_users` 形式的文件名
+fn parse_file_stem(stem: &str) -> Result<(i64, String)> {
+    let (version, name) = stem.split_once('_').unwrap_or((stem, ""));
+    let version = version.parse::<i64>()
+        .map_err(|_| DatabaseError::parse_error(format!("迁移文件名 '{}' 缺少版本号", stem)))?;
+    Ok((version, name.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_dir_pairs_up_and_down() {
+        let dir = std::env::temp_dir().join(format!("simple-db-migrations-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("0002_add_posts.sql"), "CREATE TABLE posts (id INTEGER PRIMARY KEY);").unwrap();
+        fs::write(dir.join("0001_create_users.up.sql"), "CREATE TABLE users (id INTEGER PRIMARY KEY);").unwrap();
+        fs::write(dir.join("0001_create_users.down.sql"), "DROP TABLE users;").unwrap();
+        fs::write(dir.join("README.md"), "ignored").unwrap();
+
+        let migrator = Migrator::from_dir(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let versions: Vec<_> = migrator.migrations().iter().map(|m| m.version).collect();
+        assert_eq!(versions, vec![1, 2]);
+        assert_eq!(migrator.get(1).unwrap().name, "create_users");
+        assert!(migrator.get(1).unwrap().down.is_some());
+        assert!(migrator.get(2).unwrap().down.is_none());
+        assert_eq!(migrator.latest_version(), Some(2));
+
+        let duplicate = migrator.migration(Migration::sql(2, "again", ""));
+        assert!(duplicate.is_err());
+    }
+}
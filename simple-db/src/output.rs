@@ -0,0 +1,246 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::binary::BinaryFormat;
+use crate::error::{DatabaseError, Result};
+use crate::query::{QueryResult, QueryType};
+use crate::types::{Row, Value};
+
+/// 查询结果的输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// 带边框的表格
+    #[default]
+    Table,
+    /// JSON 数组，每行一个对象
+    Json,
+    /// 带表头的 CSV
+    Csv,
+    /// 每列一行，适合列很多的结果
+    Vertical,
+}
+
+impl FromStr for OutputFormat {
+    type Err = DatabaseError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "table" => Ok(OutputFormat::Table),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            "vertical" => Ok(OutputFormat::Vertical),
+            _ => Err(DatabaseError::parse_error(format!("未知的输出格式: {}（可选 table、json、csv、vertical）", s))),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OutputFormat::Table => "table",
+            OutputFormat::Json => "json",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Vertical => "vertical",
+        })
+    }
+}
+
+/// 结果的列顺序：优先使用结果携带的列信息，其次按表结构的顺序，再按名称排列其余的列
+pub fn result_columns(result: &QueryResult) -> Vec<String> {
+    let mut columns: Vec<String> = if result.columns.is_empty() {
+        result.schema.iter()
+            .flat_map(|schema| schema.columns.iter().map(|column| column.name.clone()))
+            .collect()
+    } else {
+        result.columns.iter().map(|(name, _)| name.clone()).collect()
+    };
+    let mut extra: Vec<&str> = result.rows.iter()
+        .flat_map(|row| row.columns())
+        .filter(|column| !columns.iter().any(|known| known == column))
+        .collect();
+    extra.sort_unstable();
+    extra.dedup();
+    columns.extend(extra.into_iter().map(str::to_string));
+    columns
+}
+
+/// 把结果渲染为文本，末尾带换行；COUNT 与写操作的结果按单行单列（`count` 或 `affected_rows`）输出
+pub fn render(result: &QueryResult, format: OutputFormat) -> String {
+    render_with(result, format, BinaryFormat::default())
+}
+
+/// 同 `render`，二进制值按 `binary` 显示
+pub fn render_with(result: &QueryResult, format: OutputFormat, binary: BinaryFormat) -> String {
+    let (columns, rows) = match result.query_type {
+        QueryType::Select => (result_columns(result), result.rows.clone()),
+        QueryType::Count => summary_row("count", result.count.unwrap_or(0)),
+        _ => summary_row("affected_rows", result.affected_rows),
+    };
+    render_rows_with(&columns, &rows, format, binary)
+}
+
+/// 按给定的列顺序渲染行，缺失的列按 NULL 输出
+pub fn render_rows(columns: &[String], rows: &[Row], format: OutputFormat) -> String {
+    render_rows_with(columns, rows, format, BinaryFormat::default())
+}
+
+/// 同 `render_rows`，二进制值按 `binary` 显示；JSON 中 `size` 输出字节数组，其余输出为字符串
+pub fn render_rows_with(columns: &[String], rows: &[Row], format: OutputFormat, binary: BinaryFormat) -> String {
+    match format {
+        OutputFormat::Table => render_table(columns, rows, binary),
+        OutputFormat::Json => render_json(columns, rows, binary),
+        OutputFormat::Csv => render_csv(columns, rows, binary),
+        OutputFormat::Vertical => render_vertical(columns, rows, binary),
+    }
+}
+
+fn summary_row(column: &str, value: usize) -> (Vec<String>, Vec<Row>) {
+    let mut row = Row::new();
+    row.set(column, Value::Integer(value as i64));
+    (vec![column.to_string()], vec![row])
+}
+
+fn text(value: &Value, binary: BinaryFormat) -> String {
+    match value {
+        Value::Binary(bytes) => binary.render(bytes),
+        value => value.to_string(),
+    }
+}
+
+fn cell(row: &Row, column: &str, binary: BinaryFormat) -> String {
+    row.get(column).map_or_else(|| "NULL".to_string(), |value| text(value, binary))
+}
+
+fn render_table(columns: &[String], rows: &[Row], binary: BinaryFormat) -> String {
+    let cells: Vec<Vec<String>> = rows.iter()
+        .map(|row| columns.iter().map(|column| cell(row, column, binary)).collect())
+        .collect();
+    let widths: Vec<usize> = columns.iter().enumerate()
+        .map(|(i, column)| cells.iter().map(|row| row[i].chars().count()).fold(column.chars().count(), usize::max))
+        .collect();
+
+    let border: String = widths.iter().map(|width| format!("{}+", "-".repeat(width + 2))).collect();
+    let line = |values: &[String]| -> String {
+        let padded: String = values.iter().zip(&widths)
+            .map(|(value, width)| format!(" {}{} |", value, " ".repeat(width - value.chars().count())))
+            .collect();
+        format!("|{}\n", padded)
+    };
+
+    let mut out = format!("+{}\n", border);
+    out.push_str(&line(columns));
+    out.push_str(&format!("+{}\n", border));
+    for row in &cells {
+        out.push_str(&line(row));
+    }
+    out.push_str(&format!("+{}\n", border));
+    out
+}
+
+fn render_json(columns: &[String], rows: &[Row], binary: BinaryFormat) -> String {
+    // 手工拼接对象以保持列顺序
+    let objects: Vec<String> = rows.iter()
+        .map(|row| {
+            let fields: Vec<String> = columns.iter()
+                .map(|column| {
+                    let value = match row.get(column) {
+                        Some(Value::Binary(bytes)) if binary != BinaryFormat::Size => serde_json::Value::from(binary.render(bytes)),
+                        value => value.map_or(serde_json::Value::Null, Value::to_json),
+                    };
+                    format!("{}: {}", serde_json::Value::from(column.as_str()), value)
+                })
+                .collect();
+            format!("{{{}}}", fields.join(", "))
+        })
+        .collect();
+    if objects.is_empty() {
+        "[]\n".to_string()
+    } else {
+        format!("[\n  {}\n]\n", objects.join(",\n  "))
+    }
+}
+
+fn render_csv(columns: &[String], rows: &[Row], binary: BinaryFormat) -> String {
+    let field = |text: &str| {
+        if text.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", text.replace('"', "\"\""))
+        } else {
+            text.to_string()
+        }
+    };
+    let mut out = columns.iter().map(|column| field(column)).collect::<Vec<_>>().join(",");
+    out.push('\n');
+    for row in rows {
+        // NULL 输出为空字段
+        let values: Vec<String> = columns.iter()
+            .map(|column| row.get(column).filter(|value| !value.is_null()).map_or(String::new(), |value| field(&text(value, binary))))
+            .collect();
+        out.push_str(&values.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+fn render_vertical(columns: &[String], rows: &[Row], binary: BinaryFormat) -> String {
+    let width = columns.iter().map(|column| column.chars().count()).max().unwrap_or(0);
+    let mut out = String::new();
+    for (index, row) in rows.iter().enumerate() {
+        out.push_str(&format!("{0} {1}. row {0}\n", "*".repeat(27), index + 1));
+        for column in columns {
+            out.push_str(&format!("{:>width$}: {}\n", column, cell(row, column, binary), width = width));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ColumnDefinition, DataType, Schema};
+
+    #[test]
+    fn test_render_formats() {
+        let schema = Schema::new(vec![
+            ColumnDefinition::new("name", DataType::Text, false),
+            ColumnDefinition::new("id", DataType::Integer, true),
+        ]);
+        let mut row = Row::new();
+        row.set("id", Value::Integer(1));
+        row.set("name", Value::Text("O'Neil, \"Jr\"".to_string()));
+        row.set("label", Value::Text("x".to_string()));
+        let mut empty = Row::new();
+        empty.set("id", Value::Integer(2));
+        let result = QueryResult::new(QueryType::Select, "people".to_string(), 0)
+            .with_rows(vec![row, empty])
+            .with_schema(schema);
+
+        assert_eq!(result_columns(&result), vec!["name", "id", "label"]);
+        assert_eq!(
+            render(&result, OutputFormat::Csv),
+            "name,id,label\n\"O'Neil, \"\"Jr\"\"\",1,x\n,2,\n"
+        );
+        assert_eq!(
+            render(&result, OutputFormat::Json),
+            "[\n  {\"name\": \"O'Neil, \\\"Jr\\\"\", \"id\": 1, \"label\": \"x\"},\n  {\"name\": null, \"id\": 2, \"label\": null}\n]\n"
+        );
+        assert!(render(&result, OutputFormat::Vertical).contains("  id: 2\n"));
+        assert!(render(&result, OutputFormat::Table).starts_with("+--------------+----+-------+\n| name         | id | label |\n"));
+
+        let count = QueryResult::new(QueryType::Count, "people".to_string(), 0).with_count(2);
+        assert_eq!(render(&count, OutputFormat::Csv), "count\n2\n");
+        assert_eq!("JSON".parse::<OutputFormat>().unwrap(), OutputFormat::Json);
+        assert!("xml".parse::<OutputFormat>().is_err());
+    }
+
+    #[test]
+    fn test_render_binary_formats() {
+        let mut row = Row::new();
+        row.set("data", Value::Binary(vec![0xca, 0xfe]));
+        let columns = vec!["data".to_string()];
+        let rows = [row];
+        assert_eq!(render_rows(&columns, &rows, OutputFormat::Csv), "data\nBINARY(2 bytes)\n");
+        assert_eq!(render_rows_with(&columns, &rows, OutputFormat::Csv, BinaryFormat::Hex), "data\n\\xcafe\n");
+        assert_eq!(render_rows_with(&columns, &rows, OutputFormat::Json, BinaryFormat::Base64), "[\n  {\"data\": \"base64:yv4=\"}\n]\n");
+        assert_eq!(render_rows(&columns, &rows, OutputFormat::Json), "[\n  {\"data\": [202,254]}\n]\n");
+    }
+}
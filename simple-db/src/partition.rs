@@ -0,0 +1,278 @@
+use std::cmp::Ordering;
+use serde::{Deserialize, Serialize};
+
+use crate::collation::Collation;
+use crate::error::{DatabaseError, Result};
+use crate::query::{ComparisonOperator, Condition};
+use crate::types::{Schema, Value};
+
+/// 表分区方式
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PartitionSpec {
+    /// 按分区键的哈希值分到 `buckets` 个分区
+    Hash { column: String, buckets: usize },
+    /// 按分区键的范围分区：`bounds` 严格递增，分区 i 保存 `bounds[i-1] <= v < bounds[i]`
+    /// 的行，共 `bounds.len() + 1` 个分区
+    Range { column: String, bounds: Vec<Value> },
+}
+
+impl PartitionSpec {
+    pub fn hash<S: Into<String>>(column: S, buckets: usize) -> Self {
+        PartitionSpec::Hash { column: column.into(), buckets }
+    }
+
+    pub fn range<S: Into<String>>(column: S, bounds: Vec<Value>) -> Self {
+        PartitionSpec::Range { column: column.into(), bounds }
+    }
+
+    /// 分区键
+    pub fn column(&self) -> &str {
+        match self {
+            PartitionSpec::Hash { column, .. } | PartitionSpec::Range { column, .. } => column,
+        }
+    }
+
+    pub fn partition_count(&self) -> usize {
+        match self {
+            PartitionSpec::Hash { buckets, .. } => *buckets,
+            PartitionSpec::Range { bounds, .. } => bounds.len() + 1,
+        }
+    }
+
+    /// 检查分区定义与表结构是否匹配
+    pub fn validate(&self, schema: &Schema) -> Result<()> {
+        if schema.get_column(self.column()).is_none() {
+            return Err(DatabaseError::column_not_found(self.column()));
+        }
+
+        match self {
+            PartitionSpec::Hash { buckets, .. } if *buckets == 0 => {
+                Err(DatabaseError::other("哈希分区数必须大于 0"))
+            }
+            PartitionSpec::Range { bounds, .. } => {
+                let ascending = bounds.windows(2)
+                    .all(|pair| compare_values(&pair[0], &pair[1]) == Some(Ordering::Less));
+                if ascending {
+                    Ok(())
+                } else {
+                    Err(DatabaseError::other("范围分区边界必须严格递增且类型一致"))
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// 分区键取值所在的分区，NULL 与缺失值归入第一个分区
+    pub fn partition_for(&self, value: Option<&Value>) -> usize {
+        let value = match value {
+            Some(value) if !value.is_null() => value,
+            _ => return 0,
+        };
+
+        match self {
+            PartitionSpec::Hash { buckets, .. } => partition_hash(value) as usize % *buckets,
+            PartitionSpec::Range { bounds, .. } => bounds.iter()
+                .position(|bound| compare_values(value, bound) == Some(Ordering::Less))
+                .unwrap_or(bounds.len()),
+        }
+    }
+
+    /// 根据查询条件裁剪分区，返回每个分区是否可能包含匹配的行
+    pub fn prune(&self, conditions: &[Condition]) -> Vec<bool> {
+        let mut candidates = vec![true; self.partition_count()];
+        // 非二进制排序规则下相等的文本可能落在不同分区，不参与裁剪；JSON 路径与表达式条件比较的不是分区键本身
+        let prunable = |c: &&Condition| {
+            c.column == self.column() && c.param.is_none() && c.path.is_none() && c.is_single_column()
+                && c.collation == Collation::Binary
+        };
+        for condition in conditions.iter().filter(prunable) {
+            for (index, keep) in candidates.iter_mut().enumerate() {
+                *keep = *keep && self.may_match(index, condition);
+            }
+        }
+        candidates
+    }
+
+    fn may_match(&self, index: usize, condition: &Condition) -> bool {
+        match &condition.operator {
+            ComparisonOperator::Equal => self.partition_for(Some(&condition.value)) == index,
+            ComparisonOperator::IsNull => self.partition_for(None) == index,
+            ComparisonOperator::In => match &condition.value {
+                Value::Json(serde_json::Value::Array(items)) => items.iter()
+                    .any(|item| self.partition_for(Some(&Value::from(item.clone()))) == index),
+                _ => true,
+            },
+            operator => match self {
+                PartitionSpec::Range { bounds, .. } => {
+                    // 分区 index 覆盖 [lower, upper)；NULL 在比较中视为最小值，位于第一个分区
+                    let lower = index.checked_sub(1).map(|i| &bounds[i]);
+                    let upper = bounds.get(index);
+                    let value = &condition.value;
+                    match operator {
+                        ComparisonOperator::GreaterThan | ComparisonOperator::GreaterThanOrEqual => upper
+                            .is_none_or(|upper| !matches!(compare_values(upper, value), Some(Ordering::Less | Ordering::Equal))),
+                        ComparisonOperator::LessThan => lower
+                            .is_none_or(|lower| !matches!(compare_values(lower, value), Some(Ordering::Greater | Ordering::Equal))),
+                        ComparisonOperator::LessThanOrEqual => lower
+                            .is_none_or(|lower| !matches!(compare_values(lower, value), Some(Ordering::Greater))),
+                        _ => true,
+                    }
+                }
+                PartitionSpec::Hash { .. } => true,
+            },
+        }
+    }
+}
+
+/// 分区键的稳定哈希，哈希分区与分片路由共用
+///
+/// 对值的规范编码（类型标记加内容）计算 CRC32，不随进程、平台或编译器版本变化，保存的分区与分片在重启后仍然有效；
+/// 数值按数学值编码，`Integer(2)` 与 `Float(2.0)` 落在同一个分区。
+pub fn partition_hash(value: &Value) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    encode_key(value, &mut hasher);
+    hasher.finalize()
+}
+
+fn encode_key(value: &Value, hasher: &mut crc32fast::Hasher) {
+    match value {
+        Value::Null => hasher.update(b"0"),
+        Value::Integer(i) => {
+            hasher.update(b"i");
+            hasher.update(&i.to_be_bytes());
+        }
+        // 整数值的浮点数按整数编码，-0.0 与 0.0 相同，所有 NaN 相同
+        Value::Float(f) if f.fract() == 0.0 && *f >= i64::MIN as f64 && *f < i64::MAX as f64 => {
+            encode_key(&Value::Integer(*f as i64), hasher);
+        }
+        Value::Float(f) => {
+            hasher.update(b"f");
+            hasher.update(&canonical_float(*f).to_be_bytes());
+        }
+        Value::Text(s) => {
+            hasher.update(b"s");
+            encode_bytes(s.as_bytes(), hasher);
+        }
+        Value::Boolean(b) => hasher.update(if *b { b"b1" } else { b"b0" }),
+        Value::Date(d) => {
+            hasher.update(b"d");
+            hasher.update(&chrono::Datelike::num_days_from_ce(d).to_be_bytes());
+        }
+        Value::Time(t) => {
+            hasher.update(b"t");
+            hasher.update(&chrono::Timelike::num_seconds_from_midnight(t).to_be_bytes());
+            hasher.update(&chrono::Timelike::nanosecond(t).to_be_bytes());
+        }
+        Value::DateTime(dt) => {
+            let utc = dt.and_utc();
+            hasher.update(b"T");
+            hasher.update(&utc.timestamp().to_be_bytes());
+            hasher.update(&utc.timestamp_subsec_nanos().to_be_bytes());
+        }
+        // serde_json 的对象按键排序，同样的 JSON 值得到同样的文本
+        Value::Json(json) => {
+            hasher.update(b"j");
+            encode_bytes(json.to_string().as_bytes(), hasher);
+        }
+        Value::Binary(bytes) => {
+            hasher.update(b"x");
+            encode_bytes(bytes, hasher);
+        }
+        Value::Array(items) => {
+            hasher.update(b"a");
+            hasher.update(&(items.len() as u64).to_be_bytes());
+            for item in items {
+                encode_key(item, hasher);
+            }
+        }
+        Value::Uuid(id) => {
+            hasher.update(b"u");
+            hasher.update(id.as_bytes());
+        }
+        Value::Point { lat, lon } => {
+            hasher.update(b"p");
+            hasher.update(&canonical_float(*lat).to_be_bytes());
+            hasher.update(&canonical_float(*lon).to_be_bytes());
+        }
+        Value::Interval(interval) => {
+            hasher.update(b"v");
+            hasher.update(&interval.months.to_be_bytes());
+            hasher.update(&interval.days.to_be_bytes());
+            hasher.update(&interval.micros.to_be_bytes());
+        }
+    }
+}
+
+/// 带长度前缀，相邻的变长内容不会拼出同样的编码
+fn encode_bytes(bytes: &[u8], hasher: &mut crc32fast::Hasher) {
+    hasher.update(&(bytes.len() as u64).to_be_bytes());
+    hasher.update(bytes);
+}
+
+fn canonical_float(f: f64) -> u64 {
+    if f.is_nan() {
+        f64::NAN.to_bits()
+    } else if f == 0.0 {
+        0
+    } else {
+        f.to_bits()
+    }
+}
+
+/// 比较同类型的值，类型不同时返回 None（整数与浮点数可以比较）
+pub(crate) fn compare_values(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Integer(a), Value::Integer(b)) => Some(a.cmp(b)),
+        (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+        (Value::Integer(a), Value::Float(b)) => (*a as f64).partial_cmp(b),
+        (Value::Float(a), Value::Integer(b)) => a.partial_cmp(&(*b as f64)),
+        (Value::Text(a), Value::Text(b)) => Some(a.cmp(b)),
+        (Value::Date(a), Value::Date(b)) => Some(a.cmp(b)),
+        (Value::Time(a), Value::Time(b)) => Some(a.cmp(b)),
+        (Value::DateTime(a), Value::DateTime(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_range_routing_and_pruning() {
+        let spec = PartitionSpec::range("year", vec![Value::Integer(2020), Value::Integer(2023)]);
+        assert_eq!(spec.partition_count(), 3);
+        assert_eq!(spec.partition_for(Some(&Value::Integer(2019))), 0);
+        assert_eq!(spec.partition_for(Some(&Value::Integer(2020))), 1);
+        assert_eq!(spec.partition_for(Some(&Value::Integer(2030))), 2);
+
+        let eq = Condition::new("year", ComparisonOperator::Equal, Value::Integer(2021));
+        assert_eq!(spec.prune(&[eq]), vec![false, true, false]);
+
+        let gte = Condition::new("year", ComparisonOperator::GreaterThanOrEqual, Value::Integer(2023));
+        assert_eq!(spec.prune(&[gte]), vec![false, false, true]);
+
+        let lt = Condition::new("year", ComparisonOperator::LessThan, Value::Integer(2020));
+        assert_eq!(spec.prune(&[lt]), vec![true, false, false]);
+
+        let other = Condition::new("name", ComparisonOperator::Equal, Value::Integer(1));
+        assert_eq!(spec.prune(&[other]), vec![true, true, true]);
+    }
+
+    #[test]
+    fn test_hash_pruning() {
+        let spec = PartitionSpec::hash("id", 4);
+        let target = spec.partition_for(Some(&Value::Integer(42)));
+        let eq = Condition::new("id", ComparisonOperator::Equal, Value::Integer(42));
+        let candidates = spec.prune(&[eq]);
+        assert_eq!(candidates.iter().filter(|keep| **keep).count(), 1);
+        assert!(candidates[target]);
+
+        // 哈希只取决于值本身：固定的值落在固定的分区，数值按数学值比较
+        assert_eq!(partition_hash(&Value::Integer(42)), 0xCD72_EA87);
+        assert_eq!(target, 0xCD72_EA87 % 4);
+        assert_eq!(spec.partition_for(Some(&Value::Float(42.0))), target);
+        assert_eq!(partition_hash(&Value::Float(-0.0)), partition_hash(&Value::Integer(0)));
+        assert_ne!(partition_hash(&Value::Text("42".to_string())), partition_hash(&Value::Integer(42)));
+    }
+}
@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::{DatabaseError, Result};
+use crate::query::{ComparisonOperator, Params, Query};
+use crate::types::Value;
+
+/// 存储过程中的一个步骤
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ProcedureStep {
+    /// 执行查询，查询中的 `:param` 占位符由调用参数绑定
+    Execute(Query),
+    /// 根据上一条语句影响的行数选择分支: `IF affected_rows <op> value THEN ... ELSE ...`
+    IfAffectedRows {
+        operator: ComparisonOperator,
+        value: usize,
+        then_steps: Vec<ProcedureStep>,
+        else_steps: Vec<ProcedureStep>,
+    },
+}
+
+impl ProcedureStep {
+    pub fn execute(query: Query) -> Self {
+        ProcedureStep::Execute(query)
+    }
+
+    pub fn if_affected_rows(
+        operator: ComparisonOperator,
+        value: usize,
+        then_steps: Vec<ProcedureStep>,
+        else_steps: Vec<ProcedureStep>,
+    ) -> Self {
+        ProcedureStep::IfAffectedRows {
+            operator,
+            value,
+            then_steps,
+            else_steps,
+        }
+    }
+}
+
+/// 存储过程：带参数的多语句例程
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Procedure {
+    pub name: String,
+    pub params: Vec<String>,
+    pub body: Vec<ProcedureStep>,
+}
+
+impl Procedure {
+    pub fn new<S: Into<String>>(name: S, params: Vec<String>, body: Vec<ProcedureStep>) -> Self {
+        Self {
+            name: name.into(),
+            params,
+            body,
+        }
+    }
+
+    /// 将调用参数绑定为查询参数，参数既可按名称 (`:name`) 也可按位置 (`$1`) 引用
+    pub fn bind_args(&self, args: Vec<Value>) -> Result<Params> {
+        if args.len() != self.params.len() {
+            return Err(DatabaseError::other(format!(
+                "存储过程 '{}' 需要 {} 个参数，实际传入 {} 个",
+                self.name,
+                self.params.len(),
+                args.len()
+            )));
+        }
+
+        let mut params = Params::new();
+        for (name, value) in self.params.iter().zip(args) {
+            params = params.push(value.clone()).set(name.clone(), value);
+        }
+        Ok(params)
+    }
+}
+
+/// 计算 `affected_rows <op> value`
+pub(crate) fn compare_affected_rows(operator: &ComparisonOperator, affected_rows: usize, value: usize) -> Result<bool> {
+    match operator {
+        ComparisonOperator::Equal => Ok(affected_rows == value),
+        ComparisonOperator::NotEqual => Ok(affected_rows != value),
+        ComparisonOperator::GreaterThan => Ok(affected_rows > value),
+        ComparisonOperator::GreaterThanOrEqual => Ok(affected_rows >= value),
+        ComparisonOperator::LessThan => Ok(affected_rows < value),
+        ComparisonOperator::LessThanOrEqual => Ok(affected_rows <= value),
+        other => Err(DatabaseError::other(format!("存储过程条件不支持运算符 {}", other))),
+    }
+}
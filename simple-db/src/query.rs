@@ -1,9 +1,23 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fmt;
 
 use serde::{Deserialize, Serialize};
-
+use uuid::Uuid;
+
+use crate::clock;
+use crate::collation::Collation;
+use crate::columnar::{ColumnStore, TableLayout};
+use crate::expression::{Expr, Projection};
+use crate::geo;
+use crate::json_path::JsonPath;
 use crate::error::{DatabaseError, Result};
-use crate::types::{Value, Table, Row};
+use crate::sql;
+use crate::vectorized::{self, Aggregate, Totals};
+use crate::types::{
+    DataType, Value, Table, Row, Schema, CREATED_AT_COLUMN, ID_COLUMN, UPDATED_AT_COLUMN, VERSION_COLUMN,
+};
 
 /// 查询条件运算符
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -15,34 +29,143 @@ pub enum ComparisonOperator {
     LessThan,
     LessThanOrEqual,
     Like,
+    /// 文本或二进制值以给定的前缀开始
+    StartsWith,
     In,
     IsNull,
     IsNotNull,
+    /// 数组包含给定的全部元素（`@>`），给定单个值时视为单元素数组
+    Contains,
+    /// 数组的元素都在给定数组中（`<@`）
+    ContainedBy,
+    /// 数组与给定数组至少有一个共同元素（`&&`）
+    Overlaps,
+    /// 坐标在给定中心点的半径之内，值为 `[中心点, 半径米数]`，见 `Condition::within_radius`
+    WithinRadius,
 }
 
 impl ComparisonOperator {
-    pub fn to_string(&self) -> String {
+    /// 是否为数组运算符，可以使用元素索引
+    pub fn is_array_operator(&self) -> bool {
+        matches!(self, ComparisonOperator::Contains | ComparisonOperator::ContainedBy | ComparisonOperator::Overlaps)
+    }
+}
+
+impl fmt::Display for ComparisonOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            ComparisonOperator::Equal => "=",
+            ComparisonOperator::NotEqual => "!=",
+            ComparisonOperator::GreaterThan => ">",
+            ComparisonOperator::GreaterThanOrEqual => ">=",
+            ComparisonOperator::LessThan => "<",
+            ComparisonOperator::LessThanOrEqual => "<=",
+            ComparisonOperator::Like => "LIKE",
+            ComparisonOperator::StartsWith => "STARTS WITH",
+            ComparisonOperator::In => "IN",
+            ComparisonOperator::IsNull => "IS NULL",
+            ComparisonOperator::IsNotNull => "IS NOT NULL",
+            ComparisonOperator::Contains => "@>",
+            ComparisonOperator::ContainedBy => "<@",
+            ComparisonOperator::Overlaps => "&&",
+            ComparisonOperator::WithinRadius => "WITHIN",
+        };
+        f.write_str(symbol)
+    }
+}
+
+/// 查询参数占位符
+///
+/// 参数在执行前通过 `Query::bind` 绑定为类型化的 `Value`，
+/// 用户输入永远不会被拼接进查询结构中。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Placeholder {
+    /// 位置参数，从 1 开始（`$1`）
+    Positional(usize),
+    /// 命名参数（`:name`）
+    Named(String),
+}
+
+impl Placeholder {
+    /// 解析占位符文本：`$1`、`:name`
+    pub fn parse(text: &str) -> Option<Self> {
+        if let Some(index) = text.strip_prefix('$') {
+            return index.parse().ok().filter(|i| *i > 0).map(Placeholder::Positional);
+        }
+        text.strip_prefix(':')
+            .filter(|name| !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_'))
+            .map(|name| Placeholder::Named(name.to_string()))
+    }
+}
+
+impl fmt::Display for Placeholder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            ComparisonOperator::Equal => "=".to_string(),
-            ComparisonOperator::NotEqual => "!=".to_string(),
-            ComparisonOperator::GreaterThan => ">".to_string(),
-            ComparisonOperator::GreaterThanOrEqual => ">=".to_string(),
-            ComparisonOperator::LessThan => "<".to_string(),
-            ComparisonOperator::LessThanOrEqual => "<=".to_string(),
-            ComparisonOperator::Like => "LIKE".to_string(),
-            ComparisonOperator::In => "IN".to_string(),
-            ComparisonOperator::IsNull => "IS NULL".to_string(),
-            ComparisonOperator::IsNotNull => "IS NOT NULL".to_string(),
+            Placeholder::Positional(index) => write!(f, "${}", index),
+            Placeholder::Named(name) => write!(f, ":{}", name),
         }
     }
 }
 
+/// 查询参数
+#[derive(Debug, Clone, Default)]
+pub struct Params {
+    positional: Vec<Value>,
+    named: HashMap<String, Value>,
+}
+
+impl Params {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 追加一个位置参数
+    pub fn push<V: Into<Value>>(mut self, value: V) -> Self {
+        self.positional.push(value.into());
+        self
+    }
+
+    /// 设置一个命名参数
+    pub fn set<S: Into<String>, V: Into<Value>>(mut self, name: S, value: V) -> Self {
+        self.named.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn get(&self, placeholder: &Placeholder) -> Option<&Value> {
+        match placeholder {
+            Placeholder::Positional(index) => self.positional.get(index.checked_sub(1)?),
+            Placeholder::Named(name) => self.named.get(name),
+        }
+    }
+}
+
+impl From<HashMap<String, Value>> for Params {
+    fn from(named: HashMap<String, Value>) -> Self {
+        Self { positional: Vec::new(), named }
+    }
+}
+
 /// 查询条件
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Condition {
     pub column: String,
     pub operator: ComparisonOperator,
     pub value: Value,
+    /// 尚未绑定的参数占位符；绑定后 `value` 为参数值
+    #[serde(default)]
+    pub param: Option<Placeholder>,
+    /// 文本比较的排序规则，执行时取列定义的规则，除非显式指定
+    #[serde(default)]
+    pub collation: Collation,
+    /// 设置时比较 JSON 列中该路径处的值，而不是整列
+    #[serde(default)]
+    pub path: Option<JsonPath>,
+    /// 设置时比较表达式的值，`column` 仅为表达式的文本
+    #[serde(default)]
+    pub expr: Option<Expr>,
+    /// 设置时与该表达式（例如另一列）的值比较，而不是 `value`
+    #[serde(default)]
+    pub right: Option<Expr>,
 }
 
 impl Condition {
@@ -51,74 +174,335 @@ impl Condition {
             column: column.into(),
             operator,
             value,
+            param: None,
+            collation: Collation::Binary,
+            path: None,
+            expr: None,
+            right: None,
+        }
+    }
+
+    /// 条件的结构，比较的值替换为 `?`
+    fn shape(&self) -> String {
+        let left = match (&self.expr, &self.path) {
+            (Some(expr), _) => expr.to_string(),
+            (None, Some(path)) => format!("JSON_EXTRACT({}, '{}')", sql::quote_identifier(&self.column), path),
+            (None, None) => sql::quote_identifier(&self.column),
+        };
+        match (&self.operator, &self.right) {
+            (ComparisonOperator::IsNull | ComparisonOperator::IsNotNull, _) => format!("{} {}", left, self.operator),
+            (operator, Some(right)) => format!("{} {} {}", left, operator, right),
+            (ComparisonOperator::In, None) => format!("{} IN (?)", left),
+            (ComparisonOperator::WithinRadius, None) => format!("{} WITHIN ? OF ?", left),
+            (operator, None) => format!("{} {} ?", left, operator),
+        }
+    }
+
+    /// 创建带参数占位符的条件
+    pub fn with_param<S: Into<String>>(column: S, operator: ComparisonOperator, param: Placeholder) -> Self {
+        Self {
+            column: column.into(),
+            operator,
+            value: Value::Null,
+            param: Some(param),
+            collation: Collation::Binary,
+            path: None,
+            expr: None,
+            right: None,
+        }
+    }
+
+    /// 坐标列与 `center` 的距离不超过 `meters` 米
+    pub fn within_radius<S: Into<String>>(column: S, center: (f64, f64), meters: f64) -> Self {
+        let center = Value::Point { lat: center.0, lon: center.1 };
+        Self::new(column, ComparisonOperator::WithinRadius, Value::Array(vec![center, Value::Float(meters)]))
+    }
+
+    /// 半径条件的中心点与半径（米），值的形式不对时返回 None
+    pub fn radius(&self) -> Option<((f64, f64), f64)> {
+        match &self.value {
+            Value::Array(items) => match items.as_slice() {
+                [Value::Point { lat, lon }, Value::Float(meters)] => Some(((*lat, *lon), *meters)),
+                [Value::Point { lat, lon }, Value::Integer(meters)] => Some(((*lat, *lon), *meters as f64)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// 比较 JSON 列中 `path` 处的值，路径不存在时按 NULL 处理
+    pub fn json_path<S: Into<String>>(column: S, path: JsonPath, operator: ComparisonOperator, value: Value) -> Self {
+        let mut condition = Self::new(column, operator, value);
+        condition.path = Some(path);
+        condition
+    }
+
+    /// 比较两个表达式，例如 `price * qty > 100` 或列与列的比较 `updated_at > created_at`
+    ///
+    /// 左侧为单独的列时按普通列条件处理，右侧为常量时在此求值。
+    pub fn expression(left: Expr, operator: ComparisonOperator, right: Expr) -> Result<Self> {
+        let mut condition = match left {
+            Expr::Column(column) => Self::new(column, operator, Value::Null),
+            left => {
+                let mut condition = Self::new(left.to_string(), operator, Value::Null);
+                condition.expr = Some(left);
+                condition
+            }
+        };
+        if right.is_constant() {
+            condition.value = right.evaluate(&Row::new())?;
+        } else {
+            condition.right = Some(right);
         }
+        Ok(condition)
+    }
+
+    /// 条件读取的所有列
+    pub fn columns(&self) -> Vec<&str> {
+        let mut columns = match &self.expr {
+            Some(expr) => expr.columns(),
+            None => vec![self.column.as_str()],
+        };
+        columns.extend(self.right.iter().flat_map(Expr::columns));
+        columns
+    }
+
+    /// 对同一行多次求值结果总是相同，不依赖 `NOW()` 这类随时间变化的函数
+    pub fn is_deterministic(&self) -> bool {
+        self.expr.iter().chain(self.right.iter()).all(Expr::is_deterministic)
+    }
+
+    /// 只读取 `column` 一列的条件，可以按列求值
+    pub fn is_single_column(&self) -> bool {
+        self.expr.is_none() && self.right.is_none()
+    }
+
+    /// 显式指定排序规则，例如对二进制列做忽略大小写的查找
+    pub fn with_collation(mut self, collation: Collation) -> Self {
+        self.collation = collation;
+        self
     }
 
+    /// 对一行求值，结果为 UNKNOWN 的行视为不匹配
     pub fn evaluate(&self, row: &Row) -> Result<bool> {
-        let row_value = row.get(&self.column);
+        Ok(self.evaluate_tri(row)? == Some(true))
+    }
+
+    /// 按 SQL 三值逻辑求值：`None` 表示 UNKNOWN
+    ///
+    /// 除 IS [NOT] NULL 外，任一侧为 NULL（包括缺失的列）的比较结果都是 UNKNOWN，
+    /// 因此 `col = NULL` 与 `col != 5` 都不会匹配 NULL 行。
+    pub fn evaluate_tri(&self, row: &Row) -> Result<Option<bool>> {
+        let row_value = match &self.expr {
+            Some(expr) => Some(Cow::Owned(expr.evaluate(row)?)),
+            None => row.resolve(&self.column),
+        };
+        match &self.right {
+            Some(right) => self.evaluate_against(row_value.as_deref(), &right.evaluate(row)?),
+            None => self.evaluate_against(row_value.as_deref(), &self.value),
+        }
+    }
+
+    /// 对单个列值求值，`None` 表示该列没有值；UNKNOWN 视为不匹配
+    pub fn evaluate_value(&self, row_value: Option<&Value>) -> Result<bool> {
+        Ok(self.evaluate_against(row_value, &self.value)? == Some(true))
+    }
+
+    fn evaluate_against(&self, row_value: Option<&Value>, value: &Value) -> Result<Option<bool>> {
+        if let Some(path) = &self.path {
+            let selected = match row_value {
+                Some(Value::Json(json)) => path.select(json)?
+                    .map(|selected| Value::from_json(selected, None))
+                    .transpose()?,
+                _ => None,
+            };
+            return self.evaluate_operator(selected.as_ref(), value);
+        }
+        self.evaluate_operator(row_value, value)
+    }
 
+    fn evaluate_operator(&self, row_value: Option<&Value>, value: &Value) -> Result<Option<bool>> {
+        let compare = |test: fn(i32) -> bool| -> Result<Option<bool>> {
+            Ok(self.compare_values(row_value, value)?.map(test))
+        };
         match self.operator {
-            ComparisonOperator::Equal => Ok(self.compare_values(row_value, &self.value)? == 0),
-            ComparisonOperator::NotEqual => Ok(self.compare_values(row_value, &self.value)? != 0),
-            ComparisonOperator::GreaterThan => Ok(self.compare_values(row_value, &self.value)? > 0),
-            ComparisonOperator::GreaterThanOrEqual => Ok(self.compare_values(row_value, &self.value)? >= 0),
-            ComparisonOperator::LessThan => Ok(self.compare_values(row_value, &self.value)? < 0),
-            ComparisonOperator::LessThanOrEqual => Ok(self.compare_values(row_value, &self.value)? <= 0),
-            ComparisonOperator::Like => Ok(self.evaluate_like(row_value)),
-            ComparisonOperator::In => Ok(self.evaluate_in(row_value)),
-            ComparisonOperator::IsNull => Ok(row_value.map_or(true, |v| v.is_null())),
-            ComparisonOperator::IsNotNull => Ok(row_value.map_or(false, |v| !v.is_null())),
+            ComparisonOperator::Equal => compare(|ordering| ordering == 0),
+            ComparisonOperator::NotEqual => compare(|ordering| ordering != 0),
+            ComparisonOperator::GreaterThan => compare(|ordering| ordering > 0),
+            ComparisonOperator::GreaterThanOrEqual => compare(|ordering| ordering >= 0),
+            ComparisonOperator::LessThan => compare(|ordering| ordering < 0),
+            ComparisonOperator::LessThanOrEqual => compare(|ordering| ordering <= 0),
+            ComparisonOperator::Like => Ok(self.evaluate_like(row_value, value)),
+            ComparisonOperator::StartsWith => self.evaluate_prefix(row_value, value),
+            ComparisonOperator::In => Ok(self.evaluate_in(row_value, value)),
+            ComparisonOperator::IsNull => Ok(Some(row_value.is_none_or(|v| v.is_null()))),
+            ComparisonOperator::IsNotNull => Ok(Some(row_value.is_some_and(|v| !v.is_null()))),
+            ComparisonOperator::Contains | ComparisonOperator::ContainedBy | ComparisonOperator::Overlaps => {
+                self.evaluate_array(row_value, value)
+            }
+            ComparisonOperator::WithinRadius => self.evaluate_radius(row_value),
+        }
+    }
+
+    fn evaluate_radius(&self, row_value: Option<&Value>) -> Result<Option<bool>> {
+        let ((lat, lon), meters) = self.radius().ok_or_else(|| DatabaseError::type_mismatch(
+            format!("列 '{}' 的 WITHIN 条件需要半径与 POINT 中心点", self.column)
+        ))?;
+        match row_value {
+            None | Some(Value::Null) => Ok(None),
+            Some(Value::Point { lat: row_lat, lon: row_lon }) => {
+                Ok(Some(geo::distance_meters((*row_lat, *row_lon), (lat, lon)) <= meters))
+            }
+            Some(_) => Err(DatabaseError::type_mismatch(
+                format!("列 '{}' 不是 POINT，不能使用 WITHIN", self.column)
+            )),
         }
     }
 
-    fn compare_values(&self, a: Option<&Value>, b: &Value) -> Result<i32> {
+    /// 条件值的元素键，单个值视为单元素数组
+    pub fn element_keys(&self) -> Vec<String> {
+        self.keys_of(&self.value)
+    }
+
+    fn keys_of(&self, value: &Value) -> Vec<String> {
+        let elements = match value {
+            Value::Array(items) => items.as_slice(),
+            value => std::slice::from_ref(value),
+        };
+        elements.iter().map(|element| self.collation.key(element)).collect()
+    }
+
+    fn evaluate_array(&self, row_value: Option<&Value>, value: &Value) -> Result<Option<bool>> {
+        if value.is_null() {
+            return Ok(None);
+        }
+        let items = match row_value {
+            None | Some(Value::Null) => return Ok(None),
+            Some(Value::Array(items)) => items,
+            Some(_) => return Err(DatabaseError::type_mismatch(
+                format!("列 '{}' 不是数组，不能使用 {}", self.column, self.operator)
+            )),
+        };
+        let row_keys: HashSet<String> = items.iter().map(|item| self.collation.key(item)).collect();
+        let keys = self.keys_of(value);
+        Ok(Some(match self.operator {
+            ComparisonOperator::Contains => keys.iter().all(|key| row_keys.contains(key)),
+            ComparisonOperator::ContainedBy => {
+                let keys: HashSet<&String> = keys.iter().collect();
+                row_keys.iter().all(|key| keys.contains(key))
+            }
+            _ => keys.iter().any(|key| row_keys.contains(key)),
+        }))
+    }
+
+    /// 比较两个值，任一侧为 NULL 时结果为 UNKNOWN（`None`）
+    fn compare_values(&self, a: Option<&Value>, b: &Value) -> Result<Option<i32>> {
+        let a = match a {
+            None | Some(Value::Null) => return Ok(None),
+            Some(a) => a,
+        };
+        if b.is_null() {
+            return Ok(None);
+        }
+        self.compare_non_null(a, b).map(Some)
+    }
+
+    fn compare_non_null(&self, a: &Value, b: &Value) -> Result<i32> {
         match (a, b) {
-            (Some(Value::Integer(a)), Value::Integer(b)) => Ok(a.cmp(b) as i32),
-            (Some(Value::Text(a)), Value::Text(b)) => Ok(a.cmp(b) as i32),
-            (Some(Value::Boolean(a)), Value::Boolean(b)) => Ok(a.cmp(b) as i32),
-            (Some(Value::Float(a)), Value::Float(b)) => {
+            (Value::Integer(a), Value::Integer(b)) => Ok(a.cmp(b) as i32),
+            (Value::Text(a), Value::Text(b)) => Ok(self.collation.compare(a, b) as i32),
+            (Value::Boolean(a), Value::Boolean(b)) => Ok(a.cmp(b) as i32),
+            (Value::Float(a), Value::Float(b)) => {
                 if a.partial_cmp(b).is_some() {
                     Ok(a.partial_cmp(b).unwrap() as i32)
                 } else {
                     Ok(0)
                 }
             }
-            (Some(Value::Date(a)), Value::Date(b)) => Ok(a.cmp(b) as i32),
-            (Some(Value::Time(a)), Value::Time(b)) => Ok(a.cmp(b) as i32),
-            (Some(Value::DateTime(a)), Value::DateTime(b)) => Ok(a.cmp(b) as i32),
-            (None, _) => Ok(-1), // NULL 值最小
-            (Some(_), _) => Err(DatabaseError::type_mismatch(
+            (Value::Date(a), Value::Date(b)) => Ok(a.cmp(b) as i32),
+            (Value::Time(a), Value::Time(b)) => Ok(a.cmp(b) as i32),
+            (Value::DateTime(a), Value::DateTime(b)) => Ok(a.cmp(b) as i32),
+            (Value::Uuid(a), Value::Uuid(b)) => Ok(a.cmp(b) as i32),
+            (Value::Interval(a), Value::Interval(b)) => Ok(a.cmp(b) as i32),
+            // 二进制按字节逐个比较
+            (Value::Binary(a), Value::Binary(b)) => Ok(a.cmp(b) as i32),
+            // 行 ID 可以直接与 UUID 文本比较，例如 `_id = '67e5...'`
+            (Value::Uuid(a), Value::Text(b)) => b.trim().parse::<uuid::Uuid>()
+                .map(|b| a.cmp(&b) as i32)
+                .map_err(|_| DatabaseError::type_mismatch(format!("'{}' 不是有效的 UUID", b))),
+            // 表达式的结果可能是整数与浮点数混合
+            (Value::Integer(a), Value::Float(b)) => Ok((*a as f64).partial_cmp(b).map_or(0, |o| o as i32)),
+            (Value::Float(a), Value::Integer(b)) => Ok(a.partial_cmp(&(*b as f64)).map_or(0, |o| o as i32)),
+            // 数组按元素逐个比较，前缀相同时较短者更小
+            (Value::Array(a), Value::Array(b)) => {
+                for (a, b) in a.iter().zip(b) {
+                    // 数组内部的 NULL 元素排在最前，保持数组之间的全序
+                    let ordering = match (a.is_null(), b.is_null()) {
+                        (true, true) => 0,
+                        (true, false) => -1,
+                        (false, true) => 1,
+                        (false, false) => self.compare_non_null(a, b)?,
+                    };
+                    if ordering != 0 {
+                        return Ok(ordering);
+                    }
+                }
+                Ok(a.len().cmp(&b.len()) as i32)
+            }
+            _ => Err(DatabaseError::type_mismatch(
                 format!("无法比较列 '{}' 的值", self.column)
             )),
         }
     }
 
-    fn evaluate_like(&self, row_value: Option<&Value>) -> bool {
-        match (row_value, &self.value) {
+    fn evaluate_prefix(&self, row_value: Option<&Value>, value: &Value) -> Result<Option<bool>> {
+        match (row_value, value) {
+            (None | Some(Value::Null), _) | (_, Value::Null) => Ok(None),
+            (Some(Value::Binary(bytes)), Value::Binary(prefix)) => Ok(Some(bytes.starts_with(prefix))),
+            (Some(Value::Text(text)), Value::Text(prefix)) if self.collation.like_ignores_case() => {
+                Ok(Some(text.to_lowercase().starts_with(&prefix.to_lowercase())))
+            }
+            (Some(Value::Text(text)), Value::Text(prefix)) => Ok(Some(text.starts_with(prefix.as_str()))),
+            _ => Err(DatabaseError::type_mismatch(
+                format!("列 '{}' 的 STARTS WITH 需要相同类型的文本或二进制前缀", self.column)
+            )),
+        }
+    }
+
+    fn evaluate_like(&self, row_value: Option<&Value>, value: &Value) -> Option<bool> {
+        match (row_value, value) {
+            (None | Some(Value::Null), _) | (_, Value::Null) => None,
             (Some(Value::Text(row_text)), Value::Text(pattern_text)) => {
                 let pattern = pattern_text.replace("%", ".*").replace("_", ".");
-                let regex = match regex::Regex::new(&format!("^{}$", pattern)) {
+                let flags = if self.collation.like_ignores_case() { "(?i)" } else { "" };
+                let regex = match regex::Regex::new(&format!("{}^{}$", flags, pattern)) {
                     Ok(re) => re,
-                    Err(_) => return false,
+                    Err(_) => return Some(false),
                 };
-                regex.is_match(row_text)
+                Some(regex.is_match(row_text))
             }
-            _ => false,
+            _ => Some(false),
         }
     }
 
-    fn evaluate_in(&self, row_value: Option<&Value>) -> bool {
-        if let Value::Json(json_value) = &self.value {
-            if let Some(array) = json_value.as_array() {
-                if let Some(row_val) = row_value {
-                    return array.iter().any(|item| {
-                        let item_value = Value::from(item.clone());
-                        self.compare_values(Some(row_val), &item_value).unwrap_or(0) == 0
-                    });
-                }
+    /// `x IN (a, b)` 等价于 `x = a OR x = b`：有相等项为真，否则列表含 NULL 时为 UNKNOWN
+    fn evaluate_in(&self, row_value: Option<&Value>, value: &Value) -> Option<bool> {
+        let row_val = row_value.filter(|v| !v.is_null())?;
+        let Value::Json(serde_json::Value::Array(array)) = value else {
+            return Some(false);
+        };
+        let mut unknown = false;
+        for item in array {
+            // 列表项按行值的类型解析，无法转换的项视为不相等
+            let item_value = Value::from_json(item, Some(&row_val.get_type()))
+                .or_else(|_| Value::from_json(item, None));
+            match item_value.map(|item| self.compare_values(Some(row_val), &item)) {
+                Ok(Ok(Some(0))) => return Some(true),
+                Ok(Ok(None)) => unknown = true,
+                _ => {}
             }
         }
-        false
+        if unknown { None } else { Some(false) }
     }
 }
 
@@ -127,6 +511,9 @@ impl Condition {
 pub struct OrderBy {
     pub column: String,
     pub ascending: bool,
+    /// 文本排序规则，执行时取列定义的规则，除非显式指定
+    #[serde(default)]
+    pub collation: Collation,
 }
 
 impl OrderBy {
@@ -134,8 +521,14 @@ impl OrderBy {
         Self {
             column: column.into(),
             ascending,
+            collation: Collation::Binary,
         }
     }
+
+    pub fn with_collation(mut self, collation: Collation) -> Self {
+        self.collation = collation;
+        self
+    }
 }
 
 /// 查询类型
@@ -158,6 +551,53 @@ pub struct Query {
     pub limit: Option<usize>,
     pub offset: Option<usize>,
     pub data: Option<HashMap<String, Value>>,
+    /// 从 JSON 列中提取并附加到结果行的值
+    #[serde(default)]
+    pub json_extracts: Vec<JsonExtract>,
+    /// 计算列，表达式的值附加到结果行
+    #[serde(default)]
+    pub projections: Vec<Projection>,
+    /// 键集分页的游标，取自上一页结果的 `next_cursor`，从该位置之后继续
+    #[serde(default)]
+    pub after: Option<String>,
+    /// 试运行：UPDATE/DELETE 只求出会影响的行，不修改数据也不写日志
+    #[serde(default)]
+    pub dry_run: bool,
+    /// SELECT 同时求出满足条件的总行数，不受 LIMIT、OFFSET 与游标影响，见 `QueryResult::total_matching`
+    #[serde(default)]
+    pub with_total: bool,
+    /// 多行 INSERT 的各行数据，非空时代替 `data`，整体作为一次批量插入执行
+    #[serde(default)]
+    pub rows: Vec<HashMap<String, Value>>,
+}
+
+/// 把 `column` 中 `path` 处的值以 `alias` 列名附加到结果行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonExtract {
+    pub alias: String,
+    pub column: String,
+    pub path: JsonPath,
+}
+
+impl JsonExtract {
+    pub fn new<A: Into<String>, C: Into<String>>(alias: A, column: C, path: JsonPath) -> Self {
+        Self {
+            alias: alias.into(),
+            column: column.into(),
+            path,
+        }
+    }
+
+    /// 从行中提取值，列不是 JSON 或路径不存在时为 NULL
+    pub fn extract(&self, row: &Row) -> Result<Value> {
+        match row.resolve(&self.column).as_deref() {
+            Some(Value::Json(json)) => match self.path.select(json)? {
+                Some(selected) => Value::from_json(selected, None),
+                None => Ok(Value::Null),
+            },
+            _ => Ok(Value::Null),
+        }
+    }
 }
 
 impl Query {
@@ -170,6 +610,12 @@ impl Query {
             limit: None,
             offset: None,
             data: None,
+            json_extracts: Vec::new(),
+            projections: Vec::new(),
+            after: None,
+            dry_run: false,
+            with_total: false,
+            rows: Vec::new(),
         }
     }
 
@@ -182,6 +628,30 @@ impl Query {
             limit: None,
             offset: None,
             data: Some(data),
+            json_extracts: Vec::new(),
+            projections: Vec::new(),
+            after: None,
+            dry_run: false,
+            with_total: false,
+            rows: Vec::new(),
+        }
+    }
+
+    /// 插入多行，执行时作为一次批量插入，只写一条日志；任意一行失败时整批都不会写入
+    pub fn insert_many<S: Into<String>>(table_name: S, rows: Vec<HashMap<String, Value>>) -> Self {
+        Self {
+            data: None,
+            rows,
+            ..Self::insert(table_name, HashMap::new())
+        }
+    }
+
+    /// INSERT 要插入的各行：多行插入的 `rows`，或单行插入的 `data`
+    pub fn insert_rows(&self) -> Vec<&HashMap<String, Value>> {
+        if self.rows.is_empty() {
+            self.data.iter().collect()
+        } else {
+            self.rows.iter().collect()
         }
     }
 
@@ -194,6 +664,12 @@ impl Query {
             limit: None,
             offset: None,
             data: Some(data),
+            json_extracts: Vec::new(),
+            projections: Vec::new(),
+            after: None,
+            dry_run: false,
+            with_total: false,
+            rows: Vec::new(),
         }
     }
 
@@ -206,6 +682,12 @@ impl Query {
             limit: None,
             offset: None,
             data: None,
+            json_extracts: Vec::new(),
+            projections: Vec::new(),
+            after: None,
+            dry_run: false,
+            with_total: false,
+            rows: Vec::new(),
         }
     }
 
@@ -218,6 +700,12 @@ impl Query {
             limit: None,
             offset: None,
             data: None,
+            json_extracts: Vec::new(),
+            projections: Vec::new(),
+            after: None,
+            dry_run: false,
+            with_total: false,
+            rows: Vec::new(),
         }
     }
 
@@ -240,6 +728,158 @@ impl Query {
         self.offset = Some(offset);
         self
     }
+
+    /// 改为试运行，结果给出会被更新或删除的行而不实际修改
+    pub fn dry_run(mut self) -> Self {
+        self.dry_run = true;
+        self
+    }
+
+    pub fn with_total(mut self) -> Self {
+        self.with_total = true;
+        self
+    }
+
+    /// 绑定参数，返回所有占位符都已替换为类型化值的查询
+    pub fn bind(mut self, params: &Params) -> Result<Self> {
+        for condition in &mut self.conditions {
+            if let Some(placeholder) = condition.param.take() {
+                condition.value = params.get(&placeholder)
+                    .cloned()
+                    .ok_or_else(|| DatabaseError::parse_error(format!("缺少参数 {}", placeholder)))?;
+            }
+        }
+        Ok(self)
+    }
+
+    /// 为未显式指定排序规则的条件与排序键填入列定义的排序规则
+    pub fn resolve_collations(&mut self, schema: &Schema) {
+        for condition in self.conditions.iter_mut().filter(|c| c.collation == Collation::Binary) {
+            condition.collation = schema.collation(&condition.column);
+        }
+        for order in self.order_by.iter_mut().filter(|o| o.collation == Collation::Binary) {
+            order.collation = schema.collation(&order.column);
+        }
+    }
+
+    /// 检查查询中的 JSON 路径是否都能解析
+    pub fn validate_json_paths(&self) -> Result<()> {
+        let condition_paths = self.conditions.iter().filter_map(|condition| condition.path.as_ref());
+        let extract_paths = self.json_extracts.iter().map(|extract| &extract.path);
+        for path in condition_paths.chain(extract_paths) {
+            path.segments()?;
+        }
+        Ok(())
+    }
+
+    /// 检查条件、排序、JSON 提取与计算列引用的列都在表结构中
+    pub fn validate_columns(&self, schema: &Schema) -> Result<()> {
+        let condition_columns = self.conditions.iter().flat_map(Condition::columns);
+        let order_columns = self.order_by.iter().map(|order| order.column.as_str());
+        let extract_columns = self.json_extracts.iter().map(|extract| extract.column.as_str());
+        let projection_columns = self.projections.iter().flat_map(|projection| projection.expr.columns());
+        match condition_columns.chain(order_columns).chain(extract_columns).chain(projection_columns)
+            .find(|column| !schema.has_column(column))
+        {
+            Some(column) => Err(DatabaseError::column_not_found(column)),
+            None => Ok(()),
+        }
+    }
+
+    /// 检查条件与计算列中的函数调用
+    pub fn validate_expressions(&self) -> Result<()> {
+        let condition_exprs = self.conditions.iter()
+            .flat_map(|condition| condition.expr.iter().chain(condition.right.iter()));
+        let projection_exprs = self.projections.iter().map(|projection| &projection.expr);
+        for expr in condition_exprs.chain(projection_exprs) {
+            expr.validate()?;
+        }
+        Ok(())
+    }
+
+    /// 条件或计算列是否调用了 `NEXTVAL`、`CURRVAL`
+    pub fn uses_sequences(&self) -> bool {
+        self.conditions.iter()
+            .flat_map(|condition| condition.expr.iter().chain(condition.right.iter()))
+            .chain(self.projections.iter().map(|projection| &projection.expr))
+            .any(Expr::uses_sequences)
+    }
+
+    /// 代入条件与计算列中的序列值，见 `Expr::bind_sequences`；比较的右侧代入后成为常量时改为与值比较
+    pub fn bind_sequences<F>(&mut self, resolve: &mut F) -> Result<()>
+    where
+        F: FnMut(&str, &str) -> Result<i64>,
+    {
+        for condition in &mut self.conditions {
+            if let Some(expr) = condition.expr.as_mut().filter(|expr| expr.uses_sequences()) {
+                *expr = expr.bind_sequences(resolve)?;
+            }
+            if let Some(right) = condition.right.take_if(|right| right.uses_sequences()) {
+                let right = right.bind_sequences(resolve)?;
+                if right.is_constant() {
+                    condition.value = right.evaluate(&Row::new())?;
+                } else {
+                    condition.right = Some(right);
+                }
+            }
+        }
+        for projection in &mut self.projections {
+            if projection.expr.uses_sequences() {
+                projection.expr = projection.expr.bind_sequences(resolve)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 查询中第一个未绑定的参数
+    pub fn unbound_param(&self) -> Option<&Placeholder> {
+        self.conditions.iter().find_map(|condition| condition.param.as_ref())
+    }
+
+    /// 查询的结构：字面量替换为 `?`，只有取值不同的查询结构相同，用于慢查询日志归类
+    pub fn shape(&self) -> String {
+        let table = sql::quote_identifier(&self.table_name);
+        let mut columns: Vec<&String> = self.data.iter().chain(self.rows.first()).flat_map(|data| data.keys()).collect();
+        columns.sort();
+        let mut shape = match self.query_type {
+            QueryType::Select => format!("SELECT * FROM {}", table),
+            QueryType::Count => format!("SELECT COUNT(*) FROM {}", table),
+            QueryType::Insert => format!(
+                "INSERT INTO {} ({}) VALUES ({}){}",
+                table,
+                columns.iter().map(|column| sql::quote_identifier(column)).collect::<Vec<_>>().join(", "),
+                vec!["?"; columns.len()].join(", "),
+                if self.rows.len() > 1 { ", ..." } else { "" },
+            ),
+            QueryType::Update => format!(
+                "UPDATE {} SET {}",
+                table,
+                columns.iter().map(|column| format!("{} = ?", sql::quote_identifier(column))).collect::<Vec<_>>().join(", "),
+            ),
+            QueryType::Delete => format!("DELETE FROM {}", table),
+        };
+
+        if !self.conditions.is_empty() {
+            let conditions: Vec<String> = self.conditions.iter().map(Condition::shape).collect();
+            shape.push_str(&format!(" WHERE {}", conditions.join(" AND ")));
+        }
+        if !self.order_by.is_empty() {
+            let order_by: Vec<String> = self.order_by.iter()
+                .map(|order| format!("{}{}", sql::quote_identifier(&order.column), if order.ascending { "" } else { " DESC" }))
+                .collect();
+            shape.push_str(&format!(" ORDER BY {}", order_by.join(", ")));
+        }
+        if self.limit.is_some() {
+            shape.push_str(" LIMIT ?");
+        }
+        if self.offset.is_some() {
+            shape.push_str(" OFFSET ?");
+        }
+        if self.dry_run {
+            shape.push_str(" DRY RUN");
+        }
+        shape
+    }
 }
 
 /// 查询结果
@@ -251,6 +891,27 @@ pub struct QueryResult {
     pub affected_rows: usize,
     pub execution_time_ms: u64,
     pub count: Option<usize>,
+    /// 结果行所属表的结构，SELECT 时填充
+    #[serde(default)]
+    pub schema: Option<Schema>,
+    /// 执行时读取的行数，分区裁剪与索引筛选掉的行不计入
+    #[serde(default)]
+    pub rows_scanned: usize,
+    /// 带 ORDER BY 与 LIMIT 的查询还有下一页时，指向本页最后一行的游标
+    #[serde(default)]
+    pub next_cursor: Option<String>,
+    /// 结果列的名称与类型，SELECT 时填充，结果为空时同样可用
+    #[serde(default)]
+    pub columns: Vec<(String, DataType)>,
+    /// 写操作影响的行 ID，由引擎执行 INSERT、UPDATE、DELETE 时填充
+    #[serde(default)]
+    pub affected_ids: Vec<Uuid>,
+    /// 试运行的结果：`rows` 与 `affected_ids` 是会被修改的行，数据并未改变
+    #[serde(default)]
+    pub dry_run: bool,
+    /// 查询设置了 `with_total` 时为满足条件的总行数，分页前计数
+    #[serde(default)]
+    pub total_matching: Option<usize>,
 }
 
 impl QueryResult {
@@ -262,6 +923,13 @@ impl QueryResult {
             affected_rows: 0,
             execution_time_ms,
             count: None,
+            schema: None,
+            rows_scanned: 0,
+            next_cursor: None,
+            columns: Vec::new(),
+            affected_ids: Vec::new(),
+            dry_run: false,
+            total_matching: None,
         }
     }
 
@@ -282,6 +950,38 @@ impl QueryResult {
         self.affected_rows = affected_rows;
         self
     }
+
+    pub fn with_schema(mut self, schema: Schema) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    pub fn with_rows_scanned(mut self, rows_scanned: usize) -> Self {
+        self.rows_scanned = rows_scanned;
+        self
+    }
+
+    pub fn with_next_cursor(mut self, next_cursor: Option<String>) -> Self {
+        self.next_cursor = next_cursor;
+        self
+    }
+
+    pub fn with_columns(mut self, columns: Vec<(String, DataType)>) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    pub fn with_total_matching(mut self, total: Option<usize>) -> Self {
+        self.total_matching = total;
+        self
+    }
+
+    /// 设置影响的行 ID，影响行数随之更新
+    pub fn with_affected_ids(mut self, affected_ids: Vec<Uuid>) -> Self {
+        self.affected_rows = affected_ids.len();
+        self.affected_ids = affected_ids;
+        self
+    }
 }
 
 /// 查询引擎
@@ -292,8 +992,10 @@ impl QueryEngine {
         Self
     }
 
-    pub async fn execute(&self, table: Table, query: Query) -> Result<QueryResult> {
-        let start_time = std::time::Instant::now();
+    pub async fn execute(&self, table: Table, mut query: Query) -> Result<QueryResult> {
+        prepare(&table, &mut query)?;
+
+        let start_time = crate::clock::Instant::now();
 
         let result = match query.query_type {
             QueryType::Select => self.execute_select(&table, &query).await,
@@ -310,8 +1012,166 @@ impl QueryEngine {
         })
     }
 
+    /// 对满足查询条件的行计算数值列的聚合值，忽略 NULL
+    ///
+    /// 列式表按批求值条件并直接累加列数据，不物化行；其他情况逐行求值。
+    pub async fn aggregate(&self, table: Table, mut query: Query, aggregate: Aggregate, column: &str) -> Result<Value> {
+        prepare(&table, &mut query)?;
+        if !table.schema.has_column(column) {
+            return Err(DatabaseError::column_not_found(column));
+        }
+
+        if table.layout == TableLayout::Columnar && !table.is_partitioned() {
+            let result = vectorized::filter(&table.columns, &query.conditions)
+                .and_then(|selection| vectorized::aggregate(&table.columns, column, &selection, aggregate));
+            if let Some(result) = result {
+                return result;
+            }
+        }
+
+        let mut totals = Totals::default();
+        for row in self.scan(&table, &query.conditions)?.iter() {
+            if query.conditions.iter().all(|condition| condition.evaluate(row).unwrap_or(false)) {
+                totals.add(row.get(column).unwrap_or(&Value::Null))?;
+            }
+        }
+        totals.finish(aggregate)
+    }
+
+    /// 读取可能满足条件的行；分区表只扫描未被裁剪的分区，数组条件先用元素索引筛选，
+    /// 列式表按批求值条件后只物化满足条件的行
+    fn scan<'a>(&self, table: &'a Table, conditions: &[Condition]) -> Result<Cow<'a, [Row]>> {
+        let Some(spec) = &table.partition_spec else {
+            if table.layout == TableLayout::Columnar && !conditions.is_empty() {
+                if let Some(selection) = vectorized::filter(&table.columns, conditions) {
+                    return Ok(Cow::Owned(selected_rows(&table.columns, &selection)));
+                }
+            }
+            return match self.index_candidates(table, conditions)? {
+                Some(positions) => Ok(Cow::Owned(table.rows_at(&positions)?)),
+                None => table.all_rows(),
+            };
+        };
+
+        let mut rows = Vec::new();
+        for (partition, keep) in table.partitions.iter().zip(spec.prune(conditions)) {
+            if keep {
+                rows.extend_from_slice(&partition.all_rows()?);
+            }
+        }
+        Ok(Cow::Owned(rows))
+    }
+
+    /// 用元素索引、网格索引与区域映射求出可能满足条件的行位置；没有可用的条件时返回 None
+    fn index_candidates(&self, table: &Table, conditions: &[Condition]) -> Result<Option<Vec<usize>>> {
+        let mut candidates: Option<Vec<usize>> = None;
+        let ranges = conditions.iter().filter(|condition| matches!(
+            condition.operator,
+            ComparisonOperator::Equal | ComparisonOperator::GreaterThan | ComparisonOperator::GreaterThanOrEqual
+                | ComparisonOperator::LessThan | ComparisonOperator::LessThanOrEqual
+        ) && condition.is_single_column());
+        for condition in ranges {
+            let Some(map) = table.zone_map(&condition.column)? else { continue };
+            let Some(positions) = map.candidates(condition) else { continue };
+            candidates = Some(match candidates {
+                Some(previous) => previous.into_iter().filter(|position| positions.binary_search(position).is_ok()).collect(),
+                None => positions,
+            });
+        }
+
+        let indexable = conditions.iter().filter(|condition| {
+            condition.path.is_none()
+                && condition.param.is_none()
+                && condition.is_single_column()
+                && matches!(condition.operator, ComparisonOperator::Contains | ComparisonOperator::Overlaps | ComparisonOperator::WithinRadius)
+        });
+        for condition in indexable {
+            if condition.operator == ComparisonOperator::WithinRadius {
+                let Some((center, meters)) = condition.radius() else { continue };
+                let Some(index) = table.geo_index(&condition.column)? else { continue };
+                let Some(positions) = index.candidates(center, meters) else { continue };
+                candidates = Some(match candidates {
+                    Some(previous) => previous.into_iter().filter(|position| positions.binary_search(position).is_ok()).collect(),
+                    None => positions,
+                });
+                continue;
+            }
+            let keys = condition.element_keys();
+            // 空数组被所有数组包含，索引无法缩小范围
+            if keys.is_empty() && condition.operator == ComparisonOperator::Contains {
+                continue;
+            }
+            let Some(index) = table.element_index(&condition.column)? else { continue };
+            if index.collation() != condition.collation {
+                continue;
+            }
+            let positions = match condition.operator {
+                ComparisonOperator::Contains => index.rows_containing_all(&keys),
+                _ => index.rows_containing_any(&keys),
+            };
+            candidates = Some(match candidates {
+                Some(previous) => previous.into_iter().filter(|position| positions.binary_search(position).is_ok()).collect(),
+                None => positions,
+            });
+        }
+        Ok(candidates)
+    }
+
     async fn execute_select(&self, table: &Table, query: &Query) -> Result<QueryResult> {
-        let mut filtered_rows = table.rows.clone();
+        if query.after.is_some() && (query.order_by.is_empty() || query.limit.is_none()) {
+            return Err(DatabaseError::parse_error("游标分页需要 ORDER BY 与 LIMIT"));
+        }
+
+        // 同时有 ORDER BY 和 LIMIT 时只需保留前 offset + limit 行
+        if let (false, Some(limit)) = (query.order_by.is_empty(), query.limit) {
+            let offset = query.offset.unwrap_or(0);
+            let after = query.after.as_deref().map(|cursor| Cursor::decode(cursor, &query.order_by)).transpose()?;
+            let rows = self.scan(table, &query.conditions)?;
+            let rows_scanned = rows.len();
+            // 排序键相等的行按扫描顺序排列，游标所在行之后的才属于后续页
+            let after = after.map(|cursor| {
+                let position = rows.iter().position(|row| row.id == cursor.id);
+                (cursor.row(), position)
+            });
+            let mut total = 0;
+            let matching = rows.iter().enumerate()
+                .filter(|(_, row)| query.conditions.iter().all(|condition| condition.evaluate(row).unwrap_or(false)))
+                .inspect(|_| total += 1)
+                .filter(|(index, row)| {
+                    after.as_ref().is_none_or(|(cursor, position)| match compare_rows(row, cursor, &query.order_by) {
+                        Ordering::Greater => true,
+                        Ordering::Equal => position.is_some_and(|position| *index > position),
+                        Ordering::Less => false,
+                    })
+                })
+                .map(|(_, row)| row);
+            // 多取一行判断是否还有下一页
+            let end = offset.saturating_add(limit);
+            let mut rows = self.top_n(matching, &query.order_by, end.saturating_add(1));
+            let has_more = rows.len() > end;
+            rows.truncate(end);
+            let mut rows: Vec<Row> = rows.into_iter().skip(offset).collect();
+            let next_cursor = rows.last()
+                .filter(|_| has_more)
+                .map(|row| Cursor::at(row, &query.order_by).encode());
+            apply_json_extracts(&mut rows, &query.json_extracts)?;
+            apply_projections(&mut rows, &query.projections)?;
+            let columns = column_metadata(&table.schema, query, &rows);
+
+            return Ok(QueryResult::new(
+                QueryType::Select,
+                table.name.clone(),
+                0,
+            ).with_rows(rows)
+                .with_columns(columns)
+                .with_schema(table.schema.clone())
+                .with_rows_scanned(rows_scanned)
+                .with_next_cursor(next_cursor)
+                .with_total_matching(query.with_total.then_some(total)));
+        }
+
+        let mut filtered_rows = self.scan(table, &query.conditions)?.into_owned();
+        let rows_scanned = filtered_rows.len();
 
         // 应用过滤条件
         if !query.conditions.is_empty() {
@@ -322,6 +1182,8 @@ impl QueryEngine {
             });
         }
 
+        let total = query.with_total.then_some(filtered_rows.len());
+
         // 排序
         if !query.order_by.is_empty() {
             self.sort_rows(&mut filtered_rows, &query.order_by);
@@ -335,33 +1197,36 @@ impl QueryEngine {
             filtered_rows.len()
         };
 
-        let paginated_rows = if start < filtered_rows.len() {
+        let mut paginated_rows = if start < filtered_rows.len() {
             filtered_rows[start..end.min(filtered_rows.len())].to_vec()
         } else {
             Vec::new()
         };
+        apply_json_extracts(&mut paginated_rows, &query.json_extracts)?;
+        apply_projections(&mut paginated_rows, &query.projections)?;
+        let columns = column_metadata(&table.schema, query, &paginated_rows);
 
         Ok(QueryResult::new(
             QueryType::Select,
             table.name.clone(),
             0,
-        ).with_rows(paginated_rows))
+        ).with_rows(paginated_rows)
+            .with_columns(columns)
+            .with_schema(table.schema.clone())
+            .with_rows_scanned(rows_scanned)
+            .with_total_matching(total))
     }
 
     async fn execute_insert(&self, table: &Table, query: &Query) -> Result<QueryResult> {
-        if let Some(data) = &query.data {
-            let mut row = Row::new();
-            for (column, value) in data {
-                row.set(column.clone(), value.clone());
-            }
-
+        let rows = query.insert_rows();
+        if !rows.is_empty() {
             // 在实际实现中，这里需要修改表数据
             // 由于表是不可变引用，我们返回操作信息
             Ok(QueryResult::new(
                 QueryType::Insert,
                 table.name.clone(),
                 0,
-            ).with_affected_rows(1))
+            ).with_affected_rows(rows.len()))
         } else {
             Err(DatabaseError::Other("INSERT 查询缺少数据".to_string()))
         }
@@ -369,9 +1234,10 @@ impl QueryEngine {
 
     async fn execute_update(&self, table: &Table, query: &Query) -> Result<QueryResult> {
         let mut affected_count = 0;
+        let rows = self.scan(table, &query.conditions)?;
 
         // 找到符合条件的行
-        for row in &table.rows {
+        for row in rows.iter() {
             let matches = query.conditions.iter().all(|condition| {
                 condition.evaluate(row).unwrap_or(false)
             });
@@ -380,19 +1246,24 @@ impl QueryEngine {
                 affected_count += 1;
             }
         }
+        // 有 LIMIT 时最多修改 limit 行
+        if let Some(limit) = query.limit {
+            affected_count = affected_count.min(limit);
+        }
 
         Ok(QueryResult::new(
             QueryType::Update,
             table.name.clone(),
             0,
-        ).with_affected_rows(affected_count))
+        ).with_affected_rows(affected_count).with_rows_scanned(rows.len()))
     }
 
     async fn execute_delete(&self, table: &Table, query: &Query) -> Result<QueryResult> {
         let mut affected_count = 0;
+        let rows = self.scan(table, &query.conditions)?;
 
         // 计算符合条件的行数
-        for row in &table.rows {
+        for row in rows.iter() {
             let matches = query.conditions.iter().all(|condition| {
                 condition.evaluate(row).unwrap_or(false)
             });
@@ -401,69 +1272,289 @@ impl QueryEngine {
                 affected_count += 1;
             }
         }
+        // 有 LIMIT 时最多修改 limit 行
+        if let Some(limit) = query.limit {
+            affected_count = affected_count.min(limit);
+        }
 
         Ok(QueryResult::new(
             QueryType::Delete,
             table.name.clone(),
             0,
-        ).with_affected_rows(affected_count))
+        ).with_affected_rows(affected_count).with_rows_scanned(rows.len()))
     }
 
     async fn execute_count(&self, table: &Table, query: &Query) -> Result<QueryResult> {
-        let mut count = 0;
-
-        for row in &table.rows {
-            let matches = query.conditions.iter().all(|condition| {
-                condition.evaluate(row).unwrap_or(false)
-            });
-
-            if matches {
-                count += 1;
+        let (count, rows_scanned) = match table.layout {
+            // 列式布局按列扫描条件，不需要物化整行；能按批求值时不逐个构造值
+            TableLayout::Columnar if !table.is_partitioned() && query.conditions.iter().all(Condition::is_single_column) => {
+                let selected = vectorized::filter(&table.columns, &query.conditions).unwrap_or_else(|| {
+                    let mut selected = vec![true; table.row_count()];
+                    for condition in &query.conditions {
+                        for (index, keep) in selected.iter_mut().enumerate().filter(|(_, keep)| **keep) {
+                            let value = table.columns.value(index, &condition.column);
+                            *keep = condition.evaluate_value(value.as_ref()).unwrap_or(false);
+                        }
+                    }
+                    selected
+                });
+                (selected.into_iter().filter(|keep| *keep).count(), table.row_count())
             }
-        }
+            _ => {
+                let rows = self.scan(table, &query.conditions)?;
+                let count = rows.iter()
+                    .filter(|row| query.conditions.iter().all(|condition| {
+                        condition.evaluate(row).unwrap_or(false)
+                    }))
+                    .count();
+                (count, rows.len())
+            }
+        };
 
         Ok(QueryResult::new(
             QueryType::Count,
             table.name.clone(),
             0,
-        ).with_count(count))
+        ).with_count(count).with_rows_scanned(rows_scanned))
     }
 
-    fn sort_rows(&self, rows: &mut Vec<Row>, order_by: &[OrderBy]) {
-        rows.sort_by(|a, b| {
-            for order in order_by {
-                let a_val = a.get(&order.column);
-                let b_val = b.get(&order.column);
+    fn sort_rows(&self, rows: &mut [Row], order_by: &[OrderBy]) {
+        rows.sort_by(|a, b| compare_rows(a, b, order_by));
+    }
 
-                let comparison = match (a_val, b_val) {
-                    (Some(Value::Integer(a)), Some(Value::Integer(b))) => a.cmp(b),
-                    (Some(Value::Text(a)), Some(Value::Text(b))) => a.cmp(b),
-                    (Some(Value::Boolean(a)), Some(Value::Boolean(b))) => a.cmp(b),
-                    (Some(Value::Float(a)), Some(Value::Float(b))) => {
-                        a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal)
-                    }
-                    (Some(Value::Date(a)), Some(Value::Date(b))) => a.cmp(b),
-                    (Some(Value::Time(a)), Some(Value::Time(b))) => a.cmp(b),
-                    (Some(Value::DateTime(a)), Some(Value::DateTime(b))) => a.cmp(b),
-                    (None, None) => std::cmp::Ordering::Equal,
-                    (None, Some(_)) => std::cmp::Ordering::Less,
-                    (Some(_), None) => std::cmp::Ordering::Greater,
-                    _ => std::cmp::Ordering::Equal,
-                };
+    /// 取排序后的前 `k` 行，使用容量为 `k` 的最大堆，复杂度 O(n log k)
+    ///
+    /// 相等的行按原始顺序排列，结果与完整稳定排序后截取前 `k` 行一致。
+    fn top_n<'a>(&self, rows: impl Iterator<Item = &'a Row>, order_by: &'a [OrderBy], k: usize) -> Vec<Row> {
+        if k == 0 {
+            return Vec::new();
+        }
 
-                if comparison != std::cmp::Ordering::Equal {
-                    return if order.ascending {
-                        comparison
-                    } else {
-                        comparison.reverse()
-                    };
-                }
+        let mut heap = BinaryHeap::with_capacity(k + 1);
+        for (index, row) in rows.enumerate() {
+            let entry = TopNEntry { row, index, order_by };
+            if heap.len() < k {
+                heap.push(entry);
+            } else if heap.peek().is_some_and(|max| entry < *max) {
+                heap.pop();
+                heap.push(entry);
             }
-            std::cmp::Ordering::Equal
-        });
+        }
+
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|entry| entry.row.clone())
+            .collect()
+    }
+}
+
+/// 执行前的准备：检查参数已绑定，填入排序规则并校验列、JSON 路径与函数调用
+fn prepare(table: &Table, query: &mut Query) -> Result<()> {
+    if let Some(placeholder) = query.unbound_param() {
+        return Err(DatabaseError::parse_error(format!("参数 {} 未绑定", placeholder)));
+    }
+    // 设置了存活时间的表只读取未过期的行
+    if let Some(ttl) = table.schema.settings.ttl {
+        query.conditions.push(Condition::new(UPDATED_AT_COLUMN, ComparisonOperator::GreaterThanOrEqual, expiry_cutoff(ttl)));
+    }
+    query.resolve_collations(&table.schema);
+    query.validate_columns(&table.schema)?;
+    query.validate_json_paths()?;
+    query.validate_expressions()
+}
+
+/// 存活时间为 `ttl` 时的过期界限，最后修改早于它的行已过期
+pub(crate) fn expiry_cutoff(ttl: std::time::Duration) -> Value {
+    let now = clock::now();
+    let cutoff = chrono::TimeDelta::from_std(ttl).ok()
+        .and_then(|ttl| now.checked_sub_signed(ttl))
+        .unwrap_or(chrono::DateTime::<chrono::Utc>::MIN_UTC);
+    Value::DateTime(cutoff.naive_utc())
+}
+
+/// 物化选择向量中为 true 的行
+fn selected_rows(store: &ColumnStore, selection: &[bool]) -> Vec<Row> {
+    selection.iter().enumerate()
+        .filter(|(_, keep)| **keep)
+        .map(|(index, _)| store.row(index))
+        .collect()
+}
+
+/// 结果列的名称与类型：先是表结构中的列，再按声明顺序排列 JSON 提取与计算列
+///
+/// 计算出的列取第一个非 NULL 值的类型；没有这样的值时，直接引用列或字面量的表达式取其类型，其余按 TEXT 处理。
+fn column_metadata(schema: &Schema, query: &Query, rows: &[Row]) -> Vec<(String, DataType)> {
+    let mut columns: Vec<(String, DataType)> = schema.columns.iter()
+        .map(|column| (column.name.clone(), column.data_type.clone()))
+        .collect();
+    let computed = query.json_extracts.iter()
+        .map(|extract| (&extract.alias, None))
+        .chain(query.projections.iter().map(|projection| (&projection.alias, Some(&projection.expr))));
+    for (alias, expr) in computed {
+        let data_type = rows.iter()
+            .filter_map(|row| row.get(alias))
+            .find(|value| !value.is_null())
+            .map(Value::get_type)
+            .or_else(|| match expr? {
+                Expr::Column(name) => match name.as_str() {
+                    ID_COLUMN => Some(DataType::Uuid),
+                    CREATED_AT_COLUMN | UPDATED_AT_COLUMN => Some(DataType::DateTime),
+                    VERSION_COLUMN => Some(DataType::Integer),
+                    name => schema.get_column(name).map(|column| column.data_type.clone()),
+                },
+                Expr::Literal(value) if !value.is_null() => Some(value.get_type()),
+                _ => None,
+            })
+            .unwrap_or(DataType::Text);
+        match columns.iter_mut().find(|(name, _)| name == alias) {
+            Some(column) => column.1 = data_type,
+            None => columns.push((alias.clone(), data_type)),
+        }
+    }
+    columns
+}
+
+/// 把 JSON 提取结果写入结果行
+fn apply_json_extracts(rows: &mut [Row], extracts: &[JsonExtract]) -> Result<()> {
+    for row in rows.iter_mut() {
+        for extract in extracts {
+            let value = extract.extract(row)?;
+            row.set(extract.alias.clone(), value);
+        }
+    }
+    Ok(())
+}
+
+/// 计算表达式并写入结果行；表达式只看到原始列，看不到同一查询的其他计算列
+fn apply_projections(rows: &mut [Row], projections: &[Projection]) -> Result<()> {
+    for row in rows.iter_mut() {
+        let values = projections.iter()
+            .map(|projection| projection.expr.evaluate(row))
+            .collect::<Result<Vec<_>>>()?;
+        for (projection, value) in projections.iter().zip(values) {
+            row.set(projection.alias.clone(), value);
+        }
+    }
+    Ok(())
+}
+
+/// 按 ORDER BY 子句比较两行
+pub(crate) fn compare_rows(a: &Row, b: &Row, order_by: &[OrderBy]) -> Ordering {
+    for order in order_by {
+        let a_val = a.resolve(&order.column);
+        let b_val = b.resolve(&order.column);
+
+        let comparison = match (a_val.as_deref(), b_val.as_deref()) {
+            (Some(Value::Integer(a)), Some(Value::Integer(b))) => a.cmp(b),
+            (Some(Value::Text(a)), Some(Value::Text(b))) => order.collation.compare(a, b),
+            (Some(Value::Boolean(a)), Some(Value::Boolean(b))) => a.cmp(b),
+            (Some(Value::Float(a)), Some(Value::Float(b))) => {
+                a.partial_cmp(b).unwrap_or(Ordering::Equal)
+            }
+            (Some(Value::Date(a)), Some(Value::Date(b))) => a.cmp(b),
+            (Some(Value::Time(a)), Some(Value::Time(b))) => a.cmp(b),
+            (Some(Value::DateTime(a)), Some(Value::DateTime(b))) => a.cmp(b),
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => Ordering::Less,
+            (Some(_), None) => Ordering::Greater,
+            _ => Ordering::Equal,
+        };
+
+        if comparison != Ordering::Equal {
+            return if order.ascending {
+                comparison
+            } else {
+                comparison.reverse()
+            };
+        }
     }
+    Ordering::Equal
 }
 
+/// 键集分页的位置：上一页最后一行的排序键与行 ID，编码为不透明的十六进制字符串
+#[derive(Debug, Serialize, Deserialize)]
+struct Cursor {
+    keys: Vec<(String, Option<Value>)>,
+    id: Uuid,
+}
+
+impl Cursor {
+    fn at(row: &Row, order_by: &[OrderBy]) -> Self {
+        Self {
+            keys: order_by.iter()
+                .map(|order| (order.column.clone(), row.resolve(&order.column).map(Cow::into_owned)))
+                .collect(),
+            id: row.id,
+        }
+    }
+
+    fn encode(&self) -> String {
+        serde_json::to_vec(self)
+            .unwrap_or_default()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// 解码游标，排序列必须与生成游标的查询一致
+    fn decode(cursor: &str, order_by: &[OrderBy]) -> Result<Self> {
+        let invalid = || DatabaseError::parse_error(format!("无效的游标: {}", cursor));
+        if !cursor.is_ascii() || !cursor.len().is_multiple_of(2) {
+            return Err(invalid());
+        }
+        let bytes = (0..cursor.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&cursor[i..i + 2], 16))
+            .collect::<std::result::Result<Vec<u8>, _>>()
+            .map_err(|_| invalid())?;
+        let decoded: Self = serde_json::from_slice(&bytes).map_err(|_| invalid())?;
+        if !decoded.keys.iter().map(|(column, _)| column).eq(order_by.iter().map(|order| &order.column)) {
+            return Err(DatabaseError::parse_error("游标与查询的 ORDER BY 不一致"));
+        }
+        Ok(decoded)
+    }
+
+    /// 只含排序键的行，用于与表中的行比较
+    fn row(&self) -> Row {
+        let mut row = Row::new();
+        row.id = self.id;
+        for (column, value) in &self.keys {
+            if let Some(value) = value {
+                row.data.insert(column.clone(), value.clone());
+            }
+        }
+        row
+    }
+}
+
+/// top-N 堆中的元素，按排序键比较，键相等时按原始位置比较
+struct TopNEntry<'a> {
+    row: &'a Row,
+    index: usize,
+    order_by: &'a [OrderBy],
+}
+
+impl Ord for TopNEntry<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare_rows(self.row, other.row, self.order_by)
+            .then(self.index.cmp(&other.index))
+    }
+}
+
+impl PartialOrd for TopNEntry<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for TopNEntry<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for TopNEntry<'_> {}
+
 impl Default for QueryEngine {
     fn default() -> Self {
         Self::new()
@@ -492,6 +1583,12 @@ impl QueryBuilder {
         }
     }
 
+    pub fn insert_many(table_name: &str, rows: Vec<HashMap<String, Value>>) -> Self {
+        Self {
+            query: Query::insert_many(table_name, rows),
+        }
+    }
+
     pub fn update(table_name: &str, data: HashMap<String, Value>) -> Self {
         Self {
             query: Query::update(table_name, data),
@@ -515,6 +1612,55 @@ impl QueryBuilder {
         self
     }
 
+    pub fn where_param(mut self, column: &str, operator: ComparisonOperator, param: Placeholder) -> Self {
+        self.query.conditions.push(Condition::with_param(column, operator, param));
+        self
+    }
+
+    /// 按 JSON 列中的路径过滤，例如 `.where_json("meta", "$.address.city", Equal, "Paris".into())`
+    pub fn where_json(mut self, column: &str, path: &str, operator: ComparisonOperator, value: Value) -> Self {
+        self.query.conditions.push(Condition::json_path(column, JsonPath::new(path), operator, value));
+        self
+    }
+
+    /// 坐标列在 `center` 周围 `meters` 米之内，例如 `.where_within_radius("location", (31.23, 121.47), 500.0)`
+    pub fn where_within_radius(mut self, column: &str, center: (f64, f64), meters: f64) -> Self {
+        self.query.conditions.push(Condition::within_radius(column, center, meters));
+        self
+    }
+
+    /// 把 JSON 列中 `path` 处的值作为 `alias` 列附加到结果行
+    pub fn extract_json(mut self, alias: &str, column: &str, path: &str) -> Self {
+        self.query.json_extracts.push(JsonExtract::new(alias, column, JsonPath::new(path)));
+        self
+    }
+
+    pub fn condition(mut self, condition: Condition) -> Self {
+        self.query.conditions.push(condition);
+        self
+    }
+
+    pub fn extract(mut self, extract: JsonExtract) -> Self {
+        self.query.json_extracts.push(extract);
+        self
+    }
+
+    /// 按两个表达式的比较结果过滤
+    pub fn where_expr(self, left: Expr, operator: ComparisonOperator, right: Expr) -> Result<Self> {
+        Ok(self.condition(Condition::expression(left, operator, right)?))
+    }
+
+    /// 把表达式的值作为 `alias` 列附加到结果行
+    pub fn select_expr(mut self, alias: &str, expr: Expr) -> Self {
+        self.query.projections.push(Projection::new(alias, expr));
+        self
+    }
+
+    /// 在结果行中附加 `_id` 列，值为行 ID
+    pub fn with_row_id(self) -> Self {
+        self.select_expr(ID_COLUMN, Expr::column(ID_COLUMN))
+    }
+
     pub fn order_by(mut self, column: &str, ascending: bool) -> Self {
         self.query.order_by.push(OrderBy::new(column, ascending));
         self
@@ -530,6 +1676,23 @@ impl QueryBuilder {
         self
     }
 
+    /// 从上一页结果的 `next_cursor` 之后继续，需要与上一页相同的 ORDER BY
+    pub fn after(mut self, cursor: &str) -> Self {
+        self.query.after = Some(cursor.to_string());
+        self
+    }
+
+    pub fn dry_run(mut self) -> Self {
+        self.query.dry_run = true;
+        self
+    }
+
+    /// 同时求出满足条件的总行数，便于分页显示总页数
+    pub fn with_total(mut self) -> Self {
+        self.query.with_total = true;
+        self
+    }
+
     pub fn build(self) -> Query {
         self.query
     }
@@ -538,7 +1701,8 @@ impl QueryBuilder {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::{ColumnDefinition, Schema, DataType};
+    use crate::types::{ColumnDefinition, Schema, DataType, CREATED_AT_COLUMN, UPDATED_AT_COLUMN, VERSION_COLUMN};
+    use crate::expression::BinaryOperator;
 
     #[test]
     fn test_condition_evaluation() {
@@ -567,6 +1731,135 @@ mod tests {
         assert_eq!(query.limit, Some(10));
     }
 
+    #[tokio::test]
+    async fn test_top_n_matches_full_sort() {
+        let schema = Schema::new(vec![
+            ColumnDefinition::new("id", DataType::Integer, true),
+            ColumnDefinition::new("score", DataType::Integer, false),
+        ]);
+
+        let mut table = Table::new("scores".to_string(), schema);
+        for i in 0..200 {
+            let mut row = Row::new();
+            row.set("id", Value::Integer(i));
+            row.set("score", Value::Integer((i * 37) % 50));
+            table.insert(row).unwrap();
+        }
+
+        let engine = QueryEngine::new();
+        let top = QueryBuilder::select("scores")
+            .where_condition("id", ComparisonOperator::GreaterThan, Value::Integer(10))
+            .order_by("score", false)
+            .limit(15)
+            .offset(5)
+            .build();
+        let top_result = engine.execute(table.clone(), top).await.unwrap();
+
+        let full = QueryBuilder::select("scores")
+            .where_condition("id", ComparisonOperator::GreaterThan, Value::Integer(10))
+            .order_by("score", false)
+            .build();
+        let full_result = engine.execute(table, full).await.unwrap();
+
+        let ids = |rows: &[Row]| rows.iter().map(|row| row.get("id").cloned()).collect::<Vec<_>>();
+        assert_eq!(top_result.rows.len(), 15);
+        assert_eq!(ids(&top_result.rows), ids(&full_result.rows[5..20]));
+    }
+
+    #[tokio::test]
+    async fn test_cursor_pagination() {
+        let schema = Schema::new(vec![
+            ColumnDefinition::new("id", DataType::Integer, true),
+            ColumnDefinition::new("score", DataType::Integer, false),
+        ]);
+        let mut table = Table::new("scores".to_string(), schema);
+        for i in 0..25 {
+            let mut row = Row::new();
+            row.set("id", Value::Integer(i));
+            row.set("score", Value::Integer(i % 4));
+            table.insert(row).unwrap();
+        }
+
+        // 排序键大量重复时逐页取完，结果与一次完整排序一致
+        let engine = QueryEngine::new();
+        let page = |cursor: Option<&str>| {
+            let builder = QueryBuilder::select("scores").order_by("score", false).limit(7);
+            match cursor {
+                Some(cursor) => builder.after(cursor).build(),
+                None => builder.build(),
+            }
+        };
+        let mut paged = Vec::new();
+        let mut cursor = None;
+        loop {
+            let result = engine.execute(table.clone(), page(cursor.as_deref())).await.unwrap();
+            paged.extend(result.rows.iter().map(|row| row.get("id").cloned()));
+            match result.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        let full = engine.execute(table.clone(), QueryBuilder::select("scores").order_by("score", false).build()).await.unwrap();
+        assert_eq!(paged, full.rows.iter().map(|row| row.get("id").cloned()).collect::<Vec<_>>());
+
+        // 总行数不受 LIMIT、OFFSET 与游标影响，只在要求时计算
+        let result = engine.execute(table.clone(), page(None)).await.unwrap();
+        assert_eq!(result.total_matching, None);
+        let next = QueryBuilder::select("scores").order_by("score", false).limit(7).after(result.next_cursor.as_deref().unwrap()).with_total().build();
+        assert_eq!(engine.execute(table.clone(), next).await.unwrap().total_matching, Some(25));
+        let filtered = QueryBuilder::select("scores")
+            .where_condition("score", ComparisonOperator::Equal, Value::Integer(1))
+            .limit(2)
+            .offset(1)
+            .with_total()
+            .build();
+        let result = engine.execute(table.clone(), filtered).await.unwrap();
+        assert_eq!((result.rows.len(), result.total_matching), (2, Some(6)));
+
+        // 恰好取完时没有下一页
+        let exact = engine.execute(table.clone(), QueryBuilder::select("scores").order_by("id", true).limit(25).build()).await.unwrap();
+        assert!(exact.next_cursor.is_none());
+
+        let result = engine.execute(table.clone(), page(None)).await.unwrap();
+        let other_order = QueryBuilder::select("scores").order_by("id", true).limit(7).after(result.next_cursor.as_deref().unwrap()).build();
+        assert!(engine.execute(table.clone(), other_order).await.is_err());
+        assert!(engine.execute(table.clone(), page(Some("zz"))).await.is_err());
+        assert!(engine.execute(table, QueryBuilder::select("scores").after("00").build()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_result_column_metadata() {
+        let schema = Schema::new(vec![
+            ColumnDefinition::new("id", DataType::Integer, true),
+            ColumnDefinition::new("price", DataType::Float, false),
+        ]);
+        let table = Table::new("items".to_string(), schema);
+
+        // 没有结果行时也给出表结构与计算列的类型
+        let query = QueryBuilder::select("items")
+            .select_expr("doubled", Expr::binary(Expr::column("price"), crate::expression::BinaryOperator::Multiply, Expr::literal(2)))
+            .select_expr("copy", Expr::column("price"))
+            .with_row_id()
+            .build();
+        let result = QueryEngine::new().execute(table.clone(), query.clone()).await.unwrap();
+        assert!(result.rows.is_empty());
+        assert_eq!(result.columns, vec![
+            ("id".to_string(), DataType::Integer),
+            ("price".to_string(), DataType::Float),
+            ("doubled".to_string(), DataType::Text),
+            ("copy".to_string(), DataType::Float),
+            (ID_COLUMN.to_string(), DataType::Uuid),
+        ]);
+
+        let mut table = table;
+        let mut row = Row::new();
+        row.set("id", Value::Integer(1));
+        row.set("price", Value::Float(2.5));
+        table.insert(row).unwrap();
+        let result = QueryEngine::new().execute(table, query).await.unwrap();
+        assert_eq!(result.columns[2], ("doubled".to_string(), DataType::Float));
+    }
+
     #[tokio::test]
     async fn test_query_execution() {
         let schema = Schema::new(vec![
@@ -597,6 +1890,34 @@ mod tests {
         assert_eq!(result.rows[0].get("name"), Some(&Value::Text("Alice".to_string())));
     }
 
+    #[tokio::test]
+    async fn test_unknown_columns_are_rejected() {
+        let schema = Schema::new(vec![
+            ColumnDefinition::new("id", DataType::Integer, true),
+            ColumnDefinition::new("name", DataType::Text, false),
+        ]);
+        let table = Table::new("users".to_string(), schema);
+        let engine = QueryEngine::new();
+        let missing = |result: Result<QueryResult>| match result {
+            Err(DatabaseError::ColumnNotFound(column)) => column,
+            other => panic!("unexpected result {:?}", other),
+        };
+
+        let query = QueryBuilder::select("users").where_condition("nmae", ComparisonOperator::Equal, Value::Null).build();
+        assert_eq!(missing(engine.execute(table.clone(), query).await), "nmae");
+
+        let query = QueryBuilder::select("users").order_by("age", true).build();
+        assert_eq!(missing(engine.execute(table.clone(), query).await), "age");
+
+        let total = Expr::binary(Expr::column("id"), BinaryOperator::Add, Expr::column("score"));
+        let query = QueryBuilder::select("users").select_expr("total", total).build();
+        assert_eq!(missing(engine.execute(table.clone(), query).await), "score");
+
+        // 伪列总是可用
+        let query = QueryBuilder::select("users").order_by(VERSION_COLUMN, false).build();
+        assert!(engine.execute(table, query).await.is_ok());
+    }
+
     #[test]
     fn test_like_condition() {
         let mut row = Row::new();
@@ -611,4 +1932,47 @@ mod tests {
         let condition = Condition::new("name", ComparisonOperator::Like, Value::Text("%lice%".to_string()));
         assert!(condition.evaluate(&row).unwrap());
     }
+
+    #[tokio::test]
+    async fn test_param_binding() {
+        let schema = Schema::new(vec![
+            ColumnDefinition::new("name", DataType::Text, false),
+        ]);
+        let mut table = Table::new("users".to_string(), schema);
+        let mut row = Row::new();
+        row.set("name", Value::Text("Alice".to_string()));
+        table.rows.push(row);
+
+        let query = QueryBuilder::select("users")
+            .where_param("name", ComparisonOperator::Equal, Placeholder::parse(":name").unwrap())
+            .build();
+
+        let engine = QueryEngine::new();
+        assert!(engine.execute(table.clone(), query.clone()).await.is_err());
+        assert!(query.clone().bind(&Params::new()).is_err());
+
+        // 注入式输入只会作为普通文本值参与比较
+        let injected = query.clone().bind(&Params::new().set("name", "Alice' OR '1'='1")).unwrap();
+        assert_eq!(engine.execute(table.clone(), injected).await.unwrap().rows.len(), 0);
+
+        let bound = query.bind(&Params::new().set("name", "Alice")).unwrap();
+        assert_eq!(engine.execute(table, bound).await.unwrap().rows.len(), 1);
+
+        assert_eq!(Placeholder::parse("$2"), Some(Placeholder::Positional(2)));
+        assert_eq!(Placeholder::parse("$0"), None);
+        assert_eq!(Placeholder::parse(":bad name"), None);
+    }
+
+    #[test]
+    fn test_timestamp_pseudo_columns() {
+        let mut row = Row::new();
+        row.set("name", Value::Text("Alice".to_string()));
+        let before = row.created_at.naive_utc() - chrono::Duration::seconds(1);
+
+        let condition = Condition::new(CREATED_AT_COLUMN, ComparisonOperator::GreaterThan, Value::DateTime(before));
+        assert!(condition.evaluate(&row).unwrap());
+
+        let condition = Condition::new(UPDATED_AT_COLUMN, ComparisonOperator::LessThan, Value::DateTime(before));
+        assert!(!condition.evaluate(&row).unwrap());
+    }
 }
\ No newline at end of file
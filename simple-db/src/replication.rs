@@ -0,0 +1,217 @@
+use std::collections::VecDeque;
+#[cfg(feature = "native")]
+use std::net::SocketAddr;
+use std::sync::Arc;
+#[cfg(feature = "native")]
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+#[cfg(feature = "native")]
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::task::JoinHandle;
+
+use crate::engine::DatabaseEngine;
+#[cfg(feature = "native")]
+use crate::error::DatabaseError;
+use crate::error::Result;
+use crate::storage::LogEntry;
+
+/// 主库传给从库的日志流：先是订阅时已有的日志，再是之后新写入的日志
+pub struct LogStream {
+    backlog: VecDeque<LogEntry>,
+    live: UnboundedReceiver<LogEntry>,
+}
+
+impl LogStream {
+    pub fn new(backlog: Vec<LogEntry>, live: UnboundedReceiver<LogEntry>) -> Self {
+        Self {
+            backlog: backlog.into(),
+            live,
+        }
+    }
+
+    /// 下一条日志，主库被释放后返回 None
+    pub async fn recv(&mut self) -> Option<LogEntry> {
+        match self.backlog.pop_front() {
+            Some(entry) => Some(entry),
+            None => self.live.recv().await,
+        }
+    }
+}
+
+/// 通过进程内通道复制：把 `replica` 设为只读，并持续应用 `primary` 的日志
+///
+/// 返回的任务在主库被释放时结束，之后可以调用 `replica.promote()` 接管写入。
+pub async fn follow(primary: &DatabaseEngine, replica: Arc<DatabaseEngine>) -> Result<JoinHandle<Result<()>>> {
+    let mut stream = primary.subscribe_log(replica.last_log_id().await).await?;
+    replica.set_read_only(true).await?;
+
+    Ok(tokio::spawn(async move {
+        while let Some(entry) = stream.recv().await {
+            replica.apply_replicated(entry).await?;
+        }
+        Ok(())
+    }))
+}
+
+/// 主库在 `addr` 上接受从库连接，返回实际监听的地址与接受连接的任务
+///
+/// 协议按行传输：从库连接后先发送已应用的最后日志ID，主库订阅成功后回复 `OK`
+/// （失败时回复 `ERR <原因>`），随后逐行发送日志条目，格式与日志文件相同（带校验和）。
+#[cfg(feature = "native")]
+pub async fn serve<A: ToSocketAddrs>(primary: Arc<DatabaseEngine>, addr: A) -> Result<(SocketAddr, JoinHandle<()>)> {
+    let listener = TcpListener::bind(addr).await?;
+    let local_addr = listener.local_addr()?;
+
+    let handle = tokio::spawn(async move {
+        while let Ok((socket, _)) = listener.accept().await {
+            let primary = Arc::clone(&primary);
+            tokio::spawn(async move {
+                // 从库断开时写入失败，连接任务随之结束
+                let _ = ship_log(&primary, socket).await;
+            });
+        }
+    });
+
+    Ok((local_addr, handle))
+}
+
+#[cfg(feature = "native")]
+async fn ship_log(primary: &DatabaseEngine, socket: TcpStream) -> Result<()> {
+    let (reader, mut writer) = socket.into_split();
+    let mut line = String::new();
+    BufReader::new(reader).read_line(&mut line).await?;
+
+    let subscription = match line.trim().parse::<u64>() {
+        Ok(after) => primary.subscribe_log(after).await,
+        Err(_) => Err(DatabaseError::parse_error(format!("无效的日志ID '{}'", line.trim()))),
+    };
+    let mut stream = match subscription {
+        Ok(stream) => stream,
+        Err(e) => {
+            writer.write_all(format!("ERR {}\n", e).as_bytes()).await?;
+            return Err(e);
+        }
+    };
+    writer.write_all(b"OK\n").await?;
+
+    while let Some(entry) = stream.recv().await {
+        writer.write_all(format!("{}\n", entry.encode()?).as_bytes()).await?;
+    }
+    Ok(())
+}
+
+/// 从库连接位于 `addr` 的主库，把 `replica` 设为只读并持续应用收到的日志
+///
+/// 返回时主库已完成订阅，之后主库的写入都会传到从库。
+/// 返回的任务在连接断开时结束，之后可以调用 `replica.promote()` 接管写入。
+#[cfg(feature = "native")]
+pub async fn connect<A: ToSocketAddrs>(replica: Arc<DatabaseEngine>, addr: A) -> Result<JoinHandle<Result<()>>> {
+    let socket = TcpStream::connect(addr).await?;
+    let (reader, mut writer) = socket.into_split();
+    writer.write_all(format!("{}\n", replica.last_log_id().await).as_bytes()).await?;
+
+    let mut lines = BufReader::new(reader).lines();
+    match lines.next_line().await? {
+        Some(reply) if reply == "OK" => {}
+        Some(reply) => {
+            let reason = reply.strip_prefix("ERR ").unwrap_or(&reply);
+            return Err(DatabaseError::other(format!("主库拒绝复制: {}", reason)));
+        }
+        None => return Err(DatabaseError::other("主库在握手前关闭了连接")),
+    }
+    replica.set_read_only(true).await?;
+
+    Ok(tokio::spawn(async move {
+        // 保持写半部存活，否则主库会认为连接已关闭
+        let _writer = writer;
+        let mut line_number = 0;
+        while let Some(line) = lines.next_line().await? {
+            line_number += 1;
+            let entry = LogEntry::decode(&line)
+                .map_err(|reason| DatabaseError::corrupt_log(line_number, reason))?;
+            replica.apply_replicated(entry).await?;
+        }
+        Ok(())
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use crate::query::QueryBuilder;
+    use crate::types::{ColumnDefinition, DataType, Schema, Value};
+
+    fn engine() -> DatabaseEngine {
+        DatabaseEngine::in_memory()
+    }
+
+    fn user(id: i64) -> HashMap<String, Value> {
+        let mut data = HashMap::new();
+        data.insert("id".to_string(), Value::Integer(id));
+        data
+    }
+
+    async fn wait_for(replica: &DatabaseEngine, log_id: u64) {
+        for _ in 0..200 {
+            if replica.last_log_id().await >= log_id {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("从库未能追上日志 {}", log_id);
+    }
+
+    async fn count(engine: &DatabaseEngine, table: &str) -> usize {
+        engine.query(QueryBuilder::count(table).build()).await.unwrap().count.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_channel_replication_and_promote() {
+        let primary = engine();
+        let replica = Arc::new(engine());
+        let task = follow(&primary, Arc::clone(&replica)).await.unwrap();
+
+        let schema = Schema::new(vec![ColumnDefinition::new("id", DataType::Integer, true)]);
+        primary.create_table("users", schema).await.unwrap();
+        primary.insert("users", user(1)).await.unwrap();
+        primary.batch_insert("users", vec![user(2), user(3)]).await.unwrap();
+        wait_for(&replica, primary.last_log_id().await).await;
+
+        assert_eq!(count(&replica, "users").await, 3);
+        assert!(matches!(replica.insert("users", user(4)).await, Err(DatabaseError::ReadOnly)));
+
+        // 主库下线后提升从库，之后的写入从最后应用的日志ID继续编号
+        let last_id = primary.last_log_id().await;
+        drop(primary);
+        task.await.unwrap().unwrap();
+        replica.promote().await.unwrap();
+        let mut stream = replica.subscribe_log(last_id).await.unwrap();
+        replica.insert("users", user(4)).await.unwrap();
+        assert_eq!(count(&replica, "users").await, 4);
+        assert_eq!(stream.recv().await.unwrap().id, last_id + 1);
+        assert_eq!(replica.last_log_id().await, last_id + 1);
+    }
+
+    #[tokio::test]
+    async fn test_tcp_replication() {
+        let primary = Arc::new(engine());
+        let (addr, server) = serve(Arc::clone(&primary), "127.0.0.1:0").await.unwrap();
+
+        let replica = Arc::new(engine());
+        let task = connect(Arc::clone(&replica), addr).await.unwrap();
+
+        let schema = Schema::new(vec![ColumnDefinition::new("id", DataType::Integer, true)]);
+        primary.create_table("users", schema).await.unwrap();
+        primary.insert("users", user(1)).await.unwrap();
+        let conditions = vec![("id".to_string(), crate::query::ComparisonOperator::Equal, Value::Integer(1))];
+        primary.delete("users", conditions).await.unwrap();
+        primary.insert("users", user(2)).await.unwrap();
+        wait_for(&replica, primary.last_log_id().await).await;
+
+        assert_eq!(count(&replica, "users").await, 1);
+        server.abort();
+        task.abort();
+    }
+}
@@ -0,0 +1,187 @@
+use std::borrow::Cow;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::types::Row;
+
+/// 行存储布局下的表数据：删除只在原位置留下墓碑，其余行的位置与插入顺序都不变
+///
+/// 行按槽位保存，槽位下标即行的位置；墓碑达到槽位的四分之一时由表压缩，压缩后各行前移。
+/// 序列化时只写出存活的行，格式与 `Vec<Row>` 相同。
+#[derive(Debug, Clone, Default)]
+pub struct RowStore {
+    slots: Vec<Row>,
+    dead: Vec<bool>,
+    tombstones: usize,
+}
+
+impl RowStore {
+    /// 存活的行数
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.tombstones
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 槽位数，包括墓碑
+    pub fn slot_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn tombstones(&self) -> usize {
+        self.tombstones
+    }
+
+    /// 墓碑是否多到需要压缩
+    pub fn needs_compaction(&self) -> bool {
+        self.tombstones > 0 && self.tombstones * 4 >= self.slots.len()
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        self.slots.reserve(additional);
+        self.dead.reserve(additional);
+    }
+
+    /// 追加一行，返回它的槽位
+    pub fn push(&mut self, row: Row) -> usize {
+        self.slots.push(row);
+        self.dead.push(false);
+        self.slots.len() - 1
+    }
+
+    /// 槽位 `slot` 上存活的行
+    pub fn get(&self, slot: usize) -> Option<&Row> {
+        self.slots.get(slot).filter(|_| !self.dead[slot])
+    }
+
+    pub fn get_mut(&mut self, slot: usize) -> Option<&mut Row> {
+        if self.dead.get(slot).copied().unwrap_or(true) {
+            None
+        } else {
+            Some(&mut self.slots[slot])
+        }
+    }
+
+    /// 把槽位 `slot` 上的行标记为墓碑，行不存在或已删除时返回 false
+    pub fn remove(&mut self, slot: usize) -> bool {
+        match self.dead.get_mut(slot) {
+            Some(dead) if !*dead => {
+                *dead = true;
+                self.tombstones += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// 按插入顺序遍历存活的行
+    pub fn iter(&self) -> impl Iterator<Item = &Row> {
+        self.slots.iter().zip(&self.dead).filter(|(_, dead)| !**dead).map(|(row, _)| row)
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Row> {
+        self.slots.iter_mut().zip(&self.dead).filter(|(_, dead)| !**dead).map(|(row, _)| row)
+    }
+
+    /// 按插入顺序遍历存活的行及其槽位
+    pub fn entries(&self) -> impl Iterator<Item = (usize, &Row)> {
+        self.slots.iter().enumerate().filter(|(slot, _)| !self.dead[*slot])
+    }
+
+    /// 存活的行；没有墓碑时不复制
+    pub fn rows(&self) -> Cow<'_, [Row]> {
+        if self.tombstones == 0 {
+            Cow::Borrowed(&self.slots)
+        } else {
+            Cow::Owned(self.iter().cloned().collect())
+        }
+    }
+
+    /// 全部槽位上的行，包括墓碑；下标即槽位，用于构建按位置寻址的索引
+    pub fn slots(&self) -> &[Row] {
+        &self.slots
+    }
+
+    /// 去掉墓碑，存活的行前移并保持顺序
+    pub fn compact(&mut self) {
+        if self.tombstones == 0 {
+            return;
+        }
+        let mut dead = self.dead.iter();
+        self.slots.retain(|_| !*dead.next().unwrap());
+        self.dead = vec![false; self.slots.len()];
+        self.tombstones = 0;
+    }
+
+    /// 只保留满足条件的行，同时去掉墓碑
+    pub fn retain<F: FnMut(&Row) -> bool>(&mut self, mut keep: F) {
+        self.compact();
+        self.slots.retain(|row| keep(row));
+        self.dead.truncate(self.slots.len());
+    }
+
+    /// 取出最旧的 `count` 行，同时去掉墓碑
+    pub fn drain_front(&mut self, count: usize) -> Vec<Row> {
+        self.compact();
+        self.dead.truncate(self.slots.len() - count);
+        self.slots.drain(..count).collect()
+    }
+}
+
+impl From<Vec<Row>> for RowStore {
+    fn from(slots: Vec<Row>) -> Self {
+        Self { dead: vec![false; slots.len()], slots, tombstones: 0 }
+    }
+}
+
+impl Serialize for RowStore {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<'de> Deserialize<'de> for RowStore {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<Row>::deserialize(deserializer).map(Self::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Value;
+
+    fn make_row(id: i64) -> Row {
+        let mut row = Row::new();
+        row.set("id", Value::Integer(id));
+        row
+    }
+
+    #[test]
+    fn test_tombstones_keep_positions_and_order() {
+        let mut store = RowStore::default();
+        for id in 0..4 {
+            assert_eq!(store.push(make_row(id)), id as usize);
+        }
+
+        assert!(store.remove(1));
+        assert!(!store.remove(1));
+        assert!(store.get(1).is_none());
+        assert_eq!(store.get(2).and_then(|row| row.get_integer("id")), Some(2));
+        assert_eq!((store.len(), store.slot_count(), store.tombstones()), (3, 4, 1));
+        assert!(store.needs_compaction());
+
+        let ids: Vec<i64> = store.rows().iter().filter_map(|row| row.get_integer("id")).collect();
+        assert_eq!(ids, vec![0, 2, 3]);
+        // 序列化只写出存活的行
+        let restored: RowStore = serde_json::from_str(&serde_json::to_string(&store).unwrap()).unwrap();
+        assert_eq!((restored.len(), restored.tombstones()), (3, 0));
+
+        store.compact();
+        assert_eq!((store.len(), store.slot_count(), store.tombstones()), (3, 3, 0));
+        assert_eq!(store.get(1).and_then(|row| row.get_integer("id")), Some(2));
+        assert!(matches!(store.rows(), Cow::Borrowed(_)));
+    }
+}
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::{DatabaseError, Result};
+
+/// 序列：独立于表的计数器，`NEXTVAL('name')` 取下一个值，`CURRVAL('name')` 取最近一次取得的值
+///
+/// 与列的自增不同，一个序列可以供多张表、多个列共用，起始值与步长可以自定义，步长可以为负。
+/// 序列的当前值随写入日志与快照持久化；取得的值不会因插入失败而回退。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sequence {
+    pub name: String,
+    pub start: i64,
+    pub step: i64,
+    /// 最近一次取得的值，尚未调用 NEXTVAL 时为 None
+    #[serde(default)]
+    pub current: Option<i64>,
+}
+
+impl Sequence {
+    pub fn new<S: Into<String>>(name: S, start: i64, step: i64) -> Result<Self> {
+        let name = name.into();
+        if step == 0 {
+            return Err(DatabaseError::other(format!("序列 '{}' 的步长不能为 0", name)));
+        }
+        Ok(Self { name, start, step, current: None })
+    }
+
+    /// 前进一步并返回新值，第一次调用返回起始值
+    pub fn next_value(&mut self) -> Result<i64> {
+        let next = match self.current {
+            None => self.start,
+            Some(current) => current.checked_add(self.step)
+                .ok_or_else(|| DatabaseError::other(format!("序列 '{}' 已达到上限", self.name)))?,
+        };
+        self.current = Some(next);
+        Ok(next)
+    }
+
+    pub fn current_value(&self) -> Result<i64> {
+        self.current
+            .ok_or_else(|| DatabaseError::other(format!("序列 '{}' 尚未调用 NEXTVAL", self.name)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_and_current_value() {
+        let mut sequence = Sequence::new("order_no", 1000, -10).unwrap();
+        assert!(sequence.current_value().is_err());
+        assert_eq!(sequence.next_value().unwrap(), 1000);
+        assert_eq!(sequence.next_value().unwrap(), 990);
+        assert_eq!(sequence.current_value().unwrap(), 990);
+        assert!(Sequence::new("bad", 1, 0).is_err());
+
+        let mut last = Sequence { current: Some(i64::MAX), ..Sequence::new("last", 1, 1).unwrap() };
+        assert!(last.next_value().is_err());
+        assert_eq!(last.current_value().unwrap(), i64::MAX);
+    }
+}
@@ -0,0 +1,177 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use crate::binary::BinaryFormat;
+use crate::error::{DatabaseError, Result};
+use crate::output::OutputFormat;
+use crate::query::Params;
+use crate::types::Value;
+
+/// 会话设置：语句超时（毫秒，0 或 off 表示不限制）
+pub const TIMEOUT: &str = "timeout";
+/// 会话设置：查询结果的输出格式
+pub const OUTPUT_FORMAT: &str = "output_format";
+/// 会话设置：在每条语句后输出耗时
+pub const TIMER: &str = "timer";
+/// 会话设置：结果中二进制值的显示方式
+pub const BINARY_FORMAT: &str = "binary_format";
+
+/// 一个客户端的上下文：当前用户、当前数据库与会话变量
+///
+/// 由 `DatabaseEngine::session` 创建，通过 `DatabaseEngine::execute_sql_in` 等方法执行语句。
+/// `timeout`、`output_format`、`timer`、`binary_format` 是会话设置，其余变量是用户变量，可以作为命名参数引用。
+#[derive(Debug, Clone)]
+pub struct Session {
+    /// 当前用户，开启审计时记录为操作者
+    pub user: String,
+    database: String,
+    /// 只读语句的执行时长上限
+    pub timeout: Option<Duration>,
+    pub output_format: OutputFormat,
+    pub timer: bool,
+    pub binary_format: BinaryFormat,
+    variables: HashMap<String, Value>,
+}
+
+impl Session {
+    pub(crate) fn new<U: Into<String>, D: Into<String>>(user: U, database: D) -> Self {
+        Self {
+            user: user.into(),
+            database: database.into(),
+            timeout: None,
+            output_format: OutputFormat::default(),
+            timer: false,
+            binary_format: BinaryFormat::default(),
+            variables: HashMap::new(),
+        }
+    }
+
+    pub fn with_output_format(mut self, format: OutputFormat) -> Self {
+        self.output_format = format;
+        self
+    }
+
+    pub fn with_binary_format(mut self, format: BinaryFormat) -> Self {
+        self.binary_format = format;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// 当前数据库，通过 `DatabaseEngine::use_database_in` 切换
+    pub fn database(&self) -> &str {
+        &self.database
+    }
+
+    pub(crate) fn set_database(&mut self, name: &str) {
+        self.database = name.to_string();
+    }
+
+    /// 设置变量；会话设置的值类型不对时返回错误
+    pub fn set(&mut self, name: &str, value: Value) -> Result<()> {
+        match name {
+            TIMEOUT => {
+                self.timeout = match value {
+                    Value::Null => None,
+                    Value::Text(ref text) if text.eq_ignore_ascii_case("off") => None,
+                    Value::Integer(0) => None,
+                    Value::Integer(millis) if millis > 0 => Some(Duration::from_millis(millis as u64)),
+                    other => return Err(DatabaseError::type_mismatch(format!("{} 应为毫秒数或 off，实际为 {}", TIMEOUT, other))),
+                };
+            }
+            OUTPUT_FORMAT => match value {
+                Value::Text(text) => self.output_format = text.parse()?,
+                other => return Err(DatabaseError::type_mismatch(format!("{} 应为 table、json、csv 或 vertical，实际为 {}", OUTPUT_FORMAT, other))),
+            },
+            BINARY_FORMAT => match value {
+                Value::Text(text) => self.binary_format = text.parse()?,
+                other => return Err(DatabaseError::type_mismatch(format!("{} 应为 size、hex 或 base64，实际为 {}", BINARY_FORMAT, other))),
+            },
+            TIMER => {
+                self.timer = match value {
+                    Value::Boolean(on) => on,
+                    Value::Text(ref text) if text.eq_ignore_ascii_case("on") => true,
+                    Value::Text(ref text) if text.eq_ignore_ascii_case("off") => false,
+                    other => return Err(DatabaseError::type_mismatch(format!("{} 应为 on 或 off，实际为 {}", TIMER, other))),
+                };
+            }
+            _ => {
+                self.variables.insert(name.to_string(), value);
+            }
+        }
+        Ok(())
+    }
+
+    /// 删除用户变量或把会话设置恢复为默认值，返回变量之前是否存在
+    pub fn unset(&mut self, name: &str) -> bool {
+        match name {
+            TIMEOUT => self.timeout.take().is_some(),
+            OUTPUT_FORMAT => std::mem::take(&mut self.output_format) != OutputFormat::default(),
+            TIMER => std::mem::take(&mut self.timer),
+            BINARY_FORMAT => std::mem::take(&mut self.binary_format) != BinaryFormat::default(),
+            _ => self.variables.remove(name).is_some(),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Value> {
+        match name {
+            TIMEOUT => Some(self.timeout.map_or(Value::Null, |timeout| Value::Integer(timeout.as_millis() as i64))),
+            OUTPUT_FORMAT => Some(Value::Text(self.output_format.to_string())),
+            TIMER => Some(Value::Boolean(self.timer)),
+            BINARY_FORMAT => Some(Value::Text(self.binary_format.to_string())),
+            _ => self.variables.get(name).cloned(),
+        }
+    }
+
+    /// 按名称排序的全部变量，包括会话设置
+    pub fn variables(&self) -> BTreeMap<String, Value> {
+        let mut variables: BTreeMap<String, Value> = self.variables.iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        for name in [TIMEOUT, OUTPUT_FORMAT, TIMER, BINARY_FORMAT] {
+            variables.insert(name.to_string(), self.get(name).unwrap_or(Value::Null));
+        }
+        variables
+    }
+
+    /// 用户变量组成的命名参数
+    pub fn params(&self) -> Params {
+        Params::from(self.variables.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::Placeholder;
+
+    #[test]
+    fn test_settings_and_variables() {
+        let mut session = Session::new("alice", "main");
+        session.set(TIMEOUT, Value::Integer(500)).unwrap();
+        session.set(OUTPUT_FORMAT, Value::Text("json".to_string())).unwrap();
+        session.set(TIMER, Value::Text("on".to_string())).unwrap();
+        session.set(BINARY_FORMAT, Value::Text("hex".to_string())).unwrap();
+        session.set("limit", Value::Integer(10)).unwrap();
+        assert_eq!(session.timeout, Some(Duration::from_millis(500)));
+        assert_eq!(session.output_format, OutputFormat::Json);
+        assert!(session.timer);
+        assert_eq!(session.binary_format, BinaryFormat::Hex);
+        assert!(session.set(OUTPUT_FORMAT, Value::Text("xml".to_string())).is_err());
+        assert!(session.set(TIMEOUT, Value::Integer(-1)).is_err());
+
+        // 会话设置不是命名参数
+        let params = session.params();
+        assert_eq!(params.get(&Placeholder::Named("limit".to_string())), Some(&Value::Integer(10)));
+        assert_eq!(params.get(&Placeholder::Named(TIMEOUT.to_string())), None);
+        assert_eq!(session.variables().keys().collect::<Vec<_>>(), vec![BINARY_FORMAT, "limit", OUTPUT_FORMAT, TIMEOUT, TIMER]);
+
+        assert!(session.unset(TIMEOUT));
+        assert!(session.unset("limit"));
+        assert_eq!(session.get(TIMEOUT), Some(Value::Null));
+        assert_eq!(session.get("limit"), None);
+    }
+}
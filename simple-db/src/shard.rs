@@ -0,0 +1,510 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+
+use uuid::Uuid;
+
+use crate::clock;
+use crate::engine::DatabaseEngine;
+use crate::error::{DatabaseError, Result};
+use crate::partition::partition_hash;
+use crate::query::{compare_rows, ComparisonOperator, Condition, Query, QueryResult, QueryType};
+use crate::storage::StorageOperation;
+use crate::types::{Row, Schema, Value};
+
+/// 按分片键把表的行分散到多个 `DatabaseEngine` 中
+///
+/// 每张表在所有分片上有相同的结构。插入按分片键值的哈希路由到一个分片；条件中含有 `分片键 = 值` 的查询、
+/// 更新与删除只发往该分片，其余的发往所有分片并合并结果：行按 ORDER BY 归并后再应用 OFFSET 与 LIMIT，
+/// COUNT 相加。`execute` 的跨分片写操作在各分片上分别生效，需要原子性时使用 `transaction`。
+pub struct ShardedEngine {
+    shards: Vec<DatabaseEngine>,
+    /// 表名到分片键列的映射，只保存在内存中，重新打开分片后需要用 `register_table` 重新登记
+    shard_keys: Mutex<HashMap<String, String>>,
+}
+
+impl ShardedEngine {
+    pub fn new(shards: Vec<DatabaseEngine>) -> Result<Self> {
+        if shards.is_empty() {
+            return Err(DatabaseError::other("至少需要一个分片"));
+        }
+        Ok(Self {
+            shards,
+            shard_keys: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// `count` 个纯内存分片
+    pub fn in_memory(count: usize) -> Result<Self> {
+        Self::new((0..count).map(|_| DatabaseEngine::in_memory()).collect())
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    pub fn shard(&self, index: usize) -> Option<&DatabaseEngine> {
+        self.shards.get(index)
+    }
+
+    /// 在所有分片上建表，`shard_key` 必须是表中的列
+    pub async fn create_table(&self, name: &str, schema: Schema, shard_key: &str) -> Result<()> {
+        if schema.get_column(shard_key).is_none() {
+            return Err(DatabaseError::column_not_found(shard_key));
+        }
+        for shard in &self.shards {
+            shard.create_table(name, schema.clone()).await?;
+        }
+        self.register_table(name, shard_key);
+        Ok(())
+    }
+
+    /// 登记分片上已有的表
+    pub fn register_table(&self, name: &str, shard_key: &str) {
+        self.shard_keys.lock().unwrap().insert(name.to_string(), shard_key.to_string());
+    }
+
+    pub fn shard_key(&self, table: &str) -> Option<String> {
+        self.shard_keys.lock().unwrap().get(table).cloned()
+    }
+
+    /// 分片键值所在的分片，与哈希分区使用同一个稳定哈希
+    pub fn shard_for(&self, value: &Value) -> usize {
+        partition_hash(value) as usize % self.shards.len()
+    }
+
+    fn require_shard_key(&self, table: &str) -> Result<String> {
+        self.shard_key(table)
+            .ok_or_else(|| DatabaseError::other(format!("表 '{}' 不是分片表", table)))
+    }
+
+    /// 插入一行，数据中必须有分片键
+    pub async fn insert(&self, table: &str, data: HashMap<String, Value>) -> Result<Uuid> {
+        let index = self.route_row(table, &data)?;
+        self.shards[index].insert(table, data).await
+    }
+
+    /// 批量插入，按分片分组后在每个分片上批量写入；返回的行 ID 与输入顺序一致
+    pub async fn batch_insert(&self, table: &str, rows: Vec<HashMap<String, Value>>) -> Result<Vec<Uuid>> {
+        let mut positions = vec![Vec::new(); self.shards.len()];
+        let mut groups = vec![Vec::new(); self.shards.len()];
+        for (position, data) in rows.into_iter().enumerate() {
+            let index = self.route_row(table, &data)?;
+            positions[index].push(position);
+            groups[index].push(data);
+        }
+
+        let mut ids = Vec::new();
+        for ((shard, positions), group) in self.shards.iter().zip(positions).zip(groups) {
+            if !group.is_empty() {
+                ids.extend(positions.into_iter().zip(shard.batch_insert(table, group).await?));
+            }
+        }
+        ids.sort_unstable_by_key(|(position, _)| *position);
+        Ok(ids.into_iter().map(|(_, id)| id).collect())
+    }
+
+    fn route_row(&self, table: &str, data: &HashMap<String, Value>) -> Result<usize> {
+        let shard_key = self.require_shard_key(table)?;
+        match data.get(&shard_key) {
+            Some(value) if !value.is_null() => Ok(self.shard_for(value)),
+            _ => Err(DatabaseError::not_null_violation(table, shard_key)),
+        }
+    }
+
+    /// 条件中有分片键等值比较时只涉及一个分片，否则涉及所有分片
+    fn target_shards(&self, conditions: &[Condition], shard_key: &str) -> Vec<usize> {
+        let point = conditions.iter().find(|condition| {
+            condition.column == *shard_key && condition.operator == ComparisonOperator::Equal
+                && condition.path.is_none() && condition.is_single_column() && condition.param.is_none()
+        });
+        match point {
+            Some(condition) => vec![self.shard_for(&condition.value)],
+            None => (0..self.shards.len()).collect(),
+        }
+    }
+
+    /// 执行查询或写操作，按需路由到单个分片或发往所有分片后合并结果
+    pub async fn execute(&self, query: Query) -> Result<QueryResult> {
+        let shard_key = self.require_shard_key(&query.table_name)?;
+        if let QueryType::Insert = query.query_type {
+            if !query.rows.is_empty() {
+                let ids = self.batch_insert(&query.table_name, query.rows).await?;
+                return Ok(QueryResult::new(QueryType::Insert, query.table_name, 0).with_affected_ids(ids));
+            }
+            let data = query.data.ok_or_else(|| DatabaseError::other("INSERT 查询缺少数据"))?;
+            let id = self.insert(&query.table_name, data).await?;
+            return Ok(QueryResult::new(QueryType::Insert, query.table_name, 0).with_affected_ids(vec![id]));
+        }
+        if query.data.as_ref().is_some_and(|data| data.contains_key(&shard_key)) {
+            return Err(DatabaseError::other(format!("不能修改分片键 '{}'", shard_key)));
+        }
+
+        let targets = self.target_shards(&query.conditions, &shard_key);
+        if let [index] = targets[..] {
+            return self.shards[index].execute(query).await;
+        }
+        if query.after.is_some() {
+            return Err(DatabaseError::other("跨分片查询不支持游标分页"));
+        }
+        if query.limit.is_some() && matches!(query.query_type, QueryType::Update | QueryType::Delete) {
+            return Err(DatabaseError::other("跨分片的 UPDATE/DELETE 不支持 LIMIT"));
+        }
+
+        // 每个分片取前 offset + limit 行，合并排序后再截取
+        let mut shard_query = query.clone();
+        shard_query.offset = None;
+        shard_query.limit = query.limit.map(|limit| limit + query.offset.unwrap_or(0));
+        let mut results = Vec::new();
+        for index in targets {
+            results.push(self.shards[index].execute(shard_query.clone()).await?);
+        }
+        self.merge(&query, results).await
+    }
+
+    /// 执行跨分片事务：闭包中的写操作按分片分组，通过两阶段提交在涉及的分片上原子地生效
+    ///
+    /// 各分片按编号顺序准备，任一分片准备失败时中止已准备的分片；全部准备成功后依次提交。
+    /// 准备成功的分片一直持有锁直到提交，所有事务按同样的顺序准备，并发的跨分片事务不会互相等待成环。
+    /// 提交中途崩溃时，重新打开分片后调用 `recover` 完成未决的事务。
+    pub async fn transaction<F, T>(&self, operations: F) -> Result<T>
+    where
+        F: FnOnce(&mut ShardedTransaction) -> Result<T>,
+    {
+        let mut transaction = ShardedTransaction {
+            engine: self,
+            operations: vec![Vec::new(); self.shards.len()],
+        };
+        let result = operations(&mut transaction)?;
+        self.commit_distributed(transaction.operations).await?;
+        Ok(result)
+    }
+
+    async fn commit_distributed(&self, operations: Vec<Vec<StorageOperation>>) -> Result<()> {
+        let txid = Uuid::new_v4();
+        let mut prepared: Vec<usize> = Vec::new();
+        // `operations` 按分片编号排列，准备顺序即编号顺序
+        for (index, operations) in operations.into_iter().enumerate().filter(|(_, operations)| !operations.is_empty()) {
+            if let Err(e) = self.shards[index].prepare_transaction(txid, operations).await {
+                for index in prepared {
+                    let _ = self.shards[index].abort_prepared(txid).await;
+                }
+                return Err(e);
+            }
+            prepared.push(index);
+        }
+
+        // 决定提交后即使某个分片失败也继续提交其余分片，失败的分片留待 recover 处理；
+        // 第一个分片就提交失败（例如准备超时已被中止）时还没有分片提交，改为中止整个事务
+        let mut outcome = Ok(());
+        for (position, &index) in prepared.iter().enumerate() {
+            if let Err(e) = self.shards[index].commit_prepared(txid).await {
+                if position == 0 && !self.shards[index].is_committed(txid).await {
+                    for &index in &prepared {
+                        let _ = self.shards[index].abort_prepared(txid).await;
+                    }
+                    return Err(e);
+                }
+                outcome = outcome.and(Err(e));
+            }
+        }
+        outcome?;
+        for &index in &prepared {
+            self.shards[index].forget_transaction(txid).await;
+        }
+        Ok(())
+    }
+
+    /// 完成各分片上未决的跨分片事务，返回处理的事务数
+    ///
+    /// 任一分片已提交的事务在其余分片上提交，否则中止；之后丢弃所有分片上的提交记录。
+    /// 应在重新打开分片后、执行新的跨分片事务之前调用。
+    pub async fn recover(&self) -> Result<usize> {
+        let mut in_doubt = BTreeSet::new();
+        for shard in &self.shards {
+            in_doubt.extend(shard.in_doubt_transactions().await);
+        }
+        for &txid in &in_doubt {
+            let mut committed = false;
+            for shard in &self.shards {
+                committed |= shard.is_committed(txid).await;
+            }
+            for shard in &self.shards {
+                if shard.in_doubt_transactions().await.contains(&txid) {
+                    if committed {
+                        shard.commit_prepared(txid).await?;
+                    } else {
+                        shard.abort_prepared(txid).await?;
+                    }
+                }
+            }
+        }
+        for shard in &self.shards {
+            for txid in shard.committed_transactions().await {
+                shard.forget_transaction(txid).await;
+            }
+        }
+        Ok(in_doubt.len())
+    }
+
+    async fn merge(&self, query: &Query, results: Vec<QueryResult>) -> Result<QueryResult> {
+        let mut merged = QueryResult::new(query.query_type.clone(), query.table_name.clone(), 0);
+        let mut rows: Vec<Row> = Vec::new();
+        let mut ids = Vec::new();
+        for result in results {
+            merged.execution_time_ms = merged.execution_time_ms.max(result.execution_time_ms);
+            merged.rows_scanned += result.rows_scanned;
+            merged.dry_run = result.dry_run;
+            if let Some(count) = result.count {
+                merged.count = Some(merged.count.unwrap_or(0) + count);
+            }
+            if let Some(total) = result.total_matching {
+                merged.total_matching = Some(merged.total_matching.unwrap_or(0) + total);
+            }
+            if merged.schema.is_none() {
+                merged.schema = result.schema;
+                merged.columns = result.columns;
+            }
+            rows.extend(result.rows);
+            ids.extend(result.affected_ids);
+        }
+
+        if !query.order_by.is_empty() {
+            // 未显式指定排序规则时取列定义的规则，与单个分片上的排序一致
+            let schema = self.shards[0].get_table_info(&query.table_name).await?.schema;
+            let mut order_by = query.order_by.clone();
+            for order in &mut order_by {
+                if let Some(column) = schema.get_column(&order.column).filter(|_| order.collation == Default::default()) {
+                    order.collation = column.collation;
+                }
+            }
+            rows.sort_by(|a, b| compare_rows(a, b, &order_by));
+        }
+        if matches!(query.query_type, QueryType::Select) {
+            rows = rows.into_iter()
+                .skip(query.offset.unwrap_or(0))
+                .take(query.limit.unwrap_or(usize::MAX))
+                .collect();
+        }
+        merged.rows = rows;
+        if matches!(query.query_type, QueryType::Update | QueryType::Delete) {
+            merged = merged.with_affected_ids(ids);
+        }
+        Ok(merged)
+    }
+}
+
+/// 跨分片事务中缓冲的写操作，提交时按分片分组，见 `ShardedEngine::transaction`
+pub struct ShardedTransaction<'a> {
+    engine: &'a ShardedEngine,
+    /// 每个分片上的操作
+    operations: Vec<Vec<StorageOperation>>,
+}
+
+impl ShardedTransaction<'_> {
+    /// 插入一行，按分片键路由
+    pub fn insert(&mut self, table: &str, data: HashMap<String, Value>) -> Result<Uuid> {
+        let index = self.engine.route_row(table, &data)?;
+        let mut row = Row::new();
+        for (column, value) in data {
+            row.set(column, value);
+        }
+        let id = row.id;
+        self.operations[index].push(StorageOperation::Insert { table: table.to_string(), row });
+        Ok(id)
+    }
+
+    /// 更新满足条件的行，路由规则与 `ShardedEngine::execute` 相同
+    pub fn update(&mut self, table: &str, conditions: Vec<Condition>, data: HashMap<String, Value>) -> Result<()> {
+        let shard_key = self.engine.require_shard_key(table)?;
+        if data.contains_key(&shard_key) {
+            return Err(DatabaseError::other(format!("不能修改分片键 '{}'", shard_key)));
+        }
+        let updated_at = clock::now();
+        for index in self.targets(&conditions, &shard_key)? {
+            self.operations[index].push(StorageOperation::UpdateWhere {
+                table: table.to_string(),
+                conditions: conditions.clone(),
+                data: data.clone(),
+                updated_at,
+            });
+        }
+        Ok(())
+    }
+
+    /// 删除满足条件的行
+    pub fn delete(&mut self, table: &str, conditions: Vec<Condition>) -> Result<()> {
+        let shard_key = self.engine.require_shard_key(table)?;
+        for index in self.targets(&conditions, &shard_key)? {
+            self.operations[index].push(StorageOperation::DeleteWhere { table: table.to_string(), conditions: conditions.clone() });
+        }
+        Ok(())
+    }
+
+    /// 条件在准备与提交时各求值一次，不能依赖当前时间
+    fn targets(&self, conditions: &[Condition], shard_key: &str) -> Result<Vec<usize>> {
+        if !conditions.iter().all(Condition::is_deterministic) {
+            return Err(DatabaseError::other("跨分片事务中的条件不能依赖当前时间"));
+        }
+        Ok(self.engine.target_shards(conditions, shard_key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::MemoryBackend;
+    use crate::query::QueryBuilder;
+    use crate::types::{ColumnDefinition, DataType};
+    use std::sync::Arc;
+
+    fn user(id: i64, city: &str) -> HashMap<String, Value> {
+        HashMap::from([
+            ("id".to_string(), Value::Integer(id)),
+            ("city".to_string(), Value::Text(city.to_string())),
+        ])
+    }
+
+    #[tokio::test]
+    async fn test_route_and_merge_across_shards() {
+        let sharded = ShardedEngine::in_memory(3).unwrap();
+        sharded.create_table("users", Schema::new(vec![
+            ColumnDefinition::new("id", DataType::Integer, true),
+            ColumnDefinition::new("city", DataType::Text, false),
+        ]), "id").await.unwrap();
+        let ids = sharded.batch_insert("users", (1..=9).map(|id| user(id, if id % 2 == 0 { "a" } else { "b" })).collect()).await.unwrap();
+        assert_eq!(ids.len(), 9);
+        let mut per_shard = Vec::new();
+        for index in 0..3 {
+            per_shard.push(sharded.shard(index).unwrap().get_table_info("users").await.unwrap().row_count);
+        }
+        assert_eq!(per_shard.iter().sum::<usize>(), 9);
+        assert!(per_shard.iter().filter(|count| **count > 0).count() > 1);
+
+        // 分片键等值查询只读取一个分片
+        let point = sharded.execute(QueryBuilder::select("users")
+            .where_condition("id", ComparisonOperator::Equal, Value::Integer(4)).build()).await.unwrap();
+        assert_eq!(point.rows.len(), 1);
+        assert_eq!(point.rows_scanned, per_shard[sharded.shard_for(&Value::Integer(4))]);
+
+        let page = sharded.execute(QueryBuilder::select("users").order_by("id", false).limit(3).offset(2).with_total().build()).await.unwrap();
+        let page_ids: Vec<_> = page.rows.iter().filter_map(|row| row.get("id").cloned()).collect();
+        assert_eq!(page_ids, [7, 6, 5].map(Value::Integer));
+        assert_eq!(page.total_matching, Some(9));
+
+        let count = sharded.execute(QueryBuilder::count("users")
+            .where_condition("city", ComparisonOperator::Equal, Value::Text("a".to_string())).build()).await.unwrap();
+        assert_eq!(count.count, Some(4));
+
+        let deleted = sharded.execute(QueryBuilder::delete("users")
+            .where_condition("city", ComparisonOperator::Equal, Value::Text("b".to_string())).build()).await.unwrap();
+        assert_eq!(deleted.affected_rows, 5);
+        assert!(sharded.execute(QueryBuilder::update("users", user(1, "c")).build()).await.is_err());
+        assert!(sharded.insert("users", HashMap::from([("city".to_string(), Value::Text("c".to_string()))])).await.is_err());
+    }
+
+    async fn open(backends: &[MemoryBackend]) -> ShardedEngine {
+        let mut shards = Vec::new();
+        for backend in backends {
+            shards.push(DatabaseEngine::with_backend(Arc::new(backend.clone())).await.unwrap());
+        }
+        let sharded = ShardedEngine::new(shards).unwrap();
+        sharded.register_table("users", "id");
+        sharded
+    }
+
+    async fn count(sharded: &ShardedEngine) -> Option<usize> {
+        sharded.execute(QueryBuilder::count("users").build()).await.unwrap().count
+    }
+
+    #[tokio::test]
+    async fn test_two_phase_commit_and_recovery() {
+        let backends = [MemoryBackend::new(), MemoryBackend::new()];
+        let sharded = open(&backends).await;
+        sharded.create_table("users", Schema::new(vec![
+            ColumnDefinition::new("id", DataType::Integer, true).unique(true),
+            ColumnDefinition::new("city", DataType::Text, false),
+        ]), "id").await.unwrap();
+        sharded.transaction(|tx| {
+            (1..=6).try_for_each(|id| tx.insert("users", user(id, "a")).map(drop))?;
+            tx.update("users", Vec::new(), HashMap::from([("city".to_string(), Value::Text("b".to_string()))]))
+        }).await.unwrap();
+        assert_eq!(count(&sharded).await, Some(6));
+        for index in 0..2 {
+            assert!(sharded.shard(index).unwrap().get_table_info("users").await.unwrap().row_count > 0);
+        }
+
+        // 一个分片准备失败时其他分片上的写入也不生效
+        let failed = sharded.transaction(|tx| {
+            tx.insert("users", user(7, "c"))?;
+            tx.insert("users", user(8, "c"))?;
+            tx.insert("users", user(1, "c"))
+        }).await;
+        assert!(failed.is_err());
+        assert_eq!(count(&sharded).await, Some(6));
+
+        // 协调者只在一个分片上提交后崩溃；另一个事务只在一个分片上准备
+        let (first, second) = (sharded.shard(0).unwrap(), sharded.shard(1).unwrap());
+        let row = |id: i64| {
+            let mut row = Row::new();
+            row.set("id".to_string(), Value::Integer(id));
+            StorageOperation::Insert { table: "users".to_string(), row }
+        };
+        let (committed, aborted) = (Uuid::new_v4(), Uuid::new_v4());
+        first.prepare_transaction(committed, vec![row(100)]).await.unwrap();
+        second.prepare_transaction(committed, vec![row(101)]).await.unwrap();
+        first.commit_prepared(committed).await.unwrap();
+        first.prepare_transaction(aborted, vec![row(102)]).await.unwrap();
+        drop(sharded);
+
+        let sharded = open(&backends).await;
+        assert_eq!(sharded.shard(1).unwrap().in_doubt_transactions().await, vec![committed]);
+        assert_eq!(sharded.recover().await.unwrap(), 2);
+        assert_eq!(count(&sharded).await, Some(8));
+        for index in 0..2 {
+            let shard = sharded.shard(index).unwrap();
+            assert!(shard.in_doubt_transactions().await.is_empty());
+            assert!(shard.committed_transactions().await.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_overlapping_transactions_and_stuck_prepare() {
+        let sharded = ShardedEngine::in_memory(2).unwrap();
+        sharded.create_table("users", Schema::new(vec![
+            ColumnDefinition::new("id", DataType::Integer, true).unique(true),
+            ColumnDefinition::new("city", DataType::Text, false),
+        ]), "id").await.unwrap();
+
+        // 两个并发事务都写两个分片；按编号顺序准备，不会互相等待成环
+        let overlapping = async {
+            tokio::join!(
+                sharded.transaction(|tx| (1..=10).try_for_each(|id| tx.insert("users", user(id, "a")).map(drop))),
+                sharded.transaction(|tx| (11..=20).try_for_each(|id| tx.insert("users", user(id, "b")).map(drop))),
+            )
+        };
+        let (first, second) = tokio::time::timeout(std::time::Duration::from_secs(5), overlapping).await.unwrap();
+        first.unwrap();
+        second.unwrap();
+        assert_eq!(count(&sharded).await, Some(20));
+
+        // 协调者准备后失联：超时后其他写入中止该事务并继续，协调者之后的提交失败
+        let mut shard = DatabaseEngine::in_memory();
+        shard.set_prepared_timeout(Some(std::time::Duration::from_millis(50)));
+        shard.execute_sql("CREATE TABLE users (id INTEGER PRIMARY KEY)").await.unwrap();
+        let mut row = Row::new();
+        row.set("id".to_string(), Value::Integer(1));
+        let stuck = Uuid::new_v4();
+        shard.prepare_transaction(stuck, vec![StorageOperation::Insert { table: "users".to_string(), row }]).await.unwrap();
+        let insert = shard.execute_sql("INSERT INTO users (id) VALUES (2)");
+        tokio::time::timeout(std::time::Duration::from_secs(5), insert).await.unwrap().unwrap();
+        assert!(shard.in_doubt_transactions().await.is_empty());
+        assert!(shard.commit_prepared(stuck).await.is_err());
+        assert_eq!(shard.get_table_info("users").await.unwrap().row_count, 1);
+
+        // 管理员也可以直接中止卡住的事务
+        let stuck = Uuid::new_v4();
+        shard.prepare_transaction(stuck, Vec::new()).await.unwrap();
+        shard.abort_prepared(stuck).await.unwrap();
+        assert!(shard.in_doubt_transactions().await.is_empty());
+    }
+}
@@ -0,0 +1,85 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// 写时复制的共享数据：克隆只增加引用计数，通过可变引用访问时才在仍被共享的情况下复制一份
+///
+/// 表的行数据使用它保存，查询拿到的表快照与存储共享同一份数据，读取不需要复制。
+#[derive(Debug, Default)]
+pub struct Shared<T>(Arc<T>);
+
+impl<T> Shared<T> {
+    pub fn new(value: T) -> Self {
+        Self(Arc::new(value))
+    }
+
+    /// 两个值是否共享同一份数据
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        Arc::ptr_eq(&this.0, &other.0)
+    }
+}
+
+impl<T> Clone for Shared<T> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<T> Deref for Shared<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Clone> DerefMut for Shared<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        Arc::make_mut(&mut self.0)
+    }
+}
+
+impl<T> From<T> for Shared<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: PartialEq> PartialEq for Shared<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<T: Serialize> Serialize for Shared<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Shared<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Self::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_copy_on_write() {
+        let original = Shared::new(vec![1, 2, 3]);
+        let mut snapshot = original.clone();
+        assert!(Shared::ptr_eq(&original, &snapshot));
+
+        snapshot.push(4);
+        assert!(!Shared::ptr_eq(&original, &snapshot));
+        assert_eq!(*original, vec![1, 2, 3]);
+        assert_eq!(snapshot.len(), 4);
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert_eq!(json, "[1,2,3,4]");
+        assert_eq!(serde_json::from_str::<Shared<Vec<i32>>>(&json).unwrap(), snapshot);
+    }
+}
@@ -0,0 +1,201 @@
+//! 数值列的比较与聚合内核
+//!
+//! 开启 `simd` 特性时按 `LANES` 个值一组处理：先把一组 `Option` 拆成值数组与非 NULL 掩码，
+//! 再在定长数组上做无分支的比较与累加，便于编译器生成 SIMD 指令；只用稳定版 Rust，不依赖 `std::simd`。
+//! 未开启时逐个值处理，结果相同。
+
+use std::ops::Add;
+
+/// 每组处理的值个数，8 个 64 位值正好填满一个 AVX-512 寄存器或两个 AVX2 寄存器
+#[cfg(feature = "simd")]
+pub const LANES: usize = 8;
+
+/// 可以按组比较与累加的数值类型
+pub trait Numeric: Copy + Default + PartialOrd {
+    /// 求和使用的类型，整数用 i128 避免中间结果溢出
+    type Sum: Copy + Default + Add<Output = Self::Sum>;
+    const MIN: Self;
+    const MAX: Self;
+
+    fn widen(self) -> Self::Sum;
+}
+
+impl Numeric for i64 {
+    type Sum = i128;
+    const MIN: Self = i64::MIN;
+    const MAX: Self = i64::MAX;
+
+    fn widen(self) -> i128 {
+        self as i128
+    }
+}
+
+impl Numeric for f64 {
+    type Sum = f64;
+    const MIN: Self = f64::NEG_INFINITY;
+    const MAX: Self = f64::INFINITY;
+
+    fn widen(self) -> f64 {
+        self
+    }
+}
+
+/// 选中的非 NULL 值的个数、和、最小值与最大值
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary<T: Numeric> {
+    pub count: usize,
+    pub sum: T::Sum,
+    pub min: T,
+    pub max: T,
+}
+
+impl<T: Numeric> Default for Summary<T> {
+    fn default() -> Self {
+        Self { count: 0, sum: T::Sum::default(), min: T::MAX, max: T::MIN }
+    }
+}
+
+impl<T: Numeric> Summary<T> {
+    /// 无分支地累加一个值，`take` 为 false 时不改变结果
+    #[inline(always)]
+    fn add(&mut self, value: T, take: bool) {
+        self.count += take as usize;
+        self.sum = self.sum + if take { value } else { T::default() }.widen();
+        self.min = smaller(self.min, if take { value } else { T::MAX });
+        self.max = larger(self.max, if take { value } else { T::MIN });
+    }
+}
+
+/// 较小的值；`candidate` 为 NaN 时保留 `current`
+#[inline(always)]
+fn smaller<T: PartialOrd>(current: T, candidate: T) -> T {
+    if candidate < current { candidate } else { current }
+}
+
+#[inline(always)]
+fn larger<T: PartialOrd>(current: T, candidate: T) -> T {
+    if candidate > current { candidate } else { current }
+}
+
+/// 按比较结果是否满足条件：`accepts` 依次对应小于、等于、大于
+///
+/// 既不小于也不大于的值视为相等，因此 NaN 与任何数相等，与逐行比较一致。
+#[inline(always)]
+fn accepts<U: PartialOrd>(value: U, target: U, accepts: [bool; 3]) -> bool {
+    let (less, greater) = (value < target, value > target);
+    (accepts[0] & less) | (accepts[1] & !less & !greater) | (accepts[2] & greater)
+}
+
+/// 把值经 `convert` 转换后与 `target` 比较，不满足或为 NULL 的位置在选择向量中置为 false
+#[cfg(feature = "simd")]
+pub fn select<T: Copy + Default, U: PartialOrd + Copy>(
+    selection: &mut [bool],
+    values: &[Option<T>],
+    target: U,
+    accepted: [bool; 3],
+    convert: impl Fn(T) -> U,
+) {
+    let mut selection_groups = selection.chunks_exact_mut(LANES);
+    let mut value_groups = values.chunks_exact(LANES);
+    for (keep, group) in (&mut selection_groups).zip(&mut value_groups) {
+        let present: [bool; LANES] = std::array::from_fn(|i| group[i].is_some());
+        let lanes: [U; LANES] = std::array::from_fn(|i| convert(group[i].unwrap_or_default()));
+        for i in 0..LANES {
+            keep[i] &= present[i] & accepts(lanes[i], target, accepted);
+        }
+    }
+    for (keep, value) in selection_groups.into_remainder().iter_mut().zip(value_groups.remainder()) {
+        *keep &= value.is_some_and(|value| accepts(convert(value), target, accepted));
+    }
+}
+
+/// 把值经 `convert` 转换后与 `target` 比较，不满足或为 NULL 的位置在选择向量中置为 false
+#[cfg(not(feature = "simd"))]
+pub fn select<T: Copy + Default, U: PartialOrd + Copy>(
+    selection: &mut [bool],
+    values: &[Option<T>],
+    target: U,
+    accepted: [bool; 3],
+    convert: impl Fn(T) -> U,
+) {
+    for (keep, value) in selection.iter_mut().zip(values) {
+        *keep &= value.is_some_and(|value| accepts(convert(value), target, accepted));
+    }
+}
+
+/// 汇总选择向量中为 true 的非 NULL 值
+#[cfg(feature = "simd")]
+pub fn summarize<T: Numeric>(values: &[Option<T>], selection: &[bool]) -> Summary<T> {
+    // 每个分量一个数组，同一分量的 LANES 个累加器在寄存器中相邻
+    let mut counts = [0usize; LANES];
+    let mut sums = [T::Sum::default(); LANES];
+    let mut mins = [T::MAX; LANES];
+    let mut maxes = [T::MIN; LANES];
+    let mut value_groups = values.chunks_exact(LANES);
+    let mut selection_groups = selection.chunks_exact(LANES);
+    for (group, keep) in (&mut value_groups).zip(&mut selection_groups) {
+        let take: [bool; LANES] = std::array::from_fn(|i| group[i].is_some() & keep[i]);
+        let values: [T; LANES] = std::array::from_fn(|i| group[i].unwrap_or_default());
+        for i in 0..LANES {
+            counts[i] += take[i] as usize;
+            sums[i] = sums[i] + if take[i] { values[i] } else { T::default() }.widen();
+            mins[i] = smaller(mins[i], if take[i] { values[i] } else { T::MAX });
+            maxes[i] = larger(maxes[i], if take[i] { values[i] } else { T::MIN });
+        }
+    }
+    let mut summary = Summary::default();
+    for (value, keep) in value_groups.remainder().iter().zip(selection_groups.remainder()) {
+        summary.add(value.unwrap_or_default(), value.is_some() & keep);
+    }
+    for i in 0..LANES {
+        summary.count += counts[i];
+        summary.sum = summary.sum + sums[i];
+        summary.min = smaller(summary.min, mins[i]);
+        summary.max = larger(summary.max, maxes[i]);
+    }
+    summary
+}
+
+/// 汇总选择向量中为 true 的非 NULL 值
+#[cfg(not(feature = "simd"))]
+pub fn summarize<T: Numeric>(values: &[Option<T>], selection: &[bool]) -> Summary<T> {
+    let mut summary = Summary::default();
+    for (value, keep) in values.iter().zip(selection) {
+        summary.add(value.unwrap_or_default(), value.is_some() & keep);
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kernels_handle_nulls_nan_and_remainders() {
+        // 长度不是组大小的整数倍，覆盖尾部的逐个处理
+        let integers: Vec<Option<i64>> = (0..37).map(|i| (i % 5 != 0).then_some(i * 3 - 50)).collect();
+        let mut selection = vec![true; integers.len()];
+        select(&mut selection, &integers, 10, [false, true, true], |value| value);
+        let expected: Vec<bool> = integers.iter().map(|value| value.is_some_and(|value| value >= 10)).collect();
+        assert_eq!(selection, expected);
+
+        let mut selection = vec![true; integers.len()];
+        select(&mut selection, &integers, -0.5, [true, false, false], |value| value as f64);
+        assert_eq!(selection.iter().filter(|keep| **keep).count(), integers.iter().flatten().filter(|value| **value < 0).count());
+
+        let summary = summarize(&integers, &expected);
+        let selected: Vec<i64> = integers.iter().zip(&expected).filter_map(|(value, keep)| value.filter(|_| *keep)).collect();
+        assert_eq!(summary.count, selected.len());
+        assert_eq!(summary.sum, selected.iter().map(|value| *value as i128).sum::<i128>());
+        assert_eq!((summary.min, summary.max), (13, 58));
+
+        // NaN 与任何数相等，不参与最小值与最大值
+        let floats = vec![Some(1.5), None, Some(f64::NAN), Some(-2.0), Some(8.0), Some(0.0), Some(3.0), Some(4.0), Some(9.5)];
+        let mut selection = vec![true; floats.len()];
+        select(&mut selection, &floats, 3.0, [false, true, false], |value| value);
+        assert_eq!(selection, [false, false, true, false, false, false, true, false, false]);
+        let summary = summarize(&floats, &[true; 9]);
+        assert_eq!((summary.count, summary.min, summary.max), (8, -2.0, 9.5));
+        assert_eq!(summarize::<f64>(&floats, &[false; 9]).count, 0);
+    }
+}
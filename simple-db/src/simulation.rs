@@ -0,0 +1,292 @@
+//! 可重复的模拟测试
+//!
+//! [`SimulatedEngine`] 把引擎接在 [`FaultyBackend`] 上，并在虚拟时钟下执行语句：同一个种子得到同样的时间戳、
+//! 同样的随机序列与同样的注入故障，proptest 或模糊测试发现的失败可以按种子重放。
+//! 故障在持久化后端的调用处注入，写日志失败的操作返回错误，`crash` 丢弃引擎后从后端重新打开，
+//! 检查崩溃恢复后只保留返回成功的写入。
+//!
+//! 行 ID 仍由 `Uuid::new_v4` 随机生成，比较前后状态时应比较列值而不是行 ID。
+//! 需要可重复的并发交错时使用 `current_thread` 运行时，任务只在 await 处切换。
+
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use crate::backend::{MemoryBackend, StorageBackend};
+use crate::clock::{self, VirtualClock};
+use crate::engine::DatabaseEngine;
+use crate::error::{DatabaseError, Result};
+use crate::query::QueryResult;
+use crate::storage::{LogEntry, Snapshot};
+
+/// 由种子决定的伪随机数（splitmix64），不依赖外部的随机数库
+#[derive(Debug, Clone)]
+pub struct SimRng {
+    state: u64,
+}
+
+impl SimRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// `[0, n)` 中的一个数，`n` 为 0 时返回 0
+    pub fn below(&mut self, n: u64) -> u64 {
+        if n == 0 {
+            return 0;
+        }
+        self.next_u64() % n
+    }
+
+    /// 以概率 `p` 返回 true
+    pub fn chance(&mut self, p: f64) -> bool {
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < p
+    }
+}
+
+/// 可以注入故障的后端调用
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FaultPoint {
+    Append,
+    ReadLog,
+    SaveSnapshot,
+    LoadSnapshot,
+}
+
+impl FaultPoint {
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl fmt::Display for FaultPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            FaultPoint::Append => "append",
+            FaultPoint::ReadLog => "read_log",
+            FaultPoint::SaveSnapshot => "save_snapshot",
+            FaultPoint::LoadSnapshot => "load_snapshot",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// 各后端调用随机失败的概率，默认都不失败
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FaultConfig {
+    rates: [f64; 4],
+}
+
+impl FaultConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置 `point` 失败的概率
+    pub fn rate(mut self, point: FaultPoint, p: f64) -> Self {
+        self.rates[point.index()] = p;
+        self
+    }
+
+    pub fn rate_of(&self, point: FaultPoint) -> f64 {
+        self.rates[point.index()]
+    }
+}
+
+/// 在调用内层后端之前按配置注入 IO 错误的后端，克隆共享同一份状态
+///
+/// 失败的调用不会到达内层后端：写日志失败时该条日志没有保存，保存快照失败时保留之前的快照。
+#[derive(Clone)]
+pub struct FaultyBackend {
+    inner: Arc<dyn StorageBackend>,
+    state: Arc<Mutex<FaultState>>,
+}
+
+struct FaultState {
+    rng: SimRng,
+    config: FaultConfig,
+    /// 之后必定失败的调用，按顺序各失败一次
+    queued: Vec<FaultPoint>,
+    injected: usize,
+}
+
+impl FaultyBackend {
+    pub fn new(inner: Arc<dyn StorageBackend>, seed: u64) -> Self {
+        let state = FaultState { rng: SimRng::new(seed), config: FaultConfig::default(), queued: Vec::new(), injected: 0 };
+        Self { inner, state: Arc::new(Mutex::new(state)) }
+    }
+
+    /// 设置随机失败的概率
+    pub fn set_faults(&self, config: FaultConfig) {
+        self.state.lock().unwrap().config = config;
+    }
+
+    /// 下一次 `point` 调用失败
+    pub fn fail_next(&self, point: FaultPoint) {
+        self.state.lock().unwrap().queued.push(point);
+    }
+
+    /// 已注入的故障数
+    pub fn injected(&self) -> usize {
+        self.state.lock().unwrap().injected
+    }
+
+    fn check(&self, point: FaultPoint) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let fail = match state.queued.iter().position(|queued| *queued == point) {
+            Some(position) => {
+                state.queued.remove(position);
+                true
+            }
+            None => {
+                let rate = state.config.rate_of(point);
+                rate > 0.0 && state.rng.chance(rate)
+            }
+        };
+        if !fail {
+            return Ok(());
+        }
+        state.injected += 1;
+        Err(DatabaseError::IoError(io::Error::other(format!("注入的故障: {}", point))))
+    }
+}
+
+impl StorageBackend for FaultyBackend {
+    fn append(&self, entry: &LogEntry) -> Result<()> {
+        self.check(FaultPoint::Append)?;
+        self.inner.append(entry)
+    }
+
+    fn read_log(&self, from_id: u64) -> Result<Vec<LogEntry>> {
+        self.check(FaultPoint::ReadLog)?;
+        self.inner.read_log(from_id)
+    }
+
+    fn save_snapshot(&self, snapshot: &Snapshot) -> Result<()> {
+        self.check(FaultPoint::SaveSnapshot)?;
+        self.inner.save_snapshot(snapshot)
+    }
+
+    fn load_snapshot(&self) -> Result<Option<Snapshot>> {
+        self.check(FaultPoint::LoadSnapshot)?;
+        self.inner.load_snapshot()
+    }
+}
+
+/// 由种子决定时间、随机数与故障的引擎，数据保存在 `MemoryBackend` 中
+pub struct SimulatedEngine {
+    seed: u64,
+    engine: DatabaseEngine,
+    clock: VirtualClock,
+    rng: SimRng,
+    backend: FaultyBackend,
+}
+
+impl SimulatedEngine {
+    /// 虚拟时钟的起始时间
+    pub fn start_time() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap()
+    }
+
+    pub async fn new(seed: u64) -> Result<Self> {
+        let clock = VirtualClock::new(Self::start_time());
+        // 故障与测试使用的随机数来自同一个种子的不同序列
+        let backend = FaultyBackend::new(Arc::new(MemoryBackend::new()), SimRng::new(seed).next_u64());
+        let engine = clock::with_clock(Arc::new(clock.clone()), DatabaseEngine::with_backend(Arc::new(backend.clone()))).await?;
+        Ok(Self { seed, engine, clock, rng: SimRng::new(seed), backend })
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// 被模拟的引擎；直接调用时读取系统时间，需要虚拟时间时放在 `scope` 中调用
+    pub fn engine(&self) -> &DatabaseEngine {
+        &self.engine
+    }
+
+    pub fn clock(&self) -> &VirtualClock {
+        &self.clock
+    }
+
+    /// 供测试生成操作序列的随机数
+    pub fn rng(&mut self) -> &mut SimRng {
+        &mut self.rng
+    }
+
+    pub fn backend(&self) -> &FaultyBackend {
+        &self.backend
+    }
+
+    /// 在虚拟时钟下执行 `future`，其中生成的任务需要用 `clock::with_clock` 继续使用该时钟
+    pub async fn scope<F: Future>(&self, future: F) -> F::Output {
+        clock::with_clock(Arc::new(self.clock.clone()), future).await
+    }
+
+    pub async fn execute_sql(&self, sql: &str) -> Result<Vec<QueryResult>> {
+        self.scope(self.engine.execute_sql(sql)).await
+    }
+
+    /// 把当前状态保存为后端中的快照
+    pub async fn checkpoint(&self) -> Result<()> {
+        self.scope(self.engine.save_to_disk()).await
+    }
+
+    /// 模拟进程崩溃：丢弃引擎而不写检查点，再从后端中的快照与日志重新打开
+    ///
+    /// 重新打开时同样可能被注入故障，此时返回错误，引擎保持崩溃前的状态，可以再次调用。
+    pub async fn crash(&mut self) -> Result<()> {
+        let backend: Arc<dyn StorageBackend> = Arc::new(self.backend.clone());
+        self.engine = self.scope(DatabaseEngine::with_backend(backend)).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Value;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_simulated_crash_recovery() {
+        let mut rng = SimRng::new(7);
+        let sequence: Vec<u64> = (0..4).map(|_| rng.next_u64()).collect();
+        let mut again = SimRng::new(7);
+        assert_eq!((0..4).map(|_| again.next_u64()).collect::<Vec<_>>(), sequence);
+
+        let mut sim = SimulatedEngine::new(7).await.unwrap();
+        sim.execute_sql("CREATE TABLE kv (k INTEGER PRIMARY KEY, v TEXT)").await.unwrap();
+        sim.clock().advance(chrono::Duration::seconds(30));
+        sim.execute_sql("INSERT INTO kv VALUES (1, 'a')").await.unwrap();
+        sim.checkpoint().await.unwrap();
+        sim.execute_sql("INSERT INTO kv VALUES (2, 'b')").await.unwrap();
+
+        // 写日志失败的插入返回错误，崩溃后不可见；返回成功的写入都保留
+        sim.backend().fail_next(FaultPoint::Append);
+        assert!(sim.execute_sql("INSERT INTO kv VALUES (3, 'c')").await.is_err());
+        assert_eq!(sim.backend().injected(), 1);
+        sim.backend().fail_next(FaultPoint::LoadSnapshot);
+        assert!(sim.crash().await.is_err());
+        sim.crash().await.unwrap();
+
+        let result = &sim.execute_sql("SELECT * FROM kv ORDER BY k").await.unwrap()[0];
+        let keys: Vec<Value> = result.rows.iter().map(|row| row.get("k").cloned().unwrap()).collect();
+        assert_eq!(keys, vec![Value::Integer(1), Value::Integer(2)]);
+
+        // 快照之后只有插入 2 的日志，时间戳来自虚拟时钟
+        let log = sim.backend().read_log(0).unwrap();
+        assert_eq!(log.len(), 1);
+        assert!(log.iter().all(|entry| entry.timestamp == SimulatedEngine::start_time() + chrono::Duration::seconds(30)));
+    }
+}
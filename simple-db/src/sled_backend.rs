@@ -0,0 +1,173 @@
+use std::path::Path;
+
+use crate::backend::StorageBackend;
+use crate::error::{DatabaseError, Result};
+use crate::shared::Shared;
+use crate::storage::{LogEntry, Snapshot, TableFile};
+use crate::types::Row;
+
+/// 日志条目所在的树，键为大端序的日志 ID，值为编码后的日志行
+const LOG_TREE: &str = "log";
+/// 保存快照清单的键，位于默认树中
+const SNAPSHOT_KEY: &str = "snapshot";
+/// 每次保存快照时各表的行写入以它为前缀、带代号的新树中
+const TABLE_TREE_PREFIX: &str = "table/";
+
+/// 把快照与日志保存在 sled 中的持久化后端
+///
+/// 每张表的行保存在独立的树中，键为行在表中的位置，加载时按原来的顺序读回；快照清单只记录表结构与树名。
+/// 保存快照时先把行写入新一代的树，再原子替换清单，最后删除旧的树与已被覆盖的日志，中途崩溃不会损坏已有快照。
+/// 列式布局与分区表不逐行拆分，整张表保存在清单中。
+pub struct SledBackend {
+    db: sled::Db,
+    log: sled::Tree,
+}
+
+impl SledBackend {
+    /// 打开 `path` 下的 sled 数据库，不存在时创建
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let db = sled::open(path).map_err(sled_error)?;
+        let log = db.open_tree(LOG_TREE).map_err(sled_error)?;
+        Ok(Self { db, log })
+    }
+
+    /// 下一代表数据树的代号，比现存的所有代号都大
+    fn next_generation(&self) -> u64 {
+        self.db.tree_names().iter()
+            .filter_map(|name| table_tree_generation(name))
+            .max()
+            .map_or(1, |generation| generation + 1)
+    }
+}
+
+impl StorageBackend for SledBackend {
+    fn append(&self, entry: &LogEntry) -> Result<()> {
+        self.log.insert(entry.id.to_be_bytes(), entry.encode()?.into_bytes()).map_err(sled_error)?;
+        self.log.flush().map_err(sled_error)?;
+        Ok(())
+    }
+
+    fn read_log(&self, from_id: u64) -> Result<Vec<LogEntry>> {
+        let mut entries = Vec::new();
+        for item in self.log.range((from_id + 1).to_be_bytes()..) {
+            let (_, value) = item.map_err(sled_error)?;
+            let line = String::from_utf8_lossy(&value);
+            let entry = LogEntry::decode(&line)
+                .map_err(|reason| DatabaseError::corrupt_log(entries.len() + 1, reason))?;
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
+    fn save_snapshot(&self, snapshot: &Snapshot) -> Result<()> {
+        let generation = self.next_generation();
+        let mut manifest = Snapshot::new(Vec::new(), snapshot.last_log_id);
+        manifest.timestamp = snapshot.timestamp;
+        manifest.procedures = snapshot.procedures.clone();
+        manifest.sequences = snapshot.sequences.clone();
+        manifest.prepared = snapshot.prepared.clone();
+        manifest.committed_transactions = snapshot.committed_transactions.clone();
+        manifest.unlogged_tables = snapshot.unlogged_tables.clone();
+        manifest.validation_mode = snapshot.validation_mode;
+        manifest.backup_log_id = snapshot.backup_log_id;
+        for table in &snapshot.tables {
+            let mut table = table.clone();
+            if table.partition_spec.is_none() && !table.rows.is_empty() {
+                let tree_name = format!("{}{}/{}", TABLE_TREE_PREFIX, generation, table.name);
+                let tree = self.db.open_tree(&tree_name).map_err(sled_error)?;
+                for (position, row) in table.rows.iter().enumerate() {
+                    tree.insert((position as u64).to_be_bytes(), serde_json::to_vec(row)?).map_err(sled_error)?;
+                }
+                table.rows = Shared::new(Vec::new());
+                manifest.table_files.push(TableFile { name: table.name.clone(), file: tree_name });
+            }
+            manifest.tables.push(table);
+        }
+        self.db.flush().map_err(sled_error)?;
+
+        // 清单替换后新快照才生效
+        self.db.insert(SNAPSHOT_KEY, serde_json::to_vec(&manifest)?).map_err(sled_error)?;
+        self.db.flush().map_err(sled_error)?;
+
+        let referenced: Vec<&str> = manifest.table_files.iter().map(|table_file| table_file.file.as_str()).collect();
+        for name in self.db.tree_names() {
+            if table_tree_generation(&name).is_some() && !referenced.contains(&String::from_utf8_lossy(&name).as_ref()) {
+                self.db.drop_tree(name).map_err(sled_error)?;
+            }
+        }
+        for item in self.log.range(..=snapshot.last_log_id.to_be_bytes()) {
+            let (key, _) = item.map_err(sled_error)?;
+            self.log.remove(key).map_err(sled_error)?;
+        }
+        self.log.flush().map_err(sled_error)?;
+        Ok(())
+    }
+
+    fn load_snapshot(&self) -> Result<Option<Snapshot>> {
+        let Some(manifest) = self.db.get(SNAPSHOT_KEY).map_err(sled_error)? else {
+            return Ok(None);
+        };
+        let mut snapshot: Snapshot = serde_json::from_slice(&manifest)?;
+        for table_file in std::mem::take(&mut snapshot.table_files) {
+            let tree = self.db.open_tree(&table_file.file).map_err(sled_error)?;
+            let rows = tree.iter()
+                .map(|item| {
+                    let (_, value) = item.map_err(sled_error)?;
+                    Ok(serde_json::from_slice::<Row>(&value)?)
+                })
+                .collect::<Result<Vec<Row>>>()?;
+            let table = snapshot.tables.iter_mut()
+                .find(|table| table.name == table_file.name)
+                .ok_or_else(|| DatabaseError::TableNotFound(table_file.name.clone()))?;
+            table.rows = Shared::new(rows);
+        }
+        Ok(Some(snapshot))
+    }
+}
+
+/// 表数据树名中的代号，其他树返回 None
+fn table_tree_generation(name: &[u8]) -> Option<u64> {
+    let name = std::str::from_utf8(name).ok()?;
+    let (generation, _) = name.strip_prefix(TABLE_TREE_PREFIX)?.split_once('/')?;
+    generation.parse().ok()
+}
+
+fn sled_error(e: sled::Error) -> DatabaseError {
+    DatabaseError::other(format!("sled 错误: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::DatabaseEngine;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_sled_backend_recovers_tables_and_log() {
+        let dir = std::env::temp_dir().join(format!("simple_db_sled_{}", uuid::Uuid::new_v4()));
+        {
+            let backend = Arc::new(SledBackend::open(&dir).unwrap());
+            let engine = DatabaseEngine::with_backend(backend).await.unwrap();
+            engine.execute_sql("
+                CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT);
+                INSERT INTO users VALUES (1, 'a'), (2, 'b'), (3, 'c');
+            ").await.unwrap();
+            engine.save_to_disk().await.unwrap();
+            engine.execute_sql("DELETE FROM users WHERE id = 2; INSERT INTO users VALUES (4, 'd');").await.unwrap();
+            // 再次保存快照后上一代的表数据树被删除
+            engine.save_to_disk().await.unwrap();
+            engine.execute_sql("INSERT INTO users VALUES (5, 'e')").await.unwrap();
+        }
+
+        let backend = Arc::new(SledBackend::open(&dir).unwrap());
+        assert_eq!(backend.db.tree_names().iter().filter(|name| table_tree_generation(name).is_some()).count(), 1);
+        assert_eq!(backend.read_log(0).unwrap().len(), 1);
+        let engine = DatabaseEngine::with_backend(backend).await.unwrap();
+        let result = engine.execute_sql("SELECT * FROM users").await.unwrap();
+        let ids: Vec<_> = result[0].rows.iter().filter_map(|row| row.get("id").cloned()).collect();
+        assert_eq!(ids, [1, 3, 4, 5].map(crate::types::Value::Integer));
+
+        drop(engine);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
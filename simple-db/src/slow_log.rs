@@ -0,0 +1,130 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::query::QueryType;
+
+/// 慢查询日志默认保留的条目数
+pub const DEFAULT_SLOW_LOG_CAPACITY: usize = 128;
+
+/// 一条慢查询记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlowQuery {
+    /// 查询的结构，字面量替换为 `?`，例如 `SELECT * FROM users WHERE age > ?`
+    pub shape: String,
+    pub query_type: QueryType,
+    pub table_name: String,
+    pub duration: Duration,
+    pub rows_scanned: usize,
+    /// 返回或影响的行数，与 `QueryResult::affected_rows` 一致
+    pub rows_returned: usize,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// 慢查询日志：耗时达到阈值的查询写入固定容量的环形缓冲区，满时丢弃最旧的记录
+#[derive(Debug, Clone)]
+pub struct SlowQueryLog {
+    threshold: Option<Duration>,
+    capacity: usize,
+    entries: VecDeque<SlowQuery>,
+}
+
+impl SlowQueryLog {
+    /// 创建未启用的慢查询日志
+    pub fn new() -> Self {
+        Self {
+            threshold: None,
+            capacity: DEFAULT_SLOW_LOG_CAPACITY,
+            entries: VecDeque::new(),
+        }
+    }
+
+    /// 设置阈值，None 表示关闭慢查询日志
+    pub fn set_threshold(&mut self, threshold: Option<Duration>) {
+        self.threshold = threshold;
+    }
+
+    pub fn threshold(&self) -> Option<Duration> {
+        self.threshold
+    }
+
+    /// 设置保留的条目数，超出的旧记录立即丢弃
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.trim();
+    }
+
+    /// 查询耗时是否达到阈值
+    pub fn is_slow(&self, duration: Duration) -> bool {
+        self.threshold.is_some_and(|threshold| duration >= threshold)
+    }
+
+    /// 记录查询，未达到阈值时忽略；返回是否记录
+    pub fn record(&mut self, entry: SlowQuery) -> bool {
+        if !self.is_slow(entry.duration) {
+            return false;
+        }
+        self.entries.push_back(entry);
+        self.trim();
+        true
+    }
+
+    /// 按记录顺序返回保留的慢查询
+    pub fn entries(&self) -> Vec<SlowQuery> {
+        self.entries.iter().cloned().collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    fn trim(&mut self) {
+        while self.entries.len() > self.capacity {
+            self.entries.pop_front();
+        }
+    }
+}
+
+impl Default for SlowQueryLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(shape: &str, millis: u64) -> SlowQuery {
+        SlowQuery {
+            shape: shape.to_string(),
+            query_type: QueryType::Select,
+            table_name: "t".to_string(),
+            duration: Duration::from_millis(millis),
+            rows_scanned: 0,
+            rows_returned: 0,
+            recorded_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_threshold_and_ring_buffer() {
+        let mut log = SlowQueryLog::new();
+        assert!(!log.record(entry("disabled", 1_000)));
+
+        log.set_threshold(Some(Duration::from_millis(10)));
+        log.set_capacity(2);
+        assert!(!log.record(entry("fast", 9)));
+        for shape in ["a", "b", "c"] {
+            assert!(log.record(entry(shape, 10)));
+        }
+        let shapes: Vec<String> = log.entries().into_iter().map(|entry| entry.shape).collect();
+        assert_eq!(shapes, vec!["b", "c"]);
+
+        log.set_capacity(1);
+        assert_eq!(log.entries()[0].shape, "c");
+        log.clear();
+        assert!(log.entries().is_empty());
+    }
+}
@@ -0,0 +1,115 @@
+use std::fs;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::Result;
+use crate::types::Row;
+
+/// 溢出策略：表在内存中的占用超过阈值时，把最旧的行写入磁盘段文件
+#[derive(Debug, Clone)]
+pub struct SpillPolicy {
+    /// 内存占用阈值（字节），超过后触发溢出
+    pub threshold_bytes: usize,
+    /// 段文件目录
+    pub dir: PathBuf,
+}
+
+impl SpillPolicy {
+    pub fn new<P: Into<PathBuf>>(threshold_bytes: usize, dir: P) -> Self {
+        Self {
+            threshold_bytes,
+            dir: dir.into(),
+        }
+    }
+
+    /// 溢出后内存中保留的目标占用，留出余量避免每次写入都触发溢出
+    pub fn target_bytes(&self) -> usize {
+        self.threshold_bytes / 2
+    }
+}
+
+/// 磁盘上的一个只读行段
+///
+/// 段文件在最后一个引用释放时删除，因此查询持有的表副本在段被换入后仍可读取。
+#[derive(Debug)]
+pub struct SpillSegment {
+    path: PathBuf,
+    row_count: usize,
+    file_size: u64,
+}
+
+impl SpillSegment {
+    /// 将行按 JSON Lines 写入新的段文件
+    pub fn write(dir: &Path, table_name: &str, rows: &[Row]) -> Result<Self> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{}-{}.seg", table_name, uuid::Uuid::new_v4()));
+
+        let file = fs::File::create(&path)?;
+        let mut writer = BufWriter::new(file);
+        for row in rows {
+            serde_json::to_writer(&mut writer, row)?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+
+        let file_size = fs::metadata(&path)?.len();
+        Ok(Self {
+            path,
+            row_count: rows.len(),
+            file_size,
+        })
+    }
+
+    pub fn read(&self) -> Result<Vec<Row>> {
+        let reader = BufReader::new(fs::File::open(&self.path)?);
+        let mut rows = Vec::with_capacity(self.row_count);
+        for line in reader.lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                rows.push(serde_json::from_str(&line)?);
+            }
+        }
+        Ok(rows)
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.row_count
+    }
+
+    pub fn file_size(&self) -> u64 {
+        self.file_size
+    }
+}
+
+impl Drop for SpillSegment {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Value;
+
+    #[test]
+    fn test_segment_round_trip_and_cleanup() {
+        let dir = std::env::temp_dir().join(format!("simple-db-spill-{}", uuid::Uuid::new_v4()));
+        let rows: Vec<Row> = (0..3).map(|i| {
+            let mut row = Row::new();
+            row.set("id", Value::Integer(i));
+            row
+        }).collect();
+
+        let segment = SpillSegment::write(&dir, "events", &rows).unwrap();
+        let path = segment.path.clone();
+        let loaded = segment.read().unwrap();
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(loaded[2].id, rows[2].id);
+        assert!(path.exists());
+
+        drop(segment);
+        assert!(!path.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,1246 @@
+use std::collections::HashMap;
+
+use crate::binary;
+use crate::collation::Collation;
+use crate::columnar::TableLayout;
+use crate::error::{DatabaseError, Result};
+use crate::expression::{BinaryOperator, Expr, Projection};
+use crate::functions;
+use crate::import::CopyOptions;
+use crate::partition::PartitionSpec;
+use crate::json_path::JsonPath;
+use crate::query::{ComparisonOperator, Condition, JsonExtract, Query, QueryBuilder, QueryType};
+use crate::types::{
+    ColumnDefinition, DataType, Row, Schema, TableOptions, Value, CREATED_AT_COLUMN, ID_COLUMN, UPDATED_AT_COLUMN, VERSION_COLUMN,
+};
+
+/// 解析后的 SQL 语句
+#[derive(Debug, Clone)]
+pub enum Statement {
+    CreateTable {
+        name: String,
+        schema: Schema,
+        options: TableOptions,
+    },
+    /// `CREATE TABLE t AS SELECT ...`，表结构由查询结果推断
+    CreateTableAs {
+        name: String,
+        query: Query,
+    },
+    DropTable {
+        name: String,
+    },
+    /// 清空表的所有行
+    Truncate {
+        name: String,
+    },
+    CreateDatabase {
+        name: String,
+    },
+    DropDatabase {
+        name: String,
+    },
+    /// 收集统计信息，未指定表时分析所有表
+    Analyze {
+        table: Option<String>,
+    },
+    CreateSequence {
+        name: String,
+        start: i64,
+        step: i64,
+    },
+    DropSequence {
+        name: String,
+    },
+    /// 多行 INSERT；`columns` 为空时按表结构的列顺序对应，常量值在解析时已求值为字面量
+    Insert {
+        table: String,
+        columns: Vec<String>,
+        rows: Vec<Vec<Expr>>,
+    },
+    /// `INSERT INTO t [(col, ...)] SELECT ...`，查询结果的列按位置对应；`columns` 为空时按表结构的列顺序
+    InsertSelect {
+        table: String,
+        columns: Vec<String>,
+        query: Query,
+    },
+    /// SELECT / COUNT / UPDATE / DELETE
+    Query(Query),
+    /// 从客户端读取数据批量导入，数据由 shell 等客户端在语句之后提供
+    Copy {
+        table: String,
+        options: CopyOptions,
+    },
+    /// 列出事务锁的持有者与等待者
+    ShowLocks,
+}
+
+/// 解析以分号分隔的 SQL 脚本
+///
+/// 支持的语法：
+/// - `CREATE TABLE t (col TYPE [PRIMARY KEY] [NOT NULL] [UNIQUE] [DEFAULT v | DEFAULT expr] [COLLATE NOCASE|LOCALE], ...)
+///   [LAYOUT COLUMNAR] [PARTITION BY HASH (col) BUCKETS n | PARTITION BY RANGE (col) BOUNDS (v, ...)]
+///   [WITH (UNLOGGED, TTL seconds, COMPRESSION LZ4|NONE, MAX_ROWS n)]`
+/// - `CREATE TABLE t AS SELECT ...`
+/// - `DROP TABLE t`、`TRUNCATE [TABLE] t`
+/// - `CREATE DATABASE d`、`DROP DATABASE d`
+/// - `CREATE SEQUENCE s [START [WITH] n] [INCREMENT [BY] n]`、`DROP SEQUENCE s`
+/// - `ANALYZE [t]`
+/// - `INSERT INTO t [(col, ...)] VALUES (v, ...), ...`、`INSERT INTO t [(col, ...)] SELECT ...`
+/// - `SELECT *[, expr AS alias, ...] | COUNT(*) FROM [schema.]t [WHERE ...] [ORDER BY col [ASC|DESC], ...] [LIMIT n] [OFFSET n]`
+/// - `UPDATE t SET col = v, ... [WHERE ...] [ORDER BY ...] [LIMIT n]`
+/// - `DELETE FROM t [WHERE ...] [ORDER BY ...] [LIMIT n]`
+/// - `COPY t [(col, ...)] FROM STDIN [WITH (FORMAT csv|tsv, HEADER [TRUE|FALSE], DELIMITER 'c', NULL 's')]`
+/// - `SHOW LOCKS`
+///
+/// WHERE 为以 AND 连接的 `expr op expr`、`expr IS [NOT] NULL`、`expr IN (v, ...)`、`expr LIKE 'p'`、`expr STARTS WITH v`。
+/// 表达式由列、字面量、`+ - * / %`、字符串拼接 `||`、括号与函数调用组成，
+/// 例如 `LOWER(name) = 'bob'`、`price * qty > 100`、`json_extract(meta, '$.age') >= 18`。
+/// 日期函数有 `NOW()`、`CURRENT_DATE()`、`DATE_TRUNC('month', ts)`、`EXTRACT(YEAR FROM ts)`、
+/// `AGE(ts[, ts])`（秒数）与 `DATE_ADD(ts, n, 'unit')`。
+///
+/// 数组列声明为 `TYPE[]`，字面量写作 `ARRAY[v, ...]`，支持 `@>`（包含）、`<@`（被包含）与 `&&`（有交集）。
+///
+/// 时间间隔字面量写作 `INTERVAL '90 days'`、`INTERVAL '1 year 2 months'`、`INTERVAL '2 hours 30 minutes'`，
+/// 可以与日期、日期时间加减，例如 `hire_date + INTERVAL '90 days'`、`created_at > NOW() - INTERVAL '30 days'`。
+///
+/// INSERT 的值可以是不引用列的表达式；`NEXTVAL('s')` 取序列的下一个值，`CURRVAL('s')` 取最近一次取得的值，
+/// 两者也可以用作列的默认值，查询中只能使用 `CURRVAL`。
+///
+/// 二进制字面量写作 `X'DEADBEEF'` 或 `B64'3q2+7w=='`，与二进制列比较的字符串也可以写作 `'\xDEADBEEF'` 或 `'base64:3q2+7w=='`；
+/// `OCTET_LENGTH`、`TO_HEX`、`FROM_HEX`、`TO_BASE64`、`FROM_BASE64` 处理字节。
+///
+/// 坐标列声明为 `POINT`，字面量写作 `POINT(lat, lon)`；`col WITHIN 500 OF POINT(lat, lon)` 选出 500 米之内的行，
+/// `DISTANCE(a, b)` 返回两点间的距离（米）。
+///
+/// 语法错误返回 `DatabaseError::SyntaxError`，带出错处的字符位置。
+pub fn parse_script(sql: &str) -> Result<Vec<Statement>> {
+    let mut parser = Parser::new(sql)?;
+    let mut statements = Vec::new();
+    loop {
+        while parser.eat_symbol(";") {}
+        if parser.at_end() {
+            break;
+        }
+        statements.push(parser.statement().map_err(|e| parser.locate(e))?);
+        if !parser.at_end() {
+            parser.expect_symbol(";").map_err(|e| parser.locate(e))?;
+        }
+    }
+    Ok(statements)
+}
+
+/// 解析单个表达式，例如命令行中的动态默认值 `NOW()`
+pub fn parse_expression(sql: &str) -> Result<Expr> {
+    let mut parser = Parser::new(sql)?;
+    let expr = parser.expression().map_err(|e| parser.locate(e))?;
+    if !parser.at_end() {
+        return parser.unexpected("表达式结束").map_err(|e| parser.locate(e));
+    }
+    Ok(expr)
+}
+
+/// 输入是否以不在引号或注释中的分号结束，交互式输入据此判断语句是否输入完毕
+pub fn is_complete(sql: &str) -> bool {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut quote = None;
+    let mut last = None;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match quote {
+            // 连续两个引号表示引号本身，相当于关闭后立即重新打开
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '-' && chars.get(i + 1) == Some(&'-') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None => {}
+        }
+        if !c.is_whitespace() {
+            last = Some(c);
+        }
+        i += 1;
+    }
+    quote.is_none() && last == Some(';')
+}
+
+/// 显示 SQL 文本中第 `position` 个字符所在的行，并在下一行用 `^` 标出该字符
+pub fn caret(sql: &str, position: usize) -> String {
+    let chars: Vec<char> = sql.chars().collect();
+    let position = position.min(chars.len());
+    let start = chars[..position].iter().rposition(|&c| c == '\n').map_or(0, |newline| newline + 1);
+    let end = chars[position..].iter().position(|&c| c == '\n').map_or(chars.len(), |offset| position + offset);
+    let line: String = chars[start..end].iter().collect();
+    // 制表符原样保留，使 ^ 与终端中显示的列对齐
+    let padding: String = chars[start..position].iter().map(|&c| if c == '\t' { '\t' } else { ' ' }).collect();
+    format!("{}\n{}^", line, padding)
+}
+
+/// 解析单条 SQL 语句
+pub fn parse_statement(sql: &str) -> Result<Statement> {
+    let mut statements = parse_script(sql)?;
+    match statements.len() {
+        1 => Ok(statements.remove(0)),
+        n => Err(DatabaseError::parse_error(format!("需要一条语句，实际 {} 条", n))),
+    }
+}
+
+/// SQL 关键字，不能直接用作标识符
+pub const KEYWORDS: &[&str] = &[
+    "ANALYZE", "AND", "ARRAY", "AS", "ASC", "BOUNDS", "BUCKETS", "BY", "COLLATE", "COPY", "COUNT", "CREATE", "DEFAULT", "DELETE", "DESC", "DROP",
+    "FALSE", "FROM", "HASH", "IN", "INSERT", "INTO", "IS", "KEY", "LAYOUT", "LIKE", "LIMIT", "NOT",
+    "NULL", "OFFSET", "ORDER", "PARTITION", "PRIMARY", "RANGE", "SELECT", "SET", "SHOW", "TABLE", "TRUE",
+    "UNIQUE", "UPDATE", "VALUES", "WHERE",
+];
+
+/// 标识符的最大长度（字符数）
+pub const MAX_IDENTIFIER_LENGTH: usize = 128;
+
+/// 检查表名、列名、序列名等标识符：不能为空、超过最大长度、含有控制字符或首尾有空白
+///
+/// 关键字以及含有空格等字符的名称是允许的，在 SQL 中写作双引号括起的标识符，见 [`quote_identifier`]。
+pub fn validate_identifier(name: &str) -> Result<()> {
+    let reason = if name.is_empty() {
+        "不能为空".to_string()
+    } else if name.chars().count() > MAX_IDENTIFIER_LENGTH {
+        format!("超过 {} 个字符", MAX_IDENTIFIER_LENGTH)
+    } else if name.chars().any(char::is_control) {
+        "不能包含控制字符".to_string()
+    } else if name.trim() != name {
+        "首尾不能有空白".to_string()
+    } else {
+        return Ok(());
+    };
+    Err(DatabaseError::invalid_identifier(name, reason))
+}
+
+/// 检查表名，表名还不能含有分隔模式名的 `.`
+pub fn validate_table_name(name: &str) -> Result<()> {
+    validate_identifier(name)?;
+    if name.contains('.') {
+        return Err(DatabaseError::invalid_identifier(name, "表名不能包含 '.'"));
+    }
+    Ok(())
+}
+
+/// 检查新建表的表名与列名，列名不能重复，也不能与伪列同名
+pub fn validate_table(name: &str, schema: &Schema) -> Result<()> {
+    validate_table_name(name)?;
+    let mut seen = std::collections::HashSet::new();
+    for column in &schema.columns {
+        validate_identifier(&column.name)?;
+        if matches!(column.name.as_str(), ID_COLUMN | CREATED_AT_COLUMN | UPDATED_AT_COLUMN | VERSION_COLUMN) {
+            return Err(DatabaseError::invalid_identifier(&column.name, "与伪列同名"));
+        }
+        if !seen.insert(column.name.as_str()) {
+            return Err(DatabaseError::invalid_identifier(&column.name, "列名重复"));
+        }
+    }
+    Ok(())
+}
+
+/// 输出标识符，必要时加双引号
+pub fn quote_identifier(name: &str) -> String {
+    let plain = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !KEYWORDS.contains(&name.to_uppercase().as_str());
+    if plain {
+        name.to_string()
+    } else {
+        format!("\"{}\"", name.replace('"', "\"\""))
+    }
+}
+
+/// 输出值的 SQL 字面量，解析后按列类型可还原为原值
+pub fn literal(value: &Value) -> String {
+    let quote = |s: &str| format!("'{}'", s.replace('\'', "''"));
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::Float(f) if f.is_finite() => format!("{:?}", f),
+        Value::Float(f) => quote(&f.to_string()),
+        Value::Boolean(b) => if *b { "TRUE" } else { "FALSE" }.to_string(),
+        Value::Text(s) => quote(s),
+        Value::Date(_) | Value::Time(_) | Value::Uuid(_) => quote(&value.to_string()),
+        Value::DateTime(dt) => quote(&dt.format("%Y-%m-%dT%H:%M:%S%.f").to_string()),
+        Value::Json(json) => quote(&json.to_string()),
+        Value::Binary(bytes) => format!("X'{}'", binary::encode_hex(bytes)),
+        Value::Array(items) => format!("ARRAY[{}]", items.iter().map(literal).collect::<Vec<_>>().join(", ")),
+        Value::Point { lat, lon } => format!("POINT({:?}, {:?})", lat, lon),
+        Value::Interval(interval) => format!("INTERVAL {}", quote(&interval.to_string())),
+    }
+}
+
+/// 把解析出的字面量转换为列的声明类型，例如字符串转日期、整数转浮点数
+pub fn coerce_literal(value: Value, data_type: Option<&DataType>) -> Result<Value> {
+    match (value, data_type) {
+        (Value::Integer(i), Some(DataType::Float)) => Ok(Value::Float(i as f64)),
+        (Value::Array(items), Some(DataType::Array(element))) => items.into_iter()
+            .map(|item| coerce_literal(item, Some(element)))
+            .collect::<Result<Vec<_>>>()
+            .map(Value::Array),
+        (value, Some(data_type)) if !value.is_compatible_with(data_type) => value.coerce_to(data_type)
+            .ok_or_else(|| DatabaseError::type_mismatch(format!("无法将 {} 转换为 {}", literal(&value), data_type))),
+        (value, _) => Ok(value),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    QuotedIdent(String),
+    Number(String),
+    Str(String),
+    Blob(Vec<u8>),
+    Symbol(&'static str),
+}
+
+const SYMBOLS: &[&str] = &["@>", "<@", "&&", "||", "<=", ">=", "<>", "!=", "=", "<", ">", "(", ")", ",", ";", "*", "-", "+", "/", "%", ".", "[", "]"];
+
+/// 切分记号，同时返回每个记号起始处的字符位置
+fn tokenize(sql: &str) -> Result<Vec<(Token, usize)>> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '-' && chars.get(i + 1) == Some(&'-') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if (c == 'x' || c == 'X') && chars.get(i + 1) == Some(&'\'') {
+            let (hex, next) = quoted(&chars, i + 1, '\'')?;
+            let bytes = binary::decode_hex(&hex).map_err(|e| DatabaseError::syntax_error(i, e.to_string()))?;
+            tokens.push((Token::Blob(bytes), i));
+            i = next;
+        } else if chars[i..].starts_with(&['b', '6', '4', '\'']) || chars[i..].starts_with(&['B', '6', '4', '\'']) {
+            let (text, next) = quoted(&chars, i + 3, '\'')?;
+            let bytes = binary::decode_base64(&text).map_err(|e| DatabaseError::syntax_error(i, e.to_string()))?;
+            tokens.push((Token::Blob(bytes), i));
+            i = next;
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push((Token::Ident(chars[start..i].iter().collect()), start));
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '.'
+                || ((chars[i] == '+' || chars[i] == '-') && matches!(chars[i - 1], 'e' | 'E')))
+            {
+                i += 1;
+            }
+            tokens.push((Token::Number(chars[start..i].iter().collect()), start));
+        } else if c == '\'' {
+            let (text, next) = quoted(&chars, i, '\'')?;
+            tokens.push((Token::Str(text), i));
+            i = next;
+        } else if c == '"' {
+            let (name, next) = quoted(&chars, i, '"')?;
+            tokens.push((Token::QuotedIdent(name), i));
+            i = next;
+        } else {
+            let symbol = SYMBOLS.iter()
+                .find(|symbol| symbol.chars().enumerate().all(|(k, s)| chars.get(i + k) == Some(&s)))
+                .ok_or_else(|| DatabaseError::syntax_error(i, format!("无法识别的字符 '{}'", c)))?;
+            tokens.push((Token::Symbol(symbol), i));
+            i += symbol.len();
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// 读取从 `start` 处引号开始的内容，连续两个引号表示引号本身；返回内容与结束后的位置
+fn quoted(chars: &[char], start: usize, quote: char) -> Result<(String, usize)> {
+    let mut text = String::new();
+    let mut i = start + 1;
+    loop {
+        match chars.get(i) {
+            None => return Err(DatabaseError::syntax_error(start, "引号未闭合")),
+            Some(&c) if c == quote && chars.get(i + 1) == Some(&quote) => {
+                text.push(quote);
+                i += 2;
+            }
+            Some(&c) if c == quote => return Ok((text, i + 1)),
+            Some(&c) => {
+                text.push(c);
+                i += 1;
+            }
+        }
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    /// 每个记号起始处的字符位置
+    positions: Vec<usize>,
+    /// 输入结束处的位置（忽略末尾空白），语句意外结束时指向这里
+    end: usize,
+    pos: usize,
+}
+
+impl Parser {
+    fn new(sql: &str) -> Result<Self> {
+        let (tokens, positions) = tokenize(sql)?.into_iter().unzip();
+        Ok(Self { tokens, positions, end: sql.trim_end().chars().count(), pos: 0 })
+    }
+
+    /// 把解析错误定位到当前记号，转换为带位置的语法错误
+    fn locate(&self, error: DatabaseError) -> DatabaseError {
+        match error {
+            DatabaseError::ParseError(message) => {
+                DatabaseError::syntax_error(self.positions.get(self.pos).copied().unwrap_or(self.end), message)
+            }
+            other => other,
+        }
+    }
+
+    /// 回退到第 `pos` 个记号后返回错误，使 `locate` 指向该记号
+    fn fail_at<T>(&mut self, pos: usize, error: DatabaseError) -> Result<T> {
+        self.pos = pos;
+        Err(error)
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Result<Token> {
+        let token = self.tokens.get(self.pos).cloned()
+            .ok_or_else(|| DatabaseError::parse_error("语句意外结束"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn unexpected<T>(&self, expected: &str) -> Result<T> {
+        match self.peek() {
+            Some(token) => Err(DatabaseError::parse_error(format!("期望 {}，实际为 {:?}", expected, token))),
+            None => Err(DatabaseError::parse_error(format!("期望 {}，语句意外结束", expected))),
+        }
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let matched = self.is_keyword(keyword);
+        if matched {
+            self.pos += 1;
+        }
+        matched
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<()> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            self.unexpected(keyword)
+        }
+    }
+
+    fn eat_symbol(&mut self, symbol: &str) -> bool {
+        let matched = matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol);
+        if matched {
+            self.pos += 1;
+        }
+        matched
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<()> {
+        if self.eat_symbol(symbol) {
+            Ok(())
+        } else {
+            self.unexpected(&format!("'{}'", symbol))
+        }
+    }
+
+    /// 表名，可带模式前缀，例如 `information_schema.tables`
+    fn table_name(&mut self) -> Result<String> {
+        let name = self.identifier()?;
+        if self.eat_symbol(".") {
+            Ok(format!("{}.{}", name, self.identifier()?))
+        } else {
+            Ok(name)
+        }
+    }
+
+    fn identifier(&mut self) -> Result<String> {
+        match self.peek() {
+            Some(Token::Ident(name)) if !KEYWORDS.contains(&name.to_uppercase().as_str()) => {
+                let name = name.clone();
+                self.pos += 1;
+                Ok(name)
+            }
+            Some(Token::QuotedIdent(name)) if name.is_empty() => {
+                self.fail_at(self.pos, DatabaseError::invalid_identifier("", "不能为空"))
+            }
+            Some(Token::QuotedIdent(name)) => {
+                let name = name.clone();
+                self.pos += 1;
+                Ok(name)
+            }
+            _ => self.unexpected("标识符"),
+        }
+    }
+
+    fn unsigned(&mut self) -> Result<usize> {
+        match self.next()? {
+            Token::Number(n) => match n.parse() {
+                Ok(n) => Ok(n),
+                Err(_) => self.fail_at(self.pos - 1, DatabaseError::parse_error(format!("无效的非负整数: {}", n))),
+            },
+            token => self.fail_at(self.pos - 1, DatabaseError::parse_error(format!("期望非负整数，实际为 {:?}", token))),
+        }
+    }
+
+    fn literal(&mut self) -> Result<Value> {
+        let negative = self.eat_symbol("-");
+        match self.next()? {
+            Token::Number(n) => {
+                let text = if negative { format!("-{}", n) } else { n };
+                match text.parse::<i64>().map(Value::Integer).or_else(|_| text.parse::<f64>().map(Value::Float)) {
+                    Ok(value) => Ok(value),
+                    Err(_) => self.fail_at(self.pos - 1, DatabaseError::parse_error(format!("无效的数字: {}", text))),
+                }
+            }
+            _ if negative => self.fail_at(self.pos - 1, DatabaseError::parse_error("'-' 后应为数字")),
+            Token::Str(s) => Ok(Value::Text(s)),
+            Token::Blob(bytes) => Ok(Value::Binary(bytes)),
+            Token::Ident(word) if word.eq_ignore_ascii_case("NULL") => Ok(Value::Null),
+            Token::Ident(word) if word.eq_ignore_ascii_case("TRUE") => Ok(Value::Boolean(true)),
+            Token::Ident(word) if word.eq_ignore_ascii_case("FALSE") => Ok(Value::Boolean(false)),
+            Token::Ident(word) if word.eq_ignore_ascii_case("ARRAY") => {
+                self.expect_symbol("[")?;
+                let mut items = Vec::new();
+                if !self.eat_symbol("]") {
+                    items.push(self.literal()?);
+                    while self.eat_symbol(",") {
+                        items.push(self.literal()?);
+                    }
+                    self.expect_symbol("]")?;
+                }
+                Ok(Value::Array(items))
+            }
+            Token::Ident(word) if word.eq_ignore_ascii_case("INTERVAL") => {
+                let start = self.pos;
+                match self.next()? {
+                    Token::Str(text) => match text.parse() {
+                        Ok(interval) => Ok(Value::Interval(interval)),
+                        Err(e) => self.fail_at(start, e),
+                    },
+                    token => self.fail_at(start, DatabaseError::parse_error(format!("INTERVAL 后应为字符串，实际为 {:?}", token))),
+                }
+            }
+            Token::Ident(word) if word.eq_ignore_ascii_case("POINT") => {
+                let start = self.pos - 1;
+                let coordinates = self.list(Self::literal)?;
+                match functions::call("POINT", coordinates) {
+                    Ok(point) => Ok(point),
+                    Err(e) => self.fail_at(start, e),
+                }
+            }
+            token => self.fail_at(self.pos - 1, DatabaseError::parse_error(format!("期望字面量，实际为 {:?}", token))),
+        }
+    }
+
+    /// 括号中以逗号分隔的列表
+    fn list<T>(&mut self, mut item: impl FnMut(&mut Self) -> Result<T>) -> Result<Vec<T>> {
+        self.expect_symbol("(")?;
+        let mut items = vec![item(self)?];
+        while self.eat_symbol(",") {
+            items.push(item(self)?);
+        }
+        self.expect_symbol(")")?;
+        Ok(items)
+    }
+
+    fn statement(&mut self) -> Result<Statement> {
+        if self.eat_keyword("CREATE") {
+            if self.eat_keyword("DATABASE") {
+                return Ok(Statement::CreateDatabase { name: self.identifier()? });
+            }
+            if self.eat_keyword("SEQUENCE") {
+                return self.create_sequence();
+            }
+            self.create_table()
+        } else if self.eat_keyword("DROP") {
+            if self.eat_keyword("DATABASE") {
+                return Ok(Statement::DropDatabase { name: self.identifier()? });
+            }
+            if self.eat_keyword("SEQUENCE") {
+                return Ok(Statement::DropSequence { name: self.identifier()? });
+            }
+            self.expect_keyword("TABLE")?;
+            Ok(Statement::DropTable { name: self.identifier()? })
+        } else if self.eat_keyword("TRUNCATE") {
+            self.eat_keyword("TABLE");
+            Ok(Statement::Truncate { name: self.identifier()? })
+        } else if self.eat_keyword("ANALYZE") {
+            let table = if self.at_end() || matches!(self.peek(), Some(Token::Symbol(";"))) {
+                None
+            } else {
+                Some(self.identifier()?)
+            };
+            Ok(Statement::Analyze { table })
+        } else if self.eat_keyword("INSERT") {
+            self.insert()
+        } else if self.eat_keyword("COPY") {
+            self.copy()
+        } else if self.eat_keyword("SHOW") {
+            self.expect_keyword("LOCKS")?;
+            Ok(Statement::ShowLocks)
+        } else if self.eat_keyword("SELECT") {
+            self.select()
+        } else if self.eat_keyword("UPDATE") {
+            self.update()
+        } else if self.eat_keyword("DELETE") {
+            self.expect_keyword("FROM")?;
+            let builder = QueryBuilder::delete(&self.identifier()?);
+            let builder = self.where_clause(builder)?;
+            let builder = self.order_limit(builder)?;
+            Ok(Statement::Query(self.dry_run(builder)?.build()))
+        } else {
+            self.unexpected("CREATE、DROP、TRUNCATE、ANALYZE、INSERT、COPY、SHOW、SELECT、UPDATE 或 DELETE")
+        }
+    }
+
+    fn create_table(&mut self) -> Result<Statement> {
+        self.expect_keyword("TABLE")?;
+        let name = self.identifier()?;
+        if self.eat_keyword("AS") {
+            let start = self.pos;
+            self.expect_keyword("SELECT")?;
+            return match self.select()? {
+                Statement::Query(query) if matches!(query.query_type, QueryType::Select) => Ok(Statement::CreateTableAs { name, query }),
+                _ => self.fail_at(start, DatabaseError::parse_error("CREATE TABLE AS 只能使用 SELECT 查询")),
+            };
+        }
+        let columns = self.list(Self::column_definition)?;
+
+        let mut options = TableOptions::new();
+        if self.eat_keyword("LAYOUT") {
+            options = match self.identifier()?.to_uppercase().as_str() {
+                "ROW" => options.layout(TableLayout::Row),
+                "COLUMNAR" => options.layout(TableLayout::Columnar),
+                other => return self.fail_at(self.pos - 1, DatabaseError::parse_error(format!("未知存储布局: {}", other))),
+            };
+        }
+        if self.eat_keyword("PARTITION") {
+            self.expect_keyword("BY")?;
+            let spec = if self.eat_keyword("HASH") {
+                let column = self.list(Self::identifier)?.remove(0);
+                self.expect_keyword("BUCKETS")?;
+                PartitionSpec::hash(column, self.unsigned()?)
+            } else {
+                self.expect_keyword("RANGE")?;
+                let column = self.list(Self::identifier)?.remove(0);
+                self.expect_keyword("BOUNDS")?;
+                PartitionSpec::range(column, self.list(Self::literal)?)
+            };
+            options = options.partition(spec);
+        }
+        if self.eat_keyword("WITH") {
+            self.list(|parser| parser.table_option(&mut options))?;
+        }
+
+        Ok(Statement::CreateTable { name, schema: Schema::new(columns), options })
+    }
+
+    fn table_option(&mut self, options: &mut TableOptions) -> Result<()> {
+        let settings = &mut options.settings;
+        if self.eat_keyword("UNLOGGED") {
+            settings.unlogged = true;
+        } else if self.eat_keyword("TTL") {
+            settings.ttl = Some(std::time::Duration::from_secs(self.unsigned()? as u64));
+        } else if self.eat_keyword("COMPRESSION") {
+            settings.compressed = match self.identifier()?.to_uppercase().as_str() {
+                "LZ4" => true,
+                "NONE" => false,
+                other => return self.fail_at(self.pos - 1, DatabaseError::parse_error(format!("未知压缩方式: {}", other))),
+            };
+        } else if self.eat_keyword("MAX_ROWS") {
+            settings.max_rows = Some(self.unsigned()?);
+        } else {
+            return self.unexpected("UNLOGGED、TTL、COMPRESSION 或 MAX_ROWS");
+        }
+        Ok(())
+    }
+
+    fn create_sequence(&mut self) -> Result<Statement> {
+        let name = self.identifier()?;
+        let (mut start, mut step) = (1, 1);
+        loop {
+            if self.eat_keyword("START") {
+                self.eat_keyword("WITH");
+                start = self.integer()?;
+            } else if self.eat_keyword("INCREMENT") {
+                self.eat_keyword("BY");
+                step = self.integer()?;
+            } else {
+                break;
+            }
+        }
+        Ok(Statement::CreateSequence { name, start, step })
+    }
+
+    /// 可以带负号的整数
+    fn integer(&mut self) -> Result<i64> {
+        let start = self.pos;
+        match self.literal()? {
+            Value::Integer(i) => Ok(i),
+            other => self.fail_at(start, DatabaseError::parse_error(format!("期望整数，实际为 {}", other))),
+        }
+    }
+
+    fn column_definition(&mut self) -> Result<ColumnDefinition> {
+        let name = self.identifier()?;
+        let mut data_type = match self.next()? {
+            Token::Ident(type_name) => match type_name.parse::<DataType>() {
+                Ok(data_type) => data_type,
+                Err(e) => return self.fail_at(self.pos - 1, e),
+            },
+            token => return self.fail_at(self.pos - 1, DatabaseError::parse_error(format!("期望数据类型，实际为 {:?}", token))),
+        };
+        while self.eat_symbol("[") {
+            self.expect_symbol("]")?;
+            data_type = DataType::Array(Box::new(data_type));
+        }
+
+        let mut column = ColumnDefinition::new(name, data_type, false);
+        loop {
+            if self.eat_keyword("PRIMARY") {
+                self.expect_keyword("KEY")?;
+                column.primary_key = true;
+                column.nullable = false;
+                column.unique = true;
+            } else if self.eat_keyword("NOT") {
+                self.expect_keyword("NULL")?;
+                column.nullable = false;
+            } else if self.eat_keyword("NULL") {
+                column.nullable = true;
+            } else if self.eat_keyword("UNIQUE") {
+                column.unique = true;
+            } else if self.eat_keyword("DEFAULT") {
+                // 字面量在建表时确定，其他表达式（如 NOW()）在每次插入时求值
+                match self.expression()? {
+                    Expr::Literal(value) => column.default_value = Some(coerce_literal(value, Some(&column.data_type))?),
+                    expr => column.default_expr = Some(expr),
+                }
+            } else if self.eat_keyword("COLLATE") {
+                column.collation = match self.identifier()?.parse::<Collation>() {
+                    Ok(collation) => collation,
+                    Err(e) => return self.fail_at(self.pos - 1, e),
+                };
+            } else {
+                return Ok(column);
+            }
+        }
+    }
+
+    fn insert(&mut self) -> Result<Statement> {
+        self.expect_keyword("INTO")?;
+        let table = self.identifier()?;
+        let columns = if matches!(self.peek(), Some(Token::Symbol("("))) {
+            self.list(Self::identifier)?
+        } else {
+            Vec::new()
+        };
+
+        let start = self.pos;
+        if self.eat_keyword("SELECT") {
+            return match self.select()? {
+                Statement::Query(query) if matches!(query.query_type, QueryType::Select) => Ok(Statement::InsertSelect { table, columns, query }),
+                _ => self.fail_at(start, DatabaseError::parse_error("INSERT ... SELECT 只能使用 SELECT 查询")),
+            };
+        }
+        self.expect_keyword("VALUES")?;
+        let mut rows = vec![self.list(Self::insert_value)?];
+        while self.eat_symbol(",") {
+            rows.push(self.list(Self::insert_value)?);
+        }
+        Ok(Statement::Insert { table, columns, rows })
+    }
+
+    /// INSERT 的一个值：字面量，或者不引用列的表达式，例如 `NOW()`、`NEXTVAL('s')`
+    fn insert_value(&mut self) -> Result<Expr> {
+        let start = self.pos;
+        if let Ok(value) = self.literal() {
+            if matches!(self.peek(), Some(Token::Symbol("," | ")"))) {
+                return Ok(Expr::Literal(value));
+            }
+        }
+        self.pos = start;
+        let expr = self.expression()?;
+        if let Some(column) = expr.columns().first() {
+            return self.fail_at(start, DatabaseError::parse_error(format!("INSERT 的值不能引用列 {}", column)));
+        }
+        if expr.is_constant() {
+            return match expr.evaluate(&Row::new()) {
+                Ok(value) => Ok(Expr::Literal(value)),
+                Err(e) => self.fail_at(start, e),
+            };
+        }
+        Ok(expr)
+    }
+
+    fn copy(&mut self) -> Result<Statement> {
+        let table = self.identifier()?;
+        let mut options = CopyOptions::default();
+        if matches!(self.peek(), Some(Token::Symbol("("))) {
+            options.columns = self.list(Self::identifier)?;
+        }
+        self.expect_keyword("FROM")?;
+        self.expect_keyword("STDIN")?;
+
+        let with = self.eat_keyword("WITH");
+        if with || matches!(self.peek(), Some(Token::Symbol("("))) {
+            self.list(|parser| parser.copy_option(&mut options))?;
+        }
+        Ok(Statement::Copy { table, options })
+    }
+
+    fn copy_option(&mut self, options: &mut CopyOptions) -> Result<()> {
+        let start = self.pos;
+        let text = |parser: &mut Self| match parser.next()? {
+            Token::Str(text) => Ok(text),
+            token => parser.fail_at(parser.pos - 1, DatabaseError::parse_error(format!("期望字符串，实际为 {:?}", token))),
+        };
+        if self.eat_keyword("FORMAT") {
+            options.format = self.identifier()?.parse()?;
+        } else if self.eat_keyword("HEADER") {
+            options.header = !self.eat_keyword("FALSE");
+            self.eat_keyword("TRUE");
+        } else if self.eat_keyword("DELIMITER") {
+            let delimiter = text(self)?;
+            let mut chars = delimiter.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => options.delimiter = Some(c),
+                _ => return self.fail_at(start, DatabaseError::parse_error("DELIMITER 必须是单个字符")),
+            }
+        } else if self.eat_keyword("NULL") {
+            options.null = Some(text(self)?);
+        } else {
+            return self.unexpected("FORMAT、HEADER、DELIMITER 或 NULL");
+        }
+        Ok(())
+    }
+
+    fn select(&mut self) -> Result<Statement> {
+        let mut extracts = Vec::new();
+        let count = if self.eat_keyword("COUNT") {
+            self.expect_symbol("(")?;
+            self.expect_symbol("*")?;
+            self.expect_symbol(")")?;
+            true
+        } else {
+            self.expect_symbol("*")?;
+            while self.eat_symbol(",") {
+                let expr = self.expression()?;
+                self.expect_keyword("AS")?;
+                let alias = self.identifier()?;
+                match json_path_call(&expr)? {
+                    Some((column, path)) => extracts.push(SelectItem::Json(JsonExtract::new(alias, column, path))),
+                    None => extracts.push(SelectItem::Expr(Projection::new(alias, expr))),
+                }
+            }
+            false
+        };
+        self.expect_keyword("FROM")?;
+        let table = self.table_name()?;
+        let mut builder = if count { QueryBuilder::count(&table) } else { QueryBuilder::select(&table) };
+        for item in extracts {
+            builder = match item {
+                SelectItem::Json(extract) => builder.extract(extract),
+                SelectItem::Expr(projection) => builder.select_expr(&projection.alias, projection.expr),
+            };
+        }
+        let builder = self.where_clause(builder)?;
+        let mut builder = self.order_limit(builder)?;
+        if self.eat_keyword("OFFSET") {
+            builder = builder.offset(self.unsigned()?);
+        }
+        Ok(Statement::Query(builder.build()))
+    }
+
+    /// `[ORDER BY col [ASC|DESC], ...] [LIMIT n]`
+    fn order_limit(&mut self, mut builder: QueryBuilder) -> Result<QueryBuilder> {
+        if self.eat_keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            loop {
+                let column = self.identifier()?;
+                let ascending = !self.eat_keyword("DESC");
+                if ascending {
+                    self.eat_keyword("ASC");
+                }
+                builder = builder.order_by(&column, ascending);
+                if !self.eat_symbol(",") {
+                    break;
+                }
+            }
+        }
+        if self.eat_keyword("LIMIT") {
+            builder = builder.limit(self.unsigned()?);
+        }
+        Ok(builder)
+    }
+
+    fn update(&mut self) -> Result<Statement> {
+        let table = self.identifier()?;
+        self.expect_keyword("SET")?;
+        let mut data = HashMap::new();
+        loop {
+            let column = self.identifier()?;
+            self.expect_symbol("=")?;
+            data.insert(column, self.literal()?);
+            if !self.eat_symbol(",") {
+                break;
+            }
+        }
+        let builder = self.where_clause(QueryBuilder::update(&table, data))?;
+        let builder = self.order_limit(builder)?;
+        Ok(Statement::Query(self.dry_run(builder)?.build()))
+    }
+
+    /// `[DRY RUN]`，DRY 与 RUN 不是保留字
+    fn dry_run(&mut self, builder: QueryBuilder) -> Result<QueryBuilder> {
+        if self.eat_keyword("DRY") {
+            self.expect_keyword("RUN")?;
+            return Ok(builder.dry_run());
+        }
+        Ok(builder)
+    }
+
+    /// 表达式：`+`、`-`、`||` 的优先级低于 `*`、`/`、`%`，同级从左到右结合
+    fn expression(&mut self) -> Result<Expr> {
+        let mut expr = self.term()?;
+        loop {
+            let operator = if self.eat_symbol("+") {
+                BinaryOperator::Add
+            } else if self.eat_symbol("-") {
+                BinaryOperator::Subtract
+            } else if self.eat_symbol("||") {
+                BinaryOperator::Concat
+            } else {
+                return Ok(expr);
+            };
+            expr = Expr::binary(expr, operator, self.term()?);
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr> {
+        let mut expr = self.unary()?;
+        loop {
+            let operator = if self.eat_symbol("*") {
+                BinaryOperator::Multiply
+            } else if self.eat_symbol("/") {
+                BinaryOperator::Divide
+            } else if self.eat_symbol("%") {
+                BinaryOperator::Modulo
+            } else {
+                return Ok(expr);
+            };
+            expr = Expr::binary(expr, operator, self.unary()?);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if matches!(self.peek(), Some(Token::Symbol("-"))) {
+            // 负数字面量直接解析，保证 i64::MIN 这样的值不会在取负时溢出
+            if matches!(self.tokens.get(self.pos + 1), Some(Token::Number(_))) {
+                return Ok(Expr::Literal(self.literal()?));
+            }
+            self.pos += 1;
+            return Ok(Expr::Negate(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        match self.peek() {
+            Some(Token::Symbol("(")) => {
+                self.pos += 1;
+                let expr = self.expression()?;
+                self.expect_symbol(")")?;
+                return Ok(expr);
+            }
+            Some(Token::Number(_) | Token::Str(_) | Token::Blob(_)) => return Ok(Expr::Literal(self.literal()?)),
+            Some(Token::Ident(word)) if ["NULL", "TRUE", "FALSE", "ARRAY"].contains(&word.to_uppercase().as_str()) => {
+                return Ok(Expr::Literal(self.literal()?));
+            }
+            Some(Token::Ident(word)) if word.eq_ignore_ascii_case("INTERVAL") && matches!(self.tokens.get(self.pos + 1), Some(Token::Str(_))) => {
+                return Ok(Expr::Literal(self.literal()?));
+            }
+            _ => {}
+        }
+
+        let start = self.pos;
+        let name = self.identifier()?;
+        if !matches!(self.peek(), Some(Token::Symbol("("))) {
+            return Ok(Expr::Column(name));
+        }
+        // 标准写法 EXTRACT(field FROM expr)
+        let from = matches!(self.tokens.get(self.pos + 2), Some(Token::Ident(word)) if word.eq_ignore_ascii_case("FROM"));
+        if name.eq_ignore_ascii_case("EXTRACT") && from {
+            self.pos += 1;
+            let field = match self.next()? {
+                Token::Ident(field) => field.to_lowercase(),
+                token => return self.fail_at(self.pos - 1, DatabaseError::parse_error(format!("期望 EXTRACT 字段，实际为 {:?}", token))),
+            };
+            self.expect_keyword("FROM")?;
+            let value = self.expression()?;
+            self.expect_symbol(")")?;
+            return Ok(Expr::function(name, vec![Expr::Literal(Value::Text(field)), value]));
+        }
+        let args = if matches!(self.tokens.get(self.pos + 1), Some(Token::Symbol(")"))) {
+            self.pos += 2;
+            Vec::new()
+        } else {
+            self.list(Self::expression)?
+        };
+        if let Err(e) = functions::check(&name, args.len()) {
+            return self.fail_at(start, e);
+        }
+        Ok(Expr::function(name, args))
+    }
+
+    fn where_clause(&mut self, mut builder: QueryBuilder) -> Result<QueryBuilder> {
+        if !self.eat_keyword("WHERE") {
+            return Ok(builder);
+        }
+        loop {
+            let left = self.expression()?;
+            let (operator, right) = if self.eat_keyword("IS") {
+                let operator = if self.eat_keyword("NOT") { ComparisonOperator::IsNotNull } else { ComparisonOperator::IsNull };
+                self.expect_keyword("NULL")?;
+                (operator, Expr::Literal(Value::Null))
+            } else if self.eat_keyword("IN") {
+                let items = self.list(Self::literal)?;
+                let array = serde_json::Value::Array(items.iter().map(Value::to_json).collect());
+                (ComparisonOperator::In, Expr::Literal(Value::Json(array)))
+            } else if self.eat_keyword("LIKE") {
+                (ComparisonOperator::Like, Expr::Literal(self.literal()?))
+            } else if self.eat_keyword("STARTS") {
+                self.expect_keyword("WITH")?;
+                (ComparisonOperator::StartsWith, Expr::Literal(self.literal()?))
+            } else if self.eat_keyword("WITHIN") {
+                let meters = self.literal()?;
+                self.expect_keyword("OF")?;
+                let center = self.literal()?;
+                (ComparisonOperator::WithinRadius, Expr::Literal(Value::Array(vec![center, meters])))
+            } else {
+                let operator = match self.next()? {
+                    Token::Symbol("=") => ComparisonOperator::Equal,
+                    Token::Symbol("!=") | Token::Symbol("<>") => ComparisonOperator::NotEqual,
+                    Token::Symbol(">") => ComparisonOperator::GreaterThan,
+                    Token::Symbol(">=") => ComparisonOperator::GreaterThanOrEqual,
+                    Token::Symbol("<") => ComparisonOperator::LessThan,
+                    Token::Symbol("<=") => ComparisonOperator::LessThanOrEqual,
+                    Token::Symbol("@>") => ComparisonOperator::Contains,
+                    Token::Symbol("<@") => ComparisonOperator::ContainedBy,
+                    Token::Symbol("&&") => ComparisonOperator::Overlaps,
+                    token => return self.fail_at(self.pos - 1, DatabaseError::parse_error(format!("期望比较运算符，实际为 {:?}", token))),
+                };
+                (operator, self.expression()?)
+            };
+            builder = builder.condition(condition(left, operator, right)?);
+            if !self.eat_keyword("AND") {
+                return Ok(builder);
+            }
+        }
+    }
+}
+
+/// SELECT 列表中 `*` 之后的项
+enum SelectItem {
+    Json(JsonExtract),
+    Expr(Projection),
+}
+
+/// `json_extract(col, 'path')` 形式的调用，返回列名与路径
+fn json_path_call(expr: &Expr) -> Result<Option<(String, JsonPath)>> {
+    match expr {
+        Expr::Function { name, args } if name == "JSON_EXTRACT" => match args.as_slice() {
+            [Expr::Column(column), Expr::Literal(Value::Text(path))] => Ok(Some((column.clone(), JsonPath::parse(path)?))),
+            _ => Ok(None),
+        },
+        _ => Ok(None),
+    }
+}
+
+/// 由比较的两侧构造条件；`json_extract(col, 'path')` 与常量比较时使用 JSON 路径条件
+fn condition(left: Expr, operator: ComparisonOperator, right: Expr) -> Result<Condition> {
+    if let Some((column, path)) = json_path_call(&left)? {
+        if right.is_constant() {
+            return Ok(Condition::json_path(column, path, operator, right.evaluate(&Row::new())?));
+        }
+    }
+    Condition::expression(left, operator, right)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::QueryType;
+
+    #[test]
+    fn test_parse_create_and_insert() {
+        let statements = parse_script("
+            -- 用户表
+            CREATE TABLE \"user data\" (id INTEGER PRIMARY KEY, name TEXT NOT NULL DEFAULT 'x', score FLOAT)
+                PARTITION BY HASH (id) BUCKETS 4;
+            INSERT INTO \"user data\" (id, name, score) VALUES (1, 'O''Brien', -2.5), (2, NULL, 3);
+        ").unwrap();
+        assert_eq!(statements.len(), 2);
+
+        match &statements[0] {
+            Statement::CreateTable { name, schema, options } => {
+                assert_eq!(name, "user data");
+                assert!(schema.columns[0].primary_key);
+                assert!(!schema.columns[1].nullable);
+                assert_eq!(schema.columns[1].default_value, Some(Value::Text("x".to_string())));
+                assert_eq!(options.partition, Some(PartitionSpec::hash("id", 4)));
+            }
+            other => panic!("unexpected statement {:?}", other),
+        }
+        match &statements[1] {
+            Statement::Insert { rows, .. } => {
+                assert_eq!(rows[0], vec![Expr::literal(1i64), Expr::literal("O'Brien"), Expr::literal(-2.5)]);
+                assert_eq!(rows[1][1], Expr::Literal(Value::Null));
+            }
+            other => panic!("unexpected statement {:?}", other),
+        }
+
+        let Statement::CreateTableAs { name, query } = parse_statement("CREATE TABLE recent AS SELECT *, id * 2 AS double FROM t WHERE id > 1").unwrap() else { panic!() };
+        assert_eq!((name.as_str(), query.table_name.as_str(), query.projections.len()), ("recent", "t", 1));
+        assert!(parse_statement("CREATE TABLE recent AS SELECT COUNT(*) FROM t").is_err());
+        let Statement::InsertSelect { table, columns, query } = parse_statement("INSERT INTO archive (id) SELECT * FROM t WHERE id < 5").unwrap() else { panic!() };
+        assert_eq!((table.as_str(), columns, query.conditions.len()), ("archive", vec!["id".to_string()], 1));
+
+        let statements = parse_script("
+            CREATE SEQUENCE s START WITH -5 INCREMENT BY -1;
+            INSERT INTO t VALUES (NEXTVAL('s'), 1 + 2);
+        ").unwrap();
+        assert!(matches!(&statements[0], Statement::CreateSequence { start: -5, step: -1, .. }));
+        let Statement::Insert { rows, .. } = &statements[1] else { panic!() };
+        assert_eq!(rows[0], vec![Expr::function("NEXTVAL", vec![Expr::literal("s")]), Expr::literal(3i64)]);
+        assert!(parse_statement("INSERT INTO t VALUES (id + 1)").is_err());
+
+        let Statement::CreateTable { options, .. } = parse_statement("CREATE TABLE t (id INTEGER) WITH (TTL 3600, MAX_ROWS 10)").unwrap() else { panic!() };
+        assert_eq!(options.settings.ttl, Some(std::time::Duration::from_secs(3600)));
+        assert_eq!(options.settings.max_rows, Some(10));
+        assert!(parse_statement("CREATE TABLE t (id INTEGER) WITH (COMPRESSION ZSTD)").is_err());
+    }
+
+    #[test]
+    fn test_parse_copy() {
+        let statement = parse_statement("COPY users (id, name) FROM STDIN WITH (FORMAT tsv, HEADER, DELIMITER '|', NULL '')").unwrap();
+        let Statement::Copy { table, options } = statement else { panic!("expected copy") };
+        assert_eq!(table, "users");
+        assert_eq!(options, crate::import::CopyOptions::new(crate::import::CopyFormat::Tsv)
+            .header(true)
+            .delimiter('|')
+            .null("")
+            .columns(vec!["id".to_string(), "name".to_string()]));
+
+        assert!(matches!(parse_statement("COPY users FROM STDIN"), Ok(Statement::Copy { .. })));
+        assert!(parse_statement("COPY users FROM STDIN (DELIMITER '||')").is_err());
+        assert!(parse_statement("COPY users FROM STDIN (FORMAT xml)").is_err());
+    }
+
+    #[test]
+    fn test_parse_select_with_clauses() {
+        let statement = parse_statement(
+            "SELECT * FROM users WHERE age >= 18 AND name LIKE 'A%' AND email IS NOT NULL ORDER BY age DESC, id LIMIT 10 OFFSET 5"
+        ).unwrap();
+        let Statement::Query(query) = statement else { panic!("expected query") };
+        assert!(matches!(query.query_type, QueryType::Select));
+        assert_eq!(query.conditions.len(), 3);
+        assert!(matches!(query.conditions[2].operator, ComparisonOperator::IsNotNull));
+        assert!(!query.order_by[0].ascending);
+        assert!(query.order_by[1].ascending);
+        assert_eq!((query.limit, query.offset), (Some(10), Some(5)));
+
+        assert!(parse_statement("SELECT * FROM").is_err());
+        assert!(parse_statement("SELECT * FROM t; SELECT * FROM t").is_err());
+    }
+
+    #[test]
+    fn test_syntax_error_positions() {
+        let position = |sql: &str| match parse_script(sql) {
+            Err(DatabaseError::SyntaxError { position, .. }) => position,
+            other => panic!("expected syntax error, got {:?}", other),
+        };
+        assert_eq!(position("SELECT * FORM users"), 9);
+        assert_eq!(position("SELECT * FROM users\nWHERE age = 'x' LIMIT -1;"), 42);
+        assert_eq!(position("SELECT * FROM users WHERE name = 'bob"), 33);
+        assert_eq!(position("SELECT * FROM users WHERE   "), 25);
+        assert_eq!(position("CREATE TABLE t (id NUMBERS)"), 19);
+
+        let sql = "SELECT *\n  FROM users\n  WHERE age ! 3";
+        assert_eq!(caret(sql, position(sql)), "  WHERE age ! 3\n            ^");
+
+        assert!(is_complete("SELECT * FROM t;"));
+        assert!(is_complete("SELECT * FROM t; -- done"));
+        assert!(!is_complete("SELECT * FROM t"));
+        assert!(!is_complete("SELECT * FROM t WHERE name = 'a;"));
+        assert!(is_complete("SELECT * FROM t WHERE name = 'it''s';"));
+    }
+
+    #[test]
+    fn test_validate_identifiers() {
+        let invalid = |result: Result<()>| matches!(result, Err(DatabaseError::InvalidIdentifier { .. }));
+        assert!(validate_identifier("user data").is_ok() && validate_identifier("select").is_ok());
+        assert!(invalid(validate_identifier("")));
+        assert!(invalid(validate_identifier(" name")));
+        assert!(invalid(validate_identifier("a\nb")));
+        assert!(invalid(validate_identifier(&"x".repeat(MAX_IDENTIFIER_LENGTH + 1))));
+        assert!(invalid(validate_table_name("main.users")));
+
+        let schema = |names: &[&str]| Schema::new(names.iter().map(|name| ColumnDefinition::new(*name, DataType::Integer, false)).collect());
+        assert!(validate_table("t", &schema(&["id", "order"])).is_ok());
+        assert!(invalid(validate_table("t", &schema(&["id", "id"]))));
+        assert!(invalid(validate_table("t", &schema(&["_id"]))));
+
+        // 关键字与含空格的名称加引号后可以使用，空的引号标识符在解析时报错
+        let Statement::CreateTable { name, schema, .. } = parse_statement("CREATE TABLE \"order items\" (\"select\" INTEGER)").unwrap() else { panic!() };
+        assert_eq!((name.as_str(), schema.columns[0].name.as_str()), ("order items", "select"));
+        assert_eq!(quote_identifier("order items"), "\"order items\"");
+        assert!(parse_statement("CREATE TABLE select (id INTEGER)").is_err());
+        assert!(invalid(parse_statement("SELECT * FROM \"\"").map(drop)));
+    }
+
+    #[test]
+    fn test_literal_round_trip() {
+        let values = vec![
+            Value::Float(2.0),
+            Value::Text("it's".to_string()),
+            Value::Binary(vec![0, 255]),
+            Value::DateTime(chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap().and_hms_opt(3, 4, 5).unwrap()),
+        ];
+        let types = [DataType::Float, DataType::Text, DataType::Binary, DataType::DateTime];
+        for (value, data_type) in values.into_iter().zip(types) {
+            let sql = format!("INSERT INTO t VALUES ({})", literal(&value));
+            let Statement::Insert { mut rows, .. } = parse_statement(&sql).unwrap() else { panic!() };
+            let Expr::Literal(parsed) = rows[0].remove(0) else { panic!() };
+            let parsed = coerce_literal(parsed, Some(&data_type)).unwrap();
+            assert_eq!(parsed, value);
+        }
+        assert_eq!(quote_identifier("order"), "\"order\"");
+        assert_eq!(quote_identifier("name_1"), "name_1");
+    }
+}
@@ -0,0 +1,161 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::clock;
+use crate::partition::compare_values;
+use crate::query::{ComparisonOperator, Condition};
+use crate::types::{Row, Schema, Value};
+
+/// 单列统计信息
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnStats {
+    /// 不同非空值的个数，按列的排序规则去重
+    pub distinct_count: usize,
+    pub null_count: usize,
+    pub min: Option<Value>,
+    pub max: Option<Value>,
+}
+
+impl ColumnStats {
+    /// NULL（含缺失值）所占比例
+    pub fn null_fraction(&self, row_count: usize) -> f64 {
+        if row_count == 0 {
+            0.0
+        } else {
+            self.null_count as f64 / row_count as f64
+        }
+    }
+}
+
+/// 表统计信息，由 `ANALYZE` 收集并随快照保存，供查询规划估算条件的选择性
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TableStats {
+    pub row_count: usize,
+    pub columns: HashMap<String, ColumnStats>,
+    pub analyzed_at: DateTime<Utc>,
+}
+
+impl TableStats {
+    /// 扫描全部行收集统计信息
+    pub fn collect(schema: &Schema, rows: &[Row]) -> Self {
+        let columns = schema.columns.iter()
+            .map(|column| {
+                let mut distinct = HashSet::new();
+                let mut stats = ColumnStats { distinct_count: 0, null_count: 0, min: None, max: None };
+                for value in rows.iter().map(|row| row.get(&column.name)) {
+                    let Some(value) = value.filter(|value| !value.is_null()) else {
+                        stats.null_count += 1;
+                        continue;
+                    };
+                    distinct.insert(column.collation.key(value));
+                    if stats.min.as_ref().is_none_or(|min| compare_values(value, min) == Some(Ordering::Less)) {
+                        stats.min = Some(value.clone());
+                    }
+                    if stats.max.as_ref().is_none_or(|max| compare_values(value, max) == Some(Ordering::Greater)) {
+                        stats.max = Some(value.clone());
+                    }
+                }
+                // 无法排序的类型（JSON、数组等）不记录取值范围
+                if stats.min.as_ref().is_some_and(|min| compare_values(min, min).is_none()) {
+                    stats.min = None;
+                    stats.max = None;
+                }
+                stats.distinct_count = distinct.len();
+                (column.name.clone(), stats)
+            })
+            .collect();
+
+        Self {
+            row_count: rows.len(),
+            columns,
+            analyzed_at: clock::now(),
+        }
+    }
+
+    pub fn column(&self, name: &str) -> Option<&ColumnStats> {
+        self.columns.get(name)
+    }
+
+    /// 估算满足条件的行数；没有该列的统计信息时按全部行估算
+    pub fn estimate_rows(&self, condition: &Condition) -> usize {
+        let Some(stats) = self.column(&condition.column).filter(|_| condition.is_single_column() && condition.path.is_none()) else {
+            return self.row_count;
+        };
+        let non_null = self.row_count.saturating_sub(stats.null_count);
+        // 假设取值均匀分布：每个不同值平均对应的行数
+        let per_value = non_null.div_ceil(stats.distinct_count.max(1));
+
+        match condition.operator {
+            _ if condition.value.is_null() && !matches!(condition.operator, ComparisonOperator::IsNull | ComparisonOperator::IsNotNull) => 0,
+            ComparisonOperator::IsNull => stats.null_count,
+            ComparisonOperator::IsNotNull => non_null,
+            ComparisonOperator::Equal => per_value,
+            ComparisonOperator::NotEqual => non_null.saturating_sub(per_value),
+            ComparisonOperator::In => match &condition.value {
+                Value::Json(serde_json::Value::Array(items)) => (per_value * items.len()).min(non_null),
+                _ => per_value,
+            },
+            ComparisonOperator::LessThan | ComparisonOperator::LessThanOrEqual => {
+                range_fraction(stats, &condition.value).map_or(non_null / 3, |fraction| (non_null as f64 * fraction).ceil() as usize)
+            }
+            ComparisonOperator::GreaterThan | ComparisonOperator::GreaterThanOrEqual => {
+                range_fraction(stats, &condition.value).map_or(non_null / 3, |fraction| (non_null as f64 * (1.0 - fraction)).ceil() as usize)
+            }
+            _ => non_null,
+        }
+    }
+}
+
+/// 数值在 [min, max] 中的相对位置，用于线性插值估算范围条件
+fn range_fraction(stats: &ColumnStats, value: &Value) -> Option<f64> {
+    let as_float = |value: &Value| match value {
+        Value::Integer(i) => Some(*i as f64),
+        Value::Float(f) => Some(*f),
+        _ => None,
+    };
+    let (min, max, value) = (as_float(stats.min.as_ref()?)?, as_float(stats.max.as_ref()?)?, as_float(value)?);
+    if max <= min {
+        return Some(if value < min { 0.0 } else { 1.0 });
+    }
+    Some(((value - min) / (max - min)).clamp(0.0, 1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ColumnDefinition, DataType};
+
+    #[test]
+    fn test_collect_and_estimate() {
+        let schema = Schema::new(vec![
+            ColumnDefinition::new("id", DataType::Integer, true),
+            ColumnDefinition::new("city", DataType::Text, false),
+        ]);
+        let rows: Vec<Row> = (1..=100)
+            .map(|id| {
+                let mut row = Row::new();
+                row.set("id", Value::Integer(id));
+                if id % 10 != 0 {
+                    row.set("city", Value::Text(["Paris", "Oslo", "Rome"][id as usize % 3].to_string()));
+                }
+                row
+            })
+            .collect();
+
+        let stats = TableStats::collect(&schema, &rows);
+        let city = stats.column("city").unwrap();
+        assert_eq!((stats.row_count, city.distinct_count, city.null_count), (100, 3, 10));
+        assert_eq!(city.null_fraction(stats.row_count), 0.1);
+        assert_eq!(stats.column("id").unwrap().max, Some(Value::Integer(100)));
+
+        let estimate = |column: &str, operator, value| stats.estimate_rows(&Condition::new(column, operator, value));
+        assert_eq!(estimate("city", ComparisonOperator::Equal, Value::Text("Oslo".to_string())), 30);
+        assert_eq!(estimate("city", ComparisonOperator::IsNull, Value::Null), 10);
+        assert_eq!(estimate("id", ComparisonOperator::Equal, Value::Integer(5)), 1);
+        assert_eq!(estimate("id", ComparisonOperator::LessThan, Value::Integer(25)), 25);
+        assert_eq!(estimate("id", ComparisonOperator::Equal, Value::Null), 0);
+        assert_eq!(estimate("missing", ComparisonOperator::Equal, Value::Integer(1)), 100);
+    }
+}
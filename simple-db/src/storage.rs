@@ -1,164 +1,1368 @@
 use std::fs;
-use std::path::Path;
-use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Deserializer, Serialize};
 use chrono::{DateTime, Utc};
-
+use uuid::Uuid;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+use crate::backend::StorageBackend;
+use crate::binary;
+use crate::clock;
+use crate::integrity::{IntegrityReport, IssueKind};
+use crate::legacy::LegacyLog;
 use crate::error::{DatabaseError, Result};
-use crate::types::{Table, Value, Row, Schema};
+use crate::columnar::TableLayout;
+use crate::partition::PartitionSpec;
+use crate::procedure::Procedure;
+use crate::sequence::Sequence;
+use crate::query::Condition;
+use crate::types::{Table, TableOptions, Value, Row, Schema, ValidationMode};
+
+/// 当前的日志格式版本
+///
+/// - 1：`Update`/`Delete` 的行ID为字符串，更新数据为 `[列, 值]` 数组
+/// - 2：行ID为 UUID，更新数据为以列名为键的对象
+pub const LOG_FORMAT_VERSION: u32 = 2;
+
+/// 开启日志压缩后，JSON 超过该长度（字节）的条目才尝试压缩
+pub const LOG_COMPRESSION_THRESHOLD: usize = 512;
+
+/// 当前的快照格式版本
+///
+/// - 0：没有文件头，整个文件就是快照的 JSON
+/// - 1：首行为 [`SnapshotHeader`]，其后是快照的 JSON
+pub const SNAPSHOT_FORMAT_VERSION: u32 = 1;
+
+/// 快照文件头的魔数
+const SNAPSHOT_MAGIC: &str = "simple-db-snapshot";
+
+/// 压缩条目的标志字节，后面是 LZ4 压缩后的 JSON 的 base64；未压缩的条目以 `{` 开头
+const COMPRESSED_FLAG: char = 'z';
 
 /// 存储操作类型
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum StorageOperation {
-    Create { table: String, schema: Schema },
+    Create {
+        table: String,
+        schema: Schema,
+        #[serde(default)]
+        layout: TableLayout,
+        #[serde(default)]
+        partition: Option<PartitionSpec>,
+    },
     Insert { table: String, row: Row },
-    Update { table: String, id: String, data: Vec<(String, Value)> },
-    Delete { table: String, id: String },
+    BatchInsert { table: String, rows: Vec<Row> },
+    Update {
+        table: String,
+        id: Uuid,
+        #[serde(deserialize_with = "deserialize_updates")]
+        data: HashMap<String, Value>,
+        #[serde(default)]
+        updated_at: Option<DateTime<Utc>>,
+    },
+    Delete { table: String, id: Uuid },
+    /// 按条件批量更新，重放时对同一状态重新求值条件，一条日志覆盖所有匹配行
+    UpdateWhere {
+        table: String,
+        conditions: Vec<Condition>,
+        #[serde(deserialize_with = "deserialize_updates")]
+        data: HashMap<String, Value>,
+        updated_at: DateTime<Utc>,
+    },
+    /// 按条件批量删除
+    DeleteWhere { table: String, conditions: Vec<Condition> },
+    /// 清空表的所有行，保留表结构与建表选项
+    Truncate { table: String },
     Drop { table: String },
+    /// 重命名表，`table` 为原表名
+    Rename { table: String, new_name: String },
+    /// 按源表的表结构与建表选项创建新表，`with_data` 时同时复制所有行
+    Copy { table: String, source: String, with_data: bool },
+    /// 按查询结果创建新表，表结构与全部行记录在同一条日志中
+    CreateAs { table: String, schema: Schema, rows: Vec<Row> },
+    CreateProcedure { procedure: Procedure },
+    DropProcedure { name: String },
+    CreateSequence { sequence: Sequence },
+    DropSequence { name: String },
+    /// 序列前进后的当前值，与使用这些值的写入记录在同一批日志中
+    SetSequence { name: String, value: i64 },
+    /// 切换数据校验模式，见 `DatabaseEngine::set_validation_mode`
+    SetValidationMode { mode: ValidationMode },
+    /// 两阶段提交的准备记录：事务的全部操作已验证可以执行，等待协调者的决定
+    Prepare { txid: Uuid, operations: Vec<StorageOperation> },
+    /// 提交已准备的事务，重放时才执行其中的操作
+    CommitPrepared { txid: Uuid },
+    /// 中止已准备的事务，丢弃其中的操作
+    AbortPrepared { txid: Uuid },
+}
+
+impl StorageOperation {
+    /// 操作涉及的表名，存储过程等非表操作返回 None
+    pub fn table_name(&self) -> Option<&str> {
+        match self {
+            StorageOperation::Create { table, .. }
+            | StorageOperation::Insert { table, .. }
+            | StorageOperation::BatchInsert { table, .. }
+            | StorageOperation::Update { table, .. }
+            | StorageOperation::Delete { table, .. }
+            | StorageOperation::UpdateWhere { table, .. }
+            | StorageOperation::DeleteWhere { table, .. }
+            | StorageOperation::Truncate { table }
+            | StorageOperation::Drop { table }
+            | StorageOperation::Rename { table, .. }
+            | StorageOperation::Copy { table, .. }
+            | StorageOperation::CreateAs { table, .. } => Some(table),
+            StorageOperation::CreateProcedure { .. }
+            | StorageOperation::DropProcedure { .. }
+            | StorageOperation::CreateSequence { .. }
+            | StorageOperation::DropSequence { .. }
+            | StorageOperation::SetSequence { .. }
+            | StorageOperation::SetValidationMode { .. }
+            | StorageOperation::Prepare { .. }
+            | StorageOperation::CommitPrepared { .. }
+            | StorageOperation::AbortPrepared { .. } => None,
+        }
+    }
+
+    /// 是否是对行数据的修改，不写日志的表上的这类操作不记入日志
+    pub fn modifies_rows(&self) -> bool {
+        matches!(
+            self,
+            StorageOperation::Insert { .. }
+                | StorageOperation::BatchInsert { .. }
+                | StorageOperation::Update { .. }
+                | StorageOperation::Delete { .. }
+                | StorageOperation::UpdateWhere { .. }
+                | StorageOperation::DeleteWhere { .. }
+                | StorageOperation::Truncate { .. }
+        )
+    }
+}
+
+/// 按建表、删表、改名与复制操作更新不写日志的表：建表时按表结构中的设置登记，改名与复制沿用源表
+pub(crate) fn track_unlogged(tables: &mut HashSet<String>, operation: &StorageOperation) {
+    match operation {
+        StorageOperation::Create { table, schema, .. } => {
+            if schema.settings.unlogged {
+                tables.insert(table.clone());
+            } else {
+                tables.remove(table);
+            }
+        }
+        StorageOperation::Drop { table } => {
+            tables.remove(table);
+        }
+        StorageOperation::Rename { table, new_name } if tables.remove(table) => {
+            tables.insert(new_name.clone());
+        }
+        StorageOperation::Copy { table, source, .. } if tables.contains(source) => {
+            tables.insert(table.clone());
+        }
+        _ => {}
+    }
+}
+
+/// 读取更新数据，兼容版本 1 日志中的 `[列, 值]` 数组
+fn deserialize_updates<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<HashMap<String, Value>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Updates {
+        Map(HashMap<String, Value>),
+        Pairs(Vec<(String, Value)>),
+    }
+
+    Ok(match Updates::deserialize(deserializer)? {
+        Updates::Map(map) => map,
+        Updates::Pairs(pairs) => pairs.into_iter().collect(),
+    })
+}
+
+/// 已被快照（及备份）覆盖的日志条目的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RetentionAction {
+    /// 保留在活动日志中
+    Keep,
+    /// 移动到归档目录
+    Archive,
+    /// 直接删除
+    Delete,
+}
+
+/// 日志保留策略，可按表设置不同的处理方式
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    pub default_action: RetentionAction,
+    pub table_actions: std::collections::HashMap<String, RetentionAction>,
+    /// 为 true 时，日志条目还必须已被备份覆盖才会被处理
+    pub require_backup: bool,
+}
+
+impl RetentionPolicy {
+    pub fn new(default_action: RetentionAction) -> Self {
+        Self {
+            default_action,
+            table_actions: std::collections::HashMap::new(),
+            require_backup: false,
+        }
+    }
+
+    pub fn table_action<S: Into<String>>(mut self, table: S, action: RetentionAction) -> Self {
+        self.table_actions.insert(table.into(), action);
+        self
+    }
+
+    pub fn require_backup(mut self, require_backup: bool) -> Self {
+        self.require_backup = require_backup;
+        self
+    }
+
+    pub fn action_for(&self, table: Option<&str>) -> RetentionAction {
+        table.and_then(|t| self.table_actions.get(t))
+            .copied()
+            .unwrap_or(self.default_action)
+    }
+}
+
+/// 日志归档状态报告
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveStatus {
+    /// 已被快照/备份覆盖的最大日志ID
+    pub covered_log_id: u64,
+    /// 最近一次快照覆盖的日志ID
+    pub snapshot_log_id: u64,
+    /// 最近一次备份覆盖的日志ID
+    pub backup_log_id: u64,
+    /// 活动日志中的条目数
+    pub active_log_entries: usize,
+    /// 活动日志中已被覆盖、可以处理的条目数
+    pub reclaimable_entries: usize,
+    /// 归档段文件数
+    pub archived_segments: usize,
+    /// 归档段总大小（字节）
+    pub archived_bytes: u64,
 }
 
 /// 事务日志条目
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LogEntry {
     pub id: u64,
     pub timestamp: DateTime<Utc>,
     pub operation: StorageOperation,
+    /// 写入时的日志格式版本，没有该字段的旧日志为版本 1
+    #[serde(default = "legacy_log_version")]
+    pub version: u32,
+}
+
+fn legacy_log_version() -> u32 {
+    1
 }
 
 impl LogEntry {
     pub fn new(id: u64, operation: StorageOperation) -> Self {
         Self {
             id,
-            timestamp: Utc::now(),
+            timestamp: clock::now(),
             operation,
+            version: LOG_FORMAT_VERSION,
         }
     }
+
+    /// 编码为日志行: `<crc32 十六进制> <json>`
+    pub fn encode(&self) -> Result<String> {
+        self.encode_with(false)
+    }
+
+    /// 编码为日志行；`compress` 为 true 且 JSON 较长时用 LZ4 压缩，
+    /// 写成 `<crc32 十六进制> z<base64>`，压缩后不更短时仍写 JSON。校验和覆盖写入的内容
+    pub fn encode_with(&self, compress: bool) -> Result<String> {
+        let json = serde_json::to_string(self)?;
+        let payload = if compress && json.len() > LOG_COMPRESSION_THRESHOLD {
+            let compressed = format!("{}{}", COMPRESSED_FLAG, binary::encode_base64(&lz4_flex::compress_prepend_size(json.as_bytes())));
+            if compressed.len() < json.len() { compressed } else { json }
+        } else {
+            json
+        };
+        Ok(format!("{:08x} {}", crc32fast::hash(payload.as_bytes()), payload))
+    }
+
+    /// 解析日志行并校验校验和，压缩的条目透明解压；不带校验和的旧格式行（以 `{` 开头）直接按 JSON 解析
+    pub fn decode(line: &str) -> std::result::Result<Self, String> {
+        let payload = if line.starts_with('{') {
+            line
+        } else {
+            let (checksum, payload) = line.split_once(' ')
+                .ok_or_else(|| "缺少校验和".to_string())?;
+            let expected = u32::from_str_radix(checksum, 16)
+                .map_err(|_| format!("无效的校验和 '{}'", checksum))?;
+            let actual = crc32fast::hash(payload.as_bytes());
+            if actual != expected {
+                return Err(format!("校验和不匹配 (期望 {:08x}, 实际 {:08x})", expected, actual));
+            }
+            payload
+        };
+
+        let decompressed;
+        let json = match payload.strip_prefix(COMPRESSED_FLAG) {
+            Some(encoded) => {
+                let compressed = binary::decode_base64(encoded).map_err(|e| e.to_string())?;
+                let bytes = lz4_flex::decompress_size_prepended(&compressed).map_err(|e| format!("解压失败: {}", e))?;
+                decompressed = String::from_utf8(bytes).map_err(|e| format!("解压后的内容不是 UTF-8: {}", e))?;
+                decompressed.as_str()
+            }
+            None => payload,
+        };
+
+        let entry: Self = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        if entry.version > LOG_FORMAT_VERSION {
+            return Err(format!("不支持的日志格式版本 {}（当前版本 {}）", entry.version, LOG_FORMAT_VERSION));
+        }
+        Ok(entry)
+    }
 }
 
 /// 持久化快照
+///
+/// 快照文件只是清单：表数据保存在数据目录 `tables/` 下的独立文件中，由 `table_files` 列出。
+/// 旧版快照把全部表直接写在 `tables` 中，仍可读取。
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Snapshot {
+    #[serde(default)]
     pub tables: Vec<Table>,
     pub timestamp: DateTime<Utc>,
     pub last_log_id: u64,
+    #[serde(default)]
+    pub procedures: Vec<Procedure>,
+    #[serde(default)]
+    pub sequences: Vec<Sequence>,
+    #[serde(default)]
+    pub table_files: Vec<TableFile>,
+    /// 尚未决定的两阶段提交事务
+    #[serde(default)]
+    pub prepared: Vec<PreparedTransaction>,
+    /// 已提交、尚未被协调者遗忘的两阶段提交事务，供恢复时判断其他参与者上的同一事务
+    #[serde(default)]
+    pub committed_transactions: Vec<Uuid>,
+    /// 不写日志的表，加载后继续跳过它们的数据修改
+    #[serde(default)]
+    pub unlogged_tables: Vec<String>,
+    /// 数据校验模式，没有记录的旧快照为默认的宽松模式
+    #[serde(default)]
+    pub validation_mode: ValidationMode,
+    /// 最近一次备份覆盖的日志ID；备份之后尚未保存快照就崩溃时，重启后只会多保留一些日志
+    #[serde(default)]
+    pub backup_log_id: u64,
 }
 
 impl Snapshot {
     pub fn new(tables: Vec<Table>, last_log_id: u64) -> Self {
         Self {
             tables,
-            timestamp: Utc::now(),
+            timestamp: clock::now(),
             last_log_id,
+            procedures: Vec::new(),
+            sequences: Vec::new(),
+            table_files: Vec::new(),
+            prepared: Vec::new(),
+            committed_transactions: Vec::new(),
+            unlogged_tables: Vec::new(),
+            validation_mode: ValidationMode::default(),
+            backup_log_id: 0,
+        }
+    }
+
+    /// 快照中的表数，包括内联的表与 `tables/` 下的表文件
+    pub fn table_count(&self) -> usize {
+        self.tables.len() + self.table_files.len()
+    }
+
+    /// 编码为快照文件：首行为单行 JSON 的文件头，其后是快照的 JSON
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let body = serde_json::to_vec_pretty(self)?;
+        let header = SnapshotHeader {
+            magic: SNAPSHOT_MAGIC.to_string(),
+            format_version: SNAPSHOT_FORMAT_VERSION,
+            engine_version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at: self.timestamp,
+            table_count: self.table_count(),
+            checksum: crc32fast::hash(&body),
+        };
+        let mut bytes = serde_json::to_vec(&header)?;
+        bytes.push(b'\n');
+        bytes.extend_from_slice(&body);
+        Ok(bytes)
+    }
+
+    /// 解码快照文件并校验文件头，`source` 为出错时报告的来源
+    ///
+    /// 没有文件头的旧版快照直接按 JSON 解析。
+    pub fn decode(bytes: &[u8], source: &str) -> Result<Self> {
+        let (header, body) = match bytes.iter().position(|byte| *byte == b'\n') {
+            Some(end) => match serde_json::from_slice::<SnapshotHeader>(&bytes[..end]) {
+                Ok(header) => (Some(header), &bytes[end + 1..]),
+                Err(_) => (None, bytes),
+            },
+            None => (None, bytes),
+        };
+        let Some(header) = header else {
+            return serde_json::from_slice(body)
+                .map_err(|e| DatabaseError::incompatible_snapshot(source, format!("无法解析没有文件头的快照: {}", e)));
+        };
+
+        if header.magic != SNAPSHOT_MAGIC {
+            return Err(DatabaseError::incompatible_snapshot(source, format!("不是快照文件: {}", header.magic)));
+        }
+        if header.format_version > SNAPSHOT_FORMAT_VERSION {
+            return Err(DatabaseError::incompatible_snapshot(source, format!(
+                "格式版本 {} 由 simple-db {} 写入，当前版本 {} 只支持到格式版本 {}",
+                header.format_version, header.engine_version, env!("CARGO_PKG_VERSION"), SNAPSHOT_FORMAT_VERSION
+            )));
         }
+        let actual = crc32fast::hash(body);
+        if actual != header.checksum {
+            return Err(DatabaseError::incompatible_snapshot(source, format!(
+                "校验和不匹配 (期望 {:08x}, 实际 {:08x})", header.checksum, actual
+            )));
+        }
+        let snapshot: Snapshot = serde_json::from_slice(body).map_err(|e| DatabaseError::incompatible_snapshot(
+            source, format!("无法解析 simple-db {} 写入的快照: {}", header.engine_version, e),
+        ))?;
+        if snapshot.table_count() != header.table_count {
+            return Err(DatabaseError::incompatible_snapshot(source, format!(
+                "文件头记录 {} 张表，实际 {} 张", header.table_count, snapshot.table_count()
+            )));
+        }
+        Ok(snapshot)
     }
 }
 
+/// 快照文件头
+///
+/// 校验和为文件头之后全部内容的 CRC32；文件头本身不计入，改动它会导致解析或校验失败。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotHeader {
+    pub magic: String,
+    pub format_version: u32,
+    /// 写入快照的 simple-db 版本
+    pub engine_version: String,
+    pub created_at: DateTime<Utc>,
+    pub table_count: usize,
+    pub checksum: u32,
+}
+
+/// 两阶段提交中已准备、尚未提交或中止的事务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreparedTransaction {
+    pub txid: Uuid,
+    pub operations: Vec<StorageOperation>,
+}
+
+/// 快照清单中的一张表，`file` 为 `tables/` 目录下的文件名
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TableFile {
+    pub name: String,
+    pub file: String,
+}
+
+/// 表数据文件所在的子目录
+const TABLES_DIR: &str = "tables";
+
+/// 压缩表文件开头的标记，其后是 LZ4 压缩的表 JSON
+const COMPRESSED_TABLE_MAGIC: &[u8] = b"simple-db-lz4\n";
+
+/// 表文件的内容，表设置了压缩时以 LZ4 压缩
+fn encode_table_file(table: &Table) -> Result<Vec<u8>> {
+    let json = if table.has_spilled() {
+        serde_json::to_vec_pretty(&table.materialize()?)?
+    } else {
+        serde_json::to_vec_pretty(table)?
+    };
+    if !table.schema.settings.compressed {
+        return Ok(json);
+    }
+    let mut bytes = COMPRESSED_TABLE_MAGIC.to_vec();
+    bytes.extend_from_slice(&lz4_flex::compress_prepend_size(&json));
+    Ok(bytes)
+}
+
+/// 读取表文件，按开头的标记识别压缩的表文件
+fn decode_table_file(bytes: &[u8]) -> Result<Table> {
+    match bytes.strip_prefix(COMPRESSED_TABLE_MAGIC) {
+        Some(compressed) => {
+            let json = lz4_flex::decompress_size_prepended(compressed)
+                .map_err(|e| DatabaseError::other(format!("解压失败: {}", e)))?;
+            Ok(serde_json::from_slice(&json)?)
+        }
+        None => Ok(serde_json::from_slice(bytes)?),
+    }
+}
+
+/// 写入 JSON 文件并 fsync，经缓冲写入器边序列化边写入，不在内存中拼出完整的 JSON
+fn write_json_synced<T: Serialize + ?Sized>(path: &Path, value: &T) -> Result<()> {
+    let mut writer = BufWriter::new(fs::File::create(path)?);
+    serde_json::to_writer_pretty(&mut writer, value)?;
+    let file = writer.into_inner().map_err(|e| e.into_error())?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// 同步目录，确保其中的 rename 结果持久化
+fn sync_dir(dir: &Path) {
+    if let Ok(dir) = fs::File::open(dir) {
+        let _ = dir.sync_all();
+    }
+}
+
+/// 日志段文件的默认大小上限
+pub const DEFAULT_LOG_SEGMENT_SIZE: u64 = 64 * 1024 * 1024;
+
+/// 带上日志段文件名的 `CorruptLog` 错误
+fn corrupt_segment(segment: &Path, line: usize, reason: String) -> DatabaseError {
+    let name = segment.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    DatabaseError::corrupt_log(line, format!("{}: {}", name, reason))
+}
+
+/// 序列不存在的错误
+fn sequence_not_found(name: &str) -> DatabaseError {
+    DatabaseError::other(format!("序列 '{}' 不存在", name))
+}
+
+/// 分段之前使用的单一日志文件，仍作为最早的日志段读取
+const LEGACY_LOG_FILE: &str = "transaction.log";
+
+/// 日志段文件名，序号补零保证按名称排序即按写入顺序
+fn segment_file_name(seq: u64) -> String {
+    format!("wal-{:08}.log", seq)
+}
+
+/// 解析日志段的序号，旧版的 `transaction.log` 视为序号 0
+fn segment_seq(file_name: &str) -> Option<u64> {
+    if file_name == LEGACY_LOG_FILE {
+        return Some(0);
+    }
+    file_name.strip_prefix("wal-")?.strip_suffix(".log")?.parse().ok()
+}
+
+/// 正在追加的日志段
+#[derive(Debug, Clone, Copy)]
+struct ActiveSegment {
+    seq: u64,
+    size: u64,
+}
+
 /// 存储引擎
 pub struct StorageEngine {
-    data_dir: String,
-    log_file: String,
-    snapshot_file: String,
+    /// 数据目录，使用持久化后端时为 None
+    data_dir: Option<String>,
+    /// 单个日志段的大小上限，写入新条目会超出时切换到下一个段
+    max_segment_size: u64,
+    /// 写入日志段时是否压缩较长的条目
+    compress_log: bool,
+    /// 缓存的活动段，日志文件被重写或删除后置空，下次追加时重新查找
+    active_segment: Option<ActiveSegment>,
     current_log_id: u64,
+    last_backup_log_id: u64,
+    retention_policy: Option<RetentionPolicy>,
+    /// 日志订阅者（从库），每条新日志都会推送给它们
+    subscribers: Vec<UnboundedSender<LogEntry>>,
+    /// 进行中的热备份数，期间不删除或改写日志段与表文件
+    backups_in_progress: Arc<AtomicUsize>,
+    /// 临时表：上面的操作不分配日志ID，表不进入快照与备份
+    temp_tables: HashSet<String>,
+    /// 不写日志的表：上面的数据修改不分配日志ID，见 `TableSettings::unlogged`
+    unlogged_tables: HashSet<String>,
+    /// 设置后快照与日志写入持久化后端，不使用数据目录
+    backend: Option<Arc<dyn StorageBackend>>,
 }
 
 impl StorageEngine {
     /// 创建新的存储引擎
     pub fn new() -> Self {
-        let data_dir = "data".to_string();
-        let log_file = format!("{}/transaction.log", data_dir);
-        let snapshot_file = format!("{}/snapshot.json", data_dir);
+        Self::with_data_dir("data")
+    }
+
+    /// 使用指定的数据目录创建存储引擎
+    pub fn with_data_dir<S: Into<String>>(data_dir: S) -> Self {
+        Self::with_location(Some(data_dir.into()), None)
+    }
+
+    /// 使用持久化后端创建存储引擎，快照与日志都保存在后端中
+    pub fn with_backend(backend: Arc<dyn StorageBackend>) -> Self {
+        Self::with_location(None, Some(backend))
+    }
 
+    fn with_location(data_dir: Option<String>, backend: Option<Arc<dyn StorageBackend>>) -> Self {
         Self {
             data_dir,
-            log_file,
-            snapshot_file,
+            max_segment_size: DEFAULT_LOG_SEGMENT_SIZE,
+            compress_log: false,
+            active_segment: None,
             current_log_id: 0,
+            last_backup_log_id: 0,
+            retention_policy: None,
+            subscribers: Vec::new(),
+            backups_in_progress: Arc::new(AtomicUsize::new(0)),
+            temp_tables: HashSet::new(),
+            unlogged_tables: HashSet::new(),
+            backend,
+        }
+    }
+
+    /// 是否使用持久化后端
+    pub fn has_backend(&self) -> bool {
+        self.backend.is_some()
+    }
+
+    /// 数据目录路径，使用持久化后端时为 None
+    pub fn data_dir(&self) -> Option<&str> {
+        self.data_dir.as_deref()
+    }
+
+    /// 访问数据目录中的文件前取得目录，使用持久化后端时返回错误
+    fn dir(&self) -> Result<&Path> {
+        self.data_dir.as_deref().map(Path::new)
+            .ok_or_else(|| DatabaseError::other("使用持久化后端的存储引擎没有数据目录"))
+    }
+
+    /// 快照中各表数据文件所在的目录，使用持久化后端时为 None
+    pub fn tables_dir(&self) -> Option<PathBuf> {
+        self.data_dir.as_deref().map(|dir| Path::new(dir).join(TABLES_DIR))
+    }
+
+    /// 把表登记为临时表
+    pub fn add_temp_table(&mut self, name: &str) {
+        self.temp_tables.insert(name.to_string());
+    }
+
+    /// 取消临时表的登记，返回它是否是临时表
+    pub fn remove_temp_table(&mut self, name: &str) -> bool {
+        self.temp_tables.remove(name)
+    }
+
+    pub fn is_temp_table(&self, name: &str) -> bool {
+        self.temp_tables.contains(name)
+    }
+
+    /// 按建表、删表、改名与复制操作更新不写日志的表，这些操作本身是否写日志都要调用
+    pub fn track_unlogged(&mut self, operation: &StorageOperation) {
+        track_unlogged(&mut self.unlogged_tables, operation);
+    }
+
+    /// 替换不写日志的表，加载快照并重放日志后调用
+    pub fn set_unlogged_tables(&mut self, tables: HashSet<String>) {
+        self.unlogged_tables = tables;
+    }
+
+    pub fn is_unlogged_table(&self, name: &str) -> bool {
+        self.unlogged_tables.contains(name)
+    }
+
+    /// 写入快照的不写日志的表，按名称排序
+    fn unlogged_table_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.unlogged_tables.iter().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// 最近写入的日志ID
+    pub fn current_log_id(&self) -> u64 {
+        self.current_log_id
+    }
+
+    /// 设置单个日志段的大小上限，只影响之后的写入
+    pub fn set_max_segment_size(&mut self, bytes: u64) {
+        self.max_segment_size = bytes.max(1);
+    }
+
+    pub fn max_segment_size(&self) -> u64 {
+        self.max_segment_size
+    }
+
+    /// 设置写入日志段时是否压缩超过 `LOG_COMPRESSION_THRESHOLD` 字节的条目，只影响之后的写入
+    ///
+    /// 读取时按每个条目的标志字节识别，压缩与未压缩的条目可以混在同一个日志段中。
+    pub fn set_log_compression(&mut self, enabled: bool) {
+        self.compress_log = enabled;
+    }
+
+    /// 按写入顺序列出日志段文件，旧版的 `transaction.log` 排在最前
+    pub fn log_segments(&self) -> Result<Vec<PathBuf>> {
+        let Some(dir) = self.data_dir.as_deref().filter(|dir| Path::new(dir).exists()) else {
+            return Ok(Vec::new());
+        };
+
+        let mut segments = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let seq = path.file_name().and_then(|name| name.to_str()).and_then(segment_seq);
+            if let Some(seq) = seq.filter(|_| path.is_file()) {
+                segments.push((seq, path));
+            }
+        }
+        segments.sort_unstable();
+        Ok(segments.into_iter().map(|(_, path)| path).collect())
+    }
+
+    fn segment_path(&self, seq: u64) -> Result<PathBuf> {
+        Ok(self.dir()?.join(segment_file_name(seq)))
+    }
+
+    /// 查找最后一个编号的日志段；旧版日志只读，没有编号段时从 1 开始
+    fn find_active_segment(&self) -> Result<ActiveSegment> {
+        let last = self.log_segments()?.into_iter()
+            .filter_map(|path| {
+                let seq = path.file_name().and_then(|name| name.to_str()).and_then(segment_seq)?;
+                Some((seq, path))
+            })
+            .rfind(|(seq, _)| *seq > 0);
+
+        match last {
+            Some((seq, path)) => Ok(ActiveSegment { seq, size: fs::metadata(path)?.len() }),
+            None => Ok(ActiveSegment { seq: 1, size: 0 }),
+        }
+    }
+
+    /// 从现有日志与快照中恢复日志ID计数，保证重启后日志ID继续递增
+    ///
+    /// 同时恢复快照记录的备份位置，保留策略要求备份覆盖时重启后不必重新备份。
+    pub fn recover_log_id(&mut self) -> Result<u64> {
+        let snapshot = self.load_snapshot()?;
+        if let Some(snapshot) = &snapshot {
+            self.mark_backup(snapshot.backup_log_id);
+        }
+        let mut last_id = snapshot.map_or(0, |snapshot| snapshot.last_log_id);
+        if let Some(backend) = &self.backend {
+            last_id = backend.read_log(last_id)?.last().map_or(last_id, |entry| entry.id);
         }
+
+        for segment in self.log_segments()? {
+            for line in BufReader::new(fs::File::open(&segment)?).lines() {
+                if let Ok(entry) = LogEntry::decode(&line?) {
+                    last_id = last_id.max(entry.id);
+                }
+            }
+        }
+
+        self.current_log_id = self.current_log_id.max(last_id);
+        Ok(self.current_log_id)
+    }
+
+    fn archive_dir(&self) -> Result<PathBuf> {
+        Ok(self.dir()?.join("archive"))
+    }
+
+    /// 设置日志保留策略，None 表示不自动处理旧日志
+    pub fn set_retention_policy(&mut self, policy: Option<RetentionPolicy>) {
+        self.retention_policy = policy;
+    }
+
+    pub fn retention_policy(&self) -> Option<&RetentionPolicy> {
+        self.retention_policy.as_ref()
+    }
+
+    /// 已被快照（以及策略要求时的备份）覆盖的最大日志ID
+    fn covered_log_id(&self, snapshot_log_id: u64) -> u64 {
+        match &self.retention_policy {
+            Some(policy) if policy.require_backup => snapshot_log_id.min(self.last_backup_log_id),
+            _ => snapshot_log_id,
+        }
+    }
+
+    /// 按保留策略处理已被覆盖的日志条目
+    ///
+    /// 逐个日志段处理：被覆盖的条目按所属表的规则移动到 `archive/` 下的归档段文件、删除或保留，
+    /// 其余条目原样写回该日志段（临时文件 + rename 原子替换），清空的日志段直接删除。
+    /// 返回处理的条目数。
+    pub fn apply_retention(&mut self) -> Result<usize> {
+        let Some(policy) = self.retention_policy.clone().filter(|_| self.backend.is_none()) else {
+            return Ok(0);
+        };
+        // 热备份还会读取日志段，留到下次再处理
+        if self.backup_in_progress() {
+            return Ok(0);
+        }
+
+        let snapshot_log_id = self.load_snapshot()?.map(|s| s.last_log_id).unwrap_or(0);
+        let covered = self.covered_log_id(snapshot_log_id);
+        let mut processed = 0;
+
+        for segment in self.log_segments()? {
+            let content = fs::read_to_string(&segment)?;
+            let mut active = String::new();
+            let mut archived = String::new();
+            let mut archived_ids = (u64::MAX, 0);
+            let mut segment_processed = 0;
+
+            for (index, line) in content.lines().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let entry = LogEntry::decode(line)
+                    .map_err(|reason| corrupt_segment(&segment, index + 1, reason))?;
+
+                let action = if entry.id <= covered {
+                    policy.action_for(entry.operation.table_name())
+                } else {
+                    RetentionAction::Keep
+                };
+
+                match action {
+                    RetentionAction::Keep => {
+                        active.push_str(line);
+                        active.push('\n');
+                        continue;
+                    }
+                    RetentionAction::Archive => {
+                        archived.push_str(line);
+                        archived.push('\n');
+                        archived_ids = (archived_ids.0.min(entry.id), archived_ids.1.max(entry.id));
+                    }
+                    RetentionAction::Delete => {}
+                }
+                segment_processed += 1;
+            }
+
+            if segment_processed == 0 {
+                continue;
+            }
+
+            if !archived.is_empty() {
+                let archive_dir = self.archive_dir()?;
+                fs::create_dir_all(&archive_dir)?;
+                let archive = archive_dir.join(format!("transaction-{:010}-{:010}.log", archived_ids.0, archived_ids.1));
+                let mut file = fs::OpenOptions::new().create(true).append(true).open(archive)?;
+                file.write_all(archived.as_bytes())?;
+                file.sync_all()?;
+            }
+
+            if active.is_empty() {
+                fs::remove_file(&segment)?;
+            } else {
+                self.rewrite_segment(&segment, &active)?;
+            }
+            processed += segment_processed;
+        }
+
+        if processed > 0 {
+            self.active_segment = None;
+            self.sync_data_dir();
+        }
+
+        Ok(processed)
+    }
+
+    /// 日志归档状态
+    pub fn archive_status(&self) -> Result<ArchiveStatus> {
+        let snapshot_log_id = self.load_snapshot()?.map(|s| s.last_log_id).unwrap_or(0);
+        let mut status = ArchiveStatus {
+            covered_log_id: self.covered_log_id(snapshot_log_id),
+            snapshot_log_id,
+            backup_log_id: self.last_backup_log_id,
+            ..ArchiveStatus::default()
+        };
+
+        for segment in self.log_segments()? {
+            for line in BufReader::new(fs::File::open(&segment)?).lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                status.active_log_entries += 1;
+                if let Ok(entry) = LogEntry::decode(&line) {
+                    let reclaimable = entry.id <= status.covered_log_id
+                        && self.retention_policy.as_ref()
+                            .is_some_and(|p| p.action_for(entry.operation.table_name()) != RetentionAction::Keep);
+                    if reclaimable {
+                        status.reclaimable_entries += 1;
+                    }
+                }
+            }
+        }
+
+        let archive_dir = self.data_dir.as_ref().map(|_| self.archive_dir()).transpose()?;
+        if let Some(archive_dir) = archive_dir.filter(|dir| dir.exists()) {
+            for entry in fs::read_dir(archive_dir)? {
+                let metadata = entry?.metadata()?;
+                if metadata.is_file() {
+                    status.archived_segments += 1;
+                    status.archived_bytes += metadata.len();
+                }
+            }
+        }
+
+        Ok(status)
+    }
+
+    fn snapshot_file(&self) -> Result<String> {
+        Ok(self.dir()?.join("snapshot.json").to_string_lossy().into_owned())
+    }
+
+    fn snapshot_tmp_file(&self) -> Result<String> {
+        Ok(format!("{}.tmp", self.snapshot_file()?))
+    }
+
+    fn snapshot_backup_file(&self) -> Result<String> {
+        Ok(format!("{}.bak", self.snapshot_file()?))
     }
 
     /// 初始化存储目录
     pub fn initialize(&self) -> Result<()> {
-        if !Path::new(&self.data_dir).exists() {
-            fs::create_dir_all(&self.data_dir)?;
+        if let Some(dir) = self.data_dir.as_deref().filter(|dir| !Path::new(dir).exists()) {
+            fs::create_dir_all(dir)?;
         }
         Ok(())
     }
 
     /// 写入日志
     pub fn write_log(&mut self, operation: StorageOperation) -> Result<()> {
+        self.record(operation, true)
+    }
+
+    /// 为操作分配日志ID并推送给订阅者，`persist` 为 false 时不写入日志文件
+    ///
+    /// 既不持久化也没有订阅者时，或操作的是临时表、修改的是不写日志的表中的行时，
+    /// 什么都不做，日志ID保持不变。
+    pub fn record(&mut self, operation: StorageOperation, persist: bool) -> Result<()> {
+        if !persist && self.subscribers.is_empty() {
+            return Ok(());
+        }
+        let skipped = operation.table_name().is_some_and(|table| {
+            self.temp_tables.contains(table) || (operation.modifies_rows() && self.unlogged_tables.contains(table))
+        });
+        if skipped {
+            return Ok(());
+        }
+
         self.current_log_id += 1;
         let entry = LogEntry::new(self.current_log_id, operation);
+        if persist {
+            self.append(&entry)?;
+        }
+        self.publish(&entry);
+        Ok(())
+    }
+
+    /// 写入从主库收到的日志条目，保留主库分配的日志ID
+    pub fn append_entry(&mut self, entry: &LogEntry, persist: bool) -> Result<()> {
+        if persist {
+            self.append(entry)?;
+        }
+        self.current_log_id = self.current_log_id.max(entry.id);
+        self.publish(entry);
+        Ok(())
+    }
+
+    /// 订阅之后写入的日志
+    pub fn subscribe(&mut self) -> UnboundedReceiver<LogEntry> {
+        let (sender, receiver) = unbounded_channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    pub fn has_subscribers(&self) -> bool {
+        !self.subscribers.is_empty()
+    }
+
+    /// 推送日志条目，顺便移除已断开的订阅者
+    fn publish(&mut self, entry: &LogEntry) {
+        self.subscribers.retain(|subscriber| subscriber.send(entry.clone()).is_ok());
+    }
+
+    /// 追加到活动日志段，写入后会超出大小上限时先切换到下一个段；单条超过上限的条目独占一个段
+    fn append(&mut self, entry: &LogEntry) -> Result<()> {
+        if let Some(backend) = &self.backend {
+            return backend.append(entry);
+        }
+        // 一次性写入整行，避免并发追加时行内容交错
+        let line = format!("{}\n", entry.encode_with(self.compress_log)?);
+
+        let mut active = match self.active_segment {
+            Some(active) => active,
+            None => self.find_active_segment()?,
+        };
+        if active.size > 0 && active.size + line.len() as u64 > self.max_segment_size {
+            // 写满的段不再追加，切换前落盘，之后 `sync_log` 只需处理活动段
+            fs::File::open(self.segment_path(active.seq)?)?.sync_all()?;
+            active = ActiveSegment { seq: active.seq + 1, size: 0 };
+        }
 
         let mut file = fs::OpenOptions::new()
             .create(true)
             .append(true)
-            .open(&self.log_file)?;
+            .open(self.segment_path(active.seq)?)?;
+        file.write_all(line.as_bytes())?;
 
-        let json = serde_json::to_string(&entry)?;
-        use std::io::Write;
-        writeln!(file, "{}", json)?;
+        active.size += line.len() as u64;
+        self.active_segment = Some(active);
+        Ok(())
+    }
 
+    /// 把活动日志段 fsync 到磁盘
+    ///
+    /// 追加日志只写入操作系统的缓存，进程退出不会丢失，但断电可能丢失；关闭前调用以确保日志落盘。
+    pub fn sync_log(&self) -> Result<()> {
+        if self.backend.is_some() {
+            return Ok(());
+        }
+        if let Some(active) = self.active_segment {
+            fs::File::open(self.segment_path(active.seq)?)?.sync_all()?;
+        }
         Ok(())
     }
 
     /// 创建快照
+    ///
+    /// 先写入临时文件并 fsync，再通过原子 rename 替换正式快照；
+    /// 旧快照保留为 `.bak`，写入中途崩溃不会损坏已有快照。
     pub fn create_snapshot(&self, tables: Vec<Table>) -> Result<()> {
-        let snapshot = Snapshot::new(tables, self.current_log_id);
-        let json = serde_json::to_string_pretty(&snapshot)?;
-        fs::write(&self.snapshot_file, json)?;
+        let mut storage = MemoryStorage::new();
+        for table in tables {
+            storage.add_table(table)?;
+        }
+        self.save_storage(&storage, self.current_log_id)
+    }
+
+    /// 保存内存存储的快照
+    ///
+    /// 每张表写入 `tables/` 下的独立文件，`snapshot.json` 只记录各表文件的清单；
+    /// 自上次保存后没有修改过的表（包括尚未加载的表）沿用原来的文件，不重新写入。
+    /// 清单替换完成后，删除正式快照与 `.bak` 快照都不再引用的表文件。
+    pub fn save_storage(&self, storage: &MemoryStorage, last_log_id: u64) -> Result<()> {
+        if let Some(backend) = &self.backend {
+            let tables = storage.get_all_data()?.into_iter()
+                .filter(|table| !self.temp_tables.contains(&table.name))
+                .collect();
+            let mut snapshot = Snapshot::new(tables, last_log_id);
+            snapshot.procedures = storage.get_procedures();
+            snapshot.sequences = storage.get_sequences();
+            snapshot.prepared = storage.prepared_transactions();
+            snapshot.committed_transactions = storage.committed_transactions();
+            snapshot.unlogged_tables = self.unlogged_table_names();
+            snapshot.validation_mode = storage.validation_mode();
+            snapshot.backup_log_id = self.last_backup_log_id;
+            return backend.save_snapshot(&snapshot);
+        }
+        let tables_dir = self.dir()?.join(TABLES_DIR);
+        fs::create_dir_all(&tables_dir)?;
+        let mut snapshot = Snapshot::new(Vec::new(), last_log_id);
+        snapshot.table_files = storage.save_tables(&tables_dir, &self.temp_tables)?;
+        snapshot.procedures = storage.get_procedures();
+        snapshot.sequences = storage.get_sequences();
+        snapshot.prepared = storage.prepared_transactions();
+        snapshot.committed_transactions = storage.committed_transactions();
+        snapshot.unlogged_tables = self.unlogged_table_names();
+        snapshot.validation_mode = storage.validation_mode();
+        snapshot.backup_log_id = self.last_backup_log_id;
+        sync_dir(&tables_dir);
+
+        self.save_snapshot(&snapshot)?;
+        self.remove_unreferenced_table_files()
+    }
+
+    /// 删除正式快照与 `.bak` 快照都不再引用的表文件，包括写入中途崩溃留下的临时文件
+    fn remove_unreferenced_table_files(&self) -> Result<()> {
+        let tables_dir = self.dir()?.join(TABLES_DIR);
+        if !tables_dir.exists() || self.backup_in_progress() {
+            return Ok(());
+        }
+
+        let mut referenced = HashSet::new();
+        let backup = Self::read_snapshot_file(&self.snapshot_backup_file()?).ok().flatten();
+        for snapshot in Self::read_snapshot_file(&self.snapshot_file()?)?.into_iter().chain(backup) {
+            referenced.extend(snapshot.table_files.into_iter().map(|table_file| table_file.file));
+        }
+        for entry in fs::read_dir(&tables_dir)? {
+            let entry = entry?;
+            if !referenced.contains(entry.file_name().to_string_lossy().as_ref()) {
+                fs::remove_file(entry.path())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 原子写入快照文件：先写入临时文件并 fsync，再 rename 为正式快照，旧快照保留为 `.bak`
+    pub fn save_snapshot(&self, snapshot: &Snapshot) -> Result<()> {
+        let snapshot_file = self.snapshot_file()?;
+        let tmp_file = self.snapshot_tmp_file()?;
+        let mut file = fs::File::create(&tmp_file)?;
+        file.write_all(&snapshot.encode()?)?;
+        file.sync_all()?;
+
+        if Path::new(&snapshot_file).exists() {
+            fs::rename(&snapshot_file, self.snapshot_backup_file()?)?;
+        }
+        fs::rename(&tmp_file, &snapshot_file)?;
+        self.sync_data_dir();
+
         Ok(())
     }
 
     /// 加载快照
+    ///
+    /// 正式快照缺失或无法解析时，自动回退到 `.bak` 备份快照。
     pub fn load_snapshot(&self) -> Result<Option<Snapshot>> {
-        if !Path::new(&self.snapshot_file).exists() {
+        if let Some(backend) = &self.backend {
+            return backend.load_snapshot();
+        }
+        let primary = Self::read_snapshot_file(&self.snapshot_file()?);
+        match primary {
+            Ok(Some(snapshot)) => Ok(Some(snapshot)),
+            Ok(None) => Self::read_snapshot_file(&self.snapshot_backup_file()?),
+            Err(e) => match Self::read_snapshot_file(&self.snapshot_backup_file()?) {
+                Ok(Some(snapshot)) => Ok(Some(snapshot)),
+                _ => Err(e),
+            },
+        }
+    }
+
+    fn read_snapshot_file(path: &str) -> Result<Option<Snapshot>> {
+        if !Path::new(path).exists() {
             return Ok(None);
         }
 
-        let content = fs::read_to_string(&self.snapshot_file)?;
-        let snapshot: Snapshot = serde_json::from_str(&content)?;
-        Ok(Some(snapshot))
+        Ok(Some(Snapshot::decode(&fs::read(path)?, path)?))
     }
 
-    /// 重放日志
-    pub fn replay_logs(&self, from_id: u64) -> Result<Vec<LogEntry>> {
-        if !Path::new(&self.log_file).exists() {
-            return Ok(Vec::new());
+    /// 同步数据目录，确保 rename 结果持久化
+    fn sync_data_dir(&self) {
+        if let Some(dir) = &self.data_dir {
+            sync_dir(Path::new(dir));
+        }
+    }
+
+    /// 逐行读取 `from_id` 之后的日志条目
+    ///
+    /// 读取器按顺序打开各日志段，每次只缓冲一行，内存占用与日志总大小无关。
+    /// 遇到第一条损坏或被截断的条目时产生 `CorruptLog` 错误（行号为该条目在所在日志段中的行号）
+    /// 并结束，可调用 `repair_log` 截掉损坏的尾部后再重放。
+    pub fn read_log(&self, from_id: u64) -> Result<LogReader> {
+        let mut reader = LogReader::new(self.log_segments()?, from_id);
+        if let Some(backend) = &self.backend {
+            reader.entries = backend.read_log(from_id)?.into_iter();
         }
+        Ok(reader)
+    }
 
-        let content = fs::read_to_string(&self.log_file)?;
-        let mut entries = Vec::new();
+    /// 校验快照清单、表文件与日志段，把发现的问题记入报告
+    ///
+    /// 与 `read_log` 不同，遇到损坏的条目后继续检查之后的条目；使用持久化后端时通过后端读取快照与日志。
+    pub fn verify_files(&self, report: &mut IntegrityReport) -> Result<()> {
+        if let Some(backend) = &self.backend {
+            report.files_checked += 1;
+            if let Err(e) = backend.load_snapshot() {
+                report.push(IssueKind::Snapshot, "后端快照", e.to_string());
+            }
+            match backend.read_log(0) {
+                Ok(entries) => report.log_entries_checked += entries.len(),
+                Err(e) => report.push(IssueKind::Log, "后端日志", e.to_string()),
+            }
+            return Ok(());
+        }
 
-        for line in content.lines() {
-            if line.trim().is_empty() {
+        let mut checked_files = HashSet::new();
+        for path in [self.snapshot_file()?, self.snapshot_backup_file()?] {
+            if !Path::new(&path).exists() {
+                continue;
+            }
+            report.files_checked += 1;
+            let snapshot = match fs::read(&path).map_err(DatabaseError::from).and_then(|bytes| Snapshot::decode(&bytes, &path)) {
+                Ok(snapshot) => snapshot,
+                Err(e) => {
+                    report.push(IssueKind::Snapshot, &path, e.to_string());
+                    continue;
+                }
+            };
+            for table_file in snapshot.table_files.iter().filter(|table_file| checked_files.insert(table_file.file.clone())) {
+                report.files_checked += 1;
+                let file = self.dir()?.join(TABLES_DIR).join(&table_file.file);
+                match fs::read(&file).map_err(DatabaseError::from).and_then(|bytes| decode_table_file(&bytes)) {
+                    Ok(table) if table.name != table_file.name => report.push(IssueKind::Snapshot, file.display().to_string(), format!(
+                        "文件中的表名 '{}' 与快照清单中的 '{}' 不一致", table.name, table_file.name
+                    )),
+                    Ok(_) => {}
+                    Err(e) => report.push(IssueKind::Snapshot, file.display().to_string(), e.to_string()),
+                }
+            }
+        }
+
+        let mut last_id = 0;
+        for segment in self.log_segments()? {
+            let content = fs::read(&segment)?;
+            for (index, line) in String::from_utf8_lossy(&content).lines().enumerate() {
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+                match LogEntry::decode(line) {
+                    Ok(entry) => {
+                        report.log_entries_checked += 1;
+                        if entry.id <= last_id {
+                            report.push(IssueKind::Log, segment.display().to_string(), format!(
+                                "第 {} 行: 日志ID {} 不大于前一条的 {}", index + 1, entry.id, last_id
+                            ));
+                        }
+                        last_id = entry.id;
+                    }
+                    Err(reason) => report.push(IssueKind::Log, segment.display().to_string(), format!("第 {} 行: {}", index + 1, reason)),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 重放日志，把 `read_log` 读到的条目收集起来
+    pub fn replay_logs(&self, from_id: u64) -> Result<Vec<LogEntry>> {
+        self.read_log(from_id)?.collect()
+    }
+
+    /// 修复日志：从第一条损坏的条目开始截断所在的日志段，并删除其后的所有日志段
+    ///
+    /// 返回被丢弃的行数。
+    pub fn repair_log(&mut self) -> Result<usize> {
+        let segments = self.log_segments()?;
+        let mut dropped = 0;
+        let mut corrupt = false;
+
+        for segment in segments {
+            let content = fs::read_to_string(&segment)?;
+            if corrupt {
+                dropped += content.lines().filter(|l| !l.trim().is_empty()).count();
+                fs::remove_file(&segment)?;
                 continue;
             }
 
-            if let Ok(entry) = serde_json::from_str::<LogEntry>(line) {
-                if entry.id > from_id {
-                    entries.push(entry);
+            let mut valid_len = 0;
+            let mut corrupt_at = None;
+            for line in content.split_inclusive('\n') {
+                let trimmed = line.trim();
+                if !trimmed.is_empty() && (!line.ends_with('\n') || LogEntry::decode(trimmed).is_err()) {
+                    corrupt_at = Some(valid_len);
+                    break;
                 }
+                valid_len += line.len();
+            }
+
+            if let Some(offset) = corrupt_at {
+                corrupt = true;
+                dropped += content[offset..].lines().filter(|l| !l.trim().is_empty()).count();
+                let file = fs::OpenOptions::new().write(true).open(&segment)?;
+                file.set_len(offset as u64)?;
+                file.sync_all()?;
+            }
+        }
+
+        if corrupt {
+            self.active_segment = None;
+            self.sync_data_dir();
+        }
+        Ok(dropped)
+    }
+
+    /// 把旧版本的日志条目按当前格式重写，返回升级的条目数
+    ///
+    /// 日志 ID 与时间戳保持不变；含旧版本条目的日志段先写入临时文件再原子替换。日志中有损坏的条目时返回
+    /// `CorruptLog` 错误且不修改该日志段，应先调用 `repair_log`。
+    /// 最早版本写入的 `transaction.log` 先按 [`crate::legacy`] 整理：丢弃无法解析的行，编号重复时重新编号。
+    pub fn upgrade_log(&mut self) -> Result<usize> {
+        self.upgrade_legacy_log()?;
+        let mut upgraded = 0;
+
+        for segment in self.log_segments()? {
+            let content = fs::read_to_string(&segment)?;
+            let mut entries = Vec::new();
+            for (index, line) in content.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+                entries.push(LogEntry::decode(line).map_err(|reason| corrupt_segment(&segment, index + 1, reason))?);
+            }
+            let legacy = entries.iter().filter(|entry| entry.version < LOG_FORMAT_VERSION).count();
+            if legacy == 0 {
+                continue;
+            }
+
+            let mut out = String::new();
+            for mut entry in entries {
+                entry.version = LOG_FORMAT_VERSION;
+                out.push_str(&entry.encode_with(self.compress_log)?);
+                out.push('\n');
             }
+            self.rewrite_segment(&segment, &out)?;
+            upgraded += legacy;
         }
 
-        Ok(entries)
+        if upgraded > 0 {
+            self.active_segment = None;
+            self.sync_data_dir();
+        }
+        Ok(upgraded)
     }
 
-    /// 清理旧日志
-    pub fn cleanup_logs(&self) -> Result<()> {
-        if Path::new(&self.log_file).exists() {
-            fs::remove_file(&self.log_file)?;
+    /// 整理最早版本写入的 `transaction.log`，其中的条目仍为版本 1，由 `upgrade_log` 接着升级
+    fn upgrade_legacy_log(&mut self) -> Result<()> {
+        let Some(legacy_file) = self.data_dir.as_deref().map(|dir| Path::new(dir).join(LEGACY_LOG_FILE)) else {
+            return Ok(());
+        };
+        if !legacy_file.exists() {
+            return Ok(());
+        }
+        let Some(mut log) = LegacyLog::parse(&fs::read_to_string(&legacy_file)?) else {
+            return Ok(());
+        };
+        // 已有编号的日志段时说明之后的版本接着写入过，编号以它们为准
+        let renumber = log.needs_renumber() && self.log_segments()?.len() == 1;
+        if !renumber && log.skipped == 0 {
+            return Ok(());
         }
+
+        if renumber {
+            log.renumber();
+            // 先改写快照：日志改写前崩溃时仍是未重新编号的旧日志，下次加载按时间重新换算
+            if let Some(mut snapshot) = self.load_snapshot()? {
+                snapshot.last_log_id = log.snapshot_position(snapshot.timestamp);
+                self.save_snapshot(&snapshot)?;
+            }
+        }
+        let mut out = String::new();
+        for entry in &log.entries {
+            out.push_str(&entry.encode_with(self.compress_log)?);
+            out.push('\n');
+        }
+        self.rewrite_segment(&legacy_file, &out)?;
+        self.active_segment = None;
+        Ok(())
+    }
+
+    /// 用新内容原子替换日志段
+    fn rewrite_segment(&self, segment: &Path, content: &str) -> Result<()> {
+        let tmp_file = segment.with_extension("log.tmp");
+        {
+            let mut file = fs::File::create(&tmp_file)?;
+            file.write_all(content.as_bytes())?;
+            file.sync_all()?;
+        }
+        fs::rename(&tmp_file, segment)?;
+        Ok(())
+    }
+
+    /// 清理旧日志，删除所有日志段
+    pub fn cleanup_logs(&mut self) -> Result<()> {
+        for segment in self.log_segments()? {
+            fs::remove_file(segment)?;
+        }
+        self.active_segment = None;
         Ok(())
     }
 
     /// 获取数据目录中的所有表
     pub fn list_tables(&self) -> Result<Vec<String>> {
-        if !Path::new(&self.data_dir).exists() {
+        let Some(dir) = self.data_dir.as_deref().filter(|dir| Path::new(dir).exists()) else {
             return Ok(Vec::new());
-        }
+        };
 
         let mut tables = Vec::new();
-        for entry in fs::read_dir(&self.data_dir)? {
+        for entry in fs::read_dir(dir)? {
             let entry = entry?;
             let path = entry.path();
 
@@ -174,66 +1378,320 @@ impl StorageEngine {
         Ok(tables)
     }
 
-    /// 备份数据库
-    pub fn backup(&self, backup_path: &str) -> Result<()> {
-        if !Path::new(backup_path).exists() {
-            fs::create_dir_all(backup_path)?;
-        }
-
-        let backup_log_file = format!("{}/transaction.log", backup_path);
-        let backup_snapshot_file = format!("{}/snapshot.json", backup_path);
+    /// 备份数据库
+    pub fn backup(&mut self, backup_path: &str) -> Result<()> {
+        if !Path::new(backup_path).exists() {
+            fs::create_dir_all(backup_path)?;
+        }
+
+        for (name, data) in self.backup_files()? {
+            fs::write(Path::new(backup_path).join(name), data)?;
+        }
+
+        self.mark_backup(self.current_log_id);
+
+        Ok(())
+    }
+
+    /// 读取需要备份的文件（各日志段、快照及其引用的表文件），返回 (文件名, 内容)
+    ///
+    /// 表文件的名称带 `tables/` 前缀。
+    pub fn backup_files(&self) -> Result<Vec<(String, Vec<u8>)>> {
+        let snapshot_file = self.snapshot_file()?;
+        let mut files = Vec::new();
+        for segment in self.log_segments()? {
+            let name = segment.file_name().and_then(|name| name.to_str()).unwrap_or_default().to_string();
+            files.push((name, fs::read(&segment)?));
+        }
+        if let Some(snapshot) = Self::read_snapshot_file(&snapshot_file)? {
+            for table_file in &snapshot.table_files {
+                let data = fs::read(self.dir()?.join(TABLES_DIR).join(&table_file.file))?;
+                files.push((format!("{}/{}", TABLES_DIR, table_file.file), data));
+            }
+            files.push(("snapshot.json".to_string(), fs::read(&snapshot_file)?));
+        }
+        Ok(files)
+    }
+
+    /// 建立热备份的检查点：记录当前日志位置并捕获各表的状态
+    ///
+    /// 只在调用期间需要磁盘存储与内存存储的锁；已加载的表按写时复制克隆，不复制行数据。
+    /// 检查点存活期间不会删除或改写日志段与表文件，之后的写入照常进行。
+    pub fn checkpoint(&self, storage: &MemoryStorage) -> Result<Checkpoint> {
+        self.backups_in_progress.fetch_add(1, Ordering::SeqCst);
+        let pin = BackupPin(Arc::clone(&self.backups_in_progress));
+        Ok(Checkpoint {
+            log_id: self.current_log_id,
+            segments: self.log_segments()?,
+            tables: storage.checkpoint_tables(&self.temp_tables),
+            procedures: storage.get_procedures(),
+            sequences: storage.get_sequences(),
+            prepared: storage.prepared_transactions(),
+            committed_transactions: storage.committed_transactions(),
+            unlogged_tables: self.unlogged_table_names(),
+            validation_mode: storage.validation_mode(),
+            _pin: pin,
+        })
+    }
+
+    fn backup_in_progress(&self) -> bool {
+        self.backups_in_progress.load(Ordering::SeqCst) > 0
+    }
+
+    /// 记录已备份到的日志ID，保留策略据此判断日志能否删除
+    pub fn mark_backup(&mut self, log_id: u64) {
+        self.last_backup_log_id = self.last_backup_log_id.max(log_id);
+    }
+
+    /// 恢复数据库
+    ///
+    /// 备份中有日志段或快照时先删除现有的全部日志段，再复制备份的日志段，
+    /// 避免现有的日志接在备份的快照之后重放。
+    pub fn restore(&mut self, backup_path: &str) -> Result<()> {
+        let dir = self.dir()?.to_path_buf();
+        let backup_snapshot_file = format!("{}/snapshot.json", backup_path);
+        // 恢复后内存中的数据整体替换，临时表随之消失
+        self.temp_tables.clear();
+
+        let mut backup_segments = Vec::new();
+        if Path::new(backup_path).exists() {
+            for entry in fs::read_dir(backup_path)? {
+                let path = entry?.path();
+                let name = path.file_name().and_then(|name| name.to_str()).map(str::to_string);
+                if let Some(name) = name.filter(|name| path.is_file() && segment_seq(name).is_some()) {
+                    backup_segments.push((name, path));
+                }
+            }
+        }
+        if !backup_segments.is_empty() || Path::new(&backup_snapshot_file).exists() {
+            self.cleanup_logs()?;
+            for (name, path) in backup_segments {
+                fs::copy(path, dir.join(name))?;
+            }
+        }
+
+        let backup_tables_dir = Path::new(backup_path).join(TABLES_DIR);
+        if backup_tables_dir.is_dir() {
+            let tables_dir = dir.join(TABLES_DIR);
+            fs::create_dir_all(&tables_dir)?;
+            for entry in fs::read_dir(&backup_tables_dir)? {
+                let entry = entry?;
+                fs::copy(entry.path(), tables_dir.join(entry.file_name()))?;
+            }
+        }
+        if Path::new(&backup_snapshot_file).exists() {
+            fs::copy(&backup_snapshot_file, self.snapshot_file()?)?;
+        }
+
+        Ok(())
+    }
+
+    /// 获取存储统计信息
+    pub fn get_stats(&self) -> Result<StorageStats> {
+        let mut stats = StorageStats::new();
+
+        for segment in self.log_segments()? {
+            stats.log_file_size += fs::metadata(&segment)?.len();
+            stats.total_log_entries += BufReader::new(fs::File::open(&segment)?).lines().count();
+            stats.log_segments += 1;
+        }
+
+        if self.data_dir.is_none() {
+            return Ok(stats);
+        }
+        let snapshot_file = self.snapshot_file()?;
+        if Path::new(&snapshot_file).exists() {
+            let metadata = fs::metadata(&snapshot_file)?;
+            stats.snapshot_file_size = metadata.len();
+        }
+        let tables_dir = self.dir()?.join(TABLES_DIR);
+        if tables_dir.is_dir() {
+            for entry in fs::read_dir(&tables_dir)? {
+                stats.snapshot_file_size += entry?.metadata()?.len();
+            }
+        }
+
+        Ok(stats)
+    }
+}
+
+impl Default for StorageEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 热备份的检查点，见 [`StorageEngine::checkpoint`]
+pub struct Checkpoint {
+    log_id: u64,
+    segments: Vec<PathBuf>,
+    tables: Vec<(String, TableCopy)>,
+    procedures: Vec<Procedure>,
+    sequences: Vec<Sequence>,
+    prepared: Vec<PreparedTransaction>,
+    committed_transactions: Vec<Uuid>,
+    unlogged_tables: Vec<String>,
+    validation_mode: ValidationMode,
+    _pin: BackupPin,
+}
 
-        if Path::new(&self.log_file).exists() {
-            fs::copy(&self.log_file, &backup_log_file)?;
-        }
+/// 检查点捕获的表：已加载的表为克隆，尚未加载的表直接复制其表文件
+enum TableCopy {
+    Loaded(Box<Table>),
+    File(PathBuf),
+}
 
-        if Path::new(&self.snapshot_file).exists() {
-            fs::copy(&self.snapshot_file, &backup_snapshot_file)?;
-        }
+/// 释放时减少进行中的热备份数
+struct BackupPin(Arc<AtomicUsize>);
 
-        Ok(())
+impl Drop for BackupPin {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
     }
+}
 
-    /// 恢复数据库
-    pub fn restore(&self, backup_path: &str) -> Result<()> {
-        let backup_log_file = format!("{}/transaction.log", backup_path);
-        let backup_snapshot_file = format!("{}/snapshot.json", backup_path);
+impl Checkpoint {
+    /// 检查点的日志位置
+    pub fn log_id(&self) -> u64 {
+        self.log_id
+    }
 
-        if Path::new(&backup_log_file).exists() {
-            fs::copy(&backup_log_file, &self.log_file)?;
+    /// 生成备份文件，返回 (文件名, 内容)，布局与数据目录相同
+    ///
+    /// 日志段只保留不晚于检查点的条目，读到检查点位置即停止，不会读到之后仍在追加的内容；
+    /// 快照清单的日志位置为检查点，表文件由捕获的表重新序列化。
+    pub fn files(&self) -> Result<Vec<(String, Vec<u8>)>> {
+        let mut files = Vec::new();
+        let mut reached = self.log_id == 0;
+        for segment in &self.segments {
+            if reached {
+                break;
+            }
+            let mut kept = Vec::new();
+            for (index, line) in BufReader::new(fs::File::open(segment)?).lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let entry = LogEntry::decode(&line)
+                    .map_err(|reason| corrupt_segment(segment, index + 1, reason))?;
+                if entry.id > self.log_id {
+                    reached = true;
+                    break;
+                }
+                kept.extend_from_slice(line.as_bytes());
+                kept.push(b'\n');
+                if entry.id == self.log_id {
+                    reached = true;
+                    break;
+                }
+            }
+            if !kept.is_empty() {
+                let name = segment.file_name().and_then(|name| name.to_str()).unwrap_or_default().to_string();
+                files.push((name, kept));
+            }
         }
 
-        if Path::new(&backup_snapshot_file).exists() {
-            fs::copy(&backup_snapshot_file, &self.snapshot_file)?;
+        let mut snapshot = Snapshot::new(Vec::new(), self.log_id);
+        snapshot.procedures = self.procedures.clone();
+        snapshot.sequences = self.sequences.clone();
+        snapshot.prepared = self.prepared.clone();
+        snapshot.committed_transactions = self.committed_transactions.clone();
+        snapshot.unlogged_tables = self.unlogged_tables.clone();
+        snapshot.validation_mode = self.validation_mode;
+        for (name, table) in &self.tables {
+            let data = match table {
+                TableCopy::Loaded(table) => encode_table_file(table)?,
+                TableCopy::File(path) => fs::read(path)?,
+            };
+            let file = format!("{}.json", Uuid::new_v4());
+            files.push((format!("{}/{}", TABLES_DIR, file), data));
+            snapshot.table_files.push(TableFile { name: name.clone(), file });
         }
-
-        Ok(())
+        files.push(("snapshot.json".to_string(), snapshot.encode()?));
+        Ok(files)
     }
+}
 
-    /// 获取存储统计信息
-    pub fn get_stats(&self) -> Result<StorageStats> {
-        let mut stats = StorageStats::new();
+/// 日志条目读取器，见 [`StorageEngine::read_log`]
+pub struct LogReader {
+    /// 从持久化后端读出的条目，在日志段之前返回
+    entries: std::vec::IntoIter<LogEntry>,
+    segments: std::vec::IntoIter<PathBuf>,
+    /// 正在读取的日志段及已读的行数
+    current: Option<(PathBuf, BufReader<fs::File>, usize)>,
+    from_id: u64,
+    line: String,
+    failed: bool,
+}
+
+impl LogReader {
+    fn new(segments: Vec<PathBuf>, from_id: u64) -> Self {
+        Self {
+            entries: Vec::new().into_iter(),
+            segments: segments.into_iter(),
+            current: None,
+            from_id,
+            line: String::new(),
+            failed: false,
+        }
+    }
 
-        if Path::new(&self.log_file).exists() {
-            let metadata = fs::metadata(&self.log_file)?;
-            stats.log_file_size = metadata.len();
-            let content = fs::read_to_string(&self.log_file)?;
-            stats.total_log_entries = content.lines().count();
+    fn next_entry(&mut self) -> Result<Option<LogEntry>> {
+        if let Some(entry) = self.entries.next() {
+            return Ok(Some(entry));
         }
+        loop {
+            let Some((segment, reader, line_number)) = &mut self.current else {
+                let Some(segment) = self.segments.next() else {
+                    return Ok(None);
+                };
+                let reader = BufReader::new(fs::File::open(&segment)?);
+                self.current = Some((segment, reader, 0));
+                continue;
+            };
 
-        if Path::new(&self.snapshot_file).exists() {
-            let metadata = fs::metadata(&self.snapshot_file)?;
-            stats.snapshot_file_size = metadata.len();
+            self.line.clear();
+            if reader.read_line(&mut self.line)? == 0 {
+                self.current = None;
+                continue;
+            }
+            *line_number += 1;
+
+            let line = self.line.trim_end_matches(['\n', '\r']);
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = LogEntry::decode(line)
+                .map_err(|reason| corrupt_segment(segment, *line_number, reason))?;
+            if entry.id > self.from_id {
+                return Ok(Some(entry));
+            }
         }
+    }
+}
 
-        Ok(stats)
+impl Iterator for LogReader {
+    type Item = Result<LogEntry>;
+
+    /// 出错后不再继续读取
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let result = self.next_entry();
+        self.failed = result.is_err();
+        result.transpose()
     }
 }
 
 /// 存储统计信息
 #[derive(Debug, Default)]
 pub struct StorageStats {
+    /// 所有日志段的总大小
     pub log_file_size: u64,
+    pub log_segments: usize,
+    /// 快照清单与 `tables/` 下表文件的总大小
     pub snapshot_file_size: u64,
     pub total_log_entries: usize,
     pub current_log_id: u64,
@@ -251,31 +1709,229 @@ impl StorageStats {
 
 /// 内存存储后端
 pub struct MemoryStorage {
-    tables: std::collections::HashMap<String, Table>,
+    /// 每张表独立加锁，不同表上的读写互不阻塞；外层锁只保护表目录
+    tables: std::collections::HashMap<String, TableSlot>,
+    procedures: std::collections::HashMap<String, Procedure>,
+    /// 插入时在读锁下取序列值，单独加锁
+    sequences: Mutex<std::collections::HashMap<String, Sequence>>,
+    /// 两阶段提交中已准备的事务
+    prepared: std::collections::HashMap<Uuid, Vec<StorageOperation>>,
+    /// 已提交的两阶段提交事务，见 `DatabaseEngine::forget_transaction`
+    committed: HashSet<Uuid>,
+    validation_mode: ValidationMode,
 }
 
 impl MemoryStorage {
     pub fn new() -> Self {
         Self {
             tables: std::collections::HashMap::new(),
+            procedures: std::collections::HashMap::new(),
+            sequences: Mutex::new(std::collections::HashMap::new()),
+            prepared: std::collections::HashMap::new(),
+            committed: HashSet::new(),
+            validation_mode: ValidationMode::default(),
+        }
+    }
+
+    pub fn validation_mode(&self) -> ValidationMode {
+        self.validation_mode
+    }
+
+    pub fn set_validation_mode(&mut self, mode: ValidationMode) {
+        self.validation_mode = mode;
+    }
+
+    pub fn add_prepared(&mut self, txid: Uuid, operations: Vec<StorageOperation>) -> Result<()> {
+        if self.prepared.contains_key(&txid) || self.committed.contains(&txid) {
+            return Err(DatabaseError::other(format!("事务 '{}' 已准备或已提交", txid)));
+        }
+        self.prepared.insert(txid, operations);
+        Ok(())
+    }
+
+    pub fn take_prepared(&mut self, txid: Uuid) -> Option<Vec<StorageOperation>> {
+        self.prepared.remove(&txid)
+    }
+
+    pub fn is_prepared(&self, txid: Uuid) -> bool {
+        self.prepared.contains_key(&txid)
+    }
+
+    pub fn prepared_transactions(&self) -> Vec<PreparedTransaction> {
+        self.prepared.iter()
+            .map(|(txid, operations)| PreparedTransaction { txid: *txid, operations: operations.clone() })
+            .collect()
+    }
+
+    pub fn mark_committed(&mut self, txid: Uuid) {
+        self.committed.insert(txid);
+    }
+
+    pub fn is_committed(&self, txid: Uuid) -> bool {
+        self.committed.contains(&txid)
+    }
+
+    pub fn forget_committed(&mut self, txid: Uuid) -> bool {
+        self.committed.remove(&txid)
+    }
+
+    pub fn committed_transactions(&self) -> Vec<Uuid> {
+        self.committed.iter().copied().collect()
+    }
+
+    pub fn create_procedure(&mut self, procedure: Procedure) -> Result<()> {
+        if self.procedures.contains_key(&procedure.name) {
+            return Err(DatabaseError::other(format!("存储过程 '{}' 已存在", procedure.name)));
+        }
+        self.procedures.insert(procedure.name.clone(), procedure);
+        Ok(())
+    }
+
+    pub fn drop_procedure(&mut self, name: &str) -> Result<()> {
+        self.procedures.remove(name)
+            .map(|_| ())
+            .ok_or_else(|| DatabaseError::other(format!("存储过程 '{}' 不存在", name)))
+    }
+
+    pub fn get_procedure(&self, name: &str) -> Option<&Procedure> {
+        self.procedures.get(name)
+    }
+
+    pub fn get_procedures(&self) -> Vec<Procedure> {
+        self.procedures.values().cloned().collect()
+    }
+
+    pub fn create_sequence(&mut self, sequence: Sequence) -> Result<()> {
+        let sequences = self.sequences.get_mut().unwrap();
+        if sequences.contains_key(&sequence.name) {
+            return Err(DatabaseError::other(format!("序列 '{}' 已存在", sequence.name)));
         }
+        sequences.insert(sequence.name.clone(), sequence);
+        Ok(())
+    }
+
+    pub fn drop_sequence(&mut self, name: &str) -> Result<()> {
+        self.sequences.get_mut().unwrap().remove(name)
+            .map(|_| ())
+            .ok_or_else(|| sequence_not_found(name))
+    }
+
+    /// 取序列的下一个值；只需要读锁，调用方负责记录 `SetSequence` 日志
+    pub fn next_sequence_value(&self, name: &str) -> Result<i64> {
+        self.sequences.lock().unwrap().get_mut(name)
+            .ok_or_else(|| sequence_not_found(name))?
+            .next_value()
+    }
+
+    pub fn current_sequence_value(&self, name: &str) -> Result<i64> {
+        self.sequences.lock().unwrap().get(name)
+            .ok_or_else(|| sequence_not_found(name))?
+            .current_value()
+    }
+
+    /// 重放日志时恢复序列的当前值
+    pub fn set_sequence_value(&mut self, name: &str, value: i64) -> Result<()> {
+        let sequence = self.sequences.get_mut().unwrap().get_mut(name)
+            .ok_or_else(|| sequence_not_found(name))?;
+        sequence.current = Some(value);
+        Ok(())
+    }
+
+    /// 所有序列，按名称排序
+    pub fn get_sequences(&self) -> Vec<Sequence> {
+        let mut sequences: Vec<Sequence> = self.sequences.lock().unwrap().values().cloned().collect();
+        sequences.sort_by(|a, b| a.name.cmp(&b.name));
+        sequences
     }
 
     pub fn create_table(&mut self, name: &str, schema: Schema) -> Result<()> {
-        if self.tables.contains_key(name) {
+        self.create_table_with_layout(name, schema, TableLayout::Row)
+    }
+
+    pub fn create_table_with_layout(&mut self, name: &str, schema: Schema, layout: TableLayout) -> Result<()> {
+        self.create_table_with_options(name, schema, TableOptions::new().layout(layout))
+    }
+
+    pub fn create_table_with_options(&mut self, name: &str, schema: Schema, options: TableOptions) -> Result<()> {
+        if self.tables.contains_key(name) || crate::catalog::is_system_table(name) {
             return Err(DatabaseError::TableExists(name.to_string()));
         }
 
-        self.tables.insert(name.to_string(), Table::new(name.to_string(), schema));
+        let table = Table::with_options(name.to_string(), schema, options)?;
+        self.tables.insert(name.to_string(), TableSlot::new(table));
+        Ok(())
+    }
+
+    /// 加入一张完整的表，例如从旧版快照读出的表
+    pub fn add_table(&mut self, mut table: Table) -> Result<()> {
+        if self.tables.contains_key(&table.name) {
+            return Err(DatabaseError::TableExists(table.name));
+        }
+        table.recompute_memory();
+        self.tables.insert(table.name.clone(), TableSlot::new(table));
+        Ok(())
+    }
+
+    /// 登记保存在快照表文件中的表，首次访问时才读入内存
+    pub fn add_table_file(&mut self, name: &str, path: PathBuf) -> Result<()> {
+        if self.tables.contains_key(name) {
+            return Err(DatabaseError::TableExists(name.to_string()));
+        }
+        self.tables.insert(name.to_string(), TableSlot::unloaded(name, path));
         Ok(())
     }
 
-    pub fn get_table(&self, name: &str) -> Option<&Table> {
+    /// 表是否已读入内存；不存在的表返回 false
+    pub fn is_table_loaded(&self, name: &str) -> bool {
+        self.tables.get(name).is_some_and(|slot| slot.pending.lock().unwrap().is_none())
+    }
+
+    /// 获取表的读锁，尚未加载的表先从表文件读入
+    pub fn get_table(&self, name: &str) -> Result<Option<RwLockReadGuard<'_, Table>>> {
+        let Some(slot) = self.tables.get(name) else { return Ok(None) };
+        slot.load()?;
+        Ok(Some(slot.table.read().unwrap()))
+    }
+
+    /// 获取表的写锁，只需要表目录的共享访问；尚未加载的表先从表文件读入
+    pub fn get_table_mut(&self, name: &str) -> Result<Option<TableWriteGuard<'_>>> {
+        let Some(slot) = self.tables.get(name) else { return Ok(None) };
+        slot.load()?;
+        Ok(Some(TableWriteGuard {
+            table: slot.table.write().unwrap(),
+            memory_bytes: &slot.memory_bytes,
+            version: &slot.version,
+        }))
+    }
+
+    /// 表的版本，每释放一次写锁加一；不存在的表返回 None
+    pub fn table_version(&self, name: &str) -> Option<u64> {
+        self.tables.get(name).map(|slot| slot.version.load(Ordering::Relaxed))
+    }
+
+    /// 所有表的写时复制克隆及克隆时的版本，尚未加载的表先从表文件读入
+    pub fn snapshot_tables(&self) -> Result<HashMap<String, (Table, u64)>> {
+        self.tables.iter()
+            .map(|(name, slot)| {
+                slot.load()?;
+                let table = slot.table.read().unwrap();
+                Ok((name.clone(), (table.clone(), slot.version.load(Ordering::Relaxed))))
+            })
+            .collect()
+    }
+
+    /// 表最近一次写入后的内存占用估算，不需要获取表锁
+    pub fn table_memory_bytes(&self, name: &str) -> usize {
         self.tables.get(name)
+            .map(|slot| slot.memory_bytes.load(Ordering::Relaxed))
+            .unwrap_or(0)
     }
 
-    pub fn get_table_mut(&mut self, name: &str) -> Option<&mut Table> {
-        self.tables.get_mut(name)
+    /// 所有表的内存占用估算之和，不需要获取表锁
+    pub fn memory_bytes(&self) -> usize {
+        self.tables.values()
+            .map(|slot| slot.memory_bytes.load(Ordering::Relaxed))
+            .sum()
     }
 
     pub fn drop_table(&mut self, name: &str) -> Result<()> {
@@ -285,12 +1941,60 @@ impl MemoryStorage {
         Ok(())
     }
 
+    /// 重命名表，尚未加载的表先读入内存，分区随之改名
+    pub fn rename_table(&mut self, name: &str, new_name: &str) -> Result<()> {
+        if self.tables.contains_key(new_name) || crate::catalog::is_system_table(new_name) {
+            return Err(DatabaseError::TableExists(new_name.to_string()));
+        }
+        {
+            let mut table = self.get_table_mut(name)?
+                .ok_or_else(|| DatabaseError::TableNotFound(name.to_string()))?;
+            table.name = new_name.to_string();
+            for (index, partition) in table.partitions.iter_mut().enumerate() {
+                partition.name = format!("{}#p{}", new_name, index);
+            }
+        }
+        let slot = self.tables.remove(name).expect("表已存在");
+        self.tables.insert(new_name.to_string(), slot);
+        Ok(())
+    }
+
+    /// 以源表的表结构与建表选项创建新表，`with_data` 时复制所有行（保留行 ID）
+    ///
+    /// 统计信息、溢出策略等运行时设置不复制。
+    pub fn copy_table(&mut self, source: &str, name: &str, with_data: bool) -> Result<()> {
+        if self.tables.contains_key(name) || crate::catalog::is_system_table(name) {
+            return Err(DatabaseError::TableExists(name.to_string()));
+        }
+        let table = {
+            let source_table = self.get_table(source)?
+                .ok_or_else(|| DatabaseError::TableNotFound(source.to_string()))?;
+            let options = TableOptions { layout: source_table.layout, partition: source_table.partition_spec.clone(), ..TableOptions::default() };
+            let mut table = Table::with_options(name.to_string(), source_table.schema.clone(), options)?;
+            if with_data {
+                table.insert_batch(source_table.all_rows()?.into_owned())?;
+            }
+            table
+        };
+        self.add_table(table)
+    }
+
+    /// 创建表并写入给定的行，任意一行写入失败时不创建表
+    pub fn create_table_as(&mut self, name: &str, schema: Schema, rows: Vec<Row>) -> Result<()> {
+        if self.tables.contains_key(name) || crate::catalog::is_system_table(name) {
+            return Err(DatabaseError::TableExists(name.to_string()));
+        }
+        let mut table = Table::new(name.to_string(), schema);
+        table.insert_batch(rows)?;
+        self.add_table(table)
+    }
+
     pub fn list_tables(&self) -> Vec<String> {
         self.tables.keys().cloned().collect()
     }
 
-    pub fn insert_row(&mut self, table_name: &str, row: Row) -> Result<()> {
-        if let Some(table) = self.tables.get_mut(table_name) {
+    pub fn insert_row(&self, table_name: &str, row: Row) -> Result<()> {
+        if let Some(mut table) = self.get_table_mut(table_name)? {
             table.insert(row)?;
             Ok(())
         } else {
@@ -298,17 +2002,30 @@ impl MemoryStorage {
         }
     }
 
-    pub fn update_row(&mut self, table_name: &str, id: uuid::Uuid, updates: std::collections::HashMap<String, Value>) -> Result<()> {
-        if let Some(table) = self.tables.get_mut(table_name) {
-            table.update(id, updates)?;
+    pub fn insert_rows(&self, table_name: &str, rows: Vec<Row>) -> Result<()> {
+        if let Some(mut table) = self.get_table_mut(table_name)? {
+            table.insert_batch(rows)?;
+            Ok(())
+        } else {
+            Err(DatabaseError::TableNotFound(table_name.to_string()))
+        }
+    }
+
+    pub fn update_row(&self, table_name: &str, id: uuid::Uuid, updates: std::collections::HashMap<String, Value>) -> Result<()> {
+        self.update_row_at(table_name, id, updates, clock::now())
+    }
+
+    pub fn update_row_at(&self, table_name: &str, id: uuid::Uuid, updates: std::collections::HashMap<String, Value>, updated_at: DateTime<Utc>) -> Result<()> {
+        if let Some(mut table) = self.get_table_mut(table_name)? {
+            table.update_at(id, updates, updated_at)?;
             Ok(())
         } else {
             Err(DatabaseError::TableNotFound(table_name.to_string()))
         }
     }
 
-    pub fn delete_row(&mut self, table_name: &str, id: uuid::Uuid) -> Result<()> {
-        if let Some(table) = self.tables.get_mut(table_name) {
+    pub fn delete_row(&self, table_name: &str, id: uuid::Uuid) -> Result<()> {
+        if let Some(mut table) = self.get_table_mut(table_name)? {
             table.delete(id)?;
             Ok(())
         } else {
@@ -316,8 +2033,177 @@ impl MemoryStorage {
         }
     }
 
-    pub fn get_all_data(&self) -> Vec<Table> {
-        self.tables.values().cloned().collect()
+    /// 更新满足全部条件的行，返回更新的行数
+    pub fn update_rows_where(&self, table_name: &str, conditions: &[Condition], updates: &std::collections::HashMap<String, Value>, updated_at: DateTime<Utc>) -> Result<usize> {
+        let mut table = self.get_table_mut(table_name)?
+            .ok_or_else(|| DatabaseError::TableNotFound(table_name.to_string()))?;
+        let updated = table.update_where(|row| matches_all(conditions, row), updates, updated_at)?;
+        Ok(updated.len())
+    }
+
+    /// 清空表，返回删除的行数
+    pub fn truncate_table(&self, table_name: &str) -> Result<usize> {
+        let mut table = self.get_table_mut(table_name)?
+            .ok_or_else(|| DatabaseError::TableNotFound(table_name.to_string()))?;
+        Ok(table.truncate())
+    }
+
+    /// 删除满足全部条件的行，返回删除的行数
+    pub fn delete_rows_where(&self, table_name: &str, conditions: &[Condition]) -> Result<usize> {
+        let mut table = self.get_table_mut(table_name)?
+            .ok_or_else(|| DatabaseError::TableNotFound(table_name.to_string()))?;
+        Ok(table.delete_where(|row| matches_all(conditions, row))?.len())
+    }
+
+    /// 所有表的完整副本（包括已溢出到磁盘的行）
+    pub fn get_all_data(&self) -> Result<Vec<Table>> {
+        self.tables.values()
+            .map(|slot| {
+                slot.load()?;
+                slot.table.read().unwrap().materialize()
+            })
+            .collect()
+    }
+
+    /// 捕获 `excluded` 之外所有表的当前状态，供热备份在不持锁的情况下序列化
+    fn checkpoint_tables(&self, excluded: &HashSet<String>) -> Vec<(String, TableCopy)> {
+        self.tables.iter()
+            .filter(|(name, _)| !excluded.contains(*name))
+            .map(|(name, slot)| {
+                let table = slot.table.read().unwrap();
+                let copy = match slot.pending.lock().unwrap().as_ref() {
+                    Some(path) => TableCopy::File(path.clone()),
+                    None => TableCopy::Loaded(Box::new(table.clone())),
+                };
+                (name.clone(), copy)
+            })
+            .collect()
+    }
+
+    /// 把 `excluded` 之外的每张表写入 `dir` 下的独立文件，返回快照清单中的表文件列表
+    pub(crate) fn save_tables(&self, dir: &Path, excluded: &HashSet<String>) -> Result<Vec<TableFile>> {
+        self.tables.iter()
+            .filter(|(name, _)| !excluded.contains(*name))
+            .map(|(name, slot)| Ok(TableFile { name: name.clone(), file: slot.save(dir)? }))
+            .collect()
+    }
+}
+
+/// 行是否满足全部条件，UNKNOWN 与求值错误都视为不满足
+pub(crate) fn matches_all(conditions: &[Condition], row: &Row) -> bool {
+    conditions.iter().all(|condition| condition.evaluate(row).unwrap_or(false))
+}
+
+/// 表及其内存占用估算，估算值在写锁释放时更新，供无锁读取
+struct TableSlot {
+    table: RwLock<Table>,
+    memory_bytes: AtomicUsize,
+    /// 尚未读入内存的表所在的表文件；加载前 `table` 只是占位的空表
+    pending: Mutex<Option<PathBuf>>,
+    /// 写锁释放一次加一，用于判断表自上次保存后是否修改过
+    version: AtomicU64,
+    /// 最近保存的表文件及保存时的版本
+    saved: Mutex<Option<(PathBuf, u64)>>,
+}
+
+impl TableSlot {
+    fn new(table: Table) -> Self {
+        Self {
+            memory_bytes: AtomicUsize::new(table.estimated_bytes()),
+            table: RwLock::new(table),
+            pending: Mutex::new(None),
+            version: AtomicU64::new(0),
+            saved: Mutex::new(None),
+        }
+    }
+
+    fn unloaded(name: &str, path: PathBuf) -> Self {
+        Self {
+            memory_bytes: AtomicUsize::new(0),
+            table: RwLock::new(Table::new(name.to_string(), Schema::new(Vec::new()))),
+            pending: Mutex::new(Some(path.clone())),
+            version: AtomicU64::new(0),
+            saved: Mutex::new(Some((path, 0))),
+        }
+    }
+
+    /// 读入尚未加载的表；并发的首次访问中只有一个会读取文件
+    fn load(&self) -> Result<()> {
+        let mut pending = self.pending.lock().unwrap();
+        let Some(path) = pending.as_ref() else { return Ok(()) };
+        let mut table = fs::read(path)
+            .map_err(DatabaseError::from)
+            .and_then(|bytes| decode_table_file(&bytes))
+            .map_err(|e| DatabaseError::other(format!("读取表文件 {} 失败: {}", path.display(), e)))?;
+        table.recompute_memory();
+        self.memory_bytes.store(table.estimated_bytes(), Ordering::Relaxed);
+        *self.table.write().unwrap() = table;
+        *pending = None;
+        Ok(())
+    }
+
+    /// 把表写入 `dir` 下的文件并返回文件名；自上次保存后未修改的表直接沿用原文件
+    ///
+    /// 新文件先写入临时文件并 fsync，再 rename 为正式文件名。
+    fn save(&self, dir: &Path) -> Result<String> {
+        {
+            // 持有读锁期间版本不会变化
+            let _table = self.table.read().unwrap();
+            let version = self.version.load(Ordering::Relaxed);
+            let saved = self.saved.lock().unwrap();
+            let reusable = saved.as_ref()
+                .filter(|(path, saved_version)| *saved_version == version && path.parent() == Some(dir) && path.exists())
+                .and_then(|(path, _)| path.file_name()?.to_str());
+            if let Some(file) = reusable {
+                return Ok(file.to_string());
+            }
+        }
+
+        self.load()?;
+        let table = self.table.read().unwrap();
+        let file = format!("{}.json", Uuid::new_v4());
+        let path = dir.join(&file);
+        let tmp_path = dir.join(format!("{}.tmp", file));
+        if table.schema.settings.compressed {
+            let mut file = fs::File::create(&tmp_path)?;
+            file.write_all(&encode_table_file(&table)?)?;
+            file.sync_all()?;
+        } else if table.has_spilled() {
+            write_json_synced(&tmp_path, &table.materialize()?)?;
+        } else {
+            write_json_synced(&tmp_path, &*table)?;
+        }
+        fs::rename(&tmp_path, &path)?;
+        *self.saved.lock().unwrap() = Some((path, self.version.load(Ordering::Relaxed)));
+        Ok(file)
+    }
+}
+
+/// 表的写锁，释放时同步内存占用估算并递增表的版本
+pub struct TableWriteGuard<'a> {
+    table: RwLockWriteGuard<'a, Table>,
+    memory_bytes: &'a AtomicUsize,
+    version: &'a AtomicU64,
+}
+
+impl Deref for TableWriteGuard<'_> {
+    type Target = Table;
+
+    fn deref(&self) -> &Table {
+        &self.table
+    }
+}
+
+impl DerefMut for TableWriteGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Table {
+        &mut self.table
+    }
+}
+
+impl Drop for TableWriteGuard<'_> {
+    fn drop(&mut self) {
+        self.memory_bytes.store(self.table.estimated_bytes(), Ordering::Relaxed);
+        self.version.fetch_add(1, Ordering::Relaxed);
     }
 }
 
@@ -347,11 +2233,13 @@ mod tests {
 
     #[test]
     fn test_storage_engine() {
-        let engine = StorageEngine::new();
+        let data_dir = temp_data_dir();
+        let engine = StorageEngine::with_data_dir(&data_dir);
         assert!(engine.initialize().is_ok());
 
         let stats = engine.get_stats();
         assert!(stats.is_ok());
+        std::fs::remove_dir_all(&data_dir).unwrap();
     }
 
     #[test]
@@ -359,6 +2247,8 @@ mod tests {
         let operation = StorageOperation::Create {
             table: "test".to_string(),
             schema: Schema::new(vec![]),
+            layout: TableLayout::Row,
+            partition: None,
         };
 
         let entry = LogEntry::new(1, operation);
@@ -367,4 +2257,354 @@ mod tests {
 
         assert_eq!(entry.id, deserialized.id);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_compressed_log_entries() {
+        let data_dir = temp_data_dir();
+        let mut engine = StorageEngine::with_data_dir(&data_dir);
+        engine.initialize().unwrap();
+
+        let mut row = Row::new();
+        row.set("body", Value::Text("lorem ipsum ".repeat(500)));
+        let insert = StorageOperation::Insert { table: "docs".to_string(), row };
+        engine.record(insert.clone(), true).unwrap();
+        engine.set_log_compression(true);
+        engine.record(insert, true).unwrap();
+        // 短条目不压缩
+        engine.record(StorageOperation::Drop { table: "docs".to_string() }, true).unwrap();
+
+        let content = fs::read_to_string(&engine.log_segments().unwrap()[0]).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        let payload = |line: &str| line.split_once(' ').unwrap().1.chars().next();
+        assert_eq!(lines.iter().map(|line| payload(line)).collect::<Vec<_>>(), [Some('{'), Some('z'), Some('{')]);
+        assert!(lines[1].len() * 10 < lines[0].len());
+
+        let entries = engine.replay_logs(0).unwrap();
+        assert_eq!(entries.len(), 3);
+        match &entries[1].operation {
+            StorageOperation::Insert { row, .. } => assert_eq!(row.get("body"), Some(&Value::Text("lorem ipsum ".repeat(500)))),
+            other => panic!("应为 Insert: {:?}", other),
+        }
+
+        // 压缩内容被改动时校验和不匹配
+        let corrupted = lines[1].replacen('z', "zA", 1);
+        assert!(LogEntry::decode(&corrupted).unwrap_err().contains("校验和"));
+
+        fs::remove_dir_all(&data_dir).unwrap();
+    }
+
+    #[test]
+    fn test_upgrade_legacy_log() {
+        let data_dir = temp_data_dir();
+        let mut engine = StorageEngine::with_data_dir(&data_dir);
+        engine.initialize().unwrap();
+
+        // 版本 1 的日志：字符串行ID、[列, 值] 数组，且没有版本字段
+        let id = Uuid::new_v4();
+        let legacy = [
+            format!(r#"{{"id":1,"timestamp":"2024-01-01T00:00:00Z","operation":{{"Update":{{"table":"t","id":"{}","data":[["name",{{"Text":"a"}}]]}}}}}}"#, id),
+            format!(r#"{{"id":2,"timestamp":"2024-01-01T00:00:00Z","operation":{{"Delete":{{"table":"t","id":"{}"}}}}}}"#, id),
+        ];
+        let legacy_file = Path::new(&data_dir).join(LEGACY_LOG_FILE);
+        fs::write(&legacy_file, format!("{}\n{}\n", legacy[0], legacy[1])).unwrap();
+
+        let entries = engine.replay_logs(0).unwrap();
+        match &entries[0].operation {
+            StorageOperation::Update { id: row_id, data, .. } => {
+                assert_eq!(*row_id, id);
+                assert_eq!(data.get("name"), Some(&Value::Text("a".to_string())));
+            }
+            other => panic!("应为 Update: {:?}", other),
+        }
+        assert_eq!(entries[1].version, 1);
+
+        assert_eq!(engine.upgrade_log().unwrap(), 2);
+        assert_eq!(engine.upgrade_log().unwrap(), 0);
+        let content = fs::read_to_string(&legacy_file).unwrap();
+        assert!(content.contains(r#""data":{"name":{"Text":"a"}}"#));
+        let upgraded = engine.replay_logs(0).unwrap();
+        assert_eq!((upgraded[1].id, upgraded[1].version), (2, LOG_FORMAT_VERSION));
+        assert_eq!(upgraded[0].timestamp, entries[0].timestamp);
+
+        // 更新版本写入的日志拒绝读取
+        let future = format!(r#"{{"id":3,"timestamp":"2024-01-01T00:00:00Z","operation":{{"Drop":{{"table":"t"}}}},"version":{}}}"#, LOG_FORMAT_VERSION + 1);
+        assert!(LogEntry::decode(&future).unwrap_err().contains("版本"));
+
+        fs::remove_dir_all(&data_dir).unwrap();
+    }
+
+    #[test]
+    fn test_backend_engine_has_no_data_dir() {
+        let engine = StorageEngine::with_backend(Arc::new(crate::backend::MemoryBackend::new()));
+        assert_eq!((engine.data_dir(), engine.tables_dir()), (None, None));
+        // 访问数据目录中文件的操作返回错误，而不是落到当前目录或根目录下
+        assert!(engine.backup_files().is_err());
+        assert_eq!(engine.get_stats().unwrap().snapshot_file_size, 0);
+        assert!(engine.list_tables().unwrap().is_empty());
+    }
+
+    fn temp_data_dir() -> String {
+        let dir = std::env::temp_dir().join(format!("simple-db-test-{}", uuid::Uuid::new_v4()));
+        dir.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn test_per_table_snapshot_files_and_lazy_loading() {
+        let data_dir = temp_data_dir();
+        let engine = StorageEngine::with_data_dir(&data_dir);
+        engine.initialize().unwrap();
+
+        let mut storage = MemoryStorage::new();
+        let schema = Schema::new(vec![
+            ColumnDefinition::new("id", crate::types::DataType::Integer, true),
+        ]);
+        storage.create_table("items", schema.clone()).unwrap();
+        storage.create_table("other", schema).unwrap();
+        let mut row = Row::new();
+        row.set("id", Value::Integer(7));
+        storage.insert_row("items", row.clone()).unwrap();
+
+        engine.save_storage(&storage, 42).unwrap();
+        let snapshot = engine.load_snapshot().unwrap().unwrap();
+        assert_eq!(snapshot.last_log_id, 42);
+        assert!(snapshot.tables.is_empty());
+        assert_eq!(snapshot.table_files.len(), 2);
+        let items_file = |snapshot: &Snapshot| snapshot.table_files.iter().find(|f| f.name == "items").unwrap().file.clone();
+        let first_file = items_file(&snapshot);
+
+        // 表只在首次访问时读入
+        let mut loaded = MemoryStorage::new();
+        for table_file in &snapshot.table_files {
+            loaded.add_table_file(&table_file.name, engine.tables_dir().unwrap().join(&table_file.file)).unwrap();
+        }
+        assert!(!loaded.is_table_loaded("items"));
+        assert_eq!(loaded.memory_bytes(), 0);
+        {
+            let table = loaded.get_table("items").unwrap().unwrap();
+            assert_eq!(table.rows.slots()[0].id, row.id);
+            assert_eq!(table.rows.slots()[0].get("id"), Some(&Value::Integer(7)));
+        }
+        assert!(loaded.is_table_loaded("items") && !loaded.is_table_loaded("other"));
+        assert!(loaded.memory_bytes() > 0);
+
+        // 未修改的表沿用原文件；修改过的表写入新文件，两代快照都不再引用的文件被删除
+        engine.save_storage(&loaded, 43).unwrap();
+        assert_eq!(items_file(&engine.load_snapshot().unwrap().unwrap()), first_file);
+        let mut next = Row::new();
+        next.set("id", Value::Integer(8));
+        loaded.insert_row("items", next).unwrap();
+        engine.save_storage(&loaded, 44).unwrap();
+        let second_file = items_file(&engine.load_snapshot().unwrap().unwrap());
+        assert_ne!(second_file, first_file);
+        let mut next = Row::new();
+        next.set("id", Value::Integer(9));
+        loaded.insert_row("items", next).unwrap();
+        engine.save_storage(&loaded, 45).unwrap();
+        assert!(!engine.tables_dir().unwrap().join(&first_file).exists());
+        assert!(engine.tables_dir().unwrap().join(&second_file).exists());
+        assert_eq!(fs::read_dir(engine.tables_dir().unwrap()).unwrap().count(), 3);
+
+        // 表文件损坏时访问该表报错
+        let other = engine.tables_dir().unwrap().join(&snapshot.table_files.iter().find(|f| f.name == "other").unwrap().file);
+        fs::write(&other, "{").unwrap();
+        let mut broken = MemoryStorage::new();
+        broken.add_table_file("other", other).unwrap();
+        assert!(broken.get_table("other").is_err());
+        assert!(!broken.is_table_loaded("other"));
+
+        fs::remove_dir_all(&data_dir).unwrap();
+    }
+
+    #[test]
+    fn test_snapshot_fallback_to_backup() {
+        let data_dir = temp_data_dir();
+        let engine = StorageEngine::with_data_dir(&data_dir);
+        engine.initialize().unwrap();
+
+        let schema = Schema::new(vec![
+            ColumnDefinition::new("id", crate::types::DataType::Integer, true),
+        ]);
+        engine.create_snapshot(vec![Table::new("first".to_string(), schema.clone())]).unwrap();
+        engine.create_snapshot(vec![Table::new("second".to_string(), schema)]).unwrap();
+
+        assert!(Path::new(&engine.snapshot_backup_file().unwrap()).exists());
+        assert!(!Path::new(&engine.snapshot_tmp_file().unwrap()).exists());
+
+        // 模拟写入中途崩溃导致主快照损坏
+        fs::write(engine.snapshot_file().unwrap(), "{\"tables\": [").unwrap();
+
+        let snapshot = engine.load_snapshot().unwrap().unwrap();
+        assert_eq!(snapshot.table_files[0].name, "first");
+
+        fs::remove_dir_all(&data_dir).unwrap();
+    }
+
+    #[test]
+    fn test_snapshot_header_validation() {
+        let mut snapshot = Snapshot::new(Vec::new(), 7);
+        snapshot.table_files.push(TableFile { name: "users".to_string(), file: "users.json".to_string() });
+        let bytes = snapshot.encode().unwrap();
+        let text = String::from_utf8(bytes.clone()).unwrap();
+        let (header_line, body) = text.split_once('\n').unwrap();
+        let header: SnapshotHeader = serde_json::from_str(header_line).unwrap();
+        assert_eq!(header.format_version, SNAPSHOT_FORMAT_VERSION);
+        assert_eq!(header.engine_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(header.table_count, 1);
+        assert_eq!(Snapshot::decode(&bytes, "snapshot.json").unwrap().last_log_id, 7);
+
+        // 没有文件头的旧版快照仍可读取
+        assert_eq!(Snapshot::decode(body.as_bytes(), "snapshot.json").unwrap().table_files, snapshot.table_files);
+
+        let incompatible = |bytes: &[u8]| match Snapshot::decode(bytes, "snapshot.json") {
+            Err(DatabaseError::IncompatibleSnapshot { path, reason }) => {
+                assert_eq!(path, "snapshot.json");
+                reason
+            }
+            other => panic!("应为 IncompatibleSnapshot: {:?}", other),
+        };
+        let newer = SnapshotHeader { format_version: SNAPSHOT_FORMAT_VERSION + 1, engine_version: "9.0.0".to_string(), ..header.clone() };
+        let reason = incompatible(format!("{}\n{}", serde_json::to_string(&newer).unwrap(), body).as_bytes());
+        assert!(reason.contains("9.0.0"), "{}", reason);
+        assert!(incompatible(text.replacen("users.json", "other.json", 1).as_bytes()).contains("校验和"));
+        let miscounted = SnapshotHeader { table_count: 2, ..header };
+        assert!(incompatible(format!("{}\n{}", serde_json::to_string(&miscounted).unwrap(), body).as_bytes()).contains("2 张表"));
+        assert!(incompatible(b"{\"tables\": [").contains("无法解析"));
+    }
+
+    #[test]
+    fn test_corrupt_log_detection_and_repair() {
+        let data_dir = temp_data_dir();
+        let mut engine = StorageEngine::with_data_dir(&data_dir);
+        engine.initialize().unwrap();
+
+        for i in 0..3 {
+            engine.write_log(StorageOperation::Drop { table: format!("t{}", i) }).unwrap();
+        }
+
+        // 篡改第二条日志，并追加一条被截断的条目
+        let log_file = engine.log_segments().unwrap().remove(0);
+        let content = fs::read_to_string(&log_file).unwrap();
+        let mut lines: Vec<String> = content.lines().map(|l| l.to_string()).collect();
+        lines[1] = lines[1].replace("t1", "tX");
+        fs::write(&log_file, format!("{}\n{{\"id\":4,", lines.join("\n"))).unwrap();
+
+        // 读取器先产出损坏位置之前的条目，出错后结束
+        let mut reader = engine.read_log(0).unwrap();
+        assert_eq!(reader.next().unwrap().unwrap().id, 1);
+        assert!(matches!(reader.next(), Some(Err(DatabaseError::CorruptLog { line: 2, .. }))));
+        assert!(reader.next().is_none());
+
+        match engine.replay_logs(0) {
+            Err(DatabaseError::CorruptLog { line, .. }) => assert_eq!(line, 2),
+            other => panic!("应返回 CorruptLog 错误: {:?}", other.map(|e| e.len())),
+        }
+
+        assert_eq!(engine.repair_log().unwrap(), 3);
+        assert_eq!(engine.replay_logs(0).unwrap().len(), 1);
+        assert_eq!(engine.repair_log().unwrap(), 0);
+
+        fs::remove_dir_all(&data_dir).unwrap();
+    }
+
+    #[test]
+    fn test_log_retention() {
+        let data_dir = temp_data_dir();
+        let mut engine = StorageEngine::with_data_dir(&data_dir);
+        engine.initialize().unwrap();
+
+        for table in ["events", "events", "audit", "audit"] {
+            engine.write_log(StorageOperation::Drop { table: table.to_string() }).unwrap();
+        }
+        engine.create_snapshot(Vec::new()).unwrap();
+        engine.write_log(StorageOperation::Drop { table: "events".to_string() }).unwrap();
+
+        // 要求备份覆盖但尚未备份时不处理任何日志
+        engine.set_retention_policy(Some(
+            RetentionPolicy::new(RetentionAction::Archive)
+                .table_action("audit", RetentionAction::Delete)
+                .require_backup(true),
+        ));
+        assert_eq!(engine.apply_retention().unwrap(), 0);
+        assert_eq!(engine.archive_status().unwrap().reclaimable_entries, 0);
+
+        let backup_dir = format!("{}/backup", data_dir);
+        engine.backup(&backup_dir).unwrap();
+        assert_eq!(engine.archive_status().unwrap().reclaimable_entries, 4);
+        assert_eq!(engine.apply_retention().unwrap(), 4);
+
+        let status = engine.archive_status().unwrap();
+        assert_eq!(status.covered_log_id, 4);
+        assert_eq!(status.active_log_entries, 1);
+        assert_eq!(status.archived_segments, 1);
+        assert_eq!(engine.replay_logs(0).unwrap()[0].id, 5);
+
+        // 重启后日志ID继续递增，快照记录的备份位置仍然有效
+        engine.create_snapshot(Vec::new()).unwrap();
+        let mut reopened = StorageEngine::with_data_dir(&data_dir);
+        assert_eq!(reopened.recover_log_id().unwrap(), 5);
+        assert_eq!(reopened.archive_status().unwrap().backup_log_id, 5);
+
+        fs::remove_dir_all(&data_dir).unwrap();
+    }
+
+    #[test]
+    fn test_log_segment_rotation() {
+        let data_dir = temp_data_dir();
+        let mut engine = StorageEngine::with_data_dir(&data_dir);
+        engine.initialize().unwrap();
+        engine.set_max_segment_size(200);
+
+        for i in 0..10 {
+            engine.write_log(StorageOperation::Drop { table: format!("t{}", i) }).unwrap();
+        }
+        let segments = engine.log_segments().unwrap();
+        assert!(segments.len() > 1);
+        assert!(segments.iter().all(|segment| fs::metadata(segment).unwrap().len() <= 200));
+        assert!(segments[0].ends_with(segment_file_name(1)));
+
+        let ids: Vec<u64> = engine.replay_logs(3).unwrap().iter().map(|entry| entry.id).collect();
+        assert_eq!(ids, (4..=10).collect::<Vec<_>>());
+        let stats = engine.get_stats().unwrap();
+        assert_eq!((stats.total_log_entries, stats.log_segments), (10, segments.len()));
+
+        // 旧版日志作为最早的段读取，重启后继续写入最后一个段
+        let legacy_entries = fs::read_to_string(&segments[0]).unwrap().lines().count();
+        fs::rename(&segments[0], Path::new(&data_dir).join(LEGACY_LOG_FILE)).unwrap();
+        let mut reopened = StorageEngine::with_data_dir(&data_dir);
+        reopened.set_max_segment_size(200);
+        assert_eq!(reopened.recover_log_id().unwrap(), 10);
+        reopened.write_log(StorageOperation::Drop { table: "t10".to_string() }).unwrap();
+        let ids: Vec<u64> = reopened.replay_logs(0).unwrap().iter().map(|entry| entry.id).collect();
+        assert_eq!(ids, (1..=11).collect::<Vec<_>>());
+
+        // 修复时损坏的段之后的段一并丢弃
+        let segments = reopened.log_segments().unwrap();
+        fs::write(&segments[1], "{\"id\":").unwrap();
+        match reopened.replay_logs(0) {
+            Err(DatabaseError::CorruptLog { line, reason }) => {
+                assert_eq!(line, 1);
+                assert!(reason.starts_with(&segment_file_name(2)));
+            }
+            other => panic!("应返回 CorruptLog 错误: {:?}", other.map(|e| e.len())),
+        }
+        reopened.repair_log().unwrap();
+        assert_eq!(reopened.log_segments().unwrap().len(), 2);
+        assert_eq!(reopened.replay_logs(0).unwrap().len(), legacy_entries);
+
+        // 热备份只包含检查点之前的条目，之后的写入不影响备份内容
+        let checkpoint = reopened.checkpoint(&MemoryStorage::new()).unwrap();
+        for i in 0..3 {
+            reopened.write_log(StorageOperation::Drop { table: format!("late{}", i) }).unwrap();
+        }
+        let files = checkpoint.files().unwrap();
+        let backed_up: usize = files.iter()
+            .filter(|(name, _)| segment_seq(name).is_some())
+            .map(|(_, data)| String::from_utf8_lossy(data).lines().count())
+            .sum();
+        assert_eq!(backed_up, legacy_entries);
+        let snapshot = Snapshot::decode(&files.last().unwrap().1, "snapshot.json").unwrap();
+        assert_eq!(snapshot.last_log_id, checkpoint.log_id());
+
+        fs::remove_dir_all(&data_dir).unwrap();
+    }
+}
@@ -1,8 +1,28 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::clock;
+use crate::collation::Collation;
+use crate::columnar::{ColumnStore, TableLayout};
+use crate::row_store::RowStore;
+use crate::element_index::ElementIndex;
 use crate::error::{DatabaseError, Result};
+use crate::expression::Expr;
+use crate::binary;
+use crate::geo::{self, GeoIndex};
+use crate::zone_map::{ZoneMap, ZONE_SIZE};
+use crate::integrity::{IntegrityIssue, IssueKind};
+use crate::interval::Interval;
+use crate::partition::PartitionSpec;
+use crate::spill::{SpillPolicy, SpillSegment};
+use crate::shared::Shared;
+use crate::stats::TableStats;
 
 /// 数据类型
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -16,25 +36,54 @@ pub enum DataType {
     DateTime,
     Json,
     Binary,
+    Uuid,
+    /// 经纬度坐标（WGS84，单位为度）
+    Point,
+    /// 时间间隔，见 `Interval`
+    Interval,
+    /// 元素类型相同的数组，适合标签一类的多值列
+    Array(Box<DataType>),
 }
 
 impl DataType {
-    pub fn to_string(&self) -> String {
+    /// 数组的元素类型
+    pub fn element_type(&self) -> Option<&DataType> {
         match self {
-            DataType::Integer => "INTEGER".to_string(),
-            DataType::Text => "TEXT".to_string(),
-            DataType::Boolean => "BOOLEAN".to_string(),
-            DataType::Float => "FLOAT".to_string(),
-            DataType::Date => "DATE".to_string(),
-            DataType::Time => "TIME".to_string(),
-            DataType::DateTime => "DATETIME".to_string(),
-            DataType::Json => "JSON".to_string(),
-            DataType::Binary => "BINARY".to_string(),
+            DataType::Array(element) => Some(element),
+            _ => None,
         }
     }
+}
 
-    pub fn from_str(s: &str) -> Result<Self> {
-        match s.to_uppercase().as_str() {
+impl fmt::Display for DataType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DataType::Integer => "INTEGER",
+            DataType::Text => "TEXT",
+            DataType::Boolean => "BOOLEAN",
+            DataType::Float => "FLOAT",
+            DataType::Date => "DATE",
+            DataType::Time => "TIME",
+            DataType::DateTime => "DATETIME",
+            DataType::Json => "JSON",
+            DataType::Binary => "BINARY",
+            DataType::Uuid => "UUID",
+            DataType::Point => "POINT",
+            DataType::Interval => "INTERVAL",
+            DataType::Array(element) => return write!(f, "{}[]", element),
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for DataType {
+    type Err = DatabaseError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Some(element) = s.trim_end().strip_suffix("[]") {
+            return Ok(DataType::Array(Box::new(element.parse()?)));
+        }
+        match s.trim().to_uppercase().as_str() {
             "INTEGER" | "INT" => Ok(DataType::Integer),
             "TEXT" | "STRING" | "VARCHAR" => Ok(DataType::Text),
             "BOOLEAN" | "BOOL" => Ok(DataType::Boolean),
@@ -44,6 +93,9 @@ impl DataType {
             "DATETIME" | "TIMESTAMP" => Ok(DataType::DateTime),
             "JSON" => Ok(DataType::Json),
             "BINARY" | "BLOB" => Ok(DataType::Binary),
+            "UUID" => Ok(DataType::Uuid),
+            "POINT" => Ok(DataType::Point),
+            "INTERVAL" => Ok(DataType::Interval),
             _ => Err(DatabaseError::parse_error(format!("未知数据类型: {}", s))),
         }
     }
@@ -61,7 +113,12 @@ pub enum Value {
     DateTime(chrono::NaiveDateTime),
     Json(serde_json::Value),
     Binary(Vec<u8>),
+    Array(Vec<Value>),
     Null,
+    Uuid(Uuid),
+    /// 纬度与经度，单位为度
+    Point { lat: f64, lon: f64 },
+    Interval(Interval),
 }
 
 impl Value {
@@ -76,6 +133,13 @@ impl Value {
             Value::DateTime(_) => DataType::DateTime,
             Value::Json(_) => DataType::Json,
             Value::Binary(_) => DataType::Binary,
+            Value::Uuid(_) => DataType::Uuid,
+            Value::Point { .. } => DataType::Point,
+            Value::Interval(_) => DataType::Interval,
+            // 空数组无法推断元素类型，按 TEXT[] 处理
+            Value::Array(items) => DataType::Array(Box::new(
+                items.iter().find(|item| !item.is_null()).map_or(DataType::Text, Value::get_type)
+            )),
             Value::Null => DataType::Text, // NULL 可以是任何类型，默认为 Text
         }
     }
@@ -84,18 +148,145 @@ impl Value {
         matches!(self, Value::Null)
     }
 
-    pub fn to_string(&self) -> String {
+    /// 值在堆上占用的字节数（估算）
+    pub fn heap_size(&self) -> usize {
+        match self {
+            Value::Text(text) => text.capacity(),
+            Value::Binary(bytes) => bytes.capacity(),
+            Value::Json(json) => json.to_string().len(),
+            Value::Array(items) => items.capacity() * std::mem::size_of::<Value>()
+                + items.iter().map(Value::heap_size).sum::<usize>(),
+            _ => 0,
+        }
+    }
+
+    /// 判断值是否可以存入指定类型的列（NULL 与任意类型兼容）
+    pub fn is_compatible_with(&self, data_type: &DataType) -> bool {
+        match (self, data_type) {
+            (Value::Null, _) => true,
+            (Value::Integer(_), DataType::Float) => true,
+            (Value::Array(items), DataType::Array(element)) => items.iter().all(|item| item.is_compatible_with(element)),
+            _ => &self.get_type() == data_type,
+        }
+    }
+
+    /// 尝试将值转换为指定类型，无法转换时返回 None
+    pub fn coerce_to(&self, data_type: &DataType) -> Option<Value> {
+        if self.is_compatible_with(data_type) {
+            return Some(self.clone());
+        }
+
+        match (self, data_type) {
+            (Value::Text(s), DataType::Integer) => s.trim().parse().ok().map(Value::Integer),
+            (Value::Text(s), DataType::Float) => s.trim().parse().ok().map(Value::Float),
+            (Value::Text(s), DataType::Boolean) => match s.trim().to_lowercase().as_str() {
+                "true" | "1" | "yes" | "y" => Some(Value::Boolean(true)),
+                "false" | "0" | "no" | "n" => Some(Value::Boolean(false)),
+                _ => None,
+            },
+            (Value::Text(s), DataType::Date) => s.trim().parse().ok().map(Value::Date),
+            (Value::Text(s), DataType::Time) => s.trim().parse().ok().map(Value::Time),
+            (Value::Text(s), DataType::DateTime) => {
+                let s = s.trim();
+                s.parse::<chrono::NaiveDateTime>().ok()
+                    .or_else(|| chrono::DateTime::parse_from_rfc3339(s).ok().map(|dt| dt.naive_utc()))
+                    .map(Value::DateTime)
+            }
+            (Value::Text(s), DataType::Json) => serde_json::from_str(s).ok().map(Value::Json),
+            (Value::Text(s), DataType::Uuid) => s.trim().parse().ok().map(Value::Uuid),
+            (Value::Text(s), DataType::Interval) => s.parse().ok().map(Value::Interval),
+            (Value::Text(s), DataType::Binary) => binary::parse_text(s).map(Value::Binary),
+            (Value::Text(s), DataType::Point) => geo::parse_point(s).map(|(lat, lon)| Value::Point { lat, lon }),
+            (Value::Array(items), DataType::Array(element)) => items.iter()
+                .map(|item| item.coerce_to(element))
+                .collect::<Option<Vec<_>>>()
+                .map(Value::Array),
+            // 文本与 JSON 按 JSON 数组解析，例如 '["a", "b"]'
+            (Value::Text(s), DataType::Array(_)) => serde_json::from_str(s).ok()
+                .and_then(|json: serde_json::Value| Value::from_json(&json, Some(data_type)).ok()),
+            (Value::Json(json), DataType::Array(_)) => Value::from_json(json, Some(data_type)).ok(),
+            (Value::Float(f), DataType::Integer) if f.fract() == 0.0 => Some(Value::Integer(*f as i64)),
+            (Value::Integer(i), DataType::Boolean) if *i == 0 || *i == 1 => Some(Value::Boolean(*i == 1)),
+            (value, DataType::Text) => Some(Value::Text(value.to_string())),
+            _ => None,
+        }
+    }
+
+    /// 转换为普通 JSON 值，便于与 jq 等工具交互
+    ///
+    /// 日期时间输出为 ISO 字符串，二进制输出为字节数组。
+    pub fn to_json(&self) -> serde_json::Value {
         match self {
-            Value::Integer(i) => i.to_string(),
-            Value::Text(s) => s.clone(),
-            Value::Boolean(b) => b.to_string(),
-            Value::Float(f) => f.to_string(),
-            Value::Date(d) => d.to_string(),
-            Value::Time(t) => t.to_string(),
-            Value::DateTime(dt) => dt.to_string(),
-            Value::Json(j) => j.to_string(),
-            Value::Binary(b) => format!("BINARY({} bytes)", b.len()),
-            Value::Null => "NULL".to_string(),
+            Value::Integer(i) => serde_json::Value::from(*i),
+            Value::Float(f) => serde_json::Number::from_f64(*f)
+                .map(serde_json::Value::Number)
+                .unwrap_or(serde_json::Value::Null),
+            Value::Text(s) => serde_json::Value::String(s.clone()),
+            Value::Boolean(b) => serde_json::Value::Bool(*b),
+            Value::Date(_) | Value::Time(_) | Value::DateTime(_) | Value::Uuid(_) | Value::Interval(_) => {
+                serde_json::Value::String(self.to_string())
+            }
+            Value::Json(json) => json.clone(),
+            Value::Binary(bytes) => serde_json::Value::from(bytes.clone()),
+            Value::Array(items) => serde_json::Value::Array(items.iter().map(Value::to_json).collect()),
+            Value::Null => serde_json::Value::Null,
+            Value::Point { lat, lon } => serde_json::json!({ "lat": lat, "lon": lon }),
+        }
+    }
+
+    /// 从普通 JSON 值转换，`data_type` 为列的声明类型；未知列按 JSON 类型推断
+    pub fn from_json(json: &serde_json::Value, data_type: Option<&DataType>) -> Result<Value> {
+        use serde_json::Value as Json;
+
+        let value = match (json, data_type) {
+            (Json::Null, _) => Some(Value::Null),
+            (json, Some(DataType::Json)) => Some(Value::Json(json.clone())),
+            (Json::Number(n), Some(DataType::Integer)) => n.as_i64().map(Value::Integer),
+            (Json::Number(n), Some(DataType::Float)) => n.as_f64().map(Value::Float),
+            (Json::Bool(b), Some(DataType::Boolean)) => Some(Value::Boolean(*b)),
+            (Json::String(s), Some(data_type)) => Value::Text(s.clone()).coerce_to(data_type),
+            (Json::Array(items), Some(DataType::Binary)) => items.iter()
+                .map(|item| item.as_u64().and_then(|byte| u8::try_from(byte).ok()))
+                .collect::<Option<Vec<u8>>>()
+                .map(Value::Binary),
+            (Json::Object(object), Some(DataType::Point)) => match (object.get("lat"), object.get("lon")) {
+                (Some(Json::Number(lat)), Some(Json::Number(lon))) => Some(Value::Point { lat: lat.as_f64().unwrap_or_default(), lon: lon.as_f64().unwrap_or_default() }),
+                _ => None,
+            },
+            (Json::Array(items), Some(DataType::Array(element))) => Some(Value::Array(items.iter()
+                .map(|item| Value::from_json(item, Some(element)))
+                .collect::<Result<Vec<_>>>()?)),
+            (_, Some(_)) => None,
+            (Json::Number(n), None) => n.as_i64().map(Value::Integer).or_else(|| n.as_f64().map(Value::Float)),
+            (Json::Bool(b), None) => Some(Value::Boolean(*b)),
+            (Json::String(s), None) => Some(Value::Text(s.clone())),
+            (json, None) => Some(Value::Json(json.clone())),
+        };
+
+        value.ok_or_else(|| DatabaseError::type_mismatch(match data_type {
+            Some(data_type) => format!("无法将 {} 转换为 {}", json, data_type),
+            None => format!("无法转换 {}", json),
+        }))
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Integer(i) => write!(f, "{}", i),
+            Value::Text(s) => f.write_str(s),
+            Value::Boolean(b) => write!(f, "{}", b),
+            Value::Float(v) => write!(f, "{}", v),
+            Value::Date(d) => write!(f, "{}", d),
+            Value::Time(t) => write!(f, "{}", t),
+            Value::DateTime(dt) => write!(f, "{}", dt),
+            Value::Json(j) => write!(f, "{}", j),
+            Value::Binary(b) => write!(f, "BINARY({} bytes)", b.len()),
+            Value::Array(_) => write!(f, "{}", self.to_json()),
+            Value::Null => f.write_str("NULL"),
+            Value::Uuid(id) => write!(f, "{}", id),
+            Value::Point { lat, lon } => write!(f, "POINT({}, {})", lat, lon),
+            Value::Interval(interval) => write!(f, "{}", interval),
         }
     }
 }
@@ -136,6 +327,71 @@ impl From<serde_json::Value> for Value {
     }
 }
 
+impl From<i32> for Value {
+    fn from(value: i32) -> Self {
+        Value::Integer(value as i64)
+    }
+}
+
+impl From<Uuid> for Value {
+    fn from(value: Uuid) -> Self {
+        Value::Uuid(value)
+    }
+}
+
+impl From<Interval> for Value {
+    fn from(value: Interval) -> Self {
+        Value::Interval(value)
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(value: Vec<u8>) -> Self {
+        Value::Binary(value)
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(value: Vec<Value>) -> Self {
+        Value::Array(value)
+    }
+}
+
+impl From<chrono::NaiveDate> for Value {
+    fn from(value: chrono::NaiveDate) -> Self {
+        Value::Date(value)
+    }
+}
+
+impl From<chrono::NaiveTime> for Value {
+    fn from(value: chrono::NaiveTime) -> Self {
+        Value::Time(value)
+    }
+}
+
+impl From<chrono::NaiveDateTime> for Value {
+    fn from(value: chrono::NaiveDateTime) -> Self {
+        Value::DateTime(value)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+/// 数据校验模式
+///
+/// `Lenient` 下类型不符、未知列等问题只记录为警告，便于加载旧版本数据；
+/// `Strict` 下这些问题直接返回错误。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ValidationMode {
+    Strict,
+    #[default]
+    Lenient,
+}
+
 /// 列定义
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ColumnDefinition {
@@ -145,6 +401,12 @@ pub struct ColumnDefinition {
     pub unique: bool,
     pub default_value: Option<Value>,
     pub primary_key: bool,
+    /// 文本值的排序规则
+    #[serde(default)]
+    pub collation: Collation,
+    /// 动态默认值，每次插入时求值，例如 `NOW()`；优先于 `default_value`
+    #[serde(default)]
+    pub default_expr: Option<Expr>,
 }
 
 impl ColumnDefinition {
@@ -156,6 +418,8 @@ impl ColumnDefinition {
             unique: primary_key,   // 主键默认唯一
             default_value: None,
             primary_key,
+            collation: Collation::Binary,
+            default_expr: None,
         }
     }
 
@@ -173,23 +437,61 @@ impl ColumnDefinition {
         self.default_value = Some(value);
         self
     }
+
+    pub fn collation(mut self, collation: Collation) -> Self {
+        self.collation = collation;
+        self
+    }
+
+    /// 设置动态默认值，表达式可以引用同一行中已提供的列
+    pub fn default_expr(mut self, expr: Expr) -> Self {
+        self.default_expr = Some(expr);
+        self
+    }
+
+    pub fn has_default(&self) -> bool {
+        self.default_value.is_some() || self.default_expr.is_some()
+    }
+
+    /// 默认值的文本，用于展示表结构
+    pub fn default_text(&self) -> Option<String> {
+        match (&self.default_expr, &self.default_value) {
+            (Some(expr), _) => Some(expr.to_string()),
+            (None, Some(value)) => Some(value.to_string()),
+            (None, None) => None,
+        }
+    }
 }
 
 /// 表结构
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Schema {
     pub columns: Vec<ColumnDefinition>,
+    /// 表级设置，随表结构写入日志与表文件
+    #[serde(default, skip_serializing_if = "TableSettings::is_default")]
+    pub settings: TableSettings,
 }
 
 impl Schema {
     pub fn new(columns: Vec<ColumnDefinition>) -> Self {
-        Self { columns }
+        Self { columns, settings: TableSettings::default() }
+    }
+
+    /// 列的排序规则，未知列按二进制比较
+    pub fn collation(&self, column: &str) -> Collation {
+        self.get_column(column).map_or(Collation::Binary, |c| c.collation)
     }
 
     pub fn get_column(&self, name: &str) -> Option<&ColumnDefinition> {
         self.columns.iter().find(|col| col.name == name)
     }
 
+    /// 列是否存在，伪列 `_id`、`_created_at`、`_updated_at`、`_version` 总是存在
+    pub fn has_column(&self, name: &str) -> bool {
+        self.get_column(name).is_some()
+            || matches!(name, ID_COLUMN | CREATED_AT_COLUMN | UPDATED_AT_COLUMN | VERSION_COLUMN)
+    }
+
     pub fn get_primary_key_columns(&self) -> Vec<&ColumnDefinition> {
         self.columns.iter().filter(|col| col.primary_key).collect()
     }
@@ -197,20 +499,19 @@ impl Schema {
     pub fn validate_row(&self, row: &Row) -> Result<()> {
         // 检查必填字段
         for column in &self.columns {
-            if !column.nullable && !column.primary_key {
-                if row.get(&column.name).map_or(true, |v| v.is_null()) {
-                    if column.default_value.is_none() {
-                        return Err(DatabaseError::not_null_violation(
-                            format!("列 '{}' 不能为空", column.name)
-                        ));
-                    }
-                }
+            if !column.nullable
+                && !column.primary_key
+                && row.get(&column.name).is_none_or(|v| v.is_null())
+                && !column.has_default()
+            {
+                return Err(DatabaseError::not_null_violation("", &column.name));
             }
         }
 
         // 检查主键
         let pk_columns = self.get_primary_key_columns();
-        if !pk_columns.is_empty() {
+        if let Some(first) = pk_columns.first() {
+            let first = first.name.clone();
             let mut has_pk = false;
             for pk_col in pk_columns {
                 if let Some(value) = row.get(&pk_col.name) {
@@ -221,14 +522,45 @@ impl Schema {
                 }
             }
             if !has_pk {
-                return Err(DatabaseError::not_null_violation(
-                    "主键不能为空".to_string()
-                ));
+                return Err(DatabaseError::not_null_violation("", first));
             }
         }
 
         Ok(())
     }
+
+    /// 严格模式下的额外校验：未知列与类型不匹配
+    pub fn strict_violations(&self, data: &HashMap<String, Value>) -> Vec<DatabaseError> {
+        let mut violations = Vec::new();
+
+        for (column, value) in data {
+            match self.get_column(column) {
+                None => violations.push(DatabaseError::column_not_found(column.clone())),
+                Some(def) if !value.is_compatible_with(&def.data_type) => {
+                    violations.push(DatabaseError::type_mismatch(format!(
+                        "列 '{}' 期望 {} 类型，实际为 {}",
+                        column, def.data_type, value.get_type()
+                    )));
+                }
+                Some(_) => {}
+            }
+        }
+
+        violations
+    }
+}
+
+/// 行 ID 伪列，值为插入时返回的 UUID
+pub const ID_COLUMN: &str = "_id";
+/// 行创建时间伪列
+pub const CREATED_AT_COLUMN: &str = "_created_at";
+/// 行更新时间伪列
+pub const UPDATED_AT_COLUMN: &str = "_updated_at";
+/// 行版本号伪列
+pub const VERSION_COLUMN: &str = "_version";
+
+fn initial_row_version() -> u64 {
+    1
 }
 
 /// 数据行
@@ -238,16 +570,20 @@ pub struct Row {
     pub data: HashMap<String, Value>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
+    /// 行版本号，每次更新递增，用于乐观并发控制
+    #[serde(default = "initial_row_version")]
+    pub version: u64,
 }
 
 impl Row {
     pub fn new() -> Self {
-        let now = chrono::Utc::now();
+        let now = clock::now();
         Self {
             id: Uuid::new_v4(),
             data: HashMap::new(),
             created_at: now,
             updated_at: now,
+            version: initial_row_version(),
         }
     }
 
@@ -259,6 +595,21 @@ impl Row {
         self.data.get(column)
     }
 
+    /// 获取列值，支持 `_created_at`/`_updated_at`/`_version` 伪列
+    pub fn resolve(&self, column: &str) -> Option<Cow<'_, Value>> {
+        if let Some(value) = self.data.get(column) {
+            return Some(Cow::Borrowed(value));
+        }
+
+        match column {
+            ID_COLUMN => Some(Cow::Owned(Value::Uuid(self.id))),
+            CREATED_AT_COLUMN => Some(Cow::Owned(Value::DateTime(self.created_at.naive_utc()))),
+            UPDATED_AT_COLUMN => Some(Cow::Owned(Value::DateTime(self.updated_at.naive_utc()))),
+            VERSION_COLUMN => Some(Cow::Owned(Value::Integer(self.version as i64))),
+            _ => None,
+        }
+    }
+
     pub fn get_integer(&self, column: &str) -> Option<i64> {
         match self.get(column) {
             Some(Value::Integer(i)) => Some(*i),
@@ -290,6 +641,29 @@ impl Row {
     pub fn columns(&self) -> Vec<&str> {
         self.data.keys().map(|s| s.as_str()).collect()
     }
+
+    /// 把行数据反序列化为任意 `Deserialize` 类型，列名对应字段名
+    ///
+    /// 只包含用户列，不包含行ID与 `_created_at` 等伪列。
+    pub fn deserialize_into<T: serde::de::DeserializeOwned>(&self) -> Result<T> {
+        let object = self.data.iter()
+            .map(|(column, value)| {
+                // 日期时间使用 chrono 的 serde 格式，而不是 Display 的空格分隔格式
+                let json = match value {
+                    Value::DateTime(dt) => serde_json::to_value(dt)?,
+                    value => value.to_json(),
+                };
+                Ok((column.clone(), json))
+            })
+            .collect::<Result<serde_json::Map<_, _>>>()?;
+        Ok(serde_json::from_value(serde_json::Value::Object(object))?)
+    }
+}
+
+impl Default for Row {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 /// 表
@@ -297,88 +671,1112 @@ impl Row {
 pub struct Table {
     pub name: String,
     pub schema: Schema,
-    pub rows: Vec<Row>,
+    /// 行存储布局下的数据，列式布局的数据保存在 `columns` 中
+    ///
+    /// 克隆的表与原表共享行数据，写入时才复制，查询拿到的表快照不复制行。
+    pub rows: Shared<RowStore>,
     pub created_at: chrono::DateTime<chrono::Utc>,
+    #[serde(default)]
+    pub layout: TableLayout,
+    #[serde(default)]
+    pub columns: Shared<ColumnStore>,
+    /// 分区定义；分区表自身不保存行，数据在 `partitions` 中
+    #[serde(default)]
+    pub partition_spec: Option<PartitionSpec>,
+    #[serde(default)]
+    pub partitions: Vec<Table>,
+    /// 最近一次 ANALYZE 收集的统计信息
+    #[serde(default)]
+    pub stats: Option<TableStats>,
+    /// 增量维护的内存占用估算，用于内存上限检查
+    #[serde(skip)]
+    memory_bytes: usize,
+    #[serde(skip)]
+    spill_policy: Option<SpillPolicy>,
+    /// 已溢出到磁盘的冷数据段，按插入顺序排列，均早于内存中的行
+    #[serde(skip)]
+    spilled: Vec<Arc<SpillSegment>>,
+    /// 已溢出行在唯一列上的键，插入时无需读取段文件即可检查唯一性
+    #[serde(skip)]
+    spilled_keys: HashMap<String, HashSet<String>>,
+    /// 按需构建的数组列元素索引；克隆的表共享缓存，数据变化时整体替换
+    #[serde(skip)]
+    element_indexes: Arc<Mutex<HashMap<String, Arc<ElementIndex>>>>,
+    /// 按需构建的坐标列网格索引，缓存方式与元素索引相同
+    #[serde(skip)]
+    geo_indexes: Arc<Mutex<HashMap<String, Arc<GeoIndex>>>>,
+    /// 按需构建的各列区域映射，缓存方式与元素索引相同
+    #[serde(skip)]
+    zone_maps: Arc<Mutex<HashMap<String, Arc<ZoneMap>>>>,
+    /// 行存储布局下内存中各行在 `rows` 中的位置，使按 ID 查找、更新与删除为 O(1)
+    ///
+    /// 反序列化的表没有该索引，与 `rows` 长度不一致时查找退回线性扫描，修改前重建。
+    #[serde(skip)]
+    row_positions: Shared<HashMap<Uuid, usize>>,
+}
+
+/// 表的内存占用估算（字节）
+#[derive(Debug, Clone, Default)]
+pub struct TableMemoryUsage {
+    pub table: String,
+    pub rows: usize,
+    /// 行结构本身的开销（行元数据、HashMap 槽位或列槽位）
+    pub row_bytes: usize,
+    /// 值在堆上的负载（文本、二进制、JSON）
+    pub value_bytes: usize,
+    /// 索引开销，目前只有数组列的元素索引
+    pub index_bytes: usize,
+    /// 已溢出到磁盘的行数
+    pub spilled_rows: usize,
+    /// 溢出段文件的大小
+    pub spilled_bytes: u64,
+}
+
+impl TableMemoryUsage {
+    pub fn total_bytes(&self) -> usize {
+        self.row_bytes + self.value_bytes + self.index_bytes
+    }
+}
+
+/// 保存在表结构中的表级设置，建表时由 [`TableOptions`] 指定
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TableSettings {
+    /// 不写日志：数据修改不写入日志也不推送给从库，只随快照持久化，
+    /// 崩溃后表中的数据回到上次快照时的状态；建表、删表等结构变更照常写日志
+    #[serde(default)]
+    pub unlogged: bool,
+    /// 行的存活时间：最后修改早于该时长的行对查询不可见，由 `DatabaseEngine::purge_expired` 删除
+    #[serde(default)]
+    pub ttl: Option<Duration>,
+    /// 数据目录中的表文件以 LZ4 压缩保存
+    #[serde(default)]
+    pub compressed: bool,
+    /// 行数上限，插入后会超过时返回 `TableFull` 错误
+    #[serde(default)]
+    pub max_rows: Option<usize>,
+}
+
+impl TableSettings {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// 建表选项
+#[derive(Debug, Clone, Default)]
+pub struct TableOptions {
+    pub layout: TableLayout,
+    pub partition: Option<PartitionSpec>,
+    pub settings: TableSettings,
+}
+
+impl TableOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn layout(mut self, layout: TableLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    pub fn partition(mut self, spec: PartitionSpec) -> Self {
+        self.partition = Some(spec);
+        self
+    }
+
+    /// 数据修改不写日志，见 [`TableSettings::unlogged`]
+    pub fn unlogged(mut self) -> Self {
+        self.settings.unlogged = true;
+        self
+    }
+
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.settings.ttl = Some(ttl);
+        self
+    }
+
+    pub fn compressed(mut self) -> Self {
+        self.settings.compressed = true;
+        self
+    }
+
+    pub fn max_rows(mut self, max_rows: usize) -> Self {
+        self.settings.max_rows = Some(max_rows);
+        self
+    }
+
+    /// 把选项中的表级设置写入表结构；选项没有指定任何设置时保留表结构中原有的设置
+    pub fn apply_settings(&self, schema: &mut Schema) {
+        if !self.settings.is_default() {
+            schema.settings = self.settings.clone();
+        }
+    }
 }
 
 impl Table {
     pub fn new(name: String, schema: Schema) -> Self {
+        Self::with_layout(name, schema, TableLayout::Row)
+    }
+
+    /// 按建表选项创建表，分区表会按分区定义创建各个分区
+    pub fn with_options(name: String, mut schema: Schema, options: TableOptions) -> Result<Self> {
+        options.apply_settings(&mut schema);
+        let mut table = Self::with_layout(name, schema, options.layout);
+        if let Some(spec) = options.partition {
+            spec.validate(&table.schema)?;
+            table.partitions = (0..spec.partition_count())
+                .map(|index| Self::with_layout(format!("{}#p{}", table.name, index), table.schema.clone(), options.layout))
+                .collect();
+            table.partition_spec = Some(spec);
+        }
+        Ok(table)
+    }
+
+    pub fn is_partitioned(&self) -> bool {
+        self.partition_spec.is_some()
+    }
+
+    /// 使用指定存储布局创建表
+    pub fn with_layout(name: String, schema: Schema, layout: TableLayout) -> Self {
+        let columns = match layout {
+            TableLayout::Row => ColumnStore::default(),
+            TableLayout::Columnar => ColumnStore::new(&schema),
+        }.into();
         Self {
             name,
             schema,
-            rows: Vec::new(),
-            created_at: chrono::Utc::now(),
+            rows: Shared::default(),
+            created_at: clock::now(),
+            layout,
+            columns,
+            partition_spec: None,
+            partitions: Vec::new(),
+            stats: None,
+            memory_bytes: 0,
+            spill_policy: None,
+            spilled: Vec::new(),
+            spilled_keys: HashMap::new(),
+            element_indexes: Arc::default(),
+            geo_indexes: Arc::default(),
+            zone_maps: Arc::default(),
+            row_positions: Shared::default(),
+        }
+    }
+
+    /// 设置溢出策略，None 表示关闭溢出并把已溢出的行读回内存
+    ///
+    /// 仅支持行存储布局。
+    pub fn set_spill_policy(&mut self, policy: Option<SpillPolicy>) -> Result<()> {
+        match policy {
+            Some(_) if self.is_partitioned() => Err(DatabaseError::other(
+                format!("表 '{}' 是分区表，不支持溢出到磁盘", self.name)
+            )),
+            Some(_) if self.layout == TableLayout::Columnar => Err(DatabaseError::other(
+                format!("表 '{}' 使用列式存储，不支持溢出到磁盘", self.name)
+            )),
+            Some(policy) => {
+                self.spill_policy = Some(policy);
+                self.maybe_spill()
+            }
+            None => {
+                self.spill_policy = None;
+                self.fault_in()
+            }
+        }
+    }
+
+    /// 内存占用超过阈值时，把最旧的行写入新的段文件
+    fn maybe_spill(&mut self) -> Result<()> {
+        let Some(policy) = &self.spill_policy else {
+            return Ok(());
+        };
+        if self.memory_bytes <= policy.threshold_bytes {
+            return Ok(());
+        }
+
+        let (target, dir) = (policy.target_bytes(), policy.dir.clone());
+        let mut remaining = self.memory_bytes;
+        let mut count = 0;
+        for row in self.rows.iter() {
+            if remaining <= target {
+                break;
+            }
+            remaining = remaining.saturating_sub(self.row_bytes(row));
+            count += 1;
+        }
+        if count == 0 {
+            return Ok(());
+        }
+
+        self.compact_rows();
+        let segment = SpillSegment::write(&dir, &self.name, &self.rows.slots()[..count])?;
+        let cold = self.rows.drain_front(count);
+        self.reindex_rows();
+        for column in self.schema.columns.iter().filter(|col| col.unique) {
+            let keys = self.spilled_keys.entry(column.name.clone()).or_default();
+            keys.extend(cold.iter()
+                .filter_map(|row| row.get(&column.name))
+                .filter(|value| !value.is_null())
+                .map(|value| column.collation.key(value)));
+        }
+        self.spilled.push(Arc::new(segment));
+        self.memory_bytes = remaining;
+        Ok(())
+    }
+
+    /// 把所有已溢出的行读回内存
+    fn fault_in(&mut self) -> Result<()> {
+        if self.spilled.is_empty() {
+            return Ok(());
+        }
+
+        self.compact_rows();
+        let mut rows = self.load_spilled()?;
+        self.memory_bytes += self.estimate_insert(&rows);
+        rows.extend(self.rows.iter().cloned());
+        self.rows = Shared::new(rows.into());
+        self.reindex_rows();
+        self.spilled.clear();
+        self.spilled_keys.clear();
+        Ok(())
+    }
+
+    fn load_spilled(&self) -> Result<Vec<Row>> {
+        let mut rows = Vec::with_capacity(self.spilled_row_count());
+        for segment in &self.spilled {
+            rows.extend(segment.read()?);
+        }
+        Ok(rows)
+    }
+
+    /// 已溢出到磁盘的行数
+    pub fn spilled_row_count(&self) -> usize {
+        self.spilled.iter().map(|segment| segment.row_count()).sum()
+    }
+
+    /// 重新收集统计信息
+    pub fn analyze(&mut self) -> Result<&TableStats> {
+        let stats = TableStats::collect(&self.schema, &self.all_rows()?);
+        Ok(self.stats.insert(stats))
+    }
+
+    /// 表或其分区是否有行溢出到了磁盘
+    pub fn has_spilled(&self) -> bool {
+        !self.spilled.is_empty() || self.partitions.iter().any(Table::has_spilled)
+    }
+
+    /// 返回包含全部行（含已溢出行）的内存副本，用于创建快照
+    pub fn materialize(&self) -> Result<Table> {
+        let mut table = self.clone();
+        table.spill_policy = None;
+        table.fault_in()?;
+        for partition in &mut table.partitions {
+            *partition = partition.materialize()?;
+        }
+        Ok(table)
+    }
+
+    /// 单行的内存占用估算，返回 (行结构开销, 值负载)
+    fn row_footprint(&self, row: &Row) -> (usize, usize) {
+        let value_bytes = row.data.values().map(Value::heap_size).sum();
+        let row_bytes = match self.layout {
+            TableLayout::Row => {
+                std::mem::size_of::<Row>()
+                    + row.data.keys()
+                        .map(|key| key.capacity() + std::mem::size_of::<(String, Value)>() + 1)
+                        .sum::<usize>()
+            }
+            TableLayout::Columnar => self.columns.row_slot_size(),
+        };
+        (row_bytes, value_bytes)
+    }
+
+    fn row_bytes(&self, row: &Row) -> usize {
+        let (row_bytes, value_bytes) = self.row_footprint(row);
+        row_bytes + value_bytes
+    }
+
+    /// 增量维护的内存占用估算（字节）
+    pub fn estimated_bytes(&self) -> usize {
+        self.memory_bytes + self.partitions.iter().map(Table::estimated_bytes).sum::<usize>()
+    }
+
+    /// 估算插入这些行后新增的内存占用
+    pub fn estimate_insert(&self, rows: &[Row]) -> usize {
+        rows.iter().map(|row| self.row_bytes(row)).sum()
+    }
+
+    /// 估算按 `updates` 修改这些行后增加的内存占用，变小的行不抵扣
+    pub fn estimate_update<'a, I>(&self, rows: I, updates: &HashMap<String, Value>) -> usize
+    where
+        I: IntoIterator<Item = &'a Row>,
+    {
+        rows.into_iter()
+            .map(|row| {
+                let mut updated = row.clone();
+                for (column, value) in updates {
+                    updated.set(column, value.clone());
+                }
+                self.row_bytes(&updated).saturating_sub(self.row_bytes(row))
+            })
+            .sum()
+    }
+
+    /// 重新统计内存占用，用于批量修改之后或从快照反序列化之后
+    pub fn recompute_memory(&mut self) {
+        for partition in &mut self.partitions {
+            partition.recompute_memory();
+        }
+        self.memory_bytes = self.own_memory_usage().total_bytes();
+    }
+
+    /// 重新统计内存占用明细，分区表汇总各分区
+    pub fn memory_usage(&self) -> TableMemoryUsage {
+        let mut usage = self.own_memory_usage();
+        usage.index_bytes = self.index_bytes();
+        for partition in self.partitions.iter().map(Table::memory_usage) {
+            usage.rows += partition.rows;
+            usage.row_bytes += partition.row_bytes;
+            usage.value_bytes += partition.value_bytes;
+            usage.index_bytes += partition.index_bytes;
+            usage.spilled_rows += partition.spilled_rows;
+            usage.spilled_bytes += partition.spilled_bytes;
+        }
+        usage
+    }
+
+    /// 数组列的元素索引，首次使用时构建；不是数组列或分区表时返回 None
+    ///
+    /// 位置对应 `indexed_rows` 的顺序。
+    pub fn element_index(&self, column: &str) -> Result<Option<Arc<ElementIndex>>> {
+        let Some(definition) = self.schema.get_column(column) else {
+            return Ok(None);
+        };
+        if self.is_partitioned() || definition.data_type.element_type().is_none() {
+            return Ok(None);
+        }
+
+        let mut indexes = self.element_indexes.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(index) = indexes.get(column) {
+            return Ok(Some(Arc::clone(index)));
+        }
+        let index = Arc::new(ElementIndex::build(&self.indexed_rows()?, column, definition.collation));
+        indexes.insert(column.to_string(), Arc::clone(&index));
+        Ok(Some(index))
+    }
+
+    /// 坐标列的网格索引，首次使用时构建；不是 POINT 列或分区表时返回 None
+    pub fn geo_index(&self, column: &str) -> Result<Option<Arc<GeoIndex>>> {
+        let Some(definition) = self.schema.get_column(column) else {
+            return Ok(None);
+        };
+        if self.is_partitioned() || definition.data_type != DataType::Point {
+            return Ok(None);
+        }
+
+        let mut indexes = self.geo_indexes.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(index) = indexes.get(column) {
+            return Ok(Some(Arc::clone(index)));
+        }
+        let index = Arc::new(GeoIndex::build(&self.indexed_rows()?, column));
+        indexes.insert(column.to_string(), Arc::clone(&index));
+        Ok(Some(index))
+    }
+
+    /// 列的区域映射，首次使用时构建；分区表或行数不超过一个数据块时返回 None
+    pub fn zone_map(&self, column: &str) -> Result<Option<Arc<ZoneMap>>> {
+        let Some(definition) = self.schema.get_column(column) else {
+            return Ok(None);
+        };
+        if self.is_partitioned() || self.row_count() <= ZONE_SIZE {
+            return Ok(None);
+        }
+
+        let mut maps = self.zone_maps.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(map) = maps.get(column) {
+            return Ok(Some(Arc::clone(map)));
+        }
+        let map = Arc::new(ZoneMap::build(&self.indexed_rows()?, column, definition.collation));
+        maps.insert(column.to_string(), Arc::clone(&map));
+        Ok(Some(map))
+    }
+
+    /// 按位置寻址的索引所覆盖的行：已溢出的行在前，之后是内存中的全部槽位（包括墓碑）
+    ///
+    /// 删除留下的墓碑不改变其余行的位置，已构建的索引因此在删除后仍然有效，可能给出已删除的位置，由 `rows_at` 跳过。
+    fn indexed_rows(&self) -> Result<Cow<'_, [Row]>> {
+        match self.layout {
+            TableLayout::Row if self.spilled.is_empty() => Ok(Cow::Borrowed(self.rows.slots())),
+            TableLayout::Row => {
+                let mut rows = self.load_spilled()?;
+                rows.extend_from_slice(self.rows.slots());
+                Ok(Cow::Owned(rows))
+            }
+            TableLayout::Columnar => Ok(Cow::Owned(self.columns.rows())),
+        }
+    }
+
+    /// 索引给出的位置上仍然存在的行，按位置顺序返回
+    pub fn rows_at(&self, positions: &[usize]) -> Result<Vec<Row>> {
+        match self.layout {
+            TableLayout::Row => {
+                let spilled = if self.spilled.is_empty() { Vec::new() } else { self.load_spilled()? };
+                Ok(positions.iter()
+                    .filter_map(|&position| match position.checked_sub(spilled.len()) {
+                        None => Some(spilled[position].clone()),
+                        Some(slot) => self.rows.get(slot).cloned(),
+                    })
+                    .collect())
+            }
+            TableLayout::Columnar => Ok(positions.iter().map(|&position| self.columns.row(position)).collect()),
+        }
+    }
+
+    /// 数据变化后丢弃已构建的元素索引、网格索引与区域映射，已克隆出去的表保留各自的旧索引
+    fn invalidate_indexes(&mut self) {
+        self.element_indexes = Arc::default();
+        self.geo_indexes = Arc::default();
+        self.zone_maps = Arc::default();
+    }
+
+    fn own_memory_usage(&self) -> TableMemoryUsage {
+        let mut usage = TableMemoryUsage {
+            table: self.name.clone(),
+            spilled_rows: self.spilled_row_count(),
+            spilled_bytes: self.spilled.iter().map(|segment| segment.file_size()).sum(),
+            ..TableMemoryUsage::default()
+        };
+        let in_memory = match self.layout {
+            TableLayout::Row => self.rows.rows(),
+            TableLayout::Columnar => Cow::Owned(self.columns.rows()),
+        };
+        usage.rows = in_memory.len();
+        for row in in_memory.iter() {
+            let (row_bytes, value_bytes) = self.row_footprint(row);
+            usage.row_bytes += row_bytes;
+            usage.value_bytes += value_bytes;
         }
+        usage
+    }
+
+    fn index_bytes(&self) -> usize {
+        self.element_indexes.lock().unwrap_or_else(|e| e.into_inner())
+            .values()
+            .map(|index| index.heap_size())
+            .sum::<usize>()
+            + self.geo_indexes.lock().unwrap_or_else(|e| e.into_inner())
+                .values()
+                .map(|index| index.heap_size())
+                .sum::<usize>()
+            + self.zone_maps.lock().unwrap_or_else(|e| e.into_inner())
+                .values()
+                .map(|map| map.heap_size())
+                .sum::<usize>()
     }
 
     pub fn schema(&self) -> &Schema {
         &self.schema
     }
 
+    /// 所有行；列式布局或存在已溢出的行时需要物化
+    pub fn all_rows(&self) -> Result<Cow<'_, [Row]>> {
+        if self.is_partitioned() {
+            let mut rows = Vec::with_capacity(self.row_count());
+            for partition in &self.partitions {
+                rows.extend_from_slice(&partition.all_rows()?);
+            }
+            return Ok(Cow::Owned(rows));
+        }
+        match self.layout {
+            TableLayout::Row if self.spilled.is_empty() => Ok(self.rows.rows()),
+            TableLayout::Row => {
+                let mut rows = self.load_spilled()?;
+                rows.extend(self.rows.iter().cloned());
+                Ok(Cow::Owned(rows))
+            }
+            TableLayout::Columnar => Ok(Cow::Owned(self.columns.rows())),
+        }
+    }
+
     pub fn insert(&mut self, mut row: Row) -> Result<()> {
+        self.check_capacity(1)?;
+
         // 验证行数据
-        self.schema.validate_row(&row)?;
+        self.schema.validate_row(&row).map_err(|e| e.in_table(&self.name))?;
 
         // 设置默认值
-        for column in &self.schema.columns {
-            if !row.data.contains_key(&column.name) {
-                if let Some(default_value) = &column.default_value {
-                    row.set(column.name.clone(), default_value.clone());
+        self.apply_defaults(&mut row)?;
+
+        // 检查唯一约束
+        if column_has_unique_constraint(&self.schema) {
+            for column in self.schema.columns.iter().filter(|col| col.unique) {
+                if let Some(new_val) = row.get(&column.name) {
+                    if !new_val.is_null() && self.contains_value(&column.name, new_val) {
+                        return Err(DatabaseError::unique_violation(&self.name, &column.name, new_val));
+                    }
                 }
             }
         }
 
-        // 检查唯一约束
-        if column_has_unique_constraint(&self.schema) {
-            for existing_row in &self.rows {
-                for column in &self.schema.columns {
-                    if column.unique {
-                        if let (Some(new_val), Some(existing_val)) =
-                            (row.get(&column.name), existing_row.get(&column.name)) {
-                            if new_val == existing_val && !new_val.is_null() {
-                                return Err(DatabaseError::unique_violation(
-                                    format!("列 '{}' 的值 '{}' 必须唯一", column.name, new_val.to_string())
-                                ));
-                            }
-                        }
+        self.push_row(row);
+        self.maybe_spill()
+    }
+
+    /// 批量插入：先校验全部行（含批内唯一性），全部通过后一次性写入
+    pub fn insert_batch(&mut self, rows: Vec<Row>) -> Result<()> {
+        self.check_capacity(rows.len())?;
+        let mut prepared = Vec::with_capacity(rows.len());
+        for mut row in rows {
+            self.schema.validate_row(&row).map_err(|e| e.in_table(&self.name))?;
+            self.apply_defaults(&mut row)?;
+            prepared.push(row);
+        }
+
+        for column in self.schema.columns.iter().filter(|col| col.unique) {
+            let mut seen = self.unique_keys(&column.name);
+
+            for row in &prepared {
+                if let Some(value) = row.get(&column.name) {
+                    if !value.is_null() && !seen.insert(column.collation.key(value)) {
+                        return Err(DatabaseError::unique_violation(&self.name, &column.name, value));
                     }
                 }
             }
         }
 
-        self.rows.push(row);
+        if self.is_partitioned() {
+            for row in prepared {
+                self.push_row(row);
+            }
+            return Ok(());
+        }
+
+        self.memory_bytes += self.estimate_insert(&prepared);
+        self.invalidate_indexes();
+        match self.layout {
+            TableLayout::Row => {
+                self.ensure_row_positions();
+                self.rows.reserve(prepared.len());
+                for row in prepared {
+                    let id = row.id;
+                    let slot = self.rows.push(row);
+                    self.row_positions.insert(id, slot);
+                }
+            }
+            TableLayout::Columnar => {
+                for row in prepared {
+                    self.columns.push(row);
+                }
+            }
+        }
+        self.maybe_spill()
+    }
+
+    /// 再插入 `additional` 行是否会超过建表时设置的行数上限
+    fn check_capacity(&self, additional: usize) -> Result<()> {
+        match self.schema.settings.max_rows {
+            Some(limit) if self.row_count() + additional > limit => Err(DatabaseError::table_full(&self.name, limit)),
+            _ => Ok(()),
+        }
+    }
+
+    fn push_row(&mut self, row: Row) {
+        if let Some(spec) = &self.partition_spec {
+            let index = spec.partition_for(row.get(spec.column()));
+            self.partitions[index].push_row(row);
+            return;
+        }
+        self.memory_bytes += self.row_bytes(&row);
+        self.invalidate_indexes();
+        match self.layout {
+            TableLayout::Row => {
+                self.ensure_row_positions();
+                let id = row.id;
+                let slot = self.rows.push(row);
+                self.row_positions.insert(id, slot);
+            }
+            TableLayout::Columnar => self.columns.push(row),
+        }
+    }
+
+    /// 内存中 ID 为 `id` 的行在 `rows` 中的槽位
+    fn row_position(&self, id: Uuid) -> Option<usize> {
+        if self.row_positions.len() == self.rows.len() {
+            self.row_positions.get(&id).copied()
+        } else {
+            self.rows.entries().find(|(_, row)| row.id == id).map(|(slot, _)| slot)
+        }
+    }
+
+    /// 位置索引与 `rows` 不一致时（例如反序列化之后）重建
+    fn ensure_row_positions(&mut self) {
+        if self.row_positions.len() != self.rows.len() {
+            self.reindex_rows();
+        }
+    }
+
+    fn reindex_rows(&mut self) {
+        self.row_positions = Shared::new(self.rows.entries().map(|(slot, row)| (row.id, slot)).collect());
+    }
+
+    /// 墓碑积累到一定数量时压缩行存储；各行的槽位随之改变，重建位置索引并丢弃按位置寻址的索引
+    fn maybe_compact_rows(&mut self) {
+        if self.rows.needs_compaction() {
+            self.compact_rows();
+        }
+    }
+
+    fn compact_rows(&mut self) {
+        if self.rows.tombstones() > 0 {
+            self.rows.compact();
+            self.reindex_rows();
+            self.invalidate_indexes();
+        }
+    }
+
+    fn contains_value(&self, column: &str, value: &Value) -> bool {
+        if self.is_partitioned() {
+            return self.partitions.iter().any(|partition| partition.contains_value(column, value));
+        }
+        let collation = self.schema.collation(column);
+        let key = collation.key(value);
+        if self.spilled_keys.get(column).is_some_and(|keys| keys.contains(&key)) {
+            return true;
+        }
+        match self.layout {
+            TableLayout::Row => self.rows.iter()
+                .any(|row| row.get(column).is_some_and(|v| collation.key(v) == key)),
+            TableLayout::Columnar => (0..self.columns.len())
+                .any(|index| self.columns.value(index, column).is_some_and(|v| collation.key(&v) == key)),
+        }
+    }
+
+    pub(crate) fn unique_keys(&self, column: &str) -> HashSet<String> {
+        if self.is_partitioned() {
+            return self.partitions.iter().flat_map(|partition| partition.unique_keys(column)).collect();
+        }
+        let collation = self.schema.collation(column);
+        match self.layout {
+            TableLayout::Row => self.rows.iter()
+                .filter_map(|row| row.get(column))
+                .filter(|value| !value.is_null())
+                .map(|value| collation.key(value))
+                .chain(self.spilled_keys.get(column).into_iter().flatten().cloned())
+                .collect(),
+            TableLayout::Columnar => (0..self.columns.len())
+                .filter_map(|index| self.columns.value(index, column))
+                .filter(|value| !value.is_null())
+                .map(|value| collation.key(&value))
+                .collect(),
+        }
+    }
+
+    /// 为缺失的列填入默认值
+    ///
+    /// 引擎在写日志之前调用，使 `NOW()` 这类动态默认值在重放时保持原值。
+    pub fn apply_defaults(&self, row: &mut Row) -> Result<()> {
+        self.apply_defaults_with(row, &mut |function, _| {
+            Err(DatabaseError::other(format!("{} 只能由引擎求值", function)))
+        })
+    }
+
+    /// 同 `apply_defaults`，默认值中的 `NEXTVAL`、`CURRVAL` 由 `resolve` 代入序列值
+    pub fn apply_defaults_with<F>(&self, row: &mut Row, resolve: &mut F) -> Result<()>
+    where
+        F: FnMut(&str, &str) -> Result<i64>,
+    {
+        for column in &self.schema.columns {
+            if row.data.contains_key(&column.name) {
+                continue;
+            }
+            if let Some(expr) = &column.default_expr {
+                let value = if expr.uses_sequences() {
+                    expr.bind_sequences(resolve)?.evaluate(row)?
+                } else {
+                    expr.evaluate(row)?
+                };
+                let value = value.coerce_to(&column.data_type).ok_or_else(|| DatabaseError::type_mismatch(
+                    format!("列 '{}' 的默认值 {} 无法转换为 {}", column.name, value, column.data_type)
+                ))?;
+                row.set(column.name.clone(), value);
+            } else if let Some(default_value) = &column.default_value {
+                row.set(column.name.clone(), default_value.clone());
+            }
+        }
         Ok(())
     }
 
-    pub fn find_by_id(&self, id: Uuid) -> Option<&Row> {
-        self.rows.iter().find(|row| row.id == id)
+    pub fn find_by_id(&self, id: Uuid) -> Result<Option<Cow<'_, Row>>> {
+        for partition in &self.partitions {
+            if let Some(row) = partition.find_by_id(id)? {
+                return Ok(Some(row));
+            }
+        }
+        match self.layout {
+            TableLayout::Row => match self.row_position(id) {
+                Some(index) => Ok(self.rows.get(index).map(Cow::Borrowed)),
+                None if self.spilled.is_empty() => Ok(None),
+                None => Ok(self.load_spilled()?.into_iter().find(|row| row.id == id).map(Cow::Owned)),
+            },
+            TableLayout::Columnar => Ok(self.columns.position(id).map(|index| Cow::Owned(self.columns.row(index)))),
+        }
     }
 
     pub fn update(&mut self, id: Uuid, updates: HashMap<String, Value>) -> Result<()> {
-        if let Some(row) = self.rows.iter_mut().find(|row| row.id == id) {
-            for (column, value) in updates {
-                row.set(column, value);
+        self.update_at(id, updates, clock::now())
+    }
+
+    /// 使用指定的更新时间更新行（用于日志重放）
+    pub fn update_at(&mut self, id: Uuid, updates: HashMap<String, Value>, updated_at: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        self.check_update(&updates, |row| row.id == id)?;
+        if self.is_partitioned() {
+            let mut index = None;
+            for (i, partition) in self.partitions.iter().enumerate() {
+                if partition.find_by_id(id)?.is_some() {
+                    index = Some(i);
+                    break;
+                }
             }
-            row.updated_at = chrono::Utc::now();
-            Ok(())
-        } else {
-            Err(DatabaseError::Other(format!("未找到ID为 {} 的行", id)))
+            let index = index.ok_or_else(|| DatabaseError::Other(format!("未找到ID为 {} 的行", id)))?;
+            let moves_partition = self.updates_partition_key(&updates);
+            self.partitions[index].update_at(id, updates, updated_at)?;
+            if moves_partition {
+                self.relocate_rows()?;
+            }
+            return Ok(());
         }
+        self.invalidate_indexes();
+        match self.layout {
+            TableLayout::Row => {
+                if !self.spilled.is_empty() && self.row_position(id).is_none() {
+                    self.fault_in()?;
+                }
+                let index = self.row_position(id)
+                    .ok_or_else(|| DatabaseError::Other(format!("未找到ID为 {} 的行", id)))?;
+                let before = self.row_bytes(&self.rows.slots()[index]);
+                let row = self.rows.get_mut(index)
+                    .ok_or_else(|| DatabaseError::Other(format!("未找到ID为 {} 的行", id)))?;
+                for (column, value) in updates {
+                    row.set(column, value);
+                }
+                row.updated_at = updated_at;
+                row.version += 1;
+                let after = self.row_bytes(&self.rows.slots()[index]);
+                self.memory_bytes = (self.memory_bytes + after).saturating_sub(before);
+            }
+            TableLayout::Columnar => {
+                let index = self.columns.position(id)
+                    .ok_or_else(|| DatabaseError::Other(format!("未找到ID为 {} 的行", id)))?;
+                let before = self.row_bytes(&self.columns.row(index));
+                self.columns.update(index, updates, updated_at);
+                let after = self.row_bytes(&self.columns.row(index));
+                self.memory_bytes = (self.memory_bytes + after).saturating_sub(before);
+            }
+        }
+        self.maybe_spill()
     }
 
-    pub fn delete(&mut self, id: Uuid) -> Result<()> {
-        let initial_len = self.rows.len();
-        self.rows.retain(|row| row.id != id);
+    /// 更新所有满足条件的行，返回被更新行的ID
+    pub fn update_where<F>(&mut self, predicate: F, updates: &HashMap<String, Value>, updated_at: chrono::DateTime<chrono::Utc>) -> Result<Vec<Uuid>>
+    where
+        F: Fn(&Row) -> bool,
+    {
+        self.check_update(updates, &predicate)?;
+        if self.is_partitioned() {
+            let mut updated = Vec::new();
+            // 以 trait 对象传递条件，避免递归调用时泛型无限实例化
+            for partition in &mut self.partitions {
+                updated.extend(partition.update_where(&predicate as &dyn Fn(&Row) -> bool, updates, updated_at)?);
+            }
+            if !updated.is_empty() && self.updates_partition_key(updates) {
+                self.relocate_rows()?;
+            }
+            return Ok(updated);
+        }
+
+        self.fault_in()?;
+        let mut updated = Vec::new();
+        match self.layout {
+            TableLayout::Row => {
+                for row in self.rows.iter_mut().filter(|row| predicate(row)) {
+                    for (column, value) in updates {
+                        row.set(column.clone(), value.clone());
+                    }
+                    row.updated_at = updated_at;
+                    row.version += 1;
+                    updated.push(row.id);
+                }
+            }
+            TableLayout::Columnar => {
+                for index in 0..self.columns.len() {
+                    let row = self.columns.row(index);
+                    if predicate(&row) {
+                        self.columns.update(index, updates.clone(), updated_at);
+                        updated.push(row.id);
+                    }
+                }
+            }
+        }
+        if !updated.is_empty() {
+            self.invalidate_indexes();
+            self.recompute_memory();
+        }
+        self.maybe_spill()?;
+        Ok(updated)
+    }
+
+    /// 检查更新后的值是否满足非空与唯一约束，`is_target` 判断行是否会被更新
+    ///
+    /// 与插入返回相同的错误；任何行被修改之前完成检查，失败时表保持不变。
+    fn check_update<F>(&self, updates: &HashMap<String, Value>, is_target: F) -> Result<()>
+    where
+        F: Fn(&Row) -> bool,
+    {
+        for (name, value) in updates {
+            let Some(column) = self.schema.get_column(name) else { continue };
+            if value.is_null() {
+                if column.primary_key || !column.nullable {
+                    return Err(DatabaseError::not_null_violation(&self.name, name));
+                }
+                continue;
+            }
+            if !column.unique {
+                continue;
+            }
+
+            // 多行被更新为同一个值，或与其他行的值冲突，都违反唯一约束
+            let key = column.collation.key(value);
+            let mut targets = 0;
+            let mut conflict = false;
+            for row in self.all_rows()?.iter() {
+                if is_target(row) {
+                    targets += 1;
+                } else if row.get(name).is_some_and(|v| !v.is_null() && column.collation.key(v) == key) {
+                    conflict = true;
+                }
+            }
+            if conflict || targets > 1 {
+                return Err(DatabaseError::unique_violation(&self.name, name, value));
+            }
+        }
+        Ok(())
+    }
 
-        if self.rows.len() == initial_len {
+    /// 按 ID 删除一行，其余行保持插入顺序
+    ///
+    /// 行存储布局下只在原槽位留下墓碑，其余行的槽位不变，位置索引只去掉这一行，
+    /// 按位置寻址的索引继续有效（查找时跳过墓碑）；墓碑积累到一定数量时才压缩。
+    pub fn delete(&mut self, id: Uuid) -> Result<()> {
+        if !self.is_partitioned() && self.layout == TableLayout::Row {
+            self.ensure_row_positions();
+            if let Some(index) = self.row_positions.remove(&id) {
+                let bytes = self.row_bytes(&self.rows.slots()[index]);
+                self.rows.remove(index);
+                self.memory_bytes = self.memory_bytes.saturating_sub(bytes);
+                self.maybe_compact_rows();
+                return Ok(());
+            }
+        }
+        if self.delete_where(|row| row.id == id)?.is_empty() {
             Err(DatabaseError::Other(format!("未找到ID为 {} 的行", id)))
         } else {
             Ok(())
         }
     }
 
+    /// 删除所有满足条件的行，返回被删除行的ID
+    pub fn delete_where<F>(&mut self, predicate: F) -> Result<Vec<Uuid>>
+    where
+        F: Fn(&Row) -> bool,
+    {
+        if self.is_partitioned() {
+            let mut deleted = Vec::new();
+            for partition in &mut self.partitions {
+                deleted.extend(partition.delete_where(&predicate as &dyn Fn(&Row) -> bool)?);
+            }
+            return Ok(deleted);
+        }
+
+        self.fault_in()?;
+        self.compact_rows();
+        let mut deleted = Vec::new();
+        match self.layout {
+            TableLayout::Row => {
+                self.rows.retain(|row| {
+                    let matches = predicate(row);
+                    if matches {
+                        deleted.push(row.id);
+                    }
+                    !matches
+                });
+                if !deleted.is_empty() {
+                    self.reindex_rows();
+                }
+            }
+            TableLayout::Columnar => {
+                let keep: Vec<bool> = (0..self.columns.len())
+                    .map(|index| {
+                        let row = self.columns.row(index);
+                        let matches = predicate(&row);
+                        if matches {
+                            deleted.push(row.id);
+                        }
+                        !matches
+                    })
+                    .collect();
+                self.columns.retain_mask(&keep);
+            }
+        }
+        if !deleted.is_empty() {
+            self.invalidate_indexes();
+            self.recompute_memory();
+        }
+        self.maybe_spill()?;
+        Ok(deleted)
+    }
+
+    /// 检查行数据与位置索引、分区、溢出键以及约束是否一致，返回发现的问题；不修改表，已溢出的行只读取
+    pub fn integrity_issues(&self) -> Result<Vec<IntegrityIssue>> {
+        let mut issues = Vec::new();
+        self.check_storage(&mut issues)?;
+
+        let unique: Vec<&ColumnDefinition> = self.schema.columns.iter().filter(|column| column.unique).collect();
+        let mut keys = vec![HashSet::new(); unique.len()];
+        let mut ids = HashSet::new();
+        for row in self.all_rows()?.iter() {
+            if !ids.insert(row.id) {
+                issues.push(IntegrityIssue::new(IssueKind::Index, &self.name, format!("行 ID {} 重复", row.id)));
+            }
+            if let Err(e) = self.schema.validate_row(row) {
+                issues.push(IntegrityIssue::new(IssueKind::Constraint, &self.name, format!("行 {}: {}", row.id, e)));
+            }
+            for (column, value) in &row.data {
+                let Some(definition) = self.schema.get_column(column) else { continue };
+                if !value.is_null() && !value.is_compatible_with(&definition.data_type) {
+                    issues.push(IntegrityIssue::new(IssueKind::Constraint, &self.name, format!(
+                        "行 {} 的列 '{}' 期望 {} 类型，实际为 {}", row.id, column, definition.data_type, value.get_type()
+                    )));
+                }
+            }
+            for (column, keys) in unique.iter().zip(&mut keys) {
+                let Some(value) = row.get(&column.name).filter(|value| !value.is_null()) else { continue };
+                if !keys.insert(column.collation.key(value)) {
+                    issues.push(IntegrityIssue::new(IssueKind::Constraint, &self.name, format!(
+                        "唯一列 '{}' 的值 {} 重复", column.name, value
+                    )));
+                }
+            }
+        }
+        Ok(issues)
+    }
+
+    /// 检查分区归属、位置索引与已溢出行的唯一键
+    fn check_storage(&self, issues: &mut Vec<IntegrityIssue>) -> Result<()> {
+        if let Some(spec) = &self.partition_spec {
+            for (index, partition) in self.partitions.iter().enumerate() {
+                for row in partition.all_rows()?.iter() {
+                    let expected = spec.partition_for(row.get(spec.column()));
+                    if expected != index {
+                        issues.push(IntegrityIssue::new(IssueKind::Index, &self.name, format!(
+                            "行 {} 位于分区 {}，按分区键应在分区 {}", row.id, index, expected
+                        )));
+                    }
+                }
+                partition.check_storage(issues)?;
+            }
+            return Ok(());
+        }
+
+        // 位置索引与行数不一致时下次修改前会重建，只检查已经建好的索引
+        if matches!(self.layout, TableLayout::Row) && self.row_positions.len() == self.rows.len() {
+            for (index, row) in self.rows.entries() {
+                if self.row_positions.get(&row.id) != Some(&index) {
+                    issues.push(IntegrityIssue::new(IssueKind::Index, &self.name, format!(
+                        "行 {} 的位置索引与实际位置 {} 不一致", row.id, index
+                    )));
+                }
+            }
+        }
+        if !self.spilled.is_empty() {
+            let spilled = self.load_spilled()?;
+            for column in self.schema.columns.iter().filter(|column| column.unique) {
+                let keys = self.spilled_keys.get(&column.name);
+                for row in &spilled {
+                    let Some(value) = row.get(&column.name).filter(|value| !value.is_null()) else { continue };
+                    if !keys.is_some_and(|keys| keys.contains(&column.collation.key(value))) {
+                        issues.push(IntegrityIssue::new(IssueKind::Index, &self.name, format!(
+                            "已溢出的行 {} 在唯一列 '{}' 上的键没有记录", row.id, column.name
+                        )));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 删除所有行，返回删除的行数
+    ///
+    /// 不逐行检查：直接换成空的行存储，丢弃已溢出的段与按需构建的索引，已溢出的行也不读回内存；
+    /// 表结构、分区定义与建表选项保留。
+    pub fn truncate(&mut self) -> usize {
+        let count = self.row_count();
+        if self.is_partitioned() {
+            for partition in &mut self.partitions {
+                partition.truncate();
+            }
+        } else {
+            self.rows = Shared::default();
+            self.columns = match self.layout {
+                TableLayout::Row => ColumnStore::default(),
+                TableLayout::Columnar => ColumnStore::new(&self.schema),
+            }.into();
+            self.row_positions = Shared::default();
+            self.spilled.clear();
+            self.spilled_keys.clear();
+        }
+        self.stats = None;
+        self.invalidate_indexes();
+        self.recompute_memory();
+        count
+    }
+
     pub fn row_count(&self) -> usize {
-        self.rows.len()
+        if self.is_partitioned() {
+            return self.partitions.iter().map(Table::row_count).sum();
+        }
+        match self.layout {
+            TableLayout::Row => self.rows.len() + self.spilled_row_count(),
+            TableLayout::Columnar => self.columns.len(),
+        }
+    }
+
+    fn updates_partition_key(&self, updates: &HashMap<String, Value>) -> bool {
+        self.partition_spec.as_ref().is_some_and(|spec| updates.contains_key(spec.column()))
+    }
+
+    /// 更新分区键后，把不再属于原分区的行移动到新分区，行的元数据保持不变
+    fn relocate_rows(&mut self) -> Result<()> {
+        let Some(spec) = self.partition_spec.clone() else {
+            return Ok(());
+        };
+        let mut moved = Vec::new();
+        for (index, partition) in self.partitions.iter_mut().enumerate() {
+            let misplaced: Vec<Row> = partition.all_rows()?.iter()
+                .filter(|row| spec.partition_for(row.get(spec.column())) != index)
+                .cloned()
+                .collect();
+            if misplaced.is_empty() {
+                continue;
+            }
+            let ids: HashSet<Uuid> = misplaced.iter().map(|row| row.id).collect();
+            partition.delete_where(|row| ids.contains(&row.id))?;
+            moved.extend(misplaced);
+        }
+        for row in moved {
+            self.push_row(row);
+        }
+        Ok(())
     }
 }
 
@@ -419,6 +1817,24 @@ mod tests {
         assert!(schema.validate_row(&row).is_ok());
     }
 
+    #[test]
+    fn test_strict_violations_and_coercion() {
+        let schema = Schema::new(vec![
+            ColumnDefinition::new("id", DataType::Integer, true),
+            ColumnDefinition::new("hired", DataType::Date, false),
+        ]);
+
+        let mut data = HashMap::new();
+        data.insert("id".to_string(), Value::Integer(1));
+        data.insert("hired".to_string(), Value::Text("2020-01-15".to_string()));
+        data.insert("extra".to_string(), Value::Null);
+        assert_eq!(schema.strict_violations(&data).len(), 2);
+
+        let coerced = Value::Text("2020-01-15".to_string()).coerce_to(&DataType::Date);
+        assert_eq!(coerced, Some(Value::Date(chrono::NaiveDate::from_ymd_opt(2020, 1, 15).unwrap())));
+        assert_eq!(Value::Text("abc".to_string()).coerce_to(&DataType::Integer), None);
+    }
+
     #[test]
     fn test_table_operations() {
         let schema = Schema::new(vec![
@@ -435,4 +1851,156 @@ mod tests {
         assert!(table.insert(row).is_ok());
         assert_eq!(table.row_count(), 1);
     }
+
+    #[test]
+    fn test_insert_batch_is_all_or_nothing() {
+        let schema = Schema::new(vec![
+            ColumnDefinition::new("id", DataType::Integer, true),
+        ]);
+        let mut table = Table::new("batch".to_string(), schema);
+
+        let make_row = |id: i64| {
+            let mut row = Row::new();
+            row.set("id", Value::Integer(id));
+            row
+        };
+
+        assert!(table.insert_batch(vec![make_row(1), make_row(2)]).is_ok());
+        assert_eq!(table.row_count(), 2);
+
+        // 批内重复与已有数据重复都会使整批失败
+        assert!(table.insert_batch(vec![make_row(3), make_row(3)]).is_err());
+        assert!(table.insert_batch(vec![make_row(4), make_row(1)]).is_err());
+        assert_eq!(table.row_count(), 2);
+    }
+
+    #[test]
+    fn test_row_positions_track_mutations() {
+        let schema = Schema::new(vec![
+            ColumnDefinition::new("id", DataType::Integer, true),
+        ]);
+        let mut table = Table::new("items".to_string(), schema);
+        for id in 0..5 {
+            let mut row = Row::new();
+            row.set("id", Value::Integer(id));
+            table.insert(row).unwrap();
+        }
+        let ids: Vec<Uuid> = table.all_rows().unwrap().iter().map(|row| row.id).collect();
+
+        table.delete(ids[1]).unwrap();
+        assert!(table.find_by_id(ids[1]).unwrap().is_none());
+        // 删除不改变其余行的顺序
+        let order: Vec<Uuid> = table.all_rows().unwrap().iter().map(|row| row.id).collect();
+        assert_eq!(order, vec![ids[0], ids[2], ids[3], ids[4]]);
+        let mut issues = Vec::new();
+        table.check_storage(&mut issues).unwrap();
+        assert!(issues.is_empty(), "{:?}", issues);
+        assert!(table.delete(ids[1]).is_err());
+        table.update(ids[4], HashMap::from([("id".to_string(), Value::Integer(40))])).unwrap();
+        assert_eq!(table.find_by_id(ids[4]).unwrap().unwrap().get_integer("id"), Some(40));
+
+        table.delete_where(|row| row.get_integer("id") == Some(0)).unwrap();
+        for &id in &[ids[2], ids[3], ids[4]] {
+            assert_eq!(table.find_by_id(id).unwrap().map(|row| row.id), Some(id));
+        }
+
+        // 反序列化的表没有位置索引，查找退回扫描，修改时重建
+        let mut restored: Table = serde_json::from_str(&serde_json::to_string(&table).unwrap()).unwrap();
+        assert!(restored.find_by_id(ids[3]).unwrap().is_some());
+        restored.delete(ids[3]).unwrap();
+        assert_eq!(restored.row_count(), 2);
+        assert!(restored.find_by_id(ids[2]).unwrap().is_some());
+
+        // 克隆的表共享行数据，写入时才复制
+        let snapshot = table.clone();
+        assert!(Shared::ptr_eq(&snapshot.rows, &table.rows));
+        table.delete(ids[2]).unwrap();
+        assert!(!Shared::ptr_eq(&snapshot.rows, &table.rows));
+        assert_eq!((snapshot.row_count(), table.row_count()), (3, 2));
+    }
+
+    #[test]
+    fn test_delete_from_middle_of_large_table() {
+        let schema = Schema::new(vec![
+            ColumnDefinition::new("id", DataType::Integer, true),
+        ]);
+        let mut table = Table::new("items".to_string(), schema);
+        let rows = (0..10_000).map(|id| {
+            let mut row = Row::new();
+            row.set("id", Value::Integer(id));
+            row
+        }).collect();
+        table.insert_batch(rows).unwrap();
+        let ids: Vec<Uuid> = table.all_rows().unwrap().iter().map(|row| row.id).collect();
+        let map = table.zone_map("id").unwrap().unwrap();
+
+        // 删除只留下墓碑：其余行不移动，已构建的区域映射继续使用
+        for &id in &ids[4_000..4_500] {
+            table.delete(id).unwrap();
+        }
+        assert_eq!(table.rows.tombstones(), 500);
+        assert!(Arc::ptr_eq(&map, &table.zone_map("id").unwrap().unwrap()));
+        let expected: Vec<Uuid> = ids[..4_000].iter().chain(&ids[4_500..]).copied().collect();
+        let order: Vec<Uuid> = table.all_rows().unwrap().iter().map(|row| row.id).collect();
+        assert_eq!(order, expected);
+        assert!(table.find_by_id(ids[4_200]).unwrap().is_none());
+        assert_eq!(table.find_by_id(ids[4_500]).unwrap().map(|row| row.id), Some(ids[4_500]));
+        let found: Vec<Uuid> = table.rows_at(&[0, 4_000, 4_500]).unwrap().iter().map(|row| row.id).collect();
+        assert_eq!(found, vec![ids[0], ids[4_500]]);
+
+        // 墓碑达到槽位的四分之一时压缩，位置索引随之重建
+        for &id in &ids[..2_000] {
+            table.delete(id).unwrap();
+        }
+        assert_eq!(table.rows.tombstones(), 0);
+        assert_eq!(table.row_count(), 7_500);
+        let order: Vec<Uuid> = table.all_rows().unwrap().iter().map(|row| row.id).collect();
+        assert_eq!(order, expected[2_000..]);
+        for &id in &[ids[2_000], ids[3_999], ids[4_500], ids[9_999]] {
+            assert_eq!(table.find_by_id(id).unwrap().map(|row| row.id), Some(id));
+        }
+        let mut issues = Vec::new();
+        table.check_storage(&mut issues).unwrap();
+        assert!(issues.is_empty(), "{:?}", issues);
+    }
+
+    #[test]
+    fn test_update_enforces_constraints() {
+        let schema = Schema::new(vec![
+            ColumnDefinition::new("id", DataType::Integer, true),
+            ColumnDefinition::new("email", DataType::Text, false).unique(true),
+            ColumnDefinition::new("name", DataType::Text, false).nullable(false),
+        ]);
+        let mut table = Table::new("users".to_string(), schema);
+        for (id, email) in [(1, "a@x.com"), (2, "b@x.com")] {
+            let mut row = Row::new();
+            row.set("id", Value::Integer(id));
+            row.set("email", Value::Text(email.to_string()));
+            row.set("name", Value::Text("n".to_string()));
+            table.insert(row).unwrap();
+        }
+        let first = table.all_rows().unwrap()[0].id;
+        let set = |column: &str, value: Value| HashMap::from([(column.to_string(), value)]);
+
+        let err = table.update(first, set("email", Value::Text("b@x.com".to_string()))).unwrap_err();
+        assert!(matches!(&err, DatabaseError::UniqueViolation { table, column, value }
+            if table == "users" && column == "email" && value == "b@x.com"));
+        assert_eq!((err.code(), err.is_constraint_violation()), ("SDB-2001", true));
+        assert!(matches!(table.update(first, set("name", Value::Null)), Err(DatabaseError::NotNullViolation { .. })));
+        let err = table.update(first, set("id", Value::Null)).unwrap_err();
+        assert_eq!((err.table(), err.column(), err.code()), (Some("users"), Some("id"), "SDB-2002"));
+
+        // 表结构校验返回的错误由表补上表名
+        let mut row = Row::new();
+        row.set("id", Value::Integer(3));
+        let err = table.insert(row).unwrap_err();
+        assert_eq!(err.to_string(), "违反非空约束: 表 'users' 列 'name' 不能为空");
+        // 把唯一列更新为自身当前的值是允许的
+        assert!(table.update(first, set("email", Value::Text("a@x.com".to_string()))).is_ok());
+
+        let now = chrono::Utc::now();
+        assert!(table.update_where(|_| true, &set("email", Value::Text("c@x.com".to_string())), now).is_err());
+        assert_eq!(table.all_rows().unwrap()[1].get_text("email"), Some("b@x.com"));
+        assert!(table.update_where(|_| true, &set("email", Value::Null), now).is_ok());
+    }
 }
\ No newline at end of file
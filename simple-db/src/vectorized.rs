@@ -0,0 +1,309 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::collation::Collation;
+use crate::columnar::{ColumnData, ColumnStore};
+use crate::error::{DatabaseError, Result};
+use crate::simd;
+use crate::query::{ComparisonOperator, Condition};
+use crate::types::Value;
+
+/// 每批处理的值个数
+pub const BATCH_SIZE: usize = 1024;
+
+/// 数值列的聚合函数，忽略 NULL；没有非 NULL 值时结果为 NULL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregate {
+    /// 整数列的和为整数，含浮点数时为浮点数
+    Sum,
+    /// 平均值，总是浮点数
+    Avg,
+    Min,
+    Max,
+}
+
+impl FromStr for Aggregate {
+    type Err = DatabaseError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_uppercase().as_str() {
+            "SUM" => Ok(Aggregate::Sum),
+            "AVG" => Ok(Aggregate::Avg),
+            "MIN" => Ok(Aggregate::Min),
+            "MAX" => Ok(Aggregate::Max),
+            _ => Err(DatabaseError::parse_error(format!("未知的聚合函数: {}（可选 SUM、AVG、MIN、MAX）", s))),
+        }
+    }
+}
+
+impl fmt::Display for Aggregate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Aggregate::Sum => "SUM",
+            Aggregate::Avg => "AVG",
+            Aggregate::Min => "MIN",
+            Aggregate::Max => "MAX",
+        })
+    }
+}
+
+/// 按列分批求出满足全部条件的行，返回与行一一对应的选择向量
+///
+/// 每个条件在一批值上用紧凑的循环求值，不物化行也不构造 `Value`。条件无法按列求值时
+/// （表达式、JSON 路径、参数、Mixed 列、伪列、值的类型与列不符等）返回 None，由调用方逐行求值。
+pub fn filter(store: &ColumnStore, conditions: &[Condition]) -> Option<Vec<bool>> {
+    let predicates = conditions.iter()
+        .map(|condition| Predicate::new(store, condition))
+        .collect::<Option<Vec<_>>>()?;
+    let mut selection = vec![true; store.len()];
+    for (batch, chunk) in selection.chunks_mut(BATCH_SIZE).enumerate() {
+        let start = batch * BATCH_SIZE;
+        for predicate in &predicates {
+            predicate.apply(start, chunk);
+        }
+    }
+    Some(selection)
+}
+
+/// 对选中的行按批聚合数值列；列不是 INTEGER 或 FLOAT 列时返回 None
+pub fn aggregate(store: &ColumnStore, column: &str, selection: &[bool], aggregate: Aggregate) -> Option<Result<Value>> {
+    let mut totals = Totals::default();
+    match store.column(column)? {
+        ColumnData::Integer(values) => {
+            for (values, selection) in values.chunks(BATCH_SIZE).zip(selection.chunks(BATCH_SIZE)) {
+                let summary = simd::summarize(values, selection);
+                if summary.count > 0 {
+                    totals.add_integers(summary.count, summary.sum, summary.min, summary.max);
+                }
+            }
+        }
+        ColumnData::Float(values) => {
+            for (values, selection) in values.chunks(BATCH_SIZE).zip(selection.chunks(BATCH_SIZE)) {
+                let summary = simd::summarize(values, selection);
+                if summary.count > 0 {
+                    totals.add_floats(summary.count, summary.sum, summary.min, summary.max);
+                }
+            }
+        }
+        _ => return None,
+    }
+    Some(totals.finish(aggregate))
+}
+
+/// 聚合的中间结果，按批或逐个值累加
+#[derive(Debug, Default)]
+pub struct Totals {
+    count: usize,
+    integer_sum: i128,
+    float_sum: f64,
+    has_float: bool,
+    min: Option<Value>,
+    max: Option<Value>,
+}
+
+impl Totals {
+    /// 累加一个值，NULL 被忽略
+    pub fn add(&mut self, value: &Value) -> Result<()> {
+        match value {
+            Value::Null => Ok(()),
+            Value::Integer(i) => {
+                self.add_integers(1, *i as i128, *i, *i);
+                Ok(())
+            }
+            Value::Float(f) => {
+                self.add_floats(1, *f, *f, *f);
+                Ok(())
+            }
+            other => Err(DatabaseError::type_mismatch(format!("只能聚合数值，实际为 {}", other.get_type()))),
+        }
+    }
+
+    fn add_integers(&mut self, count: usize, sum: i128, min: i64, max: i64) {
+        self.count += count;
+        self.integer_sum += sum;
+        self.merge_extremes(Value::Integer(min), Value::Integer(max));
+    }
+
+    fn add_floats(&mut self, count: usize, sum: f64, min: f64, max: f64) {
+        self.count += count;
+        self.float_sum += sum;
+        self.has_float = true;
+        self.merge_extremes(Value::Float(min), Value::Float(max));
+    }
+
+    fn merge_extremes(&mut self, min: Value, max: Value) {
+        if self.min.as_ref().is_none_or(|current| numeric_cmp(&min, current) == Ordering::Less) {
+            self.min = Some(min);
+        }
+        if self.max.as_ref().is_none_or(|current| numeric_cmp(&max, current) == Ordering::Greater) {
+            self.max = Some(max);
+        }
+    }
+
+    pub fn finish(self, aggregate: Aggregate) -> Result<Value> {
+        if self.count == 0 {
+            return Ok(Value::Null);
+        }
+        let total = self.integer_sum as f64 + self.float_sum;
+        match aggregate {
+            Aggregate::Sum if self.has_float => Ok(Value::Float(total)),
+            Aggregate::Sum => i64::try_from(self.integer_sum)
+                .map(Value::Integer)
+                .map_err(|_| DatabaseError::type_mismatch("SUM 溢出")),
+            Aggregate::Avg => Ok(Value::Float(total / self.count as f64)),
+            Aggregate::Min => Ok(self.min.unwrap_or(Value::Null)),
+            Aggregate::Max => Ok(self.max.unwrap_or(Value::Null)),
+        }
+    }
+}
+
+fn numeric_cmp(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Integer(a), Value::Integer(b)) => a.cmp(b),
+        (Value::Integer(a), Value::Float(b)) => (*a as f64).total_cmp(b),
+        (Value::Float(a), Value::Integer(b)) => a.total_cmp(&(*b as f64)),
+        (Value::Float(a), Value::Float(b)) => a.total_cmp(b),
+        _ => Ordering::Equal,
+    }
+}
+
+/// 比较结果为小于、等于、大于时是否满足条件
+#[derive(Debug, Clone, Copy)]
+struct Accepts([bool; 3]);
+
+impl Accepts {
+    fn new(operator: &ComparisonOperator) -> Option<Self> {
+        Some(Accepts(match operator {
+            ComparisonOperator::Equal => [false, true, false],
+            ComparisonOperator::NotEqual => [true, false, true],
+            ComparisonOperator::GreaterThan => [false, false, true],
+            ComparisonOperator::GreaterThanOrEqual => [false, true, true],
+            ComparisonOperator::LessThan => [true, false, false],
+            ComparisonOperator::LessThanOrEqual => [true, true, false],
+            _ => return None,
+        }))
+    }
+
+    fn test(self, ordering: Ordering) -> bool {
+        self.0[(ordering as i8 + 1) as usize]
+    }
+}
+
+/// 一个可以按列求值的条件
+enum Test<'a> {
+    /// IS NULL 为 true，IS NOT NULL 为 false
+    Null(bool),
+    Integer(i64, Accepts),
+    /// 浮点数比较，整数列与浮点数比较时也按浮点数比较；NaN 与任何数相等，与逐行比较一致
+    Float(f64, Accepts),
+    Boolean(bool, Accepts),
+    Text(&'a str, Collation, Accepts),
+}
+
+struct Predicate<'a> {
+    column: &'a ColumnData,
+    test: Test<'a>,
+}
+
+impl<'a> Predicate<'a> {
+    fn new(store: &'a ColumnStore, condition: &'a Condition) -> Option<Self> {
+        if !condition.is_single_column() || condition.path.is_some() || condition.param.is_some() {
+            return None;
+        }
+        let column = store.column(&condition.column)?;
+        let test = match (&condition.operator, column, &condition.value) {
+            (_, ColumnData::Mixed(_), _) => return None,
+            (ComparisonOperator::IsNull, _, _) => Test::Null(true),
+            (ComparisonOperator::IsNotNull, _, _) => Test::Null(false),
+            (operator, ColumnData::Integer(_), Value::Integer(value)) => Test::Integer(*value, Accepts::new(operator)?),
+            (operator, ColumnData::Integer(_) | ColumnData::Float(_), Value::Float(value)) => Test::Float(*value, Accepts::new(operator)?),
+            (operator, ColumnData::Float(_), Value::Integer(value)) => Test::Float(*value as f64, Accepts::new(operator)?),
+            (operator, ColumnData::Boolean(_), Value::Boolean(value)) => Test::Boolean(*value, Accepts::new(operator)?),
+            (operator, ColumnData::Text(_), Value::Text(value)) => Test::Text(value, condition.collation, Accepts::new(operator)?),
+            _ => return None,
+        };
+        Some(Self { column, test })
+    }
+
+    /// 在从 `start` 开始的一批行上求值，与选择向量按位与
+    fn apply(&self, start: usize, selection: &mut [bool]) {
+        let range = start..start + selection.len();
+        match (self.column, &self.test) {
+            (ColumnData::Integer(values), Test::Null(null)) => select(selection, &values[range], |value| value.is_none() == *null),
+            (ColumnData::Float(values), Test::Null(null)) => select(selection, &values[range], |value| value.is_none() == *null),
+            (ColumnData::Boolean(values), Test::Null(null)) => select(selection, &values[range], |value| value.is_none() == *null),
+            (ColumnData::Text(values), Test::Null(null)) => select(selection, &values[range], |value| value.is_none() == *null),
+            // 数值比较交给按组处理的内核
+            (ColumnData::Integer(values), Test::Integer(target, accepts)) => {
+                simd::select(selection, &values[range], *target, accepts.0, |value| value)
+            }
+            (ColumnData::Integer(values), Test::Float(target, accepts)) => {
+                simd::select(selection, &values[range], *target, accepts.0, |value| value as f64)
+            }
+            (ColumnData::Float(values), Test::Float(target, accepts)) => {
+                simd::select(selection, &values[range], *target, accepts.0, |value| value)
+            }
+            (ColumnData::Boolean(values), Test::Boolean(target, accepts)) => {
+                select(selection, &values[range], |value| value.is_some_and(|value| accepts.test(value.cmp(target))))
+            }
+            (ColumnData::Text(values), Test::Text(target, collation, accepts)) => {
+                select(selection, &values[range], |value| value.is_some_and(|value| accepts.test(collation.compare(value, target))))
+            }
+            _ => selection.fill(false),
+        }
+    }
+}
+
+/// 不满足 `test` 的位置在选择向量中置为 false；不短路，便于编译器展开循环
+fn select<T>(selection: &mut [bool], values: &[Option<T>], test: impl Fn(Option<&T>) -> bool) {
+    for (keep, value) in selection.iter_mut().zip(values) {
+        *keep &= test(value.as_ref());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ColumnDefinition, DataType, Row, Schema};
+
+    #[test]
+    fn test_filter_and_aggregate_in_batches() {
+        let schema = Schema::new(vec![
+            ColumnDefinition::new("n", DataType::Integer, false),
+            ColumnDefinition::new("price", DataType::Float, false),
+            ColumnDefinition::new("name", DataType::Text, false),
+        ]);
+        let mut store = ColumnStore::new(&schema);
+        for i in 0..3000i64 {
+            let mut row = Row::new();
+            row.set("n", Value::Integer(i));
+            if i % 3 != 0 {
+                row.set("price", Value::Float(i as f64 / 2.0));
+            }
+            row.set("name", Value::Text(if i % 2 == 0 { "Even" } else { "odd" }.to_string()));
+            store.push(row);
+        }
+
+        let conditions = [
+            Condition::new("n", ComparisonOperator::GreaterThanOrEqual, Value::Float(1000.5)),
+            Condition::new("name", ComparisonOperator::Equal, Value::Text("even".to_string())).with_collation(Collation::CaseInsensitive),
+            Condition::new("price", ComparisonOperator::IsNotNull, Value::Null),
+        ];
+        let selection = filter(&store, &conditions).unwrap();
+        let expected: Vec<i64> = (1001..3000).filter(|i| i % 2 == 0 && i % 3 != 0).collect();
+        assert_eq!(selection.iter().filter(|keep| **keep).count(), expected.len());
+
+        let sum = aggregate(&store, "n", &selection, Aggregate::Sum).unwrap().unwrap();
+        assert_eq!(sum, Value::Integer(expected.iter().sum()));
+        assert_eq!(aggregate(&store, "n", &selection, Aggregate::Max).unwrap().unwrap(), Value::Integer(2998));
+        assert_eq!(aggregate(&store, "price", &selection, Aggregate::Min).unwrap().unwrap(), Value::Float(502.0));
+        assert_eq!(aggregate(&store, "price", &vec![false; 3000], Aggregate::Avg).unwrap().unwrap(), Value::Null);
+        assert!(aggregate(&store, "name", &selection, Aggregate::Sum).is_none());
+
+        // 表达式与类型不符的值留给逐行求值
+        assert!(filter(&store, &[Condition::new("n", ComparisonOperator::Equal, Value::Text("1".to_string()))]).is_none());
+        assert!(filter(&store, &[Condition::new("n", ComparisonOperator::Like, Value::Text("1%".to_string()))]).is_none());
+        assert_eq!("avg".parse::<Aggregate>().unwrap(), Aggregate::Avg);
+    }
+}
@@ -0,0 +1,164 @@
+use std::cmp::Ordering;
+use std::ops::Range;
+
+use crate::collation::Collation;
+use crate::query::{ComparisonOperator, Condition};
+use crate::types::{Row, Value};
+
+/// 每个数据块的行数
+pub const ZONE_SIZE: usize = 1024;
+
+/// 一个数据块在某列上的取值范围
+#[derive(Debug, Clone, PartialEq)]
+enum Zone {
+    /// 块中该列全部为 NULL，任何比较都不会匹配
+    Empty,
+    /// 非 NULL 值的最小值与最大值
+    Range(Value, Value),
+    /// 含有无法排序的值（类型混杂、NaN 等），不能跳过
+    Unknown,
+}
+
+/// 区域映射：把行按 `ZONE_SIZE` 行一块划分，记录每块在某列上的最小值与最大值
+///
+/// 范围条件与某块的取值范围不相交时整块跳过，对按时间顺序写入的数据效果最好。
+/// 与元素索引一样在首次查询时按需构建并缓存在表上，表数据变化后失效重建；
+/// 位置对应 `Table::indexed_rows` 的顺序。
+#[derive(Debug, Clone)]
+pub struct ZoneMap {
+    zones: Vec<Zone>,
+    rows: usize,
+    collation: Collation,
+}
+
+impl ZoneMap {
+    pub fn build(rows: &[Row], column: &str, collation: Collation) -> Self {
+        let zones = rows.chunks(ZONE_SIZE)
+            .map(|chunk| {
+                let mut zone = Zone::Empty;
+                for value in chunk.iter().filter_map(|row| row.get(column)).filter(|value| !value.is_null()) {
+                    zone = match zone {
+                        Zone::Empty if compare(value, value, collation).is_some() => Zone::Range(value.clone(), value.clone()),
+                        Zone::Range(min, max) => match (compare(value, &min, collation), compare(value, &max, collation)) {
+                            (Some(Ordering::Less), _) => Zone::Range(value.clone(), max),
+                            (_, Some(Ordering::Greater)) => Zone::Range(min, value.clone()),
+                            (Some(_), Some(_)) => Zone::Range(min, max),
+                            _ => Zone::Unknown,
+                        },
+                        _ => Zone::Unknown,
+                    };
+                    if zone == Zone::Unknown {
+                        break;
+                    }
+                }
+                zone
+            })
+            .collect();
+        Self { zones, rows: rows.len(), collation }
+    }
+
+    /// 可能满足条件的行位置，升序；条件不能用于裁剪或没有块被跳过时返回 None
+    pub fn candidates(&self, condition: &Condition) -> Option<Vec<usize>> {
+        if !condition.is_single_column() || condition.path.is_some() || condition.param.is_some()
+            || condition.collation != self.collation || condition.value.is_null()
+        {
+            return None;
+        }
+        if !matches!(
+            condition.operator,
+            ComparisonOperator::Equal | ComparisonOperator::GreaterThan | ComparisonOperator::GreaterThanOrEqual
+                | ComparisonOperator::LessThan | ComparisonOperator::LessThanOrEqual
+        ) {
+            return None;
+        }
+        let value = &condition.value;
+        let keep = |zone: &Zone| -> bool {
+            let (min, max) = match zone {
+                Zone::Empty => return false,
+                Zone::Unknown => return true,
+                Zone::Range(min, max) => (min, max),
+            };
+            let (Some(min), Some(max)) = (compare(min, value, self.collation), compare(max, value, self.collation)) else {
+                return true;
+            };
+            match condition.operator {
+                ComparisonOperator::Equal => min != Ordering::Greater && max != Ordering::Less,
+                ComparisonOperator::GreaterThan => max == Ordering::Greater,
+                ComparisonOperator::GreaterThanOrEqual => max != Ordering::Less,
+                ComparisonOperator::LessThan => min == Ordering::Less,
+                ComparisonOperator::LessThanOrEqual => min != Ordering::Greater,
+                _ => true,
+            }
+        };
+
+        let kept: Vec<usize> = (0..self.zones.len()).filter(|zone| keep(&self.zones[*zone])).collect();
+        if kept.len() == self.zones.len() {
+            return None;
+        }
+        Some(kept.into_iter().flat_map(|zone| self.range(zone)).collect())
+    }
+
+    /// 第 `zone` 块的行位置
+    fn range(&self, zone: usize) -> Range<usize> {
+        zone * ZONE_SIZE..((zone + 1) * ZONE_SIZE).min(self.rows)
+    }
+
+    /// 映射占用的内存估算（字节）
+    pub fn heap_size(&self) -> usize {
+        self.zones.capacity() * std::mem::size_of::<Zone>()
+    }
+}
+
+/// 与条件求值一致的排序，整数与浮点数按浮点数比较；无法比较或含 NaN 时返回 None
+fn compare(a: &Value, b: &Value, collation: Collation) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Integer(a), Value::Integer(b)) => Some(a.cmp(b)),
+        (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+        (Value::Integer(a), Value::Float(b)) => (*a as f64).partial_cmp(b),
+        (Value::Float(a), Value::Integer(b)) => a.partial_cmp(&(*b as f64)),
+        (Value::Text(a), Value::Text(b)) => Some(collation.compare(a, b)),
+        (Value::Date(a), Value::Date(b)) => Some(a.cmp(b)),
+        (Value::Time(a), Value::Time(b)) => Some(a.cmp(b)),
+        (Value::DateTime(a), Value::DateTime(b)) => Some(a.cmp(b)),
+        (Value::Interval(a), Value::Interval(b)) => Some(a.cmp(b)),
+        (Value::Binary(a), Value::Binary(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zone_pruning() {
+        // 3000 行按时间递增，第二块中有一个浮点数 NaN，第三块全部为 NULL
+        let rows: Vec<Row> = (0..3000i64)
+            .map(|i| {
+                let mut row = Row::new();
+                let value = match i {
+                    1500 => Value::Float(f64::NAN),
+                    2048.. => Value::Null,
+                    _ => Value::Integer(i),
+                };
+                row.set("t", value);
+                row
+            })
+            .collect();
+        let map = ZoneMap::build(&rows, "t", Collation::Binary);
+
+        let condition = Condition::new("t", ComparisonOperator::LessThan, Value::Integer(100));
+        assert_eq!(map.candidates(&condition), Some((0..2048).collect()));
+        let condition = Condition::new("t", ComparisonOperator::Equal, Value::Float(10.0));
+        assert_eq!(map.candidates(&condition), Some((0..2048).collect()));
+        let condition = Condition::new("t", ComparisonOperator::GreaterThan, Value::Integer(1023));
+        assert_eq!(map.candidates(&condition), Some((1024..2048).collect()));
+
+        // 类型不同的值无法比较，只跳过全部为 NULL 的块；不等于与其他排序规则不裁剪
+        let condition = Condition::new("t", ComparisonOperator::LessThan, Value::Text("a".to_string()));
+        assert_eq!(map.candidates(&condition), Some((0..2048).collect()));
+        assert_eq!(map.candidates(&Condition::new("t", ComparisonOperator::NotEqual, Value::Integer(5))), None);
+        let condition = Condition::new("t", ComparisonOperator::LessThan, Value::Integer(5)).with_collation(Collation::CaseInsensitive);
+        assert_eq!(map.candidates(&condition), None);
+    }
+}
@@ -8,7 +8,7 @@ use simple_db::types::{Value, DataType, Schema, ColumnDefinition};
 
 #[tokio::test]
 async fn test_create_and_drop_table() {
-    let engine = DatabaseEngine::new();
+    let engine = DatabaseEngine::in_memory();
 
     // 创建表
     let schema = Schema::new(vec![
@@ -32,7 +32,7 @@ async fn test_create_and_drop_table() {
 
 #[tokio::test]
 async fn test_insert_and_query() {
-    let engine = DatabaseEngine::new();
+    let engine = DatabaseEngine::in_memory();
 
     // 创建表
     let schema = Schema::new(vec![
@@ -61,7 +61,7 @@ async fn test_insert_and_query() {
 
 #[tokio::test]
 async fn test_update_and_delete() {
-    let engine = DatabaseEngine::new();
+    let engine = DatabaseEngine::in_memory();
 
     // 创建表并插入数据
     let schema = Schema::new(vec![
@@ -75,7 +75,7 @@ async fn test_update_and_delete() {
     data.insert("id".to_string(), Value::Integer(1));
     data.insert("name".to_string(), Value::Text("Original".to_string()));
 
-    engine.insert("test", data).await.unwrap();
+    let id = engine.insert("test", data).await.unwrap();
 
     // 更新数据
     let conditions = vec![("id".to_string(), ComparisonOperator::Equal, Value::Integer(1))];
@@ -83,7 +83,7 @@ async fn test_update_and_delete() {
     updates.insert("name".to_string(), Value::Text("Updated".to_string()));
 
     let affected = engine.update("test", conditions, updates).await.unwrap();
-    assert_eq!(affected, 1);
+    assert_eq!(affected, vec![id]);
 
     // 验证更新
     let query = QueryBuilder::select("test").build();
@@ -93,7 +93,7 @@ async fn test_update_and_delete() {
     // 删除数据
     let conditions = vec![("id".to_string(), ComparisonOperator::Equal, Value::Integer(1))];
     let affected = engine.delete("test", conditions).await.unwrap();
-    assert_eq!(affected, 1);
+    assert_eq!(affected, vec![id]);
 
     // 验证删除
     let query = QueryBuilder::select("test").build();
@@ -103,7 +103,7 @@ async fn test_update_and_delete() {
 
 #[tokio::test]
 async fn test_constraints() {
-    let engine = DatabaseEngine::new();
+    let engine = DatabaseEngine::in_memory();
 
     // 创建带唯一约束的表
     let schema = Schema::new(vec![
@@ -126,11 +126,22 @@ async fn test_constraints() {
     data2.insert("email".to_string(), Value::Text("test@example.com".to_string())); // 重复email
 
     assert!(engine.insert("users", data2).await.is_err());
+
+    // 更新同样受唯一约束限制
+    let mut data3 = std::collections::HashMap::new();
+    data3.insert("id".to_string(), Value::Integer(3));
+    data3.insert("email".to_string(), Value::Text("other@example.com".to_string()));
+    assert!(engine.insert("users", data3).await.is_ok());
+
+    let mut updates = std::collections::HashMap::new();
+    updates.insert("email".to_string(), Value::Text("test@example.com".to_string()));
+    let conditions = vec![("id".to_string(), ComparisonOperator::Equal, Value::Integer(3))];
+    assert!(engine.update("users", conditions, updates).await.is_err());
 }
 
 #[tokio::test]
 async fn test_query_conditions() {
-    let engine = DatabaseEngine::new();
+    let engine = DatabaseEngine::in_memory();
 
     // 创建表
     let schema = Schema::new(vec![
@@ -185,11 +196,10 @@ async fn test_query_conditions() {
 
 #[tokio::test]
 async fn test_transactions() {
-    let mut engine = DatabaseEngine::new();
-    engine.set_auto_save(false);
+    let engine = DatabaseEngine::in_memory();
 
     // 测试成功的事务
-    let result = engine.transaction(|mut tx| {
+    let result = engine.transaction(|tx| {
         let schema = Schema::new(vec![
             ColumnDefinition::new("id", DataType::Integer, true),
             ColumnDefinition::new("data", DataType::Text, false),
@@ -217,7 +227,7 @@ async fn test_transactions() {
 
 #[tokio::test]
 async fn test_batch_operations() {
-    let engine = DatabaseEngine::new();
+    let engine = DatabaseEngine::in_memory();
 
     // 创建表
     let schema = Schema::new(vec![
@@ -245,9 +255,53 @@ async fn test_batch_operations() {
     assert_eq!(result.count, Some(100));
 }
 
+#[tokio::test]
+async fn test_batch_insert_single_log_entry() {
+    use simple_db::storage::StorageOperation;
+
+    let engine = DatabaseEngine::in_memory();
+    let schema = Schema::new(vec![
+        ColumnDefinition::new("id", DataType::Integer, true),
+        ColumnDefinition::new("name", DataType::Text, false),
+    ]);
+    engine.create_table("single_rows", schema.clone()).await.unwrap();
+    engine.create_table("batch_rows", schema.clone()).await.unwrap();
+    let mut log = engine.subscribe_log(0).await.unwrap();
+
+    let make_rows = || {
+        (0..3000).map(|i| {
+            let mut data = std::collections::HashMap::new();
+            data.insert("id".to_string(), Value::Integer(i));
+            data.insert("name".to_string(), Value::Text(format!("Item {}", i)));
+            data
+        }).collect::<Vec<_>>()
+    };
+
+    // 逐行插入每行一条日志
+    for data in make_rows() {
+        engine.insert("single_rows", data).await.unwrap();
+    }
+    for _ in 0..3000 {
+        assert!(matches!(log.recv().await.unwrap().operation, StorageOperation::Insert { .. }));
+    }
+
+    // 批量插入整批只写一条日志，紧接着的就是之后的建表
+    let ids = engine.batch_insert("batch_rows", make_rows()).await.unwrap();
+    engine.create_table("after", schema).await.unwrap();
+    assert_eq!(ids.len(), 3000);
+    match log.recv().await.unwrap().operation {
+        StorageOperation::BatchInsert { rows, .. } => assert_eq!(rows.len(), 3000),
+        other => panic!("unexpected operation: {:?}", other),
+    }
+    assert!(matches!(log.recv().await.unwrap().operation, StorageOperation::Create { .. }));
+
+    let result = engine.query(QueryBuilder::count("batch_rows").build()).await.unwrap();
+    assert_eq!(result.count, Some(3000));
+}
+
 #[tokio::test]
 async fn test_database_stats() {
-    let engine = DatabaseEngine::new();
+    let engine = DatabaseEngine::in_memory();
 
     // 创建表并插入数据
     let schema = Schema::new(vec![
@@ -270,7 +324,7 @@ async fn test_database_stats() {
 
 #[tokio::test]
 async fn test_error_handling() {
-    let engine = DatabaseEngine::new();
+    let engine = DatabaseEngine::in_memory();
 
     // 测试重复创建表
     let schema = Schema::new(vec![
@@ -290,7 +344,7 @@ async fn test_error_handling() {
 
 #[tokio::test]
 async fn test_table_schema() {
-    let engine = DatabaseEngine::new();
+    let engine = DatabaseEngine::in_memory();
 
     // 创建复杂的schema
     let schema = Schema::new(vec![
@@ -323,7 +377,8 @@ async fn test_table_schema() {
 
 #[tokio::test]
 async fn test_persistence() {
-    let mut engine = DatabaseEngine::new();
+    let dir = std::env::temp_dir().join(format!("simple-db-persist-{}", uuid::Uuid::new_v4()));
+    let mut engine = DatabaseEngine::open(&dir).await.unwrap();
     engine.set_auto_save(false);
 
     // 创建表和数据
@@ -339,17 +394,123 @@ async fn test_persistence() {
     data.insert("name".to_string(), Value::Text("Persistent".to_string()));
     engine.insert("persist_test", data).await.unwrap();
 
-    // 保存到磁盘
+    // 保存到磁盘后关闭，重新打开同一目录加载数据
     assert!(engine.save_to_disk().await.is_ok());
+    engine.close().await.unwrap();
 
-    // 创建新引擎并加载（但此时内存中已经有数据了）
-    // 这个测试需要重新设计，因为我们不能直接加载已经存在的数据
-    // 现在只验证保存功能正常
-    assert!(engine.save_to_disk().await.is_ok());
-
-    // 验证当前引擎中的数据
+    let engine = DatabaseEngine::open(&dir).await.unwrap();
     let query = QueryBuilder::select("persist_test").build();
     let result = engine.query(query).await.unwrap();
     assert_eq!(result.rows.len(), 1);
     assert_eq!(result.rows[0].get("name"), Some(&Value::Text("Persistent".to_string())));
-}
\ No newline at end of file
+    engine.close().await.unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[derive(Debug, PartialEq, simple_db::FromRow, simple_db::ToRow)]
+struct Product {
+    id: i64,
+    name: String,
+    price: f64,
+    released: Option<chrono::NaiveDate>,
+}
+
+#[tokio::test]
+async fn test_typed_row_mapping() {
+    let engine = DatabaseEngine::in_memory();
+
+    let schema = Schema::new(vec![
+        ColumnDefinition::new("id", DataType::Integer, true),
+        ColumnDefinition::new("name", DataType::Text, false),
+        ColumnDefinition::new("price", DataType::Float, false),
+        ColumnDefinition::new("released", DataType::Date, false),
+    ]);
+    engine.create_table("products", schema).await.unwrap();
+
+    let lamp = Product {
+        id: 1,
+        name: "lamp".to_string(),
+        price: 19.5,
+        released: chrono::NaiveDate::from_ymd_opt(2024, 3, 1),
+    };
+    engine.insert_struct("products", &lamp).await.unwrap();
+    engine.insert_struct("products", &Product { id: 2, name: "desk".to_string(), price: 120.0, released: None }).await.unwrap();
+
+    let query = QueryBuilder::select("products")
+        .where_condition("price", ComparisonOperator::LessThan, Value::Float(100.0))
+        .build();
+    let products: Vec<Product> = engine.query_as(query).await.unwrap();
+    assert_eq!(products, vec![lamp]);
+}
+
+#[tokio::test]
+async fn test_restart_loads_snapshot_rows_after_log_truncation() {
+    use simple_db::engine::DEFAULT_DATABASE;
+    use simple_db::{RetentionAction, RetentionPolicy};
+
+    let dir = std::env::temp_dir().join(format!("simple-db-restart-{}", uuid::Uuid::new_v4()));
+    let name = "restart";
+    let mut engine = DatabaseEngine::open(&dir).await.unwrap();
+    engine.create_database(name).unwrap();
+    engine.use_database(name).await.unwrap();
+    engine.execute_sql("
+        CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT);
+        INSERT INTO notes (id, body) VALUES (1, 'saved'), (2, 'also saved');
+    ").await.unwrap();
+
+    // 保存快照后删除被快照覆盖的日志，快照之前的行只存在于快照中
+    engine.set_retention_policy(Some(RetentionPolicy::new(RetentionAction::Delete))).await;
+    engine.save_to_disk().await.unwrap();
+    engine.execute_sql("INSERT INTO notes (id, body) VALUES (3, 'logged')").await.unwrap();
+    drop(engine);
+
+    // 重启：重新打开同一目录，加载快照中的行，再重放快照之后的日志
+    let mut restarted = DatabaseEngine::open(&dir).await.unwrap();
+    restarted.use_database(name).await.unwrap();
+    let result = restarted.query(QueryBuilder::select("notes").order_by("id", true).build()).await.unwrap();
+    let bodies: Vec<Value> = result.rows.iter().map(|row| row.get("body").cloned().unwrap()).collect();
+    assert_eq!(bodies, vec![
+        Value::Text("saved".to_string()),
+        Value::Text("also saved".to_string()),
+        Value::Text("logged".to_string()),
+    ]);
+
+    restarted.use_database(DEFAULT_DATABASE).await.unwrap();
+    restarted.drop_database(name).unwrap();
+    restarted.close().await.unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_open_legacy_data_dir() {
+    // 最早版本写入的数据目录：快照没有文件头，日志没有校验和，第二次启动从 1 重新编号，末尾是写入中途崩溃留下的半行
+    let fixture = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/legacy_v0");
+    let dir = std::env::temp_dir().join(format!("simple-db-legacy-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    for file in ["snapshot.json", "transaction.log"] {
+        std::fs::copy(fixture.join(file), dir.join(file)).unwrap();
+    }
+
+    for _ in 0..2 {
+        let engine = DatabaseEngine::open(&dir).await.unwrap();
+        let results = engine.execute_sql("SELECT * FROM users ORDER BY id; SELECT * FROM events ORDER BY id").await.unwrap();
+        let users: Vec<(Value, Value)> = results[0].rows.iter()
+            .map(|row| (row.get("name").cloned().unwrap(), row.get("score").cloned().unwrap_or(Value::Null)))
+            .collect();
+        assert_eq!(users, vec![
+            (Value::Text("alice".to_string()), Value::Float(9.5)),
+            (Value::Text("bob".to_string()), Value::Float(6.5)),
+            (Value::Text("dave".to_string()), Value::Null),
+        ]);
+        let kinds: Vec<Value> = results[1].rows.iter().map(|row| row.get("kind").cloned().unwrap()).collect();
+        assert_eq!(kinds, vec![Value::Text("login".to_string()), Value::Text("logout".to_string())]);
+        assert!(engine.list_tables().await.iter().any(|table| table.name == "logs"));
+
+        // 升级后的目录按当前格式继续写入，重新打开时保留
+        engine.execute_sql("INSERT INTO events (id, kind) VALUES (3, 'upgraded')").await.unwrap();
+        engine.execute_sql("DELETE FROM events WHERE id = 3").await.unwrap();
+        engine.close().await.unwrap();
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}